@@ -0,0 +1,103 @@
+//! Benchmarks reading a large entries file and summing it into a report, run with `cargo bench`.
+//!
+//! `TIMETRACKER_BENCH_ENTRIES` sets the number of entries to generate, 200 000 by default.
+
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::env;
+use std::fs;
+use std::hint::black_box;
+use std::str::FromStr;
+use timetracker::report::totals_by_account;
+use timetracker::storage::Storage;
+use timetracker::Entry;
+
+/// Entries of one or two hours on a few accounts, one after the other.
+fn generate(count: usize) -> String {
+    let accounts = [
+        "Client:Project",
+        "Client:Support",
+        "Internal",
+        "Open source",
+    ];
+    let mut start = Utc.ymd(2000, 1, 1).and_hms(8, 0, 0);
+    let mut contents = String::new();
+    for index in 0..count {
+        let stop = start + Duration::hours(1 + index as i64 % 2);
+        let mut entry = Entry::builder()
+            .start(start)
+            .stop(stop)
+            .account(accounts[index % accounts.len()])
+            .billable(index % 2 == 0);
+        if index % 3 == 0 {
+            entry = entry.tag("review");
+        }
+        if index % 5 == 0 {
+            entry = entry.description("Pairing; on the parser");
+        }
+        let entry = entry.build().expect("the entry is valid");
+        contents.push_str(&entry.to_string());
+        contents.push('\n');
+        start = stop + Duration::minutes(30);
+    }
+    contents
+}
+
+fn report(c: &mut Criterion) {
+    let count = env::var("TIMETRACKER_BENCH_ENTRIES")
+        .ok()
+        .and_then(|count| count.parse().ok())
+        .unwrap_or(200_000);
+    let dir = env::temp_dir().join(format!("timetracker-bench-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let storage = Storage::new(dir.join("entries"), dir.join("running"));
+    let contents = generate(count);
+    fs::write(storage.entries_path(), &contents).unwrap();
+
+    let mut group = c.benchmark_group(format!("{} entries", count));
+    // Each iteration goes through the whole file
+    group.sample_size(10);
+    group.bench_function("Entry::from_str", |b| {
+        b.iter(|| {
+            contents.lines().for_each(|line| {
+                black_box(Entry::from_str(line).unwrap());
+            })
+        })
+    });
+    group.bench_function("Entry::parse", |b| {
+        b.iter(|| {
+            contents.lines().for_each(|line| {
+                black_box(Entry::parse(line).unwrap());
+            })
+        })
+    });
+
+    let last = Entry::parse(contents.lines().last().unwrap()).unwrap();
+    let since: DateTime<Utc> = last.stop - Duration::weeks(1);
+    group.bench_function("report of everything", |b| {
+        b.iter(|| totals_by_account(&storage.entries().unwrap()))
+    });
+    group.bench_function("report of the last week, entries", |b| {
+        b.iter(|| {
+            let entries = storage.entries().unwrap();
+            let entries: Vec<Entry> = entries
+                .into_iter()
+                .filter(|entry| entry.stop >= since)
+                .collect();
+            totals_by_account(&entries)
+        })
+    });
+    group.bench_function("report of the last week, entries_where", |b| {
+        b.iter(|| totals_by_account(&storage.entries_where(|entry| entry.stop >= since).unwrap()))
+    });
+    let indexed = storage.clone().index(true);
+    group.bench_function("report of the last week, index", |b| {
+        b.iter(|| totals_by_account(&indexed.entries_since(since, |_| true).unwrap()))
+    });
+    group.finish();
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+criterion_group!(benches, report);
+criterion_main!(benches);
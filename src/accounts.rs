@@ -0,0 +1,424 @@
+//! Metadata about accounts, kept in `accounts.toml` next to the config.
+//!
+//! Each account has a table with the client it is for, the color it is shown in, its hourly rate
+//! and what entries for it start with, e.g.
+//!
+//! ```toml
+//! ["ClientA:ProjectX"]
+//! client = "Client A"
+//! color = "blue"
+//! rate = 120.0
+//! billable = true
+//! tags = ["dev"]
+//! daily_limit = 4.0
+//! budget = 200.0
+//! deadline = "2021-12-31"
+//! ```
+//!
+//! Sub-accounts use the metadata of the closest account that has any. Accounts which have ended
+//! can be `archived`, which goes for their sub-accounts too.
+
+use crate::config::ConfigError;
+use crate::report::is_within_account;
+use chrono::{Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AccountInfo {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<Color>,
+    /// The hourly rate, in whatever currency the client is billed in.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rate: Option<f64>,
+    /// Start entries for the account as billable.
+    #[serde(skip_serializing_if = "is_false")]
+    pub billable: bool,
+    /// Tags to start entries for the account with.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// The category of the entries for the account which have none of their own.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    /// The most hours to track on the account and its sub-accounts in a day, beyond which
+    /// entries for it are not started.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub daily_limit: Option<f64>,
+    /// The hours to spend on the account and its sub-accounts in total, which `burn` projects
+    /// against the deadline.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub budget: Option<f64>,
+    /// The day by which the budget is meant to last.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deadline: Option<NaiveDate>,
+    /// The account has ended, so it is left out when picking accounts and entries are not
+    /// started for it.
+    #[serde(skip_serializing_if = "is_false")]
+    pub archived: bool,
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+/// A terminal color, written by name like `blue` or `bright-blue`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Color(u8);
+
+const COLOR_NAMES: [&str; 6] = ["red", "green", "yellow", "blue", "magenta", "cyan"];
+
+/// The colors as CSS, in the order of `COLOR_NAMES`, the normal ones and then the bright ones.
+const CSS_COLORS: [&str; 12] = [
+    "#cd3131", "#0dbc79", "#e5e510", "#2472c8", "#bc3fbc", "#11a8cd", "#f14c4c", "#23d18b",
+    "#f5f543", "#3b8eea", "#d670d6", "#29b8db",
+];
+
+impl Color {
+    /// The SGR parameter selecting the color as the foreground.
+    pub fn code(self) -> u8 {
+        self.0
+    }
+
+    /// The color for documents, like an HTML export.
+    pub fn css(self) -> &'static str {
+        match self.0 {
+            code if code >= 91 => CSS_COLORS[(code - 91) as usize + COLOR_NAMES.len()],
+            code => CSS_COLORS[(code - 31) as usize],
+        }
+    }
+
+    /// The color an account is shown in: the one of the closest account given a color, or else
+    /// one picked by a hash of the account name, the same one every time.
+    pub fn for_account(account: &str, colors: &BTreeMap<String, Color>) -> Color {
+        let chosen = colors
+            .iter()
+            .filter(|(parent, _)| is_within_account(account, parent))
+            .max_by_key(|(parent, _)| parent.len());
+        if let Some((_, color)) = chosen {
+            return *color;
+        }
+        // The same FNV-1a hash as entry IDs, so the colors stay the same between runs
+        let hash = account
+            .bytes()
+            .fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+            });
+        let index = (hash % (2 * COLOR_NAMES.len() as u64)) as u8;
+        match index.checked_sub(COLOR_NAMES.len() as u8) {
+            Some(bright) => Color(91 + bright),
+            None => Color(31 + index),
+        }
+    }
+}
+
+impl FromStr for Color {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, base) = match s.strip_prefix("bright-") {
+            Some(name) => (name, 91),
+            None => (s, 31),
+        };
+        COLOR_NAMES
+            .iter()
+            .position(|color| *color == name)
+            .map(|index| Color(base + index as u8))
+            .ok_or_else(|| {
+                format!(
+                    "unknown color `{}`, expected one of {}, optionally prefixed with `bright-`",
+                    s,
+                    COLOR_NAMES.join(", ")
+                )
+            })
+    }
+}
+
+impl TryFrom<String> for Color {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (prefix, index) = match self.0 {
+            code if code >= 91 => ("bright-", code - 91),
+            code => ("", code - 31),
+        };
+        write!(f, "{}{}", prefix, COLOR_NAMES[index as usize])
+    }
+}
+
+impl From<Color> for String {
+    fn from(color: Color) -> Self {
+        color.to_string()
+    }
+}
+
+/// The accounts file.
+#[derive(Debug, Default)]
+pub struct Accounts {
+    path: PathBuf,
+    accounts: BTreeMap<String, AccountInfo>,
+}
+
+impl Accounts {
+    /// Reads the accounts file at the given path, which is treated as empty if it does not exist.
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let accounts = match fs::read_to_string(path) {
+            Ok(contents) => toml::from_str(&contents)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(err) => return Err(err.into()),
+        };
+        Ok(Accounts {
+            path: path.to_path_buf(),
+            accounts,
+        })
+    }
+
+    /// The metadata of the account, or of the closest account it is a sub-account of.
+    pub fn get(&self, account: &str) -> Option<&AccountInfo> {
+        self.accounts
+            .iter()
+            .filter(|(parent, _)| is_within_account(account, parent))
+            .max_by_key(|(parent, _)| parent.len())
+            .map(|(_, info)| info)
+    }
+
+    /// The daily limit of the account, from the closest account that has one, together with that
+    /// account, which the time tracked on is counted against the limit.
+    pub fn daily_limit(&self, account: &str) -> Option<(&str, Duration)> {
+        self.accounts
+            .iter()
+            .filter(|(parent, info)| {
+                info.daily_limit.is_some() && is_within_account(account, parent)
+            })
+            .max_by_key(|(parent, _)| parent.len())
+            .and_then(|(parent, info)| {
+                let hours = info.daily_limit?;
+                Some((
+                    parent.as_str(),
+                    Duration::seconds((hours * 3600.0).round() as i64),
+                ))
+            })
+    }
+
+    /// The category of the account, from the closest account that has one.
+    pub fn category(&self, account: &str) -> Option<&str> {
+        self.accounts
+            .iter()
+            .filter(|(parent, info)| info.category.is_some() && is_within_account(account, parent))
+            .max_by_key(|(parent, _)| parent.len())
+            .and_then(|(_, info)| info.category.as_deref())
+    }
+
+    /// The accounts with a budget, with the budget in hours as a duration, leaving out archived
+    /// ones.
+    pub fn hour_budgets(&self) -> Vec<(&str, Duration)> {
+        self.accounts
+            .iter()
+            .filter(|(account, _)| !self.is_archived(account))
+            .filter_map(|(account, info)| {
+                let hours = info.budget?;
+                Some((
+                    account.as_str(),
+                    Duration::seconds((hours * 3600.0).round() as i64),
+                ))
+            })
+            .collect()
+    }
+
+    /// The accounts with both a budget and a deadline, with the budget in hours as a duration,
+    /// leaving out archived ones.
+    pub fn budgets(&self) -> Vec<(&str, Duration, NaiveDate)> {
+        self.accounts
+            .iter()
+            .filter(|(account, _)| !self.is_archived(account))
+            .filter_map(|(account, info)| {
+                let hours = info.budget?;
+                Some((
+                    account.as_str(),
+                    Duration::seconds((hours * 3600.0).round() as i64),
+                    info.deadline?,
+                ))
+            })
+            .collect()
+    }
+
+    /// Whether the account or an account it is a sub-account of is archived.
+    pub fn is_archived(&self, account: &str) -> bool {
+        self.accounts
+            .iter()
+            .any(|(parent, info)| info.archived && is_within_account(account, parent))
+    }
+
+    /// The accounts with metadata, ordered by name.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &AccountInfo)> {
+        self.accounts.iter()
+    }
+
+    /// The color of each account with one, for styling output.
+    pub fn colors(&self) -> BTreeMap<String, Color> {
+        self.accounts
+            .iter()
+            .filter_map(|(account, info)| Some((account.clone(), info.color?)))
+            .collect()
+    }
+
+    /// Sets the metadata of the account, replacing any it had, and writes the file.
+    pub fn define(&mut self, account: &str, info: AccountInfo) -> Result<(), ConfigError> {
+        self.accounts.insert(account.to_string(), info);
+        self.save()
+    }
+
+    /// Archives the account, or brings it back, keeping the rest of its metadata, and writes the
+    /// file.
+    pub fn archive(&mut self, account: &str, archived: bool) -> Result<(), ConfigError> {
+        let info = self.accounts.entry(account.to_string()).or_default();
+        info.archived = archived;
+        if *info == AccountInfo::default() {
+            self.accounts.remove(account);
+        }
+        self.save()
+    }
+
+    fn save(&self) -> Result<(), ConfigError> {
+        if let Some(parent) = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, toml::to_string(&self.accounts)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_colors() {
+        assert_eq!(Color::from_str("blue").map(Color::code), Ok(34));
+        assert_eq!(Color::from_str("bright-cyan").map(Color::code), Ok(96));
+        assert_eq!(Color(91).to_string(), "bright-red");
+        assert!(Color::from_str("purple").is_err());
+        assert_eq!(Color(34).css(), "#2472c8");
+        assert_eq!(Color(96).css(), "#29b8db");
+
+        let colors: BTreeMap<String, Color> =
+            vec![("A".to_string(), Color(34))].into_iter().collect();
+        assert_eq!(Color::for_account("A:B", &colors), Color(34));
+        assert_eq!(
+            Color::for_account("Other", &colors),
+            Color::for_account("Other", &BTreeMap::new())
+        );
+    }
+
+    #[test]
+    fn define_and_look_up_accounts() {
+        let path = std::env::temp_dir()
+            .join(format!("timetracker-accounts-{}", std::process::id()))
+            .join("accounts.toml");
+        let _ = fs::remove_file(&path);
+
+        let mut accounts = Accounts::load(&path).unwrap();
+        assert!(accounts.get("ClientA").is_none());
+        accounts
+            .define(
+                "ClientA",
+                AccountInfo {
+                    client: Some("Client A".to_string()),
+                    color: Some(Color(34)),
+                    rate: Some(120.0),
+                    category: Some("development".to_string()),
+                    ..AccountInfo::default()
+                },
+            )
+            .unwrap();
+        accounts
+            .define(
+                "ClientA:Support",
+                AccountInfo {
+                    rate: Some(80.0),
+                    billable: true,
+                    tags: vec!["support".to_string()],
+                    daily_limit: Some(4.5),
+                    budget: Some(40.0),
+                    deadline: Some(NaiveDate::from_ymd(2021, 12, 31)),
+                    ..AccountInfo::default()
+                },
+            )
+            .unwrap();
+
+        let accounts = Accounts::load(&path).unwrap();
+        assert_eq!(accounts.get("ClientA:ProjectX").unwrap().rate, Some(120.0));
+        assert_eq!(
+            accounts.get("ClientA:Support:Calls").unwrap().rate,
+            Some(80.0)
+        );
+        assert!(accounts.get("ClientAB").is_none());
+        assert_eq!(
+            accounts.daily_limit("ClientA:Support:Calls"),
+            Some(("ClientA:Support", Duration::minutes(270)))
+        );
+        assert_eq!(accounts.daily_limit("ClientA:ProjectX"), None);
+        assert_eq!(
+            accounts.budgets(),
+            vec![(
+                "ClientA:Support",
+                Duration::hours(40),
+                NaiveDate::from_ymd(2021, 12, 31)
+            )]
+        );
+        assert_eq!(
+            accounts.category("ClientA:Support:Calls"),
+            Some("development")
+        );
+        assert_eq!(accounts.category("ClientB"), None);
+        assert_eq!(accounts.colors().get("ClientA"), Some(&Color(34)));
+        assert!(toml::from_str::<BTreeMap<String, AccountInfo>>("[A]\ncolor = \"pink\"").is_err());
+    }
+
+    #[test]
+    fn archive_accounts() {
+        let path = std::env::temp_dir()
+            .join(format!("timetracker-archive-{}", std::process::id()))
+            .join("accounts.toml");
+        let _ = fs::remove_file(&path);
+
+        let mut accounts = Accounts::load(&path).unwrap();
+        accounts
+            .define(
+                "ClientA",
+                AccountInfo {
+                    rate: Some(120.0),
+                    ..AccountInfo::default()
+                },
+            )
+            .unwrap();
+        accounts.archive("ClientA", true).unwrap();
+        accounts.archive("Old", true).unwrap();
+
+        let mut accounts = Accounts::load(&path).unwrap();
+        assert!(accounts.is_archived("ClientA:ProjectX"));
+        assert!(accounts.is_archived("Old"));
+        assert!(!accounts.is_archived("ClientB"));
+        assert_eq!(accounts.get("ClientA").unwrap().rate, Some(120.0));
+
+        accounts.archive("ClientA", false).unwrap();
+        accounts.archive("Old", false).unwrap();
+        assert!(!accounts.is_archived("ClientA"));
+        assert_eq!(accounts.iter().count(), 1);
+    }
+}
@@ -0,0 +1,212 @@
+//! A log of what was done while entries were running, to check the tracked time against.
+//!
+//! `tt audit --record` samples the title of the active window or the name of the focused app now
+//! and then, and appends each sample to the log as a JSON object on a line of its own, e.g.
+//!
+//! ```json
+//! {"time":"2021-07-03T10:00:00Z","title":"main.rs - vim"}
+//! ```
+//!
+//! Each sample stands for the interval after it, so an entry is matched by the samples taken
+//! while it ran.
+
+use crate::Entry;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The title of the active window or the name of the focused app at a time.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Sample {
+    pub time: DateTime<Utc>,
+    pub title: String,
+}
+
+/// The time spent in a window or app during an entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Activity {
+    pub title: String,
+    pub duration: Duration,
+}
+
+/// The activity log, by default `activity` next to the entries.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActivityLog {
+    path: PathBuf,
+}
+
+impl ActivityLog {
+    pub fn new(path: PathBuf) -> Self {
+        ActivityLog { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn record(&self, sample: &Sample) -> io::Result<()> {
+        if let Some(parent) = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let mut line = serde_json::to_string(sample).expect("samples can be serialized");
+        line.push('\n');
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(line.as_bytes())
+    }
+
+    /// Reads the samples in the order they were taken. Lines that cannot be read, e.g. cut off by
+    /// an interrupted write, are skipped.
+    pub fn samples(&self) -> io::Result<Vec<Sample>> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err),
+        };
+        Ok(contents
+            .lines()
+            .filter_map(|line| match serde_json::from_str(line) {
+                Ok(sample) => Some(sample),
+                Err(err) => {
+                    log::warn!("skipping an activity log line that cannot be read: {}", err);
+                    None
+                }
+            })
+            .collect())
+    }
+}
+
+/// The command printing the title of the active window on this platform, used unless `command`
+/// is set under `[activity]`.
+pub fn default_command() -> Option<&'static str> {
+    if cfg!(target_os = "macos") {
+        Some(
+            "osascript -e 'tell application \"System Events\" to get name of first process \
+             whose frontmost is true'",
+        )
+    } else if cfg!(unix) {
+        Some("xdotool getactivewindow getwindowname")
+    } else {
+        None
+    }
+}
+
+/// Runs the command through the shell and takes the first line it prints as the title, `None`
+/// if it prints nothing, e.g. when no window is focused.
+pub fn active_title(command: &str) -> io::Result<Option<String>> {
+    #[cfg(not(windows))]
+    let output = Command::new("sh").arg("-c").arg(command).output()?;
+    #[cfg(windows)]
+    let output = Command::new("cmd").arg("/C").arg(command).output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "`{}` exited with {}",
+            command, output.status
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .next()
+        .map(|line| line.trim().to_string())
+        .filter(|title| !title.is_empty()))
+}
+
+/// The time spent in each window or app while the entry ran, the longest first, with each
+/// sample standing for the interval after it or the time until the next sample or the end of
+/// the entry if sooner.
+pub fn during(entry: &Entry, samples: &[Sample], interval: Duration) -> Vec<Activity> {
+    let within: Vec<&Sample> = samples
+        .iter()
+        .filter(|sample| sample.time >= entry.start && sample.time < entry.stop)
+        .collect();
+    let mut durations: BTreeMap<&str, Duration> = BTreeMap::new();
+    for (i, sample) in within.iter().enumerate() {
+        let next = within.get(i + 1).map_or(entry.stop, |next| next.time);
+        let until = next.min(entry.stop).min(sample.time + interval);
+        let duration = durations
+            .entry(sample.title.as_str())
+            .or_insert_with(Duration::zero);
+        *duration = *duration + (until - sample.time);
+    }
+    let mut activities: Vec<Activity> = durations
+        .into_iter()
+        .map(|(title, duration)| Activity {
+            title: title.to_string(),
+            duration,
+        })
+        .collect();
+    activities.sort_by_key(|activity| std::cmp::Reverse(activity.duration));
+    activities
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn sample(time: &str, title: &str) -> Sample {
+        Sample {
+            time: DateTime::from_str(time).unwrap(),
+            title: title.to_string(),
+        }
+    }
+
+    #[test]
+    fn sum_samples_during_entry() {
+        let entry = Entry::from_str("2021-07-03T10:00:00Z 2021-07-03T10:30:00Z dev").unwrap();
+        let samples = vec![
+            sample("2021-07-03T09:59:00Z", "mail"),
+            sample("2021-07-03T10:00:00Z", "vim"),
+            sample("2021-07-03T10:01:00Z", "vim"),
+            sample("2021-07-03T10:02:00Z", "browser"),
+            // A gap, e.g. while the computer was asleep
+            sample("2021-07-03T10:20:00Z", "vim"),
+            sample("2021-07-03T10:29:30Z", "chat"),
+            sample("2021-07-03T10:30:00Z", "mail"),
+        ];
+        assert_eq!(
+            during(&entry, &samples, Duration::minutes(1)),
+            vec![
+                Activity {
+                    title: "vim".to_string(),
+                    duration: Duration::minutes(3),
+                },
+                Activity {
+                    title: "browser".to_string(),
+                    duration: Duration::minutes(1),
+                },
+                Activity {
+                    title: "chat".to_string(),
+                    duration: Duration::seconds(30),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn record_and_read_samples() {
+        let dir = std::env::temp_dir().join(format!("tt_activity_test_{}", std::process::id()));
+        let log = ActivityLog::new(dir.join("activity"));
+        assert_eq!(log.samples().unwrap(), Vec::new());
+        let samples = vec![
+            sample("2021-07-03T10:00:00Z", "vim \"main.rs\""),
+            sample("2021-07-03T10:01:00Z", "browser"),
+        ];
+        for sample in &samples {
+            log.record(sample).unwrap();
+        }
+        fs::write(
+            log.path(),
+            fs::read_to_string(log.path()).unwrap() + "{\"time\":\n",
+        )
+        .unwrap();
+        assert_eq!(log.samples().unwrap(), samples);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,201 @@
+//! An append-only log of every change to the data files, for when time records have to be
+//! accounted for.
+//!
+//! Each line of the log is a JSON object with the time of the change, the arguments of the
+//! command that made it, the file it was made to and the line before and after the change, e.g.
+//!
+//! ```json
+//! {"time":"2021-07-03T12:00:00Z","args":["stop"],"file":"running","before":"2021-07-03T10:00:00Z Client","after":null}
+//! ```
+//!
+//! Lines only added have no `before` and lines only removed no `after`. A line is changed when a
+//! line for the same entry, by start and account, or the same running account replaces it.
+
+use crate::storage::StorageError;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// The audit log of a storage, see `Storage::audit`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditLog {
+    path: PathBuf,
+    /// The arguments of the command making the changes, recorded with each of them.
+    args: Vec<String>,
+}
+
+/// A change to a line of a data file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Change {
+    pub time: DateTime<Utc>,
+    pub args: Vec<String>,
+    /// `entries` or `running`.
+    pub file: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+impl AuditLog {
+    pub fn new(path: PathBuf, args: Vec<String>) -> Self {
+        AuditLog { path, args }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Appends the changes of the lines to the file, each a pair of the line before and after.
+    pub fn record(
+        &self,
+        time: DateTime<Utc>,
+        file: &str,
+        changes: Vec<(Option<String>, Option<String>)>,
+    ) -> Result<(), StorageError> {
+        if changes.is_empty() {
+            return Ok(());
+        }
+        if let Some(parent) = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let mut lines = String::new();
+        for (before, after) in changes {
+            let change = Change {
+                time,
+                args: self.args.clone(),
+                file: file.to_string(),
+                before,
+                after,
+            };
+            lines += &serde_json::to_string(&change).expect("changes can be serialized");
+            lines.push('\n');
+        }
+        // A single write, so the changes of one command are not interleaved with another's
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(lines.as_bytes())?;
+        Ok(())
+    }
+
+    /// Reads the changes in the order they were made. Lines that cannot be read, e.g. cut off by
+    /// an interrupted write, are skipped.
+    pub fn changes(&self) -> Result<Vec<Change>, StorageError> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+        Ok(contents
+            .lines()
+            .filter_map(|line| match serde_json::from_str(line) {
+                Ok(change) => Some(change),
+                Err(err) => {
+                    log::warn!("skipping an audit log line that cannot be read: {}", err);
+                    None
+                }
+            })
+            .collect())
+    }
+}
+
+/// The changes from the lines before to the lines after, with the key identifying what each line
+/// is about. Lines in both are unchanged wherever they are, so reordering changes nothing.
+pub fn changes(
+    before: &[String],
+    after: &[String],
+    key: impl Fn(&str) -> Option<String>,
+) -> Vec<(Option<String>, Option<String>)> {
+    let mut added: Vec<&String> = after.iter().collect();
+    let mut removed = Vec::new();
+    for line in before {
+        match added.iter().position(|other| *other == line) {
+            Some(position) => {
+                added.remove(position);
+            }
+            None => removed.push(line),
+        }
+    }
+    let mut changes = Vec::new();
+    for line in removed {
+        let replacement = key(line).and_then(|line_key| {
+            added
+                .iter()
+                .position(|other| key(other).as_ref() == Some(&line_key))
+        });
+        match replacement {
+            Some(position) => {
+                changes.push((Some(line.clone()), Some(added.remove(position).clone())))
+            }
+            None => changes.push((Some(line.clone()), None)),
+        }
+    }
+    changes.extend(added.into_iter().map(|line| (None, Some(line.clone()))));
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn lines(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn find_changed_lines() {
+        let key = |line: &str| line.split(' ').next().map(str::to_string);
+        let before = lines(&["1 A", "2 B", "3 C"]);
+        let after = lines(&["3 C", "1 A", "2 B ; note", "4 D"]);
+
+        assert_eq!(changes(&before, &before, key), Vec::new());
+        assert_eq!(
+            changes(&before, &after, key),
+            vec![
+                (Some("2 B".to_string()), Some("2 B ; note".to_string())),
+                (None, Some("4 D".to_string())),
+            ]
+        );
+        assert_eq!(
+            changes(&after, &before, key),
+            vec![
+                (Some("2 B ; note".to_string()), Some("2 B".to_string())),
+                (Some("4 D".to_string()), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn record_and_read_changes() {
+        let path = std::env::temp_dir().join(format!("tt_audit_test_{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let log = AuditLog::new(path.clone(), vec!["stop".to_string()]);
+        let time = DateTime::from_str("2021-07-03T12:00:00Z").unwrap();
+
+        log.record(time, "running", Vec::new()).unwrap();
+        assert!(!path.exists());
+        log.record(
+            time,
+            "running",
+            vec![(Some("2021-07-03T10:00:00Z A".to_string()), None)],
+        )
+        .unwrap();
+        fs::write(&path, fs::read_to_string(&path).unwrap() + "{\"cut off\n").unwrap();
+        let changes = log.changes().unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            changes,
+            vec![Change {
+                time,
+                args: vec!["stop".to_string()],
+                file: "running".to_string(),
+                before: Some("2021-07-03T10:00:00Z A".to_string()),
+                after: None,
+            }]
+        );
+    }
+}
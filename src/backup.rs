@@ -0,0 +1,196 @@
+//! Snapshots of the data and config files, for undoing changes to them.
+//!
+//! Each snapshot is a directory in the backups directory, named after the time it was taken and
+//! what it was taken for, e.g. `2021-07-03T120000Z-import`, with a copy of each file under a name
+//! for its role, like `entries` or `config.toml`.
+
+use chrono::{DateTime, Utc};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The files to back up, each with the name its copy is stored under and where it is.
+pub type Files<'a> = [(&'a str, &'a Path)];
+
+/// A directory of snapshots.
+#[derive(Debug, Clone)]
+pub struct Backups {
+    dir: PathBuf,
+}
+
+/// A snapshot in the backups directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+impl Backups {
+    pub fn new(dir: PathBuf) -> Self {
+        Backups { dir }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Copies the files which exist into a new snapshot, named after the time and the reason if
+    /// one is given.
+    pub fn create(
+        &self,
+        files: &Files<'_>,
+        now: DateTime<Utc>,
+        reason: Option<&str>,
+    ) -> io::Result<Snapshot> {
+        fs::create_dir_all(&self.dir)?;
+        let mut name = now.format("%Y-%m-%dT%H%M%SZ").to_string();
+        if let Some(reason) = reason {
+            name = format!("{}-{}", name, reason);
+        }
+        // Snapshots taken within the same second get a number
+        let mut snapshot = Snapshot {
+            path: self.dir.join(&name),
+            name: name.clone(),
+        };
+        let mut number = 1;
+        loop {
+            match fs::create_dir(&snapshot.path) {
+                Ok(()) => break,
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+                    number += 1;
+                    snapshot.name = format!("{}.{}", name, number);
+                    snapshot.path = self.dir.join(&snapshot.name);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        for (name, path) in files {
+            match fs::copy(path, snapshot.path.join(name)) {
+                Ok(_) => {}
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(snapshot)
+    }
+
+    /// The snapshots ordered from the oldest to the newest.
+    pub fn list(&self) -> io::Result<Vec<Snapshot>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err),
+        };
+        let mut snapshots = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            if !entry.file_type()?.is_dir() {
+                continue;
+            }
+            if let Some(name) = entry.file_name().to_str() {
+                snapshots.push(Snapshot {
+                    name: name.to_string(),
+                    path: entry.path(),
+                });
+            }
+        }
+        snapshots.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(snapshots)
+    }
+
+    /// Finds the snapshot with the name, or the newest snapshot for `latest`.
+    pub fn find(&self, name: &str) -> io::Result<Option<Snapshot>> {
+        let mut snapshots = self.list()?;
+        if name == "latest" {
+            return Ok(snapshots.pop());
+        }
+        Ok(snapshots.into_iter().find(|snapshot| snapshot.name == name))
+    }
+
+    /// Removes the oldest snapshots so only the given number is left, returning the removed ones.
+    pub fn rotate(&self, keep: usize) -> io::Result<Vec<Snapshot>> {
+        let mut snapshots = self.list()?;
+        let excess = snapshots.len().saturating_sub(keep);
+        let removed: Vec<Snapshot> = snapshots.drain(..excess).collect();
+        for snapshot in &removed {
+            fs::remove_dir_all(&snapshot.path)?;
+        }
+        Ok(removed)
+    }
+}
+
+impl Snapshot {
+    /// The names of the files in the snapshot.
+    pub fn files(&self) -> io::Result<Vec<String>> {
+        let mut names = Vec::new();
+        for entry in fs::read_dir(&self.path)? {
+            if let Some(name) = entry?.file_name().to_str() {
+                names.push(name.to_string());
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    /// Copies the files in the snapshot back to where they are, and removes the ones that did
+    /// not exist when it was taken.
+    pub fn restore(&self, files: &Files<'_>) -> io::Result<()> {
+        for (name, path) in files {
+            let copy = self.path.join(name);
+            if copy.exists() {
+                if let Some(parent) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::copy(copy, path)?;
+            } else {
+                match fs::remove_file(path) {
+                    Ok(()) => {}
+                    Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                    Err(err) => return Err(err),
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn create_rotate_and_restore() {
+        let dir = std::env::temp_dir().join(format!("timetracker-backup-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let (entries, running) = (dir.join("entries"), dir.join("running"));
+        let files: &Files<'_> = &[("entries", &entries), ("running", &running)];
+        let backups = Backups::new(dir.join("backups"));
+        let now = DateTime::from_str("2021-07-03T12:00:00Z").unwrap();
+
+        fs::write(&entries, "first\n").unwrap();
+        let first = backups.create(files, now, None).unwrap();
+        assert_eq!(first.name, "2021-07-03T120000Z");
+        assert_eq!(first.files().unwrap(), vec!["entries"]);
+
+        fs::write(&entries, "second\n").unwrap();
+        fs::write(&running, "running\n").unwrap();
+        let second = backups.create(files, now, Some("import")).unwrap();
+        let third = backups.create(files, now, Some("import")).unwrap();
+        assert_eq!(third.name, "2021-07-03T120000Z-import.2");
+        assert_eq!(backups.list().unwrap(), vec![first.clone(), second, third]);
+
+        first.restore(files).unwrap();
+        assert_eq!(fs::read_to_string(&entries).unwrap(), "first\n");
+        assert!(!running.exists());
+
+        assert_eq!(backups.rotate(2).unwrap(), vec![first]);
+        assert_eq!(
+            backups.find("latest").unwrap().unwrap().name,
+            "2021-07-03T120000Z-import.2"
+        );
+        assert!(backups.find("2021-07-03T120000Z").unwrap().is_none());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
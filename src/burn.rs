@@ -0,0 +1,137 @@
+//! Projections of when the hour budgets of accounts run out, at the pace of the recent days.
+
+use crate::report;
+use crate::Entry;
+use chrono::{Duration, NaiveDate, TimeZone};
+
+/// How an account is spending its budget, as of a day.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Burn {
+    pub account: String,
+    pub budget: Duration,
+    pub deadline: NaiveDate,
+    /// The time tracked on the account and its sub-accounts so far.
+    pub used: Duration,
+    /// The average time tracked a day over the recent days.
+    pub rate: Duration,
+    /// The day the budget is used up at that rate, `None` if nothing was tracked recently and it
+    /// is not used up yet.
+    pub runs_out: Option<NaiveDate>,
+}
+
+impl Burn {
+    /// The time left of the budget, never negative.
+    pub fn remaining(&self) -> Duration {
+        std::cmp::max(self.budget - self.used, Duration::zero())
+    }
+
+    /// Whether the budget runs out before the deadline at the current rate.
+    pub fn is_at_risk(&self) -> bool {
+        self.runs_out
+            .is_some_and(|runs_out| runs_out < self.deadline)
+    }
+}
+
+/// Projects when the budget of the account runs out at the average daily rate of the `days`
+/// days up to and including `today`, by the days of the entries in the time zone. Running
+/// entries should be passed as entries stopping now to be included.
+pub fn project<Tz: TimeZone>(
+    account: &str,
+    budget: Duration,
+    deadline: NaiveDate,
+    entries: &[Entry],
+    today: NaiveDate,
+    days: u32,
+    tz: &Tz,
+) -> Burn {
+    let account_entries: Vec<Entry> = entries
+        .iter()
+        .filter(|entry| report::is_within_account(&entry.account, account))
+        .cloned()
+        .collect();
+    let totals = report::totals_by_day(&account_entries, tz);
+    let used = totals
+        .values()
+        .fold(Duration::zero(), |sum, total| sum + *total);
+    let since = today - Duration::days(days.max(1) as i64 - 1);
+    let recent = totals
+        .range(since..=today)
+        .fold(Duration::zero(), |sum, (_, total)| sum + *total);
+    let rate = recent / days.max(1) as i32;
+
+    let remaining = budget - used;
+    let runs_out = if remaining <= Duration::zero() {
+        Some(today)
+    } else if rate > Duration::zero() {
+        let days_left = (remaining.num_seconds() + rate.num_seconds() - 1) / rate.num_seconds();
+        Some(today + Duration::days(days_left))
+    } else {
+        None
+    };
+    Burn {
+        account: account.to_string(),
+        budget,
+        deadline,
+        used,
+        rate,
+        runs_out,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use std::str::FromStr;
+
+    fn entry(s: &str) -> Entry {
+        Entry::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn project_budgets() {
+        let entries = vec![
+            entry("2021-06-01T08:00:00Z 2021-06-01T18:00:00Z ClientA"),
+            entry("2021-07-01T08:00:00Z 2021-07-01T12:00:00Z ClientA:Dev"),
+            entry("2021-07-02T08:00:00Z 2021-07-02T12:00:00Z ClientA"),
+            entry("2021-07-02T12:00:00Z 2021-07-02T18:00:00Z ClientB"),
+        ];
+        let today = NaiveDate::from_ymd(2021, 7, 2);
+        let client_a = |budget, deadline| {
+            project(
+                "ClientA",
+                Duration::hours(budget),
+                deadline,
+                &entries,
+                today,
+                4,
+                &Utc,
+            )
+        };
+
+        // 18h of 30h used at 2h a day lasts 6 more days
+        let burn = client_a(30, NaiveDate::from_ymd(2021, 7, 31));
+        assert_eq!(burn.used, Duration::hours(18));
+        assert_eq!(burn.remaining(), Duration::hours(12));
+        assert_eq!(burn.rate, Duration::hours(2));
+        assert_eq!(burn.runs_out, Some(NaiveDate::from_ymd(2021, 7, 8)));
+        assert!(burn.is_at_risk());
+        assert!(!client_a(30, NaiveDate::from_ymd(2021, 7, 8)).is_at_risk());
+
+        let used_up = client_a(10, NaiveDate::from_ymd(2021, 7, 31));
+        assert_eq!(used_up.remaining(), Duration::zero());
+        assert_eq!(used_up.runs_out, Some(today));
+
+        let idle = project(
+            "ClientC",
+            Duration::hours(10),
+            today,
+            &entries,
+            today,
+            4,
+            &Utc,
+        );
+        assert_eq!(idle.runs_out, None);
+        assert!(!idle.is_at_risk());
+    }
+}
@@ -0,0 +1,86 @@
+//! Publishing of entries as events in a calendar on a CalDAV server.
+
+use crate::jira::base64;
+use std::env;
+use std::error::Error;
+use timetracker::config::CaldavConfig;
+use timetracker::export::{EntryFormatter, Ics};
+use timetracker::pushed::PushedLog;
+use timetracker::timesheet::Timesheet;
+use timetracker::Entry;
+
+/// Puts an event for each of the entries not pushed before or changed since, named after the ID
+/// of the entry so pushing it again updates the same event. The log records a hash of each event
+/// as it was pushed. Returns the number of entries pushed.
+pub fn push(
+    config: &CaldavConfig,
+    entries: &[Entry],
+    log: &mut PushedLog,
+    dry_run: bool,
+) -> Result<usize, Box<dyn Error + Send + Sync>> {
+    let url = config
+        .url
+        .as_deref()
+        .ok_or("no CalDAV URL is configured")?
+        .trim_end_matches('/');
+    let calendar = config
+        .calendar
+        .as_deref()
+        .ok_or("no CalDAV calendar is configured")?;
+    let authorization = match &config.username {
+        Some(username) => {
+            let password = env::var("CALDAV_PASSWORD")
+                .ok()
+                .or_else(|| config.password.clone())
+                .ok_or("no CalDAV password is configured")?;
+            Some(format!(
+                "Basic {}",
+                base64(format!("{}:{}", username, password).as_bytes())
+            ))
+        }
+        None => None,
+    };
+
+    let mut pushed = 0;
+    for entry in entries {
+        let id = entry.id();
+        let event = Ics.format(&Timesheet::new(vec![entry.clone()]));
+        let hash = hash(&event);
+        if log.remote_id(&id) == Some(hash.as_str()) {
+            continue;
+        }
+        if dry_run {
+            println!(
+                "{} {} {}",
+                entry.start.format("%Y-%m-%d %H:%M"),
+                entry.account,
+                if log.contains(&id) {
+                    "(changed)"
+                } else {
+                    "(new)"
+                }
+            );
+            pushed += 1;
+            continue;
+        }
+
+        let event_url = format!("{}/{}/{}.ics", url, calendar, id);
+        log::debug!("putting event {}", event_url);
+        let mut request = ureq::put(&event_url).set("Content-Type", "text/calendar; charset=utf-8");
+        if let Some(authorization) = &authorization {
+            request = request.set("Authorization", authorization);
+        }
+        request.send_string(&event)?;
+        log.record(&id, &hash)?;
+        pushed += 1;
+    }
+    Ok(pushed)
+}
+
+/// A 64-bit FNV-1a hash of the event, to tell whether it changed since it was pushed.
+fn hash(event: &str) -> String {
+    let hash = event.bytes().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    });
+    format!("{:016x}", hash)
+}
@@ -0,0 +1,445 @@
+//! Work days and public holidays, for knowing on which days hours are expected.
+
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+/// The work calendar, configured in the `[calendar]` section.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CalendarConfig {
+    /// The days of the week that are worked, by default Monday through Friday.
+    pub work_days: Vec<String>,
+    /// The country whose public holidays are days off.
+    pub country: Option<Country>,
+    /// More days off, e.g. local holidays or vacation.
+    pub holidays: Vec<NaiveDate>,
+    /// The hours expected on each work day.
+    pub hours_per_day: Option<f64>,
+    /// The first day of the contract, which `tt balance` counts from by default.
+    pub contract_start: Option<NaiveDate>,
+}
+
+impl Default for CalendarConfig {
+    fn default() -> Self {
+        CalendarConfig {
+            work_days: ["mon", "tue", "wed", "thu", "fri"]
+                .iter()
+                .map(|day| day.to_string())
+                .collect(),
+            country: None,
+            holidays: Vec::new(),
+            hours_per_day: None,
+            contract_start: None,
+        }
+    }
+}
+
+impl CalendarConfig {
+    /// Names the holiday on the date, either a public holiday of the country or one of the
+    /// configured days off.
+    pub fn holiday(&self, date: NaiveDate) -> Option<&'static str> {
+        if self.holidays.contains(&date) {
+            return Some("Day off");
+        }
+        // A holiday can be observed in the year before, e.g. the 1st of January on a Friday
+        let country = self.country?;
+        [date.year(), date.year() + 1]
+            .iter()
+            .flat_map(|year| country.holidays(*year))
+            .find(|(holiday, _)| *holiday == date)
+            .map(|(_, name)| name)
+    }
+
+    /// Whether the date is on a work day of the week and not a holiday.
+    pub fn is_work_day(&self, date: NaiveDate) -> bool {
+        self.work_days
+            .iter()
+            .any(|day| Weekday::from_str(day) == Ok(date.weekday()))
+            && self.holiday(date).is_none()
+    }
+
+    /// The work days within the dates.
+    pub fn work_days_in(&self, dates: RangeInclusive<NaiveDate>) -> usize {
+        let (mut date, last) = dates.into_inner();
+        let mut count = 0;
+        while date <= last {
+            if self.is_work_day(date) {
+                count += 1;
+            }
+            date = date.succ();
+        }
+        count
+    }
+
+    /// The hours expected on the dates, `None` unless the hours per day are configured.
+    pub fn expected(&self, dates: RangeInclusive<NaiveDate>) -> Option<Duration> {
+        let hours = self.hours_per_day?;
+        let seconds = self.work_days_in(dates) as f64 * hours * 3600.0;
+        Some(Duration::seconds(seconds.round() as i64))
+    }
+
+    /// The balance of the dates from the time tracked each day, `None` unless the hours per day
+    /// are configured. Time tracked on days off is all overtime.
+    pub fn balance(
+        &self,
+        totals: &BTreeMap<NaiveDate, Duration>,
+        dates: RangeInclusive<NaiveDate>,
+    ) -> Option<Balance> {
+        let expected = self.expected(dates.clone())?;
+        let tracked = totals
+            .range(dates)
+            .fold(Duration::zero(), |sum, (_, total)| sum + *total);
+        Some(Balance { tracked, expected })
+    }
+}
+
+/// The hours tracked and expected over some days.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Balance {
+    pub tracked: Duration,
+    pub expected: Duration,
+}
+
+impl Balance {
+    /// The overtime, negative when fewer hours than expected are tracked.
+    pub fn difference(&self) -> Duration {
+        self.tracked - self.expected
+    }
+}
+
+/// A country with built-in public holidays, written as its ISO 3166 code. Only nationwide
+/// holidays are included, regional ones can be added as configured holidays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum Country {
+    Denmark,
+    Germany,
+    Norway,
+    Sweden,
+    UnitedKingdom,
+    UnitedStates,
+}
+
+impl Country {
+    /// The public holidays in the year, in no particular order. Days observed in place of a
+    /// holiday falling on a weekend are included for the countries that have them.
+    pub fn holidays(self, year: i32) -> Vec<(NaiveDate, &'static str)> {
+        let date = |month, day| NaiveDate::from_ymd(year, month, day);
+        let easter = easter(year);
+        let after_easter = |days| easter + Duration::days(days);
+        match self {
+            Country::Denmark => {
+                let mut holidays = vec![
+                    (date(1, 1), "New Year's Day"),
+                    (after_easter(-3), "Maundy Thursday"),
+                    (after_easter(-2), "Good Friday"),
+                    (easter, "Easter Sunday"),
+                    (after_easter(1), "Easter Monday"),
+                    (after_easter(39), "Ascension Day"),
+                    (after_easter(49), "Whit Sunday"),
+                    (after_easter(50), "Whit Monday"),
+                    (date(6, 5), "Constitution Day"),
+                    (date(12, 24), "Christmas Eve"),
+                    (date(12, 25), "Christmas Day"),
+                    (date(12, 26), "Boxing Day"),
+                ];
+                // Abolished from 2024
+                if year < 2024 {
+                    holidays.push((after_easter(26), "General Prayer Day"));
+                }
+                holidays
+            }
+            Country::Germany => vec![
+                (date(1, 1), "New Year's Day"),
+                (after_easter(-2), "Good Friday"),
+                (after_easter(1), "Easter Monday"),
+                (date(5, 1), "Labour Day"),
+                (after_easter(39), "Ascension Day"),
+                (after_easter(50), "Whit Monday"),
+                (date(10, 3), "German Unity Day"),
+                (date(12, 25), "Christmas Day"),
+                (date(12, 26), "Second Day of Christmas"),
+            ],
+            Country::Norway => vec![
+                (date(1, 1), "New Year's Day"),
+                (after_easter(-3), "Maundy Thursday"),
+                (after_easter(-2), "Good Friday"),
+                (easter, "Easter Sunday"),
+                (after_easter(1), "Easter Monday"),
+                (date(5, 1), "Labour Day"),
+                (date(5, 17), "Constitution Day"),
+                (after_easter(39), "Ascension Day"),
+                (after_easter(49), "Whit Sunday"),
+                (after_easter(50), "Whit Monday"),
+                (date(12, 25), "Christmas Day"),
+                (date(12, 26), "Boxing Day"),
+            ],
+            Country::Sweden => vec![
+                (date(1, 1), "New Year's Day"),
+                (date(1, 6), "Epiphany"),
+                (after_easter(-2), "Good Friday"),
+                (easter, "Easter Sunday"),
+                (after_easter(1), "Easter Monday"),
+                (date(5, 1), "May Day"),
+                (after_easter(39), "Ascension Day"),
+                (after_easter(49), "Whit Sunday"),
+                (date(6, 6), "National Day"),
+                (weekday_from(date(6, 19), Weekday::Fri), "Midsummer Eve"),
+                (weekday_from(date(6, 20), Weekday::Sat), "Midsummer Day"),
+                (weekday_from(date(10, 31), Weekday::Sat), "All Saints' Day"),
+                (date(12, 24), "Christmas Eve"),
+                (date(12, 25), "Christmas Day"),
+                (date(12, 26), "Boxing Day"),
+                (date(12, 31), "New Year's Eve"),
+            ],
+            Country::UnitedKingdom => {
+                // Christmas on a weekend moves both days to the following work days
+                let christmas = match date(12, 25).weekday() {
+                    Weekday::Sat => [date(12, 27), date(12, 28)],
+                    Weekday::Sun => [date(12, 27), date(12, 26)],
+                    Weekday::Fri => [date(12, 25), date(12, 28)],
+                    _ => [date(12, 25), date(12, 26)],
+                };
+                vec![
+                    (next_weekday(date(1, 1)), "New Year's Day"),
+                    (after_easter(-2), "Good Friday"),
+                    (after_easter(1), "Easter Monday"),
+                    (
+                        weekday_from(date(5, 1), Weekday::Mon),
+                        "Early May Bank Holiday",
+                    ),
+                    (
+                        weekday_before(date(5, 31), Weekday::Mon),
+                        "Spring Bank Holiday",
+                    ),
+                    (
+                        weekday_before(date(8, 31), Weekday::Mon),
+                        "Summer Bank Holiday",
+                    ),
+                    (christmas[0], "Christmas Day"),
+                    (christmas[1], "Boxing Day"),
+                ]
+            }
+            Country::UnitedStates => {
+                let mut holidays = vec![
+                    (observed(date(1, 1)), "New Year's Day"),
+                    (
+                        weekday_from(date(1, 15), Weekday::Mon),
+                        "Martin Luther King Jr. Day",
+                    ),
+                    (weekday_from(date(2, 15), Weekday::Mon), "Presidents' Day"),
+                    (weekday_before(date(5, 31), Weekday::Mon), "Memorial Day"),
+                    (observed(date(7, 4)), "Independence Day"),
+                    (weekday_from(date(9, 1), Weekday::Mon), "Labor Day"),
+                    (weekday_from(date(10, 8), Weekday::Mon), "Columbus Day"),
+                    (observed(date(11, 11)), "Veterans Day"),
+                    (weekday_from(date(11, 22), Weekday::Thu), "Thanksgiving Day"),
+                    (observed(date(12, 25)), "Christmas Day"),
+                ];
+                if year >= 2021 {
+                    holidays.push((observed(date(6, 19)), "Juneteenth"));
+                }
+                holidays
+            }
+        }
+    }
+}
+
+impl FromStr for Country {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_uppercase().as_str() {
+            "DK" => Ok(Country::Denmark),
+            "DE" => Ok(Country::Germany),
+            "NO" => Ok(Country::Norway),
+            "SE" => Ok(Country::Sweden),
+            "GB" | "UK" => Ok(Country::UnitedKingdom),
+            "US" => Ok(Country::UnitedStates),
+            _ => Err(format!(
+                r#"unsupported country "{}", expected DK, DE, NO, SE, GB or US"#,
+                s
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for Country {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        Country::from_str(&s)
+    }
+}
+
+impl fmt::Display for Country {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let code = match self {
+            Country::Denmark => "DK",
+            Country::Germany => "DE",
+            Country::Norway => "NO",
+            Country::Sweden => "SE",
+            Country::UnitedKingdom => "GB",
+            Country::UnitedStates => "US",
+        };
+        write!(f, "{}", code)
+    }
+}
+
+/// Easter Sunday in the Gregorian calendar, by the anonymous Gregorian algorithm.
+fn easter(year: i32) -> NaiveDate {
+    let a = year % 19;
+    let b = year / 100;
+    let c = year % 100;
+    let d = b / 4;
+    let e = b % 4;
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30;
+    let i = c / 4;
+    let k = c % 4;
+    let l = (32 + 2 * e + 2 * i - h - k) % 7;
+    let m = (a + 11 * h + 22 * l) / 451;
+    let month = (h + l - 7 * m + 114) / 31;
+    let day = (h + l - 7 * m + 114) % 31 + 1;
+    NaiveDate::from_ymd(year, month as u32, day as u32)
+}
+
+/// The first date on the weekday on or after the date.
+fn weekday_from(mut date: NaiveDate, weekday: Weekday) -> NaiveDate {
+    while date.weekday() != weekday {
+        date = date.succ();
+    }
+    date
+}
+
+/// The last date on the weekday on or before the date.
+fn weekday_before(mut date: NaiveDate, weekday: Weekday) -> NaiveDate {
+    while date.weekday() != weekday {
+        date = date.pred();
+    }
+    date
+}
+
+/// The date itself, or the Monday after if it is on a weekend.
+fn next_weekday(date: NaiveDate) -> NaiveDate {
+    match date.weekday() {
+        Weekday::Sat | Weekday::Sun => weekday_from(date, Weekday::Mon),
+        _ => date,
+    }
+}
+
+/// The day a US federal holiday is observed, the Friday before if on a Saturday and the Monday
+/// after if on a Sunday.
+fn observed(date: NaiveDate) -> NaiveDate {
+    match date.weekday() {
+        Weekday::Sat => date.pred(),
+        Weekday::Sun => date.succ(),
+        _ => date,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::from_str(s).unwrap()
+    }
+
+    fn holidays(country: Country, year: i32) -> Vec<NaiveDate> {
+        let mut dates: Vec<NaiveDate> = country
+            .holidays(year)
+            .into_iter()
+            .map(|(date, _)| date)
+            .collect();
+        dates.sort();
+        dates
+    }
+
+    #[test]
+    fn compute_easter() {
+        assert_eq!(easter(2021), date("2021-04-04"));
+        assert_eq!(easter(2024), date("2024-03-31"));
+        assert_eq!(easter(2038), date("2038-04-25"));
+    }
+
+    #[test]
+    fn country_holidays() {
+        let norway = holidays(Country::Norway, 2024);
+        assert_eq!(norway.len(), 12);
+        assert!(norway.contains(&date("2024-03-28")));
+        assert!(norway.contains(&date("2024-05-09")));
+
+        let sweden = Country::Sweden.holidays(2024);
+        assert!(sweden.contains(&(date("2024-06-21"), "Midsummer Eve")));
+        assert!(sweden.contains(&(date("2024-11-02"), "All Saints' Day")));
+
+        // Christmas on a Sunday moves to the Tuesday after Boxing Day
+        let uk = holidays(Country::UnitedKingdom, 2022);
+        assert_eq!(
+            uk,
+            vec![
+                date("2022-01-03"),
+                date("2022-04-15"),
+                date("2022-04-18"),
+                date("2022-05-02"),
+                date("2022-05-30"),
+                date("2022-08-29"),
+                date("2022-12-26"),
+                date("2022-12-27"),
+            ]
+        );
+
+        let us = Country::UnitedStates.holidays(2021);
+        assert!(us.contains(&(date("2021-11-25"), "Thanksgiving Day")));
+        assert!(us.contains(&(date("2021-07-05"), "Independence Day")));
+        assert!(!us.contains(&(date("2021-12-31"), "New Year's Day")));
+        assert!(holidays(Country::Denmark, 2023).contains(&date("2023-05-05")));
+        assert!(!holidays(Country::Denmark, 2024).contains(&date("2024-04-26")));
+        assert_eq!(Country::from_str("uk"), Ok(Country::UnitedKingdom));
+        assert!(Country::from_str("XX").is_err());
+    }
+
+    #[test]
+    fn work_days_and_expected_hours() {
+        let calendar: CalendarConfig =
+            toml::from_str("country = \"US\"\nholidays = [\"2021-12-30\"]\nhours_per_day = 7.5")
+                .unwrap();
+        // New Year's Day 2022 is a Saturday, observed on Friday the 31st
+        assert_eq!(calendar.holiday(date("2021-12-31")), Some("New Year's Day"));
+        assert_eq!(calendar.holiday(date("2021-12-30")), Some("Day off"));
+        assert!(!calendar.is_work_day(date("2022-01-01")));
+        assert!(calendar.is_work_day(date("2021-12-29")));
+        assert_eq!(
+            calendar.work_days_in(date("2021-12-27")..=date("2022-01-02")),
+            3
+        );
+        assert_eq!(
+            calendar.expected(date("2021-12-27")..=date("2022-01-02")),
+            Some(Duration::minutes(3 * 450))
+        );
+
+        let totals: BTreeMap<NaiveDate, Duration> = vec![
+            (date("2021-12-26"), Duration::hours(2)),
+            (date("2021-12-27"), Duration::hours(9)),
+            (date("2021-12-31"), Duration::hours(1)),
+        ]
+        .into_iter()
+        .collect();
+        let balance = calendar
+            .balance(&totals, date("2021-12-27")..=date("2021-12-31"))
+            .unwrap();
+        assert_eq!(balance.tracked, Duration::hours(10));
+        assert_eq!(balance.difference(), Duration::minutes(-750));
+        assert_eq!(
+            CalendarConfig::default().expected(date("2021-12-27")..=date("2022-01-02")),
+            None
+        );
+    }
+}
@@ -0,0 +1,221 @@
+//! Charts drawn as inline SVG, for documents like the HTML export which are shared as a single file
+//! without any scripts.
+
+use crate::accounts::Color;
+use crate::duration::format_hours_minutes;
+use crate::export::Markup;
+use chrono::{Duration, NaiveDate};
+use std::collections::BTreeMap;
+use std::f64::consts::PI;
+use std::fmt::Write;
+
+const BAR_WIDTH: f64 = 36.0;
+const BAR_GAP: f64 = 8.0;
+const BAR_HEIGHT: f64 = 160.0;
+/// The space above the bars for their totals and below them for their dates.
+const LABEL_HEIGHT: f64 = 20.0;
+const RADIUS: f64 = 80.0;
+const LEGEND_WIDTH: f64 = 300.0;
+const LEGEND_LINE_HEIGHT: f64 = 20.0;
+
+/// A bar for each day with its total in hours, stacked from the time of each account on it in the
+/// color of the account. Empty without any time.
+pub fn daily_bars(
+    days: &[(NaiveDate, Vec<(String, Duration)>)],
+    colors: &BTreeMap<String, Color>,
+) -> String {
+    let total = |accounts: &[(String, Duration)]| {
+        accounts
+            .iter()
+            .fold(Duration::zero(), |sum, (_, duration)| sum + *duration)
+    };
+    let longest = days
+        .iter()
+        .map(|(_, accounts)| total(accounts))
+        .max()
+        .unwrap_or_else(Duration::zero);
+    if longest <= Duration::zero() {
+        return String::new();
+    }
+
+    let width = BAR_GAP + days.len() as f64 * (BAR_WIDTH + BAR_GAP);
+    let height = BAR_HEIGHT + 2.0 * LABEL_HEIGHT;
+    let mut svg = start_svg(width, height, "Hours per day");
+    let base = LABEL_HEIGHT + BAR_HEIGHT;
+    for (index, (date, accounts)) in days.iter().enumerate() {
+        let x = BAR_GAP + index as f64 * (BAR_WIDTH + BAR_GAP);
+        let mut top = base;
+        for (account, duration) in accounts {
+            let bar = BAR_HEIGHT * seconds(*duration) / seconds(longest);
+            top -= bar;
+            let _ = write!(
+                svg,
+                "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"{}\">{}</rect>",
+                x,
+                top,
+                BAR_WIDTH,
+                bar,
+                Color::for_account(account, colors).css(),
+                title(account, *duration),
+            );
+        }
+        let center = x + BAR_WIDTH / 2.0;
+        let _ = write!(
+            svg,
+            "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\">{:.1}</text>\
+             <text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\">{}</text>",
+            center,
+            top - 6.0,
+            seconds(total(accounts)) / 3600.0,
+            center,
+            base + LABEL_HEIGHT - 6.0,
+            date.format("%m-%d"),
+        );
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+/// A pie with a slice for each account in the color of the account, next to a legend with the
+/// total and share of each. Empty without any time.
+pub fn account_pie(totals: &[(String, Duration)], colors: &BTreeMap<String, Color>) -> String {
+    let sum = totals
+        .iter()
+        .fold(Duration::zero(), |sum, (_, duration)| sum + *duration);
+    if sum <= Duration::zero() {
+        return String::new();
+    }
+
+    let width = 2.0 * (RADIUS + LABEL_HEIGHT) + LEGEND_WIDTH;
+    let height = (2.0 * (RADIUS + LABEL_HEIGHT))
+        .max(LABEL_HEIGHT + totals.len() as f64 * LEGEND_LINE_HEIGHT);
+    let mut svg = start_svg(width, height, "Hours per account");
+    let center = RADIUS + LABEL_HEIGHT;
+    let point = |angle: f64| (center + RADIUS * angle.cos(), center + RADIUS * angle.sin());
+    // Clockwise from the top
+    let mut angle = -PI / 2.0;
+    for (index, (account, duration)) in totals.iter().enumerate() {
+        let share = seconds(*duration) / seconds(sum);
+        let color = Color::for_account(account, colors).css();
+        let title = title(account, *duration);
+        if share >= 1.0 {
+            // An arc cannot end where it starts
+            let _ = write!(
+                svg,
+                "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"{:.1}\" fill=\"{}\">{}</circle>",
+                center, center, RADIUS, color, title
+            );
+        } else if share > 0.0 {
+            let (start_x, start_y) = point(angle);
+            angle += 2.0 * PI * share;
+            let (end_x, end_y) = point(angle);
+            let _ = write!(
+                svg,
+                "<path d=\"M {:.1} {:.1} L {:.1} {:.1} A {:.1} {:.1} 0 {} 1 {:.1} {:.1} Z\" fill=\"{}\">{}</path>",
+                center,
+                center,
+                start_x,
+                start_y,
+                RADIUS,
+                RADIUS,
+                if share > 0.5 { 1 } else { 0 },
+                end_x,
+                end_y,
+                color,
+                title,
+            );
+        }
+
+        let x = 2.0 * center;
+        let y = LABEL_HEIGHT + index as f64 * LEGEND_LINE_HEIGHT;
+        let _ = write!(
+            svg,
+            "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"12\" height=\"12\" fill=\"{}\"/>\
+             <text x=\"{:.1}\" y=\"{:.1}\">{} {} ({:.0}%)</text>",
+            x,
+            y,
+            color,
+            x + 18.0,
+            y + 11.0,
+            Markup::Html.escape(account),
+            format_hours_minutes(*duration),
+            100.0 * share,
+        );
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+fn start_svg(width: f64, height: f64, label: &str) -> String {
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.0}\" height=\"{:.0}\" \
+         viewBox=\"0 0 {:.0} {:.0}\" font-family=\"sans-serif\" font-size=\"12\" role=\"img\" \
+         aria-label=\"{}\">",
+        width, height, width, height, label
+    )
+}
+
+/// A tooltip with the account and the duration.
+fn title(account: &str, duration: Duration) -> String {
+    format!(
+        "<title>{} {}</title>",
+        Markup::Html.escape(account),
+        format_hours_minutes(duration)
+    )
+}
+
+fn seconds(duration: Duration) -> f64 {
+    duration.num_seconds() as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn accounts(accounts: &[(&str, i64)]) -> Vec<(String, Duration)> {
+        accounts
+            .iter()
+            .map(|(account, minutes)| (account.to_string(), Duration::minutes(*minutes)))
+            .collect()
+    }
+
+    #[test]
+    fn draw_daily_bars() {
+        let date = |day| NaiveDate::from_ymd(2021, 7, day);
+        let days = vec![
+            (date(3), accounts(&[("A", 120), ("B <&>", 120)])),
+            (date(4), accounts(&[("A", 60)])),
+        ];
+
+        let svg = daily_bars(&days, &BTreeMap::new());
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"96\""));
+        assert_eq!(svg.matches("<rect").count(), 3);
+        // The longest day takes the whole height, the other a quarter of it
+        assert!(svg.contains("height=\"80.0\""));
+        assert!(svg.contains("height=\"40.0\""));
+        assert!(svg.contains(">4.0</text>"));
+        assert!(svg.contains(">07-04</text>"));
+        assert!(svg.contains("<title>B &lt;&amp;&gt; 2h 00m</title>"));
+        assert_eq!(daily_bars(&[(date(3), Vec::new())], &BTreeMap::new()), "");
+    }
+
+    #[test]
+    fn draw_account_pies() {
+        let colors: BTreeMap<String, Color> = vec![("A".to_string(), "blue".parse().unwrap())]
+            .into_iter()
+            .collect();
+
+        let svg = account_pie(&accounts(&[("A", 90), ("B", 30)]), &colors);
+        assert_eq!(svg.matches("<path").count(), 2);
+        // Three quarters from the top, clockwise
+        assert!(svg.contains(
+            "d=\"M 100.0 100.0 L 100.0 20.0 A 80.0 80.0 0 1 1 20.0 100.0 Z\" fill=\"#2472c8\""
+        ));
+        assert!(svg.contains(">A 1h 30m (75%)</text>"));
+
+        let svg = account_pie(&accounts(&[("A", 90), ("B", 0)]), &colors);
+        assert_eq!(svg.matches("<circle").count(), 1);
+        assert_eq!(svg.matches("<path").count(), 0);
+        assert_eq!(account_pie(&[], &colors), "");
+    }
+}
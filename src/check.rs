@@ -0,0 +1,426 @@
+//! Detection of untracked time and of duplicated, overlapping and other suspicious entries.
+
+use crate::calendar::CalendarConfig;
+use crate::reminders::ReminderConfig;
+use crate::{Entry, RunningEntry};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, TimeZone, Utc};
+use std::collections::HashSet;
+
+/// A span of time during work hours without any entries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Gap {
+    pub start: DateTime<Utc>,
+    pub stop: DateTime<Utc>,
+}
+
+impl Gap {
+    pub fn duration(&self) -> Duration {
+        self.stop - self.start
+    }
+}
+
+/// Finds the gaps of at least the given length between consecutive entries, limited to the
+/// configured work hours on work days in the given time zone. Finds no gaps if no work hours are
+/// configured.
+pub fn gaps<Tz: TimeZone>(
+    entries: &[Entry],
+    config: &ReminderConfig,
+    calendar: &CalendarConfig,
+    min_gap: Duration,
+    tz: &Tz,
+) -> Vec<Gap> {
+    let work_hours = match config.work_hours {
+        Some(work_hours) => work_hours,
+        None => return Vec::new(),
+    };
+    let mut entries: Vec<&Entry> = entries.iter().collect();
+    entries.sort_by_key(|entry| entry.start);
+
+    let mut gaps = Vec::new();
+    let mut covered_until: Option<DateTime<Utc>> = None;
+    for entry in entries {
+        if let Some(start) = covered_until.filter(|&until| until < entry.start) {
+            // Split the untracked time into the work hours of each day it spans
+            let mut date = start.with_timezone(tz).naive_local().date();
+            while date <= entry.start.with_timezone(tz).naive_local().date() {
+                let window = (
+                    local_time(tz, date, work_hours.start),
+                    local_time(tz, date, work_hours.end),
+                );
+                if let (Some(open), Some(close)) = window {
+                    let gap = Gap {
+                        start: start.max(open),
+                        stop: entry.start.min(close),
+                    };
+                    if config.is_work_day(date.weekday())
+                        && calendar.holiday(date).is_none()
+                        && gap.duration() >= min_gap
+                    {
+                        gaps.push(gap);
+                    }
+                }
+                date = date.succ();
+            }
+        }
+        covered_until = covered_until.max(Some(entry.stop));
+    }
+    gaps
+}
+
+fn local_time<Tz: TimeZone>(tz: &Tz, date: NaiveDate, time: NaiveTime) -> Option<DateTime<Utc>> {
+    tz.from_local_datetime(&date.and_time(time))
+        .earliest()
+        .map(|datetime| datetime.with_timezone(&Utc))
+}
+
+/// Finds the entries which are exact copies of an earlier entry, in order.
+pub fn duplicates(entries: &[Entry]) -> Vec<&Entry> {
+    let mut seen = HashSet::new();
+    entries
+        .iter()
+        .filter(|entry| !seen.insert(*entry))
+        .collect()
+}
+
+/// Finds the entries which start before the entry preceding them, in the order given.
+pub fn out_of_order(entries: &[Entry]) -> Vec<&Entry> {
+    entries
+        .windows(2)
+        .filter(|pair| pair[1].start < pair[0].start)
+        .map(|pair| &pair[1])
+        .collect()
+}
+
+/// Finds the pairs of entries where the second starts before the first stops, ordered by the
+/// start of the second. Exact duplicates are left to `duplicates`.
+pub fn overlaps(entries: &[Entry]) -> Vec<(&Entry, &Entry)> {
+    let mut entries: Vec<&Entry> = entries.iter().collect();
+    entries.sort_by_key(|entry| entry.start);
+    let mut overlaps = Vec::new();
+    // The entry reaching the furthest so far, which any later entry overlaps first
+    let mut longest: Option<&Entry> = None;
+    for entry in entries {
+        if let Some(previous) = longest {
+            if entry.start < previous.stop && entry != previous {
+                overlaps.push((previous, entry));
+            }
+        }
+        if longest.is_none_or(|previous| entry.stop > previous.stop) {
+            longest = Some(entry);
+        }
+    }
+    overlaps
+}
+
+/// Finds the entries which do not last any time, i.e. stop at or before their start.
+pub fn zero_durations(entries: &[Entry]) -> Vec<&Entry> {
+    entries
+        .iter()
+        .filter(|entry| entry.stop <= entry.start)
+        .collect()
+}
+
+/// Finds the running entries which have been running for longer than the maximum at the time,
+/// which are probably forgotten.
+pub fn long_running(
+    running_entries: &[RunningEntry],
+    now: DateTime<Utc>,
+    max: Duration,
+) -> Vec<&RunningEntry> {
+    running_entries
+        .iter()
+        .filter(|entry| now - entry.start > max)
+        .collect()
+}
+
+/// Finds the entries stopping after the time, as recorded with the clock ahead or by a typo.
+pub fn future_entries(entries: &[Entry], now: DateTime<Utc>) -> Vec<&Entry> {
+    entries.iter().filter(|entry| entry.stop > now).collect()
+}
+
+/// Finds the running entries starting after the time, like `future_entries`.
+pub fn future_running(running_entries: &[RunningEntry], now: DateTime<Utc>) -> Vec<&RunningEntry> {
+    running_entries
+        .iter()
+        .filter(|entry| entry.start > now)
+        .collect()
+}
+
+/// Finds the running entries already recorded as completed entries, for the same account and
+/// starting at the same time, as left behind by a stop that was interrupted before it removed
+/// them.
+pub fn already_stopped<'a>(
+    running_entries: &'a [RunningEntry],
+    entries: &[Entry],
+) -> Vec<&'a RunningEntry> {
+    running_entries
+        .iter()
+        .filter(|running| {
+            entries
+                .iter()
+                .any(|entry| entry.account == running.account && entry.start == running.start)
+        })
+        .collect()
+}
+
+/// Finds the running entries for the same account as an earlier one in the list, starting
+/// within the window of it, as left behind by a start run twice or an edit. Other running
+/// entries for the same account are left to `Storage::start` refusing them.
+pub fn duplicate_running(running_entries: &[RunningEntry], window: Duration) -> Vec<&RunningEntry> {
+    running_entries
+        .iter()
+        .enumerate()
+        .filter(|(index, running)| {
+            running_entries[..*index].iter().any(|earlier| {
+                earlier.account == running.account
+                    && (earlier.start - running.start).num_seconds().abs() <= window.num_seconds()
+            })
+        })
+        .map(|(_, running)| running)
+        .collect()
+}
+
+/// Finds the running entries starting within a completed entry for the same account, so the time
+/// is tracked twice, each with the first such entry.
+pub fn double_tracked<'a, 'b>(
+    running_entries: &'a [RunningEntry],
+    entries: &'b [Entry],
+) -> Vec<(&'a RunningEntry, &'b Entry)> {
+    running_entries
+        .iter()
+        .filter_map(|running| {
+            let entry = entries.iter().find(|entry| {
+                entry.account == running.account
+                    && entry.start < running.start
+                    && running.start < entry.stop
+            })?;
+            Some((running, entry))
+        })
+        .collect()
+}
+
+/// The time from which at least two of the running entries have been running at the same time,
+/// `None` if fewer than two are running.
+pub fn concurrent_since(running_entries: &[RunningEntry]) -> Option<DateTime<Utc>> {
+    let mut starts: Vec<DateTime<Utc>> = running_entries.iter().map(|entry| entry.start).collect();
+    starts.sort();
+    starts.get(1).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calendar::Country;
+    use crate::reminders::WorkHours;
+    use chrono::FixedOffset;
+    use std::str::FromStr;
+
+    fn entry(s: &str) -> Entry {
+        Entry::from_str(s).unwrap()
+    }
+
+    fn gap(start: &str, stop: &str) -> Gap {
+        Gap {
+            start: DateTime::from_str(start).unwrap(),
+            stop: DateTime::from_str(stop).unwrap(),
+        }
+    }
+
+    fn config() -> ReminderConfig {
+        ReminderConfig {
+            work_hours: Some(WorkHours::from_str("09:00-17:00").unwrap()),
+            ..ReminderConfig::default()
+        }
+    }
+
+    #[test]
+    fn find_gaps_within_work_hours() {
+        let entries = vec![
+            entry("2021-07-01T09:00:00Z 2021-07-01T12:00:00Z A"),
+            entry("2021-07-01T12:10:00Z 2021-07-01T14:00:00Z B"),
+            entry("2021-07-01T13:00:00Z 2021-07-01T15:00:00Z C"),
+            // Thursday after work until Friday at 10, then Friday after lunch until Monday
+            entry("2021-07-02T10:00:00Z 2021-07-02T12:00:00Z A"),
+            entry("2021-07-05T09:30:00Z 2021-07-05T17:00:00Z A"),
+        ];
+
+        assert_eq!(
+            gaps(
+                &entries,
+                &config(),
+                &CalendarConfig::default(),
+                Duration::minutes(15),
+                &Utc
+            ),
+            vec![
+                gap("2021-07-01T15:00:00Z", "2021-07-01T17:00:00Z"),
+                gap("2021-07-02T09:00:00Z", "2021-07-02T10:00:00Z"),
+                gap("2021-07-02T12:00:00Z", "2021-07-02T17:00:00Z"),
+                gap("2021-07-05T09:00:00Z", "2021-07-05T09:30:00Z"),
+            ]
+        );
+
+        // Independence Day is observed on the Monday
+        let calendar = CalendarConfig {
+            country: Some(Country::UnitedStates),
+            ..CalendarConfig::default()
+        };
+        assert_eq!(
+            gaps(&entries, &config(), &calendar, Duration::minutes(15), &Utc).last(),
+            Some(&gap("2021-07-02T12:00:00Z", "2021-07-02T17:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn find_gaps_in_local_work_hours() {
+        let entries = vec![
+            entry("2021-07-01T07:00:00Z 2021-07-01T10:00:00Z A"),
+            entry("2021-07-01T11:00:00Z 2021-07-01T15:00:00Z A"),
+        ];
+        let tz = FixedOffset::east(2 * 3600);
+
+        assert_eq!(
+            gaps(
+                &entries,
+                &config(),
+                &CalendarConfig::default(),
+                Duration::minutes(15),
+                &tz
+            ),
+            vec![gap("2021-07-01T10:00:00Z", "2021-07-01T11:00:00Z")]
+        );
+    }
+
+    #[test]
+    fn no_gaps_without_work_hours() {
+        let entries = vec![
+            entry("2021-07-01T09:00:00Z 2021-07-01T10:00:00Z A"),
+            entry("2021-07-01T12:00:00Z 2021-07-01T13:00:00Z A"),
+        ];
+
+        assert!(gaps(
+            &entries,
+            &ReminderConfig::default(),
+            &CalendarConfig::default(),
+            Duration::zero(),
+            &Utc
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn find_duplicates() {
+        let entries = vec![
+            entry("2021-07-01T09:00:00Z 2021-07-01T10:00:00Z A"),
+            entry("2021-07-01T09:00:00Z 2021-07-01T10:00:00Z B"),
+            entry("2021-07-01T09:00:00Z 2021-07-01T10:00:00Z A"),
+            entry("2021-07-01T09:00:00Z 2021-07-01T10:00:00Z A"),
+        ];
+
+        assert_eq!(duplicates(&entries), vec![&entries[2], &entries[3]]);
+    }
+
+    #[test]
+    fn find_suspicious_entries() {
+        let entries = vec![
+            entry("2021-07-01T09:00:00Z 2021-07-01T12:00:00Z A"),
+            entry("2021-07-01T10:00:00Z 2021-07-01T11:00:00Z B"),
+            entry("2021-07-01T08:00:00Z 2021-07-01T08:00:00Z C"),
+            entry("2021-07-01T11:30:00Z 2021-07-01T13:00:00Z D"),
+            entry("2021-07-01T13:00:00Z 2021-07-01T14:00:00Z E"),
+        ];
+
+        assert_eq!(out_of_order(&entries), vec![&entries[2]]);
+        assert_eq!(
+            overlaps(&entries),
+            vec![(&entries[0], &entries[1]), (&entries[0], &entries[3])]
+        );
+        assert_eq!(zero_durations(&entries), vec![&entries[2]]);
+    }
+
+    #[test]
+    fn find_long_running_entries() {
+        let running = vec![
+            RunningEntry::from_str("2021-07-01T09:00:00Z A").unwrap(),
+            RunningEntry::from_str("2021-07-01T20:00:00Z B").unwrap(),
+        ];
+        let now = DateTime::from_str("2021-07-01T21:30:00Z").unwrap();
+
+        assert_eq!(
+            long_running(&running, now, Duration::hours(12)),
+            vec![&running[0]]
+        );
+        assert!(long_running(&running, now, Duration::hours(13)).is_empty());
+    }
+
+    #[test]
+    fn find_future_entries() {
+        let entries = vec![
+            entry("2021-07-01T09:00:00Z 2021-07-01T10:00:00Z A"),
+            entry("2021-07-01T20:00:00Z 2021-07-01T22:00:00Z B"),
+        ];
+        let running = vec![
+            RunningEntry::from_str("2021-07-01T21:00:00Z A").unwrap(),
+            RunningEntry::from_str("2021-07-01T23:00:00Z B").unwrap(),
+        ];
+        let now = DateTime::from_str("2021-07-01T21:30:00Z").unwrap();
+
+        assert_eq!(future_entries(&entries, now), vec![&entries[1]]);
+        assert_eq!(future_running(&running, now), vec![&running[1]]);
+    }
+
+    #[test]
+    fn find_running_entries_already_recorded() {
+        let entries = vec![
+            entry("2021-07-01T09:00:00Z 2021-07-01T12:00:00Z A"),
+            entry("2021-07-01T13:00:00Z 2021-07-01T14:00:00Z B"),
+        ];
+        let running = vec![
+            RunningEntry::from_str("2021-07-01T09:00:00Z A").unwrap(),
+            RunningEntry::from_str("2021-07-01T10:00:00Z A").unwrap(),
+            RunningEntry::from_str("2021-07-01T10:00:00Z B").unwrap(),
+            RunningEntry::from_str("2021-07-01T14:00:00Z B").unwrap(),
+        ];
+
+        assert_eq!(already_stopped(&running, &entries), vec![&running[0]]);
+        assert_eq!(
+            double_tracked(&running, &entries),
+            vec![(&running[1], &entries[0])]
+        );
+    }
+
+    #[test]
+    fn find_duplicate_running_entries() {
+        let running = vec![
+            RunningEntry::from_str("2021-07-01T09:00:00Z A").unwrap(),
+            RunningEntry::from_str("2021-07-01T09:00:01Z A").unwrap(),
+            RunningEntry::from_str("2021-07-01T09:00:01Z B").unwrap(),
+            RunningEntry::from_str("2021-07-01T09:10:00Z A").unwrap(),
+        ];
+
+        assert_eq!(
+            duplicate_running(&running, Duration::seconds(1)),
+            vec![&running[1]]
+        );
+        assert!(duplicate_running(&running, Duration::zero()).is_empty());
+    }
+
+    #[test]
+    fn find_concurrent_running_entries() {
+        let mut running = vec![
+            RunningEntry::from_str("2021-07-01T12:00:00Z A").unwrap(),
+            RunningEntry::from_str("2021-07-01T09:00:00Z B").unwrap(),
+        ];
+        assert_eq!(concurrent_since(&running[..1]), None);
+        assert_eq!(
+            concurrent_since(&running),
+            Some(DateTime::from_str("2021-07-01T12:00:00Z").unwrap())
+        );
+
+        running.push(RunningEntry::from_str("2021-07-01T10:00:00Z C").unwrap());
+        assert_eq!(
+            concurrent_since(&running),
+            Some(DateTime::from_str("2021-07-01T10:00:00Z").unwrap())
+        );
+    }
+}
@@ -0,0 +1,174 @@
+use super::{check_category, CliError, Context, Result, ResultExt};
+use crate::output;
+use chrono::NaiveDate;
+use structopt::StructOpt;
+use timetracker::accounts::{AccountInfo, Color};
+use timetracker::is_valid_tag;
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    /// The account to define the metadata of, replacing any it had
+    #[structopt(long)]
+    pub define: Option<String>,
+
+    /// The client the account is for, which `report --by-client` sums the time of
+    #[structopt(long, requires = "define")]
+    pub client: Option<String>,
+
+    /// The color to show the account in, e.g. `blue` or `bright-blue`
+    #[structopt(long, requires = "define")]
+    pub color: Option<Color>,
+
+    /// The hourly rate, which `report` charges billable time at
+    #[structopt(long, requires = "define")]
+    pub rate: Option<f64>,
+
+    /// Start entries for the account as billable
+    #[structopt(long, requires = "define")]
+    pub billable: bool,
+
+    /// A tag to start entries for the account with
+    #[structopt(long = "tag", requires = "define")]
+    pub tags: Vec<String>,
+
+    /// The category of entries for the account and its sub-accounts without one of their
+    /// own, which `report --by-category` sums the time of
+    #[structopt(long, requires = "define")]
+    pub category: Option<String>,
+
+    /// The most hours to track on the account in a day, beyond which `start` refuses to start
+    /// entries for it
+    #[structopt(long, requires = "define")]
+    pub daily_limit: Option<f64>,
+
+    /// The hours to spend on the account and its sub-accounts in total, which `burn` projects
+    /// against the deadline
+    #[structopt(long, requires = "define")]
+    pub budget: Option<f64>,
+
+    /// The day by which the budget is meant to last, e.g. `2024-12-31`
+    #[structopt(long, requires = "define")]
+    pub deadline: Option<NaiveDate>,
+
+    /// Archive the account once it has ended, leaving it and its sub-accounts out when
+    /// picking accounts, and refusing to start them without `--force`
+    #[structopt(long, conflicts_with_all = &["define", "unarchive"])]
+    pub archive: Option<String>,
+
+    /// Bring back an archived account
+    #[structopt(long, conflicts_with = "define")]
+    pub unarchive: Option<String>,
+}
+
+pub fn run(ctx: Context, args: &Args) -> Result<()> {
+    let Context {
+        dry_run,
+        accounts_path,
+        config,
+        accounts,
+        style,
+        ..
+    } = ctx;
+    match args {
+        Args {
+            define: Some(account),
+            client,
+            color,
+            rate,
+            billable,
+            tags,
+            category,
+            daily_limit,
+            budget,
+            deadline,
+            ..
+        } => {
+            if let Some(tag) = tags.iter().find(|tag| !is_valid_tag(tag)) {
+                return Err(CliError::new(format!(r#"invalid tag "{}""#, tag)));
+            }
+            check_category(category.as_deref())?;
+            let account = config.expand_alias(account);
+            let info = AccountInfo {
+                client: client.clone(),
+                color: *color,
+                rate: *rate,
+                billable: *billable,
+                tags: tags.clone(),
+                category: category.clone(),
+                daily_limit: *daily_limit,
+                budget: *budget,
+                deadline: *deadline,
+                // Defining an archived account keeps it archived
+                archived: accounts
+                    .iter()
+                    .any(|(existing, info)| *existing == account && info.archived),
+            };
+            if dry_run {
+                println!("Would define {}: {:?}", account, info);
+                return Ok(());
+            }
+            let mut accounts = accounts;
+            accounts
+                .define(&account, info)
+                .with_context(|| format!("could not write {}", accounts_path.display()))?;
+            println!("Defined {}", account);
+        }
+        Args {
+            archive, unarchive, ..
+        } if archive.is_some() || unarchive.is_some() => {
+            let archived = archive.is_some();
+            let account = archive
+                .as_ref()
+                .or(unarchive.as_ref())
+                .expect("one is given");
+            let account = config.expand_alias(account);
+            if dry_run {
+                let action = if archived { "archive" } else { "bring back" };
+                println!("Would {} {}", action, account);
+                return Ok(());
+            }
+            let mut accounts = accounts;
+            accounts
+                .archive(&account, archived)
+                .with_context(|| format!("could not write {}", accounts_path.display()))?;
+            if archived {
+                println!("Archived {}", account);
+            } else {
+                println!("Brought back {}", account);
+            }
+        }
+        Args { define: None, .. } => {
+            let width = output::column_width(accounts.iter().map(|(account, _)| account.as_str()));
+            for (account, info) in accounts.iter() {
+                let mut details = Vec::new();
+                if let Some(client) = &info.client {
+                    details.push(format!("client {}", client));
+                }
+                if let Some(rate) = info.rate {
+                    details.push(format!("rate {}", rate));
+                }
+                if info.billable {
+                    details.push("billable".to_string());
+                }
+                if let Some(category) = &info.category {
+                    details.push(format!("@{}", category));
+                }
+                if let Some(hours) = info.daily_limit {
+                    details.push(format!("at most {}h a day", hours));
+                }
+                if let Some(hours) = info.budget {
+                    details.push(format!("budget {}h", hours));
+                }
+                if let Some(deadline) = info.deadline {
+                    details.push(format!("deadline {}", deadline));
+                }
+                if info.archived {
+                    details.push("archived".to_string());
+                }
+                details.extend(info.tags.iter().map(|tag| format!("#{}", tag)));
+                println!("{}  {}", style.account(account, width), details.join(", "));
+            }
+        }
+    }
+    Ok(())
+}
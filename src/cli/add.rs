@@ -0,0 +1,114 @@
+use super::{
+    auto_commit, check_category, parse_meta_arg, print_json, CliError, Context, Result, ResultExt,
+};
+use chrono::{Duration, Local};
+use structopt::StructOpt;
+use timetracker::duration::parse_duration;
+use timetracker::storage::StorageError;
+use timetracker::{is_valid_tag, Entry};
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    pub account: String,
+
+    /// How long the entry lasted, e.g. `1h30m`
+    #[structopt(long, parse(try_from_str = parse_duration))]
+    pub duration: Duration,
+
+    /// How long ago the entry ended, e.g. `2h`, instead of now
+    #[structopt(long, parse(try_from_str = parse_duration))]
+    pub ago: Option<Duration>,
+
+    #[structopt(short, long = "tag")]
+    pub tags: Vec<String>,
+
+    /// Mark the entry as billable
+    #[structopt(short, long)]
+    pub billable: bool,
+
+    /// The kind of work, e.g. `meetings`, by default the category of the account
+    #[structopt(short, long)]
+    pub category: Option<String>,
+
+    #[structopt(short, long)]
+    pub description: Option<String>,
+
+    /// Metadata for integrations, e.g. `ticket=PROJ-12`, may be given more than once
+    #[structopt(long, parse(try_from_str = parse_meta_arg), number_of_values = 1)]
+    pub meta: Vec<(String, String)>,
+
+    /// Print the recorded entry as JSON on a single line instead of any messages
+    #[structopt(long)]
+    pub porcelain: bool,
+}
+
+pub fn run(ctx: Context, args: &Args) -> Result<()> {
+    let Context {
+        config,
+        accounts,
+        storage,
+        ..
+    } = ctx;
+    let Args {
+        account,
+        duration,
+        ago,
+        tags: tags_arg,
+        billable,
+        category,
+        description,
+        meta,
+        porcelain,
+    } = args;
+    if let Some(tag) = tags_arg.iter().find(|tag| !is_valid_tag(tag)) {
+        return Err(CliError::new(format!(r#"invalid tag "{}""#, tag)));
+    }
+    check_category(category.as_deref())?;
+    let stop = timetracker::now() - ago.unwrap_or_else(Duration::zero);
+    // The metadata of the account fills in what the command line does not
+    let account = config.expand_alias(account);
+    let info = accounts.get(&account).cloned().unwrap_or_default();
+    let mut tags = info.tags;
+    for tag in tags_arg {
+        if !tags.contains(tag) {
+            tags.push(tag.clone());
+        }
+    }
+    let category = category
+        .clone()
+        .or_else(|| accounts.category(&account).map(str::to_string));
+    let mut builder = Entry::builder()
+        .start(stop - *duration)
+        .stop(stop)
+        .account(account)
+        .tags(tags)
+        .billable(info.billable || *billable)
+        .category_if_any(category)
+        .description_if_any(description.clone())
+        .user_if_any(config.team.user());
+    for (key, value) in meta {
+        builder = builder.meta(key, value);
+    }
+    let entry = builder.build().map_err(StorageError::from)?;
+    let overlapping = storage
+        .entries_since(entry.start, |other| other.start < entry.stop)
+        .context("could not read entries")?
+        .into_iter()
+        .find(|other| other.start < entry.stop && entry.start < other.stop);
+    if let Some(other) = overlapping {
+        return Err(StorageError::Overlap(Box::new(other)).into());
+    }
+    storage.append_entry(&entry)?;
+    if *porcelain {
+        print_json(&entry);
+    } else {
+        println!(
+            "Recorded {} from {} to {}",
+            entry.account,
+            entry.start.with_timezone(&Local).format("%H:%M"),
+            entry.stop.with_timezone(&Local).format("%H:%M")
+        );
+    }
+    auto_commit(&storage, &config, "Add")?;
+    Ok(())
+}
@@ -0,0 +1,69 @@
+use super::{auto_commit, print_json, Context, Result};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    /// `last` for the last entry, or the account to annotate the last entry of
+    pub target: String,
+
+    pub note: String,
+
+    /// Annotate the running entry for the account instead, `last` is only allowed when there
+    /// is a single running entry
+    #[structopt(long)]
+    pub running: bool,
+
+    /// Replace the description instead of adding to it
+    #[structopt(long)]
+    pub replace: bool,
+
+    /// Print the annotated entry as JSON on a single line instead of any messages
+    #[structopt(long)]
+    pub porcelain: bool,
+}
+
+pub fn run(ctx: Context, args: &Args) -> Result<()> {
+    let Context {
+        config, storage, ..
+    } = ctx;
+    let Args {
+        target,
+        note,
+        running,
+        replace,
+        porcelain,
+    } = args;
+    let account = Some(target.as_str())
+        .filter(|target| *target != "last")
+        .map(|target| config.expand_alias(target));
+    let account = account.as_deref();
+    let annotate = |description: &mut Option<String>| {
+        if *replace {
+            *description = Some(note.clone());
+        } else {
+            timetracker::annotate_description(description, note);
+        }
+    };
+    let account = if *running {
+        storage
+            .update_running_entry(account, |entry| annotate(&mut entry.description))
+            .map(|entry| {
+                if *porcelain {
+                    print_json(&entry);
+                }
+                entry.account.to_string()
+            })
+    } else {
+        storage
+            .update_last_entry(account, |entry| annotate(&mut entry.description))
+            .map(|entry| {
+                if *porcelain {
+                    print_json(&entry);
+                }
+                entry.account.to_string()
+            })
+    }?;
+
+    auto_commit(&storage, &config, &format!("Annotate {}", account))?;
+    Ok(())
+}
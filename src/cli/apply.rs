@@ -0,0 +1,90 @@
+use super::{auto_commit, parse_datetime, CliError, Context, Result, ResultExt};
+use chrono::{DateTime, Local, Utc};
+use std::fs;
+use std::io::{self, Read};
+use std::path::PathBuf;
+use structopt::StructOpt;
+use timetracker::plan;
+use timetracker::storage::StorageError;
+use timetracker::{Entry, RunningEntry};
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    /// The plan, or `-` for standard input
+    #[structopt(parse(from_os_str))]
+    pub plan: PathBuf,
+
+    /// The day of the times without one, e.g. `yesterday` or `2021-07-01`
+    #[structopt(long, default_value = "today", parse(try_from_str = parse_datetime))]
+    pub day: DateTime<Utc>,
+}
+
+pub fn run(ctx: Context, args: &Args) -> Result<()> {
+    let Context {
+        config,
+        accounts,
+        storage,
+        ..
+    } = ctx;
+    let Args { plan, day } = args;
+    let contents = if plan.as_os_str() == "-" {
+        let mut contents = String::new();
+        io::stdin()
+            .read_to_string(&mut contents)
+            .context("could not read standard input")?;
+        contents
+    } else {
+        fs::read_to_string(plan).with_context(|| format!("could not read {}", plan.display()))?
+    };
+    let spans = plan::parse(&contents, &day.with_timezone(&Local))
+        .map_err(|err| CliError::new(format!("{}", err)))?;
+    // The metadata of the account fills in the rest, like for `add`
+    let entry = |span: &plan::Span, stop: DateTime<Utc>| {
+        let account = config.expand_alias(&span.account);
+        let info = accounts.get(&account).cloned().unwrap_or_default();
+        Entry::builder()
+            .start(span.start)
+            .stop(stop)
+            .category_if_any(accounts.category(&account))
+            .account(account)
+            .tags(info.tags)
+            .billable(info.billable)
+            .user_if_any(config.team.user())
+            .build()
+            .map_err(StorageError::from)
+    };
+    let completed: Vec<Entry> = spans
+        .iter()
+        .filter_map(|span| Some(entry(span, span.stop?)))
+        .collect::<Result<_, _>>()?;
+    // Nothing is recorded unless all of it can be
+    if let (Some(first), Some(last)) = (completed.first(), completed.last()) {
+        let existing = storage
+            .entries_since(first.start, |other| other.start < last.stop)
+            .context("could not read entries")?;
+        for entry in &completed {
+            if let Some(other) = existing
+                .iter()
+                .find(|other| other.start < entry.stop && entry.start < other.stop)
+            {
+                return Err(StorageError::Overlap(Box::new(other.clone())).into());
+            }
+        }
+    }
+    for entry in &completed {
+        storage.append_entry(entry)?;
+        println!("Recorded {}", entry);
+    }
+    if let Some(span) = spans.last().filter(|span| span.stop.is_none()) {
+        let running = storage.start(RunningEntry::from(entry(span, span.start)?))?;
+        println!(
+            "Started {} at {}",
+            running.account,
+            running.start.with_timezone(&Local).format("%H:%M")
+        );
+    }
+    if !spans.is_empty() {
+        auto_commit(&storage, &config, "Apply a plan")?;
+    }
+    Ok(())
+}
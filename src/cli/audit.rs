@@ -0,0 +1,134 @@
+use super::{
+    exit_interrupted, handle_interrupts, parse_datetime, sleep_unless_interrupted, CliError,
+    Context, Result, ResultExt,
+};
+use chrono::{DateTime, Duration, Local, Utc};
+use structopt::StructOpt;
+use timetracker::activity::{self, ActivityLog, Sample};
+use timetracker::duration::{format_hours_minutes, parse_duration};
+use timetracker::report;
+use timetracker::timesheet::Timesheet;
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    /// Keep sampling the active window while entries are running, until Ctrl-C is pressed
+    #[structopt(long, conflicts_with_all = &["since", "until"])]
+    pub record: bool,
+
+    /// Show the entries starting at or after this time, today by default
+    #[structopt(long, parse(try_from_str = parse_datetime))]
+    pub since: Option<DateTime<Utc>>,
+
+    /// Show the entries starting before this time
+    #[structopt(long, parse(try_from_str = parse_datetime))]
+    pub until: Option<DateTime<Utc>>,
+
+    /// The time between samples, by default `interval` under `[activity]` or `1m`
+    #[structopt(long, parse(try_from_str = parse_duration))]
+    pub interval: Option<Duration>,
+}
+
+pub fn run(ctx: Context, args: &Args) -> Result<()> {
+    let Context {
+        dry_run,
+        config,
+        style,
+        storage,
+        ..
+    } = ctx;
+    let Args {
+        record,
+        since,
+        until,
+        interval,
+    } = args;
+    let log = ActivityLog::new(storage.entries_path().with_file_name("activity"));
+    let interval = interval
+        .or(config.activity.interval)
+        .unwrap_or_else(|| Duration::minutes(1));
+
+    if *record {
+        let command = config
+            .activity
+            .command
+            .as_deref()
+            .or(activity::default_command())
+            .ok_or_else(|| {
+                CliError::new(
+                    "there is no default command for the active window here, set `command` under \
+                     [activity]",
+                )
+            })?;
+        handle_interrupts();
+        loop {
+            let running = storage
+                .running_entries()
+                .context("could not read running entries")?;
+            if !running.is_empty() {
+                match activity::active_title(command) {
+                    Ok(Some(title)) => {
+                        let sample = Sample {
+                            time: timetracker::now(),
+                            title,
+                        };
+                        if dry_run {
+                            println!("Would record {}", sample.title);
+                        } else if let Err(err) = log.record(&sample) {
+                            ::log::error!("could not write {}: {}", log.path().display(), err);
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(err) => ::log::warn!("could not get the active window: {}", err),
+                }
+            }
+            if !sleep_unless_interrupted(interval) {
+                return Err(exit_interrupted());
+            }
+        }
+    }
+
+    let now = timetracker::now();
+    let today = now.with_timezone(&Local).date().naive_local();
+    let since = since.unwrap_or_else(|| report::day_range(today, &Local).start);
+    let mut entries: Vec<_> = storage
+        .entries_since(since, |entry| until.is_none_or(|until| entry.start < until))
+        .context("could not read entries")?
+        .into_iter()
+        .filter(|entry| entry.start >= since)
+        .collect();
+    entries.extend(
+        storage
+            .running_entries()
+            .context("could not read running entries")?
+            .into_iter()
+            .map(|entry| entry.into_entry(now))
+            .filter(|entry| entry.start >= since && until.is_none_or(|until| entry.start < until)),
+    );
+    let timesheet = Timesheet::new(entries);
+    let samples = log
+        .samples()
+        .with_context(|| format!("could not read {}", log.path().display()))?;
+
+    for entry in timesheet.entries() {
+        let activities = activity::during(entry, &samples, interval);
+        let sampled = activities.iter().fold(Duration::zero(), |total, activity| {
+            total + activity.duration
+        });
+        println!(
+            "{}-{}  {:>8}  {}  {}",
+            entry.start.with_timezone(&Local).format("%Y-%m-%d %H:%M"),
+            entry.stop.with_timezone(&Local).format("%H:%M"),
+            format_hours_minutes(entry.stop - entry.start),
+            style.account(&entry.account, 0),
+            style.dim(&format!("{} sampled", format_hours_minutes(sampled)))
+        );
+        for activity in &activities {
+            println!(
+                "    {:>8}  {}",
+                format_hours_minutes(activity.duration),
+                activity.title
+            );
+        }
+    }
+    Ok(())
+}
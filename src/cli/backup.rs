@@ -0,0 +1,61 @@
+use super::{backup_files, Context, Result, ResultExt};
+use std::path::PathBuf;
+use structopt::StructOpt;
+use timetracker::backup::Backups;
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    /// The directory to keep the snapshot in, instead of the one from the config
+    #[structopt(long, parse(from_os_str))]
+    pub to: Option<PathBuf>,
+
+    /// Remove the oldest snapshots so only this many are left, instead of the number from the
+    /// config
+    #[structopt(long)]
+    pub keep: Option<usize>,
+
+    /// List the snapshots instead of taking one
+    #[structopt(long, conflicts_with = "keep")]
+    pub list: bool,
+}
+
+pub fn run(ctx: Context, args: &Args) -> Result<()> {
+    let Context {
+        dry_run,
+        config_path,
+        favorites_path,
+        accounts_path,
+        config,
+        style,
+        storage,
+        backup_dir,
+        ..
+    } = ctx;
+    let Args { to, keep, list } = args;
+    let backup_files = backup_files(&storage, &config_path, &accounts_path, &favorites_path);
+    let backups = Backups::new(to.clone().unwrap_or_else(|| backup_dir.clone()));
+    if *list {
+        for snapshot in backups.list().context("could not list backups")? {
+            let files = snapshot.files().unwrap_or_default();
+            println!("{}  {}", snapshot.name, style.dim(&files.join(" ")));
+        }
+        return Ok(());
+    }
+    if dry_run {
+        println!("Would back up the files to {}", backups.dir().display());
+        return Ok(());
+    }
+    let snapshot = backups
+        .create(&backup_files, timetracker::now(), None)
+        .context("could not back up the files")?;
+    println!("Backed up the files to {}", snapshot.path.display());
+    if let Some(keep) = keep.or(config.backup.keep) {
+        for removed in backups
+            .rotate(keep)
+            .context("could not remove old backups")?
+        {
+            println!("Removed {}", removed.name);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,95 @@
+use super::{entries_overlapping, parse_datetime, CliError, Context, Result};
+use chrono::{DateTime, Datelike, Duration, Local, Utc};
+use structopt::StructOpt;
+use timetracker::duration::DurationFormat;
+use timetracker::report;
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    /// Count from this day, by default the start of the contract or the first entry
+    #[structopt(long, parse(try_from_str = parse_datetime))]
+    pub since: Option<DateTime<Utc>>,
+
+    /// Count the days before this time, by default up to and including today
+    #[structopt(long, parse(try_from_str = parse_datetime))]
+    pub until: Option<DateTime<Utc>>,
+
+    /// Show the balance at the end of each week
+    #[structopt(long)]
+    pub weekly: bool,
+
+    /// How to write durations
+    #[structopt(long, default_value = "hours-minutes", possible_values = DurationFormat::NAMES)]
+    pub duration_format: DurationFormat,
+}
+
+pub fn run(ctx: Context, args: &Args) -> Result<()> {
+    let Context {
+        config,
+        locale,
+        storage,
+        ..
+    } = ctx;
+    let Args {
+        since,
+        until,
+        weekly,
+        duration_format,
+    } = args;
+    if config.calendar.hours_per_day.is_none() {
+        return Err(CliError::new(
+            "no contract hours are configured, set `hours_per_day` under `[calendar]`",
+        ));
+    }
+    let now = timetracker::now();
+    let entries = entries_overlapping(&storage, now, *since, *until)?;
+    let local_date = |time: DateTime<Utc>| report::local_date(time, &Local);
+    let first = since
+        .map(local_date)
+        .or(config.calendar.contract_start)
+        .or_else(|| entries.iter().map(|entry| local_date(entry.start)).min());
+    let first = match first {
+        Some(first) => first,
+        None => return Ok(()),
+    };
+    let last = local_date(until.map_or(now, |until| until - Duration::seconds(1)));
+    let totals = report::totals_by_day(&entries, &Local);
+    let balance = |dates| {
+        config
+            .calendar
+            .balance(&totals, dates)
+            .expect("the hours per day are configured")
+    };
+
+    if *weekly {
+        let mut cumulative = Duration::zero();
+        let mut start = first;
+        while start <= last {
+            let end = (start + Duration::days(6 - start.weekday().num_days_from_monday() as i64))
+                .min(last);
+            let week = balance(start..=end);
+            cumulative = cumulative + week.difference();
+            println!(
+                "{}  {:>9}  {:>9}  {:>9}",
+                start.format("%G-W%V"),
+                locale.format_duration(*duration_format, week.tracked),
+                locale.format_duration(*duration_format, week.expected),
+                locale.format_duration(*duration_format, cumulative)
+            );
+            start = end.succ();
+        }
+    }
+    let total = balance(first..=last);
+    for (label, duration) in &[
+        ("Tracked", total.tracked),
+        ("Expected", total.expected),
+        ("Balance", total.difference()),
+    ] {
+        println!(
+            "{:<8}  {:>9}",
+            label,
+            locale.format_duration(*duration_format, *duration)
+        );
+    }
+    Ok(())
+}
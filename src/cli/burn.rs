@@ -0,0 +1,50 @@
+use super::{budget_burn, Context, Result};
+use crate::output;
+use chrono::Duration;
+use structopt::StructOpt;
+use timetracker::duration::format_hours_minutes;
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    /// The number of days up to today to average the time tracked a day over
+    #[structopt(long, default_value = "14")]
+    pub days: u32,
+}
+
+pub fn run(ctx: Context, args: &Args) -> Result<()> {
+    let Context {
+        accounts,
+        style,
+        storage,
+        ..
+    } = ctx;
+    let burns = budget_burn(&storage, &accounts, args.days)?;
+    if burns.is_empty() {
+        println!("no accounts have a budget and a deadline");
+        return Ok(());
+    }
+
+    let width = output::column_width(burns.iter().map(|burn| burn.account.as_str()));
+    for burn in burns {
+        let projection = match burn.runs_out {
+            _ if burn.remaining() == Duration::zero() => "used up".to_string(),
+            Some(runs_out) => format!("runs out {}", runs_out),
+            None => "nothing tracked recently".to_string(),
+        };
+        let projection = format!("{}, deadline {}", projection, burn.deadline);
+        let projection = if burn.is_at_risk() {
+            style.bold(&format!("{}, at risk", projection))
+        } else {
+            projection
+        };
+        println!(
+            "{}  {:>8} of {:>8}  {:>8} a day  {}",
+            style.account(&burn.account, width),
+            format_hours_minutes(burn.used),
+            format_hours_minutes(burn.budget),
+            format_hours_minutes(burn.rate),
+            projection
+        );
+    }
+    Ok(())
+}
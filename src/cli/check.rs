@@ -0,0 +1,68 @@
+use super::{CliError, Context, Result, ResultExt};
+use chrono::{Duration, Local};
+use structopt::StructOpt;
+use timetracker::check;
+use timetracker::duration::{format_hours_minutes, parse_duration};
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    /// Find gaps between entries during the work hours configured for reminders
+    #[structopt(long)]
+    pub gaps: bool,
+
+    /// The shortest gap to report, e.g. `15m` or `1h30m`
+    #[structopt(long, default_value = "15m", parse(try_from_str = parse_duration))]
+    pub min_gap: Duration,
+
+    /// Find entries which are exact copies of another entry
+    #[structopt(long)]
+    pub duplicates: bool,
+}
+
+pub fn run(ctx: Context, args: &Args) -> Result<()> {
+    let Context {
+        config, storage, ..
+    } = ctx;
+    let Args {
+        gaps,
+        min_gap,
+        duplicates,
+    } = args;
+    let (find_gaps, find_duplicates) = (*gaps || !duplicates, *duplicates || !gaps);
+    let entries = storage.entries().context("could not read entries")?;
+    let mut found = 0;
+
+    if find_gaps {
+        if config.reminders.work_hours.is_none() {
+            return Err(CliError::new(
+                "no work hours are configured, set `work_hours` under `[reminders]`",
+            ));
+        }
+        for gap in check::gaps(
+            &entries,
+            &config.reminders,
+            &config.calendar,
+            *min_gap,
+            &Local,
+        ) {
+            println!(
+                "gap {} - {} ({})",
+                gap.start.with_timezone(&Local).format("%Y-%m-%d %H:%M"),
+                gap.stop.with_timezone(&Local).format("%H:%M"),
+                format_hours_minutes(gap.duration())
+            );
+            found += 1;
+        }
+    }
+    if find_duplicates {
+        for entry in check::duplicates(&entries) {
+            println!("duplicate {}", entry);
+            found += 1;
+        }
+    }
+
+    if found > 0 {
+        return Err(CliError::exit(1));
+    }
+    Ok(())
+}
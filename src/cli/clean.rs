@@ -0,0 +1,26 @@
+use super::{auto_commit, Context, Result, ResultExt};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    /// Add the tags and description of each removed entry to the entry before it, if it is
+    /// for the same account
+    #[structopt(long)]
+    pub merge: bool,
+}
+
+pub fn run(ctx: Context, args: &Args) -> Result<()> {
+    let Context {
+        config, storage, ..
+    } = ctx;
+    let Args { merge } = args;
+    let removed = storage.clean(*merge).context("could not clean entries")?;
+    for entry in &removed {
+        println!("{}", entry);
+    }
+    println!("{} removed entries", removed.len());
+    if !removed.is_empty() {
+        auto_commit(&storage, &config, "Clean")?;
+    }
+    Ok(())
+}
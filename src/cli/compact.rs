@@ -0,0 +1,27 @@
+use super::{auto_commit, CliError, Context, Result, ResultExt};
+
+pub fn run(ctx: Context) -> Result<()> {
+    let Context {
+        dry_run,
+        config,
+        storage,
+        ..
+    } = ctx;
+    if !config.sync.oplog {
+        return Err(CliError::new(
+            "the operation log is not enabled, set `oplog = true` under [sync]",
+        ));
+    }
+    let folded = storage
+        .compact_oplog()
+        .context("could not fold the operation log")?;
+    if dry_run {
+        println!("Would fold {} operations into the data files", folded);
+    } else {
+        println!("Folded {} operations into the data files", folded);
+        if folded > 0 {
+            auto_commit(&storage, &config, "Compact the operation log")?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,64 @@
+use super::{known_accounts, Result};
+#[cfg(feature = "encryption")]
+use crate::encryption_key;
+use crate::open_storage;
+use std::path::{Path, PathBuf};
+use structopt::StructOpt;
+use timetracker::accounts::Accounts;
+use timetracker::config::Config;
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    /// What to complete
+    #[structopt(possible_values = &["accounts"])]
+    pub kind: String,
+
+    /// The text typed so far
+    #[structopt(default_value = "")]
+    pub prefix: String,
+}
+
+/// Prints the candidates starting with the prefix, one per line, for shell completion functions.
+/// Completing happens while typing, so it prints nothing rather than failing on a missing or
+/// broken file and runs without a `Context`.
+pub fn run(
+    args: &Args,
+    config_path: &Path,
+    entries_path: PathBuf,
+    running_path: PathBuf,
+) -> Result<()> {
+    let Args { kind, prefix } = args;
+    let candidates = match kind.as_str() {
+        "accounts" => accounts(config_path, entries_path, running_path),
+        _ => unreachable!("only the possible values are accepted"),
+    };
+    let mut printed = Vec::new();
+    for candidate in candidates {
+        if candidate.starts_with(prefix.as_str()) && !printed.contains(&candidate) {
+            println!("{}", candidate);
+            printed.push(candidate);
+        }
+    }
+    Ok(())
+}
+
+/// The accounts of the entries, running entries and accounts.toml, the most recently used first,
+/// and the aliases from the config.
+fn accounts(config_path: &Path, entries_path: PathBuf, running_path: PathBuf) -> Vec<String> {
+    let config = Config::load(config_path).unwrap_or_default();
+    let accounts = Accounts::load(&config_path.with_file_name("accounts.toml")).unwrap_or_default();
+    let storage = open_storage(entries_path, running_path, &config);
+    #[cfg(feature = "encryption")]
+    let storage = match encryption_key(config.storage.identity_file.as_deref()) {
+        Ok(Some(key)) => storage.key(key),
+        _ => storage,
+    };
+
+    let mut candidates = known_accounts(&storage, &accounts).unwrap_or_else(|err| {
+        log::debug!("could not read the accounts: {}", err);
+        Vec::new()
+    });
+    // Aliases are expanded wherever accounts are given
+    candidates.extend(config.aliases.keys().cloned());
+    candidates
+}
@@ -0,0 +1,75 @@
+use super::{CliError, Context, Result, ResultExt};
+use std::fs;
+use std::path::PathBuf;
+use structopt::StructOpt;
+#[cfg(feature = "compact")]
+use timetracker::compact;
+use timetracker::migrations;
+use timetracker::Entry;
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    /// The file to convert
+    #[structopt(parse(from_os_str))]
+    pub input: PathBuf,
+
+    /// The file to write the converted entries to
+    #[structopt(parse(from_os_str))]
+    pub output: PathBuf,
+
+    /// The format to convert to, the other one is converted from
+    #[structopt(long, possible_values = &["compact", "text"])]
+    pub to: String,
+
+    /// Overwrite the output file if it already exists
+    #[structopt(long)]
+    pub force: bool,
+}
+
+pub fn run(ctx: Context, args: &Args) -> Result<()> {
+    let Context { dry_run, .. } = ctx;
+    let Args {
+        input,
+        output,
+        to,
+        force,
+    } = args;
+    if output.exists() && !force {
+        return Err(CliError::new(
+            "there is already a file at the output path, use --force to overwrite it",
+        ));
+    }
+    let converted = if to == "compact" {
+        let contents = fs::read_to_string(input)
+            .with_context(|| format!("could not read {}", input.display()))?;
+        let (_, lines) = migrations::migrate(contents.lines().map(str::to_string).collect())
+            .with_context(|| format!("could not read {}", input.display()))?;
+        let entries: Vec<Entry> = lines
+            .iter()
+            .enumerate()
+            .filter(|(number, line)| *number > 0 || migrations::parse_header(line).is_none())
+            .map(|(number, line)| {
+                line.parse::<Entry>()
+                    .with_context(|| format!("line {}", number + 1))
+            })
+            .collect::<Result<_>>()?;
+        println!("Converted {} entries", entries.len());
+        compact::encode(&entries)
+    } else {
+        let contents =
+            fs::read(input).with_context(|| format!("could not read {}", input.display()))?;
+        let entries = compact::decode(&contents)
+            .with_context(|| format!("could not read {}", input.display()))?;
+        println!("Converted {} entries", entries.len());
+        let mut lines = vec![migrations::header(migrations::CURRENT_VERSION)];
+        lines.extend(entries.iter().map(Entry::to_string));
+        format!("{}\n", lines.join("\n")).into_bytes()
+    };
+    if dry_run {
+        println!("Would write {}", output.display());
+        return Ok(());
+    }
+    fs::write(output, converted)
+        .with_context(|| format!("could not write {}", output.display()))?;
+    Ok(())
+}
@@ -0,0 +1,31 @@
+use super::{auto_stop, handle_interrupts, interrupted, Context, Result, ResultExt};
+#[cfg(unix)]
+use crate::daemon;
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+
+/// How often the daemon stops the entries running at `auto_stop_at`.
+const AUTO_STOP_INTERVAL: Duration = Duration::from_secs(30);
+
+pub fn run(ctx: Context) -> Result<()> {
+    let Context {
+        daemon_running_path,
+        config,
+        storage,
+        ..
+    } = ctx;
+    let socket = daemon::socket_path(&daemon_running_path);
+    // Being stopped is how the daemon ends, so it exits successfully
+    handle_interrupts();
+    let last_auto_stop = Cell::new(Instant::now());
+    daemon::serve(&storage, &socket, &daemon_running_path, || {
+        // Checked between clients, which the daemon waits for only briefly
+        if last_auto_stop.get().elapsed() >= AUTO_STOP_INTERVAL {
+            auto_stop(&storage, &config);
+            last_auto_stop.set(Instant::now());
+        }
+        interrupted().is_some()
+    })
+    .context("could not run the daemon")?;
+    Ok(())
+}
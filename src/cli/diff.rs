@@ -0,0 +1,86 @@
+use super::{auto_commit, CliError, Context, Result, ResultExt};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use structopt::StructOpt;
+use timetracker::diff::{self, Difference};
+use timetracker::storage::Storage;
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    /// The other entries file
+    #[structopt(parse(from_os_str))]
+    pub other: PathBuf,
+
+    /// Add the entries only in the other file to the entries file
+    #[structopt(long)]
+    pub merge: bool,
+
+    /// When merging, also replace the entries that differ by the ones in the other file
+    #[structopt(long, requires = "merge")]
+    pub take_other: bool,
+}
+
+pub fn run(ctx: Context, args: &Args) -> Result<()> {
+    let Context {
+        config,
+        style,
+        storage,
+        ..
+    } = ctx;
+    let Args {
+        other,
+        merge,
+        take_other,
+    } = args;
+    let entries = storage.entries().context("could not read entries")?;
+    let other_entries = Storage::new(other.clone(), storage.running_path().to_path_buf())
+        .entries()
+        .with_context(|| format!("could not read {}", other.display()))?;
+    let differences = diff::diff(&entries, &other_entries);
+
+    for difference in &differences {
+        match difference {
+            Difference::OnlyFirst(entry) => println!("< {}", entry),
+            Difference::OnlySecond(entry) => println!("> {}", entry),
+            Difference::Changed { first, second } => {
+                println!(
+                    "~ {} {}",
+                    first.id(),
+                    style.dim(&format!("({})", difference.changed_fields().join(", ")))
+                );
+                println!("  < {}\n  > {}", first, second);
+            }
+        }
+    }
+    if !merge {
+        if !differences.is_empty() {
+            return Err(CliError::exit(1));
+        }
+        return Ok(());
+    }
+
+    let mut added = 0;
+    let mut replacements = HashMap::new();
+    for difference in differences {
+        match difference {
+            Difference::OnlySecond(entry) => {
+                storage
+                    .append_entry(&entry)
+                    .with_context(|| format!("could not add {}", entry))?;
+                added += 1;
+            }
+            Difference::Changed { first, second } if *take_other => {
+                replacements.insert(first.id(), second);
+            }
+            _ => {}
+        }
+    }
+    let replaced = storage
+        .replace_entries(&replacements)
+        .context("could not replace entries")?;
+    println!("Added {} entries, replaced {}", added, replaced);
+    if added + replaced > 0 {
+        auto_commit(&storage, &config, "Merge entries from another file")?;
+    }
+    Ok(())
+}
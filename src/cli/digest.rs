@@ -0,0 +1,55 @@
+use super::{entries_overlapping, parse_week, Context, Result, ResultExt};
+use chrono::{DateTime, Local, Utc};
+use std::fs;
+use std::ops::Range;
+use std::path::PathBuf;
+use structopt::StructOpt;
+use timetracker::digest::Digest;
+use timetracker::timesheet::Timesheet;
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    /// The week, like `2024-W15`, `this` or `last`
+    #[structopt(long, default_value = "last", parse(try_from_str = parse_week))]
+    pub week: Range<DateTime<Utc>>,
+
+    /// A file with the layout of the digest, instead of the one from the config
+    #[structopt(long, parse(from_os_str))]
+    pub template: Option<PathBuf>,
+
+    /// The number of the longest entries listed, instead of the one from the config
+    #[structopt(long)]
+    pub notable: Option<usize>,
+
+    /// The file to write to, standard output if omitted
+    #[structopt(short, long, parse(from_os_str))]
+    pub output: Option<PathBuf>,
+}
+
+pub fn run(ctx: Context, args: &Args) -> Result<()> {
+    let Context {
+        config, storage, ..
+    } = ctx;
+    let Args {
+        week,
+        template,
+        notable,
+        output,
+    } = args;
+    let now = timetracker::now();
+    let entries = entries_overlapping(&storage, now, Some(week.start), Some(week.end))?;
+    let mut digest = Digest::new(Local, notable.or(config.digest.notable).unwrap_or(5));
+    if let Some(path) = template.as_ref().or(config.digest.template.as_ref()) {
+        digest.layout = fs::read_to_string(path)
+            .context("could not read template")?
+            .parse()
+            .context("invalid template")?;
+    }
+    let summary = digest.format(&Timesheet::new(entries), week.clone());
+    match output {
+        Some(output) => fs::write(output, summary)
+            .with_context(|| format!("could not write {}", output.display()))?,
+        None => print!("{}", summary),
+    }
+    Ok(())
+}
@@ -0,0 +1,193 @@
+use super::{auto_commit, duplicate_window, open_files, CliError, Context, Result, ResultExt};
+use std::collections::BTreeSet;
+use std::fs::{self, OpenOptions};
+use structopt::StructOpt;
+use timetracker::check;
+use timetracker::duration::format_hours_minutes;
+use timetracker::RunningEntry;
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    /// Remove the running entries already recorded as completed entries
+    #[structopt(long)]
+    pub fix: bool,
+}
+
+pub fn run(ctx: Context, args: &Args) -> Result<()> {
+    let Context {
+        config_path,
+        config,
+        storage,
+        ..
+    } = ctx;
+    let Args { fix } = args;
+    let mut problems = 0;
+    let mut fixes = BTreeSet::new();
+
+    let mut paths = vec![storage.entries_path()];
+    if storage.running_path() != storage.entries_path() {
+        paths.push(storage.running_path());
+    }
+    for path in paths {
+        if path.as_os_str() == "-" {
+            continue;
+        }
+        if !path.exists() {
+            println!("{} does not exist yet", path.display());
+            continue;
+        }
+        let readable = fs::File::open(path).is_ok();
+        let writable = OpenOptions::new().write(true).open(path).is_ok();
+        if !readable || !writable {
+            println!(
+                "{} is not {}",
+                path.display(),
+                if readable { "writable" } else { "readable" }
+            );
+            problems += 1;
+            fixes.insert("give yourself read and write permission to the data files");
+        }
+    }
+    for (path, mode) in open_files(&config_path, &storage)? {
+        println!(
+            "{} can be accessed by others (permissions {:o})",
+            path.display(),
+            mode
+        );
+        problems += 1;
+        fixes.insert("run `chmod 600` on the files others can access");
+    }
+
+    let invalid_lines = storage
+        .invalid_lines()
+        .context("could not read the data files")?;
+    for invalid in &invalid_lines {
+        println!(
+            "{} line {}: {}: {:?}",
+            invalid.path.display(),
+            invalid.number,
+            invalid.err,
+            invalid.content
+        );
+    }
+    if !invalid_lines.is_empty() {
+        problems += invalid_lines.len();
+        fixes.insert(
+            "fix or remove the lines that cannot be parsed in an editor, `tt explain --line <number>` \
+             shows how a line is read",
+        );
+    }
+
+    let entries = storage.valid_entries().context("could not read entries")?;
+    let out_of_order = check::out_of_order(&entries);
+    if !out_of_order.is_empty() {
+        println!(
+            "{} entries start before the entry preceding them",
+            out_of_order.len()
+        );
+        problems += out_of_order.len();
+        fixes.insert("run `tt sort` to order the entries file");
+    }
+    for (first, second) in check::overlaps(&entries) {
+        println!(
+            "overlapping {}
+    and {}",
+            first, second
+        );
+        problems += 1;
+        fixes.insert("shorten or split overlapping entries, e.g. with `tt split`");
+    }
+    for entry in check::zero_durations(&entries) {
+        println!("no duration {}", entry);
+        problems += 1;
+        fixes.insert("run `tt clean` to remove entries without duration");
+    }
+    for entry in check::duplicates(&entries) {
+        println!("duplicate {}", entry);
+        problems += 1;
+        fixes.insert("remove the duplicated entries in an editor");
+    }
+    let running_entries = storage
+        .running_entries()
+        .context("could not read running entries")?;
+    let already_stopped = check::already_stopped(&running_entries, &entries);
+    let duplicates = check::duplicate_running(&running_entries, duplicate_window(&config));
+    if *fix && !(already_stopped.is_empty() && duplicates.is_empty()) {
+        // Of identical duplicates only the first is kept
+        let kept: Vec<RunningEntry> = running_entries
+            .iter()
+            .filter(|running| {
+                !already_stopped
+                    .iter()
+                    .any(|other| std::ptr::eq(*other, *running))
+                    && !duplicates
+                        .iter()
+                        .any(|other| std::ptr::eq(*other, *running))
+            })
+            .cloned()
+            .collect();
+        storage
+            .write_running_entries(&kept)
+            .context("could not write running entries")?;
+        for running in &already_stopped {
+            println!("Removed the running entry already recorded {}", running);
+        }
+        for running in &duplicates {
+            println!("Removed the duplicate running entry {}", running);
+        }
+        auto_commit(&storage, &config, "Remove running entries already recorded")?;
+    } else {
+        for running in &already_stopped {
+            println!("running entry already recorded {}", running);
+            problems += 1;
+            fixes.insert("run `tt doctor --fix` to remove running entries already recorded");
+        }
+        for running in &duplicates {
+            println!("duplicate running entry {}", running);
+            problems += 1;
+            fixes.insert("run `tt doctor --fix` to remove duplicate running entries");
+        }
+    }
+    for (running, entry) in check::double_tracked(&running_entries, &entries) {
+        println!(
+            "running entry tracked twice {}
+       within {}",
+            running, entry
+        );
+        problems += 1;
+        fixes
+            .insert("change the start of running entries tracked twice in an editor, or stop them");
+    }
+    let now = timetracker::now();
+    for entry in check::future_entries(&entries, now) {
+        println!("in the future {}", entry);
+        problems += 1;
+        fixes.insert("correct the times of entries in the future in an editor");
+    }
+    for running in check::future_running(&running_entries, now) {
+        println!("running entry starting in the future {}", running);
+        problems += 1;
+        fixes.insert("correct the times of entries in the future in an editor");
+    }
+    if let Some(max) = config.stop.max_running_duration {
+        for entry in check::long_running(&running_entries, now, max) {
+            println!(
+                "running for longer than {} {}",
+                format_hours_minutes(max),
+                entry
+            );
+            problems += 1;
+            fixes.insert("stop forgotten entries at the time they ended, with `tt stop --at`");
+        }
+    }
+
+    if problems == 0 {
+        println!("No problems found");
+        return Ok(());
+    }
+    println!("\n{} problems found, suggested fixes:", problems);
+    for fix in fixes {
+        println!("- {}", fix);
+    }
+    Err(CliError::exit(1))
+}
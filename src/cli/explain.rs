@@ -0,0 +1,114 @@
+use super::{CliError, Context, Result};
+use crate::output;
+use structopt::StructOpt;
+use timetracker::explain::{self, Explanation};
+use timetracker::migrations;
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    /// The line to explain, as written in the data files
+    #[structopt(required_unless = "line-number")]
+    pub line: Option<String>,
+
+    /// Explain the line with the number, counting from 1, of the entries file, or of the running
+    /// file with `--running`
+    #[structopt(long = "line", conflicts_with = "line")]
+    pub line_number: Option<usize>,
+
+    /// Read the line as a running entry, without a stop date
+    #[structopt(long)]
+    pub running: bool,
+}
+
+pub fn run(ctx: Context, args: &Args) -> Result<()> {
+    let Context { style, storage, .. } = ctx;
+    let Args {
+        line,
+        line_number,
+        running,
+    } = args;
+    let line = match (line, line_number) {
+        (Some(line), _) => line.clone(),
+        (None, Some(number)) => {
+            let path = if *running {
+                storage.running_path()
+            } else {
+                storage.entries_path()
+            };
+            match storage.raw_line(path, *number)? {
+                Some(line) => line,
+                None => {
+                    return Err(CliError::new(format!(
+                        "{} has no line {}",
+                        path.display(),
+                        number
+                    )));
+                }
+            }
+        }
+        (None, None) => unreachable!("a line is required"),
+    };
+
+    if let Some(version) = migrations::parse_header(&line) {
+        println!("The header of the files in format version {}", version);
+        return Ok(());
+    }
+    // Running entries are kept in the entries file behind a prefix in single-file mode
+    let (explanation, offset) = match line.strip_prefix("RUNNING ") {
+        Some(running_line) => (explain::explain_running_entry(running_line), 8),
+        None if *running => (explain::explain_running_entry(&line), 0),
+        None => (explain::explain_entry(&line), 0),
+    };
+    print(&style, &line, offset, &explanation);
+    if explanation.error.is_some() {
+        return Err(CliError::exit(1));
+    }
+    Ok(())
+}
+
+/// Prints the line with the parts it is split into below it, and where and why it cannot be read
+/// if it cannot. Columns are shifted by the offset, the length of a prefix before the entry.
+fn print(style: &output::Style, line: &str, offset: usize, explanation: &Explanation) {
+    println!("{}", line);
+    if let Some(err) = &explanation.error {
+        println!("{}^", " ".repeat(offset + err.column() - 1));
+        println!("error: {}", err);
+    }
+    if explanation.tokens.is_empty() {
+        return;
+    }
+
+    println!();
+    let width = output::column_width(
+        explanation
+            .tokens
+            .iter()
+            .map(|token| token.text.as_str())
+            .chain(vec!["Written"]),
+    );
+    println!(
+        "{}",
+        style.bold(&format!(
+            "{:>6}  {:<11}  {:<width$}  Read as",
+            "Column",
+            "Field",
+            "Written",
+            width = width
+        ))
+    );
+    for token in &explanation.tokens {
+        let value = match &token.value {
+            Some(value) if value.is_empty() => style.dim("(empty)"),
+            Some(value) => value.clone(),
+            None => style.dim("(invalid)"),
+        };
+        println!(
+            "{:>6}  {:<11}  {:<width$}  {}",
+            offset + token.columns.start,
+            token.field.to_string(),
+            token.text,
+            value,
+            width = width
+        );
+    }
+}
@@ -0,0 +1,430 @@
+use super::{
+    parse_datetime, parse_month, parse_week, period_bounds, CliError, Context, Result, ResultExt,
+};
+use chrono::{DateTime, Duration, Utc};
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use structopt::StructOpt;
+use timetracker::duration::parse_duration;
+use timetracker::export::{
+    Column, Csv, EntryFormatter, Gnuplot, HledgerJournal, Ics, Json, Markup, Org, Timeclock,
+    TimesheetDocument, Tsv,
+};
+use timetracker::report;
+use timetracker::rounding::{Direction, Rounding};
+use timetracker::timesheet::Timesheet;
+use timetracker::zone::Zone;
+use timetracker::Entry;
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    /// The file to write to, standard output if omitted or `-`
+    #[structopt(short, long, parse(from_os_str))]
+    pub output: Option<PathBuf>,
+
+    /// Overwrite the output file if it already exists
+    #[structopt(long, conflicts_with = "append")]
+    pub force: bool,
+
+    /// Append to the output file, by default only with the entries starting after the last
+    /// time already in it
+    #[structopt(long)]
+    pub append: bool,
+
+    /// Only export entries starting at or after this time, e.g. `2021-07-01` or `monday`
+    #[structopt(long, parse(try_from_str = parse_datetime))]
+    pub since: Option<DateTime<Utc>>,
+
+    /// Only export entries starting before this time
+    #[structopt(long, parse(try_from_str = parse_datetime))]
+    pub until: Option<DateTime<Utc>>,
+
+    /// Only include the ISO week, e.g. `2024-W15`, `this` or `last`
+    #[structopt(long, parse(try_from_str = parse_week), conflicts_with_all = &["since", "until", "month"])]
+    pub week: Option<Range<DateTime<Utc>>>,
+
+    /// Only include the month, e.g. `2024-03`, `this` or `last`
+    #[structopt(long, parse(try_from_str = parse_month), conflicts_with_all = &["since", "until"])]
+    pub month: Option<Range<DateTime<Utc>>>,
+
+    /// Only export entries for the account and its sub-accounts, can be given more than once
+    #[structopt(long = "account")]
+    pub accounts: Vec<String>,
+
+    /// Only export entries with the tag, can be given more than once for entries with all of
+    /// them
+    #[structopt(long = "tag")]
+    pub tags: Vec<String>,
+
+    /// The time zone of the exported times, `local`, `utc`, a name like `Europe/Oslo` or an
+    /// offset like `+02:00`. Local for journals, Org mode, timesheets and gnuplot and UTC
+    /// otherwise by default
+    #[structopt(long)]
+    pub timezone: Option<Zone>,
+
+    /// Timeclock entries, hledger transactions with the hours of each account per day, CSV,
+    /// tab-separated values, JSON, iCalendar events, Org mode clock lines or a timesheet with
+    /// a table of the entries of each day in Markdown or HTML, or a gnuplot data file with the
+    /// hours of each day
+    #[structopt(
+        long,
+        possible_values = &[
+            "timeclock",
+            "hledger-journal",
+            "csv",
+            "tsv",
+            "json",
+            "ics",
+            "org",
+            "markdown",
+            "html",
+            "gnuplot",
+        ],
+        default_value = "timeclock"
+    )]
+    pub format: String,
+
+    /// A file with the layout of the markdown or html timesheet, with placeholders like
+    /// `{total}` and sections like `{#weeks}...{/weeks}` repeated for each week, day and entry
+    #[structopt(long, parse(from_os_str))]
+    pub template: Option<PathBuf>,
+
+    /// The columns of the tsv format, separated by commas, out of start, stop, duration (in
+    /// seconds), account, billable, tags and description. By default
+    /// start,stop,duration,account,description,tags
+    #[structopt(long, use_delimiter = true)]
+    pub columns: Vec<Column>,
+
+    /// Export one check-in and check-out per account and day in the time zone, lasting the
+    /// total time of its entries, instead of one per entry
+    #[structopt(long, possible_values = &["entry", "day"], default_value = "entry")]
+    pub group_by: String,
+
+    /// Give the gnuplot format a column with the hours of each account instead of one with the
+    /// total
+    #[structopt(long)]
+    pub per_account: bool,
+
+    /// Report the number of entries exported so far on standard error, for large exports
+    #[structopt(long)]
+    pub progress: bool,
+
+    /// Write a file for each top-level account and its sub-accounts into the `--output`
+    /// directory instead, e.g. `ClientA.timeclock` and `ClientB.timeclock`
+    #[structopt(long, requires = "output", conflicts_with = "append")]
+    pub split_per_account: bool,
+
+    /// Round the start and stop of each exported entry to the interval, e.g. `5m` for the
+    /// nearest 5 minutes, or `down 5m` or `up 5m`. The data files are left as they are
+    #[structopt(long, parse(try_from_str = parse_round_to))]
+    pub round_to: Option<Rounding>,
+}
+
+/// Parses a rounding like `up 5m`, or only an interval for rounding to the nearest.
+fn parse_round_to(s: &str) -> Result<Rounding, String> {
+    if s.trim().contains(' ') {
+        return s.parse();
+    }
+    let interval = parse_duration(s)?;
+    if interval <= Duration::zero() {
+        return Err(format!(r#"invalid interval "{}", expected e.g. "5m""#, s));
+    }
+    Ok(Rounding {
+        direction: Direction::Nearest,
+        interval,
+    })
+}
+
+pub fn run(ctx: Context, args: &Args) -> Result<()> {
+    let Context {
+        dry_run,
+        config,
+        accounts,
+        storage,
+        ..
+    } = ctx;
+    let Args {
+        output,
+        force,
+        append,
+        since,
+        until,
+        week,
+        month,
+        accounts: account_filters,
+        tags,
+        timezone,
+        format,
+        template,
+        columns,
+        group_by,
+        per_account,
+        progress,
+        split_per_account,
+        round_to,
+    } = args;
+    if !columns.is_empty() && format != "tsv" {
+        return Err(CliError::new("--columns only applies to the tsv format"));
+    }
+    if template.is_some() && format != "markdown" && format != "html" {
+        return Err(CliError::new(
+            "--template only applies to the markdown and html formats",
+        ));
+    }
+    if *per_account && format != "gnuplot" {
+        return Err(CliError::new(
+            "--per-account only applies to the gnuplot format",
+        ));
+    }
+    let (since, until) = period_bounds(*since, *until, week, month);
+    let output = output.as_ref().filter(|output| output.as_os_str() != "-");
+    if *split_per_account && output.is_none() {
+        return Err(CliError::new(
+            "--split-per-account writes files into the --output directory",
+        ));
+    }
+
+    // Error if there's already a file located at the output path
+    let existing = match output {
+        Some(output) if output.exists() && !split_per_account => {
+            if !force && !append {
+                return Err(CliError::new("there is already a file at the output path, use --force to overwrite it or --append to add to it"));
+            }
+            fs::read_to_string(output).context("could not read output file")?
+        }
+        _ => String::new(),
+    };
+
+    // When appending, continue after the last entry or day already in the output
+    let continue_after = since.is_none() && *append;
+    let mut timesheet: Timesheet = storage
+        .entries()
+        .context("could not read entries")?
+        .into_iter()
+        .filter(|entry| {
+            since.is_none_or(|since| entry.start >= since)
+                && until.is_none_or(|until| entry.start < until)
+        })
+        .collect();
+    if !account_filters.is_empty() {
+        let accounts: Vec<String> = account_filters
+            .iter()
+            .map(|account| config.expand_alias(account))
+            .collect();
+        timesheet = timesheet
+            .into_entries()
+            .into_iter()
+            .filter(|entry| {
+                accounts
+                    .iter()
+                    .any(|account| report::is_within_account(&entry.account, account))
+            })
+            .collect();
+    }
+    for tag in tags {
+        timesheet = timesheet.filter_by_tag(tag);
+    }
+    let entries = timesheet.into_entries().into_iter().map(|mut entry| {
+        if let Some(rounding) = round_to {
+            entry.start = rounding.apply(entry.start);
+            entry.stop = rounding.apply(entry.stop);
+        }
+        entry
+    });
+
+    let timezone = timezone.unwrap_or(match format.as_str() {
+        "hledger-journal" | "org" | "markdown" | "html" | "gnuplot" => Zone::Local,
+        _ => Zone::Utc,
+    });
+    let formatter: Box<dyn EntryFormatter> = match format.as_str() {
+        "timeclock" => Box::new(Timeclock(timezone)),
+        "hledger-journal" => Box::new(HledgerJournal(timezone)),
+        "csv" => Box::new(Csv(timezone)),
+        "tsv" => Box::new(Tsv {
+            tz: timezone,
+            columns: if columns.is_empty() {
+                Column::DEFAULT.to_vec()
+            } else {
+                columns.clone()
+            },
+        }),
+        "json" => Box::new(Json),
+        "ics" => Box::new(Ics),
+        "org" => Box::new(Org(timezone)),
+        "markdown" | "html" => {
+            let markup = if format == "html" {
+                Markup::Html
+            } else {
+                Markup::Markdown
+            };
+            let layout = match template {
+                Some(path) => fs::read_to_string(path)
+                    .context("could not read template")?
+                    .parse()
+                    .context("invalid template")?,
+                None => markup.default_layout(),
+            };
+            Box::new(TimesheetDocument {
+                tz: timezone,
+                markup,
+                layout,
+                colors: accounts.colors(),
+            })
+        }
+        "gnuplot" => Box::new(Gnuplot {
+            tz: timezone,
+            per_account: *per_account,
+        }),
+        _ => unreachable!(),
+    };
+    let mut timesheet: Timesheet = entries.collect();
+    if continue_after {
+        timesheet = formatter.skip_existing(&existing, timesheet);
+    }
+    if group_by == "day" {
+        timesheet = timesheet.coalesce_by_day(&timezone);
+    }
+    let format_document = || {
+        if *append && !existing.is_empty() {
+            formatter
+                .format_appended(&timesheet)
+                .ok_or_else(|| CliError::new(format!("cannot append to a {} export", format)))
+        } else {
+            Ok(formatter.format(&timesheet))
+        }
+    };
+    let total = timesheet.entries().len();
+    let mut report_progress = |written: usize| {
+        if *progress && (written.is_multiple_of(1000) || written == total) {
+            eprint!("\rExported {} of {} entries", written, total);
+        }
+    };
+
+    // Write the exported entries to the output file or standard output
+    match output {
+        Some(output) if *split_per_account => {
+            let extension = extension(format);
+            let mut written = 0;
+            for (account, timesheet) in split_by_top_level_account(timesheet) {
+                let path = output.join(format!("{}.{}", file_name(&account), extension));
+                if dry_run {
+                    println!(
+                        "Would write {}:\n{}",
+                        path.display(),
+                        formatter.format(&timesheet)
+                    );
+                    continue;
+                }
+                if path.exists() && !force {
+                    return Err(CliError::new(format!(
+                        "there is already a file at {}, use --force to overwrite it",
+                        path.display()
+                    )));
+                }
+                fs::create_dir_all(output).context("could not create the output directory")?;
+                write_file(&*formatter, &timesheet, &path, &mut |count| {
+                    report_progress(written + count)
+                })?;
+                written += timesheet.entries().len();
+            }
+        }
+        Some(output) if dry_run => {
+            let exported = format_document()?;
+            if !exported.is_empty() {
+                println!("Would write {}:\n{}", output.display(), exported);
+            }
+        }
+        Some(output) if *append => {
+            let exported = format_document()?;
+            if exported.is_empty() {
+                return Ok(());
+            }
+            let mut file = OpenOptions::new()
+                .append(true)
+                .create(true)
+                .open(output)
+                .context("could not open output file")?;
+            // Make sure the appended entries start on a new line
+            if !existing.is_empty() && !existing.ends_with('\n') {
+                writeln!(file).context("could not write to output file")?;
+            }
+            writeln!(file, "{}", exported).context("could not write to output file")?;
+        }
+        Some(output) => write_file(&*formatter, &timesheet, output, &mut report_progress)?,
+        None => {
+            let stdout = io::stdout();
+            let mut writer = BufWriter::new(stdout.lock());
+            formatter
+                .write(&timesheet, &mut writer, &mut report_progress)
+                .and_then(|()| writeln!(writer))
+                .and_then(|()| writer.flush())
+                .context("could not write to standard output")?;
+        }
+    }
+    if *progress {
+        eprintln!();
+    }
+    Ok(())
+}
+
+/// Writes the export to the file, next to it first and renamed at the end, so an interrupted
+/// export never leaves a partial file behind.
+fn write_file(
+    formatter: &dyn EntryFormatter,
+    timesheet: &Timesheet,
+    path: &Path,
+    report_progress: &mut dyn FnMut(usize),
+) -> Result<()> {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    let file = File::create(&temporary).context("could not create output file")?;
+    let mut writer = BufWriter::new(file);
+    formatter
+        .write(timesheet, &mut writer, report_progress)
+        .and_then(|()| writer.flush())
+        .context("could not write to output file")?;
+    fs::rename(&temporary, path).context("could not write to output file")?;
+    Ok(())
+}
+
+/// The entries of each top-level account with its sub-accounts, e.g. `ClientA` with
+/// `ClientA:Support`.
+fn split_by_top_level_account(timesheet: Timesheet) -> BTreeMap<String, Timesheet> {
+    let mut accounts: BTreeMap<String, Vec<Entry>> = BTreeMap::new();
+    for entry in timesheet.into_entries() {
+        let account = entry.account.split(':').next().unwrap_or_default();
+        accounts.entry(account.to_string()).or_default().push(entry);
+    }
+    accounts
+        .into_iter()
+        .map(|(account, entries)| (account, Timesheet::new(entries)))
+        .collect()
+}
+
+/// The account as a file name, with the characters that cannot be in one replaced.
+fn file_name(account: &str) -> String {
+    let name: String = account
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | '\0' => '_',
+            c => c,
+        })
+        .collect();
+    match name.strip_prefix('.') {
+        Some(rest) => format!("_{}", rest),
+        None => name,
+    }
+}
+
+/// The file extension of the format.
+fn extension(format: &str) -> &str {
+    match format {
+        "hledger-journal" => "journal",
+        "markdown" => "md",
+        "gnuplot" => "dat",
+        format => format,
+    }
+}
@@ -0,0 +1,102 @@
+use super::{check_category, CliError, Context, Result, ResultExt};
+use std::collections::BTreeMap;
+use structopt::StructOpt;
+use timetracker::favorites::{self, Favorite, Favorites};
+use timetracker::is_valid_tag;
+
+#[derive(Debug, StructOpt)]
+pub enum FavCommand {
+    /// Adds a favorite, replacing any with the same name
+    Add {
+        name: String,
+
+        #[structopt(long)]
+        account: String,
+
+        /// Comma-separated tags
+        #[structopt(long, use_delimiter = true)]
+        tags: Vec<String>,
+
+        /// Mark the entries as billable
+        #[structopt(short, long)]
+        billable: bool,
+
+        #[structopt(short, long)]
+        category: Option<String>,
+
+        #[structopt(short, long)]
+        description: Option<String>,
+    },
+    /// Lists the favorites
+    List,
+    /// Removes a favorite
+    Remove { name: String },
+}
+
+pub fn run(ctx: Context, command: &FavCommand) -> Result<()> {
+    let Context {
+        dry_run,
+        favorites_path,
+        config,
+        ..
+    } = ctx;
+    let mut favorites = Favorites::load(&favorites_path).context("could not read favorites")?;
+    match command {
+        FavCommand::Add {
+            name,
+            account,
+            tags,
+            billable,
+            category,
+            description,
+        } => {
+            if !favorites::is_valid_name(name) {
+                return Err(CliError::new(format!(
+                    r#"invalid favorite name "{}""#,
+                    name
+                )));
+            }
+            if let Some(tag) = tags.iter().find(|tag| !is_valid_tag(tag)) {
+                return Err(CliError::new(format!(r#"invalid tag "{}""#, tag)));
+            }
+            check_category(category.as_deref())?;
+            let favorite = Favorite {
+                name: name.clone(),
+                account: config.expand_alias(account),
+                tags: tags.clone(),
+                billable: *billable,
+                category: category.clone(),
+                description: description.clone(),
+                meta: BTreeMap::new(),
+            };
+            if dry_run {
+                println!("Would add the favorite {}", favorite);
+                return Ok(());
+            }
+            favorites
+                .add(favorite)
+                .context("could not write favorites")?;
+        }
+        FavCommand::List => {
+            for favorite in favorites.iter() {
+                println!("{}", favorite);
+            }
+        }
+        FavCommand::Remove { name } => {
+            if dry_run {
+                println!("Would remove the favorite {}", name);
+                return Ok(());
+            }
+            let removed = favorites
+                .remove(name)
+                .context("could not write favorites")?;
+            if !removed {
+                return Err(CliError::new(format!(
+                    r#"there is no favorite named "{}""#,
+                    name
+                )));
+            }
+        }
+    }
+    Ok(())
+}
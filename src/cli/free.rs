@@ -0,0 +1,85 @@
+use super::{parse_datetime, CliError, Context, Result, ResultExt};
+use chrono::{DateTime, Duration, Local, Utc};
+use structopt::StructOpt;
+use timetracker::duration::DurationFormat;
+use timetracker::report;
+use timetracker::timesheet::Timesheet;
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    /// The day, e.g. `today` or `2021-07-01`
+    #[structopt(long, default_value = "today", parse(try_from_str = parse_datetime))]
+    pub day: DateTime<Utc>,
+
+    /// The hours to work, by default `hours_per_day` under `[calendar]` on work days
+    #[structopt(long)]
+    pub hours: Option<f64>,
+
+    /// How to write durations
+    #[structopt(long, default_value = "hours-minutes", possible_values = DurationFormat::NAMES)]
+    pub duration_format: DurationFormat,
+}
+
+pub fn run(ctx: Context, args: &Args) -> Result<()> {
+    let Context {
+        config,
+        locale,
+        storage,
+        ..
+    } = ctx;
+    let Args {
+        day,
+        hours,
+        duration_format,
+    } = args;
+    let now = timetracker::now();
+    let date = report::local_date(*day, &Local);
+    let target = match hours {
+        Some(hours) => Duration::seconds((hours * 3600.0).round() as i64),
+        None => config.calendar.expected(date..=date).ok_or_else(|| {
+            CliError::new(
+                "give the hours to work with --hours, or set hours_per_day under [calendar] in \
+                 the config",
+            )
+        })?,
+    };
+    let range = report::day_range(date, &Local);
+    let completed: Timesheet = storage
+        .entries_since(range.start, |entry| entry.start < range.end)
+        .context("could not read entries")?
+        .into_iter()
+        .collect();
+    let running: Timesheet = storage
+        .running_entries()
+        .context("could not read running entries")?
+        .into_iter()
+        .map(|entry| entry.into_entry(now))
+        .collect();
+    let running = running.between(range.clone());
+    let tracked = completed.between(range.clone()).total_duration() + running.total_duration();
+    let left = target - tracked;
+    println!(
+        "Tracked {} of {} on {}",
+        locale.format_duration(*duration_format, tracked),
+        locale.format_duration(*duration_format, target),
+        date.format(&locale.pattern("%A %Y-%m-%d", &date))
+    );
+    if left <= Duration::zero() {
+        println!(
+            "Done, {} over",
+            locale.format_duration(*duration_format, -left)
+        );
+    } else if running.entries().is_empty() || now >= range.end {
+        println!("{} left", locale.format_duration(*duration_format, left));
+    } else {
+        // Overlapping running entries count twice, so they reach it sooner
+        let count = running.entries().len() as i32;
+        let done = now + left / count;
+        println!(
+            "{} left, done at {} while running",
+            locale.format_duration(*duration_format, left),
+            done.with_timezone(&Local).format("%H:%M")
+        );
+    }
+    Ok(())
+}
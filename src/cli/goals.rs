@@ -0,0 +1,51 @@
+use super::{goal_progress, Context, Result};
+use timetracker::duration::format_hours_minutes;
+
+/// Renders a bar filled according to the fraction, capped at a full bar.
+fn progress_bar(fraction: f64, width: usize) -> String {
+    let filled = ((fraction.clamp(0.0, 1.0) * width as f64).round()) as usize;
+    format!("[{}{}]", "#".repeat(filled), "-".repeat(width - filled))
+}
+
+pub fn run(ctx: Context) -> Result<()> {
+    let Context {
+        config, storage, ..
+    } = ctx;
+    let progress = goal_progress(&storage, &config)?;
+    if progress.is_empty() {
+        println!("no goals are configured");
+        return Ok(());
+    }
+
+    let width = progress
+        .iter()
+        .map(|progress| progress.goal.scope.to_string().chars().count())
+        .max()
+        .unwrap_or(0);
+    for progress in progress {
+        let status = if progress.is_exceeded() {
+            format!(
+                "exceeded by {}",
+                format_hours_minutes(progress.tracked - progress.goal.target)
+            )
+        } else {
+            format!(
+                "{} remaining, {} expected by now",
+                format_hours_minutes(progress.remaining()),
+                format_hours_minutes(progress.expected)
+            )
+        };
+        println!(
+            "{:<width$}  {:<7}  {} {:>4.0}%  {} of {}  {}",
+            progress.goal.scope.to_string(),
+            progress.goal.period.to_string(),
+            progress_bar(progress.fraction(), 20),
+            progress.fraction() * 100.0,
+            format_hours_minutes(progress.tracked),
+            format_hours_minutes(progress.goal.target),
+            status,
+            width = width
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,109 @@
+use super::{
+    parse_datetime, parse_month, parse_week, period_bounds, CliError, Context, Result, ResultExt,
+};
+use crate::output;
+use chrono::{DateTime, Local, Utc};
+use regex::RegexBuilder;
+use std::ops::Range;
+use structopt::StructOpt;
+use timetracker::duration::DurationFormat;
+use timetracker::timesheet::Timesheet;
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    /// The regular expression, e.g. `login|auth`
+    pub pattern: String,
+
+    /// Match regardless of case
+    #[structopt(short, long)]
+    pub ignore_case: bool,
+
+    /// Only search entries starting at or after this time, e.g. `monday` or `2021-07-01`
+    #[structopt(long, parse(try_from_str = parse_datetime))]
+    pub since: Option<DateTime<Utc>>,
+
+    /// Only search entries starting before this time
+    #[structopt(long, parse(try_from_str = parse_datetime))]
+    pub until: Option<DateTime<Utc>>,
+
+    /// Only include the ISO week, e.g. `2024-W15`, `this` or `last`
+    #[structopt(long, parse(try_from_str = parse_week), conflicts_with_all = &["since", "until", "month"])]
+    pub week: Option<Range<DateTime<Utc>>>,
+
+    /// Only include the month, e.g. `2024-03`, `this` or `last`
+    #[structopt(long, parse(try_from_str = parse_month), conflicts_with_all = &["since", "until"])]
+    pub month: Option<Range<DateTime<Utc>>>,
+
+    /// How to write durations
+    #[structopt(long, default_value = "hours-minutes", possible_values = DurationFormat::NAMES)]
+    pub duration_format: DurationFormat,
+}
+
+pub fn run(ctx: Context, args: &Args) -> Result<()> {
+    let Context {
+        style,
+        locale,
+        storage,
+        ..
+    } = ctx;
+    let Args {
+        pattern,
+        ignore_case,
+        since,
+        until,
+        week,
+        month,
+        duration_format,
+    } = args;
+    let pattern = RegexBuilder::new(pattern)
+        .case_insensitive(*ignore_case)
+        .build()
+        .context("invalid pattern")?;
+    let (since, until) = period_bounds(*since, *until, week, month);
+    let timesheet: Timesheet = storage
+        .entries()
+        .context("could not read entries")?
+        .into_iter()
+        .filter(|entry| {
+            since.is_none_or(|since| entry.start >= since)
+                && until.is_none_or(|until| entry.start < until)
+        })
+        .collect();
+    let matching = timesheet.filter_by_pattern(&pattern);
+    if matching.is_empty() {
+        // No matches is a failure, as with grep
+        return Err(CliError::exit(1));
+    }
+
+    let width = output::column_width(matching.entries().iter().map(|entry| &*entry.account));
+    for entry in matching.entries() {
+        let start = entry.start.with_timezone(&Local);
+        let padding = width.saturating_sub(entry.account.chars().count());
+        let mut details: Vec<String> = entry.tags.iter().map(|tag| format!("#{}", tag)).collect();
+        details.extend(
+            entry
+                .description
+                .as_deref()
+                .map(|description| style.highlight(description, &pattern)),
+        );
+        println!(
+            "{}  {}-{}  {:>8}  {}{}  {}",
+            start.format("%Y-%m-%d"),
+            start.format("%H:%M"),
+            entry.stop.with_timezone(&Local).format("%H:%M"),
+            locale.format_duration(*duration_format, entry.stop - entry.start),
+            style.highlight(&entry.account, &pattern),
+            " ".repeat(padding),
+            details.join(" ")
+        );
+    }
+    println!(
+        "{}",
+        style.bold(&format!(
+            "{} entries, {} in total",
+            matching.entries().len(),
+            locale.format_duration(*duration_format, matching.total_duration())
+        ))
+    );
+    Ok(())
+}
@@ -0,0 +1,136 @@
+use super::{Context, Result, ResultExt};
+use crate::output::Style;
+use chrono::{Datelike, Duration, Local, NaiveDate};
+use std::collections::BTreeMap;
+use structopt::StructOpt;
+use timetracker::duration::format_hours_minutes;
+use timetracker::locale::Locale;
+use timetracker::report;
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    #[structopt(long)]
+    pub year: Option<i32>,
+
+    #[structopt(long)]
+    pub account: Option<String>,
+}
+
+/// The number of levels of the heatmap cells, see `Style::heat`.
+const HEATMAP_LEVELS: usize = 5;
+
+/// Picks the heatmap level for the time tracked on a day.
+fn heatmap_level(total: Duration) -> usize {
+    let hours = total.num_minutes() as f64 / 60.0;
+    match hours {
+        h if h <= 0.0 => 0,
+        h if h < 2.0 => 1,
+        h if h < 4.0 => 2,
+        h if h < 6.0 => 3,
+        _ => HEATMAP_LEVELS - 1,
+    }
+}
+
+/// Renders the daily totals of a year as a grid with a column per week and a row per weekday.
+fn render_heatmap(
+    year: i32,
+    totals: &BTreeMap<NaiveDate, Duration>,
+    locale: Locale,
+    style: &Style,
+) -> String {
+    let first = NaiveDate::from_ymd(year, 1, 1);
+    let last = NaiveDate::from_ymd(year, 12, 31);
+
+    // Start the grid at the Monday of the week containing the first day of the year
+    let grid_start = first - Duration::days(first.weekday().num_days_from_monday() as i64);
+    let weeks = ((last - grid_start).num_days() / 7 + 1) as usize;
+
+    // Label each month above the week it starts in
+    let mut months = vec![' '; weeks * 2];
+    for month in 1..=12 {
+        let date = NaiveDate::from_ymd(year, month, 1);
+        let column = ((date - grid_start).num_days() / 7) as usize * 2;
+        let name = date.format(&locale.pattern("%b", &date)).to_string();
+        for (offset, c) in name.chars().enumerate() {
+            if let Some(slot) = months.get_mut(column + offset) {
+                *slot = c;
+            }
+        }
+    }
+    let mut lines = vec![format!(
+        "    {}",
+        months.iter().collect::<String>().trim_end()
+    )];
+
+    for weekday in 0..7 {
+        let label = match weekday {
+            0 => "Mon",
+            2 => "Wed",
+            4 => "Fri",
+            _ => "",
+        };
+        let mut line = format!("{:<4}", label);
+        for week in 0..weeks {
+            let date = grid_start + Duration::days((week * 7 + weekday) as i64);
+            if date < first || date > last {
+                line.push_str("  ");
+            } else {
+                let total = totals.get(&date).copied().unwrap_or_else(Duration::zero);
+                line.push_str(&style.heat(heatmap_level(total)));
+                line.push(' ');
+            }
+        }
+        lines.push(line.trim_end().to_string());
+    }
+
+    let legend = (0..HEATMAP_LEVELS)
+        .map(|level| style.heat(level))
+        .collect::<Vec<String>>()
+        .join(" ");
+    lines.push(format!("\n    Less {} More", legend));
+
+    let year_totals: Vec<Duration> = totals
+        .iter()
+        .filter(|(date, _)| date.year() == year)
+        .map(|(_, total)| *total)
+        .collect();
+    let total = year_totals
+        .iter()
+        .fold(Duration::zero(), |sum, total| sum + *total);
+    lines.push(format!(
+        "    {} tracked over {} days",
+        format_hours_minutes(total),
+        year_totals.len()
+    ));
+
+    lines.join("\n")
+}
+
+pub fn run(ctx: Context, args: &Args) -> Result<()> {
+    let Context {
+        config,
+        locale,
+        style,
+        storage,
+        ..
+    } = ctx;
+    let Args { year, account } = args;
+    let year = year.unwrap_or_else(|| timetracker::now().with_timezone(&Local).date().year());
+    let account = account
+        .as_deref()
+        .map(|account| config.expand_alias(account));
+    let entries: Vec<_> = storage
+        .entries()
+        .context("could not read entries")?
+        .into_iter()
+        .filter(|entry| {
+            account
+                .as_ref()
+                .is_none_or(|account| *entry.account == **account)
+        })
+        .collect();
+    let totals = report::totals_by_day(&entries, &Local);
+
+    println!("{}", render_heatmap(year, &totals, locale, &style));
+    Ok(())
+}
@@ -0,0 +1,61 @@
+use super::{parse_datetime, Context, Result, ResultExt};
+use chrono::{DateTime, Local, Utc};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    /// Only show changes made at or after this time, e.g. `2021-07-01` or `monday`
+    #[structopt(long, parse(try_from_str = parse_datetime))]
+    pub since: Option<DateTime<Utc>>,
+}
+
+pub fn run(ctx: Context, args: &Args) -> Result<()> {
+    let Context {
+        config,
+        style,
+        storage: _,
+        audit_log,
+        ..
+    } = ctx;
+    let Args { since } = args;
+    let changes = audit_log
+        .changes()
+        .context("could not read the audit log")?;
+    let mut previous: Option<(DateTime<Utc>, &Vec<String>)> = None;
+    for change in changes
+        .iter()
+        .filter(|change| since.is_none_or(|since| change.time >= since))
+    {
+        // The changes of a command are listed under it
+        if previous != Some((change.time, &change.args)) {
+            println!(
+                "{}  {}",
+                style.bold(
+                    &change
+                        .time
+                        .with_timezone(&Local)
+                        .format("%Y-%m-%d %H:%M:%S")
+                        .to_string()
+                ),
+                std::iter::once("tt")
+                    .chain(change.args.iter().map(String::as_str))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            );
+            previous = Some((change.time, &change.args));
+        }
+        if let Some(before) = &change.before {
+            println!("  - {:<7}  {}", change.file, before);
+        }
+        if let Some(after) = &change.after {
+            println!("  + {:<7}  {}", change.file, after);
+        }
+    }
+    if changes.is_empty() && !config.storage.audit {
+        eprintln!(
+            "Nothing has been recorded, set audit = true under [storage] in the config \
+                 to record changes"
+        );
+    }
+    Ok(())
+}
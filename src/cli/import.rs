@@ -0,0 +1,157 @@
+use super::{auto_commit, Context, Result, ResultExt};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read};
+use std::path::PathBuf;
+use structopt::StructOpt;
+use timetracker::import::{AmbiguousTimes, CsvImport, CsvMapping, ImportedEntry};
+use timetracker::imported::ImportedLog;
+use timetracker::zone::Zone;
+use timetracker::Entry;
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    /// The file to read, standard input if `-`
+    #[structopt(parse(from_os_str))]
+    pub input: PathBuf,
+
+    /// The time tracker the file is from, or `csv` for a CSV file with the columns given by
+    /// `--map`
+    #[structopt(long, possible_values = &["watson", "csv"], default_value = "watson")]
+    pub format: String,
+
+    /// The columns of the fields of a CSV file, by number or header name, e.g.
+    /// `start=2,stop=3,account=1,description=5`; tags, billable and an id identifying each
+    /// record can also be given
+    #[structopt(long, required_if("format", "csv"))]
+    pub map: Option<CsvMapping>,
+
+    /// The format of times in a CSV file, e.g. `%d.%m.%Y %H:%M`, by default RFC 3339. Times
+    /// without an offset are in the time zone from `--assume-timezone`
+    #[structopt(long)]
+    pub datetime_format: Option<String>,
+
+    /// The time zone of times without an offset, `local`, `utc`, a name like `Europe/Oslo`
+    /// or an offset like `+02:00`
+    #[structopt(long, default_value = "local")]
+    pub assume_timezone: Zone,
+
+    /// Which time to take when a time without an offset occurs twice, as clocks are turned
+    /// back for daylight saving time. Records with such times are skipped by default
+    #[structopt(long, default_value = "reject", possible_values = AmbiguousTimes::NAMES)]
+    pub ambiguous_times: AmbiguousTimes,
+
+    /// Skip the header of a CSV file, which allows mapping columns by name
+    #[structopt(long)]
+    pub header: bool,
+
+    /// Change the entries imported before whose records have changed since, instead of
+    /// leaving them as they are
+    #[structopt(long)]
+    pub update: bool,
+}
+
+pub fn run(ctx: Context, args: &Args) -> Result<()> {
+    let Context {
+        dry_run,
+        config,
+        storage,
+        ..
+    } = ctx;
+    let Args {
+        input,
+        format,
+        map,
+        datetime_format,
+        assume_timezone,
+        ambiguous_times,
+        header,
+        update,
+    } = args;
+    let mut contents = String::new();
+    if input.as_os_str() == "-" {
+        io::stdin()
+            .read_to_string(&mut contents)
+            .context("could not read standard input")?;
+    } else {
+        contents = fs::read_to_string(input).context("could not read input file")?;
+    }
+    let (imported, errors) = match format.as_str() {
+        "watson" => {
+            timetracker::import::watson_frames(&contents).map(|entries| (entries, Vec::new()))
+        }
+        "csv" => CsvImport {
+            mapping: map.clone().expect("--map is required for csv"),
+            datetime_format: datetime_format.clone(),
+            header: *header,
+            ambiguous: *ambiguous_times,
+        }
+        .entries(&contents, assume_timezone),
+        _ => unreachable!(),
+    }
+    .with_context(|| format!("could not import {}", input.display()))?;
+    for error in &errors {
+        eprintln!("skipping {}", error);
+    }
+
+    // Skip entries that were imported before, found by their source or else as they are
+    let mut log = ImportedLog::load(&storage.running_path().with_file_name("imported"))
+        .context("could not read imported sources")?;
+    let existing: HashMap<String, Entry> = storage
+        .entries()
+        .context("could not read entries")?
+        .into_iter()
+        .map(|entry| (entry.id(), entry))
+        .collect();
+    let mut new = Vec::new();
+    let mut updated = HashMap::new();
+    let mut recorded = Vec::new();
+    for ImportedEntry { entry, source } in &imported {
+        let previous = source
+            .as_deref()
+            .and_then(|source| log.entry_id(source))
+            .map(|id| (id.to_string(), existing.get(id)));
+        match previous {
+            // Changed records are only updated when asked to, deleted entries stay deleted
+            Some((id, Some(previous))) if previous != entry && *update => {
+                updated.insert(id, entry.clone());
+            }
+            Some(_) => continue,
+            None if existing.get(&entry.id()) == Some(entry) => {}
+            None => new.push(entry),
+        }
+        recorded.extend(source.iter().map(|source| (source, entry.id())));
+    }
+
+    if dry_run {
+        for entry in new.iter().copied().chain(updated.values()) {
+            println!("{}", entry);
+        }
+    } else {
+        for entry in &new {
+            storage
+                .append_entry(entry)
+                .context("could not write entry")?;
+        }
+        storage
+            .replace_entries(&updated)
+            .context("could not update entries")?;
+        for (source, id) in recorded {
+            log.record(source, &id)
+                .context("could not record imported source")?;
+        }
+    }
+    println!(
+        "{} new entries, {} updated, {} already imported",
+        new.len(),
+        updated.len(),
+        imported.len() - new.len() - updated.len()
+    );
+    if !errors.is_empty() {
+        println!("{} rows could not be imported", errors.len());
+    }
+    if !dry_run && (!new.is_empty() || !updated.is_empty()) {
+        auto_commit(&storage, &config, "Import")?;
+    }
+    Ok(())
+}
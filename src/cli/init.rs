@@ -0,0 +1,126 @@
+use super::{ask, CliError, Result, ResultExt};
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::Path;
+use structopt::StructOpt;
+use timetracker::calendar::{CalendarConfig, Country};
+use timetracker::config::InitialConfig;
+use timetracker::migrations;
+use timetracker::permissions;
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    /// Use the defaults instead of asking, e.g. when setting up from a script
+    #[structopt(long)]
+    pub defaults: bool,
+
+    /// Replace an existing config file
+    #[structopt(long)]
+    pub force: bool,
+}
+
+/// Asks for the settings `tt init` writes to the config file until each answer is valid.
+fn ask_initial_config() -> Result<InitialConfig> {
+    let default_days = CalendarConfig::default().work_days.join(",");
+    let work_days = loop {
+        let answer = ask("Which days of the week do you work?", &default_days)?;
+        let days: Vec<String> = answer
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|day| !day.is_empty())
+            .map(str::to_lowercase)
+            .collect();
+        match days
+            .iter()
+            .find(|day| day.parse::<chrono::Weekday>().is_err())
+        {
+            Some(day) => eprintln!(r#""{}" is not a day of the week, e.g. mon"#, day),
+            None => break days,
+        }
+    };
+    let hours_per_day = loop {
+        let answer = ask(
+            "How many hours do you work a day? (empty for no target)",
+            "",
+        )?;
+        if answer.is_empty() {
+            break None;
+        }
+        match answer.parse::<f64>() {
+            Ok(hours) if hours > 0.0 => break Some(hours),
+            _ => eprintln!("expected a number of hours, e.g. 7.5"),
+        }
+    };
+    let country = loop {
+        let answer = ask(
+            "Which country's public holidays do you have off? (DK, DE, NO, SE, GB, US or empty)",
+            "",
+        )?;
+        if answer.is_empty() {
+            break None;
+        }
+        match answer.parse::<Country>() {
+            Ok(country) => break Some(country),
+            Err(err) => eprintln!("{}", err),
+        }
+    };
+    Ok(InitialConfig {
+        work_days,
+        hours_per_day,
+        country,
+    })
+}
+
+/// Writes the config file and creates the data files, which works before there is a config and
+/// with a broken one when replacing it, so it runs without a `Context`.
+pub fn run(
+    args: &Args,
+    dry_run: bool,
+    config_path: &Path,
+    entries_path: &Path,
+    running_path: &Path,
+) -> Result<()> {
+    if config_path.exists() && !args.force {
+        return Err(CliError::new(format!(
+            "{} already exists, use --force to replace it",
+            config_path.display()
+        )));
+    }
+    let initial = if args.defaults || !io::stdin().is_terminal() {
+        InitialConfig {
+            work_days: CalendarConfig::default().work_days,
+            hours_per_day: None,
+            country: None,
+        }
+    } else {
+        ask_initial_config()?
+    };
+    if dry_run {
+        println!(
+            "Would write {}:\n{}",
+            config_path.display(),
+            initial.to_toml()
+        );
+        return Ok(());
+    }
+    let create_dir = |path: &Path| -> Result<()> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("could not create {}", dir.display()))?;
+        }
+        Ok(())
+    };
+    create_dir(config_path)?;
+    permissions::write_private(config_path, initial.to_toml())
+        .with_context(|| format!("could not write {}", config_path.display()))?;
+    println!("Wrote {}", config_path.display());
+    create_dir(running_path)?;
+    create_dir(entries_path)?;
+    if !entries_path.exists() {
+        let header = migrations::header(migrations::CURRENT_VERSION);
+        permissions::write_private(entries_path, format!("{}\n", header))
+            .with_context(|| format!("could not write {}", entries_path.display()))?;
+        println!("Created {}", entries_path.display());
+    }
+    println!("Start tracking with `tt start <account>`");
+    Ok(())
+}
@@ -0,0 +1,132 @@
+use super::{parse_month, print_json, Context, Result, ResultExt};
+use chrono::{DateTime, Local, Utc};
+use std::ops::Range;
+use std::path::PathBuf;
+use structopt::StructOpt;
+use timetracker::duration::format_hours_minutes;
+use timetracker::invoices::InvoiceLog;
+use timetracker::report;
+use timetracker::storage::Storage;
+
+#[derive(Debug, StructOpt)]
+pub enum InvoiceCommand {
+    /// Issues an invoice for the billable time of the account in the month which is not on an
+    /// invoice yet, numbering it by the month
+    Issue(IssueArgs),
+    /// Lists the issued invoices
+    List(ListArgs),
+}
+
+#[derive(Debug, StructOpt)]
+pub struct IssueArgs {
+    /// The account to invoice, with its sub-accounts
+    #[structopt(long)]
+    pub account: String,
+
+    /// The month to invoice, e.g. `2024-03`, `this` or `last`
+    #[structopt(long, parse(try_from_str = parse_month))]
+    pub month: Range<DateTime<Utc>>,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct ListArgs {
+    /// Only list the invoices for the account and its sub-accounts
+    #[structopt(long)]
+    pub account: Option<String>,
+
+    /// Print each invoice as JSON on a line of its own
+    #[structopt(long)]
+    pub porcelain: bool,
+}
+
+/// The invoices are kept next to the entries file.
+fn invoices_path(storage: &Storage) -> PathBuf {
+    storage.entries_path().with_file_name("invoices")
+}
+
+pub fn run(ctx: Context, command: &InvoiceCommand) -> Result<()> {
+    let Context {
+        dry_run,
+        config,
+        accounts,
+        storage,
+        ..
+    } = ctx;
+    let mut log =
+        InvoiceLog::load(&invoices_path(&storage)).context("could not read the invoices")?;
+    match command {
+        InvoiceCommand::Issue(IssueArgs { account, month }) => {
+            let account = config.expand_alias(account);
+            let entries = storage
+                .entries_since(month.start, |entry| {
+                    entry.billable()
+                        && entry.start >= month.start
+                        && entry.start < month.end
+                        && report::is_within_account(entry.account(), &account)
+                })
+                .context("could not read entries")?;
+            let label = month
+                .start
+                .with_timezone(&Local)
+                .format("%Y-%m")
+                .to_string();
+            let today = timetracker::now()
+                .with_timezone(&Local)
+                .date()
+                .naive_local();
+            let invoice = log.invoice(&account, &label, today, &entries, |account| {
+                accounts.get(account).and_then(|info| info.rate)
+            });
+            let invoice = match invoice {
+                Some(invoice) => invoice,
+                None => {
+                    println!("Nothing to invoice for {} in {}", account, label);
+                    return Ok(());
+                }
+            };
+            let amount = invoice
+                .amount
+                .map_or(String::new(), |amount| format!(", {:.2}", amount));
+            let summary = format!(
+                "invoice {} for {} in {}: {} entries, {}{}",
+                invoice.number,
+                account,
+                label,
+                invoice.entries.len(),
+                format_hours_minutes(invoice.duration()),
+                amount
+            );
+            if dry_run {
+                println!("Would issue {}", summary);
+                return Ok(());
+            }
+            log.record(invoice)?;
+            println!("Issued {}", summary);
+        }
+        InvoiceCommand::List(ListArgs { account, porcelain }) => {
+            let account = account.as_ref().map(|account| config.expand_alias(account));
+            for invoice in log.invoices().iter().filter(|invoice| {
+                account
+                    .as_ref()
+                    .is_none_or(|account| report::is_within_account(&invoice.account, account))
+            }) {
+                if *porcelain {
+                    print_json(invoice);
+                    continue;
+                }
+                println!(
+                    "{}  {}  {}  {:>8}  {:>10}  {}",
+                    invoice.number,
+                    invoice.issued,
+                    invoice.month,
+                    format_hours_minutes(invoice.duration()),
+                    invoice
+                        .amount
+                        .map_or(String::new(), |amount| format!("{:.2}", amount)),
+                    invoice.account
+                );
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,37 @@
+use super::{CliError, Result};
+use std::fs;
+use std::io;
+use std::path::Path;
+use timetracker::config;
+
+/// Prints every problem with the config file, exiting with 1 if there are any.
+pub fn run(config_path: &Path) -> Result<()> {
+    let contents = match fs::read_to_string(config_path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            println!(
+                "{} does not exist, the defaults are used",
+                config_path.display()
+            );
+            return Ok(());
+        }
+        Err(err) => {
+            return Err(CliError::new(format!(
+                "could not read {}: {}",
+                config_path.display(),
+                err
+            )))
+        }
+    };
+    let problems = config::lint(&contents);
+    for problem in &problems {
+        match problem.line {
+            Some(line) => println!("{}:{}: {}", config_path.display(), line, problem.message),
+            None => println!("{}: {}", config_path.display(), problem.message),
+        }
+    }
+    if !problems.is_empty() {
+        return Err(CliError::exit(1));
+    }
+    Ok(())
+}
@@ -0,0 +1,350 @@
+use super::{
+    account_name, notes_dir, parse_datetime, parse_meta_arg, parse_month, parse_week,
+    period_bounds, CliError, Context, Result, ResultExt,
+};
+use crate::output::{self, Style};
+use chrono::{Date, DateTime, Duration, Local, Utc};
+use std::collections::BTreeSet;
+use std::io::{self, Write};
+use std::ops::Range;
+use structopt::StructOpt;
+use timetracker::config::Config;
+use timetracker::duration::DurationFormat;
+use timetracker::locale::Locale;
+use timetracker::notes::Notes;
+use timetracker::report;
+use timetracker::timesheet::Timesheet;
+use timetracker::Entry;
+#[cfg(feature = "follow")]
+use {
+    super::{exit_interrupted, handle_interrupts, interrupted},
+    std::collections::HashSet,
+    std::path::Path,
+    std::sync::mpsc::{self, RecvTimeoutError},
+    std::time::{Duration as StdDuration, Instant},
+    timetracker::storage::{Storage, StorageError},
+};
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    /// Only list entries starting at or after this time, e.g. `monday` or `2021-07-01`
+    #[structopt(long, parse(try_from_str = parse_datetime))]
+    pub since: Option<DateTime<Utc>>,
+
+    /// Only list entries starting before this time
+    #[structopt(long, parse(try_from_str = parse_datetime))]
+    pub until: Option<DateTime<Utc>>,
+
+    /// Only include the ISO week, e.g. `2024-W15`, `this` or `last`
+    #[structopt(long, parse(try_from_str = parse_week), conflicts_with_all = &["since", "until", "month"])]
+    pub week: Option<Range<DateTime<Utc>>>,
+
+    /// Only include the month, e.g. `2024-03`, `this` or `last`
+    #[structopt(long, parse(try_from_str = parse_month), conflicts_with_all = &["since", "until"])]
+    pub month: Option<Range<DateTime<Utc>>>,
+
+    /// How to write durations
+    #[structopt(long, default_value = "hours-minutes", possible_values = DurationFormat::NAMES)]
+    pub duration_format: DurationFormat,
+
+    /// Show accounts by their aliases from the config
+    #[structopt(long)]
+    pub aliases: bool,
+
+    /// Only list entries with the metadata, e.g. `ticket=PROJ-12`, may be given more than once
+    #[structopt(long, parse(try_from_str = parse_meta_arg), number_of_values = 1)]
+    pub meta: Vec<(String, String)>,
+
+    /// Show the ID of each entry, marking the ones with notes
+    #[structopt(long)]
+    pub notes: bool,
+
+    /// `ndjson` prints each entry as JSON on a line of its own as it is read, for tools like
+    /// `jq` to go through long histories with
+    #[structopt(long, default_value = "text", possible_values = &["text", "ndjson"])]
+    pub format: String,
+
+    /// Keep printing entries as they are added to the entries file, like by other machines
+    /// syncing it, until Ctrl-C is pressed
+    #[cfg(feature = "follow")]
+    #[structopt(long)]
+    pub follow: bool,
+}
+
+pub fn run(ctx: Context, args: &Args) -> Result<()> {
+    let Context {
+        config,
+        accounts: _,
+        style,
+        locale,
+        storage,
+        ..
+    } = ctx;
+    let Args {
+        since,
+        until,
+        week,
+        month,
+        duration_format,
+        aliases,
+        meta,
+        notes,
+        format,
+        ..
+    } = args;
+    let (since, until) = period_bounds(*since, *until, week, month);
+    let today = timetracker::now().with_timezone(&Local).date();
+    let since = since
+        .unwrap_or_else(|| report::start_of_day(today.naive_local() - Duration::days(6), &Local));
+    let within = |start: DateTime<Utc>| start >= since && until.is_none_or(|until| start < until);
+    let included = |entry: &Entry| {
+        within(entry.start)
+            && meta
+                .iter()
+                .all(|(key, value)| entry.meta_value(key) == Some(value))
+    };
+    if format == "ndjson" {
+        let stdout = io::stdout();
+        let mut stdout = io::BufWriter::new(stdout.lock());
+        let mut written = Ok(());
+        storage
+            .visit_entries_since(since, |entry| {
+                if written.is_err()
+                    || !within(entry.start)
+                    || !meta
+                        .iter()
+                        .all(|(key, value)| entry.meta_value(key) == Some(value))
+                {
+                    return;
+                }
+                written = serde_json::to_writer(&mut stdout, &entry.to_entry())
+                    .map_err(io::Error::from)
+                    .and_then(|_| writeln!(stdout));
+            })
+            .context("could not read entries")?;
+        match written.and_then(|_| stdout.flush()) {
+            // The reader, like `head`, may stop reading early
+            Err(err) if err.kind() != io::ErrorKind::BrokenPipe => {
+                return Err(CliError::new(format!(
+                    "could not write to standard output: {}",
+                    err
+                )))
+            }
+            Err(_) => return Ok(()),
+            Ok(()) => {}
+        }
+        #[cfg(feature = "follow")]
+        if args.follow {
+            follow(&storage, included, |entry| {
+                serde_json::to_writer(&mut stdout, entry)?;
+                writeln!(stdout)?;
+                stdout.flush()
+            })?;
+        }
+        return Ok(());
+    }
+    let timesheet: Timesheet = storage
+        .entries()
+        .context("could not read entries")?
+        .into_iter()
+        .filter(|entry| included(entry))
+        .collect();
+
+    let accounts: Vec<String> = timesheet
+        .entries()
+        .iter()
+        .map(|entry| account_name(&config, &entry.account, *aliases))
+        .collect();
+    let printer = Printer {
+        width: output::column_width(accounts.iter().map(String::as_str)),
+        with_notes: if *notes {
+            Notes::new(notes_dir(&storage))
+                .ids()
+                .context("could not read notes")?
+        } else {
+            BTreeSet::new()
+        },
+        config: &config,
+        style: &style,
+        locale: &locale,
+        duration_format: *duration_format,
+        aliases: *aliases,
+        notes: *notes,
+        today,
+    };
+    let stdout = io::stdout();
+    for entry in timesheet.entries() {
+        printer
+            .write(&mut stdout.lock(), entry)
+            .context("could not write to standard output")?;
+    }
+    #[cfg(feature = "follow")]
+    if args.follow {
+        let mut stdout = stdout.lock();
+        follow(&storage, included, |entry| {
+            printer
+                .write(&mut stdout, entry)
+                .and_then(|_| stdout.flush())
+        })?;
+    }
+    Ok(())
+}
+
+/// Waits for changes to the entries file and passes the entries added to it which `include` does
+/// not exclude to `print`, until interrupted or the reader of the output is gone.
+///
+/// Sync services and editors often write a file in several steps or replace it, so the file is
+/// only read again once the changes have settled, at most every `SETTLE_LIMIT`. Entries are told
+/// apart by their IDs, so reordering or rewriting the file does not print its entries again.
+#[cfg(feature = "follow")]
+fn follow(
+    storage: &Storage,
+    include: impl Fn(&Entry) -> bool,
+    mut print: impl FnMut(&Entry) -> io::Result<()>,
+) -> Result<()> {
+    use notify::{EventKind, RecursiveMode, Watcher};
+
+    /// How long the file must be left unchanged before it is read.
+    const SETTLE: StdDuration = StdDuration::from_millis(250);
+    /// How long changes may keep coming before the file is read anyway.
+    const SETTLE_LIMIT: StdDuration = StdDuration::from_secs(2);
+
+    let path = storage.entries_path();
+    let mut seen: HashSet<String> = storage
+        .entries()
+        .context("could not read entries")?
+        .iter()
+        .map(Entry::id)
+        .collect();
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher =
+        notify::recommended_watcher(sender).context("could not watch the entries file")?;
+    // The directory is watched, as the file itself may be replaced
+    let dir = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    watcher
+        .watch(dir, RecursiveMode::NonRecursive)
+        .with_context(|| format!("could not watch {}", dir.display()))?;
+    let is_change = |event: &notify::Result<notify::Event>| match event {
+        Ok(event) => {
+            !matches!(event.kind, EventKind::Access(_))
+                && event
+                    .paths
+                    .iter()
+                    .any(|changed| changed.file_name() == path.file_name())
+        }
+        Err(err) => {
+            log::warn!("could not watch the entries file: {}", err);
+            false
+        }
+    };
+
+    handle_interrupts();
+    while interrupted().is_none() {
+        match receiver.recv_timeout(StdDuration::from_millis(200)) {
+            Ok(event) if is_change(&event) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+            _ => continue,
+        }
+        let limit = Instant::now() + SETTLE_LIMIT;
+        while Instant::now() < limit && receiver.recv_timeout(SETTLE).is_ok() {}
+        let entries = match storage.entries() {
+            Ok(entries) => entries,
+            // The line is read once it is complete, as that changes the file again
+            Err(err @ StorageError::PartialLine(_)) => {
+                log::debug!("waiting for the rest of the entries file: {}", err);
+                continue;
+            }
+            Err(err) => {
+                log::warn!("could not read entries: {}", err);
+                continue;
+            }
+        };
+        for entry in &entries {
+            if !seen.insert(entry.id()) || !include(entry) {
+                continue;
+            }
+            match print(entry) {
+                Ok(()) => {}
+                Err(err) if err.kind() == io::ErrorKind::BrokenPipe => return Ok(()),
+                Err(err) => {
+                    return Err(CliError::new(format!(
+                        "could not write to standard output: {}",
+                        err
+                    )))
+                }
+            }
+        }
+    }
+    match interrupted() {
+        Some(_) => Err(exit_interrupted()),
+        None => Ok(()),
+    }
+}
+
+/// Writes entries as lines of the text format.
+struct Printer<'a> {
+    config: &'a Config,
+    style: &'a Style,
+    locale: &'a Locale,
+    duration_format: DurationFormat,
+    aliases: bool,
+    notes: bool,
+    /// The IDs of the entries with notes.
+    with_notes: BTreeSet<String>,
+    /// The width of the account column.
+    width: usize,
+    today: Date<Local>,
+}
+
+impl Printer<'_> {
+    fn write(&self, out: &mut impl Write, entry: &Entry) -> io::Result<()> {
+        let style = self.style;
+        let start = entry.start.with_timezone(&Local);
+        if self.notes {
+            let id = entry.id();
+            let marker = if self.with_notes.contains(&id) {
+                "+"
+            } else {
+                " "
+            };
+            write!(out, "{}{}  ", style.dim(&id), marker)?;
+        }
+        let times = format!(
+            "{}  {}-{}  {:>8}",
+            start.format("%Y-%m-%d"),
+            start.format("%H:%M"),
+            entry.stop.with_timezone(&Local).format("%H:%M"),
+            self.locale
+                .format_duration(self.duration_format, entry.stop - entry.start)
+        );
+        let times = if start.date() == self.today {
+            style.bold(&times)
+        } else {
+            times
+        };
+        let mut details: Vec<String> = entry.tags.iter().map(|tag| format!("#{}", tag)).collect();
+        details.extend(
+            entry
+                .meta
+                .iter()
+                .map(|(key, value)| format!("{}={}", key, value)),
+        );
+        details.extend(entry.description.clone());
+        let account = account_name(self.config, &entry.account, self.aliases);
+        // The account is only padded to align the details following it
+        if details.is_empty() {
+            writeln!(out, "{}  {}", times, style.account(&account, 0))
+        } else {
+            writeln!(
+                out,
+                "{}  {}  {}",
+                times,
+                style.account(&account, self.width),
+                style.dim(&details.join(" "))
+            )
+        }
+    }
+}
@@ -0,0 +1,30 @@
+use super::{auto_commit, Context, Result, ResultExt};
+use chrono::Duration;
+use structopt::StructOpt;
+use timetracker::duration::parse_duration;
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    /// Merge entries less than this far apart, e.g. `5m` or `1h`
+    #[structopt(long, default_value = "5m", parse(try_from_str = parse_duration))]
+    pub max_gap: Duration,
+}
+
+pub fn run(ctx: Context, args: &Args) -> Result<()> {
+    let Context {
+        config, storage, ..
+    } = ctx;
+    let Args { max_gap } = args;
+    let merged = storage.merge(*max_gap).context("could not merge entries")?;
+    for entry in &merged {
+        println!("{}", entry);
+    }
+    match merged.len() {
+        1 => println!("1 merged entry"),
+        count => println!("{} merged entries", count),
+    }
+    if !merged.is_empty() {
+        auto_commit(&storage, &config, "Merge")?;
+    }
+    Ok(())
+}
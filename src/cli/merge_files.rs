@@ -0,0 +1,89 @@
+use super::{CliError, Context, Result, ResultExt};
+use std::fs;
+use std::path::PathBuf;
+use structopt::StructOpt;
+use timetracker::diff::{self, Difference};
+use timetracker::migrations;
+use timetracker::storage::Storage;
+use timetracker::Entry;
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    /// The entries files to combine
+    #[structopt(parse(from_os_str), min_values = 2, required = true)]
+    pub files: Vec<PathBuf>,
+
+    /// The file to write the combined entries to
+    #[structopt(short, long, parse(from_os_str))]
+    pub output: PathBuf,
+
+    /// Write the file despite conflicts, with the entry from the first file given
+    #[structopt(long)]
+    pub keep_first: bool,
+
+    /// Overwrite the output file if it already exists
+    #[structopt(long)]
+    pub force: bool,
+}
+
+pub fn run(ctx: Context, args: &Args) -> Result<()> {
+    let Context {
+        dry_run,
+        style,
+        storage,
+        ..
+    } = ctx;
+    let Args {
+        files,
+        output,
+        keep_first,
+        force,
+    } = args;
+    if output.exists() && !force {
+        return Err(CliError::new(
+            "there is already a file at the output path, use --force to overwrite it",
+        ));
+    }
+    let sets: Vec<Vec<Entry>> = files
+        .iter()
+        .map(|file| {
+            Storage::new(file.clone(), storage.running_path().to_path_buf())
+                .entries()
+                .with_context(|| format!("could not read {}", file.display()))
+        })
+        .collect::<Result<_>>()?;
+    let merged = diff::merge(&sets);
+
+    for conflict in &merged.conflicts {
+        if let Difference::Changed { first, second } = conflict {
+            println!(
+                "~ {} {}",
+                first.id(),
+                style.dim(&format!("({})", conflict.changed_fields().join(", ")))
+            );
+            println!("  < {}\n  > {}", first, second);
+        }
+    }
+    println!(
+        "{} entries, {} duplicates left out, {} conflicts",
+        merged.entries.len(),
+        merged.duplicates,
+        merged.conflicts.len()
+    );
+    if !merged.conflicts.is_empty() && !keep_first {
+        eprintln!(
+            "Not writing {}, use --keep-first to keep the entries from the first file",
+            output.display()
+        );
+        return Err(CliError::exit(1));
+    }
+    if dry_run {
+        println!("Would write {}", output.display());
+        return Ok(());
+    }
+    let mut lines = vec![migrations::header(migrations::CURRENT_VERSION)];
+    lines.extend(merged.entries.iter().map(Entry::to_string));
+    fs::write(output, format!("{}\n", lines.join("\n")))
+        .with_context(|| format!("could not write {}", output.display()))?;
+    Ok(())
+}
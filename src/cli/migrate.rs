@@ -0,0 +1,52 @@
+use super::{auto_commit, CliError, Context, Result, ResultExt};
+use structopt::StructOpt;
+use timetracker::migrations;
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    /// Only check whether the file is at the current version, failing if it is not
+    #[structopt(long)]
+    pub check: bool,
+}
+
+pub fn run(ctx: Context, args: &Args) -> Result<()> {
+    let Context {
+        config, storage, ..
+    } = ctx;
+    let Args { check } = args;
+    let version = storage.format_version().context("could not read entries")?;
+    if *check {
+        if version != migrations::CURRENT_VERSION {
+            eprintln!(
+                "The entries file is at version {} of the format, not {}",
+                version,
+                migrations::CURRENT_VERSION
+            );
+            return Err(CliError::exit(1));
+        }
+        return Ok(());
+    }
+
+    let pending = migrations::pending(version);
+    match storage
+        .migrate()
+        .context("could not migrate the entries file")?
+    {
+        Some(from) => {
+            for description in pending {
+                println!("Migration: {}", description);
+            }
+            println!(
+                "Migrated the entries file from version {} to {}",
+                from,
+                migrations::CURRENT_VERSION
+            );
+            auto_commit(&storage, &config, "Migrate")?;
+        }
+        None => println!(
+            "The entries file is already at version {}",
+            migrations::CURRENT_VERSION
+        ),
+    }
+    Ok(())
+}
@@ -0,0 +1,21 @@
+use super::{Context, Result, ResultExt};
+use timetracker::paths;
+
+pub fn run(ctx: Context) -> Result<()> {
+    let Context {
+        dry_run, profile, ..
+    } = ctx;
+    let migrations = paths::pending_migrations(profile.as_deref());
+    if migrations.is_empty() {
+        println!("Nothing to migrate");
+    }
+    for (from, to) in migrations {
+        if dry_run {
+            println!("Would move {} to {}", from.display(), to.display());
+            continue;
+        }
+        paths::migrate(&from, &to).with_context(|| format!("could not move {}", from.display()))?;
+        println!("Moved {} to {}", from.display(), to.display());
+    }
+    Ok(())
+}
@@ -0,0 +1,916 @@
+//! The commands, each in a module of its own with its arguments and a `run` function taking
+//! the `Context` set up from the global options, the config and the data files. `run` returns the
+//! failure of the command as a `CliError`, which `main` reports and exits with.
+
+use crate::output::Style;
+#[cfg(feature = "http")]
+use crate::slack;
+use chrono::{DateTime, Duration, Local, Utc};
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::thread;
+use timetracker::accounts::Accounts;
+use timetracker::audit::AuditLog;
+use timetracker::burn::Burn;
+use timetracker::config::Config;
+use timetracker::fuzzy;
+use timetracker::goals::Progress;
+use timetracker::locale::Locale;
+use timetracker::permissions;
+use timetracker::reminders;
+use timetracker::storage::{self, Storage, StorageError, WriteMode};
+use timetracker::sync::Repository;
+use timetracker::timeparse;
+use timetracker::timesheet::Timesheet;
+use timetracker::{is_valid_tag, Entry, EntryRef, RunningEntry};
+
+pub mod accounts;
+pub mod add;
+pub mod annotate;
+pub mod apply;
+#[cfg(feature = "activity")]
+pub mod audit;
+pub mod backup;
+pub mod balance;
+pub mod burn;
+pub mod check;
+pub mod clean;
+pub mod compact;
+pub mod complete;
+#[cfg(feature = "compact")]
+pub mod convert;
+#[cfg(unix)]
+pub mod daemon;
+pub mod diff;
+pub mod digest;
+pub mod doctor;
+pub mod explain;
+pub mod export;
+pub mod fav;
+pub mod free;
+pub mod goals;
+pub mod grep;
+pub mod heatmap;
+pub mod history;
+pub mod import;
+pub mod init;
+pub mod invoice;
+pub mod lint_config;
+pub mod log;
+pub mod merge;
+pub mod merge_files;
+pub mod migrate;
+pub mod migrate_paths;
+pub mod normalize;
+pub mod note;
+pub mod notify;
+pub mod pomo;
+pub mod prompt;
+#[cfg(feature = "http")]
+pub mod push;
+pub mod reassign;
+pub mod rename_account;
+pub mod report;
+pub mod restore;
+pub mod running;
+pub mod schedule;
+pub mod schema;
+#[cfg(feature = "server")]
+pub mod serve;
+pub mod show;
+pub mod sort;
+pub mod split;
+#[cfg(feature = "sqlite")]
+pub mod sql;
+pub mod start;
+pub mod stats;
+pub mod status;
+pub mod stop;
+pub mod sync;
+pub mod tag;
+pub mod today;
+pub mod track;
+pub mod trim;
+
+/// What the commands work with, set up from the global options, the config and the data files.
+pub struct Context {
+    /// Print what would be written instead of changing any files.
+    pub dry_run: bool,
+    pub profile: Option<String>,
+    pub config_path: PathBuf,
+    pub favorites_path: PathBuf,
+    pub accounts_path: PathBuf,
+    /// The running file as given, which identifies the files to the daemon.
+    pub daemon_running_path: PathBuf,
+    pub config: Config,
+    pub accounts: Accounts,
+    pub style: Style,
+    pub locale: Locale,
+    pub write_mode: WriteMode,
+    pub storage: Storage,
+    pub audit_log: AuditLog,
+    pub backup_dir: PathBuf,
+}
+
+/// Why a command failed, with the exit code to exit with, see the `EXIT_` codes.
+#[derive(Debug)]
+pub struct CliError {
+    code: i32,
+    /// `None` if the command has already told why, like a check listing the problems it found.
+    message: Option<String>,
+}
+
+pub type Result<T, E = CliError> = std::result::Result<T, E>;
+
+impl CliError {
+    /// A failure exiting with 1.
+    pub fn new(message: impl Into<String>) -> Self {
+        CliError::with_code(1, message)
+    }
+
+    pub fn with_code(code: i32, message: impl Into<String>) -> Self {
+        CliError {
+            code,
+            message: Some(message.into()),
+        }
+    }
+
+    /// Exits with the code without a message.
+    pub fn exit(code: i32) -> Self {
+        CliError {
+            code,
+            message: None,
+        }
+    }
+
+    pub fn code(&self) -> i32 {
+        self.code
+    }
+
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.message {
+            Some(message) => f.write_str(message),
+            None => write!(f, "exit code {}", self.code),
+        }
+    }
+}
+
+impl Error for CliError {}
+
+/// The failures to change an entry exit with the codes scripts can tell apart.
+impl From<StorageError> for CliError {
+    fn from(err: StorageError) -> Self {
+        let code = match err {
+            StorageError::AlreadyRunning(_) => EXIT_ALREADY_RUNNING,
+            StorageError::NotRunning(_)
+            | StorageError::NoRunningEntries
+            | StorageError::NoEntries(_) => EXIT_NOTHING_RUNNING,
+            StorageError::AccountRequired => EXIT_ACCOUNT_REQUIRED,
+            StorageError::Invalid(_) | StorageError::ZeroDuration | StorageError::Overlap(_) => {
+                EXIT_INVALID_ENTRY
+            }
+            _ => 1,
+        };
+        CliError::with_code(code, err.to_string())
+    }
+}
+
+/// Describes other errors by what failed, e.g. `could not read entries: <the error>`.
+pub trait ResultExt<T> {
+    fn context(self, context: impl fmt::Display) -> Result<T>;
+
+    fn with_context<C: fmt::Display>(self, context: impl FnOnce() -> C) -> Result<T>;
+}
+
+impl<T, E: fmt::Display> ResultExt<T> for Result<T, E> {
+    fn context(self, context: impl fmt::Display) -> Result<T> {
+        self.map_err(|err| CliError::new(format!("{}: {}", context, err)))
+    }
+
+    fn with_context<C: fmt::Display>(self, context: impl FnOnce() -> C) -> Result<T> {
+        self.map_err(|err| CliError::new(format!("{}: {}", context(), err)))
+    }
+}
+
+/// The files backed up and restored, by their names in a snapshot.
+pub fn backup_files<'a>(
+    storage: &'a Storage,
+    config_path: &'a Path,
+    accounts_path: &'a Path,
+    favorites_path: &'a Path,
+) -> [(&'static str, &'a Path); 5] {
+    [
+        ("entries", storage.entries_path()),
+        ("running", storage.running_path()),
+        ("config.toml", config_path),
+        ("accounts.toml", accounts_path),
+        ("favorites", favorites_path),
+    ]
+}
+
+/// Parses a time given on the command line, see `timeparse::parse` for the accepted formats.
+pub fn parse_datetime(s: &str) -> Result<DateTime<Utc>, String> {
+    timeparse::parse(s, &timetracker::now().with_timezone(&Local))
+}
+
+pub fn parse_week(s: &str) -> Result<Range<DateTime<Utc>>, String> {
+    timeparse::parse_week(s, &timetracker::now().with_timezone(&Local))
+}
+
+pub fn parse_month(s: &str) -> Result<Range<DateTime<Utc>>, String> {
+    timeparse::parse_month(s, &timetracker::now().with_timezone(&Local))
+}
+
+/// The bounds of the `--week` or `--month` period if either is given, otherwise `--since` and
+/// `--until`.
+pub fn period_bounds(
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+    week: &Option<Range<DateTime<Utc>>>,
+    month: &Option<Range<DateTime<Utc>>>,
+) -> (Option<DateTime<Utc>>, Option<DateTime<Utc>>) {
+    match week.as_ref().or(month.as_ref()) {
+        Some(period) => (Some(period.start), Some(period.end)),
+        None => (since, until),
+    }
+}
+
+/// Keeps the time tracked within the range, which is open ended where no bound is given.
+pub fn within(
+    timesheet: &Timesheet,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+) -> Timesheet {
+    let entries = timesheet.entries();
+    let (first, last) = match (
+        entries.first(),
+        entries.iter().map(|entry| entry.stop).max(),
+    ) {
+        (Some(first), Some(last)) => (first.start, last),
+        _ => return Timesheet::default(),
+    };
+    timesheet.between(since.unwrap_or(first)..until.unwrap_or(last))
+}
+
+/// The accounts of the entries and running entries, the most recently used first, followed by
+/// the ones only described in accounts.toml, leaving out archived accounts.
+pub fn known_accounts(storage: &Storage, accounts: &Accounts) -> Result<Vec<String>, StorageError> {
+    let mut used: Vec<(DateTime<Utc>, String)> = storage
+        .entries()?
+        .into_iter()
+        .map(|entry| (entry.start, entry.account.to_string()))
+        .collect();
+    used.extend(
+        storage
+            .running_entries()?
+            .into_iter()
+            .map(|entry| (entry.start, entry.account.to_string())),
+    );
+    used.sort_by_key(|(start, _)| std::cmp::Reverse(*start));
+    let mut known: Vec<String> = Vec::new();
+    let used = used.into_iter().map(|(_, account)| account);
+    for account in used.chain(accounts.iter().map(|(account, _)| account.clone())) {
+        if !known.contains(&account) && !accounts.is_archived(&account) {
+            known.push(account);
+        }
+    }
+    Ok(known)
+}
+
+/// Reads the completed entries together with the running entries stopped at the given time.
+pub fn entries_until(storage: &Storage, now: DateTime<Utc>) -> Result<Vec<Entry>> {
+    let mut entries = storage.entries().context("could not read entries")?;
+    entries.extend(
+        storage
+            .running_entries()
+            .context("could not read running entries")?
+            .into_iter()
+            .map(|entry| entry.into_entry(now)),
+    );
+    Ok(entries)
+}
+
+/// Reads the entries like `entries_until`, skipping the completed entries outside the period
+/// without copying them, and with the index without reading the entries before it.
+pub fn entries_overlapping(
+    storage: &Storage,
+    now: DateTime<Utc>,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+) -> Result<Vec<Entry>> {
+    // Entries touching the period are kept, `within` decides about those
+    let before_until = |entry: &EntryRef| until.is_none_or(|until| entry.start <= until);
+    let mut entries = match since {
+        Some(since) => storage.entries_since(since, before_until),
+        None => storage.entries_where(before_until),
+    }
+    .context("could not read entries")?;
+    entries.extend(
+        storage
+            .running_entries()
+            .context("could not read running entries")?
+            .into_iter()
+            .map(|entry| entry.into_entry(now)),
+    );
+    Ok(entries)
+}
+
+/// Computes the progress of every configured goal at the current time.
+pub fn goal_progress(storage: &Storage, config: &Config) -> Result<Vec<Progress>> {
+    let goals = config.goals();
+    if goals.is_empty() {
+        return Ok(Vec::new());
+    }
+    let now = timetracker::now();
+    let entries = entries_until(storage, now)?;
+    let now = now.with_timezone(&Local);
+    Ok(goals
+        .iter()
+        .map(|goal| timetracker::goals::progress(goal, &entries, &now))
+        .collect())
+}
+
+/// The marker from the config for the output of status bars, if there is something to alert
+/// about, see `timetracker::prompt::alerts`. Status bars show the output all the time, so data
+/// files which cannot be read only leave the alerts out.
+pub fn alert_marker<'a>(
+    storage: &Storage,
+    config: &'a Config,
+    accounts: &Accounts,
+) -> Option<&'a str> {
+    let marker = config.prompt.alert_marker.as_deref()?;
+    let budgets = accounts.hour_budgets();
+    if config.thresholds.daily_limit.is_none() && budgets.is_empty() {
+        return None;
+    }
+    let now = timetracker::now();
+    let read = storage.entries().and_then(|mut entries| {
+        let running = storage.running_entries()?;
+        entries.extend(running.into_iter().map(|entry| entry.into_entry(now)));
+        Ok(entries)
+    });
+    let entries = match read {
+        Ok(entries) => entries,
+        Err(err) => {
+            ::log::debug!("could not read the entries for alerts: {}", err);
+            return None;
+        }
+    };
+    let alerts = timetracker::prompt::alerts(
+        &entries,
+        config.thresholds.daily_limit,
+        &budgets,
+        config
+            .prompt
+            .budget_alert
+            .unwrap_or(timetracker::prompt::DEFAULT_BUDGET_ALERT),
+        &now.with_timezone(&Local),
+    );
+    for alert in &alerts {
+        ::log::info!("{}", alert);
+    }
+    Some(marker).filter(|_| !alerts.is_empty())
+}
+
+/// Projects when the budget of every account with a budget and deadline runs out, at the rate of
+/// the last `days` days.
+pub fn budget_burn(storage: &Storage, accounts: &Accounts, days: u32) -> Result<Vec<Burn>> {
+    let budgets = accounts.budgets();
+    if budgets.is_empty() {
+        return Ok(Vec::new());
+    }
+    let now = timetracker::now();
+    let entries = entries_until(storage, now)?;
+    let today = now.with_timezone(&Local).date().naive_local();
+    Ok(budgets
+        .into_iter()
+        .map(|(account, budget, deadline)| {
+            timetracker::burn::project(account, budget, deadline, &entries, today, days, &Local)
+        })
+        .collect())
+}
+
+/// Shows a desktop notification, falling back to printing the message.
+#[cfg(feature = "notifications")]
+pub fn notify(message: &str) {
+    if let Err(err) = notify_rust::Notification::new()
+        .summary("Time Tracker")
+        .body(message)
+        .show()
+    {
+        ::log::warn!("could not show notification: {}", err);
+        println!("{}", message);
+    }
+}
+
+/// Prints the message, as desktop notifications are not enabled.
+#[cfg(not(feature = "notifications"))]
+pub fn notify(message: &str) {
+    println!("{}", message);
+}
+
+/// Updates the Slack status to the running entries when enabled with `update_status` under
+/// `[slack]`. Failing to is only logged, like failing hooks.
+#[cfg(feature = "http")]
+pub fn update_slack_status(storage: &Storage, config: &Config) {
+    if !config.slack.update_status {
+        return;
+    }
+    let result = storage
+        .running_entries()
+        .map_err(|err| err.into())
+        .and_then(|running_entries| slack::update_status(&config.slack, &running_entries, false));
+    if let Err(err) = result {
+        ::log::error!("could not set the Slack status: {}", err);
+    }
+}
+
+/// Does nothing, as the Slack status is set over HTTP.
+#[cfg(not(feature = "http"))]
+pub fn update_slack_status(_storage: &Storage, _config: &Config) {}
+
+/// The signals which end a command that handles them once it has cleaned up: Ctrl-C, `kill` and
+/// the terminal being closed.
+#[cfg(unix)]
+const INTERRUPTING_SIGNALS: [libc::c_int; 3] = [libc::SIGINT, libc::SIGTERM, libc::SIGHUP];
+
+/// The signals which end a command that handles them once it has cleaned up: Ctrl-C and being
+/// terminated, as Windows has no SIGHUP.
+#[cfg(not(unix))]
+const INTERRUPTING_SIGNALS: [libc::c_int; 2] = [libc::SIGINT, libc::SIGTERM];
+
+/// The last of `INTERRUPTING_SIGNALS` received during a command that handles them, or 0.
+static INTERRUPTED: AtomicI32 = AtomicI32::new(0);
+
+/// Records the `INTERRUPTING_SIGNALS`, e.g. Ctrl-C, instead of exiting right away, so the command
+/// can stop or save what it is running and leave the files whole, see `interrupted`.
+pub fn handle_interrupts() {
+    for signal in INTERRUPTING_SIGNALS {
+        // Safety: the handler only stores to an atomic, which is async-signal-safe
+        let registered = unsafe {
+            signal_hook_registry::register(signal, move || {
+                INTERRUPTED.store(signal, Ordering::SeqCst)
+            })
+        };
+        registered.expect("could not handle signals");
+    }
+}
+
+/// The signal received since `handle_interrupts`, if any.
+pub fn interrupted() -> Option<libc::c_int> {
+    Some(INTERRUPTED.load(Ordering::SeqCst)).filter(|&signal| signal != 0)
+}
+
+/// The error to exit with after an interruption, with the exit code of being killed by the
+/// signal like shells report, e.g. 130 for Ctrl-C.
+pub fn exit_interrupted() -> CliError {
+    CliError::exit(128 + interrupted().unwrap_or(libc::SIGINT))
+}
+
+/// Sleeps for the duration, returning early with `false` if the command is interrupted.
+pub fn sleep_unless_interrupted(duration: Duration) -> bool {
+    let until = std::time::Instant::now() + duration.to_std().unwrap_or_default();
+    while interrupted().is_none() {
+        let now = std::time::Instant::now();
+        if now >= until {
+            return true;
+        }
+        thread::sleep((until - now).min(std::time::Duration::from_millis(200)));
+    }
+    false
+}
+
+/// Asks for a description of an entry on the terminal, returning `None` if the answer is empty.
+pub fn prompt_description(account: &str) -> Result<Option<String>> {
+    eprint!("Description for {}: ", account);
+    let answer = read_answer()?;
+    Ok(Some(answer.trim().to_string()).filter(|answer| !answer.is_empty()))
+}
+
+/// Reads a line answering the question just written to standard error.
+fn read_answer() -> Result<String> {
+    io::stderr()
+        .flush()
+        .context("could not write to standard error")?;
+    let mut answer = String::new();
+    io::stdin()
+        .read_line(&mut answer)
+        .context("could not read standard input")?;
+    Ok(answer)
+}
+
+/// Lets the user edit the text in $VISUAL or $EDITOR, falling back to `vi`, and returns the
+/// edited text.
+pub fn edit_text(name: &str, text: &str) -> Result<String> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    let path = env::temp_dir().join(format!("tt-{}-{}.txt", name, process::id()));
+    fs::write(&path, text).with_context(|| format!("could not write {}", path.display()))?;
+    // Through the shell, since editors are often set with arguments, like `code --wait`
+    #[cfg(not(windows))]
+    let status = process::Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$1\"", editor))
+        .arg("sh")
+        .arg(&path)
+        .status();
+    #[cfg(windows)]
+    let status = process::Command::new("cmd")
+        .arg("/C")
+        .arg(&editor)
+        .arg(&path)
+        .status();
+    match status {
+        Ok(status) if status.success() => {}
+        Ok(status) => return Err(CliError::new(format!("{} exited with {}", editor, status))),
+        Err(err) => return Err(CliError::new(format!("could not run {}: {}", editor, err))),
+    }
+    let edited =
+        fs::read_to_string(&path).with_context(|| format!("could not read {}", path.display()))?;
+    let _ = fs::remove_file(&path);
+    Ok(edited)
+}
+
+/// Asks a question on the terminal, returning the trimmed answer, or the default shown in brackets
+/// if the answer is empty.
+pub fn ask(question: &str, default: &str) -> Result<String> {
+    if default.is_empty() {
+        eprint!("{} ", question);
+    } else {
+        eprint!("{} [{}] ", question, default);
+    }
+    let answer = read_answer()?;
+    Ok(match answer.trim() {
+        "" => default.to_string(),
+        answer => answer.to_string(),
+    })
+}
+
+/// Asks on the terminal to pick one of the numbered choices, returning its index, or `None` if
+/// the answer is empty or not one of the numbers.
+pub fn pick(question: &str, choices: &[String]) -> Result<Option<usize>> {
+    for (number, choice) in choices.iter().enumerate() {
+        eprintln!("{:>3}) {}", number + 1, choice);
+    }
+    eprint!("{} [1-{}] ", question, choices.len());
+    let answer = read_answer()?;
+    Ok(answer
+        .trim()
+        .parse::<usize>()
+        .ok()
+        .and_then(|number| number.checked_sub(1))
+        .filter(|index| *index < choices.len()))
+}
+
+/// The most matches of a fuzzy search listed at a time.
+pub const PICK_LIMIT: usize = 10;
+
+/// Asks on the terminal for a search of the accounts, listing the best matches of each answer to
+/// pick one of by its number, or narrow down by searching again. Returns `None` if an answer is
+/// empty.
+pub fn pick_account(question: &str, accounts: &[String]) -> Result<Option<String>> {
+    if accounts.is_empty() {
+        eprintln!("There are no accounts to pick from");
+        return Ok(None);
+    }
+    let mut query = String::new();
+    loop {
+        let matches = fuzzy::matches(&query, accounts);
+        if matches.len() == 1 && !query.is_empty() {
+            return Ok(Some(matches[0].to_string()));
+        }
+        if matches.is_empty() {
+            eprintln!(r#"No account matches "{}""#, query);
+        }
+        for (number, account) in matches.iter().take(PICK_LIMIT).enumerate() {
+            eprintln!("{:>3}) {}", number + 1, account);
+        }
+        if matches.len() > PICK_LIMIT {
+            eprintln!("     and {} more", matches.len() - PICK_LIMIT);
+        }
+        eprint!("{} [number or search] ", question);
+        let answer = read_answer()?;
+        let answer = answer.trim();
+        if answer.is_empty() {
+            return Ok(None);
+        }
+        let picked = answer
+            .parse::<usize>()
+            .ok()
+            .and_then(|number| number.checked_sub(1))
+            .filter(|index| *index < matches.len().min(PICK_LIMIT));
+        if let Some(index) = picked {
+            return Ok(Some(matches[index].to_string()));
+        }
+        query = answer.to_string();
+    }
+}
+
+/// Finds the git repository of the data directory, which is the directory of the entries file.
+pub fn data_repository(storage: &Storage) -> Option<Repository> {
+    let dir = match storage.entries_path().parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    Repository::discover(dir)
+}
+
+/// The git repository of the current directory, for `--from-git`.
+pub fn current_repository() -> Result<Repository> {
+    let dir = std::env::current_dir().context("could not get the current directory")?;
+    Repository::discover(&dir)
+        .ok_or_else(|| CliError::new("the current directory is not in a git repository"))
+}
+
+/// The account as shown, shortened with its alias if `aliases` is set.
+pub fn account_name(config: &Config, account: &str, aliases: bool) -> String {
+    if aliases {
+        config.shorten_with_alias(account)
+    } else {
+        account.to_string()
+    }
+}
+
+/// The running entries from the daemon for the running file, if one is running.
+#[cfg(unix)]
+pub fn from_daemon(running_path: &Path) -> Option<Vec<RunningEntry>> {
+    crate::daemon::running_entries(&crate::daemon::socket_path(running_path), running_path)
+}
+
+#[cfg(not(unix))]
+pub fn from_daemon(_running_path: &Path) -> Option<Vec<RunningEntry>> {
+    None
+}
+
+/// Exit codes for the failures of `add`, `start`, `stop`, `annotate` and `trim` that scripts can
+/// tell apart, listed in the README. Their other failures exit with 1.
+pub const EXIT_ALREADY_RUNNING: i32 = 3;
+
+pub const EXIT_NOTHING_RUNNING: i32 = 4;
+
+pub const EXIT_ACCOUNT_REQUIRED: i32 = 5;
+
+pub const EXIT_INVALID_ENTRY: i32 = 6;
+
+pub const EXIT_TOO_LONG: i32 = 7;
+
+pub const EXIT_OVER_LIMIT: i32 = 8;
+
+/// Fails unless the time given with `--at` has passed, as a time in the future is usually a typo
+/// or a clock set wrong.
+pub fn refuse_future(command: &str, at: DateTime<Utc>) -> Result<()> {
+    let now = timetracker::now();
+    if at > now {
+        return Err(CliError::with_code(
+            EXIT_INVALID_ENTRY,
+            format!(
+                "{} is in the future, it is {} now; use --force to {} at that time anyway",
+                at.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S"),
+                now.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S"),
+                command
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Fails if the entry stops before it started, which when stopping now means the clock has been set
+/// back since the start. Nothing is written before the entries stopped are finished.
+pub fn refuse_before_start(entry: &Entry) -> Result<()> {
+    if entry.stop < entry.start {
+        return Err(CliError::with_code(
+            EXIT_INVALID_ENTRY,
+            format!(
+                "cannot stop {} at {}, before it started at {}; if the clock was wrong, check it \
+                 and stop with --at",
+                entry.account,
+                entry.stop.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S"),
+                entry
+                    .start
+                    .with_timezone(&Local)
+                    .format("%Y-%m-%d %H:%M:%S")
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// How close the starts of running entries for the same account are to be taken as duplicates.
+pub fn duplicate_window(config: &Config) -> Duration {
+    config
+        .start
+        .duplicate_window
+        .unwrap_or_else(|| Duration::seconds(storage::DEFAULT_DUPLICATE_WINDOW_SECONDS))
+}
+
+/// Fails unless the category can be written in the line format, which restricts it like a tag.
+pub fn check_category(category: Option<&str>) -> Result<()> {
+    match category.filter(|category| !is_valid_tag(category)) {
+        Some(category) => Err(CliError::new(format!(r#"invalid category "{}""#, category))),
+        None => Ok(()),
+    }
+}
+
+/// The config and data files which others than their owner may read or write, with their
+/// permissions, see `permissions::too_open`.
+pub fn open_files<'a>(config_path: &'a Path, storage: &'a Storage) -> Result<Vec<(&'a Path, u32)>> {
+    let mut paths = vec![config_path, storage.entries_path()];
+    if storage.running_path() != storage.entries_path() {
+        paths.push(storage.running_path());
+    }
+    let mut open = Vec::new();
+    for path in paths {
+        let mode = permissions::too_open(path)
+            .with_context(|| format!("could not read the permissions of {}", path.display()))?;
+        open.extend(mode.map(|mode| (path, mode)));
+    }
+    Ok(open)
+}
+
+/// Parses a `key=value` word of metadata for `--meta`, see `timetracker::parse_meta`.
+pub fn parse_meta_arg(s: &str) -> Result<(String, String), String> {
+    timetracker::parse_meta(s)
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .ok_or_else(|| {
+            format!(
+                r#"invalid metadata "{}", expected key=value with a key of letters, digits, `_`, `-` and `.`"#,
+                s
+            )
+        })
+}
+
+/// Prints an entry as JSON on a single line, for `--porcelain`.
+pub fn print_json(entry: &impl serde::Serialize) {
+    println!(
+        "{}",
+        serde_json::to_string(entry).expect("entries can be serialized")
+    );
+}
+
+/// Commits the data files if automatic commits are enabled and the data directory is a git
+/// repository.
+pub fn auto_commit(storage: &Storage, config: &Config, message: &str) -> Result<()> {
+    if !config.sync.auto_commit || storage.is_dry_run() {
+        return Ok(());
+    }
+    if let Some(repository) = data_repository(storage) {
+        repository
+            .commit(&[storage.entries_path(), storage.running_path()], message)
+            .context("could not commit changes")?;
+    }
+    Ok(())
+}
+
+/// Stops the entries still running at `auto_stop_at` under `[stop]` at that time, tagged with
+/// `auto-stopped`, see `reminders::auto_stop_time`. Failing is only logged, so it never gets in
+/// the way of the command being run.
+pub fn auto_stop(storage: &Storage, config: &Config) {
+    let at = match config.stop.auto_stop_at {
+        Some(at) => at,
+        None => return,
+    };
+    let due = |entry: &RunningEntry| Some(reminders::auto_stop_time(entry.start, at, &Local));
+    let finish = |mut entry: Entry| {
+        if !entry
+            .tags
+            .iter()
+            .any(|tag| tag == reminders::AUTO_STOPPED_TAG)
+        {
+            entry.tags.push(reminders::AUTO_STOPPED_TAG.to_string());
+        }
+        if config.stop.split_days {
+            timetracker::report::split_at_midnight(&entry, &Local)
+        } else {
+            vec![entry]
+        }
+    };
+    let recorded = match storage.stop_due(timetracker::now(), due, finish) {
+        Ok(recorded) => recorded,
+        Err(err) => {
+            ::log::warn!("could not stop the entries running at {}: {}", at, err);
+            return;
+        }
+    };
+    if recorded.is_empty() {
+        return;
+    }
+
+    let mut accounts = Vec::new();
+    for entries in recorded {
+        // Split entries are reported as the single entry that was stopped
+        let mut stopped = entries[0].clone();
+        stopped.stop = entries[entries.len() - 1].stop;
+        eprintln!(
+            "Auto-stopped {} at {}",
+            stopped.account,
+            stopped.stop.with_timezone(&Local).format("%Y-%m-%d %H:%M")
+        );
+        if !storage.is_dry_run() {
+            if let Err(err) = config.hooks.stopped(&stopped) {
+                ::log::error!("{}", err);
+            }
+        }
+        accounts.push(stopped.account);
+    }
+    if let Err(err) = auto_commit(
+        storage,
+        config,
+        &format!("Auto-stop {}", accounts.join(", ")),
+    ) {
+        ::log::warn!("{}", err);
+    }
+}
+
+/// The directory with the notes of the entries, see `Notes`.
+pub fn notes_dir(storage: &Storage) -> PathBuf {
+    storage.entries_path().with_file_name("notes")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use timetracker::ValidationError;
+
+    #[test]
+    fn exit_with_the_code_of_the_storage_error() {
+        let code = |err| CliError::from(err).code();
+        assert_eq!(
+            code(StorageError::AlreadyRunning("dev".to_string())),
+            EXIT_ALREADY_RUNNING
+        );
+        assert_eq!(
+            code(StorageError::NotRunning("dev".to_string())),
+            EXIT_NOTHING_RUNNING
+        );
+        assert_eq!(code(StorageError::NoRunningEntries), EXIT_NOTHING_RUNNING);
+        assert_eq!(code(StorageError::NoEntries(None)), EXIT_NOTHING_RUNNING);
+        assert_eq!(code(StorageError::AccountRequired), EXIT_ACCOUNT_REQUIRED);
+        assert_eq!(
+            code(StorageError::Invalid(ValidationError::MissingAccount)),
+            EXIT_INVALID_ENTRY
+        );
+        assert_eq!(code(StorageError::ZeroDuration), EXIT_INVALID_ENTRY);
+        let entry: Entry = "2021-07-01T09:00:00Z 2021-07-01T10:00:00Z dev"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            code(StorageError::Overlap(Box::new(entry))),
+            EXIT_INVALID_ENTRY
+        );
+        assert_eq!(code(StorageError::ReadOnly), 1);
+
+        let err = CliError::from(StorageError::AccountRequired);
+        assert_eq!(
+            err.message(),
+            Some(StorageError::AccountRequired.to_string().as_str())
+        );
+    }
+
+    #[test]
+    fn describe_errors_by_what_failed() {
+        let err = Err::<(), _>("denied")
+            .context("could not read entries")
+            .unwrap_err();
+        assert_eq!(err.code(), 1);
+        assert_eq!(err.to_string(), "could not read entries: denied");
+
+        let err = CliError::exit(2);
+        assert_eq!(err.message(), None);
+        assert_eq!(err.to_string(), "exit code 2");
+    }
+
+    #[test]
+    fn refuse_entries_in_the_future_or_stopping_before_they_start() {
+        let far = Utc.ymd(2100, 1, 1).and_hms(0, 0, 0);
+        assert_eq!(
+            refuse_future("start", far).unwrap_err().code(),
+            EXIT_INVALID_ENTRY
+        );
+        assert!(refuse_future("start", Utc.ymd(2000, 1, 1).and_hms(0, 0, 0)).is_ok());
+
+        let mut entry: Entry = "2021-07-01T09:00:00Z 2021-07-01T10:00:00Z dev"
+            .parse()
+            .unwrap();
+        assert!(refuse_before_start(&entry).is_ok());
+        entry.stop = Utc.ymd(2021, 7, 1).and_hms(8, 0, 0);
+        assert_eq!(
+            refuse_before_start(&entry).unwrap_err().code(),
+            EXIT_INVALID_ENTRY
+        );
+    }
+}
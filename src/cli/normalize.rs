@@ -0,0 +1,36 @@
+use super::{auto_commit, CliError, Context, Result, ResultExt};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    /// Only check whether the files are in the canonical format, failing if they are not
+    #[structopt(long)]
+    pub check: bool,
+}
+
+pub fn run(ctx: Context, args: &Args) -> Result<()> {
+    let Context {
+        config, storage, ..
+    } = ctx;
+    let Args { check } = args;
+    let (entries, running_entries) = storage
+        .normalize(*check)
+        .context("could not normalize the data files")?;
+
+    if *check {
+        if entries + running_entries > 0 {
+            eprintln!(
+                "{} entries and {} running entries are not in the canonical format",
+                entries, running_entries
+            );
+            return Err(CliError::exit(1));
+        }
+    } else {
+        println!(
+            "Normalized {} entries and {} running entries",
+            entries, running_entries
+        );
+        auto_commit(&storage, &config, "Normalize")?;
+    }
+    Ok(())
+}
@@ -0,0 +1,53 @@
+use super::{edit_text, notes_dir, CliError, Context, Result, ResultExt};
+use structopt::StructOpt;
+use timetracker::notes::Notes;
+use timetracker::selector::Selector;
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    /// `last` for the last entry, or the ID of the entry, at least its first 4 characters
+    pub selector: Selector,
+
+    /// Print the note instead of editing it
+    #[structopt(long)]
+    pub print: bool,
+}
+
+pub fn run(ctx: Context, args: &Args) -> Result<()> {
+    let Context {
+        dry_run, storage, ..
+    } = ctx;
+    let Args { selector, print } = args;
+    let entries = storage.entries().context("could not read entries")?;
+    let entry = selector
+        .find(&entries)
+        .map_err(|err| CliError::new(format!("{}", err)))?;
+    let notes = Notes::new(notes_dir(&storage));
+    let id = entry.id();
+    let note = notes.get(&id).context("could not read the note")?;
+    if *print {
+        match note {
+            Some(note) => print!("{}", note),
+            None => {
+                eprintln!("{} has no note", id);
+                return Err(CliError::exit(1));
+            }
+        }
+        return Ok(());
+    }
+
+    let edited = edit_text(&id, &note.unwrap_or_default())?;
+    if dry_run {
+        println!("Would write {}:\n{}", notes.path(&id).display(), edited);
+        return Ok(());
+    }
+    notes
+        .set(&id, &edited)
+        .context("could not write the note")?;
+    if edited.trim().is_empty() {
+        println!("Removed the note of {}", entry);
+    } else {
+        println!("Saved the note of {}", entry);
+    }
+    Ok(())
+}
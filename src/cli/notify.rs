@@ -0,0 +1,55 @@
+use super::{notify, Context, Result, ResultExt};
+use chrono::Local;
+use std::collections::HashSet;
+use std::thread;
+use structopt::StructOpt;
+use timetracker::reminders::{self, Reminder};
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    /// Keep checking instead of exiting after a single check
+    #[structopt(long)]
+    pub watch: bool,
+
+    /// Seconds between checks when watching
+    #[structopt(long, default_value = "300")]
+    pub interval: u64,
+}
+
+pub fn run(ctx: Context, args: &Args) -> Result<()> {
+    let Context {
+        config, storage, ..
+    } = ctx;
+    let Args { watch, interval } = args;
+    // Reminders that have been sent, so each is only sent once while it applies
+    let mut notified = HashSet::new();
+    loop {
+        let running_entries = storage
+            .running_entries()
+            .context("could not read running entries")?;
+        let reminders = reminders::reminders(
+            &running_entries,
+            &timetracker::now().with_timezone(&Local),
+            &config.reminders,
+        );
+
+        let mut keys = HashSet::new();
+        for reminder in reminders {
+            let key = match &reminder {
+                Reminder::LongRunning { entry, .. } => entry.to_string(),
+                Reminder::Idle => "idle".to_string(),
+            };
+            if !notified.contains(&key) {
+                notify(&reminder.to_string());
+            }
+            keys.insert(key);
+        }
+        notified = keys;
+
+        if !watch {
+            break;
+        }
+        thread::sleep(std::time::Duration::from_secs(*interval));
+    }
+    Ok(())
+}
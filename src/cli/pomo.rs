@@ -0,0 +1,99 @@
+use super::{
+    auto_commit, exit_interrupted, handle_interrupts, notify, sleep_unless_interrupted,
+    update_slack_status, CliError, Context, Result,
+};
+use chrono::Duration;
+use structopt::StructOpt;
+use timetracker::duration::{format_hours_minutes, parse_duration};
+use timetracker::RunningEntry;
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    pub account: String,
+
+    /// How long to work for in each cycle
+    #[structopt(long, default_value = "25m", parse(try_from_str = parse_duration))]
+    pub work: Duration,
+
+    /// How long to take a break for between cycles
+    #[structopt(long = "break", default_value = "5m", parse(try_from_str = parse_duration))]
+    pub break_duration: Duration,
+
+    /// The number of cycles of work
+    #[structopt(long, default_value = "4")]
+    pub cycles: u32,
+}
+
+pub fn run(ctx: Context, args: &Args) -> Result<()> {
+    let Context {
+        dry_run,
+        config,
+        accounts,
+        storage,
+        ..
+    } = ctx;
+    let Args {
+        account,
+        work,
+        break_duration,
+        cycles,
+    } = args;
+    let account = config.expand_alias(account);
+    handle_interrupts();
+    for cycle in 1..=*cycles {
+        let running_entry = storage
+            .start(
+                RunningEntry::builder()
+                    .start(timetracker::now())
+                    .account(account.clone())
+                    .tag("pomodoro")
+                    .category_if_any(accounts.category(&account))
+                    .user_if_any(config.team.user())
+                    .build()
+                    .map_err(|err| CliError::new(err.to_string()))?,
+            )
+            .map_err(|err| CliError::new(err.to_string()))?;
+        if !dry_run {
+            if let Err(err) = config.hooks.started(&running_entry) {
+                log::error!("{}", err);
+            }
+            update_slack_status(&storage, &config);
+        }
+        notify(&format!(
+            "Pomodoro {} of {}: work on {} for {}",
+            cycle,
+            cycles,
+            account,
+            format_hours_minutes(*work)
+        ));
+
+        let completed = sleep_unless_interrupted(*work);
+        let entry = storage
+            .stop(Some(&account), timetracker::now())
+            .map_err(|err| CliError::new(err.to_string()))?;
+        if !dry_run {
+            if let Err(err) = config.hooks.stopped(&entry) {
+                log::error!("{}", err);
+            }
+            update_slack_status(&storage, &config);
+        }
+        auto_commit(&storage, &config, &format!("Pomodoro {}", account))?;
+        if !completed {
+            println!("Stopped {}", entry);
+            return Err(exit_interrupted());
+        }
+
+        if cycle == *cycles {
+            notify(&format!("Done with {} pomodoros of {}", cycles, account));
+        } else {
+            notify(&format!(
+                "Take a break for {}",
+                format_hours_minutes(*break_duration)
+            ));
+            if !sleep_unless_interrupted(*break_duration) {
+                return Err(exit_interrupted());
+            }
+        }
+    }
+    Ok(())
+}
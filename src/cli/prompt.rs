@@ -0,0 +1,51 @@
+use super::{alert_marker, from_daemon, Result};
+#[cfg(feature = "encryption")]
+use crate::encryption_key;
+use crate::open_storage;
+use chrono::Local;
+use std::path::{Path, PathBuf};
+use structopt::StructOpt;
+use timetracker::accounts::Accounts;
+use timetracker::config::Config;
+use timetracker::prompt;
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    /// The line for each running entry, with the placeholders `{account}`, `{elapsed}`,
+    /// `{start}`, `{tags}` and `{description}`
+    #[structopt(long, default_value = "{account} {elapsed}")]
+    pub format: String,
+}
+
+/// Prints the running entries for a shell prompt, which is shown all the time, so it never fails
+/// on a missing or broken file and runs without a `Context`.
+pub fn run(
+    args: &Args,
+    config_path: &Path,
+    entries_path: PathBuf,
+    running_path: PathBuf,
+    daemon_running_path: &Path,
+) -> Result<()> {
+    let config = Config::load(config_path).unwrap_or_default();
+    let storage = open_storage(entries_path, running_path, &config);
+    #[cfg(feature = "encryption")]
+    let storage = match encryption_key(config.storage.identity_file.as_deref()) {
+        Ok(Some(key)) => storage.key(key),
+        _ => storage,
+    };
+    let running_entries = from_daemon(daemon_running_path)
+        .unwrap_or_else(|| storage.running_entries().unwrap_or_default());
+    let line = prompt::format_running(
+        &args.format,
+        &running_entries,
+        &timetracker::now().with_timezone(&Local),
+    );
+    let accounts = Accounts::load(&config_path.with_file_name("accounts.toml")).unwrap_or_default();
+    match (line, alert_marker(&storage, &config, &accounts)) {
+        (Some(line), Some(marker)) => println!("{} {}", line, marker),
+        (Some(line), None) => println!("{}", line),
+        (None, Some(marker)) => println!("{}", marker),
+        (None, None) => {}
+    }
+    Ok(())
+}
@@ -0,0 +1,121 @@
+use super::{parse_datetime, Context, Result, ResultExt};
+#[cfg(feature = "http")]
+use crate::caldav;
+#[cfg(feature = "http")]
+use crate::clockify;
+#[cfg(feature = "http")]
+use crate::jira;
+#[cfg(feature = "http")]
+use crate::slack;
+use chrono::{DateTime, Utc};
+use structopt::StructOpt;
+#[cfg(feature = "http")]
+use timetracker::config::CaldavConfig;
+#[cfg(feature = "http")]
+use timetracker::pushed::PushedLog;
+use timetracker::Entry;
+
+#[cfg(feature = "http")]
+#[derive(Debug, StructOpt)]
+pub enum PushTarget {
+    /// Creates an event for each entry in a calendar on a CalDAV server, e.g. Nextcloud, and
+    /// updates the events of entries that changed
+    Caldav {
+        /// Only push entries starting at or after this time, e.g. `2021-07-01` or `monday`
+        #[structopt(long, parse(try_from_str = parse_datetime))]
+        since: Option<DateTime<Utc>>,
+
+        /// The URL of the collection of calendars, instead of the one from the config
+        #[structopt(long)]
+        url: Option<String>,
+
+        /// The calendar to create the events in, instead of the one from the config
+        #[structopt(long)]
+        calendar: Option<String>,
+    },
+    /// Creates Clockify time entries, mapping accounts to projects with `[clockify.projects]`
+    Clockify {
+        /// Only push entries starting at or after this time, e.g. `2021-07-01` or `monday`
+        #[structopt(long, parse(try_from_str = parse_datetime))]
+        since: Option<DateTime<Utc>>,
+    },
+    /// Adds worklogs to the JIRA issues whose keys appear in the accounts or tags of entries
+    Jira {
+        /// Only push entries starting at or after this time, e.g. `2021-07-01` or `monday`
+        #[structopt(long, parse(try_from_str = parse_datetime))]
+        since: Option<DateTime<Utc>>,
+    },
+    /// Sets the Slack status configured for the account of the running entry under
+    /// `[slack.statuses]`, or clears it when nothing with a status is running
+    SlackStatus,
+}
+
+pub fn run(ctx: Context, args: &PushTarget) -> Result<()> {
+    let Context {
+        dry_run,
+        config,
+        storage,
+        ..
+    } = ctx;
+    match args {
+        PushTarget::SlackStatus => {
+            let running_entries = storage
+                .running_entries()
+                .context("could not read running entries")?;
+            let status = slack::update_status(&config.slack, &running_entries, dry_run)
+                .context("could not set the Slack status")?;
+            if !dry_run {
+                match status {
+                    Some(status) => {
+                        println!("Set the Slack status {} {}", status.emoji, status.text)
+                    }
+                    None => println!("Cleared the Slack status"),
+                }
+            }
+        }
+        target => {
+            let (name, since) = match target {
+                PushTarget::Caldav { since, .. } => ("caldav", since),
+                PushTarget::Clockify { since } => ("clockify", since),
+                PushTarget::Jira { since } => ("jira", since),
+                PushTarget::SlackStatus => unreachable!("handled above"),
+            };
+            let entries: Vec<Entry> = storage
+                .entries()
+                .context("could not read entries")?
+                .into_iter()
+                .filter(|entry| since.is_none_or(|since| entry.start >= since))
+                .collect();
+
+            // Remember the pushed entries next to the running entries, as it is state rather
+            // than data
+            let log_path = storage
+                .running_path()
+                .with_file_name(format!("pushed-{}", name));
+            let mut log = PushedLog::load(&log_path).context("could not read pushed entries")?;
+
+            let pushed = match target {
+                PushTarget::Caldav { url, calendar, .. } => {
+                    let config = CaldavConfig {
+                        url: url.clone().or_else(|| config.caldav.url.clone()),
+                        calendar: calendar.clone().or_else(|| config.caldav.calendar.clone()),
+                        ..config.caldav.clone()
+                    };
+                    caldav::push(&config, &entries, &mut log, dry_run)
+                }
+                PushTarget::Clockify { .. } => {
+                    clockify::push(&config.clockify, &entries, &mut log, dry_run)
+                }
+                PushTarget::Jira { .. } => jira::push(&config.jira, &entries, &mut log, dry_run),
+                PushTarget::SlackStatus => unreachable!("handled above"),
+            }
+            .with_context(|| format!("could not push to {}", name))?;
+            if dry_run {
+                println!("{} entries would be pushed", pushed);
+            } else {
+                println!("pushed {} entries", pushed);
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,58 @@
+use super::{auto_commit, parse_datetime, CliError, Context, Result, ResultExt};
+use chrono::{DateTime, Duration, Local, Utc};
+use structopt::StructOpt;
+use timetracker::duration::format_hours_minutes;
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    /// The start of the time to give to the account, e.g. `13:00`
+    #[structopt(long, parse(try_from_str = parse_datetime))]
+    pub from: DateTime<Utc>,
+
+    /// The end of the time to give to the account, e.g. `15:00`
+    #[structopt(long, parse(try_from_str = parse_datetime))]
+    pub to: DateTime<Utc>,
+
+    /// The account the time was actually spent on
+    #[structopt(long)]
+    pub account: String,
+}
+
+pub fn run(ctx: Context, args: &Args) -> Result<()> {
+    let Context {
+        config, storage, ..
+    } = ctx;
+    let Args { from, to, account } = args;
+    if from >= to {
+        return Err(CliError::new("--from must be before --to"));
+    }
+    let account = config.expand_alias(account);
+    // Only the time already recorded can be carved up
+    let running = storage
+        .running_entries()
+        .context("could not read running entries")?;
+    if let Some(entry) = running.iter().find(|entry| entry.start < *to) {
+        return Err(CliError::new(format!(
+            "{} is running since {}, stop it first to reassign its time",
+            entry.account,
+            entry.start.with_timezone(&Local).format("%Y-%m-%d %H:%M")
+        )));
+    }
+
+    let reassigned =
+        storage.reassign(*from..*to, |entry| entry.account = account.as_str().into())?;
+    if reassigned.is_empty() {
+        println!("Nothing was tracked on another account in that time");
+        return Ok(());
+    }
+    for entry in &reassigned {
+        println!("{}", entry);
+    }
+    let total = reassigned.iter().fold(Duration::zero(), |sum, entry| {
+        sum + (entry.stop - entry.start)
+    });
+    println!("Reassigned {} to {}", format_hours_minutes(total), account);
+
+    auto_commit(&storage, &config, &format!("Reassign time to {}", account))?;
+    Ok(())
+}
@@ -0,0 +1,27 @@
+use super::{auto_commit, Context, Result, ResultExt};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    pub old: String,
+    pub new: String,
+}
+
+pub fn run(ctx: Context, args: &Args) -> Result<()> {
+    let Context {
+        config, storage, ..
+    } = ctx;
+    let Args { old, new } = args;
+    let (old, new) = (config.expand_alias(old), config.expand_alias(new));
+    let (entries, running_entries) = storage
+        .rename_account(&old, &new)
+        .context("could not rename the account")?;
+    println!(
+        "Renamed {} entries and {} running entries",
+        entries, running_entries
+    );
+    if entries + running_entries > 0 {
+        auto_commit(&storage, &config, &format!("Rename {} to {}", old, new))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,644 @@
+use super::{account_name, entries_overlapping, within, CliError, Context, Result};
+use crate::output;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+use std::collections::{BTreeMap, BTreeSet};
+use structopt::StructOpt;
+use timetracker::accounts::Accounts;
+use timetracker::duration::DurationFormat;
+use timetracker::report::{self, DayTotal, GroupTotal, PeriodReport};
+use timetracker::timeparse;
+use timetracker::timesheet::Timesheet;
+use timetracker::zone::Zone;
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    /// Only count time tracked at or after this time, e.g. `monday` or `2021-07-01`
+    #[structopt(long)]
+    pub since: Option<String>,
+
+    /// Only count time tracked before this time
+    #[structopt(long)]
+    pub until: Option<String>,
+
+    /// Only include the ISO week, e.g. `2024-W15`, `this` or `last`
+    #[structopt(long, conflicts_with_all = &["since", "until", "month"])]
+    pub week: Option<String>,
+
+    /// Only include the month, e.g. `2024-03`, `this` or `last`
+    #[structopt(long, conflicts_with_all = &["since", "until"])]
+    pub month: Option<String>,
+
+    /// The time zone of the times given and the days of the report, `local`, `utc`, a name
+    /// like `Europe/Oslo` or an offset like `+02:00`, by default local. Given more than once,
+    /// the report is shown for each side by side, with the periods and days of each zone
+    #[structopt(long = "timezone", number_of_values = 1)]
+    pub timezones: Vec<Zone>,
+
+    /// How to write durations
+    #[structopt(long, default_value = "hours-minutes", possible_values = DurationFormat::NAMES)]
+    pub duration_format: DurationFormat,
+
+    /// Show accounts by their aliases from the config
+    #[structopt(long)]
+    pub aliases: bool,
+
+    /// Sum the time tracked for each client from accounts.toml instead, with accounts
+    /// without a client on their own
+    #[structopt(long)]
+    pub by_client: bool,
+
+    /// Sum the time tracked in each category across the accounts instead, taking the
+    /// category of the account from accounts.toml for entries without one
+    #[structopt(long, conflicts_with = "by-client")]
+    pub by_category: bool,
+
+    /// Sum the time tracked by each person instead, from the `user` of the entries, for a team
+    /// sharing the data files
+    #[structopt(long, conflicts_with_all = &["by-client", "by-category"])]
+    pub by_user: bool,
+
+    /// Only count the time tracked by the person, e.g. `alice`
+    #[structopt(long)]
+    pub user: Option<String>,
+
+    /// Show the total of each day before the accounts
+    #[structopt(long)]
+    pub by_day: bool,
+
+    /// Compare the time of the period with the period before it, the week or month before,
+    /// or as long before `--since`, with the change of each account
+    #[structopt(long, conflicts_with = "by-day")]
+    pub compare: bool,
+
+    /// Write the report as JSON or CSV for other programs, in seconds, see `tt schema
+    /// period-report`. Only for a single time zone, and not with --compare
+    #[structopt(long, default_value = "text", possible_values = &["text", "json", "csv"])]
+    pub format: String,
+}
+
+/// The bounds of a period, either of which may be open.
+type Bounds = (Option<DateTime<Utc>>, Option<DateTime<Utc>>);
+
+/// The period of a report in the time zone, from `--week` or `--month` if given or else from
+/// `--since` and `--until`, failing if one of them cannot be parsed.
+fn report_period(
+    zone: &Zone,
+    now: DateTime<Utc>,
+    since: Option<&str>,
+    until: Option<&str>,
+    week: Option<&str>,
+    month: Option<&str>,
+) -> Result<Bounds> {
+    let now = now.with_timezone(zone);
+    let parsed = match (week, month) {
+        (Some(week), _) => {
+            timeparse::parse_week(week, &now).map(|week| (Some(week.start), Some(week.end)))
+        }
+        (None, Some(month)) => {
+            timeparse::parse_month(month, &now).map(|month| (Some(month.start), Some(month.end)))
+        }
+        (None, None) => since
+            .map(|since| timeparse::parse(since, &now))
+            .transpose()
+            .and_then(|since| {
+                let until = until
+                    .map(|until| timeparse::parse(until, &now))
+                    .transpose()?;
+                Ok((since, until))
+            }),
+    };
+    parsed.map_err(CliError::new)
+}
+
+/// The period before the one from the time to the other, for `report --compare`: the week or
+/// month before in the time zone, or an equally long period.
+fn previous_period(
+    zone: &Zone,
+    since: DateTime<Utc>,
+    until: DateTime<Utc>,
+    week: bool,
+    month: bool,
+) -> (DateTime<Utc>, DateTime<Utc>) {
+    let first = report::local_date(since, zone);
+    if week {
+        (
+            report::start_of_day(first - Duration::weeks(1), zone),
+            since,
+        )
+    } else if month {
+        let last = first.pred();
+        let start = NaiveDate::from_ymd(last.year(), last.month(), 1);
+        (report::start_of_day(start, zone), since)
+    } else {
+        (since - (until - since), since)
+    }
+}
+
+/// The change from the previous time in percent, like `+25%`, or `new` if there was none.
+fn percent_change(current: Duration, previous: Duration) -> String {
+    if previous.is_zero() {
+        return if current.is_zero() {
+            String::new()
+        } else {
+            "new".to_string()
+        };
+    }
+    let change = (current - previous).num_seconds() as f64 / previous.num_seconds() as f64;
+    format!("{:+.0}%", change * 100.0)
+}
+
+/// The report in one time zone.
+struct ReportColumn {
+    zone: Zone,
+    /// The total of each day, if they are shown.
+    days: BTreeMap<NaiveDate, Duration>,
+    /// The time and the amount charged for it, see `report_totals`.
+    totals: Vec<(String, Duration, Option<f64>)>,
+    billable: Duration,
+    non_billable: Duration,
+    /// The time expected to be worked in the period, if it has a start.
+    expected: Option<Duration>,
+}
+
+/// The time tracked on each account, named by `name`, or summed for each client, category or
+/// user, with the amount charged for it if the account has a rate.
+fn report_totals(
+    timesheet: &Timesheet,
+    accounts: &Accounts,
+    name: impl Fn(&str) -> String,
+    by_client: bool,
+    by_category: bool,
+    by_user: bool,
+) -> Vec<(String, Duration, Option<f64>)> {
+    let mut totals: Vec<(String, Duration, Option<f64>)> = Vec::new();
+    for (account, entries) in timesheet.group_by_account() {
+        let info = accounts.get(&account);
+        let groups = if by_category {
+            entries
+                .group_by_category()
+                .into_iter()
+                .map(|(category, entries)| {
+                    (
+                        category.unwrap_or_else(|| "Uncategorized".to_string()),
+                        entries,
+                    )
+                })
+                .collect()
+        } else if by_user {
+            entries
+                .group_by_user()
+                .into_iter()
+                .map(|(user, entries)| (user.unwrap_or_else(|| "Unknown".to_string()), entries))
+                .collect()
+        } else {
+            let name = match info.and_then(|info| info.client.clone()) {
+                Some(client) if by_client => client,
+                _ => name(&account),
+            };
+            vec![(name, entries)]
+        };
+        for (name, entries) in groups {
+            // Only billable time is charged
+            let amount = info.and_then(|info| info.rate).map(|rate| {
+                let billable = entries.filter_by_billable(true).total_duration();
+                billable.num_seconds() as f64 / 3600.0 * rate
+            });
+            let duration = entries.total_duration();
+            match totals.iter_mut().find(|(existing, _, _)| *existing == name) {
+                Some((_, total, sum)) => {
+                    *total = *total + duration;
+                    *sum = match (*sum, amount) {
+                        (Some(sum), Some(amount)) => Some(sum + amount),
+                        (sum, amount) => sum.or(amount),
+                    };
+                }
+                None => totals.push((name, duration, amount)),
+            }
+        }
+    }
+    if by_client || by_category || by_user {
+        totals.sort_by(|(a, _, _), (b, _, _)| a.cmp(b));
+    }
+    totals
+}
+
+pub fn run(ctx: Context, args: &Args) -> Result<()> {
+    let Context {
+        config,
+        accounts,
+        style,
+        locale,
+        storage,
+        ..
+    } = ctx;
+    let Args {
+        since,
+        until,
+        week,
+        month,
+        duration_format,
+        aliases,
+        by_client,
+        by_category,
+        by_user,
+        user,
+        by_day,
+        timezones,
+        compare,
+        format,
+    } = args;
+    let now = timetracker::now();
+    let zones = if timezones.is_empty() {
+        vec![Zone::Local]
+    } else {
+        timezones.clone()
+    };
+    let period = |zone: &Zone| {
+        report_period(
+            zone,
+            now,
+            since.as_deref(),
+            until.as_deref(),
+            week.as_deref(),
+            month.as_deref(),
+        )
+    };
+    // The entries within the period, with the category of their account if they are
+    // summed by category
+    let period_timesheet =
+        |since: Option<DateTime<Utc>>, until: Option<DateTime<Utc>>| -> Result<Timesheet> {
+            let timesheet = Timesheet::new(entries_overlapping(&storage, now, since, until)?);
+            let timesheet = within(&timesheet, since, until);
+            let timesheet = match user {
+                Some(user) => timesheet
+                    .into_entries()
+                    .into_iter()
+                    .filter(|entry| entry.user() == Some(user.as_str()))
+                    .collect(),
+                None => timesheet,
+            };
+            if !*by_category {
+                return Ok(timesheet);
+            }
+            Ok(timesheet
+                .into_entries()
+                .into_iter()
+                .map(|mut entry| {
+                    if entry.category.is_none() {
+                        entry.category = accounts.category(&entry.account).map(str::to_string);
+                    }
+                    entry
+                })
+                .collect())
+        };
+    let totals_of = |timesheet: &Timesheet| {
+        report_totals(
+            timesheet,
+            &accounts,
+            |account| account_name(&config, account, *aliases),
+            *by_client,
+            *by_category,
+            *by_user,
+        )
+    };
+
+    if *compare {
+        if format != "text" {
+            return Err(CliError::new(format!(
+                "--format {} cannot be used with --compare",
+                format
+            )));
+        }
+        let zone = &zones[0];
+        let (since, until) = match period(zone)? {
+            (Some(since), until) => (since, until.unwrap_or(now)),
+            (None, _) => {
+                return Err(CliError::new("--compare needs a period, e.g. --week this"));
+            }
+        };
+        let previous = previous_period(zone, since, until, week.is_some(), month.is_some());
+        let current = totals_of(&period_timesheet(Some(since), Some(until))?);
+        let previous = totals_of(&period_timesheet(Some(previous.0), Some(previous.1))?);
+        let total_of = |totals: &[(String, Duration, Option<f64>)], name: &str| {
+            totals
+                .iter()
+                .find(|(existing, _, _)| existing == name)
+                .map_or_else(Duration::zero, |(_, total, _)| *total)
+        };
+        let mut names: Vec<&str> = Vec::new();
+        for (name, _, _) in current.iter().chain(&previous) {
+            if !names.contains(&name.as_str()) {
+                names.push(name);
+            }
+        }
+        let width = output::column_width(names.iter().copied().chain(vec!["Total"]));
+        println!(
+            "{}",
+            style.bold(&format!(
+                "{:width$}  {:>9}  {:>9}  {:>9}  {:>7}",
+                "",
+                "This",
+                "Previous",
+                "Change",
+                "",
+                width = width
+            ))
+        );
+        let row = |current: Duration, previous: Duration| {
+            let change = current - previous;
+            let sign = if change > Duration::zero() { "+" } else { "" };
+            format!(
+                "{:>9}  {:>9}  {:>9}  {:>7}",
+                locale.format_duration(*duration_format, current),
+                locale.format_duration(*duration_format, previous),
+                format!(
+                    "{}{}",
+                    sign,
+                    locale.format_duration(*duration_format, change)
+                ),
+                percent_change(current, previous)
+            )
+        };
+        for name in &names {
+            let (current, previous) = (total_of(&current, name), total_of(&previous, name));
+            println!("{}  {}", style.account(name, width), row(current, previous));
+        }
+        let sum = |totals: &[(String, Duration, Option<f64>)]| {
+            totals
+                .iter()
+                .fold(Duration::zero(), |sum, (_, total, _)| sum + *total)
+        };
+        println!(
+            "{}",
+            style.bold(&format!(
+                "{:<width$}  {}",
+                "Total",
+                row(sum(&current), sum(&previous)),
+                width = width
+            ))
+        );
+        return Ok(());
+    }
+
+    let columns: Vec<ReportColumn> = zones
+        .iter()
+        .map(|zone| {
+            let (since, until) = period(zone)?;
+            let timesheet = period_timesheet(since, until)?;
+            let totals = totals_of(&timesheet);
+            let expected = since.and_then(|since| {
+                // Only the days up to today are expected to be worked
+                let last = until.map_or(now, |until| (until - Duration::seconds(1)).min(now));
+                let local_date = |time: DateTime<Utc>| report::local_date(time, zone);
+                config
+                    .calendar
+                    .expected(local_date(since)..=local_date(last))
+            });
+            Ok(ReportColumn {
+                zone: *zone,
+                days: if *by_day {
+                    report::totals_by_day(timesheet.entries(), zone)
+                } else {
+                    BTreeMap::new()
+                },
+                totals,
+                billable: timesheet.filter_by_billable(true).total_duration(),
+                non_billable: timesheet.filter_by_billable(false).total_duration(),
+                expected,
+            })
+        })
+        .collect::<Result<_>>()?;
+    if format != "text" {
+        if columns.len() > 1 {
+            return Err(CliError::new(format!(
+                "--format {} is for a single time zone",
+                format
+            )));
+        }
+        let column = &columns[0];
+        let (since, until) = period(&column.zone)?;
+        let total = column
+            .totals
+            .iter()
+            .fold(Duration::zero(), |sum, (_, total, _)| sum + *total);
+        let document = PeriodReport {
+            zone: column.zone.to_string(),
+            since,
+            until,
+            days: column
+                .days
+                .iter()
+                .map(|(date, total)| DayTotal {
+                    date: *date,
+                    seconds: total.num_seconds(),
+                })
+                .collect(),
+            totals: column
+                .totals
+                .iter()
+                .map(|(name, total, amount)| GroupTotal {
+                    name: name.clone(),
+                    seconds: total.num_seconds(),
+                    amount: *amount,
+                })
+                .collect(),
+            billable_seconds: column.billable.num_seconds(),
+            non_billable_seconds: column.non_billable.num_seconds(),
+            total_seconds: total.num_seconds(),
+            total_amount: column
+                .totals
+                .iter()
+                .filter_map(|(_, _, amount)| *amount)
+                .reduce(|sum, amount| sum + amount),
+            expected_seconds: column.expected.map(|expected| expected.num_seconds()),
+        };
+        if format == "json" {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&document).expect("reports can be serialized")
+            );
+        } else {
+            println!("{}", document.to_csv());
+        }
+        return Ok(());
+    }
+    // Amounts are only shown for a single time zone, to keep the columns of durations
+    // side by side
+    let single = columns.len() == 1;
+    let column_width = |column: &ReportColumn| column.zone.to_string().len().max(9);
+
+    let mut names: Vec<&str> = Vec::new();
+    for column in &columns {
+        for (name, _, _) in &column.totals {
+            if !names.contains(&name.as_str()) {
+                names.push(name);
+            }
+        }
+    }
+    let days: BTreeSet<NaiveDate> = columns
+        .iter()
+        .flat_map(|column| column.days.keys().copied())
+        .collect();
+    let dates: Vec<String> = days
+        .iter()
+        .map(|date| {
+            date.format(&locale.pattern("%Y-%m-%d %a", date))
+                .to_string()
+        })
+        .collect();
+    let mut width =
+        output::column_width(names.iter().copied().chain(vec!["Non-billable", "Total"]));
+    if !single {
+        // The days line up with the accounts below them
+        width = width.max(output::column_width(dates.iter().map(String::as_str)));
+    }
+    let row = |label: &str, durations: &mut dyn Iterator<Item = Duration>| {
+        let durations: Vec<String> = columns
+            .iter()
+            .zip(durations)
+            .map(|(column, duration)| {
+                format!(
+                    "{:>width$}",
+                    locale.format_duration(*duration_format, duration),
+                    width = column_width(column)
+                )
+            })
+            .collect();
+        format!("{:<width$}  {}", label, durations.join("  "), width = width)
+    };
+    if !single {
+        let zones: Vec<String> = columns
+            .iter()
+            .map(|column| {
+                format!(
+                    "{:>width$}",
+                    column.zone.to_string(),
+                    width = column_width(column)
+                )
+            })
+            .collect();
+        println!(
+            "{}",
+            style.bold(&format!(
+                "{:width$}  {}",
+                "",
+                zones.join("  "),
+                width = width
+            ))
+        );
+    }
+
+    if *by_day {
+        for (date, label) in days.iter().zip(&dates) {
+            let durations: Vec<String> = columns
+                .iter()
+                .map(|column| {
+                    let total = column
+                        .days
+                        .get(date)
+                        .copied()
+                        .unwrap_or_else(Duration::zero);
+                    style.threshold(
+                        &format!(
+                            "{:>width$}",
+                            locale.format_duration(*duration_format, total),
+                            width = column_width(column)
+                        ),
+                        config.thresholds.daily_level(total),
+                    )
+                })
+                .collect();
+            if single {
+                println!("{}  {}", label, durations.join("  "));
+            } else {
+                println!("{:<width$}  {}", label, durations.join("  "), width = width);
+            }
+        }
+        if !days.is_empty() {
+            println!();
+        }
+    }
+
+    for name in &names {
+        let total = |column: &ReportColumn| {
+            column
+                .totals
+                .iter()
+                .find(|(existing, _, _)| existing == name)
+                .map(|(_, total, amount)| (*total, *amount))
+        };
+        if single {
+            let (total, amount) = total(&columns[0]).expect("the name is of the column");
+            let amount = amount.map_or(String::new(), |amount| format!("  {:>10.2}", amount));
+            println!(
+                "{}  {:>duration_width$}{}",
+                style.account(name, width),
+                locale.format_duration(*duration_format, total),
+                amount,
+                duration_width = column_width(&columns[0])
+            );
+            continue;
+        }
+        let durations: Vec<String> = columns
+            .iter()
+            .map(|column| {
+                let total = total(column).map_or_else(Duration::zero, |(total, _)| total);
+                format!(
+                    "{:>width$}",
+                    locale.format_duration(*duration_format, total),
+                    width = column_width(column)
+                )
+            })
+            .collect();
+        println!("{}  {}", style.account(name, width), durations.join("  "));
+    }
+    println!(
+        "{}",
+        row(
+            "Billable",
+            &mut columns.iter().map(|column| column.billable)
+        )
+    );
+    println!(
+        "{}",
+        row(
+            "Non-billable",
+            &mut columns.iter().map(|column| column.non_billable)
+        )
+    );
+    let total = |column: &ReportColumn| {
+        column
+            .totals
+            .iter()
+            .fold(Duration::zero(), |sum, (_, total, _)| sum + *total)
+    };
+    let amount = columns[0]
+        .totals
+        .iter()
+        .filter_map(|(_, _, amount)| *amount)
+        .reduce(|sum, amount| sum + amount)
+        .filter(|_| single)
+        .map_or(String::new(), |amount| format!("  {:>10.2}", amount));
+    println!(
+        "{}",
+        style.bold(&format!(
+            "{}{}",
+            row("Total", &mut columns.iter().map(total)),
+            amount
+        ))
+    );
+    if columns.iter().all(|column| column.expected.is_some()) {
+        let expected = |column: &ReportColumn| column.expected.unwrap_or_else(Duration::zero);
+        println!("{}", row("Expected", &mut columns.iter().map(expected)));
+        println!(
+            "{}",
+            row(
+                "Difference",
+                &mut columns
+                    .iter()
+                    .map(|column| total(column) - expected(column))
+            )
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,69 @@
+use super::{auto_commit, backup_files, CliError, Context, Result, ResultExt};
+use std::path::PathBuf;
+use structopt::StructOpt;
+use timetracker::backup::Backups;
+use timetracker::storage::WriteMode;
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    /// The name of the snapshot, or `latest`
+    pub snapshot: String,
+
+    /// The directory the snapshot is in, instead of the one from the config
+    #[structopt(long, parse(from_os_str))]
+    pub from: Option<PathBuf>,
+}
+
+pub fn run(ctx: Context, args: &Args) -> Result<()> {
+    let Context {
+        config_path,
+        favorites_path,
+        accounts_path,
+        config,
+        write_mode,
+        storage,
+        backup_dir,
+        ..
+    } = ctx;
+    let Args { snapshot, from } = args;
+    let backup_files = backup_files(&storage, &config_path, &accounts_path, &favorites_path);
+    let backups = Backups::new(from.clone().unwrap_or_else(|| backup_dir.clone()));
+    let snapshot = backups
+        .find(snapshot)
+        .context("could not list backups")?
+        .ok_or_else(|| {
+            CliError::new(format!(
+                r#"there is no backup named "{}" in {}"#,
+                snapshot,
+                backups.dir().display()
+            ))
+        })?;
+    match write_mode {
+        WriteMode::DryRun => {
+            println!("Would restore {}", snapshot.name);
+            return Ok(());
+        }
+        WriteMode::ReadOnly => return Err(CliError::new("the data files are read-only")),
+        WriteMode::Write => {}
+    }
+
+    // The files as they are can be restored in turn. Old snapshots are only removed after
+    // restoring, as the one restored could be one of them
+    let current = backups
+        .create(&backup_files, timetracker::now(), Some("restore"))
+        .context("could not back up the files")?;
+    snapshot
+        .restore(&backup_files)
+        .with_context(|| format!("could not restore {}", snapshot.name))?;
+    if let Some(keep) = config.backup.keep {
+        backups
+            .rotate(keep)
+            .context("could not remove old backups")?;
+    }
+    println!(
+        "Restored {}, the files before it are in {}",
+        snapshot.name, current.name
+    );
+    auto_commit(&storage, &config, "Restore")?;
+    Ok(())
+}
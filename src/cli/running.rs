@@ -0,0 +1,73 @@
+use super::{from_daemon, Context, Result, ResultExt};
+use crate::output;
+use chrono::Local;
+use structopt::StructOpt;
+use timetracker::duration::DurationFormat;
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    /// The order of the entries, by default the order of the running file. `start` lists the
+    /// earliest started first and `elapsed` the one running for the shortest time first
+    #[structopt(long, possible_values = &["start", "account", "elapsed"])]
+    pub sort: Option<String>,
+
+    /// The line format of the running file, or a table with the time each entry has been
+    /// running for
+    #[structopt(long, possible_values = &["line", "table"], default_value = "line")]
+    pub format: String,
+
+    /// How to write durations in the table
+    #[structopt(long, default_value = "hours-minutes", possible_values = DurationFormat::NAMES)]
+    pub duration_format: DurationFormat,
+}
+
+pub fn run(ctx: Context, args: &Args) -> Result<()> {
+    let Context {
+        daemon_running_path,
+        style,
+        locale,
+        storage,
+        ..
+    } = ctx;
+    let Args {
+        sort,
+        format,
+        duration_format,
+    } = args;
+    let now = timetracker::now();
+    let mut running = match from_daemon(&daemon_running_path) {
+        Some(running) => running,
+        None => storage
+            .running_entries()
+            .context("could not read running entries")?,
+    };
+    match sort.as_deref() {
+        Some("start") => running.sort_by_key(|entry| entry.start),
+        Some("account") => running.sort_by(|a, b| a.account.cmp(&b.account)),
+        Some("elapsed") => running.sort_by_key(|entry| now - entry.start),
+        _ => {}
+    }
+
+    if format == "line" {
+        running.iter().for_each(|entry| println!("{}", entry));
+        return Ok(());
+    }
+    let width = output::column_width(running.iter().map(|entry| &*entry.account));
+    for entry in &running {
+        let mut details: Vec<String> = entry.tags.iter().map(|tag| format!("#{}", tag)).collect();
+        details.extend(entry.description.clone());
+        let elapsed = format!(
+            "{:>8}",
+            locale.format_duration(*duration_format, now - entry.start)
+        );
+        let used = "YYYY-MM-DD HH:MM".len() + elapsed.chars().count() + width + 6;
+        println!(
+            "{}  {}  {}  {}",
+            entry.start.with_timezone(&Local).format("%Y-%m-%d %H:%M"),
+            elapsed,
+            style.account(&entry.account, width),
+            style.dim(&output::fit(&details.join(" "), used))
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,95 @@
+use super::{auto_commit, entries_overlapping, parse_datetime, Context, Result};
+use chrono::{DateTime, Local, Utc};
+use structopt::StructOpt;
+use timetracker::report;
+use timetracker::schedule;
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    /// The first day, e.g. `2021-07-01` or `monday`
+    #[structopt(long, parse(try_from_str = parse_datetime))]
+    pub since: Option<DateTime<Utc>>,
+
+    /// The last day, by default the first
+    #[structopt(long, parse(try_from_str = parse_datetime))]
+    pub until: Option<DateTime<Utc>>,
+
+    /// List the recurring entries instead
+    #[structopt(long, conflicts_with_all = &["since", "until"])]
+    pub list: bool,
+}
+
+pub fn run(ctx: Context, args: &Args) -> Result<()> {
+    let Context {
+        config,
+        style,
+        storage,
+        ..
+    } = ctx;
+    let Args { since, until, list } = args;
+    if *list {
+        for (name, recurring) in &config.schedule {
+            let days = match &recurring.days {
+                Some(days) => days.join(" "),
+                None => "work days".to_string(),
+            };
+            println!(
+                "{}  {}-{}  {}  {}",
+                name,
+                recurring.time.start.format("%H:%M"),
+                recurring.time.end.format("%H:%M"),
+                recurring.account,
+                style.dim(&days)
+            );
+        }
+        return Ok(());
+    }
+    let now = timetracker::now();
+    let date = |time: DateTime<Utc>| report::local_date(time, &Local);
+    let first = since.map_or_else(
+        || {
+            timetracker::now()
+                .with_timezone(&Local)
+                .date()
+                .naive_local()
+        },
+        date,
+    );
+    let last = until.map_or(first, date);
+    let scheduled = schedule::entries(&config.schedule, &config.calendar, first..=last, &Local);
+    let (since, until) = match (
+        scheduled.first(),
+        scheduled.iter().map(|entry| entry.stop).max(),
+    ) {
+        (Some(first), Some(until)) => (first.start, until),
+        _ => {
+            println!("Nothing is scheduled");
+            return Ok(());
+        }
+    };
+    let mut existing = entries_overlapping(&storage, now, Some(since), Some(until))?;
+    let mut recorded = 0;
+    for entry in scheduled {
+        if entry.stop > now {
+            continue;
+        }
+        if existing.iter().any(|other| other.id() == entry.id()) {
+            continue;
+        }
+        if let Some(other) = existing
+            .iter()
+            .find(|other| other.start < entry.stop && entry.start < other.stop)
+        {
+            println!("Skipped {}, it would overlap {}", entry, other);
+            continue;
+        }
+        storage.append_entry(&entry)?;
+        println!("Recorded {}", entry);
+        existing.push(entry);
+        recorded += 1;
+    }
+    if recorded > 0 {
+        auto_commit(&storage, &config, "Record scheduled entries")?;
+    }
+    Ok(())
+}
@@ -0,0 +1,43 @@
+use super::{Context, Result};
+use structopt::StructOpt;
+use timetracker::schema;
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    #[structopt(parse(try_from_str = parse_schema_name))]
+    pub name: Option<String>,
+}
+
+fn parse_schema_name(s: &str) -> Result<String, String> {
+    match schema::document(s) {
+        Some(_) => Ok(s.to_string()),
+        None => Err(format!(
+            r#"unknown schema "{}", expected one of {}"#,
+            s,
+            schema::NAMES
+                .iter()
+                .map(|(name, _)| *name)
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+pub fn run(_ctx: Context, args: &Args) -> Result<()> {
+    let Args { name } = args;
+    match name {
+        Some(name) => {
+            let document = schema::document(name).expect("the name was checked when parsed");
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&document).expect("schemas can be serialized")
+            );
+        }
+        None => {
+            for (name, description) in &schema::NAMES {
+                println!("{:<15} {}", name, description);
+            }
+        }
+    };
+    Ok(())
+}
@@ -0,0 +1,30 @@
+use super::{auto_stop, CliError, Context, Result};
+#[cfg(feature = "server")]
+use crate::server;
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    #[structopt(long, default_value = "127.0.0.1:8420")]
+    pub listen: String,
+}
+
+pub fn run(ctx: Context, args: &Args) -> Result<()> {
+    let Context {
+        accounts,
+        config,
+        storage,
+        ..
+    } = ctx;
+    let Args { listen } = args;
+    // Stops forgotten entries on time instead of on the next request after it
+    server::serve(
+        listen,
+        &storage,
+        &accounts.colors(),
+        config.team.user(),
+        || auto_stop(&storage, &config),
+    )
+    .map_err(|err| CliError::new(err.to_string()))?;
+    Ok(())
+}
@@ -0,0 +1,121 @@
+use super::{notes_dir, CliError, Context, Result, ResultExt};
+use chrono::{DateTime, Local, Utc};
+use structopt::StructOpt;
+use timetracker::duration::format_hours_minutes;
+use timetracker::imported::ImportedLog;
+use timetracker::notes::Notes;
+use timetracker::selector::Selector;
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    /// `last` for the last entry, or the ID of the entry, at least its first 4 characters
+    pub selector: Selector,
+
+    /// Print the entry as text or as JSON, also accepted as `--format` like the other commands
+    #[structopt(
+        long,
+        alias = "format",
+        default_value = "text",
+        possible_values = &["text", "json"]
+    )]
+    pub output: String,
+}
+
+pub fn run(ctx: Context, args: &Args) -> Result<()> {
+    let Context {
+        accounts: _,
+        style,
+        storage,
+        ..
+    } = ctx;
+    let Args { selector, output } = args;
+    let entries = storage.entries().context("could not read entries")?;
+    let entry = selector
+        .find(&entries)
+        .map_err(|err| CliError::new(format!("{}", err)))?;
+    let id = entry.id();
+    let imported = ImportedLog::load(&storage.running_path().with_file_name("imported"))
+        .context("could not read imported sources")?;
+    let sources = imported.sources(&id);
+    let note = Notes::new(notes_dir(&storage))
+        .get(&id)
+        .context("could not read the note")?;
+    // The account and the accounts it is within, outermost first
+    let parts: Vec<&str> = entry.account.split(':').collect();
+    let accounts: Vec<String> = (1..=parts.len())
+        .map(|count| parts[..count].join(":"))
+        .collect();
+    let duration = entry.stop - entry.start;
+    let local = |time: &DateTime<Utc>| time.with_timezone(&Local).to_rfc3339();
+
+    if output == "json" {
+        let details = serde_json::json!({
+            "id": id,
+            "start": entry.start,
+            "stop": entry.stop,
+            "start_local": local(&entry.start),
+            "stop_local": local(&entry.stop),
+            "seconds": duration.num_seconds(),
+            "duration": format_hours_minutes(duration),
+            "account": entry.account,
+            "accounts": accounts,
+            "tags": entry.tags,
+            "billable": entry.billable,
+            "category": entry.category,
+            "description": entry.description,
+            "sources": sources,
+            "note": note,
+        });
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&details).expect("details can be serialized")
+        );
+        return Ok(());
+    }
+
+    let utc = |time: &DateTime<Utc>| time.format("%Y-%m-%dT%H:%M:%SZ").to_string();
+    let local = |time: &DateTime<Utc>| {
+        time.with_timezone(&Local)
+            .format("%Y-%m-%d %H:%M:%S %:z")
+            .to_string()
+    };
+    let tags: Vec<String> = entry.tags.iter().map(|tag| format!("#{}", tag)).collect();
+    let mut fields = vec![
+        ("ID", id.clone()),
+        (
+            "Start",
+            format!("{}  {}", local(&entry.start), style.dim(&utc(&entry.start))),
+        ),
+        (
+            "Stop",
+            format!("{}  {}", local(&entry.stop), style.dim(&utc(&entry.stop))),
+        ),
+        (
+            "Duration",
+            format!(
+                "{}  {}",
+                format_hours_minutes(duration),
+                style.dim(&format!("{} seconds", duration.num_seconds()))
+            ),
+        ),
+        ("Account", accounts.join(" > ")),
+        ("Tags", tags.join(" ")),
+        (
+            "Billable",
+            if entry.billable { "yes" } else { "no" }.to_string(),
+        ),
+        ("Category", entry.category.clone().unwrap_or_default()),
+        ("Description", entry.description.clone().unwrap_or_default()),
+    ];
+    if !sources.is_empty() {
+        fields.push(("Imported from", sources.join(", ")));
+    }
+    for (name, value) in fields {
+        let line = format!("{}  {}", style.bold(&format!("{:<13}", name)), value);
+        println!("{}", line.trim_end());
+    }
+    if let Some(note) = note {
+        println!("\n{}", note.trim_end());
+    }
+    Ok(())
+}
@@ -0,0 +1,30 @@
+use super::{auto_commit, CliError, Context, Result, ResultExt};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    /// Only check whether the file is in order, failing if it is not
+    #[structopt(long)]
+    pub check: bool,
+}
+
+pub fn run(ctx: Context, args: &Args) -> Result<()> {
+    let Context {
+        config, storage, ..
+    } = ctx;
+    let Args { check } = args;
+    let unsorted = storage
+        .sort(*check)
+        .context("could not sort the entries file")?;
+
+    if *check {
+        if unsorted {
+            eprintln!("the entries are not in chronological order");
+            return Err(CliError::exit(1));
+        }
+    } else if unsorted {
+        println!("Sorted the entries");
+        auto_commit(&storage, &config, "Sort")?;
+    }
+    Ok(())
+}
@@ -0,0 +1,38 @@
+use super::{auto_commit, parse_datetime, Context, Result};
+use chrono::{DateTime, Utc};
+use structopt::StructOpt;
+use timetracker::selector::Selector;
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    /// `last` for the last entry, or the ID of the entry, at least its first 4 characters
+    pub selector: Selector,
+
+    /// The time to split at, e.g. `12:00` or `1h ago`
+    #[structopt(long, parse(try_from_str = parse_datetime))]
+    pub at: DateTime<Utc>,
+
+    /// Record the time after the split for this account instead
+    #[structopt(long)]
+    pub account: Option<String>,
+}
+
+pub fn run(ctx: Context, args: &Args) -> Result<()> {
+    let Context {
+        config, storage, ..
+    } = ctx;
+    let Args {
+        selector,
+        at,
+        account,
+    } = args;
+    let (first, second) = storage.split_entry(selector, *at, |entry| {
+        if let Some(account) = account {
+            entry.account = config.expand_alias(account).into();
+        }
+    })?;
+    println!("{}\n{}", first, second);
+
+    auto_commit(&storage, &config, &format!("Split {}", first.account))?;
+    Ok(())
+}
@@ -0,0 +1,48 @@
+use super::{CliError, Context, Result, ResultExt};
+use crate::output;
+use structopt::StructOpt;
+use timetracker::sql;
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    /// The query, e.g. `SELECT account, SUM(seconds) / 3600.0 FROM entries GROUP BY account`
+    pub query: String,
+
+    #[structopt(long, default_value = "table", possible_values = &["table", "csv"])]
+    pub format: String,
+}
+
+pub fn run(ctx: Context, args: &Args) -> Result<()> {
+    let Context { style, storage, .. } = ctx;
+    let Args { query, format } = args;
+    let entries = storage.entries().context("could not read entries")?;
+    let table = sql::query(&entries, query).map_err(|err| CliError::new(format!("{}", err)))?;
+
+    if format == "csv" {
+        println!("{}", table.to_csv());
+        return Ok(());
+    }
+    let widths: Vec<usize> = (0..table.columns.len())
+        .map(|index| {
+            output::column_width(
+                std::iter::once(&table.columns[index])
+                    .chain(table.rows.iter().map(|row| &row[index]))
+                    .map(String::as_str),
+            )
+        })
+        .collect();
+    let line = |row: &[String]| {
+        row.iter()
+            .zip(&widths)
+            .map(|(value, width)| format!("{:<width$}", value, width = width))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+    println!("{}", style.bold(&line(&table.columns)));
+    for row in &table.rows {
+        println!("{}", line(row));
+    }
+    Ok(())
+}
@@ -0,0 +1,439 @@
+use super::{
+    auto_commit, check_category, current_repository, entries_overlapping, known_accounts,
+    parse_datetime, parse_meta_arg, pick_account, print_json, prompt_description,
+    refuse_before_start, refuse_future, update_slack_status, CliError, Context, Result, ResultExt,
+    EXIT_OVER_LIMIT,
+};
+use chrono::{DateTime, Duration, Local, Utc};
+use std::collections::BTreeMap;
+use std::io::{self, IsTerminal, Write};
+use structopt::StructOpt;
+use timetracker::accounts::Accounts;
+use timetracker::config::{GitDescription, OtherRunning};
+use timetracker::duration::format_hours_minutes;
+use timetracker::favorites::Favorites;
+use timetracker::project::{self, Project};
+use timetracker::report;
+use timetracker::storage::{Storage, StorageError};
+use timetracker::suggest;
+use timetracker::sync::Repository;
+use timetracker::templates;
+use timetracker::timesheet::Timesheet;
+use timetracker::{is_valid_tag, Entry, RunningEntry};
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    /// The account, or `@<name>` for the account, tags and description of a favorite. By
+    /// default the account of the `.tt` project file in the current directory or above it, or
+    /// else one of the accounts usually started at this time, picked by number
+    pub account: Option<String>,
+
+    /// Take the account from the git repository of the current directory, and the
+    /// description from its branch or last commit, see `[git]` in the config
+    #[structopt(long, conflicts_with = "account")]
+    pub from_git: bool,
+
+    /// Pick the account by searching the accounts tracked before and those in accounts.toml
+    #[structopt(long, conflicts_with_all = &["account", "from-git"])]
+    pub pick: bool,
+
+    #[structopt(short, long = "tag")]
+    pub tags: Vec<String>,
+
+    /// Mark the entry as billable
+    #[structopt(short, long)]
+    pub billable: bool,
+
+    /// The kind of work, e.g. `meetings`, by default the category of the account
+    #[structopt(short, long)]
+    pub category: Option<String>,
+
+    #[structopt(short, long)]
+    pub description: Option<String>,
+
+    /// Metadata for integrations, e.g. `ticket=PROJ-12`, may be given more than once
+    #[structopt(long, parse(try_from_str = parse_meta_arg), number_of_values = 1)]
+    pub meta: Vec<(String, String)>,
+
+    /// Use the description template with this name from the config
+    #[structopt(long, conflicts_with = "description")]
+    pub template: Option<String>,
+
+    /// Prompt for a description if none is given
+    #[structopt(long)]
+    pub ask_description: bool,
+
+    /// Start at this time instead of now, e.g. `15m ago` or `09:30`
+    #[structopt(long, parse(try_from_str = parse_datetime))]
+    pub at: Option<DateTime<Utc>>,
+
+    /// Stop the entries already running, instead of asking
+    #[structopt(long, conflicts_with = "keep-others")]
+    pub stop_others: bool,
+
+    /// Keep the entries already running, instead of asking
+    #[structopt(long)]
+    pub keep_others: bool,
+
+    /// Print the started entry as JSON on a single line instead of any messages
+    #[structopt(long)]
+    pub porcelain: bool,
+
+    /// Start the entry even if the daily limit of the account is reached
+    #[structopt(long = "override")]
+    pub override_limit: bool,
+
+    /// Start the entry even if the account is archived, or at a time given with --at in the
+    /// future
+    #[structopt(long)]
+    pub force: bool,
+}
+
+/// Asks a yes or no question on the terminal, where anything but yes counts as no.
+fn confirm(question: &str) -> Result<bool> {
+    eprint!("{} [y/N] ", question);
+    io::stderr()
+        .flush()
+        .context("could not write to standard error")?;
+    let mut answer = String::new();
+    io::stdin()
+        .read_line(&mut answer)
+        .context("could not read standard input")?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// The most accounts suggested when starting without one.
+const SUGGESTIONS: usize = 5;
+
+/// The accounts most likely to be started at the time by the entries of the last 90 days, see
+/// `suggest`, leaving out archived and running accounts.
+fn suggested_accounts(
+    storage: &Storage,
+    accounts: &Accounts,
+    now: DateTime<Utc>,
+) -> Result<Vec<String>> {
+    let entries = storage
+        .entries_since(now - Duration::days(90), |_| true)
+        .context("could not read entries")?;
+    let running: Vec<String> = storage
+        .running_entries()
+        .context("could not read running entries")?
+        .into_iter()
+        .map(|entry| entry.account.to_string())
+        .collect();
+    Ok(suggest::suggest(&entries, &now.with_timezone(&Local))
+        .into_iter()
+        .map(|suggestion| suggestion.account)
+        .filter(|account| !accounts.is_archived(account) && !running.contains(account))
+        .take(SUGGESTIONS)
+        .collect())
+}
+
+/// The description of an entry started in the repository, as configured.
+fn git_description(repository: &Repository, from: GitDescription) -> Result<Option<String>> {
+    let description = match from {
+        GitDescription::Branch => repository.current_branch(),
+        GitDescription::Commit => repository.last_commit_subject(),
+        GitDescription::None => Ok(None),
+    };
+    description.context("could not read the git repository")
+}
+
+pub fn run(ctx: Context, args: &Args) -> Result<()> {
+    let Context {
+        dry_run,
+        favorites_path,
+        config,
+        accounts,
+        storage,
+        ..
+    } = ctx;
+    let Args {
+        account,
+        from_git,
+        pick,
+        tags: tags_arg,
+        billable,
+        category,
+        description,
+        meta: meta_arg,
+        template,
+        ask_description,
+        at,
+        stop_others,
+        keep_others,
+        porcelain,
+        override_limit,
+        force,
+    } = args;
+    if let Some(tag) = tags_arg.iter().find(|tag| !is_valid_tag(tag)) {
+        return Err(CliError::new(format!(r#"invalid tag "{}""#, tag)));
+    }
+    check_category(category.as_deref())?;
+    if let Some(at) = at.filter(|_| !*force) {
+        refuse_future("start", at)?;
+    }
+    let now = at.unwrap_or_else(timetracker::now);
+    let mut description = description.clone();
+    let mut project = None;
+    let account = if *from_git {
+        let repository = current_repository()?;
+        if description.is_none() {
+            description = git_description(&repository, config.git.description)?;
+        }
+        config.git.account(repository.root())
+    } else if *pick {
+        match pick_account(
+            "Start which account?",
+            &known_accounts(&storage, &accounts)?,
+        )? {
+            Some(account) => account,
+            None => {
+                eprintln!("Nothing was started");
+                return Ok(());
+            }
+        }
+    } else if let Some(account) = account {
+        account.clone()
+    } else {
+        let dir = std::env::current_dir().context("could not get the current directory")?;
+        match Project::find(&dir).context("could not read the project file")? {
+            Some((path, found)) => {
+                log::debug!("using the project file {}", path.display());
+                let account = found.account.clone();
+                project = Some(found);
+                account
+            }
+            // Suggest the accounts usually started at this time instead
+            None => {
+                let suggestions = if io::stdin().is_terminal() {
+                    suggested_accounts(&storage, &accounts, now)?
+                } else {
+                    Vec::new()
+                };
+                if suggestions.is_empty() {
+                    return Err(CliError::new(format!(
+                        "no account given, and there is no {} project file in {} or above it",
+                        project::FILE_NAME,
+                        dir.display()
+                    )));
+                }
+                match super::pick("Start which account?", &suggestions)? {
+                    Some(index) => suggestions[index].clone(),
+                    None => {
+                        eprintln!("Nothing was started");
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    };
+
+    // Favorites fill in what is not given on the command line
+    let favorite = match account.strip_prefix('@') {
+        Some(name) => Some(
+            Favorites::load(&favorites_path)
+                .context("could not read favorites")?
+                .get(name)
+                .ok_or_else(|| CliError::new(format!(r#"there is no favorite named "{}""#, name)))?
+                .start(now),
+        ),
+        None => None,
+    };
+    let (account, mut tags, mut billable, category, mut description, mut meta) = match favorite {
+        Some(favorite) => (
+            favorite.account.to_string(),
+            favorite.tags,
+            favorite.billable || *billable,
+            category.clone().or(favorite.category),
+            description.or(favorite.description),
+            favorite.meta,
+        ),
+        None => {
+            // The metadata of the account fills in what the favorite would
+            let account = config.expand_alias(&account);
+            let info = accounts.get(&account).cloned().unwrap_or_default();
+            let category = category
+                .clone()
+                .or_else(|| accounts.category(&account).map(str::to_string));
+            (
+                account,
+                info.tags,
+                info.billable || *billable,
+                category,
+                description,
+                BTreeMap::new(),
+            )
+        }
+    };
+    // The project file fills in what the command line and the account do not
+    if let Some(project) = project {
+        billable |= project.billable;
+        description = description.or(project.description);
+        for tag in project.tags {
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+    }
+    for tag in tags_arg {
+        if !tags.contains(tag) {
+            tags.push(tag.clone());
+        }
+    }
+    meta.extend(meta_arg.iter().cloned());
+    let account = &account;
+    if accounts.is_archived(account) && !*force {
+        return Err(CliError::new(format!(
+            "{} is archived; start it anyway with --force, or bring it back with \
+             `tt accounts --unarchive`",
+            account
+        )));
+    }
+    if let Some(name) = template {
+        let template = config
+            .templates
+            .get(name)
+            .ok_or_else(|| CliError::new(format!(r#"there is no template named "{}""#, name)))?;
+        description = Some(templates::expand(
+            template,
+            account,
+            &now.with_timezone(&Local),
+        ));
+    }
+    if description.is_none() && (*ask_description || config.always_ask_description) {
+        description = prompt_description(account)?;
+    }
+
+    if let Some((limited, limit)) = accounts.daily_limit(account) {
+        let day = report::day_range(report::local_date(now, &Local), &Local);
+        let entries = entries_overlapping(&storage, now, Some(day.start), Some(day.end))?;
+        let tracked = Timesheet::new(entries)
+            .filter_by_account(limited)
+            .between(day)
+            .total_duration();
+        if !*override_limit {
+            check_daily_limit(limited, tracked, limit, config.start.warn_over_daily_limit)?;
+        }
+    }
+
+    // A second running entry is usually a forgotten stop
+    let others = storage
+        .running_entries()
+        .context("could not read running entries")?;
+    if !others.is_empty() && others.iter().all(|other| &*other.account != account) {
+        let policy = if *stop_others {
+            OtherRunning::Stop
+        } else if *keep_others {
+            OtherRunning::Keep
+        } else {
+            config.start.other_running
+        };
+        let stop = match policy {
+            OtherRunning::Stop => true,
+            OtherRunning::Keep => false,
+            OtherRunning::Ask => {
+                let accounts: Vec<&str> = others.iter().map(|other| &*other.account).collect();
+                io::stdin().is_terminal()
+                    && confirm(&format!("Stop {} first?", accounts.join(", ")))?
+            }
+        };
+        if stop {
+            let split_days = config.stop.split_days;
+            let stopped = storage.stop_all_with(now, |entry| -> Result<Vec<Entry>> {
+                refuse_before_start(&entry)?;
+                Ok(if split_days {
+                    report::split_at_midnight(&entry, &Local)
+                } else {
+                    vec![entry]
+                })
+            })?;
+            for entries in stopped {
+                let mut stopped = entries[0].clone();
+                stopped.stop = entries[entries.len() - 1].stop;
+                if !dry_run {
+                    if let Err(err) = config.hooks.stopped(&stopped) {
+                        log::error!("{}", err);
+                    }
+                }
+                if config
+                    .stop
+                    .max_running_duration
+                    .is_some_and(|max| (stopped.stop - stopped.start) > max)
+                {
+                    log::warn!(
+                        "{} ran for {}, it may have been forgotten",
+                        stopped.account,
+                        format_hours_minutes(stopped.stop - stopped.start)
+                    );
+                }
+                if !*porcelain {
+                    println!("Stopped {}", stopped.account);
+                }
+            }
+        }
+    }
+
+    let mut builder = RunningEntry::builder()
+        .start(now)
+        .account(account.clone())
+        .tags(tags)
+        .billable(billable)
+        .category_if_any(category)
+        .description_if_any(description)
+        .user_if_any(config.team.user());
+    for (key, value) in meta {
+        builder = builder.meta(key, value);
+    }
+    let running_entry = storage.start(builder.build().map_err(StorageError::from)?)?;
+    if *porcelain {
+        print_json(&running_entry);
+    }
+
+    if !dry_run {
+        if let Err(err) = config.hooks.started(&running_entry) {
+            log::error!("{}", err);
+        }
+        update_slack_status(&storage, &config);
+    }
+
+    auto_commit(&storage, &config, &format!("Start {}", account))?;
+    Ok(())
+}
+
+/// Fails once the time tracked today reaches the daily limit of the account, or only warns if
+/// configured to.
+fn check_daily_limit(account: &str, tracked: Duration, limit: Duration, warn: bool) -> Result<()> {
+    if tracked < limit {
+        return Ok(());
+    }
+    let reached = format!(
+        "{} has been tracked for {} today, reaching its daily limit of {}",
+        account,
+        format_hours_minutes(tracked),
+        format_hours_minutes(limit)
+    );
+    if !warn {
+        return Err(CliError::with_code(
+            EXIT_OVER_LIMIT,
+            format!("{}; start it anyway with --override", reached),
+        ));
+    }
+    eprintln!("warning: {}", reached);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::EXIT_OVER_LIMIT;
+
+    #[test]
+    fn refuse_accounts_over_their_daily_limit_unless_warning() {
+        let limit = Duration::hours(4);
+        assert!(check_daily_limit("dev", Duration::hours(3), limit, false).is_ok());
+        let err = check_daily_limit("dev", limit, limit, false).unwrap_err();
+        assert_eq!(err.code(), EXIT_OVER_LIMIT);
+        assert!(check_daily_limit("dev", Duration::hours(5), limit, true).is_ok());
+    }
+}
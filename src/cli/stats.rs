@@ -0,0 +1,54 @@
+use super::{parse_datetime, within, Context, Result, ResultExt};
+use crate::output;
+use chrono::{DateTime, Local, Utc};
+use structopt::StructOpt;
+use timetracker::duration::format_hours_minutes;
+use timetracker::stats;
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    /// Only count time tracked at or after this time, e.g. `monday` or `2021-07-01`
+    #[structopt(long, parse(try_from_str = parse_datetime))]
+    pub since: Option<DateTime<Utc>>,
+
+    /// Only count time tracked before this time
+    #[structopt(long, parse(try_from_str = parse_datetime))]
+    pub until: Option<DateTime<Utc>>,
+}
+
+pub fn run(ctx: Context, args: &Args) -> Result<()> {
+    let Context { style, storage, .. } = ctx;
+    let Args { since, until } = args;
+    let timesheet = within(
+        &storage.timesheet().context("could not read entries")?,
+        *since,
+        *until,
+    );
+
+    println!(
+        "Average per day:      {}",
+        format_hours_minutes(stats::average_daily(&timesheet, &Local))
+    );
+    println!(
+        "Average per entry:    {}",
+        format_hours_minutes(stats::average_session(&timesheet))
+    );
+    println!(
+        "Longest streak:       {} days",
+        stats::longest_streak(&timesheet, &Local)
+    );
+    let top = stats::top_account_by_weekday(&timesheet, &Local);
+    if !top.is_empty() {
+        println!("Most tracked:");
+        let width = output::column_width(top.iter().map(|(_, account, _)| account.as_str()));
+        for (weekday, account, total) in &top {
+            println!(
+                "  {}  {}  {:>8}",
+                weekday,
+                style.account(account, width),
+                format_hours_minutes(*total)
+            );
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,224 @@
+use super::{
+    alert_marker, budget_burn, entries_until, exit_interrupted, from_daemon, goal_progress,
+    handle_interrupts, sleep_unless_interrupted, CliError, Context, Result, ResultExt,
+};
+use crate::output;
+use chrono::{DateTime, Duration, Local, Utc};
+use structopt::StructOpt;
+use timetracker::accounts::Accounts;
+use timetracker::check;
+use timetracker::config::Config;
+use timetracker::duration::{format_hours_minutes, parse_duration};
+use timetracker::prompt;
+use timetracker::report;
+use timetracker::status::Status;
+use timetracker::storage::Storage;
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    /// Print a single line that is kept stable for scripts, `<account>|<elapsed seconds>` of
+    /// the latest running entry or `idle`
+    #[structopt(long, conflicts_with = "watch")]
+    pub short: bool,
+
+    /// Keep showing the status, redrawing it after every interval
+    #[structopt(long)]
+    pub watch: bool,
+
+    /// The time between redraws when watching, e.g. `1s` or `1m`
+    #[structopt(long, default_value = "1s", parse(try_from_str = parse_duration))]
+    pub interval: Duration,
+
+    /// Write the status as JSON for other programs, in seconds, see `tt schema status`. Not with
+    /// --short or --watch
+    #[structopt(long, default_value = "text", possible_values = &["text", "json"])]
+    pub format: String,
+}
+
+/// The time tracked today, including the running entries up to now.
+fn today_total(storage: &Storage, now: DateTime<Utc>) -> Result<Duration> {
+    let today = now.with_timezone(&Local).date().naive_local();
+    Ok(report::totals_by_day(&entries_until(storage, now)?, &Local)
+        .get(&today)
+        .copied()
+        .unwrap_or_else(Duration::zero))
+}
+
+/// The local time something started at, with the date unless it started today.
+fn format_since(start: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let start = start.with_timezone(&Local);
+    if start.date() == now.with_timezone(&Local).date() {
+        start.format("%H:%M").to_string()
+    } else {
+        start.format("%Y-%m-%d %H:%M").to_string()
+    }
+}
+
+/// Prints the running entries, the time tracked today, exceeded budgets and budgets running out
+/// before their deadlines.
+fn print_status(
+    storage: &Storage,
+    config: &Config,
+    accounts: &Accounts,
+    style: &output::Style,
+) -> Result<()> {
+    let now = timetracker::now();
+    let running_entries = storage
+        .running_entries()
+        .context("could not read running entries")?;
+    if running_entries.is_empty() {
+        println!("No running entries");
+    }
+    let width = output::column_width(running_entries.iter().map(|entry| &*entry.account));
+    let starts: Vec<String> = running_entries
+        .iter()
+        .map(|entry| format_since(entry.start, now))
+        .collect();
+    let starts_width = output::column_width(starts.iter().map(String::as_str));
+    for (entry, start) in running_entries.iter().zip(&starts) {
+        let elapsed = format!("{:>8}", format_hours_minutes(now - entry.start));
+        let mut details: Vec<String> = entry.tags.iter().map(|tag| format!("#{}", tag)).collect();
+        details.extend(entry.description.clone());
+        let used = "Running: ".len() + width + "  since ".len() + starts_width + 2;
+        let used = used + elapsed.chars().count();
+        let details = output::fit(&details.join(" "), used + 2);
+        println!(
+            "Running: {}  since {:<starts_width$}  {}{}",
+            style.account(&entry.account, width),
+            start,
+            elapsed,
+            if details.is_empty() {
+                String::new()
+            } else {
+                format!("  {}", style.dim(&details))
+            }
+        );
+    }
+    if let Some(since) =
+        check::concurrent_since(&running_entries).filter(|_| !config.allow_concurrent)
+    {
+        let warning = format!(
+            "warning: {} entries running at once since {} ({}), did you forget to stop one?",
+            running_entries.len(),
+            format_since(since, now),
+            format_hours_minutes(now - since)
+        );
+        println!("{}", style.bold(&warning));
+    }
+    if let Some(max) = config.stop.max_running_duration {
+        for entry in check::long_running(&running_entries, now, max) {
+            println!(
+                "warning: {} has been running for longer than {}, did you forget to stop it?",
+                entry.account,
+                format_hours_minutes(max)
+            );
+        }
+    }
+
+    let today_total = today_total(storage, now)?;
+    println!(
+        "Today: {}",
+        style.bold(&style.threshold(
+            &format_hours_minutes(today_total),
+            config.thresholds.daily_level(today_total)
+        ))
+    );
+
+    for progress in goal_progress(storage, config)? {
+        if progress.is_exceeded() {
+            println!(
+                "warning: {} has exceeded its {} budget ({} of {})",
+                progress.goal.scope,
+                progress.goal.period,
+                format_hours_minutes(progress.tracked),
+                format_hours_minutes(progress.goal.target)
+            );
+        }
+    }
+    // At the rate `burn` projects with by default
+    for burn in budget_burn(storage, accounts, 14)? {
+        if let Some(runs_out) = burn.runs_out.filter(|_| burn.is_at_risk()) {
+            println!(
+                "warning: {} is on track to use up its budget of {} by {}, before its deadline {}",
+                burn.account,
+                format_hours_minutes(burn.budget),
+                runs_out,
+                burn.deadline
+            );
+        }
+    }
+    Ok(())
+}
+
+pub fn run(ctx: Context, args: &Args) -> Result<()> {
+    let Context {
+        daemon_running_path,
+        config,
+        accounts,
+        style,
+        storage,
+        ..
+    } = ctx;
+    if args.format == "json" {
+        if args.short || args.watch {
+            return Err(CliError::new(
+                "--format json cannot be used with --short or --watch",
+            ));
+        }
+        let now = timetracker::now();
+        let running_entries = storage
+            .running_entries()
+            .context("could not read running entries")?;
+        let status = Status::new(
+            running_entries,
+            today_total(&storage, now)?,
+            &goal_progress(&storage, &config)?,
+            &budget_burn(&storage, &accounts, 14)?,
+        );
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&status).expect("the status can be serialized")
+        );
+        return Ok(());
+    }
+    match args {
+        Args { short: true, .. } => {
+            let running_entries = match from_daemon(&daemon_running_path) {
+                Some(running_entries) => running_entries,
+                None => storage
+                    .running_entries()
+                    .context("could not read running entries")?,
+            };
+            let status = prompt::format_short_status(&running_entries, timetracker::now());
+            match alert_marker(&storage, &config, &accounts) {
+                Some(marker) => println!("{} {}", status, marker),
+                None => println!("{}", status),
+            }
+        }
+        Args {
+            short: false,
+            watch,
+            interval,
+            ..
+        } => {
+            if *watch {
+                // A redraw is finished before exiting, so no color is left on
+                handle_interrupts();
+            }
+            loop {
+                if *watch {
+                    // Clear the screen and move the cursor to the top left corner
+                    print!("\x1b[2J\x1b[H");
+                }
+                print_status(&storage, &config, &accounts, &style)?;
+                if !watch {
+                    break;
+                }
+                if !sleep_unless_interrupted(*interval) {
+                    return Err(exit_interrupted());
+                }
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,231 @@
+use super::{
+    auto_commit, current_repository, edit_text, parse_datetime, pick, pick_account, print_json,
+    prompt_description, refuse_before_start, refuse_future, update_slack_status, CliError, Context,
+    Result, ResultExt, EXIT_TOO_LONG,
+};
+use crate::output;
+use chrono::{DateTime, Duration, Local, Utc};
+use std::io::{self, IsTerminal};
+use structopt::StructOpt;
+use timetracker::duration::format_hours_minutes;
+use timetracker::report;
+use timetracker::Entry;
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    pub account: Option<String>,
+
+    /// Stop every running entry at the same time
+    #[structopt(long, conflicts_with = "account")]
+    pub all: bool,
+
+    /// Stop the entry for the account of the git repository of the current directory
+    #[structopt(long, conflicts_with_all = &["account", "all"])]
+    pub from_git: bool,
+
+    /// Pick the entry to stop by searching the accounts of the running entries
+    #[structopt(long, conflicts_with_all = &["account", "all", "from-git"])]
+    pub pick: bool,
+
+    /// Record an entry spanning midnight as one entry per day
+    #[structopt(long)]
+    pub split_days: bool,
+
+    /// Prompt for a description, keeping the current one if the answer is empty
+    #[structopt(long)]
+    pub ask_description: bool,
+
+    /// Edit the description in $VISUAL or $EDITOR before recording the entry, clearing it if
+    /// the text is left empty
+    #[structopt(long, conflicts_with_all = &["ask-description", "all", "porcelain"])]
+    pub edit_description: bool,
+
+    /// Stop at this time instead of now, e.g. `15m ago` or `17:00`
+    #[structopt(long, parse(try_from_str = parse_datetime))]
+    pub at: Option<DateTime<Utc>>,
+
+    /// Print each stopped entry as JSON on a line of its own instead of any messages
+    #[structopt(long)]
+    pub porcelain: bool,
+
+    /// Stop entries running for longer than `max_running_duration` under `[stop]`, or at a
+    /// time given with --at in the future
+    #[structopt(long)]
+    pub force: bool,
+}
+
+pub fn run(ctx: Context, args: &Args) -> Result<()> {
+    let Context {
+        dry_run,
+        config,
+        accounts: _,
+        style,
+        storage,
+        ..
+    } = ctx;
+    let Args {
+        account,
+        all,
+        from_git,
+        pick: pick_running,
+        split_days,
+        ask_description,
+        edit_description,
+        at,
+        porcelain,
+        force,
+    } = args;
+    let account = if *from_git {
+        Some(config.git.account(current_repository()?.root()))
+    } else if *pick_running {
+        let mut running: Vec<String> = Vec::new();
+        for entry in storage
+            .running_entries()
+            .context("could not read running entries")?
+        {
+            if !running.iter().any(|account| *account == *entry.account) {
+                running.push(entry.account.to_string());
+            }
+        }
+        match pick_account("Stop which account?", &running)? {
+            Some(account) => Some(account),
+            None => {
+                eprintln!("Nothing was stopped");
+                return Ok(());
+            }
+        }
+    } else {
+        account
+            .as_deref()
+            .map(|account| config.expand_alias(account))
+    };
+    let split_days = *split_days || config.stop.split_days;
+    let finish = |mut entry: Entry| -> Result<Vec<Entry>> {
+        refuse_before_start(&entry)?;
+        // Nothing is written unless every stopped entry is finished
+        refuse_too_long(&entry, config.stop.max_running_duration.filter(|_| !*force))?;
+        if *ask_description || (config.always_ask_description && entry.description.is_none()) {
+            if let Some(description) = prompt_description(&entry.account)? {
+                entry.description = Some(description);
+            }
+        }
+        if *edit_description {
+            let edited = edit_text(
+                "description",
+                &entry.description.clone().unwrap_or_default(),
+            )?;
+            // Descriptions are on the line of the entry, so lines are joined
+            let edited = edited.split_whitespace().collect::<Vec<_>>().join(" ");
+            entry.description = Some(edited).filter(|edited| !edited.is_empty());
+        }
+        Ok(if split_days {
+            report::split_at_midnight(&entry, &Local)
+        } else {
+            vec![entry]
+        })
+    };
+    if let Some(at) = at.filter(|_| !*force) {
+        refuse_future("stop", at)?;
+    }
+    let now = at.unwrap_or_else(timetracker::now);
+    // With several entries running, ask which one to stop instead of failing
+    let mut account = account;
+    if account.is_none() && !*all && !*porcelain && io::stdin().is_terminal() {
+        let running = storage
+            .running_entries()
+            .context("could not read running entries")?;
+        if running.len() > 1 {
+            let width = output::column_width(running.iter().map(|entry| &*entry.account));
+            let choices: Vec<String> = running
+                .iter()
+                .map(|entry| {
+                    format!(
+                        "{}  since {}  {:>8}",
+                        style.account(&entry.account, width),
+                        entry.start.with_timezone(&Local).format("%H:%M"),
+                        format_hours_minutes(now - entry.start)
+                    )
+                })
+                .collect();
+            match pick("Stop which entry?", &choices)? {
+                Some(index) => account = Some(running[index].account.to_string()),
+                None => {
+                    eprintln!("Nothing was stopped");
+                    return Ok(());
+                }
+            }
+        }
+    }
+    let recorded = if *all {
+        storage.stop_all_with(now, finish)
+    } else {
+        storage
+            .stop_with(account.as_deref(), now, finish)
+            .map(|entries| vec![entries])
+    }?;
+
+    let mut accounts = Vec::new();
+    for entries in recorded {
+        // Split entries are reported as the single entry that was stopped
+        let mut stopped = entries[0].clone();
+        stopped.stop = entries[entries.len() - 1].stop;
+        if !dry_run {
+            if let Err(err) = config.hooks.stopped(&stopped) {
+                log::error!("{}", err);
+            }
+        }
+        if *porcelain {
+            print_json(&stopped);
+        }
+        accounts.push(stopped.account);
+    }
+    if !dry_run {
+        update_slack_status(&storage, &config);
+    }
+
+    auto_commit(&storage, &config, &format!("Stop {}", accounts.join(", ")))?;
+    Ok(())
+}
+
+/// Fails if the entry has been running for longer than the maximum, which is usually a forgotten
+/// stop.
+fn refuse_too_long(entry: &Entry, max: Option<Duration>) -> Result<()> {
+    if let Some(max) = max.filter(|max| entry.stop - entry.start > *max) {
+        return Err(CliError::with_code(
+            EXIT_TOO_LONG,
+            format!(
+                "{} has been running for {}, longer than the maximum of {}; stop it with \
+                 --force, or at the time it ended with --at",
+                entry.account,
+                format_hours_minutes(entry.stop - entry.start),
+                format_hours_minutes(max)
+            ),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::EXIT_TOO_LONG;
+
+    #[test]
+    fn refuse_entries_running_for_longer_than_the_maximum() {
+        let entry: Entry = "2021-07-01T09:00:00Z 2021-07-01T19:00:00Z dev"
+            .parse()
+            .unwrap();
+        assert!(refuse_too_long(&entry, None).is_ok());
+        assert!(refuse_too_long(&entry, Some(Duration::hours(10))).is_ok());
+
+        let err = refuse_too_long(&entry, Some(Duration::hours(8))).unwrap_err();
+        assert_eq!(err.code(), EXIT_TOO_LONG);
+        assert_eq!(
+            err.message(),
+            Some(
+                "dev has been running for 10h 00m, longer than the maximum of 8h 00m; stop it with \
+                 --force, or at the time it ended with --at"
+            )
+        );
+    }
+}
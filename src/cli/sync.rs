@@ -0,0 +1,44 @@
+use super::{data_repository, CliError, Context, Result, ResultExt};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    #[structopt(long)]
+    pub no_pull: bool,
+
+    #[structopt(long)]
+    pub no_push: bool,
+}
+
+pub fn run(ctx: Context, args: &Args) -> Result<()> {
+    let Context {
+        dry_run, storage, ..
+    } = ctx;
+    let Args { no_pull, no_push } = args;
+    let repository = data_repository(&storage)
+        .ok_or_else(|| CliError::new("the data directory is not a git repository"))?;
+    if dry_run {
+        println!("Would commit the data files, pull and push");
+        return Ok(());
+    }
+
+    // Commit any changes made since the last command, e.g. manual edits
+    repository
+        .commit(&[storage.entries_path(), storage.running_path()], "Sync")
+        .context("could not commit changes")?;
+
+    if !repository
+        .has_remote()
+        .context("could not list git remotes")?
+    {
+        log::info!("the data repository has no remotes, skipping pull and push");
+        return Ok(());
+    }
+    if !no_pull {
+        repository.pull().context("could not pull changes")?;
+    }
+    if !no_push {
+        repository.push().context("could not push changes")?;
+    }
+    Ok(())
+}
@@ -0,0 +1,94 @@
+use super::{auto_commit, parse_datetime, CliError, Context, Result, ResultExt};
+use chrono::{DateTime, Utc};
+use structopt::StructOpt;
+use timetracker::{is_valid_tag, report};
+
+#[derive(Debug, StructOpt)]
+pub enum TagCommand {
+    /// Adds the tag to the matching entries which do not have it yet
+    Add(Args),
+    /// Removes the tag from the matching entries
+    Remove(Args),
+}
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    pub tag: String,
+
+    /// Only change entries for the account and its sub-accounts, can be given more than once
+    #[structopt(long = "account")]
+    pub accounts: Vec<String>,
+
+    /// Only change entries starting at or after this time, e.g. `2021-07-01` or `monday`
+    #[structopt(long, parse(try_from_str = parse_datetime))]
+    pub from: Option<DateTime<Utc>>,
+
+    /// Only change entries starting before this time
+    #[structopt(long, parse(try_from_str = parse_datetime))]
+    pub to: Option<DateTime<Utc>>,
+}
+
+pub fn run(ctx: Context, command: &TagCommand) -> Result<()> {
+    let Context {
+        dry_run,
+        config,
+        storage,
+        ..
+    } = ctx;
+    let (add, args) = match command {
+        TagCommand::Add(args) => (true, args),
+        TagCommand::Remove(args) => (false, args),
+    };
+    let Args {
+        tag,
+        accounts,
+        from,
+        to,
+    } = args;
+    if !is_valid_tag(tag) {
+        return Err(CliError::new(format!(r#"invalid tag "{}""#, tag)));
+    }
+    let accounts: Vec<String> = accounts
+        .iter()
+        .map(|account| config.expand_alias(account))
+        .collect();
+
+    let changed = storage
+        .update_entries(|entry| {
+            let matches = (accounts.is_empty()
+                || accounts
+                    .iter()
+                    .any(|account| report::is_within_account(&entry.account, account)))
+                && from.is_none_or(|from| entry.start >= from)
+                && to.is_none_or(|to| entry.start < to);
+            if !matches {
+                return false;
+            }
+            let tagged = entry.tags.contains(tag);
+            if add && !tagged {
+                entry.tags.push(tag.clone());
+            } else if !add && tagged {
+                entry.tags.retain(|other| other != tag);
+            }
+            add != tagged
+        })
+        .context("could not change the entries")?;
+    for entry in &changed {
+        println!("{}", entry);
+    }
+    let verb = match (add, dry_run) {
+        (true, false) => "Tagged",
+        (true, true) => "Would tag",
+        (false, false) => "Untagged",
+        (false, true) => "Would untag",
+    };
+    println!("{} {} entries", verb, changed.len());
+    if !changed.is_empty() {
+        let message = match add {
+            true => format!("Tag entries #{}", tag),
+            false => format!("Untag entries #{}", tag),
+        };
+        auto_commit(&storage, &config, &message)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,122 @@
+use super::{account_name, Context, Result, ResultExt};
+use crate::output;
+use chrono::{Duration, Local};
+use structopt::StructOpt;
+use timetracker::duration::DurationFormat;
+use timetracker::report;
+use timetracker::timesheet::Timesheet;
+use timetracker::Entry;
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    /// How to write durations
+    #[structopt(long, default_value = "hours-minutes", possible_values = DurationFormat::NAMES)]
+    pub duration_format: DurationFormat,
+
+    /// Show accounts by their aliases from the config
+    #[structopt(long)]
+    pub aliases: bool,
+}
+
+pub fn run(ctx: Context, args: &Args) -> Result<()> {
+    let Context {
+        config,
+        style,
+        locale,
+        storage,
+        ..
+    } = ctx;
+    let Args {
+        duration_format,
+        aliases,
+    } = args;
+    let now = timetracker::now();
+    let today = timetracker::now()
+        .with_timezone(&Local)
+        .date()
+        .naive_local();
+    let day = report::day_range(today, &Local);
+    let completed: Timesheet = storage
+        .entries_since(day.start, |entry| entry.start < day.end)
+        .context("could not read entries")?
+        .into_iter()
+        .collect();
+    let completed = completed.between(day.clone());
+    let running: Timesheet = storage
+        .running_entries()
+        .context("could not read running entries")?
+        .into_iter()
+        .map(|entry| entry.into_entry(now))
+        .collect();
+    let running = running.between(day);
+
+    let name = |account: &str| account_name(&config, account, *aliases);
+    let mut all = completed.entries().to_vec();
+    all.extend(running.entries().iter().cloned());
+    let totals = report::totals_by_account(&all);
+    let names: Vec<String> = totals.iter().map(|(account, _)| name(account)).collect();
+    let width = output::column_width(names.iter().map(String::as_str).chain(Some("Total")));
+
+    println!(
+        "{}",
+        style.bold(
+            &today
+                .format(&locale.pattern("%A %Y-%m-%d", &today))
+                .to_string()
+        )
+    );
+    let print_entry = |entry: &Entry, stop: &str| {
+        let mut details: Vec<String> = entry.tags.iter().map(|tag| format!("#{}", tag)).collect();
+        details.extend(entry.description.clone());
+        let times = format!(
+            "  {}-{:<5}  {:>8}",
+            entry.start.with_timezone(&Local).format("%H:%M"),
+            stop,
+            locale.format_duration(*duration_format, entry.stop - entry.start)
+        );
+        // The account is only padded to align the details following it, like in `log`
+        if details.is_empty() {
+            println!("{}  {}", times, style.account(&name(&entry.account), 0));
+        } else {
+            println!(
+                "{}  {}  {}",
+                times,
+                style.account(&name(&entry.account), width),
+                style.dim(&details.join(" "))
+            );
+        }
+    };
+    for entry in completed.entries() {
+        print_entry(
+            entry,
+            &entry.stop.with_timezone(&Local).format("%H:%M").to_string(),
+        );
+    }
+    for entry in running.entries() {
+        print_entry(entry, "now");
+    }
+    if all.is_empty() {
+        println!("  Nothing tracked yet");
+        return Ok(());
+    }
+
+    println!();
+    for (account, total) in &totals {
+        println!(
+            "  {}  {:>8}",
+            style.account(&name(account), width),
+            locale.format_duration(*duration_format, *total)
+        );
+    }
+    let total = totals
+        .iter()
+        .fold(Duration::zero(), |sum, (_, total)| sum + *total);
+    let total_text = format!("{:>8}", locale.format_duration(*duration_format, total));
+    println!(
+        "  {:<width$}  {}",
+        "Total",
+        style.bold(&style.threshold(&total_text, config.thresholds.daily_level(total))),
+        width = width
+    );
+    Ok(())
+}
@@ -0,0 +1,158 @@
+use super::{
+    auto_commit, handle_interrupts, interrupted, update_slack_status, CliError, Context, Result,
+    ResultExt,
+};
+#[cfg(unix)]
+use std::os::unix::process::ExitStatusExt;
+use std::process::{self, Child, ExitStatus};
+use std::thread;
+use std::time::Duration;
+use structopt::StructOpt;
+use timetracker::duration::format_hours_minutes;
+use timetracker::storage::StorageError;
+use timetracker::{is_valid_tag, RunningEntry};
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    pub account: String,
+
+    #[structopt(short, long = "tag")]
+    pub tags: Vec<String>,
+
+    /// The description, instead of the command line
+    #[structopt(short, long)]
+    pub description: Option<String>,
+
+    /// The command to run, with its arguments
+    #[structopt(last = true, required = true)]
+    pub command: Vec<String>,
+}
+
+pub fn run(ctx: Context, args: &Args) -> Result<()> {
+    let Context {
+        dry_run,
+        config,
+        accounts,
+        storage,
+        ..
+    } = ctx;
+    let Args {
+        account,
+        tags,
+        description,
+        command,
+    } = args;
+    if let Some(tag) = tags.iter().find(|tag| !is_valid_tag(tag)) {
+        return Err(CliError::new(format!(r#"invalid tag "{}""#, tag)));
+    }
+    let account = config.expand_alias(account);
+    let info = accounts.get(&account).cloned().unwrap_or_default();
+    let mut all_tags = info.tags;
+    for tag in tags {
+        if !all_tags.contains(tag) {
+            all_tags.push(tag.clone());
+        }
+    }
+    // Arguments with whitespace are quoted, so the description reads like the command
+    let command_line: Vec<String> = command
+        .iter()
+        .map(|arg| match arg.contains(char::is_whitespace) {
+            true => format!("'{}'", arg),
+            false => arg.clone(),
+        })
+        .collect();
+
+    let running_entry = RunningEntry::builder()
+        .account(account.clone())
+        .tags(all_tags)
+        .billable(info.billable)
+        .category_if_any(accounts.category(&account))
+        .user_if_any(config.team.user())
+        .description(
+            description
+                .clone()
+                .unwrap_or_else(|| command_line.join(" ")),
+        );
+
+    // Signals are meant for the command, the entry is stopped once it exits
+    handle_interrupts();
+    let mut child = process::Command::new(&command[0])
+        .args(&command[1..])
+        .spawn()
+        .with_context(|| format!("could not run {}", command[0]))?;
+    let running_entry = match running_entry
+        .start(timetracker::now())
+        .build()
+        .map_err(StorageError::from)
+        .and_then(|running_entry| storage.start(running_entry))
+    {
+        Ok(running_entry) => running_entry,
+        Err(err) => {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(err.into());
+        }
+    };
+    if !dry_run {
+        if let Err(err) = config.hooks.started(&running_entry) {
+            log::error!("{}", err);
+        }
+        update_slack_status(&storage, &config);
+    }
+
+    let status = wait_passing_on_signals(&mut child);
+    let entry = storage.stop(Some(&account), timetracker::now())?;
+    if !dry_run {
+        if let Err(err) = config.hooks.stopped(&entry) {
+            log::error!("{}", err);
+        }
+        update_slack_status(&storage, &config);
+    }
+    auto_commit(&storage, &config, &format!("Track {}", account))?;
+
+    let status = status.with_context(|| format!("could not wait for {}", command[0]))?;
+    eprintln!(
+        "Tracked {} for {}",
+        format_hours_minutes(entry.stop - entry.start),
+        account
+    );
+    match exit_code(status) {
+        0 => Ok(()),
+        code => Err(CliError::exit(code)),
+    }
+}
+
+/// How often to check whether the command exited or a signal is to be passed on.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Waits for the command to exit, passing on SIGTERM and SIGHUP sent to this process. Ctrl-C
+/// reaches the command from the terminal itself.
+fn wait_passing_on_signals(child: &mut Child) -> std::io::Result<ExitStatus> {
+    let mut passed_on = None;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(status);
+        }
+        let signal =
+            interrupted().filter(|&signal| signal != libc::SIGINT && Some(signal) != passed_on);
+        if let Some(signal) = signal {
+            #[cfg(unix)]
+            // Safety: sending a signal does not touch the memory of this process
+            unsafe {
+                libc::kill(child.id() as libc::pid_t, signal);
+            }
+            passed_on = Some(signal);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// The exit code of the command, which for a command killed by a signal is the one shells
+/// report, e.g. 143 for SIGTERM.
+fn exit_code(status: ExitStatus) -> i32 {
+    #[cfg(unix)]
+    if let Some(signal) = status.signal() {
+        return 128 + signal;
+    }
+    status.code().unwrap_or(1)
+}
@@ -0,0 +1,65 @@
+use super::{auto_commit, print_json, Context, Result};
+use chrono::Duration;
+use structopt::StructOpt;
+use timetracker::duration::parse_signed_duration;
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    /// `last` for the last entry, or the account to trim the last entry of
+    pub target: String,
+
+    /// Move the start by this amount, e.g. `+10m` for later or `-5m` for earlier
+    #[structopt(
+        long,
+        required_unless = "stop",
+        allow_hyphen_values = true,
+        parse(try_from_str = parse_signed_duration)
+    )]
+    pub start: Option<Duration>,
+
+    /// Move the stop by this amount, e.g. `-15m` for earlier
+    #[structopt(
+        long,
+        allow_hyphen_values = true,
+        parse(try_from_str = parse_signed_duration)
+    )]
+    pub stop: Option<Duration>,
+
+    /// Allow the entry to overlap other entries
+    #[structopt(long)]
+    pub allow_overlap: bool,
+
+    /// Print the trimmed entry as JSON on a single line instead of any messages
+    #[structopt(long)]
+    pub porcelain: bool,
+}
+
+pub fn run(ctx: Context, args: &Args) -> Result<()> {
+    let Context {
+        config, storage, ..
+    } = ctx;
+    let Args {
+        target,
+        start,
+        stop,
+        allow_overlap,
+        porcelain,
+    } = args;
+    let target = Some(target.as_str())
+        .filter(|target| *target != "last")
+        .map(|target| config.expand_alias(target));
+    let trimmed = storage.trim_last_entry(
+        target.as_deref(),
+        start.unwrap_or_else(Duration::zero),
+        stop.unwrap_or_else(Duration::zero),
+        *allow_overlap,
+    )?;
+    if *porcelain {
+        print_json(&trimmed);
+    } else {
+        println!("{}", trimmed);
+    }
+
+    auto_commit(&storage, &config, &format!("Trim {}", trimmed.account))?;
+    Ok(())
+}
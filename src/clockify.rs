@@ -0,0 +1,70 @@
+//! Uploading of entries to Clockify through its REST API.
+
+use serde::Deserialize;
+use serde_json::json;
+use std::env;
+use std::error::Error;
+use timetracker::config::ClockifyConfig;
+use timetracker::pushed::PushedLog;
+use timetracker::Entry;
+
+const API_URL: &str = "https://api.clockify.me/api/v1";
+
+#[derive(Debug, Deserialize)]
+struct TimeEntry {
+    id: String,
+}
+
+/// Creates a Clockify time entry for each of the entries not pushed before, recording them in the
+/// log as they are pushed. Returns the number of entries pushed.
+pub fn push(
+    config: &ClockifyConfig,
+    entries: &[Entry],
+    log: &mut PushedLog,
+    dry_run: bool,
+) -> Result<usize, Box<dyn Error + Send + Sync>> {
+    let api_key = env::var("CLOCKIFY_API_KEY")
+        .ok()
+        .or_else(|| config.api_key.clone())
+        .ok_or("no Clockify API key is configured")?;
+    let workspace_id = config
+        .workspace_id
+        .as_deref()
+        .ok_or("no Clockify workspace is configured")?;
+    let url = format!("{}/workspaces/{}/time-entries", API_URL, workspace_id);
+
+    let mut pushed = 0;
+    for entry in entries {
+        let id = entry.id();
+        if log.contains(&id) {
+            continue;
+        }
+        let project = config.project_for(&entry.account);
+        if dry_run {
+            println!(
+                "{} {} {}",
+                entry.start.format("%Y-%m-%d %H:%M"),
+                entry.account,
+                project.unwrap_or("(no project)")
+            );
+            pushed += 1;
+            continue;
+        }
+
+        let body = json!({
+            "start": entry.start,
+            "end": entry.stop,
+            "projectId": project,
+            "description": entry.description.as_deref().unwrap_or(&entry.account),
+            "billable": entry.billable,
+        });
+        log::debug!("creating Clockify time entry {}", body);
+        let created: TimeEntry = ureq::post(&url)
+            .set("X-Api-Key", &api_key)
+            .send_json(body)?
+            .into_json()?;
+        log.record(&id, &created.id)?;
+        pushed += 1;
+    }
+    Ok(pushed)
+}
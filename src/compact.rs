@@ -0,0 +1,422 @@
+//! A compact binary format for large archives of entries, which loads faster than the line
+//! format. The line format stays the one every other file is in, archives are converted to and
+//! from it with `tt convert`.
+//!
+//! The format is [MessagePack](https://msgpack.org): an array of the name `timetracker-entries`,
+//! the version of the format and an array of the entries. Each entry is an array of the start,
+//! stop, account, whether it is billable, an array of the tags, the description or nil, since
+//! version 2 the category or nil and, since version 3, an array of the metadata keys each followed
+//! by its value. Times are seconds since the Unix epoch, or timestamp extensions
+//! when they have fractions of a second.
+
+use crate::Entry;
+use chrono::{DateTime, TimeZone, Timelike, Utc};
+use std::collections::BTreeMap;
+use std::convert::TryInto;
+use std::error::Error;
+use std::fmt;
+
+const NAME: &str = "timetracker-entries";
+const VERSION: u64 = 3;
+
+/// The MessagePack extension type of timestamps.
+const TIMESTAMP: i8 = -1;
+
+/// Checks whether the contents of a file are in the compact format, by how they start.
+pub fn is_compact(contents: &[u8]) -> bool {
+    let mut reader = Reader::new(contents);
+    reader.array_len().is_ok() && reader.str().is_ok_and(|name| name == NAME)
+}
+
+/// Writes the entries in the compact format.
+pub fn encode(entries: &[Entry]) -> Vec<u8> {
+    let mut out = Vec::new();
+    write_array_len(&mut out, 3);
+    write_str(&mut out, NAME);
+    write_uint(&mut out, VERSION);
+    write_array_len(&mut out, entries.len());
+    for entry in entries {
+        write_array_len(&mut out, 8);
+        write_time(&mut out, &entry.start);
+        write_time(&mut out, &entry.stop);
+        write_str(&mut out, &entry.account);
+        out.push(if entry.billable { 0xc3 } else { 0xc2 });
+        write_array_len(&mut out, entry.tags.len());
+        for tag in &entry.tags {
+            write_str(&mut out, tag);
+        }
+        write_optional_str(&mut out, entry.description.as_deref());
+        write_optional_str(&mut out, entry.category.as_deref());
+        write_array_len(&mut out, entry.meta.len() * 2);
+        for (key, value) in &entry.meta {
+            write_str(&mut out, key);
+            write_str(&mut out, value);
+        }
+    }
+    out
+}
+
+/// Reads the entries of a file in the compact format.
+pub fn decode(contents: &[u8]) -> Result<Vec<Entry>, DecodeError> {
+    let mut reader = Reader::new(contents);
+    if reader.array_len()? != 3 || reader.str()? != NAME {
+        return Err(reader.error("the name of the format"));
+    }
+    let version = reader.uint()?;
+    if !(1..=VERSION).contains(&version) {
+        return Err(DecodeError::UnsupportedVersion(version));
+    }
+    // Version 1 had no categories and version 2 no metadata
+    let fields = match version {
+        1 => 6,
+        2 => 7,
+        _ => 8,
+    };
+    let count = reader.array_len()?;
+    let mut entries = Vec::with_capacity(count.min(contents.len()));
+    for _ in 0..count {
+        if reader.array_len()? != fields {
+            return Err(reader.error("an entry"));
+        }
+        let start = reader.time()?;
+        let stop = reader.time()?;
+        let account = reader.str()?.to_string();
+        let billable = reader.bool()?;
+        let tags = (0..reader.array_len()?)
+            .map(|_| reader.str().map(str::to_string))
+            .collect::<Result<_, _>>()?;
+        let description = reader.optional_str()?.map(str::to_string);
+        let category = match fields {
+            6 => None,
+            _ => reader.optional_str()?.map(str::to_string),
+        };
+        let mut meta = BTreeMap::new();
+        if fields == 8 {
+            let len = reader.array_len()?;
+            if len % 2 != 0 {
+                return Err(reader.error("the metadata of an entry"));
+            }
+            for _ in 0..len / 2 {
+                let key = reader.str()?.to_string();
+                meta.insert(key, reader.str()?.to_string());
+            }
+        }
+        entries.push(Entry {
+            start,
+            stop,
+            account: account.into(),
+            tags,
+            billable,
+            category,
+            description,
+            meta,
+        });
+    }
+    if reader.offset != contents.len() {
+        return Err(reader.error("the end of the file"));
+    }
+    Ok(entries)
+}
+
+fn write_uint(out: &mut Vec<u8>, value: u64) {
+    match value {
+        0..=0x7f => out.push(value as u8),
+        0x80..=0xff => out.extend_from_slice(&[0xcc, value as u8]),
+        0x100..=0xffff => {
+            out.push(0xcd);
+            out.extend_from_slice(&(value as u16).to_be_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            out.push(0xce);
+            out.extend_from_slice(&(value as u32).to_be_bytes());
+        }
+        _ => {
+            out.push(0xcf);
+            out.extend_from_slice(&value.to_be_bytes());
+        }
+    }
+}
+
+fn write_optional_str(out: &mut Vec<u8>, s: Option<&str>) {
+    match s {
+        Some(s) => write_str(out, s),
+        None => out.push(0xc0),
+    }
+}
+
+fn write_len(out: &mut Vec<u8>, len: usize, fix: u8, fix_max: usize, markers: [u8; 3]) {
+    if len <= fix_max {
+        out.push(fix | len as u8);
+    } else if len <= 0xff && markers[0] != 0 {
+        out.extend_from_slice(&[markers[0], len as u8]);
+    } else if len <= 0xffff {
+        out.push(markers[1]);
+        out.extend_from_slice(&(len as u16).to_be_bytes());
+    } else {
+        out.push(markers[2]);
+        out.extend_from_slice(&(len as u32).to_be_bytes());
+    }
+}
+
+fn write_array_len(out: &mut Vec<u8>, len: usize) {
+    // Arrays have no 8-bit length
+    write_len(out, len, 0x90, 15, [0, 0xdc, 0xdd]);
+}
+
+fn write_str(out: &mut Vec<u8>, s: &str) {
+    write_len(out, s.len(), 0xa0, 31, [0xd9, 0xda, 0xdb]);
+    out.extend_from_slice(s.as_bytes());
+}
+
+fn write_time(out: &mut Vec<u8>, time: &DateTime<Utc>) {
+    let (seconds, nanoseconds) = (time.timestamp(), time.nanosecond());
+    if nanoseconds == 0 && seconds >= 0 {
+        write_uint(out, seconds as u64);
+    } else {
+        out.extend_from_slice(&[0xc7, 12, TIMESTAMP as u8]);
+        out.extend_from_slice(&nanoseconds.to_be_bytes());
+        out.extend_from_slice(&seconds.to_be_bytes());
+    }
+}
+
+/// Reads MessagePack values from the start of the bytes.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Reader { bytes, offset: 0 }
+    }
+
+    fn error(&self, expected: &'static str) -> DecodeError {
+        DecodeError::Invalid {
+            offset: self.offset,
+            expected,
+        }
+    }
+
+    fn peek(&self) -> Result<u8, DecodeError> {
+        self.bytes
+            .get(self.offset)
+            .copied()
+            .ok_or_else(|| self.error("more data"))
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+        let bytes = self
+            .bytes
+            .get(self.offset..self.offset + len)
+            .ok_or_else(|| self.error("more data"))?;
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    fn take_uint(&mut self, len: usize) -> Result<u64, DecodeError> {
+        Ok(self
+            .take(len)?
+            .iter()
+            .fold(0, |value, byte| value << 8 | *byte as u64))
+    }
+
+    fn uint(&mut self) -> Result<u64, DecodeError> {
+        let marker = self.peek()?;
+        let len = match marker {
+            0x00..=0x7f => 0,
+            0xcc => 1,
+            0xcd => 2,
+            0xce => 4,
+            0xcf => 8,
+            _ => return Err(self.error("an unsigned integer")),
+        };
+        self.offset += 1;
+        match len {
+            0 => Ok(marker as u64),
+            len => self.take_uint(len),
+        }
+    }
+
+    fn len(&mut self, fix: u8, fix_mask: u8, markers: [u8; 3]) -> Option<usize> {
+        let marker = *self.bytes.get(self.offset)?;
+        let len = if marker & !fix_mask == fix {
+            0
+        } else {
+            (0..3).find(|index| markers[*index] == marker && marker != 0)? + 1
+        };
+        self.offset += 1;
+        match len {
+            0 => Some((marker & fix_mask) as usize),
+            len => self.take_uint(1 << (len - 1)).ok().map(|len| len as usize),
+        }
+    }
+
+    fn array_len(&mut self) -> Result<usize, DecodeError> {
+        let start = self.offset;
+        self.len(0x90, 0x0f, [0, 0xdc, 0xdd]).ok_or_else(|| {
+            self.offset = start;
+            self.error("an array")
+        })
+    }
+
+    fn str(&mut self) -> Result<&'a str, DecodeError> {
+        let start = self.offset;
+        let len = self.len(0xa0, 0x1f, [0xd9, 0xda, 0xdb]).ok_or_else(|| {
+            self.offset = start;
+            self.error("a string")
+        })?;
+        let bytes = self.take(len)?;
+        std::str::from_utf8(bytes).map_err(|_| DecodeError::Invalid {
+            offset: start,
+            expected: "a UTF-8 string",
+        })
+    }
+
+    /// A string, or `None` for nil.
+    fn optional_str(&mut self) -> Result<Option<&'a str>, DecodeError> {
+        if self.peek()? == 0xc0 {
+            self.offset += 1;
+            return Ok(None);
+        }
+        self.str().map(Some)
+    }
+
+    fn bool(&mut self) -> Result<bool, DecodeError> {
+        let value = match self.peek()? {
+            0xc2 => false,
+            0xc3 => true,
+            _ => return Err(self.error("a boolean")),
+        };
+        self.offset += 1;
+        Ok(value)
+    }
+
+    fn time(&mut self) -> Result<DateTime<Utc>, DecodeError> {
+        let start = self.offset;
+        let (seconds, nanoseconds) = if self.peek()? == 0xc7 {
+            let header = self.take(3)?;
+            if header[1] != 12 || header[2] != TIMESTAMP as u8 {
+                return Err(DecodeError::Invalid {
+                    offset: start,
+                    expected: "a timestamp",
+                });
+            }
+            let nanoseconds = u32::from_be_bytes(self.take(4)?.try_into().expect("4 bytes"));
+            let seconds = i64::from_be_bytes(self.take(8)?.try_into().expect("8 bytes"));
+            (seconds, nanoseconds)
+        } else {
+            (self.uint()? as i64, 0)
+        };
+        Utc.timestamp_opt(seconds, nanoseconds)
+            .single()
+            .ok_or(DecodeError::Invalid {
+                offset: start,
+                expected: "a valid time",
+            })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    Invalid {
+        /// The byte offset of the invalid value.
+        offset: usize,
+        expected: &'static str,
+    },
+    UnsupportedVersion(u64),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Invalid { offset, expected } => {
+                write!(f, "expected {} at byte {}", expected, offset)
+            }
+            DecodeError::UnsupportedVersion(version) => {
+                write!(f, "unsupported version {} of the compact format", version)
+            }
+        }
+    }
+}
+
+impl Error for DecodeError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn entry(s: &str) -> Entry {
+        Entry::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn encode_and_decode() {
+        let long_description = "x".repeat(300);
+        let entries = vec![
+            entry(
+                "2021-07-03T10:00:00Z 2021-07-03T11:30:00Z Client $ @development #dev pr=482 ; Fix, then ship",
+            ),
+            entry("2021-07-03T12:00:00.25Z 2021-07-03T12:15:00Z Other"),
+            entry(&format!(
+                "1969-12-31T23:00:00Z 1970-01-01T00:00:00Z Before the epoch ; {}",
+                long_description
+            )),
+        ];
+        let encoded = encode(&entries);
+
+        assert!(is_compact(&encoded));
+        assert!(!is_compact(
+            b"2021-07-03T10:00:00Z 2021-07-03T11:30:00Z Client\n"
+        ));
+        assert!(!is_compact(&[]));
+        assert_eq!(decode(&encoded), Ok(entries.clone()));
+        assert_eq!(decode(&encode(&[])), Ok(Vec::new()));
+
+        // Each entry takes a fraction of its line
+        let lines: usize = entries
+            .iter()
+            .map(|entry| entry.to_string().len() + 1)
+            .sum();
+        assert!(encoded.len() < lines);
+    }
+
+    #[test]
+    fn decode_older_versions() {
+        let entries = vec![entry("2021-07-03T10:00:00Z 2021-07-03T11:30:00Z Client")];
+        let mut encoded = encode(&entries);
+        // The same entry without the metadata
+        encoded[NAME.len() + 2] = 2;
+        encoded[NAME.len() + 4] = 0x97;
+        encoded.pop();
+        assert_eq!(decode(&encoded), Ok(entries.clone()));
+
+        // And without the category
+        encoded[NAME.len() + 2] = 1;
+        encoded[NAME.len() + 4] = 0x96;
+        encoded.pop();
+        assert_eq!(decode(&encoded), Ok(entries));
+    }
+
+    #[test]
+    fn reject_invalid_files() {
+        let encoded = encode(&[entry("2021-07-03T10:00:00Z 2021-07-03T11:30:00Z Client")]);
+
+        assert!(matches!(
+            decode(&encoded[..encoded.len() - 1]),
+            Err(DecodeError::Invalid { .. })
+        ));
+        let mut trailing = encoded.clone();
+        trailing.push(0xc0);
+        assert_eq!(
+            decode(&trailing),
+            Err(DecodeError::Invalid {
+                offset: encoded.len(),
+                expected: "the end of the file"
+            })
+        );
+        let mut newer = encoded;
+        newer[NAME.len() + 2] = 4;
+        assert_eq!(decode(&newer), Err(DecodeError::UnsupportedVersion(4)));
+    }
+}
@@ -0,0 +1,1027 @@
+use crate::calendar::{CalendarConfig, Country};
+use crate::goals::{Goal, Period, Scope};
+use crate::hooks::HookConfig;
+use crate::reminders::ReminderConfig;
+use crate::report::is_within_account;
+use crate::rounding::Rounding;
+use crate::schedule::Recurring;
+use crate::storage::ZeroDurationPolicy;
+use chrono::{Duration, NaiveTime};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// User configuration, read from a TOML file.
+///
+/// Every setting has a default, so a missing file or section is equivalent to an empty one.
+/// Unknown settings are refused rather than ignored, so typos do not go unnoticed.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Prompt for a description when starting or stopping an entry without one, as if
+    /// `--ask-description` was given.
+    pub always_ask_description: bool,
+    /// Running several entries at the same time is intended, so `status` does not warn about it.
+    pub allow_concurrent: bool,
+    /// The command run by `tt` without one, e.g. `status`, instead of showing the usage.
+    pub default_command: Option<String>,
+    /// Start the account given in place of a command, so `tt ClientA` is `tt start ClientA`.
+    pub start_unknown_commands: bool,
+    /// Description templates keyed by name, see `templates::expand` for the placeholders.
+    pub templates: BTreeMap<String, String>,
+    /// Short names for accounts, e.g. `dev = "ClientA:ProjectX:Development"`.
+    pub aliases: BTreeMap<String, String>,
+    /// The language of names of days and months and of the decimal separator in output, e.g.
+    /// `de`, instead of the one of `LANG`.
+    pub locale: Option<String>,
+    pub sync: SyncConfig,
+    /// Hour targets keyed by account, or by tag prefixed by `#`, e.g. `"#deep-work"`.
+    pub goals: BTreeMap<String, GoalConfig>,
+    /// Recurring entries keyed by name, see `schedule`.
+    pub schedule: BTreeMap<String, Recurring>,
+    pub reminders: ReminderConfig,
+    pub calendar: CalendarConfig,
+    pub clockify: ClockifyConfig,
+    pub jira: JiraConfig,
+    pub caldav: CaldavConfig,
+    pub slack: SlackConfig,
+    pub start: StartConfig,
+    pub stop: StopConfig,
+    pub storage: StorageConfig,
+    pub backup: BackupConfig,
+    pub thresholds: ThresholdConfig,
+    pub digest: DigestConfig,
+    pub git: GitConfig,
+    pub hooks: HookConfig,
+    pub activity: ActivityConfig,
+    pub team: TeamConfig,
+    pub prompt: PromptConfig,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PromptConfig {
+    /// Mark the output of `tt prompt` and `tt status --short` with this, e.g. `"!"`, when the
+    /// time tracked today is beyond `daily_limit` under `[thresholds]` or an account has used
+    /// most of its budget. Nothing is marked without it.
+    pub alert_marker: Option<String>,
+    /// The share of the budget of an account used from which it is alerted about, `0.9` by
+    /// default.
+    pub budget_alert: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TeamConfig {
+    /// Record who tracked each new entry, as `user=<name>`, for a team sharing the data files.
+    pub record_user: bool,
+    /// The name to record, by default the login name from `$USER`.
+    #[serde(deserialize_with = "deserialize_optional_user")]
+    pub user: Option<String>,
+}
+
+impl TeamConfig {
+    /// The name recorded on new entries, if users are recorded.
+    pub fn user(&self) -> Option<String> {
+        if !self.record_user {
+            return None;
+        }
+        self.user.clone().or_else(|| {
+            env::var("USER")
+                .or_else(|_| env::var("USERNAME"))
+                .ok()
+                .filter(|user| crate::is_valid_tag(user))
+        })
+    }
+}
+
+/// Reads an optional user name, which is written as a single word like tags.
+fn deserialize_optional_user<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<String>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|user| {
+            if crate::is_valid_tag(&user) {
+                Ok(user)
+            } else {
+                Err(serde::de::Error::custom(format!(
+                    r#"invalid user "{}", expected a single word"#,
+                    user
+                )))
+            }
+        })
+        .transpose()
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SyncConfig {
+    /// Commit the data files after every command that changes them, when the data directory is a
+    /// git repository.
+    pub auto_commit: bool,
+    /// Append changes to a log of operations of this machine in `ops` next to the entries file
+    /// instead of rewriting the data files, so the directory can be synced by services like
+    /// Dropbox without conflicts. `tt compact` folds the operations into the data files.
+    pub oplog: bool,
+    /// The name of this machine, which its log of operations is named after, by default the
+    /// hostname.
+    pub machine: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageConfig {
+    /// Keep the entries file in chronological order when adding entries.
+    pub keep_sorted: bool,
+    /// Keep the running entries in the entries file, on lines starting with `RUNNING `, instead
+    /// of the running file.
+    pub single_file: bool,
+    /// Refuse every change to the data files, e.g. for files synced from another machine.
+    pub read_only: bool,
+    /// Move an incomplete last line, as left behind by an interrupted write, to a `.corrupt` file
+    /// next to the data file instead of failing.
+    pub quarantine_partial_lines: bool,
+    /// What happens to entries that do not last any time when they are recorded, `keep`, `drop`
+    /// or `error`.
+    pub zero_duration: ZeroDurationPolicy,
+    /// Keep an index of the entries file next to it, so reports of a period skip the entries
+    /// before it instead of reading the whole file.
+    pub index: bool,
+    /// Record every change to the data files in the audit log next to the entries file, see
+    /// `tt history`.
+    pub audit: bool,
+    /// Round the start of entries when starting them, e.g. `down 5m`.
+    #[serde(deserialize_with = "crate::rounding::deserialize_optional")]
+    pub round_start: Option<Rounding>,
+    /// Round the stop of entries when stopping them, e.g. `up 5m`.
+    #[serde(deserialize_with = "crate::rounding::deserialize_optional")]
+    pub round_stop: Option<Rounding>,
+    /// An age identity file with the key to encrypt the data files with, which requires the
+    /// `encryption` feature. `TIMETRACKER_IDENTITY` takes precedence with the key itself.
+    pub identity_file: Option<PathBuf>,
+    /// The longest line read from the data files in bytes, 64 KiB by default.
+    pub max_line_length: Option<usize>,
+    /// The largest data file read in megabytes, 512 by default.
+    pub max_file_size_mb: Option<u64>,
+    /// Refuse to run while the data files or the config file can be read or written by others
+    /// than their owner.
+    pub require_private_files: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BackupConfig {
+    /// The directory to keep snapshots in, by default `backups` next to the entries file.
+    pub dir: Option<PathBuf>,
+    /// How many snapshots to keep, the oldest beyond it are removed. All are kept by default.
+    pub keep: Option<usize>,
+    /// Take a snapshot before the commands which rewrite the data files, like `import`, `sort`
+    /// and `rename-account`.
+    pub before_changes: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GitConfig {
+    /// The accounts to start entries for with `--from-git`, keyed by the path of a repository or
+    /// the name of its directory. Other repositories use the name of their directory.
+    pub accounts: BTreeMap<String, String>,
+    /// What the description of entries started with `--from-git` is taken from.
+    pub description: GitDescription,
+}
+
+/// What the description of entries started with `--from-git` is taken from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GitDescription {
+    /// The name of the checked out branch.
+    #[default]
+    Branch,
+    /// The subject of the last commit.
+    Commit,
+    /// No description.
+    None,
+}
+
+impl GitConfig {
+    /// The account of the repository with its root at the given path.
+    pub fn account(&self, root: &Path) -> String {
+        let name = root
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        self.accounts
+            .iter()
+            .find(|(key, _)| Path::new(key) == root)
+            .or_else(|| self.accounts.iter().find(|(key, _)| **key == name))
+            .map_or(name, |(_, account)| account.clone())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DigestConfig {
+    /// A file with the layout of `tt digest`, see `digest::Digest` for the placeholders.
+    pub template: Option<PathBuf>,
+    /// The number of the longest entries listed, 5 by default.
+    pub notable: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThresholdConfig {
+    /// Show the total of a day in yellow from this much time tracked, e.g. `6h`.
+    #[serde(deserialize_with = "crate::duration::deserialize_optional")]
+    pub daily_warning: Option<Duration>,
+    /// Show the total of a day in red beyond this much time tracked, e.g. `9h`.
+    #[serde(deserialize_with = "crate::duration::deserialize_optional")]
+    pub daily_limit: Option<Duration>,
+}
+
+/// How the time tracked in a day compares to the thresholds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThresholdLevel {
+    Below,
+    Warning,
+    Exceeded,
+}
+
+impl ThresholdConfig {
+    /// The level of the total of a day, or `None` if no daily thresholds are configured.
+    pub fn daily_level(&self, total: Duration) -> Option<ThresholdLevel> {
+        if self.daily_warning.is_none() && self.daily_limit.is_none() {
+            return None;
+        }
+        Some(if self.daily_limit.is_some_and(|limit| total > limit) {
+            ThresholdLevel::Exceeded
+        } else if self.daily_warning.is_some_and(|warning| total >= warning) {
+            ThresholdLevel::Warning
+        } else {
+            ThresholdLevel::Below
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StartConfig {
+    /// What happens to the entries already running when starting another one.
+    pub other_running: OtherRunning,
+    /// Only warn when starting an account which reached its daily limit, instead of refusing.
+    pub warn_over_daily_limit: bool,
+    /// Starting an account already running since at most this long before does nothing, rather
+    /// than failing, as a start run twice by a hook. `1s` by default.
+    #[serde(deserialize_with = "crate::duration::deserialize_optional")]
+    pub duplicate_window: Option<Duration>,
+}
+
+/// What happens to the entries already running when starting another one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OtherRunning {
+    /// Ask whether to stop them when standard input is a terminal, otherwise keep them.
+    #[default]
+    Ask,
+    /// Stop them, as if `--stop-others` was given.
+    Stop,
+    /// Keep them running, as if `--keep-others` was given.
+    Keep,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StopConfig {
+    /// Record entries spanning midnight as one entry per day, as if `--split-days` was given.
+    pub split_days: bool,
+    /// Refuse to stop entries running for longer than this without `--force`, as they are
+    /// usually forgotten, e.g. `12h`.
+    #[serde(deserialize_with = "crate::duration::deserialize_optional")]
+    pub max_running_duration: Option<Duration>,
+    /// Stop the entries still running at this local time, like `18:30`, tagging them with
+    /// `auto-stopped`. The daemon and `tt serve` stop them on time, otherwise the next command
+    /// stops them at the time.
+    #[serde(deserialize_with = "crate::reminders::deserialize_optional_time")]
+    pub auto_stop_at: Option<NaiveTime>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ActivityConfig {
+    /// A shell command printing the title of the active window or the name of the focused app,
+    /// which `tt audit --record` samples, by default `xdotool getactivewindow getwindowname` or
+    /// on macOS the name of the frontmost app.
+    pub command: Option<String>,
+    /// The time between samples, `1m` by default.
+    #[serde(deserialize_with = "crate::duration::deserialize_optional")]
+    pub interval: Option<Duration>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GoalConfig {
+    /// Hours to track each week.
+    pub weekly: Option<f64>,
+    /// Hours to track each month.
+    pub monthly: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClockifyConfig {
+    /// API key for Clockify, which can also be given through the `CLOCKIFY_API_KEY` environment
+    /// variable.
+    pub api_key: Option<String>,
+    pub workspace_id: Option<String>,
+    /// Clockify project IDs keyed by account, also applying to sub-accounts.
+    pub projects: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct JiraConfig {
+    /// Base URL of the JIRA site, e.g. `https://example.atlassian.net`.
+    pub url: Option<String>,
+    pub email: Option<String>,
+    /// API token for JIRA, which can also be given through the `JIRA_API_TOKEN` environment
+    /// variable.
+    pub api_token: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CaldavConfig {
+    /// URL of the collection of calendars, e.g.
+    /// `https://cloud.example.com/remote.php/dav/calendars/me`.
+    pub url: Option<String>,
+    /// The name of the calendar in the collection to create the events in.
+    pub calendar: Option<String>,
+    pub username: Option<String>,
+    /// Password for the CalDAV server, which can also be given through the `CALDAV_PASSWORD`
+    /// environment variable.
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SlackConfig {
+    /// User token for Slack with the `users.profile:write` scope, which can also be given through
+    /// the `SLACK_TOKEN` environment variable.
+    pub token: Option<String>,
+    /// Set the status when starting an entry and clear it when stopping, as if
+    /// `push slack-status` was run after both.
+    pub update_status: bool,
+    /// The statuses keyed by account, also applying to sub-accounts. Accounts without one clear
+    /// the status.
+    pub statuses: BTreeMap<String, SlackStatus>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SlackStatus {
+    /// An emoji code like `:computer:`.
+    pub emoji: String,
+    pub text: String,
+}
+
+impl SlackConfig {
+    /// Finds the status for the given account, preferring the most specific configured account.
+    pub fn status_for(&self, account: &str) -> Option<&SlackStatus> {
+        self.statuses
+            .iter()
+            .filter(|(parent, _)| is_within_account(account, parent))
+            .max_by_key(|(parent, _)| parent.len())
+            .map(|(_, status)| status)
+    }
+}
+
+impl ClockifyConfig {
+    /// Finds the project for the given account, preferring the most specific configured account.
+    pub fn project_for(&self, account: &str) -> Option<&str> {
+        self.projects
+            .iter()
+            .filter(|(parent, _)| is_within_account(account, parent))
+            .max_by_key(|(parent, _)| parent.len())
+            .map(|(_, project)| project.as_str())
+    }
+}
+
+impl Config {
+    /// Loads the configuration from the given path, falling back to the defaults if there is no
+    /// file at the path.
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        match fs::read_to_string(path) {
+            Ok(contents) => match lint(&contents).into_iter().next() {
+                Some(problem) => Err(ConfigError::Invalid(problem)),
+                None => Ok(toml::from_str(&contents)?),
+            },
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                log::debug!(
+                    "{} does not exist, using the default config",
+                    path.display()
+                );
+                Ok(Config::default())
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Expands an alias at the start of the account, so with the alias `dev` both `dev` and
+    /// `dev:Meetings` are expanded. Other accounts are returned as they are.
+    pub fn expand_alias(&self, account: &str) -> String {
+        let (first, rest) = match account.split_once(':') {
+            Some((first, rest)) => (first, Some(rest)),
+            None => (account, None),
+        };
+        match (self.aliases.get(first), rest) {
+            (Some(expanded), Some(rest)) => format!("{}:{}", expanded, rest),
+            (Some(expanded), None) => expanded.clone(),
+            (None, _) => account.to_string(),
+        }
+    }
+
+    /// Shortens the account with the alias for it or its most specific parent account, the
+    /// opposite of `expand_alias`.
+    pub fn shorten_with_alias(&self, account: &str) -> String {
+        self.aliases
+            .iter()
+            .filter(|(_, expanded)| is_within_account(account, expanded))
+            .max_by_key(|(_, expanded)| expanded.len())
+            .map_or_else(
+                || account.to_string(),
+                |(alias, expanded)| format!("{}{}", alias, &account[expanded.len()..]),
+            )
+    }
+
+    /// Lists every configured goal, ordered by tag and then account.
+    pub fn goals(&self) -> Vec<Goal> {
+        let mut goals = Vec::new();
+        for (key, goal) in &self.goals {
+            let periods = [(Period::Week, goal.weekly), (Period::Month, goal.monthly)];
+            for (period, hours) in periods.iter() {
+                if let Some(hours) = hours {
+                    goals.push(Goal {
+                        scope: Scope::parse(key),
+                        period: *period,
+                        target: Duration::seconds((hours * 3600.0).round() as i64),
+                    });
+                }
+            }
+        }
+        goals
+    }
+}
+
+/// The settings `tt init` asks for when creating the config file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InitialConfig {
+    pub work_days: Vec<String>,
+    pub hours_per_day: Option<f64>,
+    pub country: Option<Country>,
+}
+
+impl InitialConfig {
+    /// The contents of the config file, with only the settings differing from the defaults.
+    pub fn to_toml(&self) -> String {
+        let mut calendar = Vec::new();
+        if self.work_days != CalendarConfig::default().work_days {
+            let days: Vec<String> = self
+                .work_days
+                .iter()
+                .map(|day| format!("\"{}\"", day))
+                .collect();
+            calendar.push(format!("work_days = [{}]", days.join(", ")));
+        }
+        if let Some(hours) = self.hours_per_day {
+            calendar.push(format!("hours_per_day = {:?}", hours));
+        }
+        if let Some(country) = self.country {
+            calendar.push(format!("country = \"{}\"", country));
+        }
+
+        let mut contents = "# Created by tt init, see the README for every setting\n".to_string();
+        if !calendar.is_empty() {
+            contents.push_str(&format!("\n[calendar]\n{}\n", calendar.join("\n")));
+        }
+        contents
+    }
+}
+
+/// Settings which cannot be combined, each as its section and key.
+const CONFLICTS: [[(&str, &str); 2]; 3] = [
+    [("sync", "oplog"), ("storage", "single_file")],
+    [("sync", "oplog"), ("storage", "identity_file")],
+    [("storage", "index"), ("storage", "single_file")],
+];
+
+/// The conflicting settings which are both set in the config, see `CONFLICTS`.
+fn conflicts(value: &toml::Value) -> Vec<[(&'static str, &'static str); 2]> {
+    let is_set = |(section, key): &(&str, &str)| {
+        !matches!(
+            value.get(section).and_then(|section| section.get(key)),
+            None | Some(toml::Value::Boolean(false))
+        )
+    };
+    CONFLICTS
+        .iter()
+        .filter(|settings| settings.iter().all(is_set))
+        .copied()
+        .collect()
+}
+
+fn conflict_message(settings: &[(&str, &str); 2]) -> String {
+    let [(section, key), (other_section, other_key)] = settings;
+    format!(
+        "`{}` under [{}] cannot be combined with `{}` under [{}]",
+        key, section, other_key, other_section
+    )
+}
+
+/// A problem with a config file, see `lint`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Problem {
+    /// The line number, starting at 1, if the problem is on a line.
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}", line, self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+/// Lists every problem with the contents of a config file, by line: unknown settings, values
+/// which cannot be read, like durations, and settings which cannot be combined. Only the first
+/// syntax error is found, as nothing after it can be read.
+pub fn lint(contents: &str) -> Vec<Problem> {
+    let value = match toml::from_str(contents) {
+        Ok(value) => value,
+        Err(err) => {
+            return vec![Problem {
+                line: err.line_col().map(|(line, _)| line + 1),
+                message: without_position(&err),
+            }]
+        }
+    };
+    let mut lines: Vec<&str> = contents.lines().collect();
+    let mut problems: Vec<Problem> = conflicts(&value)
+        .iter()
+        .map(|settings| {
+            let (section, key) = settings[1];
+            Problem {
+                line: find_setting(&lines, &[section.to_string(), key.to_string()])
+                    .map(|index| index + 1),
+                message: conflict_message(settings),
+            }
+        })
+        .collect();
+    // Reading stops at the first problem, so each is left out in turn to find the next
+    loop {
+        let err = match toml::from_str::<Config>(&lines.join("\n")) {
+            Ok(_) => break,
+            Err(err) => err,
+        };
+        let index = find_setting(&lines, &error_path(&err));
+        problems.push(Problem {
+            line: index.map(|index| index + 1),
+            message: without_position(&err),
+        });
+        match index {
+            Some(index) => leave_out(&mut lines, index),
+            None => break,
+        }
+    }
+    problems.sort_by_key(|problem| problem.line);
+    problems
+}
+
+/// The message of the TOML error without where it is, which is the start of a table rather than
+/// the line of the setting for errors in values.
+fn without_position(err: &toml::de::Error) -> String {
+    let message = err.to_string();
+    match message.rfind(" at line ") {
+        Some(position) => message[..position].to_string(),
+        None => message,
+    }
+}
+
+/// The path of the setting the error is about, from `for key` in its message and the name of an
+/// unknown setting.
+fn error_path(err: &toml::de::Error) -> Vec<String> {
+    let message = without_position(err);
+    let mut path = match message.rfind(" for key `") {
+        Some(position) => split_key(message[position + 10..].trim_end_matches('`')),
+        None => Vec::new(),
+    };
+    if let Some(name) = message.strip_prefix("unknown field `") {
+        path.push(name[..name.find('`').unwrap_or(name.len())].to_string());
+    }
+    path
+}
+
+/// Splits a dotted key into its parts, without quotes.
+fn split_key(key: &str) -> Vec<String> {
+    key.split('.')
+        .map(|part| part.trim().trim_matches('"').to_string())
+        .collect()
+}
+
+/// The index of the line setting the value at the path, or starting a table at or within it.
+fn find_setting(lines: &[&str], path: &[String]) -> Option<usize> {
+    if path.is_empty() {
+        return None;
+    }
+    let mut table = Vec::new();
+    for (index, line) in lines.iter().enumerate() {
+        let line = line.trim();
+        let key = if line.starts_with('[') {
+            table = split_key(line.trim_matches(|c| c == '[' || c == ']'));
+            table.clone()
+        } else {
+            match line.split_once('=') {
+                Some((key, _)) if !line.starts_with('#') => {
+                    table.iter().cloned().chain(split_key(key)).collect()
+                }
+                _ => continue,
+            }
+        };
+        if key.starts_with(path) {
+            return Some(index);
+        }
+    }
+    None
+}
+
+/// Blanks out the line at the index, and all of its table if it starts one.
+fn leave_out(lines: &mut [&str], index: usize) {
+    let is_table = lines[index].trim().starts_with('[');
+    lines[index] = "";
+    if is_table {
+        for line in lines[index + 1..]
+            .iter_mut()
+            .take_while(|line| !line.trim().starts_with('['))
+        {
+            *line = "";
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    Toml(toml::de::Error),
+    Serialize(toml::ser::Error),
+    /// The first problem found by `lint`.
+    Invalid(Problem),
+}
+
+impl From<io::Error> for ConfigError {
+    fn from(err: io::Error) -> Self {
+        ConfigError::Io(err)
+    }
+}
+
+impl From<toml::de::Error> for ConfigError {
+    fn from(err: toml::de::Error) -> Self {
+        ConfigError::Toml(err)
+    }
+}
+
+impl From<toml::ser::Error> for ConfigError {
+    fn from(err: toml::ser::Error) -> Self {
+        ConfigError::Serialize(err)
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(err) => err.fmt(f),
+            ConfigError::Toml(err) => err.fmt(f),
+            ConfigError::Serialize(err) => err.fmt(f),
+            ConfigError::Invalid(problem) => problem.fmt(f),
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calendar::Country;
+
+    #[test]
+    fn empty_config_is_default() {
+        let config: Config = toml::from_str("").unwrap();
+
+        assert_eq!(config, Config::default());
+    }
+
+    #[test]
+    fn parse_calendar_config() {
+        let config: Config =
+            toml::from_str("[calendar]\ncountry = \"SE\"\nhours_per_day = 8.0").unwrap();
+
+        assert_eq!(config.calendar.country, Some(Country::Sweden));
+        assert_eq!(config.calendar.work_days.len(), 5);
+        assert!(toml::from_str::<Config>("[calendar]\ncountry = \"XX\"").is_err());
+    }
+
+    #[test]
+    fn parse_stop_config() {
+        let config: Config = toml::from_str("[stop]\nmax_running_duration = \"12h\"").unwrap();
+        assert_eq!(config.stop.max_running_duration, Some(Duration::hours(12)));
+
+        assert!(toml::from_str::<Config>("[stop]\nmax_running_duration = \"12\"").is_err());
+
+        let config: Config = toml::from_str("[stop]\nauto_stop_at = \"18:30\"").unwrap();
+        assert_eq!(
+            config.stop.auto_stop_at,
+            Some(NaiveTime::from_hms(18, 30, 0))
+        );
+        assert!(toml::from_str::<Config>("[stop]\nauto_stop_at = \"6pm\"").is_err());
+    }
+
+    #[test]
+    fn parse_team_config() {
+        let config: Config =
+            toml::from_str("[team]\nrecord_user = true\nuser = \"alice\"").unwrap();
+        assert_eq!(config.team.user(), Some("alice".to_string()));
+        let config: Config = toml::from_str("[team]\nuser = \"alice\"").unwrap();
+        assert_eq!(config.team.user(), None);
+
+        assert!(toml::from_str::<Config>("[team]\nuser = \"Alice Smith\"").is_err());
+    }
+
+    #[test]
+    fn parse_backup_config() {
+        let config: Config = toml::from_str("[backup]\nkeep = 5\nbefore_changes = true").unwrap();
+
+        assert_eq!(config.backup.keep, Some(5));
+        assert!(config.backup.before_changes);
+        assert_eq!(config.backup.dir, None);
+    }
+
+    #[test]
+    fn classify_daily_totals() {
+        let config: Config =
+            toml::from_str("[thresholds]\ndaily_warning = \"6h\"\ndaily_limit = \"9h\"").unwrap();
+        let level = |hours| config.thresholds.daily_level(Duration::hours(hours));
+
+        assert_eq!(level(5), Some(ThresholdLevel::Below));
+        assert_eq!(level(6), Some(ThresholdLevel::Warning));
+        assert_eq!(level(9), Some(ThresholdLevel::Warning));
+        assert_eq!(level(10), Some(ThresholdLevel::Exceeded));
+        assert_eq!(
+            ThresholdConfig::default().daily_level(Duration::hours(10)),
+            None
+        );
+    }
+
+    #[test]
+    fn map_repositories_to_accounts() {
+        let config: Config = toml::from_str(
+            "[git]\ndescription = \"commit\"\n\
+             [git.accounts]\n\"/home/me/src/site\" = \"ClientA:Site\"\ntimetracker = \"Personal\"",
+        )
+        .unwrap();
+
+        assert_eq!(config.git.description, GitDescription::Commit);
+        assert_eq!(
+            config.git.account(Path::new("/home/me/src/site")),
+            "ClientA:Site"
+        );
+        assert_eq!(
+            config.git.account(Path::new("/home/me/src/timetracker")),
+            "Personal"
+        );
+        assert_eq!(config.git.account(Path::new("/tmp/site")), "site");
+    }
+
+    #[test]
+    fn parse_sync_config() {
+        let config: Config =
+            toml::from_str("[sync]\nauto_commit = true\noplog = true\nmachine = \"laptop\"")
+                .unwrap();
+
+        assert!(config.sync.auto_commit);
+        assert!(config.sync.oplog);
+        assert_eq!(config.sync.machine.as_deref(), Some("laptop"));
+        assert!(!config.stop.split_days);
+    }
+
+    #[test]
+    fn lint_every_problem() {
+        let contents = "\
+always_ask_descripton = true
+
+[storage]
+single_file = true
+index = true
+
+[stop]
+max_running_duration = \"12\"
+
+[colors]
+dev = \"red\"
+
+[backup]
+keep = 5
+befor_changes = true
+";
+        let problems = lint(contents);
+        let lines: Vec<Option<usize>> = problems.iter().map(|problem| problem.line).collect();
+        assert_eq!(lines, vec![Some(1), Some(4), Some(8), Some(10), Some(15)]);
+        assert!(problems[0]
+            .message
+            .starts_with("unknown field `always_ask_descripton`"));
+        assert_eq!(
+            problems[1].message,
+            "`index` under [storage] cannot be combined with `single_file` under [storage]"
+        );
+        assert!(problems[2].message.contains("stop.max_running_duration"));
+        assert!(problems[4]
+            .message
+            .starts_with("unknown field `befor_changes`"));
+        assert!(toml::from_str::<Config>(contents).is_err());
+
+        assert_eq!(lint("[storage]\nkeep_sorted = true\n"), Vec::new());
+        let syntax = lint("[storage\nkeep_sorted = true\n");
+        assert_eq!(syntax.len(), 1);
+        assert_eq!(syntax[0].line, Some(1));
+    }
+
+    #[test]
+    fn parse_start_config() {
+        let config: Config = toml::from_str(
+            "[start]
+other_running = \"stop\"",
+        )
+        .unwrap();
+        assert_eq!(config.start.other_running, OtherRunning::Stop);
+
+        let config: Config = toml::from_str("").unwrap();
+        assert_eq!(config.start.other_running, OtherRunning::Ask);
+        assert!(toml::from_str::<Config>("[start]\nother_running = \"never\"").is_err());
+    }
+
+    #[test]
+    fn parse_reminder_config() {
+        let config: Config = toml::from_str(
+            "[reminders]\nmax_running_hours = 6\nwork_hours = \"08:30-16:00\"\nwork_days = [\"mon\"]",
+        )
+        .unwrap();
+
+        assert_eq!(config.reminders.max_running_hours, Some(6.0));
+        assert!(config.reminders.work_hours.is_some());
+        assert_eq!(config.reminders.work_days, vec!["mon".to_string()]);
+        assert!(toml::from_str::<Config>("[reminders]\nwork_hours = \"late\"").is_err());
+    }
+
+    #[test]
+    fn clockify_projects_match_sub_accounts() {
+        let config: Config = toml::from_str(
+            "[clockify]\nworkspace_id = \"ws\"\n[clockify.projects]\nClientA = \"p1\"\n\"ClientA:Support\" = \"p2\"",
+        )
+        .unwrap();
+
+        assert_eq!(config.clockify.workspace_id.as_deref(), Some("ws"));
+        assert_eq!(config.clockify.project_for("ClientA"), Some("p1"));
+        assert_eq!(config.clockify.project_for("ClientA:Meetings"), Some("p1"));
+        assert_eq!(
+            config.clockify.project_for("ClientA:Support:Tickets"),
+            Some("p2")
+        );
+        assert_eq!(config.clockify.project_for("ClientAB"), None);
+    }
+
+    #[test]
+    fn write_initial_config() {
+        let initial = InitialConfig {
+            work_days: vec!["mon".to_string(), "tue".to_string(), "wed".to_string()],
+            hours_per_day: Some(8.0),
+            country: Some(Country::Norway),
+        };
+        let config: Config = toml::from_str(&initial.to_toml()).unwrap();
+        assert_eq!(config.calendar.work_days, initial.work_days);
+        assert_eq!(config.calendar.hours_per_day, Some(8.0));
+        assert_eq!(config.calendar.country, Some(Country::Norway));
+
+        let defaults = InitialConfig {
+            work_days: CalendarConfig::default().work_days,
+            hours_per_day: None,
+            country: None,
+        };
+        assert_eq!(
+            toml::from_str::<Config>(&defaults.to_toml()).unwrap(),
+            Config::default()
+        );
+        assert!(!defaults.to_toml().contains("[calendar]"));
+    }
+
+    #[test]
+    fn slack_statuses_match_sub_accounts() {
+        let config: Config = toml::from_str(
+            "[slack]\nupdate_status = true\n\
+             [slack.statuses]\nClientA = { emoji = \":computer:\", text = \"Heads down\" }\n\
+             \"ClientA:Meetings\" = { emoji = \":calendar:\" }",
+        )
+        .unwrap();
+
+        assert!(config.slack.update_status);
+        assert_eq!(
+            config.slack.status_for("ClientA:Support").unwrap().text,
+            "Heads down"
+        );
+        let meetings = config.slack.status_for("ClientA:Meetings").unwrap();
+        assert_eq!(meetings.emoji, ":calendar:");
+        assert_eq!(meetings.text, "");
+        assert_eq!(config.slack.status_for("Internal"), None);
+    }
+
+    #[test]
+    fn parse_description_settings() {
+        let config: Config = toml::from_str(
+            "always_ask_description = true\ndefault_command = \"status\"\n[templates]\nstandup = \"Standup {date}\"",
+        )
+        .unwrap();
+
+        assert!(config.always_ask_description);
+        assert_eq!(config.default_command.as_deref(), Some("status"));
+        assert!(!config.start_unknown_commands);
+        assert_eq!(
+            config.templates.get("standup").map(String::as_str),
+            Some("Standup {date}")
+        );
+    }
+
+    #[test]
+    fn expand_and_shorten_aliases() {
+        let config: Config = toml::from_str(
+            "[aliases]
+dev = \"ClientA:ProjectX:Development\"\nx = \"ClientA:ProjectX\"",
+        )
+        .unwrap();
+
+        assert_eq!(config.expand_alias("dev"), "ClientA:ProjectX:Development");
+        assert_eq!(
+            config.expand_alias("dev:Meetings"),
+            "ClientA:ProjectX:Development:Meetings"
+        );
+        assert_eq!(config.expand_alias("Other:dev"), "Other:dev");
+        assert_eq!(
+            config.shorten_with_alias("ClientA:ProjectX:Development:Meetings"),
+            "dev:Meetings"
+        );
+        assert_eq!(
+            config.shorten_with_alias("ClientA:ProjectX:Design"),
+            "x:Design"
+        );
+        assert_eq!(config.shorten_with_alias("ClientA"), "ClientA");
+    }
+
+    #[test]
+    fn parse_goals() {
+        let config: Config = toml::from_str(
+            "[goals.ClientA]\nweekly = 20\nmonthly = 80.5\n[goals.\"ClientB:Support\"]\nweekly = 2\n\
+             [goals.\"#deep-work\"]\nweekly = 10",
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.goals(),
+            vec![
+                Goal {
+                    scope: Scope::Tag("deep-work".to_string()),
+                    period: Period::Week,
+                    target: Duration::hours(10),
+                },
+                Goal {
+                    scope: Scope::Account("ClientA".to_string()),
+                    period: Period::Week,
+                    target: Duration::hours(20),
+                },
+                Goal {
+                    scope: Scope::Account("ClientA".to_string()),
+                    period: Period::Month,
+                    target: Duration::minutes(80 * 60 + 30),
+                },
+                Goal {
+                    scope: Scope::Account("ClientB:Support".to_string()),
+                    period: Period::Week,
+                    target: Duration::hours(2),
+                },
+            ]
+        );
+    }
+}
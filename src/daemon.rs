@@ -0,0 +1,303 @@
+//! A long-lived process keeping the running entries in memory and answering for them over a Unix
+//! socket, so commands run over and over by status bars do not parse the files every time.
+//!
+//! A client writes a line with the request, `running`, and the path of the running file it would
+//! read, and gets back a line per running entry in the line format. The daemon only answers for
+//! the files it was started for, and clients fall back to reading the files themselves whenever
+//! it does not answer. It never writes the files, every change is still made by the command
+//! making it, and the daemon reads the running entries again when their file changed.
+//!
+//! Only the running entries are served, which is all that `tt prompt`, `tt status --short` and
+//! `tt running` need. Commands working with the completed entries, like `tt status` and reports,
+//! read the files themselves.
+
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use timetracker::storage::Storage;
+use timetracker::RunningEntry;
+
+/// The name of the socket, next to the running file.
+const SOCKET: &str = "daemon.sock";
+
+/// How long a client waits for an answer before reading the files itself.
+const TIMEOUT: Duration = Duration::from_millis(500);
+
+/// How long the daemon waits for a client before checking whether it is to stop.
+const POLL_TIMEOUT_MS: libc::c_int = 200;
+
+/// The running entries as of the last change of their file, by its modification time, size and
+/// inode, which changes when the file is replaced.
+struct Cache {
+    stamp: Option<(SystemTime, u64, u64)>,
+    running: Result<Vec<RunningEntry>, String>,
+}
+
+impl Cache {
+    fn running(&mut self, storage: &Storage) -> &Result<Vec<RunningEntry>, String> {
+        let stamp = fs::metadata(storage.running_path())
+            .and_then(|metadata| Ok((metadata.modified()?, metadata.len(), metadata.ino())))
+            .ok();
+        if stamp.is_none() || stamp != self.stamp {
+            log::debug!("reading the running entries");
+            self.running = storage.running_entries().map_err(|err| err.to_string());
+            self.stamp = stamp;
+        }
+        &self.running
+    }
+}
+
+/// Answers requests on the socket until `stop` returns true, then removes the socket. `running_path`
+/// is the path clients for the storage ask for, before the config is read.
+pub fn serve(
+    storage: &Storage,
+    socket: &Path,
+    running_path: &Path,
+    stop: impl Fn() -> bool,
+) -> io::Result<()> {
+    if UnixStream::connect(socket).is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AddrInUse,
+            format!("a daemon is already listening on {}", socket.display()),
+        ));
+    }
+    // Left behind by a daemon which did not exit cleanly
+    match fs::remove_file(socket) {
+        Ok(()) => log::info!("removed the stale socket {}", socket.display()),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+        Err(err) => return Err(err),
+    }
+    // The entries are only for the user, even if the files are encrypted, so the socket is
+    // created without access for others rather than restricted after it can be connected to
+    // Safety: setting the file mode mask has no memory effects
+    let umask = unsafe { libc::umask(0o077) };
+    let bound = UnixListener::bind(socket);
+    // Safety: as above
+    unsafe { libc::umask(umask) };
+    let listener = bound?;
+    fs::set_permissions(socket, fs::Permissions::from_mode(0o600))?;
+    eprintln!("Listening on {}", socket.display());
+
+    let mut cache = Cache {
+        stamp: None,
+        running: Ok(Vec::new()),
+    };
+    while !stop() {
+        if !wait_for_client(&listener)? {
+            continue;
+        }
+        let result = listener.accept().and_then(|(stream, _)| {
+            stream.set_read_timeout(Some(TIMEOUT))?;
+            answer(stream, storage, running_path, &mut cache)
+        });
+        if let Err(err) = result {
+            log::warn!("could not answer a request: {}", err);
+        }
+    }
+    log::info!("stopping, removing {}", socket.display());
+    fs::remove_file(socket)
+}
+
+/// Waits a while for a client to connect, returning whether one did. A signal ends the wait
+/// early.
+fn wait_for_client(listener: &UnixListener) -> io::Result<bool> {
+    let mut poll = libc::pollfd {
+        fd: listener.as_raw_fd(),
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    // Safety: the call only writes the events which happened into the struct
+    match unsafe { libc::poll(&mut poll, 1, POLL_TIMEOUT_MS) } {
+        -1 => match io::Error::last_os_error() {
+            err if err.kind() == io::ErrorKind::Interrupted => Ok(false),
+            err => Err(err),
+        },
+        ready => Ok(ready > 0),
+    }
+}
+
+fn answer(
+    stream: UnixStream,
+    storage: &Storage,
+    running_path: &Path,
+    cache: &mut Cache,
+) -> io::Result<()> {
+    let mut request = String::new();
+    // Connecting without a request checks whether a daemon is listening
+    if BufReader::new(&stream).read_line(&mut request)? == 0 {
+        return Ok(());
+    }
+    let mut stream = stream;
+    let response = match request.trim_end().split_once(' ') {
+        Some(("running", path)) if Path::new(path) == running_path => {
+            match cache.running(storage) {
+                Ok(running) => running.iter().map(|entry| format!("{}\n", entry)).collect(),
+                Err(err) => format!("error {}\n", err),
+            }
+        }
+        Some(("running", _)) => "error the daemon is for other files\n".to_string(),
+        _ => "error unknown request\n".to_string(),
+    };
+    stream.write_all(response.as_bytes())
+}
+
+/// The running entries from the daemon for the running file, or `None` if there is no daemon for
+/// it or it failed to answer.
+pub fn running_entries(socket: &Path, running_path: &Path) -> Option<Vec<RunningEntry>> {
+    let stream = UnixStream::connect(socket).ok()?;
+    stream.set_read_timeout(Some(TIMEOUT)).ok()?;
+    stream.set_write_timeout(Some(TIMEOUT)).ok()?;
+    writeln!(&stream, "running {}", running_path.display()).ok()?;
+    let mut running = Vec::new();
+    for line in BufReader::new(&stream).lines() {
+        let line = line.ok()?;
+        if let Some(err) = line.strip_prefix("error ") {
+            log::debug!("the daemon could not answer: {}", err);
+            return None;
+        }
+        running.push(line.parse().ok()?);
+    }
+    Some(running)
+}
+
+/// The socket of the daemon for the running file.
+pub fn socket_path(running_path: &Path) -> PathBuf {
+    running_path.with_file_name(SOCKET)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread::{self, JoinHandle};
+    use std::time::Instant;
+    use tempfile::TempDir;
+
+    /// A daemon serving the files in a temporary directory on a thread of its own.
+    struct Daemon {
+        dir: TempDir,
+        stop: Arc<AtomicBool>,
+        thread: Option<JoinHandle<io::Result<()>>>,
+    }
+
+    impl Daemon {
+        fn start(running: &str) -> Self {
+            let dir = TempDir::new().unwrap();
+            fs::write(dir.path().join("running"), running).unwrap();
+            let mut daemon = Daemon {
+                dir,
+                stop: Arc::new(AtomicBool::new(false)),
+                thread: None,
+            };
+            daemon.thread = Some(daemon.serve());
+            let started = Instant::now();
+            while !daemon.socket().exists() {
+                assert!(started.elapsed() < Duration::from_secs(5), "no socket");
+                thread::sleep(Duration::from_millis(10));
+            }
+            daemon
+        }
+
+        fn serve(&self) -> JoinHandle<io::Result<()>> {
+            let (entries_path, running_path) = (self.path("entries"), self.running_path());
+            let socket = self.socket();
+            let stop = self.stop.clone();
+            thread::spawn(move || {
+                let storage = Storage::new(entries_path, running_path.clone());
+                serve(&storage, &socket, &running_path, || {
+                    stop.load(Ordering::SeqCst)
+                })
+            })
+        }
+
+        fn path(&self, name: &str) -> PathBuf {
+            self.dir.path().join(name)
+        }
+
+        fn running_path(&self) -> PathBuf {
+            self.path("running")
+        }
+
+        fn socket(&self) -> PathBuf {
+            socket_path(&self.running_path())
+        }
+
+        fn running(&self) -> Option<Vec<RunningEntry>> {
+            running_entries(&self.socket(), &self.running_path())
+        }
+
+        fn stop(mut self) -> io::Result<()> {
+            self.stop.store(true, Ordering::SeqCst);
+            self.thread.take().unwrap().join().unwrap()
+        }
+    }
+
+    fn running(lines: &[&str]) -> Vec<RunningEntry> {
+        lines.iter().map(|line| line.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn serve_running_entries_until_stopped() {
+        let daemon = Daemon::start("2021-07-01T09:00:00Z dev #x\n");
+        assert_eq!(
+            daemon.running(),
+            Some(running(&["2021-07-01T09:00:00Z dev #x"]))
+        );
+
+        // Changes made by other commands are read again
+        fs::write(
+            daemon.running_path(),
+            "2021-07-01T09:00:00Z dev #x\n2021-07-01T10:00:00Z meetings\n",
+        )
+        .unwrap();
+        assert_eq!(
+            daemon.running(),
+            Some(running(&[
+                "2021-07-01T09:00:00Z dev #x",
+                "2021-07-01T10:00:00Z meetings"
+            ]))
+        );
+
+        // Clients for other files read them themselves
+        assert_eq!(
+            running_entries(&daemon.socket(), &daemon.path("other")),
+            None
+        );
+
+        let socket = daemon.socket();
+        daemon.stop().unwrap();
+        assert!(!socket.exists());
+        assert_eq!(running_entries(&socket, &socket), None);
+    }
+
+    #[test]
+    fn only_let_the_user_connect() {
+        let daemon = Daemon::start("");
+        let mode = fs::metadata(daemon.socket()).unwrap().permissions().mode();
+        assert_eq!(mode & 0o077, 0, "{:o}", mode);
+        daemon.stop().unwrap();
+    }
+
+    #[test]
+    fn refuse_a_second_daemon_and_replace_stale_sockets() {
+        let daemon = Daemon::start("");
+        let storage = Storage::new(daemon.path("entries"), daemon.running_path());
+        let second = serve(&storage, &daemon.socket(), &daemon.running_path(), || true);
+        assert_eq!(second.unwrap_err().kind(), io::ErrorKind::AddrInUse);
+        daemon.stop().unwrap();
+
+        // A socket nobody listens on is what a daemon which did not exit cleanly leaves behind
+        let dir = TempDir::new().unwrap();
+        let socket = socket_path(&dir.path().join("running"));
+        drop(UnixListener::bind(&socket).unwrap());
+        assert!(socket.exists());
+        let storage = Storage::new(dir.path().join("entries"), dir.path().join("running"));
+        serve(&storage, &socket, &dir.path().join("running"), || true).unwrap();
+        assert!(!socket.exists());
+    }
+}
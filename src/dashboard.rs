@@ -0,0 +1,152 @@
+//! The page `tt serve` shows at `/`: the running entries, the totals of today and a chart of the
+//! week, as a single HTML document without scripts or external assets, so any browser on the
+//! network can show it, e.g. on a phone.
+
+use crate::accounts::Color;
+use crate::charts;
+use crate::duration::format_hours_minutes;
+use crate::export::Markup;
+use crate::report::{self, day_range};
+use crate::{Entry, RunningEntry};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc};
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+
+/// How often the page reloads itself, in seconds.
+const REFRESH_SECONDS: u32 = 60;
+
+const STYLE: &str = "body{font-family:sans-serif;margin:1em;max-width:40em}\
+                     table{border-collapse:collapse;width:100%}\
+                     td,th{padding:.3em;border-bottom:1px solid #ddd;text-align:left}\
+                     td:last-child,th:last-child{text-align:right}\
+                     svg{max-width:100%;height:auto}";
+
+/// Renders the dashboard as of now, with the days in the time zone. The entries should include
+/// those of the current week, running entries are counted up to now.
+pub fn render<Tz: TimeZone>(
+    running: &[RunningEntry],
+    entries: &[Entry],
+    now: DateTime<Utc>,
+    tz: &Tz,
+    colors: &BTreeMap<String, Color>,
+) -> String
+where
+    Tz::Offset: fmt::Display,
+{
+    let escape = |text: &str| Markup::Html.escape(text);
+    let today = report::local_date(now, tz);
+    let monday = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+    let week = day_range(monday, tz).start..day_range(monday + Duration::days(6), tz).end;
+
+    // The parts of the entries of each day of the week, the running ones stopping now
+    let mut days: BTreeMap<NaiveDate, Vec<Entry>> = (0..7)
+        .map(|day| (monday + Duration::days(day), Vec::new()))
+        .collect();
+    for part in entries
+        .iter()
+        .cloned()
+        .chain(running.iter().map(|entry| entry.clone().into_entry(now)))
+        .flat_map(|entry| report::split_at_midnight(&entry, tz))
+        .filter(|part| week.contains(&part.start))
+    {
+        days.entry(report::local_date(part.start, tz))
+            .or_default()
+            .push(part);
+    }
+
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <meta http-equiv=\"refresh\" content=\"{}\">\n<title>tt</title>\n\
+         <style>{}</style>\n</head>\n<body>\n",
+        REFRESH_SECONDS, STYLE
+    );
+
+    html.push_str("<h2>Running</h2>\n");
+    if running.is_empty() {
+        html.push_str("<p>Nothing is running</p>\n");
+    } else {
+        html.push_str("<table>\n");
+        for entry in running {
+            let mut what = entry.account.to_string();
+            for tag in &entry.tags {
+                what += &format!(" #{}", tag);
+            }
+            if let Some(description) = &entry.description {
+                what += &format!(" {}", description);
+            }
+            let _ = writeln!(
+                html,
+                "<tr><td>{}</td><td>since {}</td><td>{}</td></tr>",
+                escape(&what),
+                entry.start.with_timezone(tz).format("%H:%M"),
+                format_hours_minutes(now - entry.start)
+            );
+        }
+        html.push_str("</table>\n");
+    }
+
+    let _ = writeln!(html, "<h2>Today</h2>\n<table>");
+    let today_totals = report::totals_by_account(&days[&today]);
+    for (account, total) in &today_totals {
+        let _ = writeln!(
+            html,
+            "<tr><td>{}</td><td>{}</td></tr>",
+            escape(account),
+            format_hours_minutes(*total)
+        );
+    }
+    let total = today_totals
+        .iter()
+        .fold(Duration::zero(), |sum, (_, total)| sum + *total);
+    let _ = writeln!(
+        html,
+        "<tr><th>Total</th><th>{}</th></tr>\n</table>",
+        format_hours_minutes(total)
+    );
+
+    let days: Vec<(NaiveDate, Vec<(String, Duration)>)> = days
+        .into_iter()
+        .map(|(date, parts)| (date, report::totals_by_account(&parts)))
+        .collect();
+    let _ = writeln!(
+        html,
+        "<h2>Week {}</h2>\n{}",
+        monday.format("%G-W%V"),
+        charts::daily_bars(&days, colors)
+    );
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn render_running_today_and_week() {
+        let entries: Vec<Entry> = [
+            // The week before is left out
+            "2021-06-27T09:00:00Z 2021-06-27T17:00:00Z Old",
+            "2021-06-28T09:00:00Z 2021-06-28T12:00:00Z Dev",
+            "2021-07-01T09:00:00Z 2021-07-01T10:30:00Z <Support>",
+        ]
+        .iter()
+        .map(|s| Entry::from_str(s).unwrap())
+        .collect();
+        let running = vec![RunningEntry::from_str("2021-07-01T11:00:00Z Dev #x").unwrap()];
+        let now = DateTime::from_str("2021-07-01T12:15:00Z").unwrap();
+        let html = render(&running, &entries, now, &Utc, &BTreeMap::new());
+
+        assert!(html.contains("<tr><td>Dev #x</td><td>since 11:00</td><td>1h 15m</td></tr>"));
+        assert!(html.contains("<tr><td>&lt;Support&gt;</td><td>1h 30m</td></tr>"));
+        assert!(html.contains("<tr><th>Total</th><th>2h 45m</th></tr>"));
+        assert!(html.contains("<h2>Week 2021-W26</h2>\n<svg"));
+        assert!(!html.contains("Old"));
+
+        let idle = render(&[], &[], now, &Utc, &BTreeMap::new());
+        assert!(idle.contains("<p>Nothing is running</p>"));
+        assert!(idle.contains("<tr><th>Total</th><th>0h 00m</th></tr>"));
+    }
+}
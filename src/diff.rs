@@ -0,0 +1,186 @@
+//! Comparison of two sets of entries, e.g. the copies of the entries file on two machines.
+//!
+//! Entries are matched by their ID, so an entry whose start or account changed shows up as one
+//! entry only in each set.
+//!
+//! `merge` combines any number of sets into one, the building block for syncing copies.
+
+use crate::Entry;
+use std::collections::HashMap;
+
+/// An entry which is not the same in both sets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Difference {
+    OnlyFirst(Entry),
+    OnlySecond(Entry),
+    /// An entry with the same ID in both sets, but with other fields.
+    Changed {
+        first: Entry,
+        second: Entry,
+    },
+}
+
+impl Difference {
+    /// The entry of the difference, the first one if it is in both sets.
+    pub fn entry(&self) -> &Entry {
+        match self {
+            Difference::OnlyFirst(entry) | Difference::OnlySecond(entry) => entry,
+            Difference::Changed { first, .. } => first,
+        }
+    }
+
+    /// The names of the fields which differ between the entries of a changed entry.
+    pub fn changed_fields(&self) -> Vec<&'static str> {
+        let (first, second) = match self {
+            Difference::Changed { first, second } => (first, second),
+            _ => return Vec::new(),
+        };
+        let mut fields = Vec::new();
+        if first.stop != second.stop {
+            fields.push("stop");
+        }
+        if first.tags != second.tags {
+            fields.push("tags");
+        }
+        if first.billable != second.billable {
+            fields.push("billable");
+        }
+        if first.description != second.description {
+            fields.push("description");
+        }
+        fields
+    }
+}
+
+/// Finds the entries which are not the same in both sets, ordered by when they start.
+pub fn diff(first: &[Entry], second: &[Entry]) -> Vec<Difference> {
+    let mut unmatched: HashMap<String, &Entry> =
+        second.iter().map(|entry| (entry.id(), entry)).collect();
+    let mut differences = Vec::new();
+    for entry in first {
+        match unmatched.remove(&entry.id()) {
+            Some(other) if other == entry => {}
+            Some(other) => differences.push(Difference::Changed {
+                first: entry.clone(),
+                second: other.clone(),
+            }),
+            None => differences.push(Difference::OnlyFirst(entry.clone())),
+        }
+    }
+    // Keep the order of the second set for its own entries
+    differences.extend(
+        second
+            .iter()
+            .filter(|entry| unmatched.contains_key(&entry.id()))
+            .map(|entry| Difference::OnlySecond(entry.clone())),
+    );
+    differences.sort_by_key(|difference| difference.entry().start);
+    differences
+}
+
+/// Sets of entries combined into one, see `merge`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Merged {
+    /// The entries of all sets, ordered by when they start.
+    pub entries: Vec<Entry>,
+    /// The number of entries left out for being exactly the same as another one.
+    pub duplicates: usize,
+    /// The entries with the same ID as an earlier one, but with other fields, each as changed from
+    /// the earlier one, which is the one kept.
+    pub conflicts: Vec<Difference>,
+}
+
+/// Combines the sets of entries, leaving out exact duplicates. Of entries with the same ID but
+/// other fields the one from the earliest set is kept, and the others are conflicts.
+pub fn merge(sets: &[Vec<Entry>]) -> Merged {
+    let mut kept: HashMap<String, usize> = HashMap::new();
+    let mut merged = Merged {
+        entries: Vec::new(),
+        duplicates: 0,
+        conflicts: Vec::new(),
+    };
+    for entry in sets.iter().flatten() {
+        match kept.get(&entry.id()) {
+            Some(&index) if merged.entries[index] == *entry => merged.duplicates += 1,
+            Some(&index) => merged.conflicts.push(Difference::Changed {
+                first: merged.entries[index].clone(),
+                second: entry.clone(),
+            }),
+            None => {
+                kept.insert(entry.id(), merged.entries.len());
+                merged.entries.push(entry.clone());
+            }
+        }
+    }
+    merged.entries.sort_by_key(|entry| entry.start);
+    merged
+        .conflicts
+        .sort_by_key(|conflict| conflict.entry().start);
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn entry(s: &str) -> Entry {
+        Entry::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn diff_entries() {
+        let first = vec![
+            entry("2021-07-03T08:00:00Z 2021-07-03T09:00:00Z Same"),
+            entry("2021-07-03T10:00:00Z 2021-07-03T11:00:00Z Client #dev"),
+            entry("2021-07-03T12:00:00Z 2021-07-03T13:00:00Z Laptop"),
+        ];
+        let second = vec![
+            entry("2021-07-03T08:00:00Z 2021-07-03T09:00:00Z Same"),
+            entry("2021-07-03T10:00:00Z 2021-07-03T11:30:00Z Client #dev ; Fix"),
+            entry("2021-07-03T09:00:00Z 2021-07-03T10:00:00Z Desktop"),
+        ];
+
+        let differences = diff(&first, &second);
+        assert_eq!(
+            differences,
+            vec![
+                Difference::OnlySecond(second[2].clone()),
+                Difference::Changed {
+                    first: first[1].clone(),
+                    second: second[1].clone()
+                },
+                Difference::OnlyFirst(first[2].clone()),
+            ]
+        );
+        assert_eq!(differences[1].changed_fields(), vec!["stop", "description"]);
+        assert!(diff(&first, &first).is_empty());
+    }
+
+    #[test]
+    fn merge_entries() {
+        let first = vec![
+            entry("2021-07-03T10:00:00Z 2021-07-03T11:00:00Z Client"),
+            entry("2021-07-03T08:00:00Z 2021-07-03T09:00:00Z Same"),
+        ];
+        let second = vec![
+            entry("2021-07-03T08:00:00Z 2021-07-03T09:00:00Z Same"),
+            entry("2021-07-03T09:00:00Z 2021-07-03T10:00:00Z Desktop"),
+            entry("2021-07-03T10:00:00Z 2021-07-03T11:30:00Z Client"),
+        ];
+
+        let merged = merge(&[first.clone(), second.clone(), first.clone()]);
+        assert_eq!(
+            merged.entries,
+            vec![first[1].clone(), second[1].clone(), first[0].clone()]
+        );
+        assert_eq!(merged.duplicates, 3);
+        assert_eq!(
+            merged.conflicts,
+            vec![Difference::Changed {
+                first: first[0].clone(),
+                second: second[2].clone()
+            }]
+        );
+    }
+}
@@ -0,0 +1,171 @@
+//! A plain-text summary of the time tracked in a period, e.g. for emailing every week.
+
+use crate::duration::format_hours_minutes;
+use crate::report;
+use crate::templates::{Context, Layout, Value};
+use crate::timesheet::Timesheet;
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+
+/// The layout of a digest without a template, which starts with a subject line so it can be piped
+/// into `sendmail -t` as it is.
+pub const DEFAULT_LAYOUT: &str = "Subject: Time tracked {since} to {until}\n\
+                                  \n\
+                                  Total: {total}\n\
+                                  \n\
+                                  {#accounts}\n\
+                                  {total:>8}  {account}\n\
+                                  {/accounts}\n\
+                                  {#any_notable}\n\
+                                  \n\
+                                  Longest entries:\n\
+                                  {#notable}\n\
+                                  {date}  {duration:>8}  {account}\
+                                  {#description}  {description}{/description}\n\
+                                  {/notable}\n\
+                                  {/any_notable}\n";
+
+/// A summary of the entries in a period, laid out by a template.
+///
+/// The layout is filled in with the `since` and `until` dates of the period, the `total` of it, a
+/// list of `accounts` each with the `account` and its `total`, and a list of the `notable`
+/// entries, the longest ones, each with the `date`, `weekday`, `account`, `tags`, `description`
+/// and `duration`, with `any_notable` for a section shown only if there are any. Totals and
+/// durations also come padded on the left, like `{total:>8}`.
+pub struct Digest<Tz> {
+    pub tz: Tz,
+    pub layout: Layout,
+    /// The number of notable entries.
+    pub notable: usize,
+}
+
+impl<Tz: TimeZone> Digest<Tz>
+where
+    Tz::Offset: fmt::Display,
+{
+    /// Summarizes the time tracked in the period, the entries of which have to be given.
+    pub fn format(&self, timesheet: &Timesheet, period: Range<DateTime<Utc>>) -> String {
+        let timesheet = timesheet.between(period.clone());
+        let date = |time: &DateTime<Utc>| time.with_timezone(&self.tz).format("%Y-%m-%d");
+
+        let mut context = Context::new();
+        context.insert("since".to_string(), text(date(&period.start).to_string()));
+        // The period ends at midnight, after its last day
+        let last = period.end - Duration::seconds(1);
+        context.insert("until".to_string(), text(date(&last).to_string()));
+        insert_duration(&mut context, "total", timesheet.total_duration());
+        let accounts = report::totals_by_account(timesheet.entries())
+            .into_iter()
+            .map(|(account, total)| {
+                let mut item = Context::new();
+                item.insert("account".to_string(), text(account));
+                insert_duration(&mut item, "total", total);
+                item
+            })
+            .collect();
+        context.insert("accounts".to_string(), Value::List(accounts));
+
+        let mut longest = timesheet.entries().to_vec();
+        // The longest first, the earliest of equally long ones
+        longest.sort_by_key(|entry| (entry.start - entry.stop, entry.start));
+        let notable = longest
+            .into_iter()
+            .take(self.notable)
+            .map(|entry| {
+                let start = entry.start.with_timezone(&self.tz);
+                let tags: Vec<String> = entry.tags.iter().map(|tag| format!("#{}", tag)).collect();
+                let mut item = Context::new();
+                item.insert(
+                    "date".to_string(),
+                    text(start.format("%Y-%m-%d").to_string()),
+                );
+                item.insert("weekday".to_string(), text(start.format("%A").to_string()));
+                item.insert("account".to_string(), text(entry.account.to_string()));
+                item.insert("tags".to_string(), text(tags.join(" ")));
+                item.insert(
+                    "description".to_string(),
+                    text(entry.description.clone().unwrap_or_default()),
+                );
+                insert_duration(&mut item, "duration", entry.stop - entry.start);
+                item
+            })
+            .collect::<Vec<_>>();
+        let any_notable = if notable.is_empty() { "" } else { "yes" };
+        context.insert("any_notable".to_string(), text(any_notable.to_string()));
+        context.insert("notable".to_string(), Value::List(notable));
+
+        self.layout.render(&context, |text| text.to_string())
+    }
+}
+
+impl<Tz> Digest<Tz> {
+    /// The digest with the default layout.
+    pub fn new(tz: Tz, notable: usize) -> Self {
+        Digest {
+            tz,
+            layout: Layout::from_str(DEFAULT_LAYOUT).expect("the default layout is valid"),
+            notable,
+        }
+    }
+}
+
+fn text(text: String) -> Value {
+    Value::Text(text)
+}
+
+/// Inserts the duration under the name, and padded to 8 characters under `<name>:>8`.
+fn insert_duration(context: &mut Context, name: &str, duration: Duration) {
+    let formatted = format_hours_minutes(duration);
+    context.insert(format!("{}:>8", name), text(format!("{:>8}", formatted)));
+    context.insert(name.to_string(), text(formatted));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Entry;
+    use chrono::Utc;
+
+    #[test]
+    fn format_digest() {
+        let timesheet: Timesheet = [
+            "2021-06-27T10:00:00Z 2021-06-27T11:00:00Z Before",
+            "2021-06-28T09:00:00Z 2021-06-28T12:30:00Z Client:Project #dev ; Release",
+            "2021-06-29T09:00:00Z 2021-06-29T10:00:00Z Client:Support",
+            "2021-06-30T13:00:00Z 2021-06-30T14:15:00Z Internal ; Planning",
+        ]
+        .iter()
+        .map(|line| line.parse::<Entry>().unwrap())
+        .collect();
+        let week = DateTime::from_str("2021-06-28T00:00:00Z").unwrap()
+            ..DateTime::from_str("2021-07-05T00:00:00Z").unwrap();
+
+        assert_eq!(
+            Digest::new(Utc, 2).format(&timesheet, week.clone()),
+            "Subject: Time tracked 2021-06-28 to 2021-07-04\n\
+             \n\
+             Total: 5h 45m\n\
+             \n\
+             \x20 3h 30m  Client:Project\n\
+             \x20 1h 00m  Client:Support\n\
+             \x20 1h 15m  Internal\n\
+             \n\
+             Longest entries:\n\
+             2021-06-28    3h 30m  Client:Project  Release\n\
+             2021-06-30    1h 15m  Internal  Planning\n"
+        );
+        // Entries without a description end at the account
+        assert!(Digest::new(Utc, 3)
+            .format(&timesheet, week.clone())
+            .ends_with("2021-06-29    1h 00m  Client:Support\n"));
+        assert_eq!(
+            Digest::new(Utc, 5).format(&Timesheet::new(Vec::new()), week),
+            "Subject: Time tracked 2021-06-28 to 2021-07-04\n\
+             \n\
+             Total: 0h 00m\n\
+             \n"
+        );
+    }
+}
@@ -0,0 +1,298 @@
+//! Formatting and parsing of durations for humans.
+
+use chrono::Duration;
+use serde::de::{self, Deserialize, Deserializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// Formats a duration as hours and minutes, e.g. `3h 05m`.
+pub fn format_hours_minutes(duration: Duration) -> String {
+    let sign = if duration < Duration::zero() { "-" } else { "" };
+    let minutes = duration.num_minutes().abs();
+    format!("{}{}h {:02}m", sign, minutes / 60, minutes % 60)
+}
+
+/// Reads an optional duration in a config file, written like `parse_duration` expects.
+pub fn deserialize_optional<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Duration>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|s| parse_duration(&s).map_err(de::Error::custom))
+        .transpose()
+}
+
+/// Parses an amount to move a time by, a duration like `parse_duration` expects with a sign, e.g.
+/// `+10m` or `-1h30m`.
+pub fn parse_signed_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    if let Some(duration) = s.strip_prefix('+') {
+        parse_duration(duration)
+    } else if let Some(duration) = s.strip_prefix('-') {
+        parse_duration(duration).map(|duration| -duration)
+    } else {
+        Err(format!(
+            r#"missing sign in "{}", expected e.g. "+10m" or "-15m""#,
+            s
+        ))
+    }
+}
+
+/// Parses a duration written as hours, minutes and seconds, e.g. `1h30m`, `15m` or `90s`.
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let invalid = || format!(r#"invalid duration "{}", expected e.g. "1h30m""#, s);
+    let mut duration = Duration::zero();
+    let mut number = String::new();
+    for c in s.trim().chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let value: i64 = number.parse().map_err(|_| invalid())?;
+        duration = duration
+            + match c {
+                'h' => Duration::hours(value),
+                'm' => Duration::minutes(value),
+                's' => Duration::seconds(value),
+                _ => return Err(invalid()),
+            };
+        number.clear();
+    }
+    if !number.is_empty() || s.trim().is_empty() {
+        return Err(invalid());
+    }
+    Ok(duration)
+}
+
+/// A way of writing durations, the other formats are meant for scripts and other tools.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DurationFormat {
+    /// Hours and minutes, e.g. `3h 05m`.
+    #[default]
+    HoursMinutes,
+    /// ISO 8601 durations, e.g. `PT3H5M`.
+    Iso8601,
+    /// Hours, minutes and seconds separated by colons, e.g. `3:05:00`.
+    Hms,
+    /// Hours with two decimals, e.g. `3.08`.
+    DecimalHours,
+}
+
+impl DurationFormat {
+    pub const NAMES: &'static [&'static str] =
+        &["hours-minutes", "iso8601", "hms", "decimal-hours"];
+
+    /// Writes the duration in the format, leaving out fractions of seconds.
+    pub fn format(self, duration: Duration) -> String {
+        let sign = if duration < Duration::zero() { "-" } else { "" };
+        let seconds = duration.num_seconds().abs();
+        let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+        match self {
+            DurationFormat::HoursMinutes => format_hours_minutes(duration),
+            DurationFormat::Iso8601 => {
+                let mut iso = format!("{}PT", sign);
+                if hours > 0 {
+                    iso += &format!("{}H", hours);
+                }
+                if minutes > 0 {
+                    iso += &format!("{}M", minutes);
+                }
+                if seconds > 0 || (hours == 0 && minutes == 0) {
+                    iso += &format!("{}S", seconds);
+                }
+                iso
+            }
+            DurationFormat::Hms => format!("{}{}:{:02}:{:02}", sign, hours, minutes, seconds),
+            DurationFormat::DecimalHours => {
+                format!("{:.2}", duration.num_seconds() as f64 / 3600.0)
+            }
+        }
+    }
+
+    /// Reads a duration written in the format.
+    pub fn parse(self, s: &str) -> Result<Duration, String> {
+        let invalid = || format!(r#"invalid {} duration "{}""#, self, s);
+        let (negative, unsigned) = match s.trim().strip_prefix('-') {
+            Some(unsigned) => (true, unsigned),
+            None => (false, s.trim()),
+        };
+        let duration = match self {
+            DurationFormat::HoursMinutes => parse_duration(&unsigned.replace(' ', ""))?,
+            DurationFormat::Iso8601 => parse_iso8601(unsigned).ok_or_else(invalid)?,
+            DurationFormat::Hms => {
+                let parts = unsigned
+                    .split(':')
+                    .map(|part| part.parse::<u32>().map_err(|_| invalid()))
+                    .collect::<Result<Vec<_>, _>>()?;
+                match parts.as_slice() {
+                    [hours, minutes, seconds] if *minutes < 60 && *seconds < 60 => {
+                        Duration::seconds(
+                            *hours as i64 * 3600 + *minutes as i64 * 60 + *seconds as i64,
+                        )
+                    }
+                    _ => return Err(invalid()),
+                }
+            }
+            DurationFormat::DecimalHours => {
+                let hours: f64 = unsigned.parse().map_err(|_| invalid())?;
+                if !hours.is_finite() || unsigned.starts_with(['-', '+']) {
+                    return Err(invalid());
+                }
+                Duration::seconds((hours * 3600.0).round() as i64)
+            }
+        };
+        Ok(if negative { -duration } else { duration })
+    }
+}
+
+/// Parses the time part of an ISO 8601 duration, along with days and weeks, which unlike months
+/// and years have a fixed length.
+fn parse_iso8601(s: &str) -> Option<Duration> {
+    let s = s.strip_prefix('P')?;
+    let (date, time) = match s.split_once('T') {
+        Some((date, time)) if !time.is_empty() => (date, Some(time)),
+        Some(_) => return None,
+        None => (s, None),
+    };
+    if date.is_empty() && time.is_none() {
+        return None;
+    }
+
+    let mut duration = Duration::zero();
+    for (part, units) in [(date, "WD"), (time.unwrap_or(""), "HMS")] {
+        let mut units = units.chars();
+        let mut number = String::new();
+        for c in part.chars() {
+            if c.is_ascii_digit() {
+                number.push(c);
+                continue;
+            }
+            // Each unit may appear once, in order
+            units.by_ref().find(|unit| *unit == c)?;
+            let value: i64 = number.parse().ok()?;
+            duration = duration
+                + match c {
+                    'W' => Duration::weeks(value),
+                    'D' => Duration::days(value),
+                    'H' => Duration::hours(value),
+                    'M' => Duration::minutes(value),
+                    _ => Duration::seconds(value),
+                };
+            number.clear();
+        }
+        if !number.is_empty() {
+            return None;
+        }
+    }
+    Some(duration)
+}
+
+impl fmt::Display for DurationFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            DurationFormat::HoursMinutes => "hours-minutes",
+            DurationFormat::Iso8601 => "iso8601",
+            DurationFormat::Hms => "hms",
+            DurationFormat::DecimalHours => "decimal-hours",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for DurationFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hours-minutes" => Ok(DurationFormat::HoursMinutes),
+            "iso8601" => Ok(DurationFormat::Iso8601),
+            "hms" => Ok(DurationFormat::Hms),
+            "decimal-hours" => Ok(DurationFormat::DecimalHours),
+            _ => Err(format!(r#"unknown duration format "{}""#, s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_as_hours_minutes() {
+        assert_eq!(format_hours_minutes(Duration::minutes(185)), "3h 05m");
+        assert_eq!(format_hours_minutes(Duration::seconds(59)), "0h 00m");
+        assert_eq!(format_hours_minutes(Duration::hours(-2)), "-2h 00m");
+    }
+
+    #[test]
+    fn parse_durations() {
+        assert_eq!(parse_duration("15m"), Ok(Duration::minutes(15)));
+        assert_eq!(parse_duration("1h30m"), Ok(Duration::minutes(90)));
+        assert_eq!(parse_duration("90s"), Ok(Duration::seconds(90)));
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("15").is_err());
+        assert!(parse_duration("2h 5s").is_err());
+        assert!(parse_duration("m").is_err());
+
+        assert_eq!(parse_signed_duration("+10m"), Ok(Duration::minutes(10)));
+        assert_eq!(parse_signed_duration("-1h30m"), Ok(Duration::minutes(-90)));
+        assert!(parse_signed_duration("10m").is_err());
+        assert!(parse_duration("1d").is_err());
+    }
+
+    #[test]
+    fn format_in_other_formats() {
+        let duration = Duration::seconds(3 * 3600 + 5 * 60 + 7);
+
+        assert_eq!(DurationFormat::Iso8601.format(duration), "PT3H5M7S");
+        assert_eq!(DurationFormat::Iso8601.format(Duration::hours(2)), "PT2H");
+        assert_eq!(DurationFormat::Iso8601.format(Duration::zero()), "PT0S");
+        assert_eq!(
+            DurationFormat::Iso8601.format(-Duration::minutes(5)),
+            "-PT5M"
+        );
+        assert_eq!(DurationFormat::Hms.format(duration), "3:05:07");
+        assert_eq!(DurationFormat::Hms.format(-Duration::hours(2)), "-2:00:00");
+        assert_eq!(DurationFormat::DecimalHours.format(duration), "3.09");
+        assert_eq!(
+            DurationFormat::DecimalHours.format(Duration::zero()),
+            "0.00"
+        );
+    }
+
+    #[test]
+    fn parse_other_formats() {
+        assert_eq!(
+            DurationFormat::Iso8601.parse("PT1H30M"),
+            Ok(Duration::minutes(90))
+        );
+        assert_eq!(
+            DurationFormat::Iso8601.parse("P1DT2S"),
+            Ok(Duration::days(1) + Duration::seconds(2))
+        );
+        assert!(DurationFormat::Iso8601.parse("P1M").is_err());
+        assert!(DurationFormat::Iso8601.parse("PT5M1H").is_err());
+        assert!(DurationFormat::Iso8601.parse("PT").is_err());
+        assert_eq!(
+            DurationFormat::Hms.parse("-1:02:03"),
+            Ok(-Duration::seconds(3723))
+        );
+        assert!(DurationFormat::Hms.parse("1:60:00").is_err());
+        assert_eq!(
+            DurationFormat::DecimalHours.parse("1.25"),
+            Ok(Duration::minutes(75))
+        );
+        assert_eq!(
+            DurationFormat::HoursMinutes.parse("3h 05m"),
+            Ok(Duration::minutes(185))
+        );
+    }
+
+    #[test]
+    fn parse_formatted_durations() {
+        for name in DurationFormat::NAMES {
+            let format: DurationFormat = name.parse().unwrap();
+            let duration = -Duration::minutes(90);
+            assert_eq!(format.parse(&format.format(duration)), Ok(duration));
+        }
+    }
+}
@@ -0,0 +1,162 @@
+//! Encryption of the data files at rest with age.
+
+use age::x25519::Identity;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::iter;
+use std::path::Path;
+use std::str::FromStr;
+
+/// An age X25519 secret key, which the data files are encrypted to and decrypted with.
+#[derive(Clone)]
+pub struct Key {
+    identity: Identity,
+}
+
+impl Key {
+    /// Reads the first secret key from an identity file, e.g. one created by `age-keygen`.
+    pub fn load(path: &Path) -> Result<Self, EncryptionError> {
+        fs::read_to_string(path)?
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty() && !line.starts_with('#'))
+            .ok_or(EncryptionError::InvalidKey)?
+            .parse()
+    }
+
+    /// Whether the contents are encrypted with age rather than plain text.
+    pub fn is_encrypted(contents: &[u8]) -> bool {
+        contents.starts_with(b"age-encryption.org/")
+    }
+
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, EncryptionError> {
+        let recipient = Box::new(self.identity.to_public());
+        let encryptor =
+            age::Encryptor::with_recipients(vec![recipient]).expect("there is a recipient");
+        let mut encrypted = Vec::new();
+        let mut writer = encryptor
+            .wrap_output(&mut encrypted)
+            .map_err(|err| EncryptionError::Encrypt(err.to_string()))?;
+        writer.write_all(plaintext)?;
+        writer.finish()?;
+        Ok(encrypted)
+    }
+
+    pub fn decrypt(&self, encrypted: &[u8]) -> Result<Vec<u8>, EncryptionError> {
+        let decryptor = match age::Decryptor::new(encrypted) {
+            Ok(age::Decryptor::Recipients(decryptor)) => decryptor,
+            Ok(_) => {
+                return Err(EncryptionError::Decrypt(
+                    "encrypted with a passphrase".into(),
+                ))
+            }
+            Err(err) => return Err(EncryptionError::Decrypt(err.to_string())),
+        };
+        let mut reader = decryptor
+            .decrypt(iter::once(&self.identity as &dyn age::Identity))
+            .map_err(|err| EncryptionError::Decrypt(err.to_string()))?;
+        let mut plaintext = Vec::new();
+        reader.read_to_end(&mut plaintext)?;
+        Ok(plaintext)
+    }
+
+    /// The public key the files are encrypted to, which is safe to show.
+    fn recipient(&self) -> String {
+        self.identity.to_public().to_string()
+    }
+}
+
+impl FromStr for Key {
+    type Err = EncryptionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let identity = s.trim().parse().map_err(|_| EncryptionError::InvalidKey)?;
+        Ok(Key { identity })
+    }
+}
+
+impl fmt::Debug for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Key")
+            .field("recipient", &self.recipient())
+            .finish_non_exhaustive()
+    }
+}
+
+impl PartialEq for Key {
+    fn eq(&self, other: &Self) -> bool {
+        self.recipient() == other.recipient()
+    }
+}
+
+impl Eq for Key {}
+
+#[derive(Debug)]
+pub enum EncryptionError {
+    Io(io::Error),
+    InvalidKey,
+    Encrypt(String),
+    Decrypt(String),
+}
+
+impl From<io::Error> for EncryptionError {
+    fn from(err: io::Error) -> Self {
+        EncryptionError::Io(err)
+    }
+}
+
+impl fmt::Display for EncryptionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EncryptionError::Io(err) => err.fmt(f),
+            EncryptionError::InvalidKey => write!(f, "invalid age secret key"),
+            EncryptionError::Encrypt(err) => write!(f, "could not encrypt: {}", err),
+            EncryptionError::Decrypt(err) => write!(f, "could not decrypt: {}", err),
+        }
+    }
+}
+
+impl Error for EncryptionError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key() -> Key {
+        Key {
+            identity: Identity::generate(),
+        }
+    }
+
+    #[test]
+    fn encrypt_and_decrypt() {
+        let key = key();
+        let encrypted = key.encrypt(b"2021-07-03T10:00:00Z Client\n").unwrap();
+
+        assert!(Key::is_encrypted(&encrypted));
+        assert!(!Key::is_encrypted(b"2021-07-03T10:00:00Z Client\n"));
+        assert_eq!(
+            key.decrypt(&encrypted).unwrap(),
+            b"2021-07-03T10:00:00Z Client\n"
+        );
+        assert!(matches!(
+            self::key().decrypt(&encrypted),
+            Err(EncryptionError::Decrypt(_))
+        ));
+    }
+
+    #[test]
+    fn parse_keys() {
+        let key = key();
+        let secret = age::secrecy::ExposeSecret::expose_secret(&key.identity.to_string()).clone();
+
+        assert_eq!(secret.parse::<Key>().unwrap(), key);
+        assert!(!format!("{:?}", key).contains(&secret));
+        assert!(matches!(
+            "AGE-SECRET-KEY-1".parse::<Key>(),
+            Err(EncryptionError::InvalidKey)
+        ));
+    }
+}
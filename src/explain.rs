@@ -0,0 +1,235 @@
+//! Explaining how a line of the data files is read, for finding out why a hand-edited line does
+//! not parse.
+
+use crate::{field_ranges, parse_meta, unescape, Entry, ParseError, RunningEntry};
+use chrono::{DateTime, Utc};
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+
+/// What a part of a line is read as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Start,
+    Stop,
+    Account,
+    Billable,
+    Category,
+    Tag,
+    Meta,
+    Description,
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Field::Start => "start",
+            Field::Stop => "stop",
+            Field::Account => "account",
+            Field::Billable => "billable",
+            Field::Category => "category",
+            Field::Tag => "tag",
+            Field::Meta => "metadata",
+            Field::Description => "description",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A part of a line and what it is read as.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub field: Field,
+    /// Where the part is, counting characters from 1, excluding the end.
+    pub columns: Range<usize>,
+    /// The part as written.
+    pub text: String,
+    /// What the part is read as, like the account without escapes, `None` if it cannot be read.
+    pub value: Option<String>,
+}
+
+/// How a line is split into its parts, and why it cannot be read if it cannot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Explanation {
+    pub tokens: Vec<Token>,
+    pub error: Option<ParseError>,
+}
+
+/// Explains how the line of a completed entry is read, as far as it can be.
+pub fn explain_entry(line: &str) -> Explanation {
+    let mut tokens = Vec::new();
+    if let Some((start, remainder)) = line.split_once(' ') {
+        tokens.push(date_token(line, Field::Start, 0, start));
+        if let Some((stop, fields)) = remainder.split_once(' ') {
+            tokens.push(date_token(line, Field::Stop, start.len() + 1, stop));
+            tokens.extend(field_tokens(line, line.len() - fields.len()));
+        }
+    }
+    Explanation {
+        tokens,
+        error: Entry::parse(line).err(),
+    }
+}
+
+/// Explains how the line of a running entry is read, as far as it can be.
+pub fn explain_running_entry(line: &str) -> Explanation {
+    let mut tokens = Vec::new();
+    if let Some((start, fields)) = line.split_once(' ') {
+        tokens.push(date_token(line, Field::Start, 0, start));
+        tokens.extend(field_tokens(line, line.len() - fields.len()));
+    }
+    Explanation {
+        tokens,
+        error: RunningEntry::from_str(line).err(),
+    }
+}
+
+/// The token of the part of the line at the byte range.
+fn token(line: &str, field: Field, range: Range<usize>, value: Option<String>) -> Token {
+    Token {
+        field,
+        columns: crate::column(line, range.start)..crate::column(line, range.end),
+        text: line[range].to_string(),
+        value,
+    }
+}
+
+fn date_token(line: &str, field: Field, offset: usize, date: &str) -> Token {
+    let value = DateTime::<Utc>::from_str(date)
+        .ok()
+        .map(|date| crate::format_datetime(&date));
+    token(line, field, offset..offset + date.len(), value)
+}
+
+/// The tokens of the fields following the timestamps, which start at the byte offset.
+fn field_tokens(line: &str, offset: usize) -> Vec<Token> {
+    let fields = &line[offset..];
+    let ranges = field_ranges(fields);
+    let at = |range: &Range<usize>| offset + range.start..offset + range.end;
+    let mut tokens = Vec::new();
+
+    let account = unescape(&fields[ranges.account.clone()]);
+    tokens.push(token(
+        line,
+        Field::Account,
+        at(&ranges.account),
+        Some(account).filter(|account| !account.is_empty()),
+    ));
+    if let Some(billable) = &ranges.billable {
+        tokens.push(token(
+            line,
+            Field::Billable,
+            at(billable),
+            Some("yes".into()),
+        ));
+    }
+    if let Some(category) = &ranges.category {
+        let value = fields[category.start + 1..category.end].to_string();
+        tokens.push(token(line, Field::Category, at(category), Some(value)));
+    }
+    for (start, word) in words(fields, &ranges.tags) {
+        let value = word.strip_prefix('#').map(str::to_string);
+        tokens.push(token(
+            line,
+            Field::Tag,
+            offset + start..offset + start + word.len(),
+            value,
+        ));
+    }
+    for (start, word) in words(fields, &ranges.meta) {
+        let value = parse_meta(word).map(|(key, value)| format!("{} = {}", key, value));
+        tokens.push(token(
+            line,
+            Field::Meta,
+            offset + start..offset + start + word.len(),
+            value,
+        ));
+    }
+    if let Some(description) = &ranges.description {
+        let value = unescape(&fields[description.clone()]);
+        tokens.push(token(
+            line,
+            Field::Description,
+            at(description),
+            Some(value),
+        ));
+    }
+    tokens
+}
+
+/// The words separated by spaces within the byte range, with their byte offsets.
+fn words<'a>(s: &'a str, range: &Range<usize>) -> impl Iterator<Item = (usize, &'a str)> {
+    let start = range.start;
+    s[range.clone()]
+        .split(' ')
+        .scan(start, |offset, word| {
+            let word_start = *offset;
+            *offset += word.len() + 1;
+            Some((word_start, word))
+        })
+        .filter(|(_, word)| !word.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(explanation: &Explanation) -> Vec<(Field, Range<usize>, &str)> {
+        explanation
+            .tokens
+            .iter()
+            .map(|token| (token.field, token.columns.clone(), token.text.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn explain_every_field() {
+        let explanation = explain_entry(
+            "2021-07-03T10:00:00Z 2021-07-03T12:00:00Z Client:A\\#1 $ @dev #a #b pr=4 ; Notes",
+        );
+
+        assert_eq!(explanation.error, None);
+        assert_eq!(
+            fields(&explanation),
+            vec![
+                (Field::Start, 1..21, "2021-07-03T10:00:00Z"),
+                (Field::Stop, 22..42, "2021-07-03T12:00:00Z"),
+                (Field::Account, 43..54, "Client:A\\#1"),
+                (Field::Billable, 55..56, "$"),
+                (Field::Category, 57..61, "@dev"),
+                (Field::Tag, 62..64, "#a"),
+                (Field::Tag, 65..67, "#b"),
+                (Field::Meta, 68..72, "pr=4"),
+                (Field::Description, 75..80, "Notes"),
+            ]
+        );
+        assert_eq!(explanation.tokens[2].value.as_deref(), Some("Client:A#1"));
+        assert_eq!(explanation.tokens[7].value.as_deref(), Some("pr = 4"));
+    }
+
+    #[test]
+    fn explain_errors() {
+        let explanation = explain_entry("2021-07-03T10:00:00Z 2021-13-03T12:00:00Z Client");
+        assert_eq!(explanation.error.as_ref().map(ParseError::column), Some(22));
+        assert_eq!(explanation.tokens[1].field, Field::Stop);
+        assert_eq!(explanation.tokens[1].value, None);
+
+        let explanation = explain_entry("2021-07-03T10:00:00Z Client");
+        assert!(matches!(
+            explanation.error,
+            Some(ParseError::MissingStop { column: 22 })
+        ));
+        assert_eq!(explanation.tokens.len(), 1);
+
+        let explanation = explain_running_entry("2021-07-03T10:00:00Z Client #a");
+        assert_eq!(explanation.error, None);
+        assert_eq!(
+            fields(&explanation),
+            vec![
+                (Field::Start, 1..21, "2021-07-03T10:00:00Z"),
+                (Field::Account, 22..28, "Client"),
+                (Field::Tag, 29..31, "#a"),
+            ]
+        );
+    }
+}
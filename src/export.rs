@@ -0,0 +1,1088 @@
+//! Output formats for exporting entries to other tools.
+
+use crate::accounts::Color;
+use crate::charts;
+use crate::duration::{format_hours_minutes, DurationFormat};
+use crate::journal;
+use crate::report::{self, split_at_midnight};
+use crate::templates::{Context, Layout, Value};
+use crate::timesheet::Timesheet;
+use crate::{Entry, RunningEntry};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+/// Formats entries as a document of some output format.
+pub trait EntryFormatter {
+    /// Formats the entries as a complete document.
+    fn format(&self, timesheet: &Timesheet) -> String;
+
+    /// Formats the entries to be appended to an existing document, or `None` if the format
+    /// cannot be appended to.
+    fn format_appended(&self, _timesheet: &Timesheet) -> Option<String> {
+        None
+    }
+
+    /// Leaves out the entries that an existing document already covers, so appending continues
+    /// after them. By default every entry is kept.
+    fn skip_existing(&self, _existing: &str, timesheet: Timesheet) -> Timesheet {
+        timesheet
+    }
+
+    /// Writes the entries as a complete document, calling `progress` with the number of entries
+    /// written so far. By default the document is formatted whole, formats with a line for each
+    /// entry write one line at a time instead, so large exports are not held in memory.
+    fn write(
+        &self,
+        timesheet: &Timesheet,
+        out: &mut dyn Write,
+        progress: &mut dyn FnMut(usize),
+    ) -> io::Result<()> {
+        out.write_all(self.format(timesheet).as_bytes())?;
+        progress(timesheet.entries().len());
+        Ok(())
+    }
+}
+
+/// Writes the lines of a document separated by line breaks, one for each entry after the header
+/// if there is one.
+fn write_lines(
+    header: Option<String>,
+    lines: impl Iterator<Item = String>,
+    out: &mut dyn Write,
+    progress: &mut dyn FnMut(usize),
+) -> io::Result<()> {
+    let mut first = true;
+    if let Some(header) = header {
+        out.write_all(header.as_bytes())?;
+        first = false;
+    }
+    for (written, line) in lines.enumerate() {
+        if !first {
+            out.write_all(b"\n")?;
+        }
+        out.write_all(line.as_bytes())?;
+        first = false;
+        progress(written + 1);
+    }
+    Ok(())
+}
+
+/// Timeclock check-ins and check-outs for hledger and ledger, with the times in the time zone.
+pub struct Timeclock<Tz>(pub Tz);
+
+impl<Tz: TimeZone> EntryFormatter for Timeclock<Tz>
+where
+    Tz::Offset: fmt::Display,
+{
+    fn format(&self, timesheet: &Timesheet) -> String {
+        timesheet
+            .entries()
+            .iter()
+            .map(|entry| entry.format_as_timeclock_in(&self.0))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    fn write(
+        &self,
+        timesheet: &Timesheet,
+        out: &mut dyn Write,
+        progress: &mut dyn FnMut(usize),
+    ) -> io::Result<()> {
+        let lines = timesheet
+            .entries()
+            .iter()
+            .map(|entry| entry.format_as_timeclock_in(&self.0));
+        write_lines(None, lines, out, progress)
+    }
+
+    fn format_appended(&self, timesheet: &Timesheet) -> Option<String> {
+        Some(self.format(timesheet))
+    }
+
+    fn skip_existing(&self, existing: &str, timesheet: Timesheet) -> Timesheet {
+        skip_until(timesheet, crate::latest_timeclock_check_in(existing))
+    }
+}
+
+/// hledger transactions with the hours of each account per day in the time zone, see
+/// `journal::format_transactions`.
+pub struct HledgerJournal<Tz>(pub Tz);
+
+impl<Tz: TimeZone> EntryFormatter for HledgerJournal<Tz> {
+    fn format(&self, timesheet: &Timesheet) -> String {
+        journal::format_transactions(timesheet, &self.0)
+    }
+
+    fn format_appended(&self, timesheet: &Timesheet) -> Option<String> {
+        Some(self.format(timesheet))
+    }
+
+    fn skip_existing(&self, existing: &str, timesheet: Timesheet) -> Timesheet {
+        match journal::latest_transaction_date(existing) {
+            Some(after) => timesheet
+                .entries()
+                .iter()
+                .filter(|entry| entry.start.with_timezone(&self.0).naive_local().date() > after)
+                .cloned()
+                .collect(),
+            None => timesheet,
+        }
+    }
+}
+
+/// Comma-separated values with a header row, the times in RFC 3339 in the time zone and the tags
+/// separated by spaces.
+pub struct Csv<Tz>(pub Tz);
+
+impl<Tz: TimeZone> Csv<Tz>
+where
+    Tz::Offset: fmt::Display,
+{
+    fn rows<'a>(&'a self, timesheet: &'a Timesheet) -> impl Iterator<Item = String> + 'a {
+        let datetime_format = "%Y-%m-%dT%H:%M:%S%:z";
+        timesheet.entries().iter().map(move |entry| {
+            [
+                entry
+                    .start
+                    .with_timezone(&self.0)
+                    .format(datetime_format)
+                    .to_string(),
+                entry
+                    .stop
+                    .with_timezone(&self.0)
+                    .format(datetime_format)
+                    .to_string(),
+                entry.account.to_string(),
+                entry.billable.to_string(),
+                entry.tags.join(" "),
+                entry.description.clone().unwrap_or_default(),
+            ]
+            .iter()
+            .map(|field| csv_field(field))
+            .collect::<Vec<_>>()
+            .join(",")
+        })
+    }
+}
+
+impl<Tz: TimeZone> EntryFormatter for Csv<Tz>
+where
+    Tz::Offset: fmt::Display,
+{
+    fn format(&self, timesheet: &Timesheet) -> String {
+        let mut rows = vec!["start,stop,account,billable,tags,description".to_string()];
+        rows.extend(self.rows(timesheet));
+        rows.join("\n")
+    }
+
+    fn write(
+        &self,
+        timesheet: &Timesheet,
+        out: &mut dyn Write,
+        progress: &mut dyn FnMut(usize),
+    ) -> io::Result<()> {
+        let header = "start,stop,account,billable,tags,description".to_string();
+        write_lines(Some(header), self.rows(timesheet), out, progress)
+    }
+
+    fn format_appended(&self, timesheet: &Timesheet) -> Option<String> {
+        Some(self.rows(timesheet).collect::<Vec<_>>().join("\n"))
+    }
+
+    fn skip_existing(&self, existing: &str, timesheet: Timesheet) -> Timesheet {
+        let latest = existing
+            .lines()
+            .filter_map(|line| DateTime::parse_from_rfc3339(line.split(',').next()?).ok())
+            .map(|start| start.with_timezone(&Utc))
+            .max();
+        skip_until(timesheet, latest)
+    }
+}
+
+/// Quotes the field if it contains a comma, a quote or a line break.
+pub(crate) fn csv_field(field: &str) -> String {
+    if field.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// A column of the tab-separated export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    Start,
+    Stop,
+    /// The duration in seconds.
+    Duration,
+    Account,
+    Billable,
+    /// The tags separated by spaces.
+    Tags,
+    Description,
+}
+
+impl Column {
+    /// The columns exported when none are given.
+    pub const DEFAULT: &'static [Column] = &[
+        Column::Start,
+        Column::Stop,
+        Column::Duration,
+        Column::Account,
+        Column::Description,
+        Column::Tags,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Column::Start => "start",
+            Column::Stop => "stop",
+            Column::Duration => "duration",
+            Column::Account => "account",
+            Column::Billable => "billable",
+            Column::Tags => "tags",
+            Column::Description => "description",
+        }
+    }
+}
+
+impl FromStr for Column {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "start" => Ok(Column::Start),
+            "stop" => Ok(Column::Stop),
+            "duration" => Ok(Column::Duration),
+            "account" => Ok(Column::Account),
+            "billable" => Ok(Column::Billable),
+            "tags" => Ok(Column::Tags),
+            "description" => Ok(Column::Description),
+            _ => Err(format!(
+                r#"unknown column "{}", expected start, stop, duration, account, billable, tags or description"#,
+                s
+            )),
+        }
+    }
+}
+
+/// Tab-separated values with a header row and the given columns, with the times in the time
+/// zone. Tabs and line breaks in the fields are replaced with spaces, since the format has no
+/// quoting.
+pub struct Tsv<Tz> {
+    pub tz: Tz,
+    pub columns: Vec<Column>,
+}
+
+impl<Tz: TimeZone> Tsv<Tz>
+where
+    Tz::Offset: fmt::Display,
+{
+    fn header(&self) -> String {
+        self.columns
+            .iter()
+            .map(|column| column.name())
+            .collect::<Vec<_>>()
+            .join("\t")
+    }
+
+    fn rows<'a>(&'a self, timesheet: &'a Timesheet) -> impl Iterator<Item = String> + 'a {
+        let datetime_format = "%Y-%m-%dT%H:%M:%S%:z";
+        timesheet.entries().iter().map(move |entry| {
+            self.columns
+                .iter()
+                .map(|column| {
+                    let field = match column {
+                        Column::Start => entry
+                            .start
+                            .with_timezone(&self.tz)
+                            .format(datetime_format)
+                            .to_string(),
+                        Column::Stop => entry
+                            .stop
+                            .with_timezone(&self.tz)
+                            .format(datetime_format)
+                            .to_string(),
+                        Column::Duration => (entry.stop - entry.start).num_seconds().to_string(),
+                        Column::Account => entry.account.to_string(),
+                        Column::Billable => entry.billable.to_string(),
+                        Column::Tags => entry.tags.join(" "),
+                        Column::Description => entry.description.clone().unwrap_or_default(),
+                    };
+                    field.replace(&['\t', '\n', '\r'][..], " ")
+                })
+                .collect::<Vec<_>>()
+                .join("\t")
+        })
+    }
+}
+
+impl<Tz: TimeZone> EntryFormatter for Tsv<Tz>
+where
+    Tz::Offset: fmt::Display,
+{
+    fn format(&self, timesheet: &Timesheet) -> String {
+        let mut rows = vec![self.header()];
+        rows.extend(self.rows(timesheet));
+        rows.join("\n")
+    }
+
+    fn write(
+        &self,
+        timesheet: &Timesheet,
+        out: &mut dyn Write,
+        progress: &mut dyn FnMut(usize),
+    ) -> io::Result<()> {
+        write_lines(Some(self.header()), self.rows(timesheet), out, progress)
+    }
+
+    fn format_appended(&self, timesheet: &Timesheet) -> Option<String> {
+        Some(self.rows(timesheet).collect::<Vec<_>>().join("\n"))
+    }
+
+    /// Continues after the latest start time in the start column, every entry is kept without
+    /// one.
+    fn skip_existing(&self, existing: &str, timesheet: Timesheet) -> Timesheet {
+        let position = match self
+            .columns
+            .iter()
+            .position(|column| *column == Column::Start)
+        {
+            Some(position) => position,
+            None => return timesheet,
+        };
+        let latest = existing
+            .lines()
+            .filter_map(|line| DateTime::parse_from_rfc3339(line.split('\t').nth(position)?).ok())
+            .map(|start| start.with_timezone(&Utc))
+            .max();
+        skip_until(timesheet, latest)
+    }
+}
+
+/// A JSON array of the entries, with the times in UTC.
+pub struct Json;
+
+impl EntryFormatter for Json {
+    fn format(&self, timesheet: &Timesheet) -> String {
+        serde_json::to_string_pretty(timesheet.entries()).expect("entries can be serialized")
+    }
+}
+
+/// An iCalendar calendar with an event for each entry, identified by `Entry::id`.
+pub struct Ics;
+
+impl EntryFormatter for Ics {
+    fn format(&self, timesheet: &Timesheet) -> String {
+        let mut lines = ics_calendar_start();
+        for entry in timesheet.entries() {
+            push_ics_event(&mut lines, entry, false);
+        }
+        lines.push("END:VCALENDAR".to_string());
+        lines.join("\r\n")
+    }
+}
+
+/// An iCalendar feed for calendar apps to subscribe to. Like `Ics`, with the running entries as
+/// tentative events lasting until the time, which keep their identifier once stopped.
+pub struct IcsFeed {
+    pub running: Vec<RunningEntry>,
+    pub now: DateTime<Utc>,
+}
+
+impl EntryFormatter for IcsFeed {
+    fn format(&self, timesheet: &Timesheet) -> String {
+        let mut lines = ics_calendar_start();
+        lines.push("X-WR-CALNAME:Time tracked".to_string());
+        // Ask subscribed calendars to refresh often, running entries grow all the time
+        lines.push("REFRESH-INTERVAL;VALUE=DURATION:PT5M".to_string());
+        lines.push("X-PUBLISHED-TTL:PT5M".to_string());
+        for entry in timesheet.entries() {
+            push_ics_event(&mut lines, entry, false);
+        }
+        for running in &self.running {
+            push_ics_event(&mut lines, &running.clone().into_entry(self.now), true);
+        }
+        lines.push("END:VCALENDAR".to_string());
+        lines.join("\r\n")
+    }
+}
+
+fn ics_calendar_start() -> Vec<String> {
+    vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//timetracker//EN".to_string(),
+    ]
+}
+
+fn push_ics_event(lines: &mut Vec<String>, entry: &Entry, running: bool) {
+    lines.push("BEGIN:VEVENT".to_string());
+    lines.push(format!("UID:{}@timetracker", entry.id()));
+    lines.push(format!("DTSTAMP:{}", ics_datetime(&entry.stop)));
+    lines.push(format!("DTSTART:{}", ics_datetime(&entry.start)));
+    lines.push(format!("DTEND:{}", ics_datetime(&entry.stop)));
+    lines.push(format!("SUMMARY:{}", ics_text(&entry.account)));
+    if running {
+        lines.push("STATUS:TENTATIVE".to_string());
+    }
+    if let Some(description) = &entry.description {
+        lines.push(format!("DESCRIPTION:{}", ics_text(description)));
+    }
+    if !entry.tags.is_empty() {
+        let tags: Vec<String> = entry.tags.iter().map(|tag| ics_text(tag)).collect();
+        lines.push(format!("CATEGORIES:{}", tags.join(",")));
+    }
+    lines.push("END:VEVENT".to_string());
+}
+
+fn ics_datetime(datetime: &DateTime<Utc>) -> String {
+    datetime.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Escapes the characters with a special meaning in iCalendar text values.
+fn ics_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// An Org mode heading for each account with the entries as clock lines in its logbook, newest
+/// first like Org mode adds them, with the times in the time zone.
+pub struct Org<Tz>(pub Tz);
+
+impl<Tz: TimeZone> EntryFormatter for Org<Tz>
+where
+    Tz::Offset: fmt::Display,
+{
+    fn format(&self, timesheet: &Timesheet) -> String {
+        timesheet
+            .group_by_account()
+            .into_iter()
+            .map(|(account, entries)| {
+                let mut heading = format!("* {}\n:LOGBOOK:\n", account);
+                for entry in entries.entries().iter().rev() {
+                    heading += &format!("{}\n", self.clock_line(entry));
+                }
+                heading + ":END:"
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+impl<Tz: TimeZone> Org<Tz>
+where
+    Tz::Offset: fmt::Display,
+{
+    fn clock_line(&self, entry: &Entry) -> String {
+        let timestamp_format = "[%Y-%m-%d %a %H:%M]";
+        let minutes = (entry.stop - entry.start).num_minutes();
+        format!(
+            "CLOCK: {}--{} => {:2}:{:02}",
+            entry.start.with_timezone(&self.0).format(timestamp_format),
+            entry.stop.with_timezone(&self.0).format(timestamp_format),
+            minutes / 60,
+            minutes % 60
+        )
+    }
+}
+
+/// A gnuplot data file with a line for each day in the time zone from the first to the last one
+/// with entries, the date and the hours tracked on it, or with a column of hours for each
+/// account, e.g. for `plot "hours.dat" using 1:2` with `set xdata time` and
+/// `set timefmt "%Y-%m-%d"`. A comment on the first line names the columns.
+pub struct Gnuplot<Tz> {
+    pub tz: Tz,
+    pub per_account: bool,
+}
+
+impl<Tz: TimeZone> EntryFormatter for Gnuplot<Tz> {
+    fn format(&self, timesheet: &Timesheet) -> String {
+        let accounts: Vec<String> = if self.per_account {
+            timesheet.group_by_account().into_keys().collect()
+        } else {
+            Vec::new()
+        };
+        let mut header = String::from("# date");
+        if self.per_account {
+            for account in &accounts {
+                header += &format!(" {:?}", account);
+            }
+        } else {
+            header += " hours";
+        }
+        let mut lines = vec![header];
+        let days = timesheet.group_by_day(&self.tz);
+        let (first, last) = match (days.keys().next(), days.keys().next_back()) {
+            (Some(first), Some(last)) => (*first, *last),
+            _ => return lines.join("\n"),
+        };
+        let hours =
+            |timesheet: &Timesheet| DurationFormat::DecimalHours.format(timesheet.total_duration());
+        let empty = Timesheet::default();
+        for date in first.iter_days().take_while(|date| *date <= last) {
+            let day = days.get(&date).unwrap_or(&empty);
+            let mut line = date.format("%Y-%m-%d").to_string();
+            if self.per_account {
+                let day = day.group_by_account();
+                for account in &accounts {
+                    line += " ";
+                    line += &day.get(account).map(hours).unwrap_or_else(|| hours(&empty));
+                }
+            } else {
+                line += " ";
+                line += &hours(day);
+            }
+            lines.push(line);
+        }
+        lines.join("\n")
+    }
+}
+
+/// A markup language for documents meant to be read by people.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Markup {
+    Markdown,
+    Html,
+}
+
+impl Markup {
+    /// A timesheet with a heading for each week and a table of the entries for each day, for HTML
+    /// a whole document which declares its encoding, so it reads right when opened as a file.
+    pub fn default_layout(self) -> Layout {
+        let layout = match self {
+            Markup::Markdown => {
+                "{#weeks}\n\
+                 # Week {week}\n\
+                 \n\
+                 {#days}\n\
+                 ## {weekday} {date}\n\
+                 \n\
+                 | Time | Account | Description | Duration |\n\
+                 | ---- | ------- | ----------- | -------: |\n\
+                 {#entries}\n\
+                 | {start}-{stop} | {account} | {description} | {duration} |\n\
+                 {/entries}\n\
+                 | | | **Total** | **{total}** |\n\
+                 \n\
+                 {/days}\n\
+                 **Week total: {total}**\n\
+                 \n\
+                 {/weeks}\n\
+                 **Total: {total}**\n"
+            }
+            Markup::Html => {
+                "<!DOCTYPE html>\n\
+                 <html>\n\
+                 <head>\n\
+                 <meta charset=\"utf-8\">\n\
+                 <title>Timesheet</title>\n\
+                 </head>\n\
+                 <body>\n\
+                 <h1>Timesheet</h1>\n\
+                 <p><strong>Total: {total}</strong></p>\n\
+                 {#day_chart}\n\
+                 {day_chart}\n\
+                 {account_chart}\n\
+                 {/day_chart}\n\
+                 {#weeks}\n\
+                 <h2>Week {week}</h2>\n\
+                 {#days}\n\
+                 <h3>{weekday} {date}</h3>\n\
+                 <table>\n\
+                 <tr><th>Time</th><th>Account</th><th>Description</th><th>Duration</th></tr>\n\
+                 {#entries}\n\
+                 <tr><td>{start}-{stop}</td><td>{account}</td><td>{description}</td><td>{duration}</td></tr>\n\
+                 {/entries}\n\
+                 <tr><th colspan=\"3\">Total</th><th>{total}</th></tr>\n\
+                 </table>\n\
+                 {/days}\n\
+                 <p><strong>Week total: {total}</strong></p>\n\
+                 {/weeks}\n\
+                 </body>\n\
+                 </html>\n"
+            }
+        };
+        Layout::from_str(layout).expect("the default layouts are valid")
+    }
+
+    pub(crate) fn escape(self, text: &str) -> String {
+        match self {
+            // Keep each entry on a single row of its table
+            Markup::Markdown => text.replace('|', "\\|").replace('\n', " "),
+            Markup::Html => text
+                .replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;")
+                .replace('"', "&quot;")
+                .replace('\n', "<br>"),
+        }
+    }
+}
+
+/// A timesheet for people to read, with the entries of each day and the totals of each day and
+/// week in the time zone, laid out by a template.
+///
+/// The layout is filled in with the `total` of every entry and a list of `weeks`, each with the
+/// `week` like `2021-W26`, its `total` and a list of `days`. Each day has the `date`, `weekday`,
+/// `total` and a list of `entries`, each with the `start` and `stop` time, `account`, `tags`,
+/// `description`, `duration` and `billable` (`$` if billable). Entries spanning midnight are
+/// split, so the days only have the time tracked on them.
+///
+/// HTML documents also have a `day_chart` with a bar of the hours of each day and an
+/// `account_chart` with a pie of the hours of each account, drawn as inline SVG in the colors of
+/// the accounts.
+pub struct TimesheetDocument<Tz> {
+    pub tz: Tz,
+    pub markup: Markup,
+    pub layout: Layout,
+    /// The colors of the accounts in charts, see `Color::for_account`.
+    pub colors: BTreeMap<String, Color>,
+}
+
+impl<Tz: TimeZone> EntryFormatter for TimesheetDocument<Tz>
+where
+    Tz::Offset: fmt::Display,
+{
+    fn format(&self, timesheet: &Timesheet) -> String {
+        let mut parts: Vec<Entry> = timesheet
+            .entries()
+            .iter()
+            .flat_map(|entry| split_at_midnight(entry, &self.tz))
+            .collect();
+        parts.sort_by_key(|entry| entry.start);
+
+        let text = |text: String| Value::Text(text);
+        let total = |entries: &[Entry]| {
+            let total = entries.iter().fold(Duration::zero(), |sum, entry| {
+                sum + (entry.stop - entry.start)
+            });
+            Value::Text(format_hours_minutes(total))
+        };
+        let mut weeks: Vec<Context> = Vec::new();
+        let mut week_entries: Vec<Entry> = Vec::new();
+        let mut days: Vec<Context> = Vec::new();
+        let mut day_entries: Vec<Entry> = Vec::new();
+        for (index, part) in parts.iter().enumerate() {
+            let date = part.start.with_timezone(&self.tz).naive_local().date();
+            day_entries.push(part.clone());
+            week_entries.push(part.clone());
+
+            // Close the day and the week after their last entry
+            let next = parts
+                .get(index + 1)
+                .map(|next| next.start.with_timezone(&self.tz).naive_local().date());
+            if next == Some(date) {
+                continue;
+            }
+            let mut day = Context::new();
+            day.insert(
+                "date".to_string(),
+                text(date.format("%Y-%m-%d").to_string()),
+            );
+            day.insert("weekday".to_string(), text(date.format("%A").to_string()));
+            day.insert("total".to_string(), total(&day_entries));
+            let entries = day_entries
+                .drain(..)
+                .map(|entry| self.entry_context(&entry))
+                .collect();
+            day.insert("entries".to_string(), Value::List(entries));
+            days.push(day);
+            if next.is_some_and(|next| next.iso_week() == date.iso_week()) {
+                continue;
+            }
+            let mut week = Context::new();
+            week.insert("week".to_string(), text(date.format("%G-W%V").to_string()));
+            week.insert("total".to_string(), total(&week_entries));
+            week.insert("days".to_string(), Value::List(std::mem::take(&mut days)));
+            weeks.push(week);
+            week_entries.clear();
+        }
+
+        let mut context = Context::new();
+        context.insert("total".to_string(), total(&parts));
+        context.insert("weeks".to_string(), Value::List(weeks));
+        if self.markup == Markup::Html {
+            let mut days: BTreeMap<NaiveDate, Vec<Entry>> = BTreeMap::new();
+            for part in &parts {
+                let date = part.start.with_timezone(&self.tz).naive_local().date();
+                days.entry(date).or_default().push(part.clone());
+            }
+            let days: Vec<(NaiveDate, Vec<(String, Duration)>)> = days
+                .into_iter()
+                .map(|(date, entries)| (date, report::totals_by_account(&entries)))
+                .collect();
+            context.insert(
+                "day_chart".to_string(),
+                Value::Markup(charts::daily_bars(&days, &self.colors)),
+            );
+            context.insert(
+                "account_chart".to_string(),
+                Value::Markup(charts::account_pie(
+                    &report::totals_by_account(&parts),
+                    &self.colors,
+                )),
+            );
+        }
+        self.layout
+            .render(&context, |text| self.markup.escape(text))
+            .trim_end()
+            .to_string()
+    }
+}
+
+impl<Tz: TimeZone> TimesheetDocument<Tz>
+where
+    Tz::Offset: fmt::Display,
+{
+    fn entry_context(&self, entry: &Entry) -> Context {
+        let time = |time: &DateTime<Utc>| time.with_timezone(&self.tz).format("%H:%M").to_string();
+        let tags: Vec<String> = entry.tags.iter().map(|tag| format!("#{}", tag)).collect();
+        let fields = vec![
+            ("start", time(&entry.start)),
+            ("stop", time(&entry.stop)),
+            ("account", entry.account.to_string()),
+            ("tags", tags.join(" ")),
+            ("description", entry.description.clone().unwrap_or_default()),
+            ("duration", format_hours_minutes(entry.stop - entry.start)),
+            (
+                "billable",
+                if entry.billable { "$" } else { "" }.to_string(),
+            ),
+        ];
+        fields
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), Value::Text(value)))
+            .collect()
+    }
+}
+
+/// Keeps the entries starting after the time, if any.
+fn skip_until(timesheet: Timesheet, after: Option<DateTime<Utc>>) -> Timesheet {
+    match after {
+        Some(after) => timesheet
+            .entries()
+            .iter()
+            .filter(|entry| entry.start > after)
+            .cloned()
+            .collect(),
+        None => timesheet,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::FixedOffset;
+    use std::str::FromStr;
+
+    fn entry(s: &str) -> Entry {
+        Entry::from_str(s).unwrap()
+    }
+
+    fn timesheet() -> Timesheet {
+        Timesheet::new(vec![
+            entry("2021-07-03T10:00:00Z 2021-07-03T11:30:00Z Client $ #dev ; Fix, then \"ship\""),
+            entry("2021-07-03T12:00:00Z 2021-07-03T12:15:00Z Other"),
+        ])
+    }
+
+    #[test]
+    fn format_timeclock() {
+        let timeclock = Timeclock(FixedOffset::east(2 * 3600));
+        assert_eq!(
+            timeclock.format(&timesheet()),
+            "i 2021-07-03 12:00:00+0200 Client  Fix, then \"ship\"  ; dev:\n\
+             o 2021-07-03 13:30:00+0200\n\
+             i 2021-07-03 14:00:00+0200 Other\no 2021-07-03 14:15:00+0200"
+        );
+
+        let existing = "i 2021-07-03 12:00:00+0200 Client\no 2021-07-03 13:30:00+0200\n";
+        let remaining = timeclock.skip_existing(existing, timesheet());
+        assert_eq!(remaining.entries().len(), 1);
+        assert_eq!(&*remaining.entries()[0].account, "Other");
+    }
+
+    #[test]
+    fn format_hledger_journal() {
+        let journal = HledgerJournal(Utc);
+        assert_eq!(
+            journal.format(&timesheet()),
+            "2021-07-03 Time tracked\n    (Client)  1.5h\n    (Other)  0.25h"
+        );
+        assert!(journal
+            .skip_existing("2021-07-03 Time tracked\n", timesheet())
+            .is_empty());
+    }
+
+    #[test]
+    fn format_csv() {
+        let csv = Csv(Utc);
+        assert_eq!(
+            csv.format(&timesheet()),
+            "start,stop,account,billable,tags,description\n\
+             2021-07-03T10:00:00+00:00,2021-07-03T11:30:00+00:00,Client,true,dev,\"Fix, then \"\"ship\"\"\"\n\
+             2021-07-03T12:00:00+00:00,2021-07-03T12:15:00+00:00,Other,false,,"
+        );
+        assert_eq!(
+            csv.format_appended(&timesheet()).unwrap().lines().count(),
+            2
+        );
+
+        let existing = csv.format(&timesheet());
+        assert!(csv.skip_existing(&existing, timesheet()).is_empty());
+    }
+
+    #[test]
+    fn format_tsv() {
+        let tsv = Tsv {
+            tz: Utc,
+            columns: Column::DEFAULT.to_vec(),
+        };
+        assert_eq!(
+            tsv.format(&timesheet()),
+            "start\tstop\tduration\taccount\tdescription\ttags\n\
+             2021-07-03T10:00:00+00:00\t2021-07-03T11:30:00+00:00\t5400\tClient\tFix, then \"ship\"\tdev\n\
+             2021-07-03T12:00:00+00:00\t2021-07-03T12:15:00+00:00\t900\tOther\t\t"
+        );
+        let existing = tsv.format(&timesheet());
+        assert!(tsv.skip_existing(&existing, timesheet()).is_empty());
+
+        let columns = "account, billable"
+            .split(',')
+            .map(Column::from_str)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let tsv = Tsv { tz: Utc, columns };
+        assert_eq!(
+            tsv.format_appended(&timesheet()).unwrap(),
+            "Client\ttrue\nOther\tfalse"
+        );
+        assert_eq!(
+            tsv.skip_existing("Client\ttrue", timesheet())
+                .entries()
+                .len(),
+            2
+        );
+        assert!(Column::from_str("hours").is_err());
+    }
+
+    #[test]
+    fn write_line_by_line() {
+        let formatters: Vec<Box<dyn EntryFormatter>> = vec![
+            Box::new(Timeclock(Utc)),
+            Box::new(Csv(Utc)),
+            Box::new(Tsv {
+                tz: Utc,
+                columns: Column::DEFAULT.to_vec(),
+            }),
+            Box::new(Json),
+        ];
+        for formatter in formatters {
+            let mut written = Vec::new();
+            let mut progress = Vec::new();
+            formatter
+                .write(&timesheet(), &mut written, &mut |count| {
+                    progress.push(count)
+                })
+                .unwrap();
+            assert_eq!(
+                String::from_utf8(written).unwrap(),
+                formatter.format(&timesheet())
+            );
+            assert_eq!(progress.last(), Some(&2));
+        }
+    }
+
+    #[test]
+    fn format_json() {
+        let json: serde_json::Value = serde_json::from_str(&Json.format(&timesheet())).unwrap();
+        assert_eq!(json[0]["account"], "Client");
+        assert_eq!(json[0]["billable"], true);
+        assert_eq!(json[1]["stop"], "2021-07-03T12:15:00Z");
+        assert!(Json.format_appended(&timesheet()).is_none());
+    }
+
+    #[test]
+    fn format_ics() {
+        let ics = Ics.format(&timesheet());
+        let lines: Vec<&str> = ics.split("\r\n").collect();
+        assert_eq!(lines.first(), Some(&"BEGIN:VCALENDAR"));
+        assert_eq!(lines.last(), Some(&"END:VCALENDAR"));
+        assert!(lines.contains(&"DTSTART:20210703T100000Z"));
+        assert!(lines.contains(&"DTEND:20210703T113000Z"));
+        assert!(lines.contains(&"DESCRIPTION:Fix\\, then \"ship\""));
+        assert!(lines.contains(&"CATEGORIES:dev"));
+        assert_eq!(
+            lines.iter().filter(|line| **line == "BEGIN:VEVENT").count(),
+            2
+        );
+    }
+
+    #[test]
+    fn format_ics_feed() {
+        let running = RunningEntry::from_str("2021-07-03T13:00:00Z Client #dev").unwrap();
+        let now = DateTime::from_str("2021-07-03T13:20:00Z").unwrap();
+        let feed = IcsFeed {
+            running: vec![running.clone()],
+            now,
+        }
+        .format(&timesheet());
+        let lines: Vec<&str> = feed.split("\r\n").collect();
+        assert!(lines.contains(&"REFRESH-INTERVAL;VALUE=DURATION:PT5M"));
+        assert_eq!(
+            lines.iter().filter(|line| **line == "BEGIN:VEVENT").count(),
+            3
+        );
+
+        // The running entry keeps its identifier once stopped
+        let stopped = running.into_entry(now);
+        let running_event = &lines[lines.len() - 10..];
+        assert_eq!(
+            running_event,
+            &[
+                "BEGIN:VEVENT",
+                &format!("UID:{}@timetracker", stopped.id()),
+                "DTSTAMP:20210703T132000Z",
+                "DTSTART:20210703T130000Z",
+                "DTEND:20210703T132000Z",
+                "SUMMARY:Client",
+                "STATUS:TENTATIVE",
+                "CATEGORIES:dev",
+                "END:VEVENT",
+                "END:VCALENDAR",
+            ][..]
+        );
+    }
+
+    #[test]
+    fn format_org() {
+        let timesheet = Timesheet::new(vec![
+            entry("2021-07-03T10:00:00Z 2021-07-03T11:30:00Z Client"),
+            entry("2021-07-03T12:00:00Z 2021-07-03T12:15:00Z Other"),
+            entry("2021-07-05T08:00:00Z 2021-07-05T18:05:00Z Client"),
+        ]);
+        assert_eq!(
+            Org(Utc).format(&timesheet),
+            "* Client\n\
+             :LOGBOOK:\n\
+             CLOCK: [2021-07-05 Mon 08:00]--[2021-07-05 Mon 18:05] => 10:05\n\
+             CLOCK: [2021-07-03 Sat 10:00]--[2021-07-03 Sat 11:30] =>  1:30\n\
+             :END:\n\
+             * Other\n\
+             :LOGBOOK:\n\
+             CLOCK: [2021-07-03 Sat 12:00]--[2021-07-03 Sat 12:15] =>  0:15\n\
+             :END:"
+        );
+    }
+
+    #[test]
+    fn format_gnuplot() {
+        let mut entries = timesheet().into_entries();
+        entries.push(entry("2021-07-05T09:00:00Z 2021-07-05T10:00:00Z Client"));
+        let timesheet = Timesheet::new(entries);
+        let gnuplot = Gnuplot {
+            tz: Utc,
+            per_account: false,
+        };
+        assert_eq!(
+            gnuplot.format(&timesheet),
+            "# date hours\n2021-07-03 1.75\n2021-07-04 0.00\n2021-07-05 1.00"
+        );
+        let gnuplot = Gnuplot {
+            tz: Utc,
+            per_account: true,
+        };
+        assert_eq!(
+            gnuplot.format(&timesheet),
+            "# date \"Client\" \"Other\"\n\
+             2021-07-03 1.50 0.25\n2021-07-04 0.00 0.00\n2021-07-05 1.00 0.00"
+        );
+        assert_eq!(gnuplot.format(&Timesheet::default()), "# date");
+    }
+
+    #[test]
+    fn format_timesheet_documents() {
+        let timesheet = Timesheet::new(vec![
+            entry("2021-07-04T21:00:00Z 2021-07-04T23:00:00Z Client ; Deploy | verify"),
+            entry("2021-07-03T10:00:00Z 2021-07-03T11:30:00Z Client $ #dev ; Fix <b>"),
+        ]);
+        let markdown = TimesheetDocument {
+            tz: FixedOffset::east(2 * 3600),
+            markup: Markup::Markdown,
+            layout: Markup::Markdown.default_layout(),
+            colors: BTreeMap::new(),
+        };
+        assert_eq!(
+            markdown.format(&timesheet),
+            "# Week 2021-W26\n\
+             \n\
+             ## Saturday 2021-07-03\n\
+             \n\
+             | Time | Account | Description | Duration |\n\
+             | ---- | ------- | ----------- | -------: |\n\
+             | 12:00-13:30 | Client | Fix <b> | 1h 30m |\n\
+             | | | **Total** | **1h 30m** |\n\
+             \n\
+             ## Sunday 2021-07-04\n\
+             \n\
+             | Time | Account | Description | Duration |\n\
+             | ---- | ------- | ----------- | -------: |\n\
+             | 23:00-00:00 | Client | Deploy \\| verify | 1h 00m |\n\
+             | | | **Total** | **1h 00m** |\n\
+             \n\
+             **Week total: 2h 30m**\n\
+             \n\
+             # Week 2021-W27\n\
+             \n\
+             ## Monday 2021-07-05\n\
+             \n\
+             | Time | Account | Description | Duration |\n\
+             | ---- | ------- | ----------- | -------: |\n\
+             | 00:00-01:00 | Client | Deploy \\| verify | 1h 00m |\n\
+             | | | **Total** | **1h 00m** |\n\
+             \n\
+             **Week total: 1h 00m**\n\
+             \n\
+             **Total: 3h 30m**"
+        );
+
+        let html = TimesheetDocument {
+            tz: Utc,
+            markup: Markup::Html,
+            layout: Layout::from_str(
+                "{#weeks}{#days}{#entries}{description}{billable};{/entries}{/days}{/weeks}",
+            )
+            .unwrap(),
+            colors: BTreeMap::new(),
+        };
+        assert_eq!(html.format(&timesheet), "Fix &lt;b&gt;$;Deploy | verify;");
+        let html = TimesheetDocument {
+            layout: Markup::Html.default_layout(),
+            ..html
+        };
+        let document = html.format(&timesheet);
+        assert!(document.starts_with("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">"));
+        assert!(document.ends_with("</body>\n</html>"));
+        assert_eq!(document.matches("<svg").count(), 2);
+        let heading = document
+            .find("<h1>Timesheet</h1>\n<p><strong>Total: 3h 30m</strong></p>")
+            .unwrap();
+        assert!(heading < document.find("<svg").unwrap());
+        let empty = html.format(&Timesheet::new(Vec::new()));
+        assert!(!empty.contains("<svg"));
+        assert!(
+            empty.contains("<h1>Timesheet</h1>\n<p><strong>Total: 0h 00m</strong></p>\n</body>")
+        );
+        assert!(Markup::Html
+            .default_layout()
+            .render(&Context::new(), str::to_string)
+            .contains("<p>"));
+    }
+}
@@ -0,0 +1,202 @@
+//! Named presets for starting recurring activities, e.g. `tt start @standup`.
+
+use crate::storage::StorageError;
+use crate::{parse_account_fields, write_account_fields, ParseError, RunningEntry};
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// The account, tags, category, metadata and description to start an entry with.
+///
+/// Written as the name followed by the fields of the line format, e.g.
+/// `standup Team:Meetings #meeting ; Daily standup`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Favorite {
+    pub name: String,
+    pub account: String,
+    pub tags: Vec<String>,
+    pub billable: bool,
+    pub category: Option<String>,
+    pub description: Option<String>,
+    pub meta: BTreeMap<String, String>,
+}
+
+impl Favorite {
+    /// A running entry with the fields of the favorite.
+    pub fn start(&self, start: DateTime<Utc>) -> RunningEntry {
+        RunningEntry {
+            start,
+            account: self.account.as_str().into(),
+            tags: self.tags.clone(),
+            billable: self.billable,
+            category: self.category.clone(),
+            description: self.description.clone(),
+            meta: self.meta.clone(),
+        }
+    }
+}
+
+impl fmt::Display for Favorite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ", self.name)?;
+        write_account_fields(
+            f,
+            &self.account,
+            self.billable,
+            self.category.as_deref(),
+            &self.tags,
+            &self.meta,
+            self.description.as_deref(),
+        )
+    }
+}
+
+impl FromStr for Favorite {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, remainder) = s.split_once(' ').ok_or(ParseError::MissingAccount {
+            column: s.chars().count() + 1,
+        })?;
+        let fields = parse_account_fields(remainder, name.chars().count() + 2)?;
+        Ok(Favorite {
+            name: name.to_string(),
+            account: fields.account,
+            tags: fields.tags,
+            billable: fields.billable,
+            category: fields.category,
+            description: fields.description,
+            meta: fields.meta,
+        })
+    }
+}
+
+/// Checks that a favorite name is a single word, so it can be written to the favorites file.
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains(char::is_whitespace)
+}
+
+/// The favorites file, with one favorite per line.
+#[derive(Debug)]
+pub struct Favorites {
+    path: PathBuf,
+    favorites: BTreeMap<String, Favorite>,
+}
+
+impl Favorites {
+    /// Reads the favorites at the given path, which is treated as empty if it does not exist.
+    pub fn load(path: &Path) -> Result<Self, StorageError> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err.into()),
+        };
+        let mut favorites = BTreeMap::new();
+        for (index, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let favorite = Favorite::from_str(line).map_err(|err| StorageError::Parse {
+                line: index + 1,
+                err,
+            })?;
+            favorites.insert(favorite.name.clone(), favorite);
+        }
+        Ok(Favorites {
+            path: path.to_path_buf(),
+            favorites,
+        })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Favorite> {
+        self.favorites.get(name)
+    }
+
+    /// The favorites ordered by name.
+    pub fn iter(&self) -> impl Iterator<Item = &Favorite> {
+        self.favorites.values()
+    }
+
+    /// Adds the favorite, replacing any with the same name, and writes the file.
+    pub fn add(&mut self, favorite: Favorite) -> Result<(), StorageError> {
+        self.favorites.insert(favorite.name.clone(), favorite);
+        self.save()
+    }
+
+    /// Removes the favorite with the name and writes the file, returning whether there was one.
+    pub fn remove(&mut self, name: &str) -> Result<bool, StorageError> {
+        if self.favorites.remove(name).is_none() {
+            return Ok(false);
+        }
+        self.save()?;
+        Ok(true)
+    }
+
+    fn save(&self) -> Result<(), StorageError> {
+        if let Some(parent) = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let contents: String = self
+            .favorites
+            .values()
+            .map(|favorite| format!("{}\n", favorite))
+            .collect();
+        fs::write(&self.path, contents)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_format_favorites() {
+        let line = "standup Team:Meetings $ #meeting #daily ; Daily standup";
+        let favorite = Favorite::from_str(line).unwrap();
+        assert_eq!(
+            favorite,
+            Favorite {
+                name: "standup".to_string(),
+                account: "Team:Meetings".to_string(),
+                tags: vec!["meeting".to_string(), "daily".to_string()],
+                billable: true,
+                category: None,
+                description: Some("Daily standup".to_string()),
+                meta: BTreeMap::new(),
+            }
+        );
+        assert_eq!(favorite.to_string(), line);
+        assert!(Favorite::from_str("standup").is_err());
+    }
+
+    #[test]
+    fn add_and_remove_favorites() {
+        let path = std::env::temp_dir()
+            .join(format!("timetracker-favorites-{}", std::process::id()))
+            .join("favorites");
+        let _ = fs::remove_file(&path);
+
+        let mut favorites = Favorites::load(&path).unwrap();
+        favorites
+            .add(Favorite::from_str("review Client #review").unwrap())
+            .unwrap();
+        favorites
+            .add(Favorite::from_str("standup Team ; Daily standup").unwrap())
+            .unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "review Client #review\nstandup Team ; Daily standup\n"
+        );
+
+        let mut favorites = Favorites::load(&path).unwrap();
+        assert_eq!(favorites.get("review").unwrap().account, "Client");
+        assert!(favorites.remove("review").unwrap());
+        assert!(!favorites.remove("review").unwrap());
+        assert_eq!(Favorites::load(&path).unwrap().iter().count(), 1);
+    }
+}
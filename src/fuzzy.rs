@@ -0,0 +1,97 @@
+//! Fuzzy matching of accounts for picking one interactively, scoring candidates containing the
+//! characters of a query in order like skim and fzf do.
+
+/// The score of every character of the query matched.
+const MATCH: i64 = 16;
+
+/// The bonus of a character matched right after the one before it.
+const CONSECUTIVE: i64 = 8;
+
+/// The bonus of a character matched at the start of a word, like the `P` of `ClientA:ProjectX`.
+const WORD_START: i64 = 10;
+
+/// The penalty of every character skipped between two matched characters.
+const GAP: i64 = 1;
+
+/// The score of the candidate for the query, or `None` if it does not contain the characters of
+/// the query in order. Case is ignored, and a higher score is a better match.
+pub fn score(query: &str, candidate: &str) -> Option<i64> {
+    let mut query = query.chars().flat_map(char::to_lowercase).peekable();
+    let mut score = 0;
+    let mut previous: Option<char> = None;
+    let mut last_match: Option<usize> = None;
+    for (index, c) in candidate.chars().enumerate() {
+        let wanted = match query.peek() {
+            Some(wanted) => *wanted,
+            None => break,
+        };
+        if c.to_lowercase().eq(std::iter::once(wanted)) {
+            score += MATCH;
+            match last_match {
+                Some(last) if last + 1 == index => score += CONSECUTIVE,
+                Some(last) => score -= GAP * (index - last - 1) as i64,
+                None => {}
+            }
+            let word_start = match previous {
+                Some(previous) => {
+                    !previous.is_alphanumeric() || (previous.is_lowercase() && c.is_uppercase())
+                }
+                None => true,
+            };
+            if word_start {
+                score += WORD_START;
+            }
+            last_match = Some(index);
+            query.next();
+        }
+        previous = Some(c);
+    }
+    if query.peek().is_some() {
+        return None;
+    }
+    Some(score)
+}
+
+/// The candidates matching the query, the best match first and otherwise in their order.
+pub fn matches<'a>(query: &str, candidates: &'a [String]) -> Vec<&'a str> {
+    let mut scored: Vec<(i64, &str)> = candidates
+        .iter()
+        .filter_map(|candidate| Some((score(query, candidate)?, candidate.as_str())))
+        .collect();
+    // The sort is stable, keeping candidates of the same score in order
+    scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+    scored.into_iter().map(|(_, candidate)| candidate).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn score_candidates() {
+        assert_eq!(score("", "Work"), Some(0));
+        assert_eq!(
+            score("cpx", "ClientA:ProjectX"),
+            score("CPX", "ClientA:ProjectX")
+        );
+        assert!(score("xp", "ClientA:ProjectX").is_none());
+        assert!(score("works", "Work").is_none());
+        // Consecutive characters and the starts of words count for more than scattered ones
+        assert!(score("work", "Work") > score("work", "Wxoxrxk"));
+        assert!(score("cp", "ClientA:ProjectX") > score("cp", "Accept"));
+    }
+
+    #[test]
+    fn rank_matches() {
+        let candidates: Vec<String> = ["Support", "ClientA:ProjectX", "Home", "ClientB:Prototype"]
+            .iter()
+            .map(|candidate| candidate.to_string())
+            .collect();
+        assert_eq!(
+            matches("pt", &candidates),
+            vec!["ClientB:Prototype", "ClientA:ProjectX", "Support"]
+        );
+        assert_eq!(matches("", &candidates).len(), 4);
+        assert!(matches("zzz", &candidates).is_empty());
+    }
+}
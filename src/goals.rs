@@ -0,0 +1,249 @@
+//! Hour targets per account or tag for weeks and months.
+
+use crate::report;
+use crate::Entry;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Period {
+    Week,
+    Month,
+}
+
+impl Period {
+    /// Finds the first day of the period containing the date and the first day of the next one.
+    ///
+    /// Weeks start on Mondays.
+    pub fn bounds(&self, date: NaiveDate) -> (NaiveDate, NaiveDate) {
+        match self {
+            Period::Week => {
+                let start = date - Duration::days(date.weekday().num_days_from_monday() as i64);
+                (start, start + Duration::weeks(1))
+            }
+            Period::Month => {
+                let start = NaiveDate::from_ymd(date.year(), date.month(), 1);
+                let end = if date.month() == 12 {
+                    NaiveDate::from_ymd(date.year() + 1, 1, 1)
+                } else {
+                    NaiveDate::from_ymd(date.year(), date.month() + 1, 1)
+                };
+                (start, end)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Period {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Period::Week => write!(f, "weekly"),
+            Period::Month => write!(f, "monthly"),
+        }
+    }
+}
+
+/// What the time of a goal is tracked on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Scope {
+    /// An account, including its sub-accounts.
+    Account(String),
+    /// Entries with a tag, on any account.
+    Tag(String),
+}
+
+impl Scope {
+    /// Reads a key of `[goals]`, a tag if it starts with `#` and otherwise an account.
+    pub fn parse(key: &str) -> Self {
+        match key.strip_prefix('#') {
+            Some(tag) => Scope::Tag(tag.to_string()),
+            None => Scope::Account(key.to_string()),
+        }
+    }
+
+    pub fn includes(&self, entry: &Entry) -> bool {
+        match self {
+            Scope::Account(account) => report::is_within_account(&entry.account, account),
+            Scope::Tag(tag) => entry.tags.contains(tag),
+        }
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Scope::Account(account) => write!(f, "{}", account),
+            Scope::Tag(tag) => write!(f, "#{}", tag),
+        }
+    }
+}
+
+/// A number of hours to track on an account or tag each period.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Goal {
+    pub scope: Scope,
+    pub period: Period,
+    pub target: Duration,
+}
+
+/// How far along a goal is in the current period.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Progress {
+    pub goal: Goal,
+    /// The first day of the period.
+    pub start: NaiveDate,
+    /// The first day after the period.
+    pub end: NaiveDate,
+    pub tracked: Duration,
+    /// The part of the target that should have been tracked by now if the time was spread evenly
+    /// over the period.
+    pub expected: Duration,
+}
+
+impl Progress {
+    /// The time left to track to reach the target, never negative.
+    pub fn remaining(&self) -> Duration {
+        std::cmp::max(self.goal.target - self.tracked, Duration::zero())
+    }
+
+    /// The tracked time as a fraction of the target.
+    pub fn fraction(&self) -> f64 {
+        if self.goal.target <= Duration::zero() {
+            return 1.0;
+        }
+        self.tracked.num_seconds() as f64 / self.goal.target.num_seconds() as f64
+    }
+
+    /// Whether more time than the target has been tracked, i.e. the budget is exceeded.
+    pub fn is_exceeded(&self) -> bool {
+        self.tracked > self.goal.target
+    }
+}
+
+/// Computes the progress of the goal in the period containing `now`.
+///
+/// Entries are bucketed into days in the time zone of `now`, running entries should be passed as
+/// entries stopping at `now` to be included.
+pub fn progress<Tz: TimeZone>(goal: &Goal, entries: &[Entry], now: &DateTime<Tz>) -> Progress {
+    let tz = now.timezone();
+    let (start, end) = goal.period.bounds(now.date().naive_local());
+
+    let goal_entries: Vec<Entry> = entries
+        .iter()
+        .filter(|entry| goal.scope.includes(entry))
+        .cloned()
+        .collect();
+    let tracked = report::totals_by_day(&goal_entries, &tz)
+        .into_iter()
+        .filter(|(date, _)| *date >= start && *date < end)
+        .fold(Duration::zero(), |sum, (_, total)| sum + total);
+
+    // Prorate the target by the part of the period that has passed
+    let period_start = report::start_of_day(start, &tz);
+    let period_end = report::start_of_day(end, &tz);
+    let elapsed = (now.with_timezone(&Utc) - period_start).num_seconds() as f64;
+    let length = (period_end - period_start).num_seconds() as f64;
+    let expected =
+        Duration::seconds((goal.target.num_seconds() as f64 * elapsed / length).round() as i64);
+
+    Progress {
+        goal: goal.clone(),
+        start,
+        end,
+        tracked,
+        expected,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use std::str::FromStr;
+
+    fn goal(period: Period, hours: i64) -> Goal {
+        Goal {
+            scope: Scope::Account("ClientA".to_string()),
+            period,
+            target: Duration::hours(hours),
+        }
+    }
+
+    #[test]
+    fn week_bounds() {
+        assert_eq!(
+            Period::Week.bounds(NaiveDate::from_ymd(2021, 7, 3)),
+            (
+                NaiveDate::from_ymd(2021, 6, 28),
+                NaiveDate::from_ymd(2021, 7, 5)
+            )
+        );
+    }
+
+    #[test]
+    fn month_bounds() {
+        assert_eq!(
+            Period::Month.bounds(NaiveDate::from_ymd(2021, 12, 24)),
+            (
+                NaiveDate::from_ymd(2021, 12, 1),
+                NaiveDate::from_ymd(2022, 1, 1)
+            )
+        );
+    }
+
+    #[test]
+    fn weekly_progress() {
+        let entries = vec![
+            // Previous week
+            Entry::from_str("2021-06-27T10:00:00Z 2021-06-27T18:00:00Z ClientA").unwrap(),
+            Entry::from_str("2021-06-28T10:00:00Z 2021-06-28T16:00:00Z ClientA:Dev").unwrap(),
+            Entry::from_str("2021-06-29T10:00:00Z 2021-06-29T16:00:00Z ClientB").unwrap(),
+            Entry::from_str("2021-07-01T10:00:00Z 2021-07-01T14:00:00Z ClientA").unwrap(),
+        ];
+        let now = DateTime::<Utc>::from_str("2021-07-01T12:00:00Z").unwrap();
+
+        let progress = progress(&goal(Period::Week, 20), &entries, &now);
+
+        assert_eq!(progress.tracked, Duration::hours(10));
+        assert_eq!(progress.remaining(), Duration::hours(10));
+        assert!(!progress.is_exceeded());
+        // 3.5 of 7 days have passed
+        assert_eq!(progress.expected, Duration::hours(10));
+    }
+
+    #[test]
+    fn tag_progress() {
+        let entries = vec![
+            Entry::from_str("2021-06-28T10:00:00Z 2021-06-28T13:00:00Z ClientA #deep-work")
+                .unwrap(),
+            Entry::from_str("2021-06-29T10:00:00Z 2021-06-29T12:00:00Z ClientB #deep-work")
+                .unwrap(),
+            Entry::from_str("2021-06-30T10:00:00Z 2021-06-30T16:00:00Z ClientA").unwrap(),
+        ];
+        let now = DateTime::<Utc>::from_str("2021-07-01T12:00:00Z").unwrap();
+        let goal = Goal {
+            scope: Scope::parse("#deep-work"),
+            period: Period::Week,
+            target: Duration::hours(10),
+        };
+
+        let progress = progress(&goal, &entries, &now);
+
+        assert_eq!(goal.scope.to_string(), "#deep-work");
+        assert_eq!(progress.tracked, Duration::hours(5));
+        assert_eq!(progress.remaining(), Duration::hours(5));
+    }
+
+    #[test]
+    fn exceeded_monthly_goal() {
+        let entries =
+            vec![Entry::from_str("2021-07-01T08:00:00Z 2021-07-01T20:00:00Z ClientA").unwrap()];
+        let now = DateTime::<Utc>::from_str("2021-07-02T12:00:00Z").unwrap();
+
+        let progress = progress(&goal(Period::Month, 10), &entries, &now);
+
+        assert!(progress.is_exceeded());
+        assert_eq!(progress.remaining(), Duration::zero());
+        assert!(progress.fraction() > 1.0);
+    }
+}
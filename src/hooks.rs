@@ -0,0 +1,186 @@
+//! User commands run when entries are started and stopped.
+
+use crate::{Entry, RunningEntry};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Write};
+use std::process::{Command, ExitStatus, Stdio};
+
+/// Shell commands to run on events, configured in the `[hooks]` section.
+///
+/// The commands get the entry as JSON on standard input, and its fields in the `TT_ACCOUNT`,
+/// `TT_START`, `TT_STOP` (when stopped), `TT_TAGS` (comma separated) and `TT_DESCRIPTION`
+/// environment variables. `TT_EVENT` is either `start` or `stop`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HookConfig {
+    pub on_start: Option<String>,
+    pub on_stop: Option<String>,
+}
+
+impl HookConfig {
+    /// Runs the start hook, if any, for an entry which was just started.
+    pub fn started(&self, entry: &RunningEntry) -> Result<(), HookError> {
+        match &self.on_start {
+            Some(command) => {
+                let mut vars = vec![("TT_START", entry.start.to_rfc3339())];
+                vars.extend(account_vars(
+                    &entry.account,
+                    &entry.tags,
+                    entry.description.as_deref(),
+                ));
+                run(command, "start", entry, &vars)
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Runs the stop hook, if any, for an entry which was just stopped.
+    pub fn stopped(&self, entry: &Entry) -> Result<(), HookError> {
+        match &self.on_stop {
+            Some(command) => {
+                let mut vars = vec![
+                    ("TT_START", entry.start.to_rfc3339()),
+                    ("TT_STOP", entry.stop.to_rfc3339()),
+                ];
+                vars.extend(account_vars(
+                    &entry.account,
+                    &entry.tags,
+                    entry.description.as_deref(),
+                ));
+                run(command, "stop", entry, &vars)
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+fn account_vars(
+    account: &str,
+    tags: &[String],
+    description: Option<&str>,
+) -> Vec<(&'static str, String)> {
+    vec![
+        ("TT_ACCOUNT", account.to_string()),
+        ("TT_TAGS", tags.join(",")),
+        ("TT_DESCRIPTION", description.unwrap_or("").to_string()),
+    ]
+}
+
+fn run<T: Serialize>(
+    command: &str,
+    event: &str,
+    entry: &T,
+    vars: &[(&str, String)],
+) -> Result<(), HookError> {
+    log::debug!("running {} hook {:?}", event, command);
+    let mut child = shell(command)
+        .env("TT_EVENT", event)
+        .envs(vars.iter().map(|(name, value)| (name, value)))
+        .stdin(Stdio::piped())
+        .spawn()?;
+
+    let json = serde_json::to_string(entry).expect("entries can be serialized");
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    // The command may exit without reading its input
+    match writeln!(stdin, "{}", json) {
+        Err(err) if err.kind() != io::ErrorKind::BrokenPipe => return Err(err.into()),
+        _ => drop(stdin),
+    }
+
+    let status = child.wait()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(HookError::Failed {
+            command: command.to_string(),
+            status,
+        })
+    }
+}
+
+#[cfg(not(windows))]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+#[derive(Debug)]
+pub enum HookError {
+    Io(io::Error),
+    Failed { command: String, status: ExitStatus },
+}
+
+impl From<io::Error> for HookError {
+    fn from(err: io::Error) -> Self {
+        HookError::Io(err)
+    }
+}
+
+impl fmt::Display for HookError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HookError::Io(err) => err.fmt(f),
+            HookError::Failed { command, status } => {
+                write!(f, "hook {:?} failed with {}", command, status)
+            }
+        }
+    }
+}
+
+impl Error for HookError {}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::str::FromStr;
+
+    #[test]
+    fn run_stop_hook() {
+        let output = std::env::temp_dir().join(format!("tt_hook_test_{}", std::process::id()));
+        let config = HookConfig {
+            on_start: None,
+            on_stop: Some(format!(
+                r#"echo "$TT_EVENT $TT_ACCOUNT $TT_STOP $TT_TAGS" > '{0}' && cat >> '{0}'"#,
+                output.display()
+            )),
+        };
+        let entry =
+            Entry::from_str("2021-07-03T10:00:00Z 2021-07-03T13:00:00Z dev #a #b ; note").unwrap();
+
+        config.stopped(&entry).unwrap();
+        let contents = fs::read_to_string(&output).unwrap();
+        fs::remove_file(&output).unwrap();
+
+        let mut lines = contents.lines();
+        assert_eq!(lines.next(), Some("stop dev 2021-07-03T13:00:00+00:00 a,b"));
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(lines.next().unwrap()).unwrap()["account"],
+            "dev"
+        );
+    }
+
+    #[test]
+    fn report_failing_hook() {
+        let config = HookConfig {
+            on_start: Some("exit 3".to_string()),
+            on_stop: None,
+        };
+        let entry = RunningEntry::from_str("2021-07-03T10:00:00Z dev").unwrap();
+
+        assert!(matches!(
+            config.started(&entry),
+            Err(HookError::Failed { .. })
+        ));
+    }
+}
@@ -0,0 +1,550 @@
+//! Conversion of the data files of other time trackers into entries.
+
+use crate::Entry;
+use chrono::offset::LocalResult;
+use chrono::{DateTime, FixedOffset, NaiveDateTime, TimeZone, Utc};
+use serde::de::IgnoredAny;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// An entry converted from a record of another time tracker.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportedEntry {
+    pub entry: Entry,
+    /// Identifies the record it was converted from, like `watson:a1b2`, so importing the record
+    /// again finds the entry instead of adding another. `None` if the record has no identifier.
+    pub source: Option<String>,
+}
+
+/// A frame as stored in Watson's `frames` file, which is a JSON array of these as arrays: the
+/// start, stop, project, ID, tags and last update time.
+#[derive(Debug, Deserialize)]
+struct WatsonFrame(
+    i64,
+    i64,
+    String,
+    String,
+    #[serde(default)] Vec<String>,
+    #[serde(default)] IgnoredAny,
+);
+
+/// Converts the frames of a Watson `frames` file into entries, with the project as the account
+/// and the tags kept as tags, ordered by start time. The source of each entry is `watson:` and the
+/// ID of its frame.
+///
+/// Whitespace in tags is replaced by dashes, as tags can't contain whitespace here.
+pub fn watson_frames(json: &str) -> Result<Vec<ImportedEntry>, ImportError> {
+    let frames: Vec<WatsonFrame> = serde_json::from_str(json)?;
+    let mut entries = frames
+        .into_iter()
+        .map(|WatsonFrame(start, stop, project, id, tags, _updated)| {
+            let entry = Entry {
+                start: Utc
+                    .timestamp_opt(start, 0)
+                    .single()
+                    .ok_or(ImportError::InvalidTimestamp(start))?,
+                stop: Utc
+                    .timestamp_opt(stop, 0)
+                    .single()
+                    .ok_or(ImportError::InvalidTimestamp(stop))?,
+                account: project.into(),
+                tags: tags
+                    .iter()
+                    .map(|tag| tag.split_whitespace().collect::<Vec<_>>().join("-"))
+                    .filter(|tag| !tag.is_empty())
+                    .collect(),
+                billable: false,
+                category: None,
+                description: None,
+                meta: BTreeMap::new(),
+            };
+            Ok(ImportedEntry {
+                entry,
+                source: Some(format!("watson:{}", id)).filter(|_| !id.is_empty()),
+            })
+        })
+        .collect::<Result<Vec<ImportedEntry>, ImportError>>()?;
+    entries.sort_by_key(|imported| imported.entry.start);
+    Ok(entries)
+}
+
+/// A column of a CSV file, either its number counting from 1 or the name in its header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CsvColumn {
+    Number(usize),
+    Name(String),
+}
+
+impl CsvColumn {
+    /// The index of the column in the records.
+    fn index(&self, header: Option<&[String]>) -> Result<usize, ImportError> {
+        match self {
+            CsvColumn::Number(number) => Ok(number - 1),
+            CsvColumn::Name(name) => header
+                .ok_or(ImportError::MissingHeader)?
+                .iter()
+                .position(|column| column.trim() == name)
+                .ok_or_else(|| ImportError::UnknownColumn(name.clone())),
+        }
+    }
+}
+
+/// The columns of a CSV file holding the fields of entries, written like
+/// `start=2,stop=3,account=1,description=5`. The start, stop and account are required.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvMapping {
+    pub start: CsvColumn,
+    pub stop: CsvColumn,
+    pub account: CsvColumn,
+    pub tags: Option<CsvColumn>,
+    pub billable: Option<CsvColumn>,
+    pub description: Option<CsvColumn>,
+    /// A column identifying each record, which becomes the source of its entry as `csv:` and
+    /// the identifier.
+    pub id: Option<CsvColumn>,
+}
+
+impl FromStr for CsvMapping {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (mut start, mut stop, mut account) = (None, None, None);
+        let (mut tags, mut billable, mut description, mut id) = (None, None, None, None);
+        for pair in s.split(',') {
+            let (field, column) = pair
+                .split_once('=')
+                .ok_or_else(|| format!("expected `field=column`, got `{}`", pair))?;
+            let column = match column.trim().parse() {
+                Ok(0) => return Err("columns are counted from 1".to_string()),
+                Ok(number) => CsvColumn::Number(number),
+                Err(_) => CsvColumn::Name(column.trim().to_string()),
+            };
+            let slot = match field.trim() {
+                "start" => &mut start,
+                "stop" => &mut stop,
+                "account" => &mut account,
+                "tags" => &mut tags,
+                "billable" => &mut billable,
+                "description" => &mut description,
+                "id" => &mut id,
+                field => return Err(format!("unknown field `{}`", field)),
+            };
+            *slot = Some(column);
+        }
+        let required = |column: Option<CsvColumn>, field| {
+            column.ok_or_else(|| format!("no column given for `{}`", field))
+        };
+        Ok(CsvMapping {
+            start: required(start, "start")?,
+            stop: required(stop, "stop")?,
+            account: required(account, "account")?,
+            tags,
+            billable,
+            description,
+            id,
+        })
+    }
+}
+
+/// Reads entries from CSV files exported by other tools.
+#[derive(Debug, Clone)]
+pub struct CsvImport {
+    pub mapping: CsvMapping,
+    /// The `strftime` format of the start and stop, by default RFC 3339. Times without an offset
+    /// are in the time zone given to `entries`.
+    pub datetime_format: Option<String>,
+    /// Whether the first record is a header, which is skipped and names the columns.
+    pub header: bool,
+    /// Which time to take for times without an offset that occur twice, when clocks are turned
+    /// back.
+    pub ambiguous: AmbiguousTimes,
+}
+
+/// What happens to times without an offset which occur twice in the time zone, when clocks are
+/// turned back for daylight saving time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AmbiguousTimes {
+    /// Skip the record, reporting the times it could be.
+    #[default]
+    Reject,
+    Earliest,
+    Latest,
+}
+
+impl AmbiguousTimes {
+    pub const NAMES: &'static [&'static str] = &["reject", "earliest", "latest"];
+}
+
+impl FromStr for AmbiguousTimes {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reject" => Ok(AmbiguousTimes::Reject),
+            "earliest" => Ok(AmbiguousTimes::Earliest),
+            "latest" => Ok(AmbiguousTimes::Latest),
+            _ => Err(format!(
+                "unknown choice `{}`, expected one of {}",
+                s,
+                Self::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+impl CsvImport {
+    /// Converts the records of the CSV file into entries, ordered by start time, along with the
+    /// records that could not be converted.
+    ///
+    /// Tags are separated by commas or whitespace. The billable column is true for `true`, `yes`,
+    /// `y`, `x` or `1`, ignoring case.
+    pub fn entries<Tz: TimeZone>(
+        &self,
+        csv: &str,
+        tz: &Tz,
+    ) -> Result<(Vec<ImportedEntry>, Vec<RowError>), ImportError> {
+        let mut records = csv_records(csv).into_iter();
+        let header = match self.header {
+            true => records.next().map(|(_, fields)| fields),
+            false => None,
+        };
+        let header = header.as_deref();
+        let index = |column: &CsvColumn| column.index(header);
+        let optional = |column: &Option<CsvColumn>| column.as_ref().map(index).transpose();
+        let (start, stop, account) = (
+            index(&self.mapping.start)?,
+            index(&self.mapping.stop)?,
+            index(&self.mapping.account)?,
+        );
+        let (tags, billable, description, id) = (
+            optional(&self.mapping.tags)?,
+            optional(&self.mapping.billable)?,
+            optional(&self.mapping.description)?,
+            optional(&self.mapping.id)?,
+        );
+
+        let mut entries = Vec::new();
+        let mut errors = Vec::new();
+        for (line, fields) in records {
+            if fields.iter().all(|field| field.trim().is_empty()) {
+                continue;
+            }
+            let field = |index: usize| -> Result<&str, String> {
+                fields
+                    .get(index)
+                    .map(|field| field.trim())
+                    .ok_or_else(|| format!("missing column {}", index + 1))
+            };
+            let optional_field = |index: Option<usize>| index.map(field).transpose();
+            let entry = (|| {
+                let entry = Entry {
+                    start: self.parse_datetime(field(start)?, tz)?,
+                    stop: self.parse_datetime(field(stop)?, tz)?,
+                    account: field(account)?.into(),
+                    tags: optional_field(tags)?
+                        .unwrap_or_default()
+                        .split(|c: char| c == ',' || c.is_whitespace())
+                        .map(|tag| tag.trim_start_matches('#'))
+                        .filter(|tag| !tag.is_empty())
+                        .map(str::to_string)
+                        .collect(),
+                    billable: optional_field(billable)?.is_some_and(|billable| {
+                        ["true", "yes", "y", "x", "1"]
+                            .iter()
+                            .any(|truthy| billable.eq_ignore_ascii_case(truthy))
+                    }),
+                    category: None,
+                    description: optional_field(description)?
+                        .filter(|description| !description.is_empty())
+                        .map(str::to_string),
+                    meta: BTreeMap::new(),
+                };
+                entry.validate().map_err(|err| err.to_string())?;
+                let source = optional_field(id)?
+                    .filter(|id| !id.is_empty())
+                    .map(|id| format!("csv:{}", id));
+                Ok(ImportedEntry { entry, source })
+            })();
+            match entry {
+                Ok(entry) => entries.push(entry),
+                Err(message) => errors.push(RowError { line, message }),
+            }
+        }
+        entries.sort_by_key(|imported| imported.entry.start);
+        Ok((entries, errors))
+    }
+
+    fn parse_datetime<Tz: TimeZone>(&self, s: &str, tz: &Tz) -> Result<DateTime<Utc>, String> {
+        let with_offset = match &self.datetime_format {
+            Some(format) => DateTime::parse_from_str(s, format),
+            None => DateTime::<FixedOffset>::from_str(s),
+        };
+        if let Ok(datetime) = with_offset {
+            return Ok(datetime.with_timezone(&Utc));
+        }
+        let naive = match &self.datetime_format {
+            Some(format) => NaiveDateTime::parse_from_str(s, format),
+            None => NaiveDateTime::from_str(s),
+        }
+        .map_err(|err| format!("invalid time `{}`: {}", s, err))?;
+        match tz.from_local_datetime(&naive) {
+            LocalResult::Single(datetime) => Ok(datetime.with_timezone(&Utc)),
+            LocalResult::Ambiguous(earliest, latest) => match self.ambiguous {
+                AmbiguousTimes::Earliest => Ok(earliest.with_timezone(&Utc)),
+                AmbiguousTimes::Latest => Ok(latest.with_timezone(&Utc)),
+                AmbiguousTimes::Reject => Err(format!(
+                    "the time `{}` occurs twice as the clocks are turned back, at {} and {}",
+                    s,
+                    earliest.with_timezone(&Utc).format("%H:%M UTC"),
+                    latest.with_timezone(&Utc).format("%H:%M UTC")
+                )),
+            },
+            LocalResult::None => Err(format!(
+                "the time `{}` does not exist as the clocks are turned forward",
+                s
+            )),
+        }
+    }
+}
+
+/// Splits a CSV file into records, each with the line number it starts on. Quoted fields can
+/// contain commas, newlines and quotes written twice.
+fn csv_records(csv: &str) -> Vec<(usize, Vec<String>)> {
+    let mut records = Vec::new();
+    let (mut fields, mut field) = (Vec::new(), String::new());
+    let (mut line, mut record_line) = (1, 1);
+    let mut quoted = false;
+    let mut chars = csv.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            '\r' if !quoted && chars.peek() == Some(&'\n') => {}
+            '\n' if !quoted => {
+                fields.push(std::mem::take(&mut field));
+                records.push((record_line, std::mem::take(&mut fields)));
+                line += 1;
+                record_line = line;
+            }
+            c => {
+                if c == '\n' {
+                    line += 1;
+                }
+                field.push(c);
+            }
+        }
+    }
+    if !field.is_empty() || !fields.is_empty() {
+        fields.push(field);
+        records.push((record_line, fields));
+    }
+    records
+}
+
+/// A record of an imported file that could not be converted into an entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RowError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for RowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+#[derive(Debug)]
+pub enum ImportError {
+    Json(serde_json::Error),
+    InvalidTimestamp(i64),
+    /// A column is mapped by name but the file has no header.
+    MissingHeader,
+    UnknownColumn(String),
+}
+
+impl From<serde_json::Error> for ImportError {
+    fn from(err: serde_json::Error) -> Self {
+        ImportError::Json(err)
+    }
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::Json(err) => err.fmt(f),
+            ImportError::InvalidTimestamp(timestamp) => {
+                write!(f, "invalid timestamp {}", timestamp)
+            }
+            ImportError::MissingHeader => {
+                write!(f, "columns can only be mapped by name with a header")
+            }
+            ImportError::UnknownColumn(name) => write!(f, "no column named `{}`", name),
+        }
+    }
+}
+
+impl Error for ImportError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn import_watson_frames() {
+        let json = r#"[
+            [1625313600, 1625324400, "timetracker", "a1b2", ["cli", "bug fix"], 1625324400],
+            [1625220000, 1625223600, "Client A", "c3d4", [], 1625223600]
+        ]"#;
+
+        assert_eq!(
+            watson_frames(json).unwrap(),
+            vec![
+                ImportedEntry {
+                    entry: Entry::from_str("2021-07-02T10:00:00Z 2021-07-02T11:00:00Z Client A")
+                        .unwrap(),
+                    source: Some("watson:c3d4".to_string()),
+                },
+                ImportedEntry {
+                    entry: Entry::from_str(
+                        "2021-07-03T12:00:00Z 2021-07-03T15:00:00Z timetracker #cli #bug-fix"
+                    )
+                    .unwrap(),
+                    source: Some("watson:a1b2".to_string()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_watson_frames() {
+        assert!(watson_frames("{}").is_err());
+        assert!(watson_frames(r#"[[1, 2, "p"]]"#).is_err());
+    }
+
+    #[test]
+    fn parse_csv_mappings() {
+        let mapping = CsvMapping::from_str("start=2,stop=3,account=Project,description=5").unwrap();
+        assert_eq!(mapping.start, CsvColumn::Number(2));
+        assert_eq!(mapping.account, CsvColumn::Name("Project".to_string()));
+        assert_eq!(mapping.description, Some(CsvColumn::Number(5)));
+        assert_eq!(mapping.tags, None);
+        assert_eq!(
+            CsvMapping::from_str("start=1,stop=2,account=3,id=ID")
+                .unwrap()
+                .id,
+            Some(CsvColumn::Name("ID".to_string()))
+        );
+
+        assert!(CsvMapping::from_str("start=1,stop=2").is_err());
+        assert!(CsvMapping::from_str("start=0,stop=2,account=3").is_err());
+        assert!(CsvMapping::from_str("start=1,stop=2,account=3,rate=4").is_err());
+    }
+
+    #[test]
+    fn import_csv() {
+        let csv = "Project,Start,End,Tags,Notes\r\n\
+                   Client A,2021-07-02 12:00,2021-07-02 13:00,\"cli, #bug\",\"Fix \"\"the\"\" bug\"\r\n\
+                   ,2021-07-02 14:00,2021-07-02 15:00,,\n\
+                   Client B,2021-07-01 14:00,yesterday,,\n\
+                   Client B,2021-07-01 10:00,2021-07-01 09:00,,\n\
+                   \n\
+                   Client A,2021-07-01 08:00,2021-07-01 09:00,,\"Two\nlines\"\n";
+        let import = CsvImport {
+            mapping: CsvMapping::from_str(
+                "account=Project,start=2,stop=3,tags=4,description=Notes",
+            )
+            .unwrap(),
+            datetime_format: Some("%Y-%m-%d %H:%M".to_string()),
+            header: true,
+            ambiguous: AmbiguousTimes::Reject,
+        };
+        let (entries, errors) = import.entries(csv, &FixedOffset::east(2 * 3600)).unwrap();
+
+        assert!(entries.iter().all(|imported| imported.source.is_none()));
+        assert_eq!(
+            entries
+                .into_iter()
+                .map(|imported| imported.entry)
+                .collect::<Vec<_>>(),
+            vec![
+                Entry::from_str("2021-07-01T06:00:00Z 2021-07-01T07:00:00Z Client A ; Two\\nlines")
+                    .unwrap(),
+                Entry::from_str(
+                    "2021-07-02T10:00:00Z 2021-07-02T11:00:00Z Client A #cli #bug ; Fix \"the\" bug"
+                )
+                .unwrap(),
+            ]
+        );
+        assert_eq!(
+            errors.iter().map(|error| error.line).collect::<Vec<_>>(),
+            vec![3, 4, 5]
+        );
+        assert!(errors[1].message.starts_with("invalid time `yesterday`"));
+
+        let without_header = CsvImport {
+            header: false,
+            ..import
+        };
+        assert!(without_header.entries(csv, &Utc).is_err());
+    }
+
+    #[test]
+    fn import_csv_around_daylight_saving_changes() {
+        let csv = "2021-10-31 02:30,2021-10-31 03:30,Back\n\
+                   2021-03-28 02:30,2021-03-28 03:30,Forward\n";
+        let mut import = CsvImport {
+            mapping: CsvMapping::from_str("start=1,stop=2,account=3").unwrap(),
+            datetime_format: Some("%Y-%m-%d %H:%M".to_string()),
+            header: false,
+            ambiguous: AmbiguousTimes::Reject,
+        };
+        let (entries, errors) = import.entries(csv, &chrono_tz::Europe::Berlin).unwrap();
+        assert!(entries.is_empty());
+        assert_eq!(
+            errors[0].message,
+            "the time `2021-10-31 02:30` occurs twice as the clocks are turned back, \
+             at 00:30 UTC and 01:30 UTC"
+        );
+        assert!(errors[1].message.contains("does not exist"));
+
+        import.ambiguous = AmbiguousTimes::Latest;
+        let (entries, _) = import.entries(csv, &chrono_tz::Europe::Berlin).unwrap();
+        assert_eq!(
+            entries[0].entry,
+            Entry::from_str("2021-10-31T01:30:00Z 2021-10-31T02:30:00Z Back").unwrap()
+        );
+    }
+
+    #[test]
+    fn import_csv_in_rfc3339() {
+        let import = CsvImport {
+            mapping: CsvMapping::from_str("start=1,stop=2,account=3,billable=4,id=5").unwrap(),
+            datetime_format: None,
+            header: false,
+            ambiguous: AmbiguousTimes::Reject,
+        };
+        let (entries, errors) = import
+            .entries(
+                "2021-07-02T12:00:00+02:00,2021-07-02T13:00:00+02:00,A,Yes,17",
+                &Utc,
+            )
+            .unwrap();
+        assert!(errors.is_empty());
+        assert_eq!(
+            entries,
+            vec![ImportedEntry {
+                entry: Entry::from_str("2021-07-02T10:00:00Z 2021-07-02T11:00:00Z A $").unwrap(),
+                source: Some("csv:17".to_string()),
+            }]
+        );
+    }
+}
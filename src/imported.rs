@@ -0,0 +1,101 @@
+//! Bookkeeping of the entries imported from other time trackers.
+
+use crate::storage::StorageError;
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// A file mapping the sources of imported entries, like `watson:a1b2`, to the IDs of the entries
+/// they became, one pair per line, so that importing a record again finds its entry.
+///
+/// A source can be recorded again when its entry changes, the last line for it wins.
+#[derive(Debug)]
+pub struct ImportedLog {
+    path: PathBuf,
+    entry_ids: BTreeMap<String, String>,
+}
+
+impl ImportedLog {
+    /// Reads the log at the given path, which is treated as empty if it does not exist.
+    pub fn load(path: &Path) -> Result<Self, StorageError> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err.into()),
+        };
+        // Sources can contain spaces, entry IDs cannot
+        let entry_ids = contents
+            .lines()
+            .filter_map(|line| {
+                let (source, id) = line.rsplit_once(' ')?;
+                Some((source.to_string(), id.to_string()))
+            })
+            .filter(|(source, id)| !source.is_empty() && !id.is_empty())
+            .collect();
+        Ok(ImportedLog {
+            path: path.to_path_buf(),
+            entry_ids,
+        })
+    }
+
+    /// The ID of the entry imported from the source.
+    pub fn entry_id(&self, source: &str) -> Option<&str> {
+        self.entry_ids.get(source).map(String::as_str)
+    }
+
+    /// The sources imported as the entry with the given ID.
+    pub fn sources(&self, entry_id: &str) -> Vec<&str> {
+        self.entry_ids
+            .iter()
+            .filter(|(_, id)| *id == entry_id)
+            .map(|(source, _)| source.as_str())
+            .collect()
+    }
+
+    /// Records that the source was imported as the entry with the given ID, appending it to the
+    /// file right away.
+    pub fn record(&mut self, source: &str, entry_id: &str) -> Result<(), StorageError> {
+        if self.entry_id(source) == Some(entry_id) {
+            return Ok(());
+        }
+        if let Some(parent) = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{} {}", source, entry_id)?;
+        self.entry_ids
+            .insert(source.to_string(), entry_id.to_string());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn record_and_reload() {
+        let path = env::temp_dir().join(format!("tt_imported_test_{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let mut log = ImportedLog::load(&path).unwrap();
+        assert_eq!(log.entry_id("watson:a1b2"), None);
+        log.record("watson:a1b2", "aa58e89a4b2e7c4c").unwrap();
+        log.record("csv:Task 17", "0123456789abcdef").unwrap();
+        log.record("watson:a1b2", "fedcba9876543210").unwrap();
+        let reloaded = ImportedLog::load(&path).unwrap();
+        let lines = fs::read_to_string(&path).unwrap().lines().count();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(reloaded.entry_id("watson:a1b2"), Some("fedcba9876543210"));
+        assert_eq!(reloaded.entry_id("csv:Task 17"), Some("0123456789abcdef"));
+        assert_eq!(lines, 3);
+        assert_eq!(reloaded.sources("fedcba9876543210"), vec!["watson:a1b2"]);
+        assert!(reloaded.sources("aa58e89a4b2e7c4c").is_empty());
+    }
+}
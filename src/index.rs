@@ -0,0 +1,285 @@
+//! A sidecar index of the entries file, for reading the entries of a period without parsing the
+//! lines before it.
+//!
+//! The index is stored next to the entries file, with `.index` appended to its name. It has the
+//! position of the first entry starting on each day (in UTC) and the duration of the longest
+//! entry, which together tell where the entries overlapping a period can start. It only holds for
+//! a file sorted by start time, and only for the exact file it was built from, recognized by its
+//! size and modification time. When either no longer matches the index is rebuilt.
+
+use crate::migrations;
+use crate::Entry;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fmt::Write as _;
+use std::fs::{self, Metadata};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+const HEADER: &str = "# timetracker index";
+
+/// Where a line starts in the entries file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    /// The byte offset of the line.
+    pub offset: u64,
+    /// The line number, counting from 1.
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Index {
+    size: u64,
+    /// The modification time in nanoseconds since the Unix epoch.
+    modified: u128,
+    /// The number of lines in the file.
+    lines: usize,
+    last_start: Option<DateTime<Utc>>,
+    /// The duration of the longest entry in seconds, rounded up.
+    longest: i64,
+    days: BTreeMap<NaiveDate, Position>,
+}
+
+impl Index {
+    /// The path of the index for the entries file.
+    pub fn path(entries_path: &Path) -> PathBuf {
+        let mut path = OsString::from(entries_path.as_os_str());
+        path.push(".index");
+        PathBuf::from(path)
+    }
+
+    /// Indexes the contents of the entries file, or returns `None` if the entries are not sorted
+    /// by start time or a line cannot be parsed.
+    pub fn build(contents: &str, metadata: &Metadata) -> Option<Index> {
+        let mut index = Index::default();
+        index.set_file(metadata);
+        let mut offset = 0;
+        for (number, line) in contents.split_inclusive('\n').enumerate() {
+            let position = Position {
+                offset,
+                line: number + 1,
+            };
+            offset += line.len() as u64;
+            let line = line.trim_end_matches('\n').trim_end_matches('\r');
+            if number == 0 && migrations::parse_header(line).is_some() {
+                index.lines = 1;
+                continue;
+            }
+            let entry = Entry::parse(line).ok()?;
+            if !index.record(position, entry.start, entry.stop) {
+                return None;
+            }
+        }
+        Some(index)
+    }
+
+    /// Reads the index at the path, `None` if there is none or it cannot be read.
+    pub fn load(path: &Path) -> Option<Index> {
+        let contents = fs::read_to_string(path)
+            .map_err(|err| log::debug!("could not read {}: {}", path.display(), err))
+            .ok()?;
+        let index = Index::parse(&contents);
+        if index.is_none() {
+            log::debug!("ignoring the invalid index {}", path.display());
+        }
+        index
+    }
+
+    fn parse(contents: &str) -> Option<Index> {
+        let mut lines = contents.lines();
+        if lines.next()? != HEADER {
+            return None;
+        }
+        let mut index = Index::default();
+        for line in lines {
+            let (key, value) = line.split_once(' ')?;
+            match key {
+                "size" => index.size = value.parse().ok()?,
+                "modified" => index.modified = value.parse().ok()?,
+                "lines" => index.lines = value.parse().ok()?,
+                "last" => index.last_start = Some(value.parse().ok()?),
+                "longest" => index.longest = value.parse().ok()?,
+                day => {
+                    let (offset, line) = value.split_once(' ')?;
+                    let position = Position {
+                        offset: offset.parse().ok()?,
+                        line: line.parse().ok()?,
+                    };
+                    index.days.insert(day.parse().ok()?, position);
+                }
+            }
+        }
+        Some(index)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut contents = format!(
+            "{}\nsize {}\nmodified {}\nlines {}\nlongest {}\n",
+            HEADER, self.size, self.modified, self.lines, self.longest
+        );
+        if let Some(last_start) = self.last_start {
+            writeln!(contents, "last {}", last_start.to_rfc3339()).expect("writing to a string");
+        }
+        for (day, position) in &self.days {
+            writeln!(contents, "{} {} {}", day, position.offset, position.line)
+                .expect("writing to a string");
+        }
+        fs::write(path, contents)
+    }
+
+    /// Whether the index was built from the file as it is now.
+    pub fn is_current(&self, metadata: &Metadata) -> bool {
+        self.size == metadata.len() && self.modified == modified(metadata)
+    }
+
+    /// The size and modification time of the file the index was last updated for.
+    fn set_file(&mut self, metadata: &Metadata) {
+        self.size = metadata.len();
+        self.modified = modified(metadata);
+    }
+
+    /// The size of the file when the index was last updated.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Adds a line appended to the file after the index was last updated, which is now at the
+    /// given size and modification time. Returns `false` if the entry starts before the last
+    /// one, so the file is no longer sorted and the index is no longer valid.
+    pub fn append(&mut self, entry: &Entry, metadata: &Metadata) -> bool {
+        let position = Position {
+            offset: self.size,
+            line: self.lines + 1,
+        };
+        if !self.record(position, entry.start, entry.stop) {
+            return false;
+        }
+        self.set_file(metadata);
+        true
+    }
+
+    fn record(&mut self, position: Position, start: DateTime<Utc>, stop: DateTime<Utc>) -> bool {
+        if self.last_start.is_some_and(|last_start| start < last_start) {
+            return false;
+        }
+        self.last_start = Some(start);
+        self.lines = position.line;
+        let duration = stop - start;
+        let seconds = duration.num_seconds();
+        let rounded_up = if Duration::seconds(seconds) < duration {
+            seconds + 1
+        } else {
+            seconds
+        };
+        self.longest = self.longest.max(rounded_up);
+        self.days
+            .entry(start.naive_utc().date())
+            .or_insert(position);
+        true
+    }
+
+    /// Where to start reading to get every entry stopping at or after the time. The entries
+    /// before it all stop earlier, since they start earlier than the longest entry before it.
+    pub fn start_of(&self, since: DateTime<Utc>) -> Position {
+        let day = (since - Duration::seconds(self.longest)).naive_utc().date();
+        self.days
+            .range(day..)
+            .next()
+            .map(|(_, position)| *position)
+            .unwrap_or(Position {
+                offset: self.size,
+                line: self.lines + 1,
+            })
+    }
+}
+
+/// The modification time in nanoseconds since the Unix epoch, 0 if it is not available.
+fn modified(metadata: &Metadata) -> u128 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |modified| modified.as_nanos())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn datetime(s: &str) -> DateTime<Utc> {
+        DateTime::from_str(s).unwrap()
+    }
+
+    fn write(name: &str, contents: &str) -> (PathBuf, Metadata) {
+        let path =
+            std::env::temp_dir().join(format!("timetracker-index-{}-{}", name, std::process::id()));
+        fs::write(&path, contents).unwrap();
+        let metadata = fs::metadata(&path).unwrap();
+        (path, metadata)
+    }
+
+    #[test]
+    fn build_and_seek() {
+        let contents = "# timetracker entries format 1\n\
+                        2021-07-01T22:00:00Z 2021-07-02T02:00:00Z A\n\
+                        2021-07-02T10:00:00Z 2021-07-02T11:00:00Z B\n\
+                        2021-07-04T10:00:00Z 2021-07-04T11:00:00Z C\n";
+        let (path, metadata) = write("build", contents);
+        let index = Index::build(contents, &metadata).unwrap();
+        assert!(index.is_current(&metadata));
+
+        // The entry from the day before still overlaps the start of the day
+        let position = index.start_of(datetime("2021-07-02T01:00:00Z"));
+        assert_eq!(
+            position,
+            Position {
+                offset: 31,
+                line: 2
+            }
+        );
+        assert!(contents[position.offset as usize..].starts_with("2021-07-01T22"));
+
+        // Without entries on the day, the next day with entries is used
+        let position = index.start_of(datetime("2021-07-03T12:00:00Z"));
+        assert_eq!(position.line, 4);
+        assert!(contents[position.offset as usize..].starts_with("2021-07-04T10"));
+
+        let position = index.start_of(datetime("2021-08-01T00:00:00Z"));
+        assert_eq!(position.offset, contents.len() as u64);
+
+        let index_path = Index::path(&path);
+        index.save(&index_path).unwrap();
+        assert_eq!(Index::load(&index_path), Some(index));
+    }
+
+    #[test]
+    fn append_and_invalidate() {
+        let contents = "2021-07-02T10:00:00Z 2021-07-02T11:00:00Z B\n";
+        let (path, metadata) = write("append", contents);
+        let mut index = Index::build(contents, &metadata).unwrap();
+
+        let entry = Entry::from_str("2021-07-03T10:00:00Z 2021-07-03T11:00:00Z C").unwrap();
+        fs::write(&path, format!("{}{}\n", contents, entry)).unwrap();
+        let metadata = fs::metadata(&path).unwrap();
+        assert!(!index.is_current(&metadata));
+        assert!(index.append(&entry, &metadata));
+        assert!(index.is_current(&metadata));
+        assert_eq!(
+            index.start_of(datetime("2021-07-03T05:00:00Z")),
+            Position {
+                offset: 44,
+                line: 2
+            }
+        );
+
+        let earlier = Entry::from_str("2021-07-01T10:00:00Z 2021-07-01T11:00:00Z A").unwrap();
+        assert!(!index.append(&earlier, &metadata));
+        assert_eq!(
+            Index::build(&format!("{}{}\n", contents, earlier), &metadata),
+            None
+        );
+    }
+}
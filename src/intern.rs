@@ -0,0 +1,64 @@
+//! Interning of account names while reading entries, so a file with many entries for few accounts
+//! keeps a single copy of each name, shared by the entries through `Arc<str>`.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// The distinct account names seen so far.
+#[derive(Debug, Clone, Default)]
+pub struct Interner {
+    names: HashSet<Arc<str>>,
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Interner::default()
+    }
+
+    /// The shared copy of the name, made the first time it is seen.
+    pub fn intern(&mut self, name: &str) -> Arc<str> {
+        match self.names.get(name) {
+            Some(interned) => Arc::clone(interned),
+            None => {
+                let interned: Arc<str> = Arc::from(name);
+                self.names.insert(Arc::clone(&interned));
+                interned
+            }
+        }
+    }
+
+    /// The number of distinct names.
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// The distinct names, ordered.
+    pub fn into_names(self) -> Vec<Arc<str>> {
+        let mut names: Vec<Arc<str>> = self.names.into_iter().collect();
+        names.sort_unstable();
+        names
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn share_equal_names() {
+        let mut interner = Interner::new();
+        let a = interner.intern("Client:ProjectX");
+        let b = interner.intern(&String::from("Client:ProjectX"));
+        let c = interner.intern("Support");
+        assert!(Arc::ptr_eq(&a, &b));
+        assert!(!Arc::ptr_eq(&a, &c));
+        assert_eq!(interner.len(), 2);
+
+        let names = interner.into_names();
+        assert_eq!(names, vec![a, c]);
+    }
+}
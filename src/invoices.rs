@@ -0,0 +1,205 @@
+//! Invoices issued for the billable time of an account in a month, so that the entries on an
+//! invoice are never billed again.
+//!
+//! The invoices are kept in a file next to the entries file, each line a JSON object with the
+//! number, the day it was issued, the account and month it is for, its totals and the IDs of its
+//! entries, e.g.
+//!
+//! ```json
+//! {"number":"2024-03-001","issued":"2024-04-01","account":"ClientA","month":"2024-03","seconds":36000,"amount":1200.0,"entries":["aa58e89a4b2e7c4c"]}
+//! ```
+//!
+//! Invoices are numbered by the month they are for, `2024-03-001` being the first for March 2024.
+
+use crate::storage::StorageError;
+use crate::Entry;
+use chrono::{Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// An issued invoice.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Invoice {
+    pub number: String,
+    pub issued: NaiveDate,
+    /// The account the invoice is for, with its sub-accounts.
+    pub account: String,
+    /// The month the invoice is for, like `2024-03`.
+    pub month: String,
+    /// The billable time on the invoice.
+    pub seconds: i64,
+    /// What the time is charged at the hourly rates of the accounts, if any has one.
+    pub amount: Option<f64>,
+    /// The IDs of the entries on the invoice, see `Entry::id`.
+    pub entries: Vec<String>,
+}
+
+impl Invoice {
+    pub fn duration(&self) -> Duration {
+        Duration::seconds(self.seconds)
+    }
+}
+
+/// The invoices issued so far, read from their file.
+#[derive(Debug)]
+pub struct InvoiceLog {
+    path: PathBuf,
+    invoices: Vec<Invoice>,
+    /// The IDs of the entries on any of the invoices.
+    invoiced: HashSet<String>,
+}
+
+impl InvoiceLog {
+    /// Reads the invoices at the given path, which is treated as empty if it does not exist.
+    pub fn load(path: &Path) -> Result<Self, StorageError> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err.into()),
+        };
+        let invoices = contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                serde_json::from_str::<Invoice>(line)
+                    .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let invoiced = invoices
+            .iter()
+            .flat_map(|invoice| invoice.entries.iter().cloned())
+            .collect();
+        Ok(InvoiceLog {
+            path: path.to_path_buf(),
+            invoices,
+            invoiced,
+        })
+    }
+
+    /// The invoices in the order they were issued.
+    pub fn invoices(&self) -> &[Invoice] {
+        &self.invoices
+    }
+
+    /// Whether the entry with the ID is on an invoice.
+    pub fn is_invoiced(&self, id: &str) -> bool {
+        self.invoiced.contains(id)
+    }
+
+    /// The number of the next invoice for the month, like `2024-03`.
+    pub fn next_number(&self, month: &str) -> String {
+        let issued = self
+            .invoices
+            .iter()
+            .filter(|invoice| invoice.month == month)
+            .count();
+        format!("{}-{:03}", month, issued + 1)
+    }
+
+    /// Issues an invoice for the entries of the account in the month which are not on one yet,
+    /// charging each at the rate `rate` gives for its account. Returns `None` if there are none,
+    /// the invoice is only recorded with `record`.
+    pub fn invoice(
+        &self,
+        account: &str,
+        month: &str,
+        issued: NaiveDate,
+        entries: &[Entry],
+        rate: impl Fn(&str) -> Option<f64>,
+    ) -> Option<Invoice> {
+        let entries: Vec<&Entry> = entries
+            .iter()
+            .filter(|entry| !self.is_invoiced(&entry.id()))
+            .collect();
+        if entries.is_empty() {
+            return None;
+        }
+        let mut amount = None;
+        for entry in &entries {
+            if let Some(rate) = rate(&entry.account) {
+                let hours = (entry.stop - entry.start).num_seconds() as f64 / 3600.0;
+                *amount.get_or_insert(0.0) += hours * rate;
+            }
+        }
+        Some(Invoice {
+            number: self.next_number(month),
+            issued,
+            account: account.to_string(),
+            month: month.to_string(),
+            seconds: entries
+                .iter()
+                .map(|entry| (entry.stop - entry.start).num_seconds())
+                .sum(),
+            amount,
+            entries: entries.iter().map(|entry| entry.id()).collect(),
+        })
+    }
+
+    /// Appends the invoice to the file.
+    pub fn record(&mut self, invoice: Invoice) -> Result<(), StorageError> {
+        if let Some(parent) = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let line = serde_json::to_string(&invoice).expect("an invoice can be written as JSON");
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", line)?;
+        self.invoiced.extend(invoice.entries.iter().cloned());
+        self.invoices.push(invoice);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::str::FromStr;
+
+    #[test]
+    fn number_invoices_and_skip_invoiced_entries() {
+        let path = env::temp_dir().join(format!("tt_invoices_test_{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let entries: Vec<Entry> = [
+            "2024-03-04T09:00:00Z 2024-03-04T12:00:00Z ClientA $",
+            "2024-03-05T09:00:00Z 2024-03-05T10:30:00Z ClientA:Support $",
+        ]
+        .iter()
+        .map(|s| Entry::from_str(s).unwrap())
+        .collect();
+        let rate = |account: &str| match account {
+            "ClientA" => Some(100.0),
+            _ => None,
+        };
+        let issued = NaiveDate::from_ymd(2024, 4, 1);
+
+        let mut log = InvoiceLog::load(&path).unwrap();
+        let invoice = log
+            .invoice("ClientA", "2024-03", issued, &entries[..1], rate)
+            .unwrap();
+        assert_eq!(invoice.number, "2024-03-001");
+        assert_eq!(invoice.duration(), Duration::hours(3));
+        assert_eq!(invoice.amount, Some(300.0));
+        log.record(invoice).unwrap();
+
+        // Only the entry not invoiced yet is on the next invoice
+        let log = InvoiceLog::load(&path).unwrap();
+        let invoice = log
+            .invoice("ClientA", "2024-03", issued, &entries, rate)
+            .unwrap();
+        assert_eq!(invoice.number, "2024-03-002");
+        assert_eq!(invoice.duration(), Duration::minutes(90));
+        assert_eq!(invoice.amount, None);
+        assert_eq!(invoice.entries, vec![entries[1].id()]);
+        assert_eq!(log.next_number("2024-04"), "2024-04-001");
+        assert!(log
+            .invoice("ClientA", "2024-03", issued, &entries[..1], rate)
+            .is_none());
+        fs::remove_file(&path).unwrap();
+    }
+}
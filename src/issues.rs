@@ -0,0 +1,77 @@
+//! Recognition of issue tracker keys, e.g. `PROJ-123`, in accounts and tags.
+
+use crate::Entry;
+
+/// Finds the first issue key in the text, i.e. a project key of uppercase letters, digits and
+/// underscores starting with a letter, followed by a dash and the issue number.
+pub fn find_issue_key(text: &str) -> Option<&str> {
+    let bytes = text.as_bytes();
+    let is_key_char = |b: u8| b.is_ascii_uppercase() || b.is_ascii_digit() || b == b'_';
+    let mut start = 0;
+    while start < bytes.len() {
+        // A key can't continue a word
+        if !bytes[start].is_ascii_uppercase()
+            || (start > 0 && (bytes[start - 1].is_ascii_alphanumeric() || bytes[start - 1] == b'_'))
+        {
+            start += 1;
+            continue;
+        }
+        let mut dash = start + 1;
+        while dash < bytes.len() && is_key_char(bytes[dash]) {
+            dash += 1;
+        }
+        let mut end = dash + 1;
+        while end < bytes.len() && bytes[end].is_ascii_digit() {
+            end += 1;
+        }
+        let bounded = end == bytes.len() || !bytes[end].is_ascii_alphanumeric();
+        if dash < bytes.len() && bytes[dash] == b'-' && end > dash + 1 && bounded {
+            return Some(&text[start..end]);
+        }
+        start = dash;
+    }
+    None
+}
+
+/// Finds the issue an entry was tracked for, looking at the most specific part of the account
+/// first and then at the tags.
+pub fn issue_key(entry: &Entry) -> Option<&str> {
+    entry
+        .account
+        .rsplit(':')
+        .chain(entry.tags.iter().map(String::as_str))
+        .find_map(find_issue_key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn find_keys() {
+        assert_eq!(find_issue_key("PROJ-123"), Some("PROJ-123"));
+        assert_eq!(find_issue_key("fix PROJ-123: crash"), Some("PROJ-123"));
+        assert_eq!(find_issue_key("AB_2-7"), Some("AB_2-7"));
+        assert_eq!(find_issue_key("Client-1"), None);
+        assert_eq!(find_issue_key("xPROJ-123"), None);
+        assert_eq!(find_issue_key("PROJ-12a"), None);
+        assert_eq!(find_issue_key("PROJ-"), None);
+        assert_eq!(find_issue_key("2PROJ-1 X-2"), Some("X-2"));
+    }
+
+    #[test]
+    fn entry_issue_key() {
+        let in_account =
+            Entry::from_str("2021-07-03T10:00:00Z 2021-07-03T11:00:00Z PROJ-1:PROJ-12 #OPS-3")
+                .unwrap();
+        let in_tag =
+            Entry::from_str("2021-07-03T10:00:00Z 2021-07-03T11:00:00Z Client #review #OPS-3")
+                .unwrap();
+        let none = Entry::from_str("2021-07-03T10:00:00Z 2021-07-03T11:00:00Z Client").unwrap();
+
+        assert_eq!(issue_key(&in_account), Some("PROJ-12"));
+        assert_eq!(issue_key(&in_tag), Some("OPS-3"));
+        assert_eq!(issue_key(&none), None);
+    }
+}
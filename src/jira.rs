@@ -0,0 +1,96 @@
+//! Logging of work on JIRA issues through its REST API.
+
+use serde::Deserialize;
+use serde_json::json;
+use std::env;
+use std::error::Error;
+use timetracker::config::JiraConfig;
+use timetracker::issues::issue_key;
+use timetracker::pushed::PushedLog;
+use timetracker::Entry;
+
+#[derive(Debug, Deserialize)]
+struct Worklog {
+    id: String,
+}
+
+/// Adds a worklog to the issue of each of the entries not pushed before, skipping entries without
+/// an issue key. Returns the number of entries pushed.
+pub fn push(
+    config: &JiraConfig,
+    entries: &[Entry],
+    log: &mut PushedLog,
+    dry_run: bool,
+) -> Result<usize, Box<dyn Error + Send + Sync>> {
+    let url = config
+        .url
+        .as_deref()
+        .ok_or("no JIRA URL is configured")?
+        .trim_end_matches('/');
+    let email = config
+        .email
+        .as_deref()
+        .ok_or("no JIRA email is configured")?;
+    let api_token = env::var("JIRA_API_TOKEN")
+        .ok()
+        .or_else(|| config.api_token.clone())
+        .ok_or("no JIRA API token is configured")?;
+    let authorization = format!(
+        "Basic {}",
+        base64(format!("{}:{}", email, api_token).as_bytes())
+    );
+
+    let mut pushed = 0;
+    for entry in entries {
+        let id = entry.id();
+        let key = match issue_key(entry) {
+            Some(key) if !log.contains(&id) => key,
+            _ => continue,
+        };
+        let seconds = (entry.stop - entry.start).num_seconds();
+        if dry_run {
+            println!(
+                "{} {} {}s",
+                entry.start.format("%Y-%m-%d %H:%M"),
+                key,
+                seconds
+            );
+            pushed += 1;
+            continue;
+        }
+
+        let body = json!({
+            "started": entry.start.format("%Y-%m-%dT%H:%M:%S%.3f%z").to_string(),
+            "timeSpentSeconds": seconds,
+            "comment": entry.description.as_deref().unwrap_or(&entry.account),
+        });
+        log::debug!("adding worklog to {}: {}", key, body);
+        let worklog: Worklog = ureq::post(&format!("{}/rest/api/2/issue/{}/worklog", url, key))
+            .set("Authorization", &authorization)
+            .send_json(body)?
+            .into_json()?;
+        log.record(&id, &worklog.id)?;
+        pushed += 1;
+    }
+    Ok(pushed)
+}
+
+/// Encodes the bytes with the standard base64 alphabet, as used for basic authentication.
+pub fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
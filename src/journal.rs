@@ -0,0 +1,120 @@
+//! Exporting entries as hledger journal transactions.
+
+use crate::hledger_text;
+use crate::timesheet::Timesheet;
+use chrono::{Duration, NaiveDate, TimeZone};
+
+/// Formats the entries as one hledger transaction per day in the given time zone, with an
+/// unbalanced posting of the hours tracked for each account, e.g. `(Client)  3.5h`, like hledger
+/// counts timeclock entries. Accounts are written like in timeclock entries, so runs of
+/// whitespace and semicolons cannot end them early.
+///
+/// Unlike timeclock entries, these transactions can be mixed with other transactions in a journal.
+pub fn format_transactions<Tz: TimeZone>(timesheet: &Timesheet, tz: &Tz) -> String {
+    timesheet
+        .group_by_day(tz)
+        .into_iter()
+        .map(|(date, day)| {
+            let mut transaction = format!("{} Time tracked", date.format("%Y-%m-%d"));
+            for (account, entries) in day.group_by_account() {
+                transaction += &format!(
+                    "\n    ({})  {}h",
+                    hledger_text(&account),
+                    hours(entries.total_duration())
+                );
+            }
+            transaction
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// The duration in hours with as many decimals as needed up to six, which tells apart durations
+/// a second apart, e.g. `3.5` or `0.333333` for 20 minutes.
+fn hours(duration: Duration) -> String {
+    let micro_hours = (duration.num_seconds() * 1_000_000 + 1800) / 3600;
+    let hours = format!("{}.{:06}", micro_hours / 1_000_000, micro_hours % 1_000_000);
+    hours
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+/// Finds the date of the latest transaction in a journal, an incremental export continues with
+/// the days after it.
+pub fn latest_transaction_date(journal: &str) -> Option<NaiveDate> {
+    journal
+        .lines()
+        .filter_map(|line| {
+            let date = line.split_whitespace().next()?;
+            ["%Y-%m-%d", "%Y/%m/%d", "%Y.%m.%d"]
+                .iter()
+                .find_map(|format| NaiveDate::parse_from_str(date, format).ok())
+        })
+        .max()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Entry;
+    use chrono::Utc;
+    use std::str::FromStr;
+
+    fn entry(s: &str) -> Entry {
+        Entry::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn format_days_as_transactions() {
+        let timesheet = Timesheet::new(vec![
+            entry("2021-07-03T10:00:00Z 2021-07-03T13:30:00Z Client A"),
+            entry("2021-07-03T14:00:00Z 2021-07-03T14:20:00Z Other"),
+            entry("2021-07-03T15:00:00Z 2021-07-03T15:00:40Z Other"),
+            entry("2021-07-03T23:00:00Z 2021-07-04T01:00:00Z Client A"),
+        ]);
+
+        assert_eq!(
+            format_transactions(&timesheet, &Utc),
+            "2021-07-03 Time tracked\n    \
+                 (Client A)  4.5h\n    \
+                 (Other)  0.344444h\n\
+             \n\
+             2021-07-04 Time tracked\n    \
+                 (Client A)  1h"
+        );
+    }
+
+    #[test]
+    fn keep_accounts_from_ending_early() {
+        let start = "2021-07-03T10:00:00Z".parse().unwrap();
+        let timesheet = Timesheet::new(vec![Entry::builder()
+            .start(start)
+            .stop(start + Duration::minutes(90))
+            .account("Client  A; old".to_string())
+            .build()
+            .unwrap()]);
+
+        assert_eq!(
+            format_transactions(&timesheet, &Utc),
+            "2021-07-03 Time tracked\n    (Client A, old)  1.5h"
+        );
+    }
+
+    #[test]
+    fn find_latest_transaction_date() {
+        let journal = "2021-07-03 Time tracked\n    \
+                           (Client)  1.00h\n\
+                       \n\
+                       ; comment\n\
+                       2021/07/05 Groceries\n    \
+                           expenses:food  $10\n    \
+                           assets:cash\n";
+
+        assert_eq!(
+            latest_transaction_date(journal),
+            NaiveDate::from_ymd_opt(2021, 7, 5)
+        );
+        assert_eq!(latest_transaction_date("; nothing yet\n"), None);
+    }
+}
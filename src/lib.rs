@@ -1,36 +1,415 @@
-use chrono::{DateTime, SecondsFormat, Utc};
+//! Tracking time in plain text files, the library behind the `tt` command.
+//!
+//! `prelude` has what most code needs and is kept stable between minor releases. The modules
+//! hidden from the documentation are details of the command which may change in any release.
+
+use crate::intern::Interner;
+use chrono::{DateTime, Duration, Local, NaiveDateTime, SecondsFormat, SubsecRound, TimeZone, Utc};
+use serde::Serialize;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::error::Error;
 use std::fmt;
+use std::ops::Range;
 use std::str::FromStr;
+use std::sync::{Arc, OnceLock};
+
+pub mod accounts;
+#[cfg(feature = "activity")]
+pub mod activity;
+pub mod audit;
+pub mod backup;
+pub mod burn;
+pub mod calendar;
+pub mod charts;
+pub mod check;
+#[cfg(feature = "compact")]
+pub mod compact;
+pub mod config;
+#[doc(hidden)]
+pub mod dashboard;
+pub mod diff;
+pub mod digest;
+pub mod duration;
+#[cfg(feature = "encryption")]
+pub mod encryption;
+pub mod explain;
+pub mod export;
+pub mod favorites;
+#[doc(hidden)]
+pub mod fuzzy;
+pub mod goals;
+pub mod hooks;
+pub mod import;
+#[doc(hidden)]
+pub mod imported;
+#[doc(hidden)]
+pub mod index;
+#[doc(hidden)]
+pub mod intern;
+pub mod invoices;
+pub mod issues;
+pub mod journal;
+pub mod locale;
+#[doc(hidden)]
+pub mod migrations;
+pub mod notes;
+#[doc(hidden)]
+pub mod oplog;
+pub mod paths;
+pub mod permissions;
+pub mod plan;
+pub mod prelude;
+pub mod project;
+pub mod prompt;
+#[doc(hidden)]
+pub mod pushed;
+pub mod reminders;
+pub mod report;
+pub mod rounding;
+pub mod schedule;
+pub mod schema;
+#[doc(hidden)]
+pub mod selector;
+#[cfg(feature = "sqlite")]
+pub mod sql;
+pub mod stats;
+pub mod status;
+pub mod storage;
+#[doc(hidden)]
+pub mod suggest;
+pub mod sync;
+pub mod templates;
+pub mod timeparse;
+pub mod timesheet;
+pub mod zone;
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+/// A completed entry.
+///
+/// The `Display` implementation produces the canonical line format, which has the timestamps in
+/// UTC with as many fractional digits as needed to be exact. Parsing also accepts other offsets
+/// and precisions, these are converted to UTC.
+///
+/// Fields may be added in any release, so code outside the crate makes entries with
+/// `Entry::builder` or by parsing them.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[non_exhaustive]
 pub struct Entry {
     pub start: DateTime<Utc>,
     pub stop: DateTime<Utc>,
-    pub account: String,
+    pub account: Arc<str>,
+    pub tags: Vec<String>,
+    /// Whether the time can be billed to a client.
+    pub billable: bool,
+    /// The kind of work, like `development` or `meetings`, for breaking down time across accounts.
+    pub category: Option<String>,
     pub description: Option<String>,
+    /// Structured context attached by integrations, like `ticket=PROJ-12`, see `is_valid_meta_key`.
+    pub meta: BTreeMap<String, String>,
 }
 
 impl Entry {
+    /// Starts building an entry, which is validated when it is built.
+    pub fn builder() -> EntryBuilder {
+        EntryBuilder::default()
+    }
+
+    /// Identifies the entry by its start time and account, for keeping track of entries in other
+    /// systems. The identifier stays the same as long as neither of them are changed.
+    pub fn id(&self) -> String {
+        // 64-bit FNV-1a, which unlike the standard library hasher is stable across releases
+        let key = format!("{} {}", format_datetime(&self.start), self.account);
+        let hash = key.bytes().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+        });
+        format!("{:016x}", hash)
+    }
+
+    /// Adds a note to the end of the description, see `annotate_description`.
+    pub fn annotate(&mut self, note: &str) {
+        annotate_description(&mut self.description, note);
+    }
+
+    /// The value of the metadata key, if the entry has it.
+    pub fn meta_value(&self, key: &str) -> Option<&str> {
+        self.meta.get(key).map(String::as_str)
+    }
+
+    /// The person who tracked the entry, when a team shares the data files, see `USER_KEY`.
+    pub fn user(&self) -> Option<&str> {
+        self.meta_value(USER_KEY)
+    }
+
+    /// Splits the entry into the part before and the part after the time, both keeping the
+    /// account, tags and description. Returns `None` unless the time is within the entry.
+    pub fn split_at(&self, at: DateTime<Utc>) -> Option<(Entry, Entry)> {
+        if at <= self.start || at >= self.stop {
+            return None;
+        }
+        Some((
+            Entry {
+                stop: at,
+                ..self.clone()
+            },
+            Entry {
+                start: at,
+                ..self.clone()
+            },
+        ))
+    }
+
+    /// Whether the next entry, which starts no earlier than this one, is for the same account
+    /// and billable alike and starts less than `max_gap` after this entry stops.
+    pub fn can_merge(&self, next: &Entry, max_gap: Duration) -> bool {
+        self.account == next.account
+            && self.billable == next.billable
+            && next.start - self.stop < max_gap
+    }
+
+    /// Extends the entry until the next entry stops, adding the tags, metadata keys and the
+    /// description of it which are missing.
+    pub fn merge(&mut self, next: &Entry) {
+        self.stop = self.stop.max(next.stop);
+        for tag in &next.tags {
+            if !self.tags.contains(tag) {
+                self.tags.push(tag.clone());
+            }
+        }
+        for (key, value) in &next.meta {
+            self.meta
+                .entry(key.clone())
+                .or_insert_with(|| value.clone());
+        }
+        if let Some(description) = &next.description {
+            if self.description.as_ref() != Some(description) {
+                self.annotate(description);
+            }
+        }
+    }
+
+    /// Checks that the entry can be recorded: it must not stop before it starts, and the account,
+    /// tags, category and metadata must survive being written in the line format.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        if self.stop < self.start {
+            return Err(ValidationError::StopBeforeStart {
+                start: self.start,
+                stop: self.stop,
+            });
+        }
+        validate_account_fields(
+            &self.account,
+            &self.tags,
+            self.category.as_deref(),
+            &self.meta,
+        )
+    }
+
     pub fn format_as_timeclock(&self) -> String {
+        self.format_as_timeclock_in(&Utc)
+    }
+
+    /// Formats the entry as a timeclock check-in and check-out with the times in the time zone.
+    ///
+    /// The check-in has the description after the account and the tags as hledger tags in a
+    /// comment, e.g. `i 2021-07-03 10:00:00+0000 Client  Fixed the login bug  ; dev:`. What hledger
+    /// would read differently is replaced: line breaks and runs of whitespace by a single space,
+    /// which keeps the account from ending early, semicolons, which start comments, by commas, and
+    /// commas and colons in tags by dashes.
+    pub fn format_as_timeclock_in<Tz: TimeZone>(&self, tz: &Tz) -> String
+    where
+        Tz::Offset: fmt::Display,
+    {
         let datetime_format = "%Y-%m-%d %H:%M:%S%z";
+        let mut check_in = format!(
+            "i {} {}",
+            self.start.with_timezone(tz).format(datetime_format),
+            hledger_text(&self.account)
+        );
+        let description = self.description.as_deref().map(hledger_text);
+        if let Some(description) = description.filter(|description| !description.is_empty()) {
+            check_in.push_str("  ");
+            check_in.push_str(&description);
+        }
+        if !self.tags.is_empty() {
+            let tags: Vec<String> = self
+                .tags
+                .iter()
+                .map(|tag| format!("{}:", tag.replace([',', ':'], "-")))
+                .collect();
+            check_in.push_str("  ; ");
+            check_in.push_str(&tags.join(", "));
+        }
         format!(
-            "i {} {}\no {}",
-            self.start.format(datetime_format),
-            self.account,
-            self.stop.format(datetime_format)
+            "{}\no {}",
+            check_in,
+            self.stop.with_timezone(tz).format(datetime_format)
         )
     }
 }
 
+/// Replaces semicolons with commas and collapses whitespace, including line breaks, to single
+/// spaces, for the timeclock and journal formats of hledger.
+pub(crate) fn hledger_text(s: &str) -> String {
+    s.replace(';', ",")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Finds the latest check-in time in a timeclock journal, an incremental export continues with
+/// the entries starting after it.
+///
+/// Times without an offset are read as local times, like hledger does.
+pub fn latest_timeclock_check_in(journal: &str) -> Option<DateTime<Utc>> {
+    journal
+        .lines()
+        .filter_map(|line| {
+            let mut words = line.split_whitespace();
+            if words.next() != Some("i") {
+                return None;
+            }
+            let datetime = format!("{} {}", words.next()?, words.next()?);
+            DateTime::parse_from_str(&datetime, "%Y-%m-%d %H:%M:%S%z")
+                .map(|datetime| datetime.with_timezone(&Utc))
+                .ok()
+                .or_else(|| {
+                    let naive =
+                        NaiveDateTime::parse_from_str(&datetime, "%Y-%m-%d %H:%M:%S").ok()?;
+                    Some(
+                        Local
+                            .from_local_datetime(&naive)
+                            .earliest()?
+                            .with_timezone(&Utc),
+                    )
+                })
+        })
+        .max()
+}
+
+/// Builds an entry field by field, e.g.
+/// `Entry::builder().account("Client").start(start).stop(stop).tag("dev").build()`, so code
+/// outside the crate does not depend on every field of `Entry`.
+#[derive(Debug, Clone, Default)]
+pub struct EntryBuilder {
+    start: Option<DateTime<Utc>>,
+    stop: Option<DateTime<Utc>>,
+    account: String,
+    tags: Vec<String>,
+    billable: bool,
+    category: Option<String>,
+    description: Option<String>,
+    meta: BTreeMap<String, String>,
+}
+
+impl EntryBuilder {
+    pub fn start(mut self, start: DateTime<Utc>) -> Self {
+        self.start = Some(start);
+        self
+    }
+
+    pub fn stop(mut self, stop: DateTime<Utc>) -> Self {
+        self.stop = Some(stop);
+        self
+    }
+
+    pub fn account(mut self, account: impl Into<String>) -> Self {
+        self.account = account.into();
+        self
+    }
+
+    /// Adds a tag, unless the entry already has it.
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        add_tag(&mut self.tags, tag.into());
+        self
+    }
+
+    /// Adds the tags, like `tag`.
+    pub fn tags<T: Into<String>>(mut self, tags: impl IntoIterator<Item = T>) -> Self {
+        for tag in tags {
+            add_tag(&mut self.tags, tag.into());
+        }
+        self
+    }
+
+    pub fn billable(mut self, billable: bool) -> Self {
+        self.billable = billable;
+        self
+    }
+
+    pub fn category(mut self, category: impl Into<String>) -> Self {
+        self.category = Some(category.into());
+        self
+    }
+
+    /// Sets the category if there is one, keeping the one set before otherwise.
+    pub fn category_if_any(self, category: Option<impl Into<String>>) -> Self {
+        match category {
+            Some(category) => self.category(category),
+            None => self,
+        }
+    }
+
+    /// Sets the description, where an empty one is the same as none.
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into()).filter(|description| !description.is_empty());
+        self
+    }
+
+    /// Sets the description if there is one, like `category_if_any`.
+    pub fn description_if_any(self, description: Option<impl Into<String>>) -> Self {
+        match description {
+            Some(description) => self.description(description),
+            None => self,
+        }
+    }
+
+    /// Sets the value of the metadata key, replacing the one set before.
+    pub fn meta(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.meta.insert(key.into(), value.into());
+        self
+    }
+
+    /// Records who tracked the entry, if anyone, see `USER_KEY`.
+    pub fn user_if_any(self, user: Option<impl Into<String>>) -> Self {
+        match user {
+            Some(user) => self.meta(USER_KEY, user),
+            None => self,
+        }
+    }
+
+    /// Builds the entry, which must have a start and a stop time and pass `Entry::validate`.
+    pub fn build(self) -> Result<Entry, ValidationError> {
+        let entry = Entry {
+            start: self.start.ok_or(ValidationError::MissingStart)?,
+            stop: self.stop.ok_or(ValidationError::MissingStop)?,
+            account: self.account.into(),
+            tags: self.tags,
+            billable: self.billable,
+            category: self.category,
+            description: self.description,
+            meta: self.meta,
+        };
+        entry.validate()?;
+        Ok(entry)
+    }
+}
+
 impl fmt::Display for Entry {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "{} {} {}",
-            self.start.to_rfc3339_opts(SecondsFormat::Secs, true),
-            self.stop.to_rfc3339_opts(SecondsFormat::Secs, true),
-            self.account
+            "{} {} ",
+            format_datetime(&self.start),
+            format_datetime(&self.stop)
+        )?;
+        write_account_fields(
+            f,
+            &self.account,
+            self.billable,
+            self.category.as_deref(),
+            &self.tags,
+            &self.meta,
+            self.description.as_deref(),
         )
     }
 }
@@ -39,31 +418,342 @@ impl FromStr for Entry {
     type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Entry::parse(s).map(|entry| entry.to_entry())
+    }
+}
+
+/// A completed entry borrowing its fields from the line it was parsed from, see `Entry::parse`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryRef<'a> {
+    pub start: DateTime<Utc>,
+    pub stop: DateTime<Utc>,
+    fields: AccountFieldsRef<'a>,
+}
+
+impl<'a> EntryRef<'a> {
+    pub fn account(&self) -> &str {
+        &self.fields.account
+    }
+
+    pub fn tags(&self) -> impl Iterator<Item = &'a str> {
+        self.fields.tags()
+    }
+
+    pub fn billable(&self) -> bool {
+        self.fields.billable
+    }
+
+    pub fn category(&self) -> Option<&str> {
+        self.fields.category.as_deref()
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.fields.description.as_deref()
+    }
+
+    /// The metadata keys and values in the order they are written.
+    pub fn meta(&self) -> impl Iterator<Item = (&'a str, &'a str)> {
+        self.fields.meta()
+    }
+
+    /// The value of the metadata key, the last one written if it is written more than once.
+    pub fn meta_value(&self, key: &str) -> Option<&'a str> {
+        self.meta()
+            .filter(|(meta_key, _)| *meta_key == key)
+            .map(|(_, value)| value)
+            .last()
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.stop - self.start
+    }
+
+    /// Copies the fields into an owned entry.
+    pub fn to_entry(&self) -> Entry {
+        self.entry_with_account(self.account().into())
+    }
+
+    /// Copies the fields into an owned entry like `to_entry`, sharing the account name with the
+    /// other entries read with the interner.
+    pub fn to_interned_entry(&self, interner: &mut Interner) -> Entry {
+        self.entry_with_account(interner.intern(self.account()))
+    }
+
+    fn entry_with_account(&self, account: Arc<str>) -> Entry {
+        Entry {
+            start: self.start,
+            stop: self.stop,
+            account,
+            tags: self.tags().map(str::to_string).collect(),
+            billable: self.billable(),
+            category: self.category().map(str::to_string),
+            description: self.description().map(str::to_string),
+            meta: self.fields.meta_map(),
+        }
+    }
+}
+
+impl Entry {
+    /// Borrows the fields of the entry, writing the tags and the metadata into the buffers as
+    /// they are written in a line.
+    #[cfg(feature = "compact")]
+    pub(crate) fn to_ref<'a>(&'a self, tags: &'a mut String, meta: &'a mut String) -> EntryRef<'a> {
+        tags.clear();
+        for tag in &self.tags {
+            if !tags.is_empty() {
+                tags.push(' ');
+            }
+            tags.push('#');
+            tags.push_str(tag);
+        }
+        meta.clear();
+        for (key, value) in &self.meta {
+            if !meta.is_empty() {
+                meta.push(' ');
+            }
+            meta.push_str(key);
+            meta.push('=');
+            meta.push_str(value);
+        }
+        EntryRef {
+            start: self.start,
+            stop: self.stop,
+            fields: AccountFieldsRef {
+                account: Cow::Borrowed(&self.account),
+                tags,
+                meta,
+                billable: self.billable,
+                category: self.category.as_deref().map(Cow::Borrowed),
+                description: self.description.as_deref().map(Cow::Borrowed),
+            },
+        }
+    }
+
+    /// Parses a line without copying the account, tags and description out of it, which is
+    /// faster than `from_str` when most entries are thrown away after a look at them.
+    ///
+    /// Only fields with escaped characters are copied, to unescape them.
+    pub fn parse(s: &str) -> Result<EntryRef<'_>, ParseError> {
         let (start, remainder) = s.split_once(' ').ok_or(ParseError::MissingStart)?;
-        let (stop, account) = remainder.split_once(' ').ok_or(ParseError::MissingStop)?;
-        Ok(Entry {
-            start: DateTime::from_str(start)?,
-            stop: DateTime::from_str(stop)?,
-            account: account.to_string(),
-            description: None,
+        let stop_column = column(s, start.len() + 1);
+        let (stop, remainder) = remainder.split_once(' ').ok_or(ParseError::MissingStop {
+            column: stop_column,
+        })?;
+        let fields = parse_account_fields_ref(remainder, column(s, s.len() - remainder.len()))?;
+        Ok(EntryRef {
+            start: parse_datetime(start, 1)?,
+            stop: parse_datetime(stop, stop_column)?,
+            fields,
         })
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+/// An entry which has been started but not yet stopped.
+///
+/// Uses the same canonical line format as `Entry`, without the stop timestamp. Like `Entry`, code
+/// outside the crate makes them with `RunningEntry::builder`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[non_exhaustive]
 pub struct RunningEntry {
     pub start: DateTime<Utc>,
-    pub account: String,
+    pub account: Arc<str>,
+    pub tags: Vec<String>,
+    /// Whether the time can be billed to a client.
+    pub billable: bool,
+    /// The kind of work, like `development` or `meetings`, for breaking down time across accounts.
+    pub category: Option<String>,
     pub description: Option<String>,
+    /// Structured context attached by integrations, like `Entry::meta`.
+    pub meta: BTreeMap<String, String>,
+}
+
+impl RunningEntry {
+    /// Starts building a running entry, which is validated when it is built.
+    pub fn builder() -> RunningEntryBuilder {
+        RunningEntryBuilder::default()
+    }
+
+    /// Completes the running entry by stopping it at the given time.
+    pub fn into_entry(self, stop: DateTime<Utc>) -> Entry {
+        Entry {
+            start: self.start,
+            stop,
+            account: self.account,
+            tags: self.tags,
+            billable: self.billable,
+            category: self.category,
+            description: self.description,
+            meta: self.meta,
+        }
+    }
+
+    /// Adds a note to the end of the description, see `annotate_description`.
+    pub fn annotate(&mut self, note: &str) {
+        annotate_description(&mut self.description, note);
+    }
+
+    /// The value of the metadata key, if the entry has it.
+    pub fn meta_value(&self, key: &str) -> Option<&str> {
+        self.meta.get(key).map(String::as_str)
+    }
+
+    /// The person who tracked the entry, when a team shares the data files, see `USER_KEY`.
+    pub fn user(&self) -> Option<&str> {
+        self.meta_value(USER_KEY)
+    }
+
+    /// Checks that the account, tags, category and metadata survive being written in the line
+    /// format.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        validate_account_fields(
+            &self.account,
+            &self.tags,
+            self.category.as_deref(),
+            &self.meta,
+        )
+    }
+}
+
+/// The entry as if it was still running, leaving out the stop time.
+impl From<Entry> for RunningEntry {
+    fn from(entry: Entry) -> Self {
+        RunningEntry {
+            start: entry.start,
+            account: entry.account,
+            tags: entry.tags,
+            billable: entry.billable,
+            category: entry.category,
+            description: entry.description,
+            meta: entry.meta,
+        }
+    }
+}
+
+/// Builds a running entry field by field, like `EntryBuilder`, e.g.
+/// `RunningEntry::builder().account("Client").start(start).build()`.
+#[derive(Debug, Clone, Default)]
+pub struct RunningEntryBuilder {
+    start: Option<DateTime<Utc>>,
+    account: String,
+    tags: Vec<String>,
+    billable: bool,
+    category: Option<String>,
+    description: Option<String>,
+    meta: BTreeMap<String, String>,
+}
+
+impl RunningEntryBuilder {
+    pub fn start(mut self, start: DateTime<Utc>) -> Self {
+        self.start = Some(start);
+        self
+    }
+
+    pub fn account(mut self, account: impl Into<String>) -> Self {
+        self.account = account.into();
+        self
+    }
+
+    /// Adds a tag, unless the entry already has it.
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        add_tag(&mut self.tags, tag.into());
+        self
+    }
+
+    /// Adds the tags, like `tag`.
+    pub fn tags<T: Into<String>>(mut self, tags: impl IntoIterator<Item = T>) -> Self {
+        for tag in tags {
+            add_tag(&mut self.tags, tag.into());
+        }
+        self
+    }
+
+    pub fn billable(mut self, billable: bool) -> Self {
+        self.billable = billable;
+        self
+    }
+
+    pub fn category(mut self, category: impl Into<String>) -> Self {
+        self.category = Some(category.into());
+        self
+    }
+
+    /// Sets the category if there is one, keeping the one set before otherwise.
+    pub fn category_if_any(self, category: Option<impl Into<String>>) -> Self {
+        match category {
+            Some(category) => self.category(category),
+            None => self,
+        }
+    }
+
+    /// Sets the description, where an empty one is the same as none.
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into()).filter(|description| !description.is_empty());
+        self
+    }
+
+    /// Sets the description if there is one, like `category_if_any`.
+    pub fn description_if_any(self, description: Option<impl Into<String>>) -> Self {
+        match description {
+            Some(description) => self.description(description),
+            None => self,
+        }
+    }
+
+    /// Sets the value of the metadata key, replacing the one set before.
+    pub fn meta(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.meta.insert(key.into(), value.into());
+        self
+    }
+
+    /// Records who tracked the entry, if anyone, see `USER_KEY`.
+    pub fn user_if_any(self, user: Option<impl Into<String>>) -> Self {
+        match user {
+            Some(user) => self.meta(USER_KEY, user),
+            None => self,
+        }
+    }
+
+    /// Builds the running entry, which must have a start time and pass `RunningEntry::validate`.
+    pub fn build(self) -> Result<RunningEntry, ValidationError> {
+        let entry = RunningEntry {
+            start: self.start.ok_or(ValidationError::MissingStart)?,
+            account: self.account.into(),
+            tags: self.tags,
+            billable: self.billable,
+            category: self.category,
+            description: self.description,
+            meta: self.meta,
+        };
+        entry.validate()?;
+        Ok(entry)
+    }
+}
+
+fn add_tag(tags: &mut Vec<String>, tag: String) {
+    if !tags.contains(&tag) {
+        tags.push(tag);
+    }
+}
+
+/// Appends a note to a description, separated from any existing description by a semicolon.
+pub fn annotate_description(description: &mut Option<String>, note: &str) {
+    *description = Some(match description.take() {
+        Some(existing) if !existing.is_empty() => format!("{}; {}", existing, note),
+        _ => note.to_string(),
+    });
 }
 
 impl fmt::Display for RunningEntry {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
+        write!(f, "{} ", format_datetime(&self.start))?;
+        write_account_fields(
             f,
-            "{} {}",
-            self.start.to_rfc3339_opts(SecondsFormat::Secs, true),
-            self.account
+            &self.account,
+            self.billable,
+            self.category.as_deref(),
+            &self.tags,
+            &self.meta,
+            self.description.as_deref(),
         )
     }
 }
@@ -72,25 +762,391 @@ impl FromStr for RunningEntry {
     type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (start, account) = s.split_once(' ').ok_or(ParseError::MissingStart)?;
+        let (start, remainder) = s.split_once(' ').ok_or(ParseError::MissingStart)?;
+        let fields = parse_account_fields(remainder, column(s, start.len() + 1))?;
         Ok(RunningEntry {
-            start: DateTime::from_str(start)?,
-            account: account.to_string(),
-            description: None,
+            start: parse_datetime(start, 1)?,
+            account: fields.account.into(),
+            tags: fields.tags,
+            billable: fields.billable,
+            category: fields.category,
+            description: fields.description,
+            meta: fields.meta,
         })
     }
 }
 
+/// The time `now` returns instead of the clock's once set with `set_now`.
+static FIXED_NOW: OnceLock<DateTime<Utc>> = OnceLock::new();
+
+/// The current time truncated to whole seconds, which is the precision of recorded times, or the
+/// time set with `set_now`.
+pub fn now() -> DateTime<Utc> {
+    FIXED_NOW
+        .get()
+        .copied()
+        .unwrap_or_else(|| Utc::now().trunc_subsecs(0))
+}
+
+/// Makes `now` return the time instead of the clock's for the rest of the process, for scripts
+/// and tests that need the same results every run. Only the first time set counts.
+pub fn set_now(now: DateTime<Utc>) {
+    let _ = FIXED_NOW.set(now.trunc_subsecs(0));
+}
+
+/// The metadata key of the person who tracked an entry, like `user=alice`, for teams sharing the
+/// data files.
+pub const USER_KEY: &str = "user";
+
+/// Checks whether a tag can be written to and read back from the line format, which requires it
+/// to be a non-empty word without semicolons or backslashes.
+pub fn is_valid_tag(tag: &str) -> bool {
+    !tag.is_empty() && !tag.contains(|c: char| c.is_whitespace() || c == ';' || c == '\\')
+}
+
+/// Checks whether a metadata key can be written to and read back from the line format, which
+/// requires it to be a non-empty word of ASCII letters, digits, `_`, `-` and `.`. Values are
+/// restricted like tags, see `is_valid_tag`.
+pub fn is_valid_meta_key(key: &str) -> bool {
+    !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+}
+
+/// Splits a `key=value` word into the metadata key and value, `None` unless both are valid.
+pub fn parse_meta(word: &str) -> Option<(&str, &str)> {
+    let (key, value) = word.split_once('=')?;
+    (is_valid_meta_key(key) && is_valid_tag(value)).then_some((key, value))
+}
+
+/// The account must not be empty and every tag, the category and the metadata must be valid,
+/// see `is_valid_tag` and `is_valid_meta_key`.
+fn validate_account_fields(
+    account: &str,
+    tags: &[String],
+    category: Option<&str>,
+    meta: &BTreeMap<String, String>,
+) -> Result<(), ValidationError> {
+    if account.trim().is_empty() {
+        return Err(ValidationError::MissingAccount);
+    }
+    if let Some(category) = category.filter(|category| !is_valid_tag(category)) {
+        return Err(ValidationError::InvalidCategory(category.to_string()));
+    }
+    if let Some(key) = meta.keys().find(|key| !is_valid_meta_key(key)) {
+        return Err(ValidationError::InvalidMetaKey(key.clone()));
+    }
+    if let Some(value) = meta.values().find(|value| !is_valid_tag(value)) {
+        return Err(ValidationError::InvalidMetaValue(value.clone()));
+    }
+    match tags.iter().find(|tag| !is_valid_tag(tag)) {
+        Some(tag) => Err(ValidationError::InvalidTag(tag.clone())),
+        None => Ok(()),
+    }
+}
+
+/// The column of the character at the byte offset in the line, counting from 1.
+fn column(line: &str, offset: usize) -> usize {
+    line[..offset].chars().count() + 1
+}
+
+fn parse_datetime(s: &str, column: usize) -> Result<DateTime<Utc>, ParseError> {
+    DateTime::from_str(s).map_err(|err| ParseError::InvalidDate { column, err })
+}
+
+/// Formats a date and time with as many fractional digits as needed to represent it exactly.
+fn format_datetime(datetime: &DateTime<Utc>) -> String {
+    datetime.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+/// Writes the account followed by `$` if billable, the category (prefixed by `@`), the tags (each
+/// prefixed by `#`), the metadata (as `key=value`) and the description (after a semicolon),
+/// escaping characters which would otherwise be read back differently.
+fn write_account_fields(
+    f: &mut fmt::Formatter<'_>,
+    account: &str,
+    billable: bool,
+    category: Option<&str>,
+    tags: &[String],
+    meta: &BTreeMap<String, String>,
+    description: Option<&str>,
+) -> fmt::Result {
+    let mut account = escape(account, &['#', ';']);
+    // An account ending with an `@` word would otherwise be read as its category, and one ending
+    // with a `key=value` word as its metadata
+    if let Some(space) = account.rfind(' ') {
+        let last = &account[space + 1..];
+        if last.starts_with('@') {
+            account.insert(space + 1, '\\');
+        } else if let Some((key, _)) = parse_meta(last) {
+            account.insert(space + 1 + key.len(), '\\');
+        }
+    }
+    // An account ending with a `$` word would otherwise be read as billable
+    match account.strip_suffix(" $") {
+        Some(account) => write!(f, "{} \\$", account)?,
+        None => write!(f, "{}", account)?,
+    }
+    if billable {
+        write!(f, " $")?;
+    }
+    if let Some(category) = category {
+        write!(f, " @{}", category)?;
+    }
+    for tag in tags {
+        write!(f, " #{}", tag)?;
+    }
+    for (key, value) in meta {
+        write!(f, " {}={}", key, value)?;
+    }
+    if let Some(description) = description {
+        write!(f, " ; {}", escape(description, &[]))?;
+    }
+    Ok(())
+}
+
+/// The fields following the timestamps in the line format.
+struct AccountFields {
+    account: String,
+    tags: Vec<String>,
+    billable: bool,
+    category: Option<String>,
+    description: Option<String>,
+    meta: BTreeMap<String, String>,
+}
+
+/// Parses the fields written by `write_account_fields`, which start at the column of the line.
+fn parse_account_fields(s: &str, column: usize) -> Result<AccountFields, ParseError> {
+    let fields = parse_account_fields_ref(s, column)?;
+    Ok(AccountFields {
+        tags: fields.tags().map(str::to_string).collect(),
+        meta: fields.meta_map(),
+        account: fields.account.into_owned(),
+        billable: fields.billable,
+        category: fields.category.map(Cow::into_owned),
+        description: fields.description.map(Cow::into_owned),
+    })
+}
+
+/// The fields following the timestamps, borrowed from the line where they need no unescaping.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct AccountFieldsRef<'a> {
+    account: Cow<'a, str>,
+    /// The tags as written, e.g. `#review #client`.
+    tags: &'a str,
+    /// The metadata as written, e.g. `ticket=PROJ-12 pr=482`.
+    meta: &'a str,
+    billable: bool,
+    category: Option<Cow<'a, str>>,
+    description: Option<Cow<'a, str>>,
+}
+
+impl<'a> AccountFieldsRef<'a> {
+    fn tags(&self) -> impl Iterator<Item = &'a str> {
+        self.tags
+            .split(' ')
+            .filter_map(|word| word.strip_prefix('#'))
+    }
+
+    fn meta(&self) -> impl Iterator<Item = (&'a str, &'a str)> {
+        self.meta.split(' ').filter_map(parse_meta)
+    }
+
+    /// The metadata, where a key written more than once has the last value.
+    fn meta_map(&self) -> BTreeMap<String, String> {
+        self.meta()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+}
+
+/// Parses the fields like `parse_account_fields`, without copying them out of the line.
+fn parse_account_fields_ref(s: &str, column: usize) -> Result<AccountFieldsRef<'_>, ParseError> {
+    let ranges = field_ranges(s);
+    let account = unescape_borrowed(&s[ranges.account]);
+    if account.is_empty() {
+        return Err(ParseError::MissingAccount { column });
+    }
+    Ok(AccountFieldsRef {
+        account,
+        tags: &s[ranges.tags],
+        meta: &s[ranges.meta],
+        billable: ranges.billable.is_some(),
+        // Without the `@`
+        category: ranges
+            .category
+            .map(|category| Cow::Borrowed(&s[category.start + 1..category.end])),
+        description: ranges
+            .description
+            .map(|description| unescape_borrowed(&s[description])),
+    })
+}
+
+/// Where the fields following the timestamps are in them, as byte ranges of the fields as
+/// written, see `field_ranges`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FieldRanges {
+    account: Range<usize>,
+    /// The `$`.
+    billable: Option<Range<usize>>,
+    /// The category with its `@`.
+    category: Option<Range<usize>>,
+    /// The tags, e.g. `#review #client`.
+    tags: Range<usize>,
+    /// The metadata, e.g. `ticket=PROJ-12 pr=482`.
+    meta: Range<usize>,
+    /// The description after the semicolon and the space following it.
+    description: Option<Range<usize>>,
+}
+
+/// Splits the fields written by `write_account_fields` into where each of them is.
+fn field_ranges(s: &str) -> FieldRanges {
+    // The description starts after the first unescaped semicolon
+    let mut escaped = false;
+    let separator = s.char_indices().find_map(|(index, c)| {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == ';' {
+            return Some(index);
+        }
+        None
+    });
+    let (body, description) = match separator {
+        Some(index) if s[index + 1..].starts_with(' ') => (&s[..index], Some(index + 2..s.len())),
+        Some(index) => (&s[..index], Some(index + 1..s.len())),
+        None => (s, None),
+    };
+    let trim_start = |range: Range<usize>| {
+        let spaces = body[range.clone()].len() - body[range.clone()].trim_start_matches(' ').len();
+        range.start + spaces..range.end
+    };
+
+    // The metadata are the trailing `key=value` words, tags the words starting with `#` before
+    // them and the account is every word before those. `end` is where the account ends, the
+    // first word is always part of it.
+    let body = body.trim_end_matches(' ');
+    let mut end = body.len();
+    while let Some(space) = body[..end].rfind(' ') {
+        match parse_meta(&body[space + 1..end]) {
+            Some(_) => end = space,
+            None => break,
+        }
+    }
+    let meta = trim_start(end..body.len());
+    let meta_start = end;
+    while let Some(space) = body[..end].rfind(' ') {
+        match body[space + 1..end].strip_prefix('#') {
+            Some(tag) if !tag.is_empty() => end = space,
+            _ => break,
+        }
+    }
+    let tags = trim_start(end..meta_start);
+
+    let category = match body[..end].rfind(' ') {
+        Some(space) if body[space + 1..end].len() > 1 && body[space + 1..].starts_with('@') => {
+            let category = space + 1..end;
+            end = space;
+            Some(category)
+        }
+        _ => None,
+    };
+
+    let billable = match body[..end].rfind(' ') {
+        Some(space) if &body[space + 1..end] == "$" => {
+            let billable = space + 1..end;
+            end = space;
+            Some(billable)
+        }
+        _ => None,
+    };
+
+    FieldRanges {
+        account: 0..body[..end].trim_end_matches(' ').len(),
+        billable,
+        category,
+        tags,
+        meta,
+        description,
+    }
+}
+
+/// Unescapes like `unescape`, borrowing the string when there is nothing to unescape.
+fn unescape_borrowed(s: &str) -> Cow<'_, str> {
+    if s.contains('\\') {
+        Cow::Owned(unescape(s))
+    } else {
+        Cow::Borrowed(s)
+    }
+}
+
+/// Escapes backslashes, line breaks and the given special characters with backslashes.
+fn escape(s: &str, special: &[char]) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c if special.contains(&c) => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Reverses `escape`, a backslash followed by any other character is read as that character.
+fn unescape(s: &str) -> String {
+    let mut unescaped = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some(c) => unescaped.push(c),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+/// A line which is not in the line format, with the column where the problem is, counting
+/// characters from 1. Storage adds the line number, see `storage::StorageError::Parse`.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum ParseError {
+    /// The line has no start date, which is always in the first column.
     MissingStart,
-    MissingStop,
-    DateParseError(chrono::ParseError),
+    MissingStop {
+        column: usize,
+    },
+    MissingAccount {
+        column: usize,
+    },
+    InvalidDate {
+        column: usize,
+        err: chrono::ParseError,
+    },
 }
 
-impl From<chrono::ParseError> for ParseError {
-    fn from(err: chrono::ParseError) -> Self {
-        ParseError::DateParseError(err)
+impl ParseError {
+    /// The column where the problem is, counting characters from 1.
+    pub fn column(&self) -> usize {
+        match self {
+            ParseError::MissingStart => 1,
+            ParseError::MissingStop { column }
+            | ParseError::MissingAccount { column }
+            | ParseError::InvalidDate { column, .. } => *column,
+        }
     }
 }
 
@@ -98,13 +1154,75 @@ impl fmt::Display for ParseError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             ParseError::MissingStart => write!(f, "missing start date"),
-            ParseError::MissingStop => write!(f, "missing stop date"),
-            ParseError::DateParseError(err) => err.fmt(f),
+            ParseError::MissingStop { column } => {
+                write!(f, "missing stop date at column {}", column)
+            }
+            ParseError::MissingAccount { column } => {
+                write!(f, "missing account at column {}", column)
+            }
+            ParseError::InvalidDate { column, err } => {
+                write!(f, "invalid date at column {}: {}", column, err)
+            }
         }
     }
 }
 
-impl Error for ParseError {}
+impl Error for ParseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ParseError::InvalidDate { err, .. } => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// An entry which can be parsed but should not be recorded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ValidationError {
+    StopBeforeStart {
+        start: DateTime<Utc>,
+        stop: DateTime<Utc>,
+    },
+    MissingAccount,
+    MissingStart,
+    MissingStop,
+    /// The tag could not be read back from the line format, see `is_valid_tag`.
+    InvalidTag(String),
+    /// The category could not be read back from the line format, it is restricted like a tag.
+    InvalidCategory(String),
+    /// The metadata key could not be read back from the line format, see `is_valid_meta_key`.
+    InvalidMetaKey(String),
+    /// The metadata value could not be read back from the line format, it is restricted like a
+    /// tag.
+    InvalidMetaValue(String),
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::StopBeforeStart { start, stop } => write!(
+                f,
+                "the entry stops at {} before it starts at {}",
+                format_datetime(stop),
+                format_datetime(start)
+            ),
+            ValidationError::MissingAccount => write!(f, "missing account"),
+            ValidationError::MissingStart => write!(f, "missing start time"),
+            ValidationError::MissingStop => write!(f, "missing stop time"),
+            ValidationError::InvalidTag(tag) => write!(f, r#"invalid tag "{}""#, tag),
+            ValidationError::InvalidCategory(category) => {
+                write!(f, r#"invalid category "{}""#, category)
+            }
+            ValidationError::InvalidMetaKey(key) => write!(f, r#"invalid metadata key "{}""#, key),
+            ValidationError::InvalidMetaValue(value) => {
+                write!(f, r#"invalid metadata value "{}""#, value)
+            }
+        }
+    }
+}
+
+impl Error for ValidationError {}
 
 #[cfg(test)]
 mod tests {
@@ -115,8 +1233,12 @@ mod tests {
         let timeclock = Entry {
             start: DateTime::from_str("2021-07-03T10:00:00Z").unwrap(),
             stop: DateTime::from_str("2021-07-03T13:00:00Z").unwrap(),
-            account: "Time Tracker".to_string(),
+            account: "Time Tracker".into(),
+            tags: Vec::new(),
+            billable: false,
+            category: None,
             description: None,
+            meta: BTreeMap::new(),
         }
         .format_as_timeclock();
 
@@ -126,13 +1248,206 @@ mod tests {
         );
     }
 
+    #[test]
+    fn escape_timeclock_fields() {
+        let entry = Entry::from_str(
+            "2021-07-03T10:00:00Z 2021-07-03T13:00:00Z Time  \\; Tracker #a,b #c:d \
+             ; Fixed it;\\nthen  shipped",
+        )
+        .unwrap();
+
+        assert_eq!(
+            entry.format_as_timeclock(),
+            "i 2021-07-03 10:00:00+0000 Time , Tracker  Fixed it, then shipped  ; a-b:, c-d:\n\
+             o 2021-07-03 13:00:00+0000"
+        );
+        let mut blank = entry;
+        blank.description = Some(" \n".to_string());
+        blank.tags.clear();
+        assert_eq!(
+            blank.format_as_timeclock(),
+            "i 2021-07-03 10:00:00+0000 Time , Tracker\no 2021-07-03 13:00:00+0000"
+        );
+    }
+
+    #[test]
+    fn build_entries() {
+        let start = DateTime::from_str("2021-07-03T10:00:00Z").unwrap();
+        let stop = DateTime::from_str("2021-07-03T13:00:00Z").unwrap();
+
+        assert_eq!(
+            Entry::builder()
+                .start(start)
+                .stop(stop)
+                .account("Client")
+                .tag("dev")
+                .tag("dev")
+                .billable(true)
+                .description("")
+                .build(),
+            Ok(Entry::from_str("2021-07-03T10:00:00Z 2021-07-03T13:00:00Z Client $ #dev").unwrap())
+        );
+        assert_eq!(
+            Entry::builder().stop(stop).account("Client").build(),
+            Err(ValidationError::MissingStart)
+        );
+        assert_eq!(
+            Entry::builder()
+                .start(stop)
+                .stop(start)
+                .account("Client")
+                .build(),
+            Err(ValidationError::StopBeforeStart {
+                start: stop,
+                stop: start
+            })
+        );
+        assert_eq!(
+            Entry::builder()
+                .start(start)
+                .stop(stop)
+                .account("Client")
+                .tag("a b")
+                .build(),
+            Err(ValidationError::InvalidTag("a b".to_string()))
+        );
+    }
+
+    #[test]
+    fn build_running_entries() {
+        let start = DateTime::from_str("2021-07-03T10:00:00Z").unwrap();
+
+        let running = RunningEntry::builder()
+            .start(start)
+            .account("Client")
+            .tags(vec!["dev", "review", "dev"])
+            .category_if_any(Some("development"))
+            .description_if_any(None::<String>)
+            .build()
+            .unwrap();
+        assert_eq!(
+            running.to_string(),
+            "2021-07-03T10:00:00Z Client @development #dev #review"
+        );
+        assert_eq!(
+            RunningEntry::from(running.clone().into_entry(start)),
+            running
+        );
+        assert_eq!(
+            RunningEntry::builder().account("Client").build(),
+            Err(ValidationError::MissingStart)
+        );
+        assert_eq!(
+            RunningEntry::builder().start(start).build(),
+            Err(ValidationError::MissingAccount)
+        );
+    }
+
+    #[test]
+    fn format_entry_as_timeclock_in_time_zone() {
+        let entry =
+            Entry::from_str("2021-07-03T10:00:00Z 2021-07-03T13:00:00Z Time Tracker").unwrap();
+
+        assert_eq!(
+            entry.format_as_timeclock_in(&chrono_tz::Europe::Oslo),
+            "i 2021-07-03 12:00:00+0200 Time Tracker\no 2021-07-03 15:00:00+0200"
+        );
+    }
+
+    #[test]
+    fn find_latest_timeclock_check_in() {
+        let journal = "i 2021-07-03 10:00:00+0000 A\n\
+                       o 2021-07-03 13:00:00+0000\n\
+                       \n\
+                       ; comment\n\
+                       i 2021-07-04 10:00:00+0200 B\n\
+                       o 2021-07-04 12:00:00+0200\n";
+
+        assert_eq!(
+            latest_timeclock_check_in(journal),
+            Some(DateTime::from_str("2021-07-04T08:00:00Z").unwrap())
+        );
+        assert_eq!(latest_timeclock_check_in("; nothing yet\n"), None);
+    }
+
+    #[test]
+    fn entry_id_depends_on_start_and_account() {
+        let entry =
+            Entry::from_str("2021-07-03T10:00:00Z 2021-07-03T13:00:00Z Time Tracker").unwrap();
+        let mut stopped_later = entry.clone();
+        stopped_later.stop = DateTime::from_str("2021-07-03T14:00:00Z").unwrap();
+        let mut other_account = entry.clone();
+        other_account.account = "Other".into();
+
+        assert_eq!(entry.id(), "aa58e89a4b2e7c4c");
+        assert_eq!(entry.id(), stopped_later.id());
+        assert_ne!(entry.id(), other_account.id());
+    }
+
+    #[test]
+    fn annotate_entries() {
+        let mut entry =
+            Entry::from_str("2021-07-03T10:00:00Z 2021-07-03T13:00:00Z Time Tracker").unwrap();
+        entry.annotate("fixed the login bug");
+        entry.annotate("added tests");
+
+        assert_eq!(
+            entry.description.as_deref(),
+            Some("fixed the login bug; added tests")
+        );
+    }
+
+    #[test]
+    fn split_entry() {
+        let entry =
+            Entry::from_str("2021-07-03T10:00:00Z 2021-07-03T13:00:00Z Time Tracker #dev").unwrap();
+
+        assert_eq!(
+            entry.split_at(DateTime::from_str("2021-07-03T11:30:00Z").unwrap()),
+            Some((
+                Entry::from_str("2021-07-03T10:00:00Z 2021-07-03T11:30:00Z Time Tracker #dev")
+                    .unwrap(),
+                Entry::from_str("2021-07-03T11:30:00Z 2021-07-03T13:00:00Z Time Tracker #dev")
+                    .unwrap()
+            ))
+        );
+        assert_eq!(entry.split_at(entry.start), None);
+        assert_eq!(entry.split_at(entry.stop), None);
+    }
+
+    #[test]
+    fn merge_entries() {
+        let mut entry =
+            Entry::from_str("2021-07-03T10:00:00Z 2021-07-03T11:00:00Z A #dev ; Parser").unwrap();
+        let next = Entry::from_str("2021-07-03T11:03:00Z 2021-07-03T12:00:00Z A #dev #bug ; Tests")
+            .unwrap();
+        let other = Entry::from_str("2021-07-03T11:03:00Z 2021-07-03T12:00:00Z B").unwrap();
+
+        assert!(entry.can_merge(&next, Duration::minutes(5)));
+        assert!(!entry.can_merge(&next, Duration::minutes(3)));
+        assert!(!entry.can_merge(&other, Duration::minutes(5)));
+
+        entry.merge(&next);
+        assert_eq!(
+            entry,
+            Entry::from_str(
+                "2021-07-03T10:00:00Z 2021-07-03T12:00:00Z A #dev #bug ; Parser; Tests"
+            )
+            .unwrap()
+        );
+    }
+
     #[test]
     fn display_entry() {
         let entry = Entry {
             start: DateTime::from_str("2021-07-03T10:00:00Z").unwrap(),
             stop: DateTime::from_str("2021-07-03T13:00:00Z").unwrap(),
-            account: "Time Tracker".to_string(),
+            account: "Time Tracker".into(),
+            tags: Vec::new(),
+            billable: false,
+            category: None,
             description: None,
+            meta: BTreeMap::new(),
         };
 
         assert_eq!(
@@ -151,8 +1466,12 @@ mod tests {
             Entry {
                 start: DateTime::from_str("2021-07-03T10:00:00Z").unwrap(),
                 stop: DateTime::from_str("2021-07-03T13:00:00Z").unwrap(),
-                account: "Time Tracker".to_string(),
+                account: "Time Tracker".into(),
+                tags: Vec::new(),
+                billable: false,
+                category: None,
                 description: None,
+                meta: BTreeMap::new(),
             }
         );
     }
@@ -161,8 +1480,12 @@ mod tests {
     fn display_running_entry() {
         let entry = RunningEntry {
             start: DateTime::from_str("2021-07-03T10:00:00Z").unwrap(),
-            account: "Time Tracker".to_string(),
+            account: "Time Tracker".into(),
+            tags: Vec::new(),
+            billable: false,
+            category: None,
             description: None,
+            meta: BTreeMap::new(),
         };
 
         assert_eq!(format!("{}", entry), "2021-07-03T10:00:00Z Time Tracker");
@@ -176,9 +1499,351 @@ mod tests {
             entry,
             RunningEntry {
                 start: DateTime::from_str("2021-07-03T10:00:00Z").unwrap(),
-                account: "Time Tracker".to_string(),
+                account: "Time Tracker".into(),
+                tags: Vec::new(),
+                billable: false,
+                category: None,
                 description: None,
+                meta: BTreeMap::new(),
             }
         );
     }
+
+    #[test]
+    fn display_entry_with_tags_and_description() {
+        let entry = Entry {
+            start: DateTime::from_str("2021-07-03T10:00:00Z").unwrap(),
+            stop: DateTime::from_str("2021-07-03T13:00:00.250Z").unwrap(),
+            account: "Client #1; Support".into(),
+            tags: vec!["billable".to_string(), "urgent".to_string()],
+            billable: false,
+            category: None,
+            description: Some("Fixed the login bug\nand the logout bug".to_string()),
+            meta: BTreeMap::new(),
+        };
+
+        assert_eq!(
+            format!("{}", entry),
+            "2021-07-03T10:00:00Z 2021-07-03T13:00:00.250Z Client \\#1\\; Support #billable #urgent ; \
+             Fixed the login bug\\nand the logout bug"
+        )
+    }
+
+    #[test]
+    fn parse_entry_with_tags_and_description() {
+        let entry = Entry::from_str(
+            "2021-07-03T10:00:00Z 2021-07-03T13:00:00Z Time Tracker #dev ; Parser #2; escaping",
+        )
+        .unwrap();
+
+        assert_eq!(&*entry.account, "Time Tracker");
+        assert_eq!(entry.tags, vec!["dev".to_string()]);
+        assert_eq!(entry.description.as_deref(), Some("Parser #2; escaping"));
+    }
+
+    #[test]
+    fn entries_with_meta() {
+        let line =
+            "2021-07-03T10:00:00Z 2021-07-03T13:00:00Z Client $ #dev ticket=PROJ-12 pr=482 ; Fix";
+        let entry = Entry::from_str(line).unwrap();
+        assert_eq!(&*entry.account, "Client");
+        assert!(entry.billable);
+        assert_eq!(entry.tags, vec!["dev".to_string()]);
+        assert_eq!(entry.meta_value("ticket"), Some("PROJ-12"));
+        assert_eq!(entry.meta_value("pr"), Some("482"));
+        assert_eq!(
+            entry.to_string(),
+            "2021-07-03T10:00:00Z 2021-07-03T13:00:00Z Client $ #dev pr=482 ticket=PROJ-12 ; Fix"
+        );
+        let entry_ref = Entry::parse(line).unwrap();
+        assert_eq!(
+            entry_ref.meta().collect::<Vec<_>>(),
+            vec![("ticket", "PROJ-12"), ("pr", "482")]
+        );
+        assert_eq!(entry_ref.meta_value("pr"), Some("482"));
+
+        // Accounts ending with a `key=value` word are escaped, words that are not metadata are not
+        let mut escaped = entry;
+        escaped.account = "Meeting room=4".into();
+        escaped.meta.clear();
+        escaped.billable = false;
+        assert_eq!(
+            escaped.to_string(),
+            "2021-07-03T10:00:00Z 2021-07-03T13:00:00Z Meeting room\\=4 #dev ; Fix"
+        );
+        assert_eq!(Entry::from_str(&escaped.to_string()), Ok(escaped));
+        let equation = Entry::from_str("2021-07-03T10:00:00Z 2021-07-03T13:00:00Z a=b c=").unwrap();
+        assert_eq!(&*equation.account, "a=b c=");
+
+        assert_eq!(
+            Entry::builder()
+                .start(DateTime::from_str("2021-07-03T10:00:00Z").unwrap())
+                .stop(DateTime::from_str("2021-07-03T13:00:00Z").unwrap())
+                .account("Client")
+                .meta("ticket", "PROJ 12")
+                .build(),
+            Err(ValidationError::InvalidMetaValue("PROJ 12".to_string()))
+        );
+        assert!(!is_valid_meta_key("#tag"));
+        assert_eq!(parse_meta("a=b=c"), Some(("a", "b=c")));
+    }
+
+    #[test]
+    fn billable_entries() {
+        let entry =
+            Entry::from_str("2021-07-03T10:00:00Z 2021-07-03T13:00:00Z Client $ #dev").unwrap();
+        let mut dollars = entry.clone();
+        dollars.account = "Client $".into();
+        dollars.billable = false;
+
+        assert_eq!(&*entry.account, "Client");
+        assert!(entry.billable);
+        assert_eq!(
+            format!("{}", dollars),
+            "2021-07-03T10:00:00Z 2021-07-03T13:00:00Z Client \\$ #dev"
+        );
+        assert_eq!(Entry::from_str(&dollars.to_string()), Ok(dollars));
+    }
+
+    #[test]
+    fn categorized_entries() {
+        let entry =
+            Entry::from_str("2021-07-03T10:00:00Z 2021-07-03T13:00:00Z Client $ @meetings #x")
+                .unwrap();
+        assert_eq!(&*entry.account, "Client");
+        assert!(entry.billable);
+        assert_eq!(entry.category.as_deref(), Some("meetings"));
+
+        let mut at = entry.clone();
+        at.account = "Client @home".into();
+        at.category = None;
+        assert_eq!(
+            at.to_string(),
+            "2021-07-03T10:00:00Z 2021-07-03T13:00:00Z Client \\@home $ #x"
+        );
+        assert_eq!(Entry::from_str(&at.to_string()), Ok(at));
+        assert_eq!(
+            Entry::from_str("2021-07-03T10:00:00Z 2021-07-03T13:00:00Z @home")
+                .unwrap()
+                .account
+                .as_ref(),
+            "@home"
+        );
+
+        let mut invalid = entry;
+        invalid.category = Some("two words".to_string());
+        assert_eq!(
+            invalid.validate(),
+            Err(ValidationError::InvalidCategory("two words".to_string()))
+        );
+    }
+
+    #[test]
+    fn parse_entry_missing_account() {
+        assert_eq!(
+            Entry::from_str("2021-07-03T10:00:00Z 2021-07-03T13:00:00Z ; description"),
+            Err(ParseError::MissingAccount { column: 43 })
+        );
+    }
+
+    #[test]
+    fn parse_borrowing_from_the_line() {
+        let line = "2021-07-03T10:00:00Z 2021-07-03T13:00:00Z Client $ #dev #review ; Notes";
+        let entry = Entry::parse(line).unwrap();
+        assert!(matches!(entry.fields.account, Cow::Borrowed("Client")));
+        assert_eq!(entry.tags().collect::<Vec<_>>(), vec!["dev", "review"]);
+        assert!(entry.billable());
+        assert_eq!(entry.description(), Some("Notes"));
+        assert_eq!(entry.duration(), Duration::hours(3));
+        assert_eq!(entry.to_entry(), Entry::from_str(line).unwrap());
+
+        let escaped = Entry::parse("2021-07-03T10:00:00Z 2021-07-03T13:00:00Z A\\$ #x").unwrap();
+        assert_eq!(escaped.account(), "A$");
+        assert!(matches!(escaped.fields.account, Cow::Owned(_)));
+    }
+
+    #[test]
+    fn parse_errors_have_columns() {
+        let err = Entry::from_str("2021-07-03T10:00:00Z 2021-07-0 A").unwrap_err();
+        assert!(matches!(err, ParseError::InvalidDate { column: 22, .. }));
+        assert!(err.source().is_some());
+        assert_eq!(
+            Entry::from_str("2021-07-03T10:00:00Z 2021-07-03T13:00:00Z"),
+            Err(ParseError::MissingStop { column: 22 })
+        );
+        assert_eq!(RunningEntry::from_str("ø 2021").unwrap_err().column(), 1);
+        assert_eq!(
+            RunningEntry::from_str("2021-07-03T10:00:00Z ").unwrap_err(),
+            ParseError::MissingAccount { column: 22 }
+        );
+    }
+
+    #[test]
+    fn validate_entries() {
+        let entry = Entry::from_str("2021-07-03T10:00:00Z 2021-07-03T13:00:00Z A #x").unwrap();
+        assert_eq!(entry.validate(), Ok(()));
+
+        let mut reversed = entry.clone();
+        reversed.stop = reversed.start - Duration::hours(1);
+        assert!(matches!(
+            reversed.validate(),
+            Err(ValidationError::StopBeforeStart { .. })
+        ));
+
+        let mut running = RunningEntry::from_str("2021-07-03T10:00:00Z A").unwrap();
+        running.tags.push("two words".to_string());
+        assert_eq!(
+            running.validate(),
+            Err(ValidationError::InvalidTag("two words".to_string()))
+        );
+        running.account = " ".into();
+        assert_eq!(running.validate(), Err(ValidationError::MissingAccount));
+    }
+
+    #[test]
+    fn parse_running_entry_with_tags() {
+        let entry = RunningEntry::from_str("2021-07-03T10:00:00.5Z A B #x #y").unwrap();
+
+        assert_eq!(&*entry.account, "A B");
+        assert_eq!(entry.tags, vec!["x".to_string(), "y".to_string()]);
+        assert_eq!(entry.start.timestamp_subsec_millis(), 500);
+    }
+
+    mod properties {
+        use super::*;
+        use chrono::TimeZone;
+        use proptest::prelude::*;
+
+        fn datetime() -> impl Strategy<Value = DateTime<Utc>> {
+            // From 1970 until 2100, with nanosecond precision
+            (0i64..4_102_444_800, 0u32..1_000_000_000)
+                .prop_map(|(seconds, nanos)| Utc.timestamp(seconds, nanos))
+        }
+
+        fn account() -> impl Strategy<Value = String> {
+            "[^\\p{Cc}]*[^\\p{Cc} ]".prop_filter("accounts cannot be empty", |account| {
+                !account.trim_start_matches(' ').is_empty()
+            })
+        }
+
+        fn tags() -> impl Strategy<Value = Vec<String>> {
+            prop::collection::vec("[^\\s;\\\\]+", 0..4)
+        }
+
+        fn category() -> impl Strategy<Value = Option<String>> {
+            prop::option::of("[^\\s;\\\\]+")
+        }
+
+        fn description() -> impl Strategy<Value = Option<String>> {
+            prop::option::of(".*")
+        }
+
+        fn meta() -> impl Strategy<Value = BTreeMap<String, String>> {
+            prop::collection::btree_map("[A-Za-z0-9_.-]+", "[^\\s;\\\\]+", 0..3)
+        }
+
+        proptest! {
+            #[test]
+            fn entry_round_trip(
+                start in datetime(),
+                stop in datetime(),
+                account in account(),
+                tags in tags(),
+                billable in any::<bool>(),
+                category in category(),
+                description in description(),
+                meta in meta(),
+            ) {
+                let entry = Entry { start, stop, account: account.into(), tags, billable, category, description, meta };
+
+                prop_assert_eq!(Entry::from_str(&entry.to_string()), Ok(entry));
+            }
+
+            #[test]
+            fn running_entry_round_trip(
+                start in datetime(),
+                account in account(),
+                tags in tags(),
+                billable in any::<bool>(),
+                category in category(),
+                description in description(),
+                meta in meta(),
+            ) {
+                let entry = RunningEntry { start, account: account.into(), tags, billable, category, description, meta };
+
+                prop_assert_eq!(RunningEntry::from_str(&entry.to_string()), Ok(entry));
+            }
+
+            #[test]
+            fn timeclock_follows_hledger_grammar(
+                start in datetime(),
+                account in account(),
+                tags in tags(),
+                description in description(),
+            ) {
+                let entry = Entry {
+                    start,
+                    stop: start,
+                    account: account.into(),
+                    tags,
+                    billable: false,
+                    category: None,
+                    description,
+                    meta: BTreeMap::new(),
+                };
+                let timeclock = entry.format_as_timeclock();
+                let lines: Vec<&str> = timeclock.lines().collect();
+                prop_assert_eq!(lines.len(), 2);
+                prop_assert!(lines[1].starts_with("o "));
+
+                // The account and the description end at two spaces, the comment starts with a
+                // semicolon and has `name:` tags separated by commas
+                let fields = lines[0].splitn(4, ' ').nth(3).unwrap();
+                let (fields, comment) = match fields.split_once(';') {
+                    Some((fields, comment)) => (fields.strip_suffix("  ").unwrap(), Some(comment)),
+                    None => (fields, None),
+                };
+                let mut fields = fields.split("  ");
+                let account = fields.next().unwrap();
+                prop_assert!(!account.is_empty() && !account.starts_with(' '));
+                prop_assert!(!account.ends_with(' '));
+                for description in fields {
+                    prop_assert!(!description.is_empty() && !description.starts_with(' '));
+                }
+                if let Some(comment) = comment {
+                    for tag in comment.split(',') {
+                        let name = tag.trim_start().strip_suffix(':').unwrap();
+                        prop_assert!(!name.is_empty());
+                        prop_assert!(!name.contains(|c: char| c == ':' || c.is_whitespace()));
+                    }
+                }
+                prop_assert_eq!(latest_timeclock_check_in(&timeclock), Some(entry.start.trunc_subsecs(0)));
+            }
+
+            #[test]
+            fn parse_arbitrary_lines(line in ".*") {
+                let _ = Entry::from_str(&line);
+                let _ = RunningEntry::from_str(&line);
+            }
+
+            #[test]
+            fn entry_serializes_to_single_line(
+                start in datetime(),
+                account in account(),
+                description in description(),
+            ) {
+                let entry = RunningEntry {
+                    start,
+                    account: account.into(),
+                    tags: Vec::new(),
+                    billable: false,
+                    category: None,
+                    description,
+                    meta: BTreeMap::new(),
+                };
+
+                prop_assert!(!entry.to_string().contains(['\n', '\r']));
+            }
+        }
+    }
 }
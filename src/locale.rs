@@ -0,0 +1,316 @@
+//! The language of human-readable output, for the names of days and months and the decimal
+//! separator of durations. Output meant for other programs is always in English.
+
+use crate::duration::DurationFormat;
+use chrono::{Datelike, Duration};
+use std::env;
+use std::str::FromStr;
+
+/// A language, chosen by `locale` in the config or otherwise by `LC_ALL` or `LANG`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Locale {
+    #[default]
+    English,
+    Danish,
+    Dutch,
+    French,
+    German,
+    Norwegian,
+    Spanish,
+    Swedish,
+}
+
+impl Locale {
+    /// The locale configured, or otherwise the one of the environment, falling back to English
+    /// for languages without translations like `C`.
+    pub fn detect(configured: Option<&str>) -> Result<Self, String> {
+        if let Some(configured) = configured {
+            return configured.parse();
+        }
+        Ok(["LC_ALL", "LANG"]
+            .iter()
+            .filter_map(|name| env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|value| value.parse().ok())
+            .unwrap_or_default())
+    }
+
+    /// Monday to Sunday.
+    fn weekdays(self) -> [&'static str; 7] {
+        match self {
+            Locale::English => [
+                "Monday",
+                "Tuesday",
+                "Wednesday",
+                "Thursday",
+                "Friday",
+                "Saturday",
+                "Sunday",
+            ],
+            Locale::Danish => [
+                "mandag", "tirsdag", "onsdag", "torsdag", "fredag", "lørdag", "søndag",
+            ],
+            Locale::Dutch => [
+                "maandag",
+                "dinsdag",
+                "woensdag",
+                "donderdag",
+                "vrijdag",
+                "zaterdag",
+                "zondag",
+            ],
+            Locale::French => [
+                "lundi", "mardi", "mercredi", "jeudi", "vendredi", "samedi", "dimanche",
+            ],
+            Locale::German => [
+                "Montag",
+                "Dienstag",
+                "Mittwoch",
+                "Donnerstag",
+                "Freitag",
+                "Samstag",
+                "Sonntag",
+            ],
+            Locale::Norwegian => [
+                "mandag", "tirsdag", "onsdag", "torsdag", "fredag", "lørdag", "søndag",
+            ],
+            Locale::Spanish => [
+                "lunes",
+                "martes",
+                "miércoles",
+                "jueves",
+                "viernes",
+                "sábado",
+                "domingo",
+            ],
+            Locale::Swedish => [
+                "måndag", "tisdag", "onsdag", "torsdag", "fredag", "lördag", "söndag",
+            ],
+        }
+    }
+
+    /// January to December.
+    fn months(self) -> [&'static str; 12] {
+        match self {
+            Locale::English => [
+                "January",
+                "February",
+                "March",
+                "April",
+                "May",
+                "June",
+                "July",
+                "August",
+                "September",
+                "October",
+                "November",
+                "December",
+            ],
+            Locale::Danish => [
+                "januar",
+                "februar",
+                "marts",
+                "april",
+                "maj",
+                "juni",
+                "juli",
+                "august",
+                "september",
+                "oktober",
+                "november",
+                "december",
+            ],
+            Locale::Norwegian => [
+                "januar",
+                "februar",
+                "mars",
+                "april",
+                "mai",
+                "juni",
+                "juli",
+                "august",
+                "september",
+                "oktober",
+                "november",
+                "desember",
+            ],
+            Locale::Dutch => [
+                "januari",
+                "februari",
+                "maart",
+                "april",
+                "mei",
+                "juni",
+                "juli",
+                "augustus",
+                "september",
+                "oktober",
+                "november",
+                "december",
+            ],
+            Locale::French => [
+                "janvier",
+                "février",
+                "mars",
+                "avril",
+                "mai",
+                "juin",
+                "juillet",
+                "août",
+                "septembre",
+                "octobre",
+                "novembre",
+                "décembre",
+            ],
+            Locale::German => [
+                "Januar",
+                "Februar",
+                "März",
+                "April",
+                "Mai",
+                "Juni",
+                "Juli",
+                "August",
+                "September",
+                "Oktober",
+                "November",
+                "Dezember",
+            ],
+            Locale::Spanish => [
+                "enero",
+                "febrero",
+                "marzo",
+                "abril",
+                "mayo",
+                "junio",
+                "julio",
+                "agosto",
+                "septiembre",
+                "octubre",
+                "noviembre",
+                "diciembre",
+            ],
+            Locale::Swedish => [
+                "januari",
+                "februari",
+                "mars",
+                "april",
+                "maj",
+                "juni",
+                "juli",
+                "augusti",
+                "september",
+                "oktober",
+                "november",
+                "december",
+            ],
+        }
+    }
+
+    /// The separator of the whole and fractional part of numbers.
+    pub fn decimal_separator(self) -> char {
+        match self {
+            Locale::English => '.',
+            _ => ',',
+        }
+    }
+
+    /// The chrono format with the names of the day and month, `%A`, `%a`, `%B` and `%b`, written
+    /// out for the date, e.g. `date.format(&locale.pattern("%A %Y-%m-%d", &date))`. Abbreviated
+    /// names are the first three letters.
+    pub fn pattern(self, pattern: &str, date: &impl Datelike) -> String {
+        let weekday = self.weekdays()[date.weekday().num_days_from_monday() as usize];
+        let month = self.months()[date.month0() as usize];
+        let short = |name: &str| name.chars().take(3).collect::<String>();
+        let mut localized = String::new();
+        let mut chars = pattern.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                localized.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('A') => localized.push_str(weekday),
+                Some('a') => localized.push_str(&short(weekday)),
+                Some('B') => localized.push_str(month),
+                Some('b') => localized.push_str(&short(month)),
+                Some(specifier) => {
+                    localized.push('%');
+                    localized.push(specifier);
+                }
+                None => localized.push('%'),
+            }
+        }
+        localized
+    }
+
+    /// Writes the duration in the format with the decimal separator of the locale.
+    pub fn format_duration(self, format: DurationFormat, duration: Duration) -> String {
+        let formatted = format.format(duration);
+        match format {
+            DurationFormat::DecimalHours => {
+                formatted.replace('.', &self.decimal_separator().to_string())
+            }
+            _ => formatted,
+        }
+    }
+}
+
+impl FromStr for Locale {
+    type Err = String;
+
+    /// Reads a language code, optionally with a territory and encoding like `de_DE.UTF-8`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let language = s
+            .split(['_', '-', '.', '@'])
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+        match language.as_str() {
+            "en" => Ok(Locale::English),
+            "da" => Ok(Locale::Danish),
+            "nl" => Ok(Locale::Dutch),
+            "fr" => Ok(Locale::French),
+            "de" => Ok(Locale::German),
+            "nb" | "nn" | "no" => Ok(Locale::Norwegian),
+            "es" => Ok(Locale::Spanish),
+            "sv" => Ok(Locale::Swedish),
+            _ => Err(format!(r#"unknown locale "{}""#, s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn parse_locales() {
+        assert_eq!("de_DE.UTF-8".parse(), Ok(Locale::German));
+        assert_eq!("nb-NO".parse(), Ok(Locale::Norwegian));
+        assert_eq!("fr".parse(), Ok(Locale::French));
+        assert!("C".parse::<Locale>().is_err());
+        assert_eq!(Locale::detect(Some("sv")), Ok(Locale::Swedish));
+        assert!(Locale::detect(Some("xx")).is_err());
+    }
+
+    #[test]
+    fn localize_output() {
+        let date = NaiveDate::from_ymd(2021, 3, 1);
+        let format =
+            |locale: Locale, pattern| date.format(&locale.pattern(pattern, &date)).to_string();
+        assert_eq!(format(Locale::English, "%A %Y-%m-%d"), "Monday 2021-03-01");
+        assert_eq!(format(Locale::German, "%A %d. %B"), "Montag 01. März");
+        assert_eq!(format(Locale::French, "%a %b %%A"), "lun mar %A");
+
+        let duration = Duration::minutes(90);
+        let decimal = DurationFormat::DecimalHours;
+        assert_eq!(Locale::English.format_duration(decimal, duration), "1.50");
+        assert_eq!(Locale::Norwegian.format_duration(decimal, duration), "1,50");
+        assert_eq!(
+            Locale::German.format_duration(DurationFormat::HoursMinutes, duration),
+            "1h 30m"
+        );
+    }
+}
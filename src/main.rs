@@ -1,23 +1,101 @@
-use chrono::Utc;
-use once_cell::sync::Lazy;
+use chrono::{DateTime, Utc};
+use cli::{CliError, ResultExt};
 use std::env;
-use std::fs::{self, OpenOptions};
-use std::io::{BufRead, BufReader, Write};
+use std::ffi::OsString;
+use std::io::{self, IsTerminal};
+#[cfg(feature = "encryption")]
+use std::path::Path;
 use std::path::PathBuf;
-use std::str::FromStr;
+use std::process;
 use structopt::StructOpt;
-use timetracker::{Entry, RunningEntry};
+use timetracker::accounts::Accounts;
+use timetracker::audit::AuditLog;
+use timetracker::backup::Backups;
+use timetracker::config::Config;
+#[cfg(feature = "encryption")]
+use timetracker::encryption::{EncryptionError, Key};
+use timetracker::locale::Locale;
+use timetracker::oplog::{self, OpLog};
+use timetracker::paths::{self, Paths};
+use timetracker::storage::{self, Storage, WriteMode};
 
-static DEFAULT_RUNNING_FILE: Lazy<String> =
-    Lazy::new(|| format!("{}/.tt_running", env::var("HOME").as_deref().unwrap_or(".")));
+#[cfg(feature = "http")]
+mod caldav;
+mod cli;
+#[cfg(feature = "http")]
+mod clockify;
+#[cfg(unix)]
+mod daemon;
+#[cfg(feature = "http")]
+mod jira;
+mod output;
+#[cfg(feature = "server")]
+mod server;
+#[cfg(feature = "http")]
+mod slack;
 
 #[derive(Debug, StructOpt)]
 struct Opt {
-    #[structopt(short, long, parse(from_os_str), env = "TIMETRACKER_FILE")]
-    file: PathBuf,
+    /// The entries file, $XDG_DATA_HOME/tt/entries (%LOCALAPPDATA%\tt\entries on Windows) by
+    /// default. Given more than once or as a pattern like `archive/*`, the entries of every file
+    /// are read and changes are made to the first one
+    #[structopt(
+        short,
+        long,
+        parse(from_os_str),
+        env = "TIMETRACKER_FILE",
+        number_of_values = 1
+    )]
+    file: Vec<PathBuf>,
 
-    #[structopt(long, parse(from_os_str), env="TIMETRACKER_RUNNING_FILE", default_value=&DEFAULT_RUNNING_FILE)]
-    running_file: PathBuf,
+    /// The running file, $XDG_STATE_HOME/tt/running (%LOCALAPPDATA%\tt\running on Windows) by
+    /// default
+    #[structopt(long, parse(from_os_str), env = "TIMETRACKER_RUNNING_FILE")]
+    running_file: Option<PathBuf>,
+
+    /// The config file, $XDG_CONFIG_HOME/tt/config.toml (%APPDATA%\tt\config.toml on Windows) by
+    /// default
+    #[structopt(long, parse(from_os_str), env = "TIMETRACKER_CONFIG")]
+    config: Option<PathBuf>,
+
+    /// Keeps the default entries, running and config files in a subdirectory named after the
+    /// profile
+    #[structopt(long, env = "TIMETRACKER_PROFILE", parse(try_from_str = parse_profile))]
+    profile: Option<String>,
+
+    /// Never color the output, which is also the case with NO_COLOR set or when not writing to a
+    /// terminal
+    #[structopt(long, global = true)]
+    no_color: bool,
+
+    /// Log more about what is done, `-vv` for debug logs about reading and writing files.
+    /// RUST_LOG takes precedence
+    #[structopt(short, long, global = true, parse(from_occurrences))]
+    verbose: u8,
+
+    /// Only log errors
+    #[structopt(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Print what would be written instead of changing any files
+    #[structopt(long, global = true)]
+    dry_run: bool,
+
+    /// Use this time as the current time, e.g. `2021-07-01T17:00:00Z`, for scripts and tests
+    /// that need the same results every run
+    #[structopt(
+        long,
+        global = true,
+        hidden = true,
+        env = "TIMETRACKER_NOW",
+        parse(try_from_str = parse_now)
+    )]
+    now: Option<DateTime<Utc>>,
+
+    /// Fail on the first line of the files that cannot be parsed, instead of skipping such lines
+    /// with a warning when only reading
+    #[structopt(long, global = true)]
+    strict: bool,
 
     #[structopt(subcommand)]
     cmd: Command,
@@ -25,166 +103,660 @@ struct Opt {
 
 #[derive(Debug, StructOpt)]
 enum Command {
-    Export {
-        #[structopt(short, long, parse(from_os_str))]
-        output: PathBuf,
-    },
-    Running,
-    Start {
-        account: String,
-    },
-    Stop {
-        account: Option<String>,
-    },
+    /// Records an entry by how long it lasted, ending now or a while ago, e.g.
+    /// `tt add Support --duration 45m`
+    Add(cli::add::Args),
+    /// Records the entries of a plan of a day, with lines like `start dev 09:00`,
+    /// `switch meetings 11:00` and `stop 17:00`. An account still started at the end is started
+    Apply(cli::apply::Args),
+    /// Adds a note to the description of the last entry, or of a running entry
+    Annotate(cli::annotate::Args),
+    /// Lists the accounts with metadata in accounts.toml, or defines the metadata of an account
+    Accounts(cli::accounts::Args),
+    /// Shows the windows or apps active during each entry, as sampled with `audit --record`
+    /// while entries were running, to check the tracked time against what was done
+    #[cfg(feature = "activity")]
+    Audit(cli::audit::Args),
+    /// Takes a snapshot of the data and config files, which `restore` can bring back
+    Backup(cli::backup::Args),
+    /// Shows the overtime, the time tracked beyond the hours expected by the work calendar
+    Balance(cli::balance::Args),
+    /// Projects when the budgets of accounts run out at the rate of the recent days, flagging
+    /// the ones running out before their deadlines, set with `accounts --budget --deadline`
+    Burn(cli::burn::Args),
+    /// Finds untracked gaps during work hours and duplicated entries, checking for both if
+    /// neither is given
+    Check(cli::check::Args),
+    /// Removes entries that do not last any time
+    Clean(cli::clean::Args),
+    /// Folds the operations logged with `oplog` under `[sync]` into the data files, on one
+    /// machine once the logs of the others are synced
+    Compact,
+    /// Converts a file of entries to or from the compact format, which loads faster and can be
+    /// read as an additional entries file given with `--file`, e.g. for archives of earlier years
+    #[cfg(feature = "compact")]
+    Convert(cli::convert::Args),
+    /// Compares the entries file with another one, e.g. a copy synced from another machine,
+    /// listing the entries only in either and the ones that differ. Exits with 1 if they differ
+    Diff(cli::diff::Args),
+    /// Keeps the running entries in memory and answers for them over a Unix socket next to the
+    /// running file, which `prompt`, `status --short` and `running` ask before reading the files
+    #[cfg(unix)]
+    Daemon,
+    /// Diagnoses problems with the data files: permissions, lines that cannot be parsed, entries
+    /// out of order, overlapping, duplicated or without duration, and running entries already
+    /// recorded or tracked twice
+    Doctor(cli::doctor::Args),
+    /// Summarizes the time tracked in a week as plain text, with the total of each account and the
+    /// longest entries, e.g. for piping into `sendmail -t` from cron
+    Digest(cli::digest::Args),
+    /// Shows how a line of the data files is read, splitting it into its start, stop, account,
+    /// tags, metadata and description, and where and why it cannot be read if it cannot, e.g.
+    /// `tt explain --line 12` for a hand-edited line that stopped parsing
+    Explain(cli::explain::Args),
+    Export(cli::export::Args),
+    /// Manages the favorites, presets which `start @<name>` starts an entry with
+    Fav(cli::fav::FavCommand),
+    /// Shows how much time is left to track on a day to reach the hours to work, counting the
+    /// running entries, and when that is reached if they keep running
+    Free(cli::free::Args),
+    /// Shows the progress of the goals configured for accounts and tags
+    Goals,
+    /// Lists the entries whose account or description matches a regular expression
+    Grep(cli::grep::Args),
+    /// Shows the changes to the data files recorded in the audit log, with the command making them
+    History(cli::history::Args),
+    /// Shows a grid of the time tracked each day of a year
+    Heatmap(cli::heatmap::Args),
+    /// Sets tt up by creating the config file, asking for the settings most people change, and
+    /// the data directory with an empty entries file
+    Init(cli::init::Args),
+    /// Adds the entries from the data file of another time tracker
+    Import(cli::import::Args),
+    /// Issues invoices for the billable time of an account in a month, marking its entries as
+    /// invoiced so they are never billed again, and lists the issued invoices
+    Invoice(cli::invoice::InvoiceCommand),
+    /// Checks the config file for unknown settings, values which cannot be read and settings
+    /// which cannot be combined, listing each problem with its line. Exits with 1 if there are any
+    LintConfig,
+    /// Lists the completed entries, by default those of the last seven days
+    Log(cli::log::Args),
+    /// Merges entries for the same account following each other with short gaps between them
+    Merge(cli::merge::Args),
+    /// Combines entries files into one, e.g. copies from several machines, leaving out exact
+    /// duplicates and ordering the entries by when they start. Entries with the same start and
+    /// account but other fields are conflicts, which are listed and keep the file from being
+    /// written, exiting with 1
+    MergeFiles(cli::merge_files::Args),
+    /// Upgrades the entries file to the current version of the format
+    Migrate(cli::migrate::Args),
+    /// Moves files from the locations used by earlier versions to the XDG base directories
+    MigratePaths,
+    /// Writes a longer note for an entry in $VISUAL or $EDITOR, kept in the notes directory next
+    /// to the entries file. Saving it blank removes it
+    Note(cli::note::Args),
+    /// Reminds about long running entries and idle work hours with desktop notifications
+    Notify(cli::notify::Args),
+    /// Rewrites the data files in the canonical line format
+    Normalize(cli::normalize::Args),
+    /// Tracks work on the account in pomodoros, starting and stopping entries tagged `pomodoro`
+    /// with a notification at each transition. Ctrl-C stops the current entry
+    Pomo(cli::pomo::Args),
+    /// Prints a short line about the running entries for shell prompts and status bars, or
+    /// nothing if nothing is running
+    Prompt(cli::prompt::Args),
+    /// Prints the accounts starting with the prefix, one per line, for shell completion
+    /// functions to call, e.g. `tt __complete accounts Cli`
+    #[structopt(name = "__complete", setting = structopt::clap::AppSettings::Hidden)]
+    Complete(cli::complete::Args),
+    /// Uploads new entries to other time tracking services
+    #[cfg(feature = "http")]
+    Push(cli::push::PushTarget),
+    /// Gives the time between `--from` and `--to` to another account, splitting the entries
+    /// covering part of it, e.g. after tracking two hours on the wrong account
+    Reassign(cli::reassign::Args),
+    /// Renames an account and its sub-accounts in every entry and running entry
+    RenameAccount(cli::rename_account::Args),
+    /// Sums the time tracked for each account, including running entries
+    Report(cli::report::Args),
+    /// Replaces the data and config files by a snapshot taken by `backup`, after taking a
+    /// snapshot of them as they are
+    Restore(cli::restore::Args),
+    /// Lists the running entries
+    Running(cli::running::Args),
+    /// Records the recurring entries of the schedule in the config for the days from `--since` to
+    /// `--until`, today by default, skipping holidays, the ones already recorded and the ones
+    /// which would overlap other entries
+    Schedule(cli::schedule::Args),
+    /// Prints the JSON Schema of JSON output, or lists the schemas without a name
+    Schema(cli::schema::Args),
+    /// Prints everything about an entry: its ID, times, duration, accounts, tags, description,
+    /// the sources it was imported from and its note
+    Show(cli::show::Args),
+    Start(cli::start::Args),
+    /// Orders the entries file chronologically
+    Sort(cli::sort::Args),
+    /// Splits an entry, the last one or one by its ID, into two entries
+    Split(cli::split::Args),
+    /// Runs a read-only SQL query against the `entries` and `tags` tables of the entries loaded
+    /// into an in-memory database, and prints the rows as a table or CSV
+    #[cfg(feature = "sqlite")]
+    Sql(cli::sql::Args),
+    /// Shows statistics about the tracked time, by default of every entry
+    Stats(cli::stats::Args),
+    /// Shows the running entries, the time tracked today and exceeded budgets
+    Status(cli::status::Args),
+    Stop(cli::stop::Args),
+    /// Serves an HTTP API for the entries and running entries, and a dashboard of them at `/`
+    #[cfg(feature = "server")]
+    Serve(cli::serve::Args),
+    /// Commits changes to the data files and synchronizes them with the git remote
+    Sync(cli::sync::Args),
+    /// Adds a tag to or removes it from all entries matching the account and time range
+    #[structopt(name = "tag")]
+    Tag(cli::tag::TagCommand),
+    /// Shows today's entries, the running entries, the total of each account and the total
+    Today(cli::today::Args),
+    /// Runs a command while tracking time for the account, e.g. `tt track Client -- make`,
+    /// stopping when the command exits and exiting with its exit code
+    Track(cli::track::Args),
+    /// Moves the start and stop of an entry by relative amounts
+    Trim(cli::trim::Args),
+}
+
+/// Checks that a profile name can be used as a directory name.
+fn parse_profile(s: &str) -> Result<String, String> {
+    if s.is_empty() || s == "." || s == ".." || s.contains(['/', '\\']) {
+        return Err(format!(r#"invalid profile name "{}""#, s));
+    }
+    Ok(s.to_string())
+}
+
+/// Parses `--now` and sets the current time to it right away, so that the times given to the
+/// command, which are parsed after the global options, are relative to it.
+fn parse_now(s: &str) -> Result<DateTime<Utc>, String> {
+    let now = cli::parse_datetime(s)?;
+    timetracker::set_now(now);
+    Ok(now)
+}
+
+/// Opens the entries and running files, or only the entries file if configured.
+fn open_storage(entries_path: PathBuf, running_path: PathBuf, config: &Config) -> Storage {
+    if config.storage.single_file {
+        Storage::single_file(entries_path)
+    } else {
+        Storage::new(entries_path, running_path)
+    }
+}
+
+/// The key for encrypting the data files, from `TIMETRACKER_IDENTITY` or else the identity file.
+#[cfg(feature = "encryption")]
+fn encryption_key(identity_file: Option<&Path>) -> Result<Option<Key>, EncryptionError> {
+    match std::env::var("TIMETRACKER_IDENTITY") {
+        Ok(secret) => secret.parse().map(Some),
+        Err(_) => identity_file.map(Key::load).transpose(),
+    }
+}
+
+/// Names the commands which rewrite the data files, for the snapshots taken before them.
+fn rewrites_data_files(cmd: &Command) -> Option<&'static str> {
+    match cmd {
+        Command::Clean(_) => Some("clean"),
+        Command::Compact => Some("compact"),
+        Command::Diff(args) if args.merge => Some("diff"),
+        Command::Import(_) => Some("import"),
+        Command::Merge(_) => Some("merge"),
+        Command::Migrate(args) if !args.check => Some("migrate"),
+        Command::Normalize(args) if !args.check => Some("normalize"),
+        Command::Reassign(_) => Some("reassign"),
+        Command::RenameAccount(_) => Some("rename-account"),
+        Command::Sort(args) if !args.check => Some("sort"),
+        Command::Tag(_) => Some("tag"),
+        _ => None,
+    }
+}
+
+/// The global options taking a value, which is not a command.
+const OPTIONS_WITH_VALUES: [&str; 5] =
+    ["--file", "--running-file", "--config", "--profile", "--now"];
+
+/// Parses the arguments, running the default command from the config without one, and starting
+/// the account given in place of a command if the config says so.
+fn parse_args() -> Opt {
+    let args: Vec<OsString> = env::args_os().collect();
+    parse_args_from(args, load_config).unwrap_or_else(|err| err.exit())
+}
+
+/// The config file the global options point to, or the default config if it cannot be read.
+fn load_config(opt: &Opt) -> Config {
+    let path = opt
+        .config
+        .clone()
+        .unwrap_or_else(|| Paths::detect(opt.profile.as_deref()).config);
+    Config::load(&path).unwrap_or_default()
+}
+
+/// Parses the arguments like `parse_args`, with the config for the global options given.
+fn parse_args_from(
+    args: Vec<OsString>,
+    load_config: impl FnOnce(&Opt) -> Config,
+) -> Result<Opt, structopt::clap::Error> {
+    let err = match Opt::from_iter_safe(&args) {
+        Ok(opt) => return Ok(opt),
+        Err(err) => err,
+    };
+    // The first argument that is neither an option nor the value of one
+    let mut position = None;
+    let mut index = 1;
+    while index < args.len() {
+        let arg = args[index].to_string_lossy();
+        if OPTIONS_WITH_VALUES.contains(&arg.as_ref()) {
+            index += 2;
+            continue;
+        }
+        if !arg.starts_with('-') {
+            position = Some(index);
+            break;
+        }
+        index += 1;
+    }
+
+    // The global options alone, with any command, tell where the config is
+    let globals = args[..position.unwrap_or(args.len())]
+        .iter()
+        .cloned()
+        .chain(Some(OsString::from("running")));
+    let config = match Opt::from_iter_safe(globals) {
+        Ok(opt) => load_config(&opt),
+        Err(_) => return Err(err),
+    };
+    let mut args = args;
+    match (position, &config.default_command) {
+        (None, Some(command)) => args.extend(command.split_whitespace().map(OsString::from)),
+        (Some(position), _) if config.start_unknown_commands => {
+            args.insert(position, OsString::from("start"))
+        }
+        _ => return Err(err),
+    }
+    Opt::from_iter_safe(args)
 }
 
 fn main() {
-    env_logger::init();
+    let opt = parse_args();
 
-    let opt = Opt::from_args();
+    let level = match (opt.quiet, opt.verbose) {
+        (true, _) => log::LevelFilter::Error,
+        (false, 0) => log::LevelFilter::Warn,
+        (false, 1) => log::LevelFilter::Info,
+        (false, 2) => log::LevelFilter::Debug,
+        (false, _) => log::LevelFilter::Trace,
+    };
+    env_logger::Builder::new()
+        .filter_level(level)
+        .parse_default_env()
+        .init();
     log::debug!("{:?}", opt);
+    if let Some(now) = opt.now {
+        log::info!("using {} as the current time", now);
+    }
 
-    match opt.cmd {
-        Command::Export { output } => {
-            // Error if there's already a file located at the output path
-            if output.exists() {
-                panic!("there is already a file at the output path");
-            }
-
-            // Read every entry and format as a timeclock entry
-            let file = OpenOptions::new()
-                .read(true)
-                .open(&opt.file)
-                .expect("could not open file");
-            let timeclock = BufReader::new(file)
-                .lines()
-                .map(|line| line.unwrap())
-                .map(|line| Entry::from_str(&line).unwrap())
-                .map(|entry| entry.format_as_timeclock())
-                .collect::<Vec<String>>()
-                .join("\n");
-
-            // Write the timeclock formatted entries to the output file
-            fs::write(output, timeclock).expect("could not write to output file");
+    // Errors are reported here alone, with the exit code they carry
+    if let Err(err) = run(opt) {
+        if let Some(message) = err.message() {
+            eprintln!("error: {}", message);
         }
+        process::exit(err.code());
+    }
+}
 
-        Command::Running => {
-            // Open the file with running entries
-            let running_file = OpenOptions::new()
-                .read(true)
-                .open(&opt.running_file)
-                .expect("could not open running file");
-
-            // Print each running entry
-            BufReader::new(running_file)
-                .lines()
-                .map(|line| line.unwrap())
-                .map(|line| RunningEntry::from_str(&line).unwrap())
-                .for_each(|entry| println!("{}", entry));
+/// Sets up the files and the config for the command and runs it.
+fn run(opt: Opt) -> cli::Result<()> {
+    // Files given explicitly take precedence over the default locations
+    let paths = Paths::detect(opt.profile.as_deref());
+    let mut expanded = Vec::new();
+    for pattern in &opt.file {
+        let paths = paths::expand_pattern(pattern)
+            .with_context(|| format!("could not expand {}", pattern.display()))?;
+        if paths.is_empty() {
+            return Err(CliError::new(format!(
+                "no files match {}",
+                pattern.display()
+            )));
         }
+        expanded.extend(paths);
+    }
+    let mut entries_paths = expanded.into_iter();
+    let entries_path = entries_paths.next().unwrap_or(paths.entries);
+    let additional_entries_paths: Vec<PathBuf> = entries_paths.collect();
+    let running_path = opt.running_file.clone().unwrap_or(paths.running);
+    let config_path = opt.config.clone().unwrap_or(paths.config);
+    let favorites_path = config_path.with_file_name("favorites");
+    let accounts_path = config_path.with_file_name("accounts.toml");
 
-        Command::Start { account } => {
-            // Create the new running entry
-            let running_entry = RunningEntry {
-                start: Utc::now(),
-                account: account.clone(),
-                description: None,
-            };
-
-            // Error if there is already a running entry for the account
-            if opt.running_file.exists() {
-                let running_file = OpenOptions::new()
-                    .read(true)
-                    .open(&opt.running_file)
-                    .expect("could not open running file");
-                if BufReader::new(running_file)
-                    .lines()
-                    .map(|line| line.unwrap())
-                    .map(|line| RunningEntry::from_str(&line).unwrap())
-                    .any(|entry| entry.account == account)
-                {
-                    panic!(
-                        r#"there is already a running entry for the account "{}""#,
-                        account
-                    );
-                }
-            }
+    // The running file as given, which identifies the files to the daemon
+    let daemon_running_path = running_path.clone();
+
+    // Setting up must work before there is a config, and with a broken one when replacing it
+    if let Command::Init(args) = &opt.cmd {
+        return cli::init::run(
+            args,
+            opt.dry_run,
+            &config_path,
+            &entries_path,
+            &running_path,
+        );
+    }
+    // Finding the problems of a broken config must not fail on them
+    if let Command::LintConfig = &opt.cmd {
+        return cli::lint_config::run(&config_path);
+    }
+    // Prompts are shown all the time, so never fail on a missing or broken file
+    if let Command::Prompt(args) = &opt.cmd {
+        return cli::prompt::run(
+            args,
+            &config_path,
+            entries_path,
+            running_path,
+            &daemon_running_path,
+        );
+    }
+
+    // Completing happens while typing, so never fail or print anything but the candidates
+    if let Command::Complete(args) = &opt.cmd {
+        return cli::complete::run(args, &config_path, entries_path, running_path);
+    }
 
-            // Open the file for running entries and append the new entry at the end
-            let mut running_file = OpenOptions::new()
-                .create(true)
-                .append(true)
-                .open(&opt.running_file)
-                .expect("could not open running file");
-            writeln!(running_file, "{}", running_entry).expect("could not write to running file");
+    if !config_path.exists() && !entries_path.exists() && io::stderr().is_terminal() {
+        eprintln!("tt is not set up yet, run `tt init` to create the config and data files");
+    }
+    let config = Config::load(&config_path).map_err(|err| {
+        CliError::new(format!(
+            "{}: {}\nrun `tt lint-config` to list every problem with the config file",
+            config_path.display(),
+            err
+        ))
+    })?;
+    log::debug!("{:?}", config);
+
+    let accounts = Accounts::load(&accounts_path)
+        .with_context(|| format!("could not read {}", accounts_path.display()))?;
+    let style = output::Style::detect(opt.no_color).with_account_colors(accounts.colors());
+    let locale = Locale::detect(config.locale.as_deref())
+        .map_err(|err| CliError::new(format!("{} in config file", err)))?;
+    let write_mode = if opt.dry_run {
+        WriteMode::DryRun
+    } else if config.storage.read_only {
+        WriteMode::ReadOnly
+    } else {
+        WriteMode::Write
+    };
+    let storage = open_storage(entries_path, running_path, &config)
+        .keep_sorted(config.storage.keep_sorted)
+        .quarantine_partial_lines(config.storage.quarantine_partial_lines)
+        .strict(opt.strict)
+        .zero_duration(config.storage.zero_duration)
+        .rounding(config.storage.round_start, config.storage.round_stop)
+        .index(config.storage.index)
+        .additional_entries_files(additional_entries_paths)
+        .limits(
+            config
+                .storage
+                .max_line_length
+                .unwrap_or(storage::DEFAULT_MAX_LINE_LENGTH),
+            config
+                .storage
+                .max_file_size_mb
+                .map_or(storage::DEFAULT_MAX_FILE_SIZE, |size| {
+                    size.saturating_mul(1024 * 1024)
+                }),
+        )
+        .duplicate_window(cli::duplicate_window(&config))
+        .write_mode(write_mode);
+    #[cfg(feature = "encryption")]
+    let storage = match encryption_key(config.storage.identity_file.as_deref())
+        .context("could not read the encryption key")?
+    {
+        Some(key) => storage.key(key),
+        None => storage,
+    };
+    #[cfg(not(feature = "encryption"))]
+    if config.storage.identity_file.is_some() || std::env::var_os("TIMETRACKER_IDENTITY").is_some()
+    {
+        return Err(CliError::new(
+            "encrypting the data files requires the `encryption` feature",
+        ));
+    }
+    let storage = if config.sync.oplog {
+        // Combining it with the settings is refused when loading the config
+        if std::env::var_os("TIMETRACKER_IDENTITY").is_some() {
+            return Err(CliError::new(
+                "the operation log cannot be used with encryption",
+            ));
+        }
+        let machine = config
+            .sync
+            .machine
+            .clone()
+            .or_else(oplog::hostname)
+            .unwrap_or_else(|| "local".to_string());
+        let dir = storage.entries_path().with_file_name("ops");
+        storage.oplog(OpLog::new(dir, machine))
+    } else {
+        storage
+    };
+    let audit_log = AuditLog::new(
+        storage.entries_path().with_file_name("audit"),
+        std::env::args().skip(1).collect(),
+    );
+    let storage = if config.storage.audit {
+        storage.audit(audit_log.clone())
+    } else {
+        storage
+    };
+    // The doctor still runs to point out the files
+    if config.storage.require_private_files && !matches!(opt.cmd, Command::Doctor(_)) {
+        let open = cli::open_files(&config_path, &storage)?;
+        for (path, mode) in &open {
+            eprintln!(
+                "error: others can access {} (permissions {:o})",
+                path.display(),
+                mode
+            );
+        }
+        if !open.is_empty() {
+            return Err(CliError::new(
+                "run `chmod 600` on the files, or set `require_private_files = false` under \
+                 [storage]",
+            ));
         }
+    }
 
-        Command::Stop { account } => {
-            let running_file = OpenOptions::new()
-                .read(true)
-                .open(&opt.running_file)
-                .expect("could not open running file");
-
-            let mut running_entries: Vec<RunningEntry> = BufReader::new(running_file)
-                .lines()
-                .map(|line| line.unwrap())
-                .map(|line| RunningEntry::from_str(&line).unwrap())
-                .collect();
-
-            // Error immediately if there are no running entries
-            if running_entries.is_empty() {
-                panic!("no running entries");
-            }
+    let backup_dir = config
+        .backup
+        .dir
+        .clone()
+        .unwrap_or_else(|| storage.entries_path().with_file_name("backups"));
+    let backup_files = cli::backup_files(&storage, &config_path, &accounts_path, &favorites_path);
+    let take_snapshot = |backups: &Backups, reason: Option<&str>| -> cli::Result<_> {
+        let snapshot = backups
+            .create(&backup_files, timetracker::now(), reason)
+            .context("could not back up the files")?;
+        if let Some(keep) = config.backup.keep {
+            backups
+                .rotate(keep)
+                .context("could not remove old backups")?;
+        }
+        Ok(snapshot)
+    };
+    if config.backup.before_changes && write_mode == WriteMode::Write {
+        if let Some(reason) = rewrites_data_files(&opt.cmd) {
+            let snapshot = take_snapshot(&Backups::new(backup_dir.clone()), Some(reason))?;
+            log::info!("backed up the files to {}", snapshot.path.display());
+        }
+    }
+
+    // Forgotten entries are stopped by whatever runs next, when no daemon did it on time
+    if write_mode == WriteMode::Write {
+        cli::auto_stop(&storage, &config);
+    }
+
+    let ctx = cli::Context {
+        dry_run: opt.dry_run,
+        profile: opt.profile.clone(),
+        config_path,
+        favorites_path,
+        accounts_path,
+        daemon_running_path,
+        config,
+        accounts,
+        style,
+        locale,
+        write_mode,
+        storage,
+        audit_log,
+        backup_dir,
+    };
+    match &opt.cmd {
+        Command::Add(args) => cli::add::run(ctx, args),
+        Command::Apply(args) => cli::apply::run(ctx, args),
+        Command::Annotate(args) => cli::annotate::run(ctx, args),
+        Command::Accounts(args) => cli::accounts::run(ctx, args),
+        #[cfg(feature = "activity")]
+        Command::Audit(args) => cli::audit::run(ctx, args),
+        Command::Backup(args) => cli::backup::run(ctx, args),
+        Command::Balance(args) => cli::balance::run(ctx, args),
+        Command::Burn(args) => cli::burn::run(ctx, args),
+        Command::Check(args) => cli::check::run(ctx, args),
+        Command::Clean(args) => cli::clean::run(ctx, args),
+        Command::Compact => cli::compact::run(ctx),
+        #[cfg(feature = "compact")]
+        Command::Convert(args) => cli::convert::run(ctx, args),
+        Command::Diff(args) => cli::diff::run(ctx, args),
+        #[cfg(unix)]
+        Command::Daemon => cli::daemon::run(ctx),
+        Command::Doctor(args) => cli::doctor::run(ctx, args),
+        Command::Digest(args) => cli::digest::run(ctx, args),
+        Command::Explain(args) => cli::explain::run(ctx, args),
+        Command::Export(args) => cli::export::run(ctx, args),
+        Command::Fav(args) => cli::fav::run(ctx, args),
+        Command::Free(args) => cli::free::run(ctx, args),
+        Command::Goals => cli::goals::run(ctx),
+        Command::Grep(args) => cli::grep::run(ctx, args),
+        Command::History(args) => cli::history::run(ctx, args),
+        Command::Heatmap(args) => cli::heatmap::run(ctx, args),
+        Command::Import(args) => cli::import::run(ctx, args),
+        Command::Invoice(command) => cli::invoice::run(ctx, command),
+        Command::Log(args) => cli::log::run(ctx, args),
+        Command::Merge(args) => cli::merge::run(ctx, args),
+        Command::MergeFiles(args) => cli::merge_files::run(ctx, args),
+        Command::Migrate(args) => cli::migrate::run(ctx, args),
+        Command::MigratePaths => cli::migrate_paths::run(ctx),
+        Command::Note(args) => cli::note::run(ctx, args),
+        Command::Notify(args) => cli::notify::run(ctx, args),
+        Command::Normalize(args) => cli::normalize::run(ctx, args),
+        Command::Pomo(args) => cli::pomo::run(ctx, args),
+        #[cfg(feature = "http")]
+        Command::Push(args) => cli::push::run(ctx, args),
+        Command::Reassign(args) => cli::reassign::run(ctx, args),
+        Command::RenameAccount(args) => cli::rename_account::run(ctx, args),
+        Command::Report(args) => cli::report::run(ctx, args),
+        Command::Restore(args) => cli::restore::run(ctx, args),
+        Command::Running(args) => cli::running::run(ctx, args),
+        Command::Schedule(args) => cli::schedule::run(ctx, args),
+        Command::Schema(args) => cli::schema::run(ctx, args),
+        Command::Show(args) => cli::show::run(ctx, args),
+        Command::Start(args) => cli::start::run(ctx, args),
+        Command::Sort(args) => cli::sort::run(ctx, args),
+        Command::Split(args) => cli::split::run(ctx, args),
+        #[cfg(feature = "sqlite")]
+        Command::Sql(args) => cli::sql::run(ctx, args),
+        Command::Stats(args) => cli::stats::run(ctx, args),
+        Command::Status(args) => cli::status::run(ctx, args),
+        Command::Stop(args) => cli::stop::run(ctx, args),
+        #[cfg(feature = "server")]
+        Command::Serve(args) => cli::serve::run(ctx, args),
+        Command::Sync(args) => cli::sync::run(ctx, args),
+        Command::Tag(command) => cli::tag::run(ctx, command),
+        Command::Today(args) => cli::today::run(ctx, args),
+        Command::Track(args) => cli::track::run(ctx, args),
+        Command::Trim(args) => cli::trim::run(ctx, args),
+        Command::Prompt(_) => unreachable!("prompts are shown before loading the config"),
+        Command::Complete(_) => unreachable!("completing runs before loading the config"),
+        Command::Init(_) => unreachable!("init runs before loading the config"),
+        Command::LintConfig => unreachable!("lint-config runs before loading the config"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn parse(args: &[&str], config: Config) -> Result<Opt, structopt::clap::Error> {
+        let args = Some("tt").iter().chain(args).map(OsString::from).collect();
+        parse_args_from(args, |_| config)
+    }
+
+    #[test]
+    fn run_the_default_command_without_one() {
+        let config = Config {
+            default_command: Some("status --short".to_string()),
+            ..Config::default()
+        };
+        let opt = parse(&["--file", "entries"], config).unwrap();
+        assert_eq!(opt.file, [PathBuf::from("entries")]);
+        assert!(matches!(opt.cmd, Command::Status(args) if args.short));
 
-            let position = match account {
-                Some(account) => running_entries
-                    .iter()
-                    .position(|entry| entry.account == account)
-                    .unwrap_or_else(|| {
-                        panic!(
-                            r#"no running entries for the account "{}" were found"#,
-                            account
-                        )
-                    }),
-                None => {
-                    if running_entries.len() != 1 {
-                        panic!(
-                            "account must be specified when there is more than one running entry"
-                        );
-                    }
-                    0
-                }
-            };
-
-            // Extract the running entry and remove it from the collection
-            let running_entry = running_entries.remove(position);
-
-            // Create a new complete entry
-            let entry = Entry {
-                start: running_entry.start,
-                stop: Utc::now(),
-                account: running_entry.account,
-                description: running_entry.description,
-            };
-
-            // Write the new entry
-            let mut entry_file = OpenOptions::new()
-                .create(true)
-                .append(true)
-                .open(opt.file)
-                .expect("could not open entries file");
-            writeln!(entry_file, "{}", entry).expect("could not write to entries file");
-
-            // Write the remaining running entries to the running file
-            fs::write(
-                &opt.running_file,
-                running_entries
-                    .iter()
-                    .map(|entry| format!("{}", entry))
-                    .collect::<Vec<String>>()
-                    .join("\n"),
-            )
-            .expect("could not write to running file");
+        let config = Config {
+            default_command: Some("status".to_string()),
+            ..Config::default()
+        };
+        assert!(matches!(
+            parse(&["running"], config).unwrap().cmd,
+            Command::Running(_)
+        ));
+
+        assert!(parse(&[], Config::default()).is_err());
+    }
+
+    #[test]
+    fn start_unknown_commands_if_configured() {
+        let config = Config {
+            start_unknown_commands: true,
+            ..Config::default()
+        };
+        let opt = parse(&["--file", "status", "dev", "--tag", "x"], config).unwrap();
+        assert_eq!(opt.file, [PathBuf::from("status")]);
+        match opt.cmd {
+            Command::Start(args) => {
+                assert_eq!(args.account.as_deref(), Some("dev"));
+                assert_eq!(args.tags, ["x"]);
+            }
+            cmd => panic!("{:?}", cmd),
         }
+
+        let config = Config {
+            start_unknown_commands: true,
+            ..Config::default()
+        };
+        assert!(matches!(
+            parse(&["status"], config).unwrap().cmd,
+            Command::Status(_)
+        ));
+
+        assert!(parse(&["dev"], Config::default()).is_err());
+    }
+
+    #[test]
+    fn parse_now_sets_the_current_time() {
+        assert!(parse_now("soon").is_err());
+
+        let now = Utc.ymd(2021, 7, 1).and_hms(9, 0, 0);
+        assert_eq!(parse_now("2021-07-01T09:00:00Z"), Ok(now));
+        assert_eq!(timetracker::now(), now);
+        assert_eq!(
+            cli::parse_datetime("15m ago"),
+            Ok(Utc.ymd(2021, 7, 1).and_hms(8, 45, 0))
+        );
     }
 }
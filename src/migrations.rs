@@ -0,0 +1,236 @@
+//! Versioning of the entries file format.
+//!
+//! A file in the current format starts with a header line naming its version, e.g.
+//! `# timetracker entries format 1`. Files without one are version 0, from before the header was
+//! introduced. Older files are upgraded by applying each migration after their version in order,
+//! so every file ends up the same regardless of the version it started at.
+
+use crate::Entry;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// The version of the format written by this version of tt.
+pub const CURRENT_VERSION: u32 = 3;
+
+const HEADER_PREFIX: &str = "# timetracker entries format ";
+
+/// An upgrade of the lines following the header to the next version of the format.
+struct Migration {
+    /// The version the lines are upgraded to.
+    to: u32,
+    description: &'static str,
+    apply: fn(Vec<String>) -> Vec<String>,
+}
+
+/// Every migration, ordered by the version they upgrade to.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        to: 1,
+        description: "add the version header",
+        // Only the header is new, which is added for every migration
+        apply: |lines| lines,
+    },
+    Migration {
+        to: 2,
+        description: "escape accounts ending with a word starting with @",
+        apply: escape_category_markers,
+    },
+    Migration {
+        to: 3,
+        description: "escape accounts ending with a word like key=value",
+        apply: escape_meta_words,
+    },
+];
+
+/// Keeps accounts ending with a word like `@home` from being read as having that category, which
+/// entries could not have before version 2.
+fn escape_category_markers(lines: Vec<String>) -> Vec<String> {
+    lines
+        .into_iter()
+        .map(|line| match Entry::from_str(&line) {
+            Ok(mut entry) if entry.category.is_some() => {
+                if let Some(category) = entry.category.take() {
+                    entry.account = format!("{} @{}", entry.account, category).into();
+                }
+                entry.to_string()
+            }
+            _ => line,
+        })
+        .collect()
+}
+
+/// Keeps accounts ending with words like `ticket=PROJ-12` from being read as having that
+/// metadata, which entries could not have before version 3. As the metadata was written last,
+/// such a line has no tags and the words before them were part of the account too.
+fn escape_meta_words(lines: Vec<String>) -> Vec<String> {
+    lines
+        .into_iter()
+        .map(|line| {
+            let entry = match Entry::parse(&line) {
+                Ok(entry) if entry.meta().next().is_some() => entry,
+                _ => return line,
+            };
+            let mut account = entry.account().to_string();
+            if entry.billable() {
+                account.push_str(" $");
+            }
+            if let Some(category) = entry.category() {
+                account.push_str(" @");
+                account.push_str(category);
+            }
+            for tag in entry.tags() {
+                account.push_str(" #");
+                account.push_str(tag);
+            }
+            for (key, value) in entry.meta() {
+                account.push_str(&format!(" {}={}", key, value));
+            }
+            let mut migrated = entry.to_entry();
+            migrated.account = account.into();
+            migrated.billable = false;
+            migrated.category = None;
+            migrated.tags.clear();
+            migrated.meta.clear();
+            migrated.to_string()
+        })
+        .collect()
+}
+
+/// The header line for the version.
+pub fn header(version: u32) -> String {
+    format!("{}{}", HEADER_PREFIX, version)
+}
+
+/// Reads the version from a header line, or `None` if the line is not a header.
+pub fn parse_header(line: &str) -> Option<u32> {
+    line.strip_prefix(HEADER_PREFIX)?.trim().parse().ok()
+}
+
+/// The version of a file from its first line, 0 if it has no header.
+pub fn version(lines: &[String]) -> u32 {
+    lines
+        .first()
+        .and_then(|line| parse_header(line))
+        .unwrap_or(0)
+}
+
+/// Describes the migrations that upgrade a file from the version, in the order they are applied.
+pub fn pending(version: u32) -> Vec<&'static str> {
+    MIGRATIONS
+        .iter()
+        .filter(|migration| migration.to > version)
+        .map(|migration| migration.description)
+        .collect()
+}
+
+/// Upgrades the lines of a file to the current version, returning the version it was at and the
+/// upgraded lines. Lines already at the current version are returned as they are.
+pub fn migrate(mut lines: Vec<String>) -> Result<(u32, Vec<String>), UnsupportedVersion> {
+    let version = version(&lines);
+    if version > CURRENT_VERSION {
+        return Err(UnsupportedVersion(version));
+    }
+    if version == CURRENT_VERSION {
+        return Ok((version, lines));
+    }
+
+    if lines
+        .first()
+        .is_some_and(|line| parse_header(line).is_some())
+    {
+        lines.remove(0);
+    }
+    for migration in MIGRATIONS.iter().filter(|migration| migration.to > version) {
+        log::info!(
+            "migrating to version {}: {}",
+            migration.to,
+            migration.description
+        );
+        lines = (migration.apply)(lines);
+    }
+    lines.insert(0, header(CURRENT_VERSION));
+    Ok((version, lines))
+}
+
+/// The file was written by a newer version of tt, with a format this version does not know.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnsupportedVersion(pub u32);
+
+impl fmt::Display for UnsupportedVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "the file is in version {} of the format, but only versions up to {} are supported",
+            self.0, CURRENT_VERSION
+        )
+    }
+}
+
+impl Error for UnsupportedVersion {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(s: &str) -> Vec<String> {
+        s.lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn parse_headers() {
+        assert_eq!(parse_header(&header(3)), Some(3));
+        assert_eq!(parse_header("# timetracker entries format x"), None);
+        assert_eq!(
+            parse_header("2021-07-03T10:00:00Z 2021-07-03T11:00:00Z A"),
+            None
+        );
+        assert_eq!(version(&[]), 0);
+    }
+
+    #[test]
+    fn migrate_legacy_files() {
+        let legacy = lines(
+            "2021-07-03T10:00:00Z 2021-07-03T11:00:00Z A\n\
+             2021-07-03T11:00:00Z 2021-07-03T12:00:00Z Home @office #x\n\
+             2021-07-03T12:00:00Z 2021-07-03T13:00:00Z Pay $ rate=2 ; Review",
+        );
+        assert_eq!(
+            pending(0),
+            vec![
+                "add the version header",
+                "escape accounts ending with a word starting with @",
+                "escape accounts ending with a word like key=value",
+            ]
+        );
+
+        let (version, migrated) = migrate(legacy).unwrap();
+        assert_eq!(version, 0);
+        assert_eq!(
+            migrated,
+            lines(
+                "# timetracker entries format 3\n\
+                 2021-07-03T10:00:00Z 2021-07-03T11:00:00Z A\n\
+                 2021-07-03T11:00:00Z 2021-07-03T12:00:00Z Home \\@office #x\n\
+                 2021-07-03T12:00:00Z 2021-07-03T13:00:00Z Pay $ rate\\=2 ; Review"
+            )
+        );
+        assert_eq!(
+            Entry::from_str(&migrated[2]).unwrap().account.as_ref(),
+            "Home @office"
+        );
+        let pay = Entry::from_str(&migrated[3]).unwrap();
+        assert_eq!(pay.account.as_ref(), "Pay $ rate=2");
+        assert!(pay.meta.is_empty() && !pay.billable);
+        assert!(pending(CURRENT_VERSION).is_empty());
+        assert_eq!(migrate(migrated.clone()).unwrap(), (3, migrated));
+    }
+
+    #[test]
+    fn reject_newer_versions() {
+        assert_eq!(
+            migrate(vec![header(CURRENT_VERSION + 1)]),
+            Err(UnsupportedVersion(CURRENT_VERSION + 1))
+        );
+    }
+}
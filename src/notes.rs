@@ -0,0 +1,101 @@
+//! Longer notes attached to entries, for what does not fit in a description.
+//!
+//! Each note is a text file in the notes directory next to the entries file, named by the ID of
+//! its entry, see `Entry::id`. A note stays with its entry as long as its start and account do not
+//! change.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The notes directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notes {
+    dir: PathBuf,
+}
+
+impl Notes {
+    pub fn new(dir: PathBuf) -> Self {
+        Notes { dir }
+    }
+
+    /// The file of the note of the entry with the ID.
+    pub fn path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.txt", id))
+    }
+
+    /// The note of the entry with the ID, if it has one.
+    pub fn get(&self, id: &str) -> io::Result<Option<String>> {
+        match fs::read_to_string(self.path(id)) {
+            Ok(note) => Ok(Some(note)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Sets the note of the entry with the ID, or removes it if the note is blank.
+    pub fn set(&self, id: &str, note: &str) -> io::Result<()> {
+        let path = self.path(id);
+        if note.trim().is_empty() {
+            return match fs::remove_file(&path) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+                _ => Ok(()),
+            };
+        }
+        fs::create_dir_all(&self.dir)?;
+        let note = note.trim_end();
+        fs::write(&path, format!("{}\n", note))
+    }
+
+    /// The IDs of the entries with notes.
+    pub fn ids(&self) -> io::Result<BTreeSet<String>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(BTreeSet::new()),
+            Err(err) => return Err(err),
+        };
+        let mut ids = BTreeSet::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_some_and(|extension| extension == "txt") {
+                ids.extend(file_stem(&path));
+            }
+        }
+        Ok(ids)
+    }
+}
+
+fn file_stem(path: &Path) -> Option<String> {
+    Some(path.file_stem()?.to_str()?.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_and_get_notes() {
+        let dir = std::env::temp_dir().join(format!("tt_notes_test_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let notes = Notes::new(dir.clone());
+
+        assert_eq!(notes.get("0123456789abcdef").unwrap(), None);
+        assert!(notes.ids().unwrap().is_empty());
+        notes
+            .set("0123456789abcdef", "Tried a few things\n\n")
+            .unwrap();
+        assert_eq!(
+            notes.get("0123456789abcdef").unwrap().as_deref(),
+            Some("Tried a few things\n")
+        );
+        assert_eq!(
+            notes.ids().unwrap().into_iter().collect::<Vec<_>>(),
+            vec!["0123456789abcdef"]
+        );
+        notes.set("0123456789abcdef", " \n").unwrap();
+        notes.set("fedcba9876543210", "").unwrap();
+        assert!(notes.ids().unwrap().is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,311 @@
+//! An append-only log of the changes to the lines of the data files, for syncing them through
+//! file sync services like Dropbox instead of git.
+//!
+//! Rather than rewriting the files, every change is appended as operations to the log of the
+//! machine making it, a file of its own in the log directory, so two machines never change the
+//! same file. Each line of a log is an operation with a unique ID adding or removing a line of
+//! the entries or running file, e.g.
+//!
+//! ```json
+//! {"id":"laptop-1625313600000000000-4242-0","time":"2021-07-03T12:00:00Z","file":"running","op":"remove","line":"2021-07-03T10:00:00Z Client"}
+//! ```
+//!
+//! The files are read with the operations of every log applied on top, in the order they were
+//! made, so changes made on two machines at once are merged by taking both. `tt compact` folds
+//! the operations into the files and removes them from the logs.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The extension of the log files in the log directory.
+const EXTENSION: &str = "ops";
+
+/// Counts the operations made by this process, so their IDs are unique even when made within
+/// the resolution of the clock.
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Op {
+    Add,
+    Remove,
+}
+
+/// A line added to or removed from a data file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Operation {
+    pub id: String,
+    pub time: DateTime<Utc>,
+    /// `entries` or `running`.
+    pub file: String,
+    pub op: Op,
+    pub line: String,
+}
+
+/// The logs in a directory, see `Storage::oplog`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpLog {
+    dir: PathBuf,
+    /// The name of this machine, which its log is named after.
+    machine: String,
+}
+
+impl OpLog {
+    pub fn new(dir: PathBuf, machine: String) -> Self {
+        OpLog { dir, machine }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Appends the changes of the lines of the file to the log of this machine.
+    pub fn record(
+        &self,
+        time: DateTime<Utc>,
+        file: &str,
+        changes: Vec<(Op, String)>,
+    ) -> io::Result<()> {
+        if changes.is_empty() {
+            return Ok(());
+        }
+        fs::create_dir_all(&self.dir)?;
+        // The clock rather than the time of the change, which may be set with `--now`
+        let clock = Utc::now().timestamp_nanos();
+        let mut lines = String::new();
+        for (op, line) in changes {
+            let operation = Operation {
+                id: format!(
+                    "{}-{}-{}-{}",
+                    self.machine,
+                    clock,
+                    std::process::id(),
+                    COUNTER.fetch_add(1, Ordering::SeqCst)
+                ),
+                time,
+                file: file.to_string(),
+                op,
+                line,
+            };
+            lines += &serde_json::to_string(&operation).expect("operations can be serialized");
+            lines.push('\n');
+        }
+        // A single write, so a partly synced log only ever misses whole changes
+        let mut log = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.dir.join(format!("{}.{}", self.machine, EXTENSION)))?;
+        log.write_all(lines.as_bytes())
+    }
+
+    /// Reads the operations of every log, each once, in the order they were made. Lines that
+    /// cannot be read, e.g. cut off by an interrupted write, are skipped.
+    pub fn operations(&self) -> io::Result<Vec<Operation>> {
+        let mut operations: BTreeMap<String, Operation> = BTreeMap::new();
+        for path in self.logs()? {
+            for line in fs::read_to_string(&path)?.lines() {
+                match serde_json::from_str::<Operation>(line) {
+                    Ok(operation) => {
+                        operations.insert(operation.id.clone(), operation);
+                    }
+                    Err(err) => log::warn!(
+                        "skipping a line of {} that cannot be read: {}",
+                        path.display(),
+                        err
+                    ),
+                }
+            }
+        }
+        let mut operations: Vec<Operation> = operations.into_values().collect();
+        operations.sort_by(|a, b| (a.time, &a.id).cmp(&(b.time, &b.id)));
+        Ok(operations)
+    }
+
+    /// Removes the operations from every log, e.g. once they are folded into the files, keeping
+    /// any made since. Logs left empty are removed.
+    pub fn remove(&self, ids: &BTreeSet<String>) -> io::Result<()> {
+        for path in self.logs()? {
+            let contents = fs::read_to_string(&path)?;
+            let kept: String = contents
+                .lines()
+                .filter(|line| {
+                    serde_json::from_str::<Operation>(line)
+                        .map_or(true, |operation| !ids.contains(&operation.id))
+                })
+                .map(|line| format!("{}\n", line))
+                .collect();
+            if kept.is_empty() {
+                fs::remove_file(&path)?;
+            } else if kept != contents {
+                fs::write(&path, kept)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The log files in the directory, of this machine and every other.
+    fn logs(&self) -> io::Result<Vec<PathBuf>> {
+        let dir = match fs::read_dir(&self.dir) {
+            Ok(dir) => dir,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err),
+        };
+        let mut logs = Vec::new();
+        for entry in dir {
+            let path = entry?.path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == EXTENSION)
+            {
+                logs.push(path);
+            }
+        }
+        logs.sort();
+        Ok(logs)
+    }
+}
+
+/// Applies the operations on the file to its lines. Added lines are appended, and a removed line
+/// takes away its first occurrence, if it is still there.
+pub fn apply(mut lines: Vec<String>, operations: &[Operation], file: &str) -> Vec<String> {
+    for operation in operations.iter().filter(|operation| operation.file == file) {
+        match operation.op {
+            Op::Add => lines.push(operation.line.clone()),
+            Op::Remove => {
+                if let Some(index) = lines.iter().position(|line| *line == operation.line) {
+                    lines.remove(index);
+                }
+            }
+        }
+    }
+    lines
+}
+
+/// The operations changing the lines before into the lines after, ignoring their order.
+pub fn diff(before: &[String], after: &[String]) -> Vec<(Op, String)> {
+    let mut counts: BTreeMap<&str, isize> = BTreeMap::new();
+    for line in before {
+        *counts.entry(line).or_default() -= 1;
+    }
+    for line in after {
+        *counts.entry(line).or_default() += 1;
+    }
+    let mut changes = Vec::new();
+    for (line, count) in &counts {
+        for _ in 0..(-count).max(0) {
+            changes.push((Op::Remove, line.to_string()));
+        }
+    }
+    for (line, count) in &counts {
+        for _ in 0..(*count).max(0) {
+            changes.push((Op::Add, line.to_string()));
+        }
+    }
+    changes
+}
+
+/// The name of this machine, which its log is named after unless `machine` is set under
+/// `[sync]`.
+pub fn hostname() -> Option<String> {
+    #[cfg(unix)]
+    {
+        let mut name = [0u8; 256];
+        // Safe, the call writes at most the length of the buffer
+        if unsafe { libc::gethostname(name.as_mut_ptr() as *mut libc::c_char, name.len()) } == 0 {
+            let end = name
+                .iter()
+                .position(|byte| *byte == 0)
+                .unwrap_or(name.len());
+            return Some(String::from_utf8_lossy(&name[..end]).into_owned())
+                .filter(|name| !name.is_empty());
+        }
+    }
+    std::env::var("COMPUTERNAME").ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn lines(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn diff_and_apply_lines() {
+        let before = lines(&["a", "b", "b", "c"]);
+        let after = lines(&["c", "b", "d"]);
+        let changes = diff(&before, &after);
+        assert_eq!(
+            changes,
+            vec![
+                (Op::Remove, "a".to_string()),
+                (Op::Remove, "b".to_string()),
+                (Op::Add, "d".to_string()),
+            ]
+        );
+        assert!(diff(&before, &lines(&["c", "b", "a", "b"])).is_empty());
+
+        let time = Utc.timestamp(0, 0);
+        let operations: Vec<Operation> = changes
+            .into_iter()
+            .enumerate()
+            .map(|(index, (op, line))| Operation {
+                id: index.to_string(),
+                time,
+                file: "entries".to_string(),
+                op,
+                line,
+            })
+            .collect();
+        assert_eq!(
+            apply(before.clone(), &operations, "entries"),
+            lines(&["b", "c", "d"])
+        );
+        assert_eq!(apply(before.clone(), &operations, "running"), before);
+    }
+
+    #[test]
+    fn merge_logs_of_machines() {
+        let dir = std::env::temp_dir().join(format!("tt_oplog_test_{}", std::process::id()));
+        let laptop = OpLog::new(dir.clone(), "laptop".to_string());
+        let desktop = OpLog::new(dir.clone(), "desktop".to_string());
+        assert_eq!(laptop.operations().unwrap(), Vec::new());
+
+        let time = |seconds| Utc.timestamp(seconds, 0);
+        laptop
+            .record(time(1), "entries", vec![(Op::Add, "a".to_string())])
+            .unwrap();
+        desktop
+            .record(time(2), "entries", vec![(Op::Add, "b".to_string())])
+            .unwrap();
+        laptop
+            .record(time(3), "entries", vec![(Op::Remove, "a".to_string())])
+            .unwrap();
+        // A copy of a log, as left behind by a sync conflict
+        fs::copy(
+            dir.join("desktop.ops"),
+            dir.join("desktop (conflicted copy).ops"),
+        )
+        .unwrap();
+
+        let operations = desktop.operations().unwrap();
+        assert_eq!(operations.len(), 3);
+        assert_eq!(apply(Vec::new(), &operations, "entries"), lines(&["b"]));
+
+        let folded = operations[..2]
+            .iter()
+            .map(|operation| operation.id.clone())
+            .collect();
+        laptop.remove(&folded).unwrap();
+        assert_eq!(laptop.operations().unwrap(), operations[2..].to_vec());
+        assert!(!dir.join("desktop.ops").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,181 @@
+//! Styling of terminal output.
+
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::env;
+use std::io::{self, IsTerminal};
+use timetracker::accounts::Color;
+use timetracker::config::ThresholdLevel;
+
+/// Shades of green of the 256-color palette for the levels of a heatmap, from no time tracked to
+/// the most time tracked.
+const HEAT_COLORS: [u8; 5] = [237, 22, 28, 34, 40];
+
+/// The glyphs for the levels of a heatmap without colors, denser the more time was tracked.
+const HEAT_GLYPHS: [char; 5] = ['·', '░', '▒', '▓', '█'];
+
+/// Applies colors and emphasis to text, unless colors are turned off.
+#[derive(Debug, Clone)]
+pub struct Style {
+    color: bool,
+    /// Colors chosen for accounts, which their sub-accounts are shown in too.
+    account_colors: BTreeMap<String, Color>,
+}
+
+impl Style {
+    /// Uses colors unless turned off with `--no-color` or `NO_COLOR`, or standard output isn't a
+    /// terminal.
+    pub fn detect(no_color: bool) -> Self {
+        let color = !no_color
+            && env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+            && io::stdout().is_terminal();
+        Style {
+            color,
+            account_colors: BTreeMap::new(),
+        }
+    }
+
+    /// Shows the accounts in the given colors instead of the ones picked for them.
+    pub fn with_account_colors(self, account_colors: BTreeMap<String, Color>) -> Self {
+        Style {
+            account_colors,
+            ..self
+        }
+    }
+
+    /// Shows an account in its own color, padded to the width.
+    pub fn account(&self, account: &str, width: usize) -> String {
+        let padded = format!("{:<width$}", account, width = width);
+        let color = Color::for_account(account, &self.account_colors);
+        self.paint(&padded, color.code())
+    }
+
+    pub fn bold(&self, text: &str) -> String {
+        self.paint(text, 1)
+    }
+
+    pub fn dim(&self, text: &str) -> String {
+        self.paint(text, 2)
+    }
+
+    /// Shows the total of a day in green, yellow or red by how it compares to the thresholds.
+    pub fn threshold(&self, text: &str, level: Option<ThresholdLevel>) -> String {
+        match level {
+            Some(ThresholdLevel::Below) => self.paint(text, 32),
+            Some(ThresholdLevel::Warning) => self.paint(text, 33),
+            Some(ThresholdLevel::Exceeded) => self.paint(text, 31),
+            None => text.to_string(),
+        }
+    }
+
+    /// Shows the parts of the text matching the pattern in bold red, like grep.
+    pub fn highlight(&self, text: &str, pattern: &Regex) -> String {
+        if !self.color {
+            return text.to_string();
+        }
+        let mut highlighted = String::new();
+        let mut last = 0;
+        for found in pattern
+            .find_iter(text)
+            .filter(|found| !found.as_str().is_empty())
+        {
+            highlighted += &text[last..found.start()];
+            highlighted += &self.bold(&self.paint(found.as_str(), 31));
+            last = found.end();
+        }
+        highlighted + &text[last..]
+    }
+
+    /// Shows a cell of a heatmap at the level from 0, nothing tracked, to 4, as a square in its
+    /// shade of green, or without colors as a glyph which is denser the higher the level.
+    pub fn heat(&self, level: usize) -> String {
+        let level = level.min(HEAT_COLORS.len() - 1);
+        if self.color {
+            format!("\x1b[38;5;{}m■\x1b[0m", HEAT_COLORS[level])
+        } else {
+            HEAT_GLYPHS[level].to_string()
+        }
+    }
+
+    fn paint(&self, text: &str, code: u8) -> String {
+        if self.color {
+            format!("\x1b[{}m{}\x1b[0m", code, text)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+/// The width of the widest of the texts, for aligning columns.
+pub fn column_width<'a>(texts: impl IntoIterator<Item = &'a str>) -> usize {
+    texts
+        .into_iter()
+        .map(|text| text.chars().count())
+        .max()
+        .unwrap_or(0)
+}
+
+/// The number of columns of the terminal standard output is shown in, or `COLUMNS` when it isn't
+/// a terminal, `None` if neither is known.
+pub fn terminal_width() -> Option<usize> {
+    #[cfg(unix)]
+    {
+        let mut size = libc::winsize {
+            ws_row: 0,
+            ws_col: 0,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        };
+        // Safe, the call only writes the size of the terminal into the struct
+        let result = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) };
+        if result == 0 && size.ws_col > 0 {
+            return Some(size.ws_col as usize);
+        }
+    }
+    env::var("COLUMNS")
+        .ok()?
+        .parse()
+        .ok()
+        .filter(|width| *width > 0)
+}
+
+/// Cuts the text short to at most `width` characters, ending it with `…` if anything was left
+/// out.
+pub fn truncate(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+    match width.checked_sub(1) {
+        Some(kept) => text.chars().take(kept).chain(Some('…')).collect(),
+        None => String::new(),
+    }
+}
+
+/// Cuts the text short to fit the rest of a terminal line after `used` columns, unless the width
+/// of the terminal isn't known.
+pub fn fit(text: &str, used: usize) -> String {
+    match terminal_width() {
+        Some(columns) => truncate(text, columns.saturating_sub(used)),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn style(color: bool) -> Style {
+        Style {
+            color,
+            account_colors: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn show_heat_as_glyphs_without_colors() {
+        let cells: String = (0..5).map(|level| style(false).heat(level)).collect();
+        assert_eq!(cells, "·░▒▓█");
+        assert_eq!(style(false).heat(9), "█");
+        assert_eq!(style(true).heat(4), "\x1b[38;5;40m■\x1b[0m");
+    }
+}
@@ -0,0 +1,311 @@
+//! Default locations of the entries, running and config files.
+//!
+//! The files are placed according to the XDG base directory specification, namespaced by the
+//! profile if there is one:
+//!
+//! - entries: `$XDG_DATA_HOME/tt/[<profile>/]entries`
+//! - running: `$XDG_STATE_HOME/tt/[<profile>/]running`
+//! - config: `$XDG_CONFIG_HOME/tt/[<profile>/]config.toml`
+//!
+//! On Windows the entries and running files are placed in `%LOCALAPPDATA%\tt\[<profile>\]`
+//! and the config file in `%APPDATA%\tt\[<profile>\]` instead.
+
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Paths {
+    pub entries: PathBuf,
+    pub running: PathBuf,
+    pub config: PathBuf,
+}
+
+impl Paths {
+    /// The default locations on this platform.
+    #[cfg(not(windows))]
+    pub fn platform(profile: Option<&str>) -> Self {
+        Paths::xdg(profile)
+    }
+
+    /// The default locations on this platform.
+    #[cfg(windows)]
+    pub fn platform(profile: Option<&str>) -> Self {
+        let dir = |base: Option<PathBuf>| {
+            let dir = base.unwrap_or_else(home_dir).join("tt");
+            match profile {
+                Some(profile) => dir.join(profile),
+                None => dir,
+            }
+        };
+        let data = dir(dirs::data_local_dir());
+        Paths {
+            entries: data.join("entries"),
+            running: data.join("running"),
+            config: dir(dirs::config_dir()).join("config.toml"),
+        }
+    }
+
+    /// The locations following the XDG base directory specification.
+    pub fn xdg(profile: Option<&str>) -> Self {
+        let home = home_dir();
+        let dir = |var: &str, default: &str| {
+            let dir = xdg_dir(env::var_os(var), &home, default).join("tt");
+            match profile {
+                Some(profile) => dir.join(profile),
+                None => dir,
+            }
+        };
+        Paths {
+            entries: dir("XDG_DATA_HOME", ".local/share").join("entries"),
+            running: dir("XDG_STATE_HOME", ".local/state").join("running"),
+            config: dir("XDG_CONFIG_HOME", ".config").join("config.toml"),
+        }
+    }
+
+    /// The locations used by earlier versions, which kept the running and config files in the
+    /// home directory, or every file of a profile in `~/.local/share/tt/<profile>/`.
+    ///
+    /// There was no default entries file without a profile, so it is the same as the XDG one.
+    fn legacy(profile: Option<&str>) -> Self {
+        let home = home_dir();
+        match profile {
+            Some(profile) => {
+                let dir = home.join(".local/share/tt").join(profile);
+                Paths {
+                    entries: dir.join("entries"),
+                    running: dir.join("running"),
+                    config: dir.join("config.toml"),
+                }
+            }
+            None => Paths {
+                entries: Paths::platform(None).entries,
+                running: home.join(".tt_running"),
+                config: home.join(".tt_config.toml"),
+            },
+        }
+    }
+
+    /// The default locations, except for files which only exist in their legacy location.
+    pub fn detect(profile: Option<&str>) -> Self {
+        let mut paths = Paths::platform(profile);
+        for (legacy, current) in pending_migrations(profile) {
+            log::warn!(
+                "using {} instead of {}, run `tt migrate-paths` to move it",
+                legacy.display(),
+                current.display()
+            );
+            if current == paths.entries {
+                paths.entries = legacy;
+            } else if current == paths.running {
+                paths.running = legacy;
+            } else if current == paths.config {
+                paths.config = legacy;
+            }
+        }
+        paths
+    }
+}
+
+/// Lists the files in legacy locations which should be moved to their default locations, as pairs
+/// of the legacy and the default location.
+pub fn pending_migrations(profile: Option<&str>) -> Vec<(PathBuf, PathBuf)> {
+    let legacy = Paths::legacy(profile);
+    let current = Paths::platform(profile);
+    vec![
+        (legacy.entries, current.entries),
+        (legacy.running, current.running),
+        (legacy.config, current.config),
+    ]
+    .into_iter()
+    .filter(|(legacy, current)| legacy != current && legacy.exists() && !current.exists())
+    .collect()
+}
+
+/// Moves a file, creating the directory it is moved to if needed.
+pub fn migrate(from: &Path, to: &Path) -> io::Result<()> {
+    if let Some(dir) = to.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    // Renaming fails across file systems, in which case the file is copied instead
+    if fs::rename(from, to).is_err() {
+        fs::copy(from, to)?;
+        fs::remove_file(from)?;
+    }
+    Ok(())
+}
+
+/// Expands a path whose file name is a pattern with `*` matching any characters and `?` matching
+/// one character, e.g. `archive/*.entries`, to the matching files in sorted order. Other paths are
+/// returned as they are.
+pub fn expand_pattern(pattern: &Path) -> io::Result<Vec<PathBuf>> {
+    let name = match pattern.file_name().and_then(|name| name.to_str()) {
+        Some(name) if name.contains(&['*', '?'][..]) => name,
+        _ => return Ok(vec![pattern.to_path_buf()]),
+    };
+    let dir = match pattern.parent() {
+        Some(dir) if dir != Path::new("") => dir,
+        _ => Path::new("."),
+    };
+    let mut paths = Vec::new();
+    for file in fs::read_dir(dir)? {
+        let file = file?;
+        let matches = file
+            .file_name()
+            .to_str()
+            .is_some_and(|file_name| matches_pattern(name, file_name));
+        if matches && file.file_type()?.is_file() {
+            paths.push(pattern.with_file_name(file.file_name()));
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+/// Whether the name matches the pattern, see `expand_pattern`.
+fn matches_pattern(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    // The positions after the last `*` and what it matched, for backtracking
+    let (mut p, mut n, mut star) = (0, 0, None);
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p + 1, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    p = star_p;
+                    n = star_n + 1;
+                    star = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// The home directory of the user, `$HOME` on Unix and the user profile directory on Windows.
+fn home_dir() -> PathBuf {
+    dirs::home_dir().unwrap_or_else(|| PathBuf::from("."))
+}
+
+/// Resolves an XDG base directory from the value of its environment variable, which is ignored
+/// unless it is an absolute path.
+fn xdg_dir(value: Option<OsString>, home: &Path, default: &str) -> PathBuf {
+    match value.map(PathBuf::from) {
+        Some(dir) if dir.is_absolute() => dir,
+        _ => home.join(default),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xdg_dir_from_environment() {
+        let home = env::temp_dir().join("home");
+        let data = env::temp_dir().join("data");
+
+        assert_eq!(
+            xdg_dir(Some(data.clone().into()), &home, ".local/share"),
+            data
+        );
+        assert_eq!(
+            xdg_dir(None, &home, ".local/share"),
+            home.join(".local/share")
+        );
+        // Relative paths are invalid according to the specification
+        assert_eq!(
+            xdg_dir(Some("data".into()), &home, ".local/share"),
+            home.join(".local/share")
+        );
+    }
+
+    #[test]
+    fn match_patterns() {
+        assert!(matches_pattern("*", "entries"));
+        assert!(matches_pattern("*.entries", "2021.entries"));
+        assert!(matches_pattern("20??-*", "2021-archive"));
+        assert!(matches_pattern("a*b*c", "abxbc"));
+        assert!(!matches_pattern("*.entries", "2021.entries.bak"));
+        assert!(!matches_pattern("20??", "202"));
+    }
+
+    #[test]
+    fn expand_patterns() {
+        let dir = env::temp_dir().join(format!("timetracker-patterns-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for name in &["2021.entries", "2020.entries", "notes"] {
+            fs::write(dir.join(name), "").unwrap();
+        }
+
+        assert_eq!(
+            expand_pattern(&dir.join("*.entries")).unwrap(),
+            vec![dir.join("2020.entries"), dir.join("2021.entries")]
+        );
+        assert_eq!(
+            expand_pattern(&dir.join("missing")).unwrap(),
+            vec![dir.join("missing")]
+        );
+    }
+
+    #[test]
+    fn profiles_have_their_own_directories() {
+        let default = Paths::platform(None);
+        let work = Paths::platform(Some("work"));
+
+        assert_eq!(
+            work.entries,
+            default
+                .entries
+                .parent()
+                .unwrap()
+                .join("work")
+                .join("entries")
+        );
+        assert_eq!(
+            work.running,
+            default
+                .running
+                .parent()
+                .unwrap()
+                .join("work")
+                .join("running")
+        );
+        assert_eq!(
+            work.config,
+            default
+                .config
+                .parent()
+                .unwrap()
+                .join("work")
+                .join("config.toml")
+        );
+    }
+
+    #[test]
+    fn migrate_file() {
+        let dir = env::temp_dir().join(format!("timetracker-migrate-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let from = dir.join(".tt_running");
+        let to = dir.join("state/tt/running");
+        fs::write(&from, "2021-07-03T10:00:00Z A\n").unwrap();
+
+        migrate(&from, &to).unwrap();
+
+        assert!(!from.exists());
+        assert_eq!(fs::read_to_string(&to).unwrap(), "2021-07-03T10:00:00Z A\n");
+    }
+}
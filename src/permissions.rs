@@ -0,0 +1,89 @@
+//! Keeping the data files private to their owner, as timesheets often hold confidential details
+//! about clients.
+//!
+//! Only Unix permissions are set and checked, elsewhere files are left as the system creates
+//! them.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+
+/// The permissions new data files are created with, read and write for the owner only.
+pub const PRIVATE_MODE: u32 = 0o600;
+
+/// Makes the files created with the options private, see `PRIVATE_MODE`. Files which already
+/// exist keep their permissions.
+pub fn private(options: &mut OpenOptions) -> &mut OpenOptions {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(PRIVATE_MODE);
+    }
+    options
+}
+
+/// Writes the file like `fs::write`, creating it private if it does not exist.
+pub fn write_private(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    private(OpenOptions::new().write(true).create(true).truncate(true))
+        .open(path)?
+        .write_all(contents.as_ref())
+}
+
+/// Gives the file the permissions of the original it replaces, if there is one, so replacing a
+/// file does not change who can read it.
+pub fn keep_permissions(original: &Path, replacement: &Path) -> io::Result<()> {
+    match fs::metadata(original) {
+        Ok(metadata) => fs::set_permissions(replacement, metadata.permissions()),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(err),
+    }
+}
+
+/// The permission bits of the file, like `0o644`, if others than its owner may read or write it.
+/// `None` if it is private or does not exist.
+pub fn too_open(path: &Path) -> io::Result<Option<u32>> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        match fs::metadata(path) {
+            Ok(metadata) => {
+                let mode = metadata.permissions().mode() & 0o777;
+                Ok(Some(mode).filter(|mode| mode & 0o077 != 0))
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        Ok(None)
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn create_private_files() {
+        let dir = env::temp_dir().join(format!("tt_permissions_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let private_file = dir.join("entries");
+        let shared_file = dir.join("shared");
+        let _ = fs::remove_file(&private_file);
+
+        write_private(&private_file, "2021-07-03T10:00:00Z A\n").unwrap();
+        assert_eq!(too_open(&private_file).unwrap(), None);
+        assert_eq!(too_open(&dir.join("missing")).unwrap(), None);
+
+        fs::write(&shared_file, "").unwrap();
+        fs::set_permissions(&shared_file, fs::Permissions::from_mode(0o644)).unwrap();
+        assert_eq!(too_open(&shared_file).unwrap(), Some(0o644));
+        keep_permissions(&shared_file, &private_file).unwrap();
+        assert_eq!(too_open(&private_file).unwrap(), Some(0o644));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
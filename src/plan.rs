@@ -0,0 +1,140 @@
+//! Plans of a day, jotted down as it went and recorded at once with `tt apply`, e.g.
+//!
+//! ```text
+//! start dev 09:00
+//! switch meetings 11:00
+//! stop 17:00
+//! ```
+//!
+//! The time ends each line and the account is the rest, so accounts may contain spaces. Blank
+//! lines and lines starting with `#` are skipped.
+
+use crate::timeparse;
+use chrono::{DateTime, TimeZone, Utc};
+use std::error::Error;
+use std::fmt;
+
+/// The time spent on an account, until the next step of the plan or still going at its end.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub account: String,
+    pub start: DateTime<Utc>,
+    pub stop: Option<DateTime<Utc>>,
+}
+
+/// Reads the plan, with the times relative to `now`, e.g. bare times on its day.
+pub fn parse<Tz: TimeZone>(plan: &str, now: &DateTime<Tz>) -> Result<Vec<Span>, PlanError> {
+    let mut spans: Vec<Span> = Vec::new();
+    let mut last = None;
+    for (index, line) in plan.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let error = |message: String| PlanError {
+            line: index + 1,
+            message,
+        };
+        let (rest, time) = line
+            .rsplit_once(char::is_whitespace)
+            .ok_or_else(|| error("expected a command and a time".to_string()))?;
+        let at = timeparse::parse(time, now).map_err(error)?;
+        if last.is_some_and(|last| at < last) {
+            return Err(error(format!("{} is before the step above", time)));
+        }
+        last = Some(at);
+        let (command, account) = match rest.trim().split_once(char::is_whitespace) {
+            Some((command, account)) => (command, Some(account.trim())),
+            None => (rest.trim(), None),
+        };
+        let open = spans.last_mut().filter(|span| span.stop.is_none());
+        match (command, account) {
+            ("start", Some(_)) if open.is_some() => {
+                let open = open.expect("a span is open");
+                return Err(error(format!(
+                    "{} is still started, use switch",
+                    open.account
+                )));
+            }
+            ("switch", Some(_)) | ("stop", None) if open.is_none() => {
+                return Err(error("nothing is started".to_string()));
+            }
+            ("start", Some(account)) | ("switch", Some(account)) => {
+                if let Some(open) = open {
+                    open.stop = Some(at);
+                }
+                spans.push(Span {
+                    account: account.to_string(),
+                    start: at,
+                    stop: None,
+                });
+            }
+            ("stop", None) => open.expect("a span is open").stop = Some(at),
+            _ => {
+                return Err(error(format!(
+                    "expected `start <account> <time>`, `switch <account> <time>` or \
+                     `stop <time>`, not `{}`",
+                    line
+                )));
+            }
+        }
+    }
+    Ok(spans)
+}
+
+/// A line of a plan that cannot be followed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlanError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for PlanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for PlanError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_plans() {
+        let now = Utc.ymd(2021, 7, 3).and_hms(18, 0, 0);
+        let time = |hour, minute| Utc.ymd(2021, 7, 3).and_hms(hour, minute, 0);
+        let spans = parse(
+            "# Saturday\n\
+             start dev 09:00\n\
+             \n\
+             switch Client A 11:30\n\
+             stop 12:00\n\
+             start dev 13:00\n",
+            &now,
+        )
+        .unwrap();
+        let span = |account: &str, start, stop| Span {
+            account: account.to_string(),
+            start,
+            stop,
+        };
+        assert_eq!(
+            spans,
+            vec![
+                span("dev", time(9, 0), Some(time(11, 30))),
+                span("Client A", time(11, 30), Some(time(12, 0))),
+                span("dev", time(13, 0), None),
+            ]
+        );
+
+        let line = |plan| parse(plan, &now).unwrap_err().line;
+        assert_eq!(line("start dev 09:00\nstart ops 10:00"), 2);
+        assert_eq!(line("switch dev 09:00"), 1);
+        assert_eq!(line("start dev 09:00\nstop 08:00"), 2);
+        assert_eq!(line("stop 09:00"), 1);
+        assert_eq!(line("start dev 9 o'clock"), 1);
+        assert_eq!(line("pause 09:00"), 1);
+    }
+}
@@ -0,0 +1,12 @@
+//! The types and functions most code using the library needs, e.g. `use timetracker::prelude::*`.
+//!
+//! What is exported here only changes in a major release. Structs which may grow fields are
+//! `#[non_exhaustive]` and made with builders, and error enums may grow variants.
+
+pub use crate::duration::{format_hours_minutes, parse_duration};
+pub use crate::storage::{Storage, StorageError};
+pub use crate::timesheet::Timesheet;
+pub use crate::{
+    is_valid_tag, Entry, EntryBuilder, EntryRef, ParseError, RunningEntry, RunningEntryBuilder,
+    ValidationError,
+};
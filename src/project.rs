@@ -0,0 +1,85 @@
+//! Project files, named `.tt`, giving the account that entries for a directory are tracked on.
+//!
+//! `tt start` without an account uses the project file of the current directory or the closest
+//! directory above it, e.g.
+//!
+//! ```toml
+//! account = "ClientA:ProjectX"
+//! tags = ["dev"]
+//! billable = true
+//! ```
+
+use crate::config::ConfigError;
+use serde::Deserialize;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The name of project files.
+pub const FILE_NAME: &str = ".tt";
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Project {
+    pub account: String,
+    /// Tags to start entries with.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Start entries as billable.
+    #[serde(default)]
+    pub billable: bool,
+    /// The description to start entries with when none is given.
+    pub description: Option<String>,
+}
+
+impl Project {
+    /// Finds the project file in the directory or the closest directory above it, returning its
+    /// path together with the project.
+    pub fn find(dir: &Path) -> Result<Option<(PathBuf, Project)>, ConfigError> {
+        for dir in dir.ancestors() {
+            let path = dir.join(FILE_NAME);
+            match fs::read_to_string(&path) {
+                Ok(contents) => return Ok(Some((path, toml::from_str(&contents)?))),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_in_parent_directories() {
+        let dir = std::env::temp_dir().join(format!("timetracker-project-{}", std::process::id()));
+        let nested = dir.join("src").join("bin");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(
+            dir.join(FILE_NAME),
+            "account = \"ClientA:ProjectX\"\ntags = [\"dev\"]",
+        )
+        .unwrap();
+
+        let found = Project::find(&nested).unwrap();
+        fs::write(dir.join("src").join(FILE_NAME), "acount = \"Typo\"").unwrap();
+        let invalid = Project::find(&nested);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            found,
+            Some((
+                dir.join(FILE_NAME),
+                Project {
+                    account: "ClientA:ProjectX".to_string(),
+                    tags: vec!["dev".to_string()],
+                    billable: false,
+                    description: None,
+                }
+            ))
+        );
+        assert!(invalid.is_err());
+    }
+}
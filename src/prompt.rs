@@ -0,0 +1,226 @@
+//! Short summaries of the running entries for shell prompts and status bars.
+
+use crate::duration::format_hours_minutes;
+use crate::report::{self, is_within_account};
+use crate::templates::fill_placeholders;
+use crate::{Entry, RunningEntry};
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use std::fmt::{self, Display};
+
+/// The share of the budget of an account used from which status bars alert about it, unless
+/// configured otherwise.
+pub const DEFAULT_BUDGET_ALERT: f64 = 0.9;
+
+/// Something worth a look, which status bars mark their output for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Alert {
+    /// The time tracked today is beyond the daily limit.
+    DailyLimit { total: Duration, limit: Duration },
+    /// Most of the budget of an account is used.
+    Budget {
+        account: String,
+        used: Duration,
+        budget: Duration,
+    },
+}
+
+impl fmt::Display for Alert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Alert::DailyLimit { total, limit } => write!(
+                f,
+                "{} tracked today, beyond the daily limit of {}",
+                format_hours_minutes(*total),
+                format_hours_minutes(*limit)
+            ),
+            Alert::Budget {
+                account,
+                used,
+                budget,
+            } => write!(
+                f,
+                "{} has used {} of its budget of {}",
+                account,
+                format_hours_minutes(*used),
+                format_hours_minutes(*budget)
+            ),
+        }
+    }
+}
+
+/// Finds the alerts for status bars: the total of today in the time zone of `now` beyond the
+/// daily limit, and the budgets of accounts of which at least `budget_share` is used. Running
+/// entries should be passed as entries stopping now to be included.
+pub fn alerts<Tz: TimeZone>(
+    entries: &[Entry],
+    daily_limit: Option<Duration>,
+    budgets: &[(&str, Duration)],
+    budget_share: f64,
+    now: &DateTime<Tz>,
+) -> Vec<Alert> {
+    let mut alerts = Vec::new();
+    if let Some(limit) = daily_limit {
+        let today = now.naive_local().date();
+        let total = report::totals_by_day(entries, &now.timezone())
+            .remove(&today)
+            .unwrap_or_else(Duration::zero);
+        if total > limit {
+            alerts.push(Alert::DailyLimit { total, limit });
+        }
+    }
+    for (account, budget) in budgets {
+        let used = entries
+            .iter()
+            .filter(|entry| is_within_account(&entry.account, account))
+            .fold(Duration::zero(), |sum, entry| {
+                sum + (entry.stop - entry.start)
+            });
+        if used.num_seconds() as f64 >= budget.num_seconds() as f64 * budget_share {
+            alerts.push(Alert::Budget {
+                account: account.to_string(),
+                used,
+                budget: *budget,
+            });
+        }
+    }
+    alerts
+}
+
+/// Formats each running entry with the format and joins them with ` | `, or returns `None` if
+/// nothing is running.
+///
+/// The format can contain the placeholders `{account}`, `{elapsed}` (e.g. `1h 05m`), `{start}`
+/// (the local start time, e.g. `09:30`), `{tags}` and `{description}`. Other text in braces is
+/// kept as it is.
+pub fn format_running<Tz: TimeZone>(
+    format: &str,
+    running_entries: &[RunningEntry],
+    now: &DateTime<Tz>,
+) -> Option<String>
+where
+    Tz::Offset: Display,
+{
+    if running_entries.is_empty() {
+        return None;
+    }
+    let lines: Vec<String> = running_entries
+        .iter()
+        .map(|entry| format_entry(format, entry, now))
+        .collect();
+    Some(lines.join(" | "))
+}
+
+/// Formats the most recently started running entry as `<account>|<elapsed seconds>`, e.g.
+/// `dev|7200`, or `idle` if nothing is running. The account is everything before the last `|`.
+///
+/// This format is meant for scripts and is kept stable across releases.
+pub fn format_short_status(running_entries: &[RunningEntry], now: DateTime<Utc>) -> String {
+    match running_entries.iter().max_by_key(|entry| entry.start) {
+        Some(entry) => format!(
+            "{}|{}",
+            entry.account,
+            (now - entry.start).num_seconds().max(0)
+        ),
+        None => "idle".to_string(),
+    }
+}
+
+fn format_entry<Tz: TimeZone>(format: &str, entry: &RunningEntry, now: &DateTime<Tz>) -> String
+where
+    Tz::Offset: Display,
+{
+    let formatted = fill_placeholders(format, |name| match name {
+        "account" => Some(entry.account.to_string()),
+        "elapsed" => Some(format_hours_minutes(now.with_timezone(&Utc) - entry.start)),
+        "start" => Some(
+            entry
+                .start
+                .with_timezone(&now.timezone())
+                .format("%H:%M")
+                .to_string(),
+        ),
+        "tags" => Some(entry.tags.join(" ")),
+        "description" => Some(entry.description.clone().unwrap_or_default()),
+        _ => None,
+    });
+    formatted.trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::FixedOffset;
+    use std::str::FromStr;
+
+    #[test]
+    fn format_running_entries() {
+        let now = DateTime::<Utc>::from_str("2021-07-03T11:05:00Z")
+            .unwrap()
+            .with_timezone(&FixedOffset::east(2 * 3600));
+        let running_entries = vec![
+            RunningEntry::from_str("2021-07-03T10:00:00Z dev #cli ; prompt").unwrap(),
+            RunningEntry::from_str("2021-07-03T11:00:00Z ops").unwrap(),
+        ];
+
+        assert_eq!(
+            format_running("{account} {elapsed}", &running_entries, &now).as_deref(),
+            Some("dev 1h 05m | ops 0h 05m")
+        );
+        assert_eq!(
+            format_running(
+                "{start} {tags} {description} {other}",
+                &running_entries[..1],
+                &now
+            )
+            .as_deref(),
+            Some("12:00 cli prompt {other}")
+        );
+    }
+
+    #[test]
+    fn nothing_when_idle() {
+        assert_eq!(format_running("{account}", &[], &Utc::now()), None);
+    }
+
+    #[test]
+    fn short_status() {
+        let now = DateTime::<Utc>::from_str("2021-07-03T12:00:00Z").unwrap();
+        let running_entries = vec![
+            RunningEntry::from_str("2021-07-03T11:00:00Z dev #cli").unwrap(),
+            RunningEntry::from_str("2021-07-03T10:00:00Z ops").unwrap(),
+        ];
+
+        assert_eq!(format_short_status(&running_entries, now), "dev|3600");
+        assert_eq!(format_short_status(&[], now), "idle");
+    }
+
+    #[test]
+    fn alerts_for_status_bars() {
+        let now = DateTime::<Utc>::from_str("2021-07-03T18:00:00Z").unwrap();
+        let entries = vec![
+            Entry::from_str("2021-07-02T08:00:00Z 2021-07-02T16:00:00Z Client:Support").unwrap(),
+            Entry::from_str("2021-07-03T08:00:00Z 2021-07-03T17:30:00Z Client").unwrap(),
+            Entry::from_str("2021-07-03T17:30:00Z 2021-07-03T18:00:00Z Other").unwrap(),
+        ];
+        let budgets = [
+            ("Client", Duration::hours(20)),
+            ("Other", Duration::hours(10)),
+        ];
+
+        assert_eq!(
+            alerts(&entries, Some(Duration::hours(9)), &budgets, 0.8, &now),
+            vec![
+                Alert::DailyLimit {
+                    total: Duration::hours(10),
+                    limit: Duration::hours(9)
+                },
+                Alert::Budget {
+                    account: "Client".to_string(),
+                    used: Duration::minutes(17 * 60 + 30),
+                    budget: Duration::hours(20)
+                },
+            ]
+        );
+        assert!(alerts(&entries, Some(Duration::hours(10)), &budgets, 0.9, &now).is_empty());
+    }
+}
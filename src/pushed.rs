@@ -0,0 +1,91 @@
+//! Bookkeeping of the entries already uploaded to other services.
+
+use crate::storage::StorageError;
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// A file mapping entry IDs to the IDs the entries were given by a remote service, one pair per
+/// line, so that each entry is only pushed once.
+#[derive(Debug)]
+pub struct PushedLog {
+    path: PathBuf,
+    remote_ids: BTreeMap<String, String>,
+}
+
+impl PushedLog {
+    /// Reads the log at the given path, which is treated as empty if it does not exist.
+    pub fn load(path: &Path) -> Result<Self, StorageError> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err.into()),
+        };
+        let remote_ids = contents
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.splitn(2, ' ');
+                let id = fields.next().filter(|id| !id.is_empty())?;
+                Some((id.to_string(), fields.next().unwrap_or("").to_string()))
+            })
+            .collect();
+        Ok(PushedLog {
+            path: path.to_path_buf(),
+            remote_ids,
+        })
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.remote_ids.contains_key(id)
+    }
+
+    pub fn remote_id(&self, id: &str) -> Option<&str> {
+        self.remote_ids.get(id).map(String::as_str)
+    }
+
+    /// Records that the entry with the given ID was pushed, appending it to the file right away so
+    /// that an interrupted push is not repeated.
+    pub fn record(&mut self, id: &str, remote_id: &str) -> Result<(), StorageError> {
+        if let Some(parent) = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{} {}", id, remote_id)?;
+        self.remote_ids
+            .insert(id.to_string(), remote_id.to_string());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn missing_log_is_empty() {
+        let log = PushedLog::load(Path::new("/nonexistent/pushed")).unwrap();
+
+        assert!(!log.contains("aa58e89a4b2e7c4c"));
+    }
+
+    #[test]
+    fn record_and_reload() {
+        let path = env::temp_dir().join(format!("tt_pushed_test_{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let mut log = PushedLog::load(&path).unwrap();
+        log.record("aa58e89a4b2e7c4c", "60f1a2").unwrap();
+        log.record("0123456789abcdef", "60f1a3").unwrap();
+        let reloaded = PushedLog::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert!(reloaded.contains("aa58e89a4b2e7c4c"));
+        assert_eq!(reloaded.remote_id("0123456789abcdef"), Some("60f1a3"));
+        assert!(!reloaded.contains("fedcba9876543210"));
+    }
+}
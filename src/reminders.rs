@@ -0,0 +1,266 @@
+//! Reminders about forgotten timers.
+
+use crate::RunningEntry;
+use chrono::{DateTime, Datelike, Duration, NaiveTime, TimeZone, Utc, Weekday};
+use serde::de::{self, Deserializer};
+use serde::Deserialize;
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+/// A daily span of local time, written as `09:00-17:00`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct WorkHours {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl WorkHours {
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        time >= self.start && time < self.end
+    }
+}
+
+impl FromStr for WorkHours {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!(r#"invalid work hours "{}", expected e.g. "09:00-17:00""#, s);
+        let (start, end) = s.split_once('-').ok_or_else(invalid)?;
+        let start = NaiveTime::parse_from_str(start.trim(), "%H:%M").map_err(|_| invalid())?;
+        let end = NaiveTime::parse_from_str(end.trim(), "%H:%M").map_err(|_| invalid())?;
+        if start >= end {
+            return Err(invalid());
+        }
+        Ok(WorkHours { start, end })
+    }
+}
+
+impl TryFrom<String> for WorkHours {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        WorkHours::from_str(&s)
+    }
+}
+
+/// Settings for when to remind about forgotten timers.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReminderConfig {
+    /// Remind about entries which have been running for longer than this many hours.
+    pub max_running_hours: Option<f64>,
+    /// Remind when nothing is running during these hours on work days.
+    pub work_hours: Option<WorkHours>,
+    /// The days the work hours apply to, by default Monday through Friday.
+    pub work_days: Vec<String>,
+}
+
+impl Default for ReminderConfig {
+    fn default() -> Self {
+        ReminderConfig {
+            max_running_hours: None,
+            work_hours: None,
+            work_days: ["mon", "tue", "wed", "thu", "fri"]
+                .iter()
+                .map(|day| day.to_string())
+                .collect(),
+        }
+    }
+}
+
+impl ReminderConfig {
+    pub fn is_work_day(&self, weekday: Weekday) -> bool {
+        self.work_days
+            .iter()
+            .any(|day| Weekday::from_str(day) == Ok(weekday))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reminder {
+    /// An entry has been running for longer than the configured maximum.
+    LongRunning {
+        entry: RunningEntry,
+        elapsed: Duration,
+    },
+    /// Nothing is running during work hours.
+    Idle,
+}
+
+impl fmt::Display for Reminder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Reminder::LongRunning { entry, elapsed } => write!(
+                f,
+                "{} has been running for {}, did you forget to stop it?",
+                entry.account,
+                crate::duration::format_hours_minutes(*elapsed)
+            ),
+            Reminder::Idle => write!(f, "No timer is running during work hours"),
+        }
+    }
+}
+
+/// The tag added to the entries stopped at the end of the day, see `auto_stop_time`.
+pub const AUTO_STOPPED_TAG: &str = "auto-stopped";
+
+/// Reads an optional time of day in a config file, written as `18:30`.
+pub fn deserialize_optional_time<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<NaiveTime>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|s| {
+            NaiveTime::parse_from_str(s.trim(), "%H:%M").map_err(|_| {
+                de::Error::custom(format!(r#"invalid time "{}", expected e.g. "18:30""#, s))
+            })
+        })
+        .transpose()
+}
+
+/// The time to stop an entry started at the given time when it is forgotten at the end of the
+/// day, the first time the clock in the time zone shows `at` after the start.
+pub fn auto_stop_time<Tz: TimeZone>(start: DateTime<Utc>, at: NaiveTime, tz: &Tz) -> DateTime<Utc> {
+    let mut date = start.with_timezone(tz).naive_local().date();
+    loop {
+        // Days on which the time is skipped by a change to daylight saving time are passed over
+        if let Some(stop) = tz.from_local_datetime(&date.and_time(at)).earliest() {
+            let stop = stop.with_timezone(&Utc);
+            if stop > start {
+                return stop;
+            }
+        }
+        date = date.succ();
+    }
+}
+
+/// Finds the reminders that apply at the given time, with work hours in its time zone.
+pub fn reminders<Tz: TimeZone>(
+    running_entries: &[RunningEntry],
+    now: &DateTime<Tz>,
+    config: &ReminderConfig,
+) -> Vec<Reminder> {
+    let mut reminders = Vec::new();
+
+    if let Some(hours) = config.max_running_hours {
+        let max = Duration::seconds((hours * 3600.0).round() as i64);
+        let now = now.with_timezone(&Utc);
+        for entry in running_entries {
+            let elapsed = now - entry.start;
+            if elapsed > max {
+                reminders.push(Reminder::LongRunning {
+                    entry: entry.clone(),
+                    elapsed,
+                });
+            }
+        }
+    }
+
+    if let Some(work_hours) = config.work_hours {
+        if running_entries.is_empty()
+            && config.is_work_day(now.weekday())
+            && work_hours.contains(now.time())
+        {
+            reminders.push(Reminder::Idle);
+        }
+    }
+
+    reminders
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn datetime(s: &str) -> DateTime<Utc> {
+        DateTime::from_str(s).unwrap()
+    }
+
+    fn running_entry(account: &str, start: &str) -> RunningEntry {
+        RunningEntry {
+            start: datetime(start),
+            account: account.into(),
+            tags: Vec::new(),
+            billable: false,
+            category: None,
+            description: None,
+            meta: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn parse_work_hours() {
+        let work_hours = WorkHours::from_str("09:00-17:30").unwrap();
+
+        assert_eq!(work_hours.start, NaiveTime::from_hms(9, 0, 0));
+        assert_eq!(work_hours.end, NaiveTime::from_hms(17, 30, 0));
+        assert!(WorkHours::from_str("17:00-09:00").is_err());
+        assert!(WorkHours::from_str("9-5").is_err());
+    }
+
+    #[test]
+    fn remind_about_long_running_entries() {
+        let config = ReminderConfig {
+            max_running_hours: Some(4.0),
+            ..ReminderConfig::default()
+        };
+        let running_entries = vec![
+            running_entry("A", "2021-07-03T06:00:00Z"),
+            running_entry("B", "2021-07-03T09:00:00Z"),
+        ];
+
+        assert_eq!(
+            reminders(&running_entries, &datetime("2021-07-03T11:00:00Z"), &config),
+            vec![Reminder::LongRunning {
+                entry: running_entries[0].clone(),
+                elapsed: Duration::hours(5),
+            }]
+        );
+    }
+
+    #[test]
+    fn remind_when_idle_during_work_hours() {
+        let config = ReminderConfig {
+            work_hours: Some(WorkHours::from_str("09:00-17:00").unwrap()),
+            ..ReminderConfig::default()
+        };
+
+        // Friday during work hours
+        assert_eq!(
+            reminders(&[], &datetime("2021-07-02T10:00:00Z"), &config),
+            vec![Reminder::Idle]
+        );
+        // Friday evening
+        assert!(reminders(&[], &datetime("2021-07-02T18:00:00Z"), &config).is_empty());
+        // Saturday
+        assert!(reminders(&[], &datetime("2021-07-03T10:00:00Z"), &config).is_empty());
+        // Something is running
+        assert!(reminders(
+            &[running_entry("A", "2021-07-02T09:00:00Z")],
+            &datetime("2021-07-02T10:00:00Z"),
+            &config
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn auto_stop_at_the_end_of_the_day() {
+        let at = NaiveTime::from_hms(18, 30, 0);
+        assert_eq!(
+            auto_stop_time(datetime("2021-07-02T09:00:00Z"), at, &Utc),
+            datetime("2021-07-02T18:30:00Z")
+        );
+        // Started after the time, so it runs until the next day
+        assert_eq!(
+            auto_stop_time(datetime("2021-07-02T20:00:00Z"), at, &Utc),
+            datetime("2021-07-03T18:30:00Z")
+        );
+        let zone = chrono::FixedOffset::east(2 * 3600);
+        assert_eq!(
+            auto_stop_time(datetime("2021-07-02T09:00:00Z"), at, &zone),
+            datetime("2021-07-02T16:30:00Z")
+        );
+    }
+}
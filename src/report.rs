@@ -0,0 +1,385 @@
+//! Aggregation of entries into reports.
+
+use crate::Entry;
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+/// Checks whether the account is the given parent account or one of its sub-accounts, which are
+/// separated by colons as in `Client:Project`.
+pub fn is_within_account(account: &str, parent: &str) -> bool {
+    account == parent || (account.starts_with(parent) && account[parent.len()..].starts_with(':'))
+}
+
+/// The total of an account in reports written as JSON.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AccountTotal {
+    pub account: String,
+    pub seconds: i64,
+    /// The total written in the format asked for, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration: Option<String>,
+}
+
+/// The report of a period written as JSON or CSV, with what the table of `report` shows.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PeriodReport {
+    /// The time zone of the days, like `local` or `Europe/Oslo`.
+    pub zone: String,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    /// The total of each day, if asked for.
+    pub days: Vec<DayTotal>,
+    /// The total of each account, or of each client or category.
+    pub totals: Vec<GroupTotal>,
+    pub billable_seconds: i64,
+    pub non_billable_seconds: i64,
+    pub total_seconds: i64,
+    /// The sum of the amounts, if any account has a rate.
+    pub total_amount: Option<f64>,
+    /// The time expected to be worked in the period, if the hours per day are configured.
+    pub expected_seconds: Option<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DayTotal {
+    pub date: NaiveDate,
+    pub seconds: i64,
+}
+
+/// The total of an account, client or category, with the amount charged for it if there is a
+/// rate.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GroupTotal {
+    pub name: String,
+    pub seconds: i64,
+    pub amount: Option<f64>,
+}
+
+impl PeriodReport {
+    /// One row for each day, each total and the sums, with the kind of row first, e.g.
+    /// `total,ClientA,3600,120.00`.
+    pub fn to_csv(&self) -> String {
+        let mut rows = vec!["kind,name,seconds,amount".to_string()];
+        for day in &self.days {
+            rows.push(format!("day,{},{},", day.date, day.seconds));
+        }
+        for total in &self.totals {
+            rows.push(format!(
+                "total,{},{},{}",
+                crate::export::csv_field(&total.name),
+                total.seconds,
+                total
+                    .amount
+                    .map_or(String::new(), |amount| format!("{:.2}", amount))
+            ));
+        }
+        rows.push(format!("billable,,{},", self.billable_seconds));
+        rows.push(format!("non-billable,,{},", self.non_billable_seconds));
+        rows.push(format!(
+            "sum,,{},{}",
+            self.total_seconds,
+            self.total_amount
+                .map_or(String::new(), |amount| format!("{:.2}", amount))
+        ));
+        if let Some(expected) = self.expected_seconds {
+            rows.push(format!("expected,,{},", expected));
+        }
+        rows.join("\n")
+    }
+}
+
+/// Sums the duration of the entries for each account, ordered by account name.
+pub fn totals_by_account(entries: &[Entry]) -> Vec<(String, Duration)> {
+    let mut totals: BTreeMap<&str, Duration> = BTreeMap::new();
+    for entry in entries {
+        let total = totals.entry(&entry.account).or_insert_with(Duration::zero);
+        *total = *total + (entry.stop - entry.start);
+    }
+    totals
+        .into_iter()
+        .map(|(account, total)| (account.to_string(), total))
+        .collect()
+}
+
+/// Sums the duration of the entries for each day in the given time zone.
+///
+/// Entries spanning midnight are split, so each day only gets the time tracked on that day.
+pub fn totals_by_day<Tz: TimeZone>(entries: &[Entry], tz: &Tz) -> BTreeMap<NaiveDate, Duration> {
+    let mut totals = BTreeMap::new();
+    for part in entries
+        .iter()
+        .flat_map(|entry| split_at_midnight(entry, tz))
+        .filter(|part| part.start < part.stop)
+    {
+        let total = totals
+            .entry(local_date(part.start, tz))
+            .or_insert_with(Duration::zero);
+        *total = *total + (part.stop - part.start);
+    }
+    totals
+}
+
+/// Splits an entry spanning midnight in the given time zone into one entry per day, each with
+/// the account, tags and description of the original entry.
+///
+/// On days starting with a daylight saving time transition, the split is made at the first
+/// instant of the day. An entry without any duration is kept as it is.
+pub fn split_at_midnight<Tz: TimeZone>(entry: &Entry, tz: &Tz) -> Vec<Entry> {
+    let mut parts = Vec::new();
+    let mut start = entry.start;
+    while start < entry.stop || parts.is_empty() {
+        let stop = day_range(local_date(start, tz), tz).end.min(entry.stop);
+        parts.push(Entry {
+            start,
+            stop,
+            ..entry.clone()
+        });
+        start = stop;
+    }
+    parts
+}
+
+/// The date of the time in the given time zone.
+pub fn local_date<Tz: TimeZone>(time: DateTime<Utc>, tz: &Tz) -> NaiveDate {
+    time.with_timezone(tz).naive_local().date()
+}
+
+/// Finds the first instant of the day in the given time zone, which is not necessarily midnight
+/// if the day starts with a daylight saving time transition.
+pub fn start_of_day<Tz: TimeZone>(date: NaiveDate, tz: &Tz) -> DateTime<Utc> {
+    (0..24)
+        .find_map(|hour| tz.from_local_datetime(&date.and_hms(hour, 0, 0)).earliest())
+        .expect("a day has at least one valid hour")
+        .with_timezone(&Utc)
+}
+
+/// The instants of the day in the given time zone, which is 23 or 25 hours long on days with a
+/// daylight saving time transition.
+pub fn day_range<Tz: TimeZone>(date: NaiveDate, tz: &Tz) -> Range<DateTime<Utc>> {
+    start_of_day(date, tz)..start_of_day(date.succ(), tz)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::FixedOffset;
+    use chrono_tz::America::Sao_Paulo;
+    use chrono_tz::Europe::Berlin;
+    use std::str::FromStr;
+
+    fn entry(s: &str) -> Entry {
+        Entry::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn split_entry_at_midnight() {
+        let tz = FixedOffset::east(2 * 3600);
+
+        assert_eq!(
+            split_at_midnight(
+                &entry("2021-07-03T20:00:00Z 2021-07-05T02:00:00Z A #tag ; note"),
+                &tz
+            ),
+            vec![
+                entry("2021-07-03T20:00:00Z 2021-07-03T22:00:00Z A #tag ; note"),
+                entry("2021-07-03T22:00:00Z 2021-07-04T22:00:00Z A #tag ; note"),
+                entry("2021-07-04T22:00:00Z 2021-07-05T02:00:00Z A #tag ; note"),
+            ]
+        );
+        assert_eq!(
+            split_at_midnight(&entry("2021-07-03T10:00:00Z 2021-07-03T12:00:00Z A"), &tz),
+            vec![entry("2021-07-03T10:00:00Z 2021-07-03T12:00:00Z A")]
+        );
+        assert_eq!(
+            split_at_midnight(&entry("2021-07-03T10:00:00Z 2021-07-03T10:00:00Z A"), &tz),
+            vec![entry("2021-07-03T10:00:00Z 2021-07-03T10:00:00Z A")]
+        );
+    }
+
+    fn datetime(s: &str) -> DateTime<Utc> {
+        DateTime::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn bound_days_across_daylight_saving_time() {
+        let date = NaiveDate::from_ymd;
+
+        // Clocks spring forward from 02:00 to 03:00 on March 28th and fall back from 03:00 to
+        // 02:00 on October 31st 2021
+        let spring_forward = day_range(date(2021, 3, 28), &Berlin);
+        assert_eq!(
+            spring_forward,
+            datetime("2021-03-27T23:00:00Z")..datetime("2021-03-28T22:00:00Z")
+        );
+        assert_eq!(
+            spring_forward.end - spring_forward.start,
+            Duration::hours(23)
+        );
+        let fall_back = day_range(date(2021, 10, 31), &Berlin);
+        assert_eq!(fall_back.end - fall_back.start, Duration::hours(25));
+        assert_eq!(
+            local_date(datetime("2021-10-31T22:59:59Z"), &Berlin),
+            date(2021, 10, 31)
+        );
+
+        // Without a midnight, the day starts when clocks spring forward to 01:00
+        assert_eq!(
+            day_range(date(2018, 11, 4), &Sao_Paulo),
+            datetime("2018-11-04T03:00:00Z")..datetime("2018-11-05T02:00:00Z")
+        );
+        assert_eq!(
+            day_range(date(2018, 11, 3), &Sao_Paulo).end,
+            datetime("2018-11-04T03:00:00Z")
+        );
+    }
+
+    #[test]
+    fn split_entry_across_daylight_saving_time_start() {
+        // Sunday is only 23 hours long
+        assert_eq!(
+            split_at_midnight(
+                &entry("2021-03-27T21:00:00Z 2021-03-28T22:30:00Z A"),
+                &Berlin
+            ),
+            vec![
+                entry("2021-03-27T21:00:00Z 2021-03-27T23:00:00Z A"),
+                entry("2021-03-27T23:00:00Z 2021-03-28T22:00:00Z A"),
+                entry("2021-03-28T22:00:00Z 2021-03-28T22:30:00Z A"),
+            ]
+        );
+        assert_eq!(
+            totals_by_day(
+                &[entry("2021-03-27T23:00:00Z 2021-03-28T22:00:00Z A")],
+                &Berlin
+            )
+            .into_iter()
+            .collect::<Vec<_>>(),
+            vec![(NaiveDate::from_ymd(2021, 3, 28), Duration::hours(23))]
+        );
+    }
+
+    #[test]
+    fn split_entry_across_daylight_saving_time_end() {
+        // Sunday is 25 hours long
+        assert_eq!(
+            split_at_midnight(
+                &entry("2021-10-30T20:00:00Z 2021-10-31T23:30:00Z A"),
+                &Berlin
+            ),
+            vec![
+                entry("2021-10-30T20:00:00Z 2021-10-30T22:00:00Z A"),
+                entry("2021-10-30T22:00:00Z 2021-10-31T23:00:00Z A"),
+                entry("2021-10-31T23:00:00Z 2021-10-31T23:30:00Z A"),
+            ]
+        );
+        assert_eq!(
+            totals_by_day(
+                &[entry("2021-10-30T22:00:00Z 2021-10-31T23:00:00Z A")],
+                &Berlin
+            )
+            .into_iter()
+            .collect::<Vec<_>>(),
+            vec![(NaiveDate::from_ymd(2021, 10, 31), Duration::hours(25))]
+        );
+    }
+
+    #[test]
+    fn write_period_reports_as_csv() {
+        let report = PeriodReport {
+            zone: "utc".to_string(),
+            since: Some(Utc.ymd(2021, 7, 1).and_hms(0, 0, 0)),
+            until: Some(Utc.ymd(2021, 7, 3).and_hms(0, 0, 0)),
+            days: vec![DayTotal {
+                date: NaiveDate::from_ymd(2021, 7, 1),
+                seconds: 5400,
+            }],
+            totals: vec![
+                GroupTotal {
+                    name: "Client, Inc".to_string(),
+                    seconds: 3600,
+                    amount: Some(120.0),
+                },
+                GroupTotal {
+                    name: "Home".to_string(),
+                    seconds: 1800,
+                    amount: None,
+                },
+            ],
+            billable_seconds: 3600,
+            non_billable_seconds: 1800,
+            total_seconds: 5400,
+            total_amount: Some(120.0),
+            expected_seconds: None,
+        };
+        assert_eq!(
+            report.to_csv(),
+            "kind,name,seconds,amount\n\
+             day,2021-07-01,5400,\n\
+             total,\"Client, Inc\",3600,120.00\n\
+             total,Home,1800,\n\
+             billable,,3600,\n\
+             non-billable,,1800,\n\
+             sum,,5400,120.00"
+        );
+    }
+
+    #[test]
+    fn sum_totals_by_account() {
+        let entries = vec![
+            Entry::from_str("2021-07-03T10:00:00Z 2021-07-03T13:00:00Z B").unwrap(),
+            Entry::from_str("2021-07-03T13:00:00Z 2021-07-03T14:00:00Z A").unwrap(),
+            Entry::from_str("2021-07-04T10:00:00Z 2021-07-04T10:30:00Z B").unwrap(),
+        ];
+
+        assert_eq!(
+            totals_by_account(&entries),
+            vec![
+                ("A".to_string(), Duration::hours(1)),
+                ("B".to_string(), Duration::minutes(210)),
+            ]
+        );
+    }
+
+    #[test]
+    fn match_sub_accounts() {
+        assert!(is_within_account("Client", "Client"));
+        assert!(is_within_account("Client:Project", "Client"));
+        assert!(!is_within_account("ClientB", "Client"));
+        assert!(!is_within_account("Client", "Client:Project"));
+    }
+
+    #[test]
+    fn sum_totals_by_day() {
+        let entries = vec![
+            Entry::from_str("2021-07-03T10:00:00Z 2021-07-03T13:00:00Z A").unwrap(),
+            Entry::from_str("2021-07-03T14:00:00Z 2021-07-03T15:00:00Z B").unwrap(),
+            Entry::from_str("2021-07-05T10:00:00Z 2021-07-05T10:30:00Z A").unwrap(),
+        ];
+
+        assert_eq!(
+            totals_by_day(&entries, &Utc)
+                .into_iter()
+                .collect::<Vec<_>>(),
+            vec![
+                (NaiveDate::from_ymd(2021, 7, 3), Duration::hours(4)),
+                (NaiveDate::from_ymd(2021, 7, 5), Duration::minutes(30)),
+            ]
+        );
+    }
+
+    #[test]
+    fn split_totals_at_local_midnight() {
+        let entries = vec![Entry::from_str("2021-07-03T20:00:00Z 2021-07-04T02:00:00Z A").unwrap()];
+        let tz = FixedOffset::east(2 * 3600);
+
+        assert_eq!(
+            totals_by_day(&entries, &tz).into_iter().collect::<Vec<_>>(),
+            vec![
+                (NaiveDate::from_ymd(2021, 7, 3), Duration::hours(2)),
+                (NaiveDate::from_ymd(2021, 7, 4), Duration::hours(4)),
+            ]
+        );
+    }
+}
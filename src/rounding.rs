@@ -0,0 +1,122 @@
+//! Rounding of times to a whole number of intervals, e.g. the start of entries down to 5 minutes.
+//!
+//! A rounding is written as the direction followed by the interval, e.g. `down 5m`, `up 15m` or
+//! `nearest 6m`. Intervals are counted from the Unix epoch, so intervals dividing an hour line up
+//! with the clock in every time zone with a whole-hour offset.
+
+use crate::duration::parse_duration;
+use chrono::{DateTime, Duration, Timelike, Utc};
+use serde::de::{self, Deserialize, Deserializer};
+use std::str::FromStr;
+
+/// Which way times are rounded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Down,
+    Up,
+    /// To the closest interval, up when halfway between two.
+    Nearest,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rounding {
+    pub direction: Direction,
+    pub interval: Duration,
+}
+
+impl Rounding {
+    /// Rounds the time to a whole number of intervals.
+    pub fn apply(&self, time: DateTime<Utc>) -> DateTime<Utc> {
+        let interval = match self.interval.num_nanoseconds() {
+            Some(interval) if interval > 0 => interval as i128,
+            _ => return time,
+        };
+        let nanoseconds = time.timestamp() as i128 * 1_000_000_000 + time.nanosecond() as i128;
+        let remainder = nanoseconds.rem_euclid(interval);
+        let down = nanoseconds - remainder;
+        let rounded = match self.direction {
+            Direction::Down => down,
+            Direction::Up if remainder == 0 => down,
+            Direction::Up => down + interval,
+            Direction::Nearest if remainder * 2 < interval => down,
+            Direction::Nearest => down + interval,
+        };
+        time + Duration::nanoseconds((rounded - nanoseconds) as i64)
+    }
+}
+
+impl FromStr for Rounding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!(r#"invalid rounding "{}", expected e.g. "down 5m""#, s);
+        let (direction, interval) = s.trim().split_once(' ').ok_or_else(invalid)?;
+        let direction = match direction {
+            "down" => Direction::Down,
+            "up" => Direction::Up,
+            "nearest" => Direction::Nearest,
+            _ => return Err(invalid()),
+        };
+        let interval = parse_duration(interval)?;
+        if interval <= Duration::zero() {
+            return Err(invalid());
+        }
+        Ok(Rounding {
+            direction,
+            interval,
+        })
+    }
+}
+
+/// Reads an optional rounding in a config file, written like `Rounding::from_str` expects.
+pub fn deserialize_optional<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Rounding>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|s| s.parse().map_err(de::Error::custom))
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(s: &str) -> DateTime<Utc> {
+        DateTime::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn round_times() {
+        let down = Rounding::from_str("down 5m").unwrap();
+        let up = Rounding::from_str("up 5m").unwrap();
+        let nearest = Rounding::from_str("nearest 15m").unwrap();
+
+        assert_eq!(
+            down.apply(time("2021-07-03T10:04:59.5Z")),
+            time("2021-07-03T10:00:00Z")
+        );
+        assert_eq!(
+            up.apply(time("2021-07-03T10:00:01Z")),
+            time("2021-07-03T10:05:00Z")
+        );
+        assert_eq!(
+            up.apply(time("2021-07-03T10:05:00Z")),
+            time("2021-07-03T10:05:00Z")
+        );
+        assert_eq!(
+            nearest.apply(time("2021-07-03T10:07:29Z")),
+            time("2021-07-03T10:00:00Z")
+        );
+        assert_eq!(
+            nearest.apply(time("2021-07-03T10:07:30Z")),
+            time("2021-07-03T10:15:00Z")
+        );
+        assert_eq!(
+            down.apply(time("1969-12-31T23:59:00Z")),
+            time("1969-12-31T23:55:00Z")
+        );
+        assert!(Rounding::from_str("sideways 5m").is_err());
+        assert!(Rounding::from_str("down 0m").is_err());
+        assert!(Rounding::from_str("down").is_err());
+    }
+}
@@ -0,0 +1,144 @@
+//! Recurring entries, like a daily standup, which are recorded for the days they happened on.
+//!
+//! Each is configured under `[schedule.<name>]`, e.g.
+//!
+//! ```toml
+//! [schedule.standup]
+//! account = "Meetings"
+//! time = "09:30-09:45"
+//! days = ["mon", "tue", "wed", "thu", "fri"]
+//! ```
+//!
+//! Without `days` they happen on the work days of the calendar. Either way they are skipped on
+//! holidays.
+
+use crate::calendar::CalendarConfig;
+use crate::reminders::WorkHours;
+use crate::Entry;
+use chrono::{Datelike, NaiveDate, TimeZone, Utc, Weekday};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Recurring {
+    pub account: String,
+    /// The local time of day it takes place, e.g. `09:30-09:45`.
+    pub time: WorkHours,
+    /// The days of the week it takes place on, by default the work days of the calendar.
+    pub days: Option<Vec<String>>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub billable: bool,
+    /// The description of the entries, by default the name of the recurring entry.
+    pub description: Option<String>,
+}
+
+impl Recurring {
+    /// Whether it takes place on the date.
+    pub fn is_on(&self, date: NaiveDate, calendar: &CalendarConfig) -> bool {
+        match &self.days {
+            Some(days) => {
+                days.iter()
+                    .any(|day| Weekday::from_str(day) == Ok(date.weekday()))
+                    && calendar.holiday(date).is_none()
+            }
+            None => calendar.is_work_day(date),
+        }
+    }
+}
+
+/// The entries of the recurring entries on the dates, in the time zone, ordered by when they
+/// start. Times which do not exist in the time zone, in daylight saving changes, are skipped.
+pub fn entries<Tz: TimeZone>(
+    schedule: &BTreeMap<String, Recurring>,
+    calendar: &CalendarConfig,
+    dates: RangeInclusive<NaiveDate>,
+    tz: &Tz,
+) -> Vec<Entry> {
+    let mut entries = Vec::new();
+    let (mut date, last) = dates.into_inner();
+    while date <= last {
+        for (name, recurring) in schedule {
+            if !recurring.is_on(date, calendar) {
+                continue;
+            }
+            let time = |time| {
+                tz.from_local_datetime(&date.and_time(time))
+                    .earliest()
+                    .map(|time| time.with_timezone(&Utc))
+            };
+            if let (Some(start), Some(stop)) =
+                (time(recurring.time.start), time(recurring.time.end))
+            {
+                entries.push(Entry {
+                    start,
+                    stop,
+                    account: recurring.account.as_str().into(),
+                    tags: recurring.tags.clone(),
+                    billable: recurring.billable,
+                    category: None,
+                    description: Some(
+                        recurring
+                            .description
+                            .clone()
+                            .unwrap_or_else(|| name.clone()),
+                    ),
+                    meta: BTreeMap::new(),
+                });
+            }
+        }
+        date = date.succ();
+    }
+    entries.sort_by_key(|entry| entry.start);
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recurring_entries() {
+        let schedule: BTreeMap<String, Recurring> = toml::from_str(
+            r#"
+            [standup]
+            account = "Meetings"
+            time = "09:30-09:45"
+
+            [review]
+            account = "Meetings"
+            time = "14:00-15:00"
+            days = ["fri", "sat"]
+            tags = ["review"]
+            description = "Weekly review"
+            "#,
+        )
+        .unwrap();
+        let calendar = CalendarConfig {
+            holidays: vec![NaiveDate::from_ymd(2021, 7, 2)],
+            ..CalendarConfig::default()
+        };
+        let date = |day| NaiveDate::from_ymd(2021, 7, day);
+
+        let lines: Vec<String> = entries(&schedule, &calendar, date(1)..=date(5), &Utc)
+            .iter()
+            .map(|entry| entry.to_string())
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                "2021-07-01T09:30:00Z 2021-07-01T09:45:00Z Meetings ; standup",
+                "2021-07-03T14:00:00Z 2021-07-03T15:00:00Z Meetings #review ; Weekly review",
+                "2021-07-05T09:30:00Z 2021-07-05T09:45:00Z Meetings ; standup",
+            ]
+        );
+        assert!(toml::from_str::<BTreeMap<String, Recurring>>(
+            "[standup]\naccount = \"A\"\ntime = \"09:30-09:45\"\nday = [\"mon\"]"
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,459 @@
+//! JSON Schemas of the JSON that tt writes, for validating it and generating code against it.
+//!
+//! Each serialized type describes itself with `Schema`, next to which the tests check that what
+//! the type serializes to matches its schema, so the two cannot drift apart.
+
+use crate::report::{AccountTotal, PeriodReport};
+use crate::status::Status;
+use crate::{Entry, RunningEntry};
+use serde_json::{json, Value};
+
+/// The version of JSON Schema the schemas are written in.
+const DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// A type serialized to JSON which can describe what it serializes to.
+pub trait Schema {
+    fn schema() -> Value;
+}
+
+/// The names of the schemas, with what they describe.
+pub const NAMES: [(&str, &str); 7] = [
+    (
+        "entry",
+        "an entry, as printed by stop and trim with --porcelain",
+    ),
+    (
+        "entries",
+        "a list of entries, as exported with --format json and served at GET /entries",
+    ),
+    (
+        "running-entry",
+        "a running entry, as printed by start with --porcelain",
+    ),
+    (
+        "running",
+        "a list of running entries, as served at GET /running",
+    ),
+    (
+        "report",
+        "the total of each account, as served at GET /report",
+    ),
+    (
+        "period-report",
+        "the report of a period, as printed by report with --format json",
+    ),
+    (
+        "status",
+        "the running entries, today's total, exceeded goals and budgets at risk, as printed by \
+         status with --format json",
+    ),
+];
+
+/// The schema document with the name, see `NAMES`.
+pub fn document(name: &str) -> Option<Value> {
+    let (schema, description) = match name {
+        "entry" => (Entry::schema(), NAMES[0].1),
+        "entries" => (array::<Entry>(), NAMES[1].1),
+        "running-entry" => (RunningEntry::schema(), NAMES[2].1),
+        "running" => (array::<RunningEntry>(), NAMES[3].1),
+        "report" => (array::<AccountTotal>(), NAMES[4].1),
+        "period-report" => (PeriodReport::schema(), NAMES[5].1),
+        "status" => (Status::schema(), NAMES[6].1),
+        _ => return None,
+    };
+    let mut document = json!({
+        "$schema": DIALECT,
+        "title": format!("timetracker {}", name),
+        "description": capitalize(description),
+    });
+    if let (Value::Object(document), Value::Object(schema)) = (&mut document, schema) {
+        document.extend(schema);
+    }
+    Some(document)
+}
+
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+fn array<T: Schema>() -> Value {
+    json!({ "type": "array", "items": T::schema() })
+}
+
+fn time() -> Value {
+    json!({
+        "type": "string",
+        "format": "date-time",
+        "description": "In UTC, e.g. 2021-07-03T10:00:00Z",
+    })
+}
+
+fn tags() -> Value {
+    json!({ "type": "array", "items": { "type": "string" } })
+}
+
+fn description() -> Value {
+    json!({ "type": ["string", "null"] })
+}
+
+fn meta() -> Value {
+    json!({
+        "type": "object",
+        "additionalProperties": { "type": "string" },
+        "description": "The metadata values by their keys, e.g. {\"ticket\": \"PROJ-12\"}",
+    })
+}
+
+impl Schema for Entry {
+    fn schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "start": time(),
+                "stop": time(),
+                "account": { "type": "string" },
+                "tags": tags(),
+                "billable": { "type": "boolean" },
+                "category": { "type": ["string", "null"] },
+                "description": description(),
+                "meta": meta(),
+            },
+            "required": [
+                "start",
+                "stop",
+                "account",
+                "tags",
+                "billable",
+                "category",
+                "description",
+                "meta",
+            ],
+            "additionalProperties": false,
+        })
+    }
+}
+
+impl Schema for RunningEntry {
+    fn schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "start": time(),
+                "account": { "type": "string" },
+                "tags": tags(),
+                "billable": { "type": "boolean" },
+                "category": { "type": ["string", "null"] },
+                "description": description(),
+                "meta": meta(),
+            },
+            "required": [
+                "start",
+                "account",
+                "tags",
+                "billable",
+                "category",
+                "description",
+                "meta",
+            ],
+            "additionalProperties": false,
+        })
+    }
+}
+
+impl Schema for AccountTotal {
+    fn schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "account": { "type": "string" },
+                "seconds": { "type": "integer" },
+                "duration": {
+                    "type": "string",
+                    "description": "The total in the format asked for",
+                },
+            },
+            "required": ["account", "seconds"],
+            "additionalProperties": false,
+        })
+    }
+}
+
+impl Schema for PeriodReport {
+    fn schema() -> Value {
+        let seconds = json!({ "type": "integer" });
+        let optional_time = json!({
+            "type": ["string", "null"],
+            "format": "date-time",
+            "description": "In UTC, null for no bound",
+        });
+        json!({
+            "type": "object",
+            "properties": {
+                "zone": { "type": "string" },
+                "since": optional_time,
+                "until": optional_time,
+                "days": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "date": { "type": "string", "format": "date" },
+                            "seconds": seconds,
+                        },
+                        "required": ["date", "seconds"],
+                        "additionalProperties": false,
+                    },
+                },
+                "totals": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "name": { "type": "string" },
+                            "seconds": seconds,
+                            "amount": { "type": ["number", "null"] },
+                        },
+                        "required": ["name", "seconds", "amount"],
+                        "additionalProperties": false,
+                    },
+                },
+                "billable_seconds": seconds,
+                "non_billable_seconds": seconds,
+                "total_seconds": seconds,
+                "total_amount": { "type": ["number", "null"] },
+                "expected_seconds": { "type": ["integer", "null"] },
+            },
+            "required": [
+                "zone",
+                "since",
+                "until",
+                "days",
+                "totals",
+                "billable_seconds",
+                "non_billable_seconds",
+                "total_seconds",
+                "total_amount",
+                "expected_seconds",
+            ],
+            "additionalProperties": false,
+        })
+    }
+}
+
+impl Schema for Status {
+    fn schema() -> Value {
+        let seconds = json!({ "type": "integer" });
+        let date = json!({ "type": "string", "format": "date" });
+        json!({
+            "type": "object",
+            "properties": {
+                "running": array::<RunningEntry>(),
+                "today_seconds": seconds,
+                "exceeded_goals": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "scope": {
+                                "type": "string",
+                                "description": "The account, or the tag after a #",
+                            },
+                            "period": { "type": "string", "enum": ["weekly", "monthly"] },
+                            "tracked_seconds": seconds,
+                            "target_seconds": seconds,
+                        },
+                        "required": ["scope", "period", "tracked_seconds", "target_seconds"],
+                        "additionalProperties": false,
+                    },
+                },
+                "budgets_at_risk": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "account": { "type": "string" },
+                            "budget_seconds": seconds,
+                            "deadline": date,
+                            "runs_out": date,
+                        },
+                        "required": ["account", "budget_seconds", "deadline", "runs_out"],
+                        "additionalProperties": false,
+                    },
+                },
+            },
+            "required": ["running", "today_seconds", "exceeded_goals", "budgets_at_risk"],
+            "additionalProperties": false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::burn::Burn;
+    use crate::goals::{Goal, Period, Progress, Scope};
+    use crate::report::{DayTotal, GroupTotal};
+    use chrono::{DateTime, Duration, NaiveDate};
+    use serde::Serialize;
+    use std::str::FromStr;
+
+    /// Checks the parts of JSON Schema the schemas use: types, enums, the formats of dates and
+    /// times, properties, additional properties and items.
+    fn matches(value: &Value, schema: &Value) -> bool {
+        let type_name = match value {
+            Value::Null => "null",
+            Value::Bool(_) => "boolean",
+            Value::Number(number) if number.is_i64() || number.is_u64() => "integer",
+            Value::Number(_) => "number",
+            Value::String(_) => "string",
+            Value::Array(_) => "array",
+            Value::Object(_) => "object",
+        };
+        let types = match &schema["type"] {
+            Value::Array(types) => types.clone(),
+            single => vec![single.clone()],
+        };
+        if !types.iter().any(|t| t == type_name) {
+            return false;
+        }
+        if let Some(values) = schema["enum"].as_array() {
+            if !values.contains(value) {
+                return false;
+            }
+        }
+        let formatted = match (value, schema["format"].as_str()) {
+            (Value::String(s), Some("date-time")) => DateTime::parse_from_rfc3339(s).is_ok(),
+            (Value::String(s), Some("date")) => NaiveDate::from_str(s).is_ok(),
+            _ => true,
+        };
+        if !formatted {
+            return false;
+        }
+        match value {
+            Value::Array(items) => items.iter().all(|item| matches(item, &schema["items"])),
+            Value::Object(object) if schema["properties"].is_null() => object
+                .values()
+                .all(|value| matches(value, &schema["additionalProperties"])),
+            Value::Object(object) => {
+                let required = schema["required"].as_array().unwrap();
+                required
+                    .iter()
+                    .all(|key| object.contains_key(key.as_str().unwrap()))
+                    && object.iter().all(|(key, value)| {
+                        schema["properties"]
+                            .get(key)
+                            .is_some_and(|property| matches(value, property))
+                    })
+            }
+            _ => true,
+        }
+    }
+
+    fn serialized(value: impl Serialize) -> Value {
+        serde_json::to_value(value).unwrap()
+    }
+
+    #[test]
+    fn serialized_values_match_their_schemas() {
+        let entries = vec![
+            Entry::from_str(
+                "2021-07-03T10:00:00Z 2021-07-03T11:30:00Z Client $ #dev ticket=PROJ-12 ; Fix",
+            )
+            .unwrap(),
+            Entry::from_str("2021-07-03T12:00:00Z 2021-07-03T12:15:00Z Other").unwrap(),
+        ];
+        let running =
+            RunningEntry::from_str("2021-07-03T13:00:00Z Client $ #dev ticket=PROJ-12 ; Fix")
+                .unwrap();
+        let entries = [
+            entries,
+            vec![Entry::builder()
+                .start(running.start)
+                .stop(running.start + Duration::minutes(5))
+                .account("Internal".to_string())
+                .category("meetings".to_string())
+                .build()
+                .unwrap()],
+        ]
+        .concat();
+        let totals = vec![
+            AccountTotal {
+                account: "Client".to_string(),
+                seconds: 5400,
+                duration: Some("1.50".to_string()),
+            },
+            AccountTotal {
+                account: "Other".to_string(),
+                seconds: 900,
+                duration: None,
+            },
+        ];
+
+        let schema = |name| document(name).unwrap();
+        assert!(matches(&serialized(&entries[0]), &schema("entry")));
+        assert!(matches(&serialized(&entries), &schema("entries")));
+        assert!(matches(&serialized(&running), &schema("running-entry")));
+        assert!(matches(
+            &serialized(vec![running.clone()]),
+            &schema("running")
+        ));
+        assert!(matches(&serialized(&totals), &schema("report")));
+        let report = PeriodReport {
+            zone: "local".to_string(),
+            since: Some(entries[0].start),
+            until: None,
+            days: vec![DayTotal {
+                date: NaiveDate::from_ymd(2021, 7, 3),
+                seconds: 6300,
+            }],
+            totals: vec![GroupTotal {
+                name: "Client".to_string(),
+                seconds: 5400,
+                amount: Some(180.0),
+            }],
+            billable_seconds: 5400,
+            non_billable_seconds: 900,
+            total_seconds: 6300,
+            total_amount: Some(180.0),
+            expected_seconds: None,
+        };
+        assert!(matches(&serialized(&report), &schema("period-report")));
+        let deadline = NaiveDate::from_ymd_opt(2021, 7, 31).unwrap();
+        let status = Status::new(
+            vec![running],
+            Duration::minutes(105),
+            &[Progress {
+                goal: Goal {
+                    scope: Scope::Tag("dev".to_string()),
+                    period: Period::Week,
+                    target: Duration::hours(1),
+                },
+                start: NaiveDate::from_ymd_opt(2021, 6, 28).unwrap(),
+                end: NaiveDate::from_ymd_opt(2021, 7, 5).unwrap(),
+                tracked: Duration::minutes(90),
+                expected: Duration::minutes(30),
+            }],
+            &[Burn {
+                account: "Client".to_string(),
+                budget: Duration::hours(10),
+                deadline,
+                used: Duration::hours(8),
+                rate: Duration::hours(1),
+                runs_out: deadline.pred_opt(),
+            }],
+        );
+        assert_eq!(status.exceeded_goals.len(), 1);
+        assert_eq!(status.budgets_at_risk.len(), 1);
+        assert!(matches(&serialized(&status), &schema("status")));
+        let mut wrong_period = serialized(&status);
+        wrong_period["exceeded_goals"][0]["period"] = json!("daily");
+        assert!(!matches(&wrong_period, &schema("status")));
+        assert!(!matches(&serialized(&entries[0]), &schema("running-entry")));
+        assert!(NAMES.iter().all(|(name, _)| document(name).is_some()));
+        assert_eq!(document("unknown"), None);
+    }
+}
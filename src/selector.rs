@@ -0,0 +1,133 @@
+//! Picking out a single completed entry on the command line, either the last one or one by its
+//! ID, see `Entry::id`.
+
+use crate::Entry;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// The fewest characters of an ID that select an entry by it.
+const MIN_ID_LENGTH: usize = 4;
+
+/// An entry given on the command line: `last` for the one starting last, or the start of its ID,
+/// like `3f2a`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Selector {
+    Last,
+    Id(String),
+}
+
+impl FromStr for Selector {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "last" {
+            return Ok(Selector::Last);
+        }
+        if (MIN_ID_LENGTH..=16).contains(&s.len()) && s.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Ok(Selector::Id(s.to_ascii_lowercase()));
+        }
+        Err(format!(
+            "expected `last` or the ID of an entry, at least its first {} characters",
+            MIN_ID_LENGTH
+        ))
+    }
+}
+
+impl Selector {
+    /// The selected entry of the entries.
+    pub fn find<'a>(&self, entries: &'a [Entry]) -> Result<&'a Entry, SelectError> {
+        self.position(entries).map(|position| &entries[position])
+    }
+
+    /// The position of the selected entry among the entries, for changing it where it is.
+    pub fn position<'a>(
+        &self,
+        entries: impl IntoIterator<Item = &'a Entry>,
+    ) -> Result<usize, SelectError> {
+        let entries = entries.into_iter().enumerate();
+        match self {
+            Selector::Last => entries
+                .max_by_key(|(_, entry)| (entry.start, entry.stop))
+                .map(|(position, _)| position)
+                .ok_or(SelectError::NoEntries),
+            Selector::Id(prefix) => {
+                let matching: Vec<(usize, &Entry)> = entries
+                    .filter(|(_, entry)| entry.id().starts_with(prefix.as_str()))
+                    .collect();
+                match matching.as_slice() {
+                    [(position, _)] => Ok(*position),
+                    [] => Err(SelectError::NotFound(prefix.clone())),
+                    _ => Err(SelectError::Ambiguous(
+                        prefix.clone(),
+                        matching.iter().map(|(_, entry)| entry.id()).collect(),
+                    )),
+                }
+            }
+        }
+    }
+}
+
+/// No single entry is selected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SelectError {
+    NoEntries,
+    NotFound(String),
+    /// The start of an ID shared by the IDs of several entries.
+    Ambiguous(String, Vec<String>),
+}
+
+impl fmt::Display for SelectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SelectError::NoEntries => write!(f, "there are no entries"),
+            SelectError::NotFound(id) => write!(f, "there is no entry with the ID {}", id),
+            SelectError::Ambiguous(id, ids) => write!(
+                f,
+                "{} is the start of the IDs of several entries: {}",
+                id,
+                ids.join(", ")
+            ),
+        }
+    }
+}
+
+impl Error for SelectError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn select_entries() {
+        let entries: Vec<Entry> = [
+            "2021-07-03T12:00:00Z 2021-07-03T13:00:00Z B",
+            "2021-07-03T10:00:00Z 2021-07-03T11:00:00Z A",
+        ]
+        .iter()
+        .map(|line| line.parse().unwrap())
+        .collect();
+        let id = entries[1].id();
+
+        assert_eq!(
+            Selector::from_str("last").unwrap().find(&entries),
+            Ok(&entries[0])
+        );
+        assert_eq!(
+            Selector::from_str(&id[..6].to_uppercase())
+                .unwrap()
+                .find(&entries),
+            Ok(&entries[1])
+        );
+        assert_eq!(
+            Selector::Id("".to_string()).find(&entries),
+            Err(SelectError::Ambiguous(
+                "".to_string(),
+                vec![entries[0].id(), id.clone()]
+            ))
+        );
+        assert_eq!(Selector::Last.find(&[]), Err(SelectError::NoEntries));
+        assert!(Selector::from_str("abc").is_err());
+        assert!(Selector::from_str("first").is_err());
+    }
+}
@@ -0,0 +1,271 @@
+//! HTTP API exposing the entries and running entries as JSON, and as an iCalendar feed, with a
+//! read-only dashboard at `/`.
+
+use chrono::{Duration, Local};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::error::Error;
+use timetracker::accounts::Color;
+use timetracker::dashboard;
+use timetracker::duration::DurationFormat;
+use timetracker::export::{EntryFormatter, IcsFeed};
+use timetracker::report::{self, AccountTotal};
+use timetracker::storage::{Storage, StorageError};
+use timetracker::timesheet::Timesheet;
+use timetracker::{is_valid_tag, now, RunningEntry};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+/// The longest time between calls to the function given to `serve` while no requests come in.
+const TICK: std::time::Duration = std::time::Duration::from_secs(30);
+
+#[derive(Debug, Deserialize)]
+struct StartRequest {
+    account: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    billable: bool,
+    #[serde(default)]
+    category: Option<String>,
+    #[serde(default)]
+    description: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct StopRequest {
+    account: Option<String>,
+}
+
+/// The body of a response.
+#[derive(Debug)]
+enum Body {
+    Json(Value),
+    Calendar(String),
+    Html(String),
+}
+
+/// An error response with a status code and a message for the client.
+#[derive(Debug)]
+struct ApiError {
+    status: u16,
+    message: String,
+}
+
+impl ApiError {
+    fn new(status: u16, message: impl ToString) -> Self {
+        ApiError {
+            status,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl From<StorageError> for ApiError {
+    fn from(err: StorageError) -> Self {
+        let status = match err {
+            StorageError::AlreadyRunning(_) | StorageError::Overlap(_) => 409,
+            StorageError::NotRunning(_)
+            | StorageError::NoRunningEntries
+            | StorageError::NoEntries(_) => 404,
+            StorageError::AccountRequired
+            | StorageError::OutsideEntry(_)
+            | StorageError::ZeroDuration
+            | StorageError::Invalid(_) => 400,
+            StorageError::ReadOnly => 403,
+            _ => 500,
+        };
+        ApiError::new(status, err)
+    }
+}
+
+/// Serves the API on the given address until the process is terminated, with the accounts in
+/// the colors on the dashboard, recording the user on the entries it starts. The given function
+/// is called between requests, at least every `TICK`.
+pub fn serve(
+    address: &str,
+    storage: &Storage,
+    colors: &BTreeMap<String, Color>,
+    user: Option<String>,
+    tick: impl Fn(),
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let server = Server::http(address)?;
+    log::info!("listening on {}", address);
+
+    loop {
+        tick();
+        let mut request = match server.recv_timeout(TICK)? {
+            Some(request) => request,
+            None => continue,
+        };
+        let (status, body) = match route(&mut request, storage, colors, &user) {
+            Ok(response) => response,
+            Err(err) => (err.status, Body::Json(json!({ "error": err.message }))),
+        };
+        log::info!("{} {} {}", request.method(), request.url(), status);
+
+        let (content_type, body) = match body {
+            Body::Json(value) => ("application/json", value.to_string()),
+            Body::Calendar(calendar) => ("text/calendar; charset=utf-8", calendar),
+            Body::Html(html) => ("text/html; charset=utf-8", html),
+        };
+        let header = Header::from_bytes("Content-Type", content_type).unwrap();
+        let response = Response::from_string(body)
+            .with_status_code(status)
+            .with_header(header);
+        if let Err(err) = request.respond(response) {
+            log::warn!("could not send response: {}", err);
+        }
+    }
+}
+
+fn route(
+    request: &mut Request,
+    storage: &Storage,
+    colors: &BTreeMap<String, Color>,
+    user: &Option<String>,
+) -> Result<(u16, Body), ApiError> {
+    let path = request.url().split('?').next().unwrap_or("").to_string();
+    match (request.method(), path.as_str()) {
+        // The entries of the last eight days cover the current week
+        (Method::Get, "/") => {
+            let now = now();
+            let entries = storage.entries_since(now - Duration::days(8), |_| true)?;
+            let running = storage.running_entries()?;
+            let html = dashboard::render(&running, &entries, now, &Local, colors);
+            Ok((200, Body::Html(html)))
+        }
+
+        (Method::Get, "/entries") => Ok((200, to_json(storage.entries()?)?)),
+
+        (Method::Get, "/running") => Ok((200, to_json(storage.running_entries()?)?)),
+
+        (Method::Post, "/running") => {
+            let body: StartRequest = read_json(request)?;
+            if let Some(tag) = body.tags.iter().find(|tag| !is_valid_tag(tag)) {
+                return Err(ApiError::new(400, format!(r#"invalid tag "{}""#, tag)));
+            }
+            let running_entry = RunningEntry::builder()
+                .start(now())
+                .account(body.account)
+                .tags(body.tags)
+                .billable(body.billable)
+                .category_if_any(body.category)
+                .description_if_any(body.description)
+                .user_if_any(user.clone())
+                .build()
+                .map_err(StorageError::from)?;
+            let running_entry = storage.start(running_entry)?;
+            Ok((201, to_json(running_entry)?))
+        }
+
+        (Method::Post, "/running/stop") => {
+            let body: StopRequest = read_json(request)?;
+            let entry = storage.stop(body.account.as_deref(), now())?;
+            Ok((200, to_json(entry)?))
+        }
+
+        (Method::Get, "/report") => {
+            let duration_format = query_parameter(request.url(), "duration_format")
+                .map(|format| format.parse::<DurationFormat>())
+                .transpose()
+                .map_err(|err| ApiError::new(400, err))?;
+            let totals: Vec<AccountTotal> = report::totals_by_account(&storage.entries()?)
+                .into_iter()
+                .map(|(account, total)| AccountTotal {
+                    account,
+                    seconds: total.num_seconds(),
+                    duration: duration_format.map(|format| format.format(total)),
+                })
+                .collect();
+            Ok((200, to_json(totals)?))
+        }
+
+        // A feed of the entries of the last `days` days, 30 by default, and the running entries
+        (Method::Get, "/calendar.ics") => {
+            let days = query_parameter(request.url(), "days")
+                .map(|days| days.parse::<u32>())
+                .transpose()
+                .map_err(|err| ApiError::new(400, format!("invalid days: {}", err)))?
+                .unwrap_or(30);
+            let now = now();
+            let since = now - Duration::days(days.into());
+            let timesheet = Timesheet::new(storage.entries_since(since, |_| true)?);
+            let feed = IcsFeed {
+                running: storage.running_entries()?,
+                now,
+            };
+            Ok((200, Body::Calendar(feed.format(&timesheet))))
+        }
+
+        (_, "/")
+        | (_, "/entries")
+        | (_, "/running")
+        | (_, "/running/stop")
+        | (_, "/report")
+        | (_, "/calendar.ics") => Err(ApiError::new(405, "method not allowed")),
+
+        _ => Err(ApiError::new(404, "not found")),
+    }
+}
+
+/// Finds the value of a query parameter in the URL, without decoding it.
+fn query_parameter<'a>(url: &'a str, name: &str) -> Option<&'a str> {
+    let (_, query) = url.split_once('?')?;
+    query
+        .split('&')
+        .find_map(|pair| match pair.split_once('=') {
+            Some((key, value)) if key == name => Some(value),
+            _ => None,
+        })
+}
+
+/// Reads the request body as JSON, treating an empty body as an empty object.
+fn read_json<T: for<'de> Deserialize<'de>>(request: &mut Request) -> Result<T, ApiError> {
+    let mut body = String::new();
+    request
+        .as_reader()
+        .read_to_string(&mut body)
+        .map_err(|err| ApiError::new(400, err))?;
+    if body.trim().is_empty() {
+        body = "{}".to_string();
+    }
+    serde_json::from_str(&body).map_err(|err| ApiError::new(400, err))
+}
+
+fn to_json<T: Serialize>(value: T) -> Result<Body, ApiError> {
+    serde_json::to_value(value)
+        .map(Body::Json)
+        .map_err(|err| ApiError::new(500, err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn respond_to_storage_errors_with_their_status() {
+        let status = |err| ApiError::from(err).status;
+        assert_eq!(status(StorageError::AlreadyRunning("dev".to_string())), 409);
+        assert_eq!(status(StorageError::NotRunning("dev".to_string())), 404);
+        assert_eq!(status(StorageError::NoRunningEntries), 404);
+        assert_eq!(status(StorageError::AccountRequired), 400);
+        assert_eq!(status(StorageError::ZeroDuration), 400);
+        assert_eq!(status(StorageError::ReadOnly), 403);
+        assert_eq!(status(StorageError::WriteToStdin), 500);
+
+        let err = ApiError::from(StorageError::NoRunningEntries);
+        assert_eq!(err.message, StorageError::NoRunningEntries.to_string());
+    }
+
+    #[test]
+    fn find_query_parameters() {
+        let url = "/report?days=7&duration_format=decimal&empty=";
+        assert_eq!(query_parameter(url, "days"), Some("7"));
+        assert_eq!(query_parameter(url, "duration_format"), Some("decimal"));
+        assert_eq!(query_parameter(url, "empty"), Some(""));
+        assert_eq!(query_parameter(url, "day"), None);
+        assert_eq!(query_parameter("/report", "days"), None);
+    }
+}
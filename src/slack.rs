@@ -0,0 +1,74 @@
+//! Setting the Slack status to what is being worked on through the Slack Web API.
+
+use serde::Deserialize;
+use serde_json::json;
+use std::env;
+use std::error::Error;
+use timetracker::config::{SlackConfig, SlackStatus};
+use timetracker::RunningEntry;
+
+const PROFILE_SET_URL: &str = "https://slack.com/api/users.profile.set";
+
+/// The response of the Slack Web API, which reports errors with a successful status code.
+#[derive(Debug, Deserialize)]
+struct ApiResponse {
+    ok: bool,
+    error: Option<String>,
+}
+
+/// Finds the status for the running entry started last which has one.
+pub fn status_for<'a>(
+    config: &'a SlackConfig,
+    running_entries: &[RunningEntry],
+) -> Option<&'a SlackStatus> {
+    let mut running_entries: Vec<&RunningEntry> = running_entries.iter().collect();
+    running_entries.sort_by_key(|entry| entry.start);
+    running_entries
+        .iter()
+        .rev()
+        .find_map(|entry| config.status_for(&entry.account))
+}
+
+/// Sets the status for the running entries, or clears it if none of them have one, and returns
+/// the status that was set.
+pub fn update_status<'a>(
+    config: &'a SlackConfig,
+    running_entries: &[RunningEntry],
+    dry_run: bool,
+) -> Result<Option<&'a SlackStatus>, Box<dyn Error + Send + Sync>> {
+    let status = status_for(config, running_entries);
+    if dry_run {
+        match status {
+            Some(status) => println!(
+                "Would set the Slack status {} {}",
+                status.emoji, status.text
+            ),
+            None => println!("Would clear the Slack status"),
+        }
+        return Ok(status);
+    }
+
+    let token = env::var("SLACK_TOKEN")
+        .ok()
+        .or_else(|| config.token.clone())
+        .ok_or("no Slack token is configured")?;
+    let body = json!({
+        "profile": {
+            "status_text": status.map_or("", |status| status.text.as_str()),
+            "status_emoji": status.map_or("", |status| status.emoji.as_str()),
+            "status_expiration": 0,
+        }
+    });
+    log::debug!("setting the Slack status {}", body);
+    let response: ApiResponse = ureq::post(PROFILE_SET_URL)
+        .set("Authorization", &format!("Bearer {}", token))
+        .send_json(body)?
+        .into_json()?;
+    if !response.ok {
+        return Err(response
+            .error
+            .unwrap_or_else(|| "unknown error".to_string())
+            .into());
+    }
+    Ok(status)
+}
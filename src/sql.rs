@@ -0,0 +1,210 @@
+//! Running SQL queries against the entries, for reports the other commands do not make. The
+//! entries are loaded into the tables of an in-memory SQLite database, so queries cannot change
+//! the data files.
+//!
+//! The `entries` table has a row for each entry with the columns `id`, `start` and `stop` in RFC
+//! 3339 in UTC, `seconds`, `account`, `billable` as 0 or 1, `category`, `tags` separated by spaces
+//! and `description`. The `tags` table has a row with the `id` and the `tag` for each tag of each
+//! entry, for joining on.
+
+use crate::export::csv_field;
+use crate::{format_datetime, Entry};
+use rusqlite::types::ValueRef;
+use rusqlite::{params, Connection};
+use std::error::Error;
+use std::fmt;
+
+const SCHEMA: &str = "
+    CREATE TABLE entries (
+        id TEXT NOT NULL,
+        start TEXT NOT NULL,
+        stop TEXT NOT NULL,
+        seconds INTEGER NOT NULL,
+        account TEXT NOT NULL,
+        billable INTEGER NOT NULL,
+        category TEXT,
+        tags TEXT NOT NULL,
+        description TEXT
+    );
+    CREATE TABLE tags (id TEXT NOT NULL, tag TEXT NOT NULL);
+";
+
+/// The columns and rows a query returns, with the values as text and `NULL` as an empty string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Table {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    /// Comma-separated values with a header row of the column names.
+    pub fn to_csv(&self) -> String {
+        std::iter::once(&self.columns)
+            .chain(&self.rows)
+            .map(|row| {
+                row.iter()
+                    .map(|value| csv_field(value))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Runs the query against the entries.
+pub fn query(entries: &[Entry], sql: &str) -> Result<Table, SqlError> {
+    let mut connection = Connection::open_in_memory()?;
+    connection.execute_batch(SCHEMA)?;
+    let transaction = connection.transaction()?;
+    {
+        let mut insert_entry =
+            transaction.prepare("INSERT INTO entries VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)")?;
+        let mut insert_tag = transaction.prepare("INSERT INTO tags VALUES (?, ?)")?;
+        for entry in entries {
+            let id = entry.id();
+            insert_entry.execute(params![
+                id,
+                format_datetime(&entry.start),
+                format_datetime(&entry.stop),
+                (entry.stop - entry.start).num_seconds(),
+                &*entry.account,
+                entry.billable,
+                entry.category,
+                entry.tags.join(" "),
+                entry.description,
+            ])?;
+            for tag in &entry.tags {
+                insert_tag.execute(params![id, tag])?;
+            }
+        }
+    }
+    transaction.commit()?;
+    connection.execute_batch("PRAGMA query_only = ON")?;
+
+    let mut statement = connection.prepare(sql)?;
+    if !statement.readonly() {
+        return Err(SqlError::NotReadOnly);
+    }
+    let columns: Vec<String> = statement
+        .column_names()
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+    let mut rows = Vec::new();
+    let mut results = statement.query([])?;
+    while let Some(row) = results.next()? {
+        let values = (0..columns.len())
+            .map(|index| {
+                Ok(match row.get_ref(index)? {
+                    ValueRef::Null => String::new(),
+                    ValueRef::Integer(value) => value.to_string(),
+                    ValueRef::Real(value) => value.to_string(),
+                    ValueRef::Text(text) | ValueRef::Blob(text) => {
+                        String::from_utf8_lossy(text).into_owned()
+                    }
+                })
+            })
+            .collect::<Result<_, rusqlite::Error>>()?;
+        rows.push(values);
+    }
+    Ok(Table { columns, rows })
+}
+
+#[derive(Debug)]
+pub enum SqlError {
+    Sqlite(rusqlite::Error),
+    /// The statement would change the tables, only queries reading them are run.
+    NotReadOnly,
+}
+
+impl From<rusqlite::Error> for SqlError {
+    fn from(err: rusqlite::Error) -> Self {
+        SqlError::Sqlite(err)
+    }
+}
+
+impl fmt::Display for SqlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SqlError::Sqlite(err) => write!(f, "{}", err),
+            SqlError::NotReadOnly => write!(f, "only queries reading the entries can be run"),
+        }
+    }
+}
+
+impl Error for SqlError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SqlError::Sqlite(err) => Some(err),
+            SqlError::NotReadOnly => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn entries() -> Vec<Entry> {
+        [
+            "2021-07-03T10:00:00Z 2021-07-03T11:30:00Z Client $ #dev #review ; Fix, then ship",
+            "2021-07-03T12:00:00Z 2021-07-03T12:20:00Z Other",
+            "2021-07-04T09:00:00Z 2021-07-04T10:00:00Z Client #dev",
+        ]
+        .iter()
+        .map(|line| Entry::from_str(line).unwrap())
+        .collect()
+    }
+
+    #[test]
+    fn query_entries() {
+        let table = query(
+            &entries(),
+            "SELECT account, SUM(seconds) AS seconds FROM entries GROUP BY account ORDER BY account",
+        )
+        .unwrap();
+        assert_eq!(table.columns, vec!["account", "seconds"]);
+        assert_eq!(
+            table.rows,
+            vec![vec!["Client", "9000"], vec!["Other", "1200"]]
+        );
+
+        let table = query(
+            &entries(),
+            "SELECT tag, COUNT(*) FROM tags GROUP BY tag ORDER BY tag",
+        )
+        .unwrap();
+        assert_eq!(table.rows, vec![vec!["dev", "2"], vec!["review", "1"]]);
+
+        let table = query(
+            &entries(),
+            "SELECT start, description, category FROM entries LIMIT 1",
+        )
+        .unwrap();
+        assert_eq!(
+            table.to_csv(),
+            "start,description,category\n2021-07-03T10:00:00Z,\"Fix, then ship\","
+        );
+    }
+
+    #[test]
+    fn refuse_changes() {
+        for sql in &[
+            "DELETE FROM entries",
+            "UPDATE entries SET account = 'Other'",
+            "DROP TABLE tags",
+        ] {
+            assert!(
+                matches!(query(&entries(), sql), Err(SqlError::NotReadOnly)),
+                "{:?} should be refused",
+                sql
+            );
+        }
+        assert!(matches!(
+            query(&entries(), "SELECT nothing FROM nowhere"),
+            Err(SqlError::Sqlite(_))
+        ));
+    }
+}
@@ -0,0 +1,140 @@
+//! Statistics about tracking habits.
+
+use crate::timesheet::Timesheet;
+use chrono::{Datelike, Duration, NaiveDate, TimeZone, Weekday};
+use std::collections::BTreeMap;
+
+/// The average time tracked on the days with any time tracked, in the given time zone.
+pub fn average_daily<Tz: TimeZone>(timesheet: &Timesheet, tz: &Tz) -> Duration {
+    let days = tracked_days(timesheet, tz);
+    if days.is_empty() {
+        return Duration::zero();
+    }
+    let total = days.values().fold(Duration::zero(), |sum, day| sum + *day);
+    total / days.len() as i32
+}
+
+/// The largest number of consecutive days with time tracked, in the given time zone.
+pub fn longest_streak<Tz: TimeZone>(timesheet: &Timesheet, tz: &Tz) -> usize {
+    let mut longest = 0;
+    let mut current = 0;
+    let mut previous = None;
+    for date in tracked_days(timesheet, tz).keys() {
+        current = match previous {
+            Some(previous) if previous == date.pred() => current + 1,
+            _ => 1,
+        };
+        longest = longest.max(current);
+        previous = Some(*date);
+    }
+    longest
+}
+
+/// The account with the most time tracked on each day of the week, from Monday, leaving out days
+/// without any time tracked. Ties go to the account that comes first alphabetically.
+pub fn top_account_by_weekday<Tz: TimeZone>(
+    timesheet: &Timesheet,
+    tz: &Tz,
+) -> Vec<(Weekday, String, Duration)> {
+    let mut totals: BTreeMap<(u32, String), Duration> = BTreeMap::new();
+    for (date, day) in timesheet.group_by_day(tz) {
+        for entry in day.entries() {
+            let total = totals
+                .entry((
+                    date.weekday().num_days_from_monday(),
+                    entry.account.to_string(),
+                ))
+                .or_insert_with(Duration::zero);
+            *total = *total + (entry.stop - entry.start);
+        }
+    }
+
+    let mut top: Vec<(Weekday, String, Duration)> = Vec::new();
+    for ((weekday, account), total) in totals {
+        let weekday = (0..weekday).fold(Weekday::Mon, |weekday, _| weekday.succ());
+        match top.last_mut() {
+            Some(last) if last.0 == weekday => {
+                if total > last.2 {
+                    *last = (weekday, account, total);
+                }
+            }
+            _ => top.push((weekday, account, total)),
+        }
+    }
+    top
+}
+
+/// The average duration of an entry.
+pub fn average_session(timesheet: &Timesheet) -> Duration {
+    let entries = timesheet.entries();
+    if entries.is_empty() {
+        return Duration::zero();
+    }
+    timesheet.total_duration() / entries.len() as i32
+}
+
+fn tracked_days<Tz: TimeZone>(timesheet: &Timesheet, tz: &Tz) -> BTreeMap<NaiveDate, Duration> {
+    timesheet
+        .group_by_day(tz)
+        .into_iter()
+        .map(|(date, day)| (date, day.total_duration()))
+        .filter(|(_, total)| *total > Duration::zero())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Entry;
+    use chrono::Utc;
+    use std::str::FromStr;
+
+    fn timesheet(lines: &[&str]) -> Timesheet {
+        lines
+            .iter()
+            .map(|line| Entry::from_str(line).unwrap())
+            .collect()
+    }
+
+    fn example() -> Timesheet {
+        // Thursday to Saturday, then Monday
+        timesheet(&[
+            "2021-07-01T09:00:00Z 2021-07-01T13:00:00Z A",
+            "2021-07-01T14:00:00Z 2021-07-01T16:00:00Z B",
+            "2021-07-02T09:00:00Z 2021-07-02T10:00:00Z B",
+            "2021-07-03T09:00:00Z 2021-07-03T11:00:00Z A",
+            "2021-07-05T09:00:00Z 2021-07-05T12:00:00Z C",
+            "2021-07-05T13:00:00Z 2021-07-05T14:00:00Z A",
+        ])
+    }
+
+    #[test]
+    fn average_per_tracked_day() {
+        assert_eq!(average_daily(&example(), &Utc), Duration::minutes(195));
+        assert_eq!(average_daily(&Timesheet::default(), &Utc), Duration::zero());
+    }
+
+    #[test]
+    fn longest_streak_of_days() {
+        assert_eq!(longest_streak(&example(), &Utc), 3);
+        assert_eq!(longest_streak(&Timesheet::default(), &Utc), 0);
+    }
+
+    #[test]
+    fn top_accounts() {
+        assert_eq!(
+            top_account_by_weekday(&example(), &Utc),
+            vec![
+                (Weekday::Mon, "C".to_string(), Duration::hours(3)),
+                (Weekday::Thu, "A".to_string(), Duration::hours(4)),
+                (Weekday::Fri, "B".to_string(), Duration::hours(1)),
+                (Weekday::Sat, "A".to_string(), Duration::hours(2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn average_entry_duration() {
+        assert_eq!(average_session(&example()), Duration::minutes(130));
+    }
+}
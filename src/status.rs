@@ -0,0 +1,74 @@
+//! The status of the time tracking written as JSON, with what `tt status` shows.
+
+use crate::burn::Burn;
+use crate::goals::Progress;
+use crate::RunningEntry;
+use chrono::{Duration, NaiveDate};
+use serde::Serialize;
+
+/// The running entries, the time tracked today, exceeded goals and budgets running out before
+/// their deadlines, as `tt status --format json` writes them.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Status {
+    pub running: Vec<RunningEntry>,
+    pub today_seconds: i64,
+    pub exceeded_goals: Vec<ExceededGoal>,
+    pub budgets_at_risk: Vec<BudgetAtRisk>,
+}
+
+/// A goal with more time tracked than its target in the current period.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ExceededGoal {
+    /// The account of the goal, or its tag after a `#`.
+    pub scope: String,
+    /// `weekly` or `monthly`.
+    pub period: String,
+    pub tracked_seconds: i64,
+    pub target_seconds: i64,
+}
+
+/// A budget on track to be used up before its deadline.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BudgetAtRisk {
+    pub account: String,
+    pub budget_seconds: i64,
+    pub deadline: NaiveDate,
+    pub runs_out: NaiveDate,
+}
+
+impl Status {
+    /// The status with the goals which are exceeded and the budgets which are at risk.
+    pub fn new(
+        running: Vec<RunningEntry>,
+        today: Duration,
+        progress: &[Progress],
+        burns: &[Burn],
+    ) -> Self {
+        Status {
+            running,
+            today_seconds: today.num_seconds(),
+            exceeded_goals: progress
+                .iter()
+                .filter(|progress| progress.is_exceeded())
+                .map(|progress| ExceededGoal {
+                    scope: progress.goal.scope.to_string(),
+                    period: progress.goal.period.to_string(),
+                    tracked_seconds: progress.tracked.num_seconds(),
+                    target_seconds: progress.goal.target.num_seconds(),
+                })
+                .collect(),
+            budgets_at_risk: burns
+                .iter()
+                .filter(|burn| burn.is_at_risk())
+                .filter_map(|burn| {
+                    Some(BudgetAtRisk {
+                        account: burn.account.clone(),
+                        budget_seconds: burn.budget.num_seconds(),
+                        deadline: burn.deadline,
+                        runs_out: burn.runs_out?,
+                    })
+                })
+                .collect(),
+        }
+    }
+}
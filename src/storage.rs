@@ -0,0 +1,3164 @@
+//! Reading and writing of the entries file and the running file.
+
+use crate::audit::{self, AuditLog};
+#[cfg(feature = "compact")]
+use crate::compact::{self, DecodeError};
+#[cfg(feature = "encryption")]
+use crate::encryption::{EncryptionError, Key};
+use crate::index::{Index, Position};
+use crate::intern::Interner;
+use crate::migrations::{self, UnsupportedVersion};
+use crate::oplog::{self, Op, OpLog};
+use crate::permissions;
+use crate::report::{is_within_account, split_at_midnight};
+use crate::rounding::Rounding;
+use crate::selector::{SelectError, Selector};
+use crate::timesheet::Timesheet;
+use crate::{Entry, EntryRef, ParseError, RunningEntry, ValidationError};
+use chrono::{DateTime, Duration, Local, TimeZone, Utc};
+use serde::Deserialize;
+use std::collections::{BTreeSet, HashMap};
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// The longest line read from the files by default, in bytes, far beyond any entry written by
+/// hand but small enough that a corrupted file cannot tie up much memory.
+pub const DEFAULT_MAX_LINE_LENGTH: usize = 64 * 1024;
+
+/// The largest file read by default, in bytes. Files are read into memory as a whole.
+pub const DEFAULT_MAX_FILE_SIZE: u64 = 512 * 1024 * 1024;
+
+/// How close the start of a running entry is to one for the same account by default to be taken
+/// as a duplicate of it, see `Storage::duplicate_window`.
+pub const DEFAULT_DUPLICATE_WINDOW_SECONDS: i64 = 1;
+
+/// The files holding the completed and the running entries.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Storage {
+    entries_path: PathBuf,
+    running_path: PathBuf,
+    keep_sorted: bool,
+    write_mode: WriteMode,
+    /// Whether the running entries are kept in the entries file, see `single_file`.
+    single_file: bool,
+    quarantine_partial_lines: bool,
+    /// Whether reading fails on the first line that cannot be parsed, see `strict`.
+    strict: bool,
+    zero_duration: ZeroDurationPolicy,
+    /// How the start and the stop of entries are rounded when they are recorded.
+    round_start: Option<Rounding>,
+    round_stop: Option<Rounding>,
+    /// Whether an index of the entries file is kept, see `index`.
+    index: bool,
+    /// Files with more completed entries, which are read along with the entries file but never
+    /// written.
+    additional_entries_paths: Vec<PathBuf>,
+    audit: Option<AuditLog>,
+    /// Where changes go instead of the files, see `oplog`.
+    oplog: Option<OpLog>,
+    /// The limits on what is read, see `limits`.
+    max_line_length: usize,
+    max_file_size: u64,
+    duplicate_window: Duration,
+    #[cfg(feature = "encryption")]
+    key: Option<Key>,
+}
+
+/// What happens to entries that do not last any time, i.e. stop when they start, when they are
+/// recorded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ZeroDurationPolicy {
+    /// The entries are recorded like any other.
+    #[default]
+    Keep,
+    /// The entries are left out.
+    Drop,
+    /// Recording the entries fails with `StorageError::ZeroDuration`.
+    Error,
+}
+
+/// A line of a data file that cannot be parsed, see `Storage::invalid_lines`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidLine {
+    pub path: PathBuf,
+    /// The line number, starting at 1.
+    pub number: usize,
+    pub content: String,
+    pub err: ParseError,
+}
+
+/// A line of a data file as read by `Storage::parse_lines`.
+struct ParsedLine<T> {
+    /// The line number, starting at 1.
+    number: usize,
+    /// The line without the prefix of its kind.
+    line: String,
+    value: Result<T, ParseError>,
+}
+
+/// What happens to changes of the files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WriteMode {
+    /// Changes are written to the files.
+    Write,
+    /// Changes are printed to standard output instead of being written.
+    DryRun,
+    /// Changes are refused with `StorageError::ReadOnly`.
+    ReadOnly,
+}
+
+impl Storage {
+    pub fn new(entries_path: PathBuf, running_path: PathBuf) -> Self {
+        Storage {
+            entries_path,
+            running_path,
+            keep_sorted: false,
+            write_mode: WriteMode::Write,
+            single_file: false,
+            quarantine_partial_lines: false,
+            strict: false,
+            zero_duration: ZeroDurationPolicy::Keep,
+            round_start: None,
+            round_stop: None,
+            index: false,
+            additional_entries_paths: Vec::new(),
+            audit: None,
+            oplog: None,
+            max_line_length: DEFAULT_MAX_LINE_LENGTH,
+            max_file_size: DEFAULT_MAX_FILE_SIZE,
+            duplicate_window: Duration::seconds(DEFAULT_DUPLICATE_WINDOW_SECONDS),
+            #[cfg(feature = "encryption")]
+            key: None,
+        }
+    }
+
+    /// Keeps the running entries in the entries file too, on lines starting with `RUNNING `
+    /// at the end of the file, so there is only one file to back up and sync.
+    pub fn single_file(path: PathBuf) -> Self {
+        Storage {
+            single_file: true,
+            ..Storage::new(path.clone(), path)
+        }
+    }
+
+    /// Keeps the entries file in chronological order when appending entries that start before
+    /// the last entry, at the cost of rewriting the file. Otherwise entries are always added to
+    /// the end.
+    pub fn keep_sorted(mut self, keep_sorted: bool) -> Self {
+        self.keep_sorted = keep_sorted;
+        self
+    }
+
+    /// Moves an incomplete last line, as left behind by an interrupted write, to a `.corrupt` file
+    /// next to the file instead of failing with `StorageError::PartialLine`.
+    pub fn quarantine_partial_lines(mut self, quarantine: bool) -> Self {
+        self.quarantine_partial_lines = quarantine;
+        self
+    }
+
+    /// Fails reading the entries at the first line that cannot be parsed. Otherwise such lines
+    /// are skipped with a warning listing them, so one broken line does not keep every entry from
+    /// being read. Changing the files always fails on them, so rewriting a file never loses them.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Keeps an index of the entries file next to it, so `entries_since` can skip the entries
+    /// before the time, see the `index` module. The index is only used while the file is sorted.
+    pub fn index(mut self, index: bool) -> Self {
+        self.index = index;
+        self
+    }
+
+    pub fn zero_duration(mut self, policy: ZeroDurationPolicy) -> Self {
+        self.zero_duration = policy;
+        self
+    }
+
+    /// Rounds the start of entries when they are started and the stop when they are stopped, for
+    /// workplaces which require rounded records. A stop is never rounded to before the start.
+    pub fn rounding(mut self, start: Option<Rounding>, stop: Option<Rounding>) -> Self {
+        self.round_start = start;
+        self.round_stop = stop;
+        self
+    }
+
+    /// Reads the completed entries of these files too, e.g. archives, ordering all entries by start
+    /// time. Changes are still only made to the entries file.
+    pub fn additional_entries_files(mut self, paths: Vec<PathBuf>) -> Self {
+        self.additional_entries_paths = paths;
+        self
+    }
+
+    /// Records every change to the lines of the files in the audit log.
+    pub fn audit(mut self, log: AuditLog) -> Self {
+        self.audit = Some(log);
+        self
+    }
+
+    /// Appends changes to the files to the operation log instead of rewriting them, and reads
+    /// the files with the operations of every machine applied, see the `oplog` module.
+    pub fn oplog(mut self, log: OpLog) -> Self {
+        self.oplog = Some(log);
+        self
+    }
+
+    /// Fails reading lines longer than the length and files larger than the size, both in bytes,
+    /// instead of reading whatever a corrupted or hostile file holds into memory.
+    pub fn limits(mut self, max_line_length: usize, max_file_size: u64) -> Self {
+        self.max_line_length = max_line_length;
+        self.max_file_size = max_file_size;
+        self
+    }
+
+    /// Takes starting an account already running since at most the window before the start as
+    /// a duplicate, as from a start run twice by a hook, which `start` does nothing for.
+    pub fn duplicate_window(mut self, window: Duration) -> Self {
+        self.duplicate_window = window;
+        self
+    }
+
+    pub fn write_mode(mut self, write_mode: WriteMode) -> Self {
+        self.write_mode = write_mode;
+        self
+    }
+
+    /// Encrypts the files with the key when writing them. Files which are not encrypted yet are
+    /// still read as they are, and encrypted on the next change.
+    #[cfg(feature = "encryption")]
+    pub fn key(mut self, key: Key) -> Self {
+        self.key = Some(key);
+        self
+    }
+
+    /// Whether changes are only printed rather than written.
+    pub fn is_dry_run(&self) -> bool {
+        self.write_mode == WriteMode::DryRun
+    }
+
+    pub fn entries_path(&self) -> &Path {
+        &self.entries_path
+    }
+
+    pub fn running_path(&self) -> &Path {
+        &self.running_path
+    }
+
+    /// Reads every completed entry, a missing file is treated as having no entries.
+    ///
+    /// With additional entries files, the entries of every file are ordered by start time.
+    pub fn entries(&self) -> Result<Vec<Entry>, StorageError> {
+        let mut entries = self.read_lines(&self.entries_path)?;
+        if !self.additional_entries_paths.is_empty() {
+            for path in &self.additional_entries_paths {
+                #[cfg(feature = "compact")]
+                if let Some(archive) = self.read_compact(path)? {
+                    entries.extend(archive);
+                    continue;
+                }
+                entries.extend(self.read_lines::<Entry>(path)?);
+            }
+            entries.sort_by_key(|entry| entry.start);
+            // Each file was read with an interner of its own
+            let mut interner = Interner::new();
+            for entry in &mut entries {
+                entry.account = interner.intern(&entry.account);
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Reads the completed entries that `keep` accepts, like `entries`. The lines are looked at
+    /// without copying them, so only kept entries are allocated, which makes picking a few
+    /// entries out of a large file much faster.
+    pub fn entries_where(
+        &self,
+        mut keep: impl FnMut(&EntryRef<'_>) -> bool,
+    ) -> Result<Vec<Entry>, StorageError> {
+        self.read_entries(None, &mut keep)
+    }
+
+    /// Reads the completed entries stopping at or after the time that `keep` accepts, like
+    /// `entries_where`. With the index enabled the start of the entries file is skipped.
+    pub fn entries_since(
+        &self,
+        since: DateTime<Utc>,
+        mut keep: impl FnMut(&EntryRef<'_>) -> bool,
+    ) -> Result<Vec<Entry>, StorageError> {
+        let start = self.index_position(since)?;
+        self.read_entries(start, &mut |entry| entry.stop >= since && keep(entry))
+    }
+
+    /// Passes each completed entry stopping at or after the time to `visit` as it is read, like
+    /// `entries_since`, without keeping the entries. The entries are in the order of the files,
+    /// with those of additional entries files after the others.
+    pub fn visit_entries_since(
+        &self,
+        since: DateTime<Utc>,
+        mut visit: impl FnMut(&EntryRef<'_>),
+    ) -> Result<(), StorageError> {
+        let start = self.index_position(since)?;
+        // Nothing is kept, so there is nothing to order either
+        self.read_entries(start, &mut |entry| {
+            if entry.stop >= since {
+                visit(entry);
+            }
+            false
+        })?;
+        Ok(())
+    }
+
+    /// Reads the entries of every entries file, starting at the position in the writable one.
+    fn read_entries(
+        &self,
+        start: Option<Position>,
+        keep: &mut dyn FnMut(&EntryRef<'_>) -> bool,
+    ) -> Result<Vec<Entry>, StorageError> {
+        let mut entries = Vec::new();
+        let mut interner = Interner::new();
+        self.read_entries_of(&self.entries_path, start, keep, &mut interner, &mut entries)?;
+        if !self.additional_entries_paths.is_empty() {
+            for path in &self.additional_entries_paths {
+                #[cfg(feature = "compact")]
+                if let Some(archive) = self.read_compact(path)? {
+                    let (mut tags, mut meta) = (String::new(), String::new());
+                    entries.extend(
+                        archive
+                            .into_iter()
+                            .filter(|entry| keep(&entry.to_ref(&mut tags, &mut meta)))
+                            .map(|mut entry| {
+                                entry.account = interner.intern(&entry.account);
+                                entry
+                            }),
+                    );
+                    continue;
+                }
+                self.read_entries_of(path, None, keep, &mut interner, &mut entries)?;
+            }
+            entries.sort_by_key(|entry| entry.start);
+        }
+        Ok(entries)
+    }
+
+    /// Reads an additional entries file in the compact format, or returns `None` if it is in the
+    /// line format. Only the start of the file is read to tell.
+    #[cfg(feature = "compact")]
+    fn read_compact(&self, path: &Path) -> Result<Option<Vec<Entry>>, StorageError> {
+        if is_stdin(path) {
+            return Ok(None);
+        }
+        let mut start = Vec::new();
+        match File::open(path) {
+            Ok(file) => file.take(32).read_to_end(&mut start)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        if !compact::is_compact(&start) {
+            return Ok(None);
+        }
+        self.check_file_size(path, fs::metadata(path)?.len())?;
+        let entries = compact::decode(&fs::read(path)?)
+            .map_err(|err| StorageError::Compact(path.to_path_buf(), err))?;
+        log::debug!("read {} entries from {}", entries.len(), path.display());
+        Ok(Some(entries))
+    }
+
+    fn read_entries_of(
+        &self,
+        path: &Path,
+        start: Option<Position>,
+        keep: &mut dyn FnMut(&EntryRef<'_>) -> bool,
+        interner: &mut Interner,
+        entries: &mut Vec<Entry>,
+    ) -> Result<(), StorageError> {
+        let (contents, first_line) = match start {
+            Some(position) => (self.read_text_from(path, position.offset)?, position.line),
+            None => (self.read_all_text(path)?, 1),
+        };
+        let mut lines: Vec<&str> = contents.lines().collect();
+        self.check_line_lengths(&lines, first_line)?;
+        let partial = !contents.is_empty()
+            && !contents.ends_with('\n')
+            && lines.last().is_some_and(|line| {
+                let line = if self.single_file {
+                    Entry::strip_prefix(line)
+                } else {
+                    Some(*line)
+                };
+                line.is_some_and(|line| {
+                    migrations::parse_header(line).is_none() && Entry::parse(line).is_err()
+                })
+            });
+        if partial {
+            // Moving the line away rewrites the whole file
+            if start.is_some() {
+                return self.read_entries_of(path, None, keep, interner, entries);
+            }
+            let line = lines.pop().expect("the file has a last line");
+            let kept: Vec<String> = lines.iter().map(|line| line.to_string()).collect();
+            self.quarantine_partial_line(path, &kept, line.to_string())?;
+        }
+
+        let mut skipped = Vec::new();
+        for (index, line) in lines.into_iter().enumerate() {
+            let number = first_line + index;
+            if number == 1 {
+                if let Some(version) = migrations::parse_header(line) {
+                    if version > migrations::CURRENT_VERSION {
+                        return Err(UnsupportedVersion(version).into());
+                    }
+                    continue;
+                }
+            }
+            let line = if self.single_file {
+                match Entry::strip_prefix(line) {
+                    Some(line) => line,
+                    None => continue,
+                }
+            } else {
+                line
+            };
+            let entry = match Entry::parse(line) {
+                Ok(entry) => entry,
+                Err(err) if self.strict => return Err(StorageError::Parse { line: number, err }),
+                Err(_) => {
+                    skipped.push(number);
+                    continue;
+                }
+            };
+            if keep(&entry) {
+                entries.push(entry.to_interned_entry(interner));
+            }
+        }
+        warn_skipped_lines(path, &skipped);
+        Ok(())
+    }
+
+    /// Where to start reading the entries file for the entries stopping at or after the time,
+    /// rebuilding the index if it is out of date. `None` when the index is not used.
+    fn index_position(&self, since: DateTime<Utc>) -> Result<Option<Position>, StorageError> {
+        if !self.index_enabled() {
+            return Ok(None);
+        }
+        let metadata = match fs::metadata(&self.entries_path) {
+            Ok(metadata) => metadata,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        let path = Index::path(&self.entries_path);
+        let index = match Index::load(&path).filter(|index| index.is_current(&metadata)) {
+            Some(index) => index,
+            None => {
+                log::debug!("rebuilding the index {}", path.display());
+                let contents = self.read_all_text(&self.entries_path)?;
+                match Index::build(&contents, &metadata) {
+                    Some(index) => {
+                        self.save_index(&path, &index);
+                        index
+                    }
+                    None => {
+                        log::debug!(
+                            "not indexing {}, it is not sorted or has invalid lines",
+                            self.entries_path.display()
+                        );
+                        return Ok(None);
+                    }
+                }
+            }
+        };
+        Ok(Some(index.start_of(since)))
+    }
+
+    /// Adds the entry just appended to the entries file to the index. An index that was out of
+    /// date before, or no longer holds since the file is out of order, is left to be rebuilt.
+    fn update_index(&self, entry: &Entry) {
+        if !self.index_enabled() || self.write_mode != WriteMode::Write {
+            return;
+        }
+        let path = Index::path(&self.entries_path);
+        let (mut index, metadata) = match (Index::load(&path), fs::metadata(&self.entries_path)) {
+            (Some(index), Ok(metadata)) => (index, metadata),
+            _ => return,
+        };
+        let appended = entry.to_string().len() as u64 + 1;
+        if index.size() + appended == metadata.len() && index.append(entry, &metadata) {
+            self.save_index(&path, &index);
+        }
+    }
+
+    /// Whether the index is used, which takes an entries file with offsets that can be seeked to.
+    fn index_enabled(&self) -> bool {
+        #[cfg(feature = "encryption")]
+        if self.key.is_some() {
+            return false;
+        }
+        self.index && !self.single_file && self.oplog.is_none() && !is_stdin(&self.entries_path)
+    }
+
+    /// Writes the index unless changes are not written. The index is only a cache, so failing to
+    /// write it is not an error.
+    fn save_index(&self, path: &Path, index: &Index) {
+        if self.write_mode != WriteMode::Write {
+            return;
+        }
+        if let Err(err) = index.save(path) {
+            log::warn!("could not write the index {}: {}", path.display(), err);
+        }
+    }
+
+    /// Reads every completed entry into a timesheet for querying.
+    pub fn timesheet(&self) -> Result<Timesheet, StorageError> {
+        Ok(Timesheet::new(self.entries()?))
+    }
+
+    /// Reads every running entry, a missing file is treated as having no entries.
+    pub fn running_entries(&self) -> Result<Vec<RunningEntry>, StorageError> {
+        self.read_lines(&self.running_path)
+    }
+
+    /// Adds the entry to the entries file, unless it does not last any time and the zero duration
+    /// policy says otherwise.
+    pub fn append_entry(&self, entry: &Entry) -> Result<(), StorageError> {
+        if !self.is_kept(entry)? {
+            return Ok(());
+        }
+        if self.keep_sorted {
+            let mut lines = self.read_raw_lines::<Entry>(&self.entries_path)?;
+            // Insert after every entry starting at the same time or earlier
+            let position = lines
+                .iter()
+                .rposition(|(_, existing)| existing.start <= entry.start)
+                .map_or(0, |position| position + 1);
+            if position < lines.len() {
+                lines.insert(position, (entry.to_string(), entry.clone()));
+                return self.write_lines::<Entry>(
+                    &self.entries_path,
+                    lines.into_iter().map(|(line, _)| line),
+                );
+            }
+        }
+        self.append_line(&self.entries_path, entry)?;
+        self.update_index(entry);
+        Ok(())
+    }
+
+    /// Validates the entry, returning whether it is to be written by the zero duration policy.
+    fn is_kept(&self, entry: &Entry) -> Result<bool, StorageError> {
+        entry.validate()?;
+        if entry.stop == entry.start {
+            match self.zero_duration {
+                ZeroDurationPolicy::Keep => {}
+                ZeroDurationPolicy::Drop => {
+                    log::info!("dropping the entry without duration: {}", entry);
+                    return Ok(false);
+                }
+                ZeroDurationPolicy::Error => return Err(StorageError::ZeroDuration),
+            }
+        }
+        Ok(true)
+    }
+
+    /// Replaces the lines of running entries in the single data file with the lines of the
+    /// entries they were completed as, so the entries stay where they were started. Each running
+    /// entry is given by its line without the prefix.
+    fn complete_in_place(&self, completed: Vec<(String, &Vec<Entry>)>) -> Result<(), StorageError> {
+        let path = &self.entries_path;
+        let (mut lines, _) = self.read_all_lines(path)?;
+        let mut changes = (Vec::new(), Vec::new());
+        for (running_line, entries) in completed {
+            let mut entry_lines = Vec::new();
+            for entry in entries {
+                if self.is_kept(entry)? {
+                    entry_lines.push(entry.to_string());
+                }
+            }
+            let prefixed = format!("{}{}", RunningEntry::PREFIX, running_line);
+            let position = lines
+                .iter()
+                .position(|line| *line == prefixed)
+                .expect("the running entry was read from the file");
+            lines.splice(position..=position, entry_lines.iter().cloned());
+            changes.0.push((Some(running_line), None));
+            changes
+                .1
+                .extend(entry_lines.into_iter().map(|line| (None, Some(line))));
+        }
+        self.write_file(path, lines)?;
+        if let Some(log) = &self.audit {
+            let now = crate::now();
+            log.record(now, RunningEntry::FILE, changes.0)?;
+            log.record(now, Entry::FILE, changes.1)?;
+        }
+        Ok(())
+    }
+
+    pub fn append_running_entry(&self, entry: &RunningEntry) -> Result<(), StorageError> {
+        self.append_line(&self.running_path, entry)
+    }
+
+    /// Replaces the content of the running file with the given entries.
+    pub fn write_running_entries(&self, entries: &[RunningEntry]) -> Result<(), StorageError> {
+        self.write_lines::<RunningEntry>(
+            &self.running_path,
+            entries.iter().map(|entry| entry.to_string()),
+        )
+    }
+
+    /// Orders the entries file by start time, keeping the order of entries starting at the same
+    /// time and the lines exactly as they were. Returns whether the file was out of order.
+    ///
+    /// With `dry_run` the order is only checked and nothing is written.
+    pub fn sort(&self, dry_run: bool) -> Result<bool, StorageError> {
+        let mut lines = self.read_raw_lines::<Entry>(&self.entries_path)?;
+        if lines
+            .windows(2)
+            .all(|pair| pair[0].1.start <= pair[1].1.start)
+        {
+            return Ok(false);
+        }
+        if !dry_run {
+            lines.sort_by_key(|(_, entry)| entry.start);
+            self.write_lines::<Entry>(&self.entries_path, lines.into_iter().map(|(line, _)| line))?;
+        }
+        Ok(true)
+    }
+
+    /// Rewrites both files in the canonical line format, returning the number of lines that
+    /// changed in the entries file and the running file respectively.
+    ///
+    /// With `dry_run` the lines are only counted and nothing is written.
+    pub fn normalize(&self, dry_run: bool) -> Result<(usize, usize), StorageError> {
+        Ok((
+            self.normalize_file::<Entry>(&self.entries_path, dry_run)?,
+            self.normalize_file::<RunningEntry>(&self.running_path, dry_run)?,
+        ))
+    }
+
+    /// Folds the operations of the operation log into the files and removes them from the logs,
+    /// returning the number of operations folded. Operations recorded meanwhile are kept.
+    ///
+    /// When changes are only printed, the operations are only counted.
+    pub fn compact_oplog(&self) -> Result<usize, StorageError> {
+        let log = match &self.oplog {
+            Some(log) => log,
+            None => return Ok(0),
+        };
+        let operations = log.operations()?;
+        match self.write_mode {
+            WriteMode::Write => {}
+            WriteMode::DryRun => return Ok(operations.len()),
+            WriteMode::ReadOnly => return Err(StorageError::ReadOnly),
+        }
+        for (path, file) in [
+            (&self.entries_path, Entry::FILE),
+            (&self.running_path, RunningEntry::FILE),
+        ] {
+            if operations.iter().any(|operation| operation.file == file) {
+                let lines = self
+                    .read_file_text(path)?
+                    .lines()
+                    .map(str::to_string)
+                    .collect();
+                self.replace_file(path, oplog::apply(lines, &operations, file))?;
+            }
+        }
+        let ids: BTreeSet<String> = operations
+            .iter()
+            .map(|operation| operation.id.clone())
+            .collect();
+        log.remove(&ids)?;
+        Ok(operations.len())
+    }
+
+    /// Adds the running entry, unless there already is one for the same account. One started
+    /// within the duplicate window of it is returned instead, see `duplicate_window`.
+    pub fn start(&self, mut running_entry: RunningEntry) -> Result<RunningEntry, StorageError> {
+        if let Some(rounding) = &self.round_start {
+            running_entry.start = rounding.apply(running_entry.start);
+        }
+        running_entry.validate()?;
+        if let Some(running) = self
+            .running_entries()?
+            .into_iter()
+            .find(|entry| entry.account == running_entry.account)
+        {
+            if (running.start - running_entry.start).num_seconds().abs()
+                <= self.duplicate_window.num_seconds()
+            {
+                log::info!("{} was just started, taking it as a duplicate", running);
+                return Ok(running);
+            }
+            return Err(StorageError::AlreadyRunning(
+                running_entry.account.to_string(),
+            ));
+        }
+
+        self.append_running_entry(&running_entry)?;
+        Ok(running_entry)
+    }
+
+    /// Changes the last completed entry for the account, or the last one of all if no account is
+    /// given, and returns the changed entry.
+    ///
+    /// The last entry is the one starting last, the file does not need to be sorted. Of entries
+    /// starting at the same time the one further down in the file is last. Every other line is
+    /// written back exactly as it was read.
+    pub fn update_last_entry(
+        &self,
+        account: Option<&str>,
+        update: impl FnOnce(&mut Entry),
+    ) -> Result<Entry, StorageError> {
+        let mut lines = self.read_raw_lines::<Entry>(&self.entries_path)?;
+        let position = last_position(&lines, account)?;
+        let (line, entry) = &mut lines[position];
+        update(entry);
+        *line = entry.to_string();
+        let entry = entry.clone();
+        self.write_lines::<Entry>(&self.entries_path, lines.into_iter().map(|(line, _)| line))?;
+        Ok(entry)
+    }
+
+    /// Splits the selected completed entry into two entries at the time, which take its place in
+    /// the file. The second entry is changed by the given function. Returns both entries.
+    pub fn split_entry(
+        &self,
+        selector: &Selector,
+        at: DateTime<Utc>,
+        update: impl FnOnce(&mut Entry),
+    ) -> Result<(Entry, Entry), StorageError> {
+        let mut lines = self.read_raw_lines::<Entry>(&self.entries_path)?;
+        let position = selector.position(lines.iter().map(|(_, entry)| entry))?;
+        let (first, mut second) = lines[position]
+            .1
+            .split_at(at)
+            .ok_or(StorageError::OutsideEntry(at))?;
+        update(&mut second);
+        lines.splice(
+            position..=position,
+            vec![
+                (first.to_string(), first.clone()),
+                (second.to_string(), second.clone()),
+            ],
+        );
+        self.write_lines::<Entry>(&self.entries_path, lines.into_iter().map(|(line, _)| line))?;
+        Ok((first, second))
+    }
+
+    /// Changes the time of the completed entries within the range with the given function, like
+    /// giving it to another account, splitting the entries partly within it so the time before
+    /// and after it is left as it was. The parts take the place of the entry in the file, and
+    /// entries the function does not change are not split. Returns the changed parts.
+    pub fn reassign(
+        &self,
+        range: Range<DateTime<Utc>>,
+        mut update: impl FnMut(&mut Entry),
+    ) -> Result<Vec<Entry>, StorageError> {
+        let lines = self.read_raw_lines::<Entry>(&self.entries_path)?;
+        let mut changed = Vec::new();
+        let mut written = Vec::with_capacity(lines.len());
+        for (line, entry) in lines {
+            if entry.stop <= range.start || range.end <= entry.start {
+                written.push(line);
+                continue;
+            }
+            let (before, within) = match entry.split_at(range.start) {
+                Some((before, within)) => (Some(before), within),
+                None => (None, entry.clone()),
+            };
+            let (mut within, after) = match within.split_at(range.end) {
+                Some((within, after)) => (within, Some(after)),
+                None => (within, None),
+            };
+            let original = within.clone();
+            update(&mut within);
+            if within == original {
+                written.push(line);
+                continue;
+            }
+            within.validate()?;
+            written.extend(before.iter().map(Entry::to_string));
+            written.push(within.to_string());
+            written.extend(after.iter().map(Entry::to_string));
+            changed.push(within);
+        }
+        if !changed.is_empty() {
+            self.write_lines::<Entry>(&self.entries_path, written.into_iter())?;
+        }
+        Ok(changed)
+    }
+
+    /// Moves the start and stop of the last completed entry for the account, or the last one of
+    /// all if no account is given, by the amounts, see `update_last_entry` for which entry is
+    /// last. Returns the trimmed entry.
+    ///
+    /// Fails if the entry would no longer last any time, or unless `allow_overlap` is given, if
+    /// it would overlap an entry it did not overlap before.
+    pub fn trim_last_entry(
+        &self,
+        account: Option<&str>,
+        start_by: Duration,
+        stop_by: Duration,
+        allow_overlap: bool,
+    ) -> Result<Entry, StorageError> {
+        let mut lines = self.read_raw_lines::<Entry>(&self.entries_path)?;
+        let position = last_position(&lines, account)?;
+        let original = lines[position].1.clone();
+        let trimmed = Entry {
+            start: original.start + start_by,
+            stop: original.stop + stop_by,
+            ..original.clone()
+        };
+        trimmed.validate()?;
+        if trimmed.stop == trimmed.start {
+            return Err(StorageError::ZeroDuration);
+        }
+        if !allow_overlap {
+            let overlaps =
+                |entry: &Entry, other: &Entry| other.start < entry.stop && entry.start < other.stop;
+            let overlapped = lines.iter().enumerate().find(|(index, (_, other))| {
+                *index != position && overlaps(&trimmed, other) && !overlaps(&original, other)
+            });
+            if let Some((_, (_, other))) = overlapped {
+                return Err(StorageError::Overlap(Box::new(other.clone())));
+            }
+        }
+        lines[position] = (trimmed.to_string(), trimmed.clone());
+        self.write_lines::<Entry>(&self.entries_path, lines.into_iter().map(|(line, _)| line))?;
+        Ok(trimmed)
+    }
+
+    /// Merges entries following each other chronologically into one when they can be merged, see
+    /// `Entry::can_merge`, and returns the merged entries.
+    ///
+    /// A merged entry takes the place of its first entry in the file, every other line is written
+    /// back exactly as it was read.
+    pub fn merge(&self, max_gap: Duration) -> Result<Vec<Entry>, StorageError> {
+        let mut lines = self.read_raw_lines::<Entry>(&self.entries_path)?;
+        let mut order: Vec<usize> = (0..lines.len()).collect();
+        order.sort_by_key(|&index| lines[index].1.start);
+
+        let mut removed = vec![false; lines.len()];
+        let mut merged: Vec<usize> = Vec::new();
+        let mut order = order.into_iter();
+        if let Some(mut current) = order.next() {
+            for index in order {
+                if !lines[current].1.can_merge(&lines[index].1, max_gap) {
+                    current = index;
+                    continue;
+                }
+                let next = lines[index].1.clone();
+                lines[current].1.merge(&next);
+                removed[index] = true;
+                if merged.last() != Some(&current) {
+                    merged.push(current);
+                }
+            }
+        }
+        if merged.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        for &index in &merged {
+            lines[index].0 = lines[index].1.to_string();
+        }
+        let entries = merged.iter().map(|&index| lines[index].1.clone()).collect();
+        self.write_lines::<Entry>(
+            &self.entries_path,
+            lines
+                .into_iter()
+                .zip(removed)
+                .filter(|(_, removed)| !removed)
+                .map(|((line, _), _)| line),
+        )?;
+        Ok(entries)
+    }
+
+    /// Removes the entries that do not last any time, i.e. stop at or before their start, and
+    /// returns them. Every other line is written back exactly as it was read.
+    ///
+    /// With `merge` the tags and description of a removed entry are added to the entry before it,
+    /// if that is for the same account and billable alike.
+    pub fn clean(&self, merge: bool) -> Result<Vec<Entry>, StorageError> {
+        let mut lines = self.read_raw_lines::<Entry>(&self.entries_path)?;
+        let mut order: Vec<usize> = (0..lines.len()).collect();
+        order.sort_by_key(|&index| lines[index].1.start);
+
+        let mut removed = vec![false; lines.len()];
+        let mut previous: Option<usize> = None;
+        for index in order {
+            let entry = lines[index].1.clone();
+            if entry.stop > entry.start {
+                previous = Some(index);
+                continue;
+            }
+            removed[index] = true;
+            if let Some(previous) = previous.filter(|_| merge) {
+                let kept = &mut lines[previous].1;
+                if kept.account == entry.account && kept.billable == entry.billable {
+                    let stop = kept.stop;
+                    kept.merge(&entry);
+                    kept.stop = stop;
+                    lines[previous].0 = kept.to_string();
+                }
+            }
+        }
+        if !removed.contains(&true) {
+            return Ok(Vec::new());
+        }
+
+        let entries = lines
+            .iter()
+            .zip(&removed)
+            .filter(|(_, removed)| **removed)
+            .map(|((_, entry), _)| entry.clone())
+            .collect();
+        self.write_lines::<Entry>(
+            &self.entries_path,
+            lines
+                .into_iter()
+                .zip(removed)
+                .filter(|(_, removed)| !removed)
+                .map(|((line, _), _)| line),
+        )?;
+        Ok(entries)
+    }
+
+    /// The format version of the entries file, see `migrations`.
+    pub fn format_version(&self) -> Result<u32, StorageError> {
+        Ok(migrations::version(
+            &self.read_all_lines(&self.entries_path)?.0,
+        ))
+    }
+
+    /// Upgrades the entries file to the current format version, see `migrations`. Returns the
+    /// version it was at, or `None` if it already was at the current version.
+    pub fn migrate(&self) -> Result<Option<u32>, StorageError> {
+        if is_stdin(&self.entries_path) {
+            return Err(StorageError::WriteToStdin);
+        }
+        let (lines, _) = self.read_all_lines(&self.entries_path)?;
+        let (version, lines) = migrations::migrate(lines)?;
+        if version == migrations::CURRENT_VERSION {
+            return Ok(None);
+        }
+        self.write_file(&self.entries_path, lines)?;
+        Ok(Some(version))
+    }
+
+    /// Renames the account and its sub-accounts in both files, returning the number of renamed
+    /// entries and running entries respectively. Every other line is written back exactly as it
+    /// was read.
+    pub fn rename_account(&self, old: &str, new: &str) -> Result<(usize, usize), StorageError> {
+        let mut entries = self.read_raw_lines::<Entry>(&self.entries_path)?;
+        let mut running_entries = self.read_raw_lines::<RunningEntry>(&self.running_path)?;
+        let renamed = (
+            rename_lines(&mut entries, |entry| &mut entry.account, old, new),
+            rename_lines(&mut running_entries, |entry| &mut entry.account, old, new),
+        );
+
+        if renamed.0 > 0 {
+            self.write_lines::<Entry>(
+                &self.entries_path,
+                entries.into_iter().map(|(line, _)| line),
+            )?;
+        }
+        if renamed.1 > 0 {
+            self.write_lines::<RunningEntry>(
+                &self.running_path,
+                running_entries.into_iter().map(|(line, _)| line),
+            )?;
+        }
+        Ok(renamed)
+    }
+
+    /// Replaces the entries with the IDs by the entries they map to, returning the number of
+    /// replaced entries. Every other line is written back exactly as it was read.
+    pub fn replace_entries(
+        &self,
+        replacements: &HashMap<String, Entry>,
+    ) -> Result<usize, StorageError> {
+        let mut lines = self.read_raw_lines::<Entry>(&self.entries_path)?;
+        let mut replaced = 0;
+        for (line, entry) in &mut lines {
+            if let Some(replacement) = replacements.get(&entry.id()) {
+                *entry = replacement.clone();
+                *line = entry.to_string();
+                replaced += 1;
+            }
+        }
+        if replaced > 0 {
+            self.write_lines::<Entry>(&self.entries_path, lines.into_iter().map(|(line, _)| line))?;
+        }
+        Ok(replaced)
+    }
+
+    /// Changes the completed entries with `update`, which returns whether it changed the entry,
+    /// returning the changed entries. Every other line is written back exactly as it was read.
+    pub fn update_entries(
+        &self,
+        mut update: impl FnMut(&mut Entry) -> bool,
+    ) -> Result<Vec<Entry>, StorageError> {
+        let mut lines = self.read_raw_lines::<Entry>(&self.entries_path)?;
+        let mut updated = Vec::new();
+        for (line, entry) in &mut lines {
+            if update(entry) {
+                *line = entry.to_string();
+                updated.push(entry.clone());
+            }
+        }
+        if !updated.is_empty() {
+            self.write_lines::<Entry>(&self.entries_path, lines.into_iter().map(|(line, _)| line))?;
+        }
+        Ok(updated)
+    }
+
+    /// Changes the running entry for the account and returns the changed entry.
+    ///
+    /// The account may only be omitted when there is exactly one running entry.
+    pub fn update_running_entry(
+        &self,
+        account: Option<&str>,
+        update: impl FnOnce(&mut RunningEntry),
+    ) -> Result<RunningEntry, StorageError> {
+        let mut lines = self.read_raw_lines::<RunningEntry>(&self.running_path)?;
+        let position = running_position(&lines, account)?;
+        let (line, running_entry) = &mut lines[position];
+        update(running_entry);
+        *line = running_entry.to_string();
+        let running_entry = running_entry.clone();
+        self.write_lines::<RunningEntry>(
+            &self.running_path,
+            lines.into_iter().map(|(line, _)| line),
+        )?;
+        Ok(running_entry)
+    }
+
+    /// Stops the running entry for the account and records it as a completed entry.
+    ///
+    /// The account may only be omitted when there is exactly one running entry.
+    ///
+    /// The other running entries are written back exactly as they were read, so they keep any
+    /// time zone offsets or formatting that differ from the canonical form.
+    pub fn stop(&self, account: Option<&str>, now: DateTime<Utc>) -> Result<Entry, StorageError> {
+        let mut entries =
+            self.stop_with(account, now, |entry| Ok::<_, StorageError>(vec![entry]))?;
+        Ok(entries.remove(0))
+    }
+
+    /// Stops the running entry for the account like `stop`, but records it as one entry per day
+    /// if it spans midnight in the given time zone.
+    pub fn stop_split_days<Tz: TimeZone>(
+        &self,
+        account: Option<&str>,
+        now: DateTime<Utc>,
+        tz: &Tz,
+    ) -> Result<Vec<Entry>, StorageError> {
+        self.stop_with(account, now, |entry| Ok(split_at_midnight(&entry, tz)))
+    }
+
+    /// Stops the running entry for the account like `stop`, recording the entries the completed
+    /// entry is turned into by the given function instead. Nothing is written if the function
+    /// fails.
+    pub fn stop_with<E: From<StorageError>>(
+        &self,
+        account: Option<&str>,
+        now: DateTime<Utc>,
+        finish: impl FnOnce(Entry) -> Result<Vec<Entry>, E>,
+    ) -> Result<Vec<Entry>, E> {
+        let mut running_entries = self.read_raw_lines::<RunningEntry>(&self.running_path)?;
+        let position = running_position(&running_entries, account)?;
+
+        // Extract the running entry and remove it from the collection
+        let (line, running_entry) = running_entries.remove(position);
+
+        // Create the new complete entries
+        let stop = self.stop_time(&running_entry, now);
+        let entries = finish(running_entry.into_entry(stop))?;
+
+        if self.single_file {
+            self.complete_in_place(vec![(line, &entries)])?;
+            return Ok(entries);
+        }
+        for entry in &entries {
+            self.append_entry(entry)?;
+        }
+        self.write_lines::<RunningEntry>(
+            &self.running_path,
+            running_entries.into_iter().map(|(line, _)| line),
+        )?;
+        Ok(entries)
+    }
+
+    /// Stops every running entry at the same time and records them as completed entries.
+    pub fn stop_all(&self, now: DateTime<Utc>) -> Result<Vec<Entry>, StorageError> {
+        Ok(self
+            .stop_all_with(now, |entry| Ok::<_, StorageError>(vec![entry]))?
+            .into_iter()
+            .flatten()
+            .collect())
+    }
+
+    /// Stops every running entry like `stop_all`, recording the entries each completed entry is
+    /// turned into by the given function instead. Returns the recorded entries for each running
+    /// entry, in the order of the running file. Nothing is written if the function fails for any
+    /// of them.
+    pub fn stop_all_with<E: From<StorageError>>(
+        &self,
+        now: DateTime<Utc>,
+        mut finish: impl FnMut(Entry) -> Result<Vec<Entry>, E>,
+    ) -> Result<Vec<Vec<Entry>>, E> {
+        // Every line is rewritten, so none may be skipped
+        let running_entries = self.read_raw_lines::<RunningEntry>(&self.running_path)?;
+        if running_entries.is_empty() {
+            return Err(StorageError::NoRunningEntries.into());
+        }
+
+        let (lines, running_entries): (Vec<String>, Vec<RunningEntry>) =
+            running_entries.into_iter().unzip();
+        let stopped: Vec<Vec<Entry>> = running_entries
+            .into_iter()
+            .map(|running_entry| {
+                let stop = self.stop_time(&running_entry, now);
+                finish(running_entry.into_entry(stop))
+            })
+            .collect::<Result<_, E>>()?;
+        if self.single_file {
+            self.complete_in_place(lines.into_iter().zip(&stopped).collect())?;
+            return Ok(stopped);
+        }
+        for entry in stopped.iter().flatten() {
+            self.append_entry(entry)?;
+        }
+        self.write_lines::<RunningEntry>(&self.running_path, std::iter::empty())?;
+        Ok(stopped)
+    }
+
+    /// Stops the running entries which are due to be stopped by `now`, each at the time given by
+    /// `due`, recording the entries each completed entry is turned into by the given function.
+    /// Entries without a due time keep running. Returns the recorded entries for each stopped
+    /// entry, like `stop_all_with`.
+    pub fn stop_due(
+        &self,
+        now: DateTime<Utc>,
+        due: impl Fn(&RunningEntry) -> Option<DateTime<Utc>>,
+        mut finish: impl FnMut(Entry) -> Vec<Entry>,
+    ) -> Result<Vec<Vec<Entry>>, StorageError> {
+        let (stopping, running_entries): (Vec<_>, Vec<_>) = self
+            .read_raw_lines::<RunningEntry>(&self.running_path)?
+            .into_iter()
+            .partition(|(_, running_entry)| due(running_entry).is_some_and(|at| at <= now));
+        if stopping.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let (lines, stopped): (Vec<String>, Vec<Vec<Entry>>) = stopping
+            .into_iter()
+            .map(|(line, running_entry)| {
+                let stop = due(&running_entry).unwrap_or(now).max(running_entry.start);
+                (line, finish(running_entry.into_entry(stop)))
+            })
+            .unzip();
+        if self.single_file {
+            self.complete_in_place(lines.into_iter().zip(&stopped).collect())?;
+            return Ok(stopped);
+        }
+        for entry in stopped.iter().flatten() {
+            self.append_entry(entry)?;
+        }
+        self.write_lines::<RunningEntry>(
+            &self.running_path,
+            running_entries.into_iter().map(|(line, _)| line),
+        )?;
+        Ok(stopped)
+    }
+
+    /// The time to stop the running entry at, with the rounding of stops.
+    fn stop_time(&self, running_entry: &RunningEntry, now: DateTime<Utc>) -> DateTime<Utc> {
+        match &self.round_stop {
+            Some(rounding) if now >= running_entry.start => {
+                rounding.apply(now).max(running_entry.start)
+            }
+            _ => now,
+        }
+    }
+
+    /// Reads the values of the lines of the file, skipping the lines that cannot be parsed unless
+    /// strict, see `strict`. Only for reading, changes have to be made with `read_raw_lines`.
+    fn read_lines<T: Record>(&self, path: &Path) -> Result<Vec<T>, StorageError> {
+        if self.strict {
+            return Ok(self
+                .read_raw_lines(path)?
+                .into_iter()
+                .map(|(_, value)| value)
+                .collect());
+        }
+        let mut skipped = Vec::new();
+        let values = self
+            .read_parsed_lines::<T>(path)?
+            .into_iter()
+            .filter_map(|parsed| match parsed.value {
+                Ok(value) => Some(value),
+                Err(_) => {
+                    skipped.push(parsed.number);
+                    None
+                }
+            })
+            .collect();
+        warn_skipped_lines(path, &skipped);
+        Ok(values)
+    }
+
+    /// Reads and parses every line of the file, keeping the original lines next to the parsed
+    /// values. In a single data file only the lines of the kind are read, without their prefix.
+    /// Fails on the first line that cannot be parsed.
+    ///
+    /// The path `-` reads from standard input.
+    fn read_raw_lines<T: Record>(&self, path: &Path) -> Result<Vec<(String, T)>, StorageError> {
+        let parsed = self
+            .read_parsed_lines::<T>(path)?
+            .into_iter()
+            .map(|parsed| match parsed.value {
+                Ok(value) => Ok((parsed.line, value)),
+                Err(err) => {
+                    log::debug!("could not parse line {} {:?}", parsed.number, parsed.line);
+                    Err(StorageError::Parse {
+                        line: parsed.number,
+                        err,
+                    })
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(parsed)
+    }
+
+    /// Reads every line of the kind in the file, dealing with a partial last line first.
+    fn read_parsed_lines<T: Record>(
+        &self,
+        path: &Path,
+    ) -> Result<Vec<ParsedLine<T>>, StorageError> {
+        let (mut lines, unterminated) = self.read_all_lines(path)?;
+        let partial = unterminated
+            && lines.last().is_some_and(|line| {
+                let line = if self.single_file {
+                    T::strip_prefix(line)
+                } else {
+                    Some(line.as_str())
+                };
+                line.is_some_and(|line| {
+                    migrations::parse_header(line).is_none() && T::from_str(line).is_err()
+                })
+            });
+        if partial {
+            let line = lines.pop().expect("the file has a last line");
+            self.quarantine_partial_line(path, &lines, line)?;
+        }
+        let total = lines.len();
+        let parsed = self.parse_lines::<T>(lines)?;
+        if parsed.len() < total {
+            log::debug!(
+                "skipped {} lines of the other kind in {}",
+                total - parsed.len(),
+                path.display()
+            );
+        }
+        Ok(parsed)
+    }
+
+    /// Parses each line of the kind. The format version header and, in single-file mode, lines of
+    /// the other kind are skipped.
+    fn parse_lines<T: Record>(
+        &self,
+        lines: Vec<String>,
+    ) -> Result<Vec<ParsedLine<T>>, StorageError> {
+        let mut parsed = Vec::new();
+        let mut interner = Interner::new();
+        for (index, line) in lines.into_iter().enumerate() {
+            // The format version header, see `migrations`
+            if index == 0 {
+                if let Some(version) = migrations::parse_header(&line) {
+                    if version > migrations::CURRENT_VERSION {
+                        return Err(UnsupportedVersion(version).into());
+                    }
+                    continue;
+                }
+            }
+            let line = if self.single_file {
+                match T::strip_prefix(&line) {
+                    Some(line) => line.to_string(),
+                    None => continue,
+                }
+            } else {
+                line
+            };
+            parsed.push(ParsedLine {
+                number: index + 1,
+                value: T::parse_interned(&line, &mut interner),
+                line,
+            });
+        }
+        Ok(parsed)
+    }
+
+    /// Lists every line of the files that cannot be parsed, where reading stops at the first.
+    pub fn invalid_lines(&self) -> Result<Vec<InvalidLine>, StorageError> {
+        let mut invalid = self.invalid_lines_of::<Entry>(&self.entries_path)?;
+        invalid.extend(self.invalid_lines_of::<RunningEntry>(&self.running_path)?);
+        Ok(invalid)
+    }
+
+    fn invalid_lines_of<T: Record>(&self, path: &Path) -> Result<Vec<InvalidLine>, StorageError> {
+        let (lines, _) = self.read_all_lines(path)?;
+        Ok(self
+            .parse_lines::<T>(lines)?
+            .into_iter()
+            .filter_map(|parsed| {
+                Some(InvalidLine {
+                    path: path.to_path_buf(),
+                    number: parsed.number,
+                    err: parsed.value.err()?,
+                    content: parsed.line,
+                })
+            })
+            .collect())
+    }
+
+    /// The line of the file with the number, counting from 1, as it is written, for looking at
+    /// lines that cannot be parsed. `None` if the file has fewer lines.
+    pub fn raw_line(&self, path: &Path, number: usize) -> Result<Option<String>, StorageError> {
+        let (lines, _) = self.read_all_lines(path)?;
+        Ok(number
+            .checked_sub(1)
+            .and_then(|index| lines.into_iter().nth(index)))
+    }
+
+    /// Reads the completed entries of the entries file in the order of the file, skipping the
+    /// lines that cannot be parsed.
+    pub fn valid_entries(&self) -> Result<Vec<Entry>, StorageError> {
+        let (lines, _) = self.read_all_lines(&self.entries_path)?;
+        Ok(self
+            .parse_lines::<Entry>(lines)?
+            .into_iter()
+            .filter_map(|parsed| parsed.value.ok())
+            .collect())
+    }
+
+    /// Reads every line of the file, of any kind, and whether the last line is missing its line
+    /// break.
+    fn read_all_lines(&self, path: &Path) -> Result<(Vec<String>, bool), StorageError> {
+        let contents = self.read_all_text(path)?;
+        let unterminated = !contents.is_empty() && !contents.ends_with('\n');
+        let lines: Vec<&str> = contents.lines().collect();
+        self.check_line_lengths(&lines, 1)?;
+        Ok((
+            lines.into_iter().map(str::to_string).collect(),
+            unterminated,
+        ))
+    }
+
+    /// Fails on the first line longer than the maximum, counting lines from the number.
+    fn check_line_lengths(&self, lines: &[&str], first_line: usize) -> Result<(), StorageError> {
+        match lines
+            .iter()
+            .position(|line| line.len() > self.max_line_length)
+        {
+            Some(index) => Err(StorageError::LineTooLong {
+                line: first_line + index,
+                max: self.max_line_length,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Fails if a file of the size is larger than the maximum.
+    fn check_file_size(&self, path: &Path, size: u64) -> Result<(), StorageError> {
+        if size > self.max_file_size {
+            return Err(StorageError::FileTooLarge {
+                path: path.to_path_buf(),
+                size,
+                max: self.max_file_size,
+            });
+        }
+        Ok(())
+    }
+
+    /// Reads the file as text from the byte offset, a missing file is read as empty.
+    fn read_text_from(&self, path: &Path, offset: u64) -> Result<String, StorageError> {
+        let mut file = match File::open(path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(String::new()),
+            Err(err) => return Err(err.into()),
+        };
+        self.check_file_size(path, file.metadata()?.len().saturating_sub(offset))?;
+        file.seek(SeekFrom::Start(offset))?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        log::debug!(
+            "read {} bytes from {} after skipping {}",
+            contents.len(),
+            path.display(),
+            offset
+        );
+        Ok(contents)
+    }
+
+    /// Reads the whole file as text, with the operations on it applied. The path `-` reads from
+    /// standard input.
+    fn read_all_text(&self, path: &Path) -> Result<String, StorageError> {
+        let contents = self.read_file_text(path)?;
+        let (log, file) = match self.oplog_file(path) {
+            Some(oplog) => oplog,
+            None => return Ok(contents),
+        };
+        let operations = log.operations()?;
+        if !operations.iter().any(|operation| operation.file == file) {
+            return Ok(contents);
+        }
+        let lines = contents.lines().map(str::to_string).collect();
+        Ok(oplog::apply(lines, &operations, file)
+            .into_iter()
+            .map(|line| line + "\n")
+            .collect())
+    }
+
+    /// The operation log and the name of the file in it, if changes to the file go there.
+    fn oplog_file(&self, path: &Path) -> Option<(&OpLog, &'static str)> {
+        let log = self.oplog.as_ref()?;
+        if path == self.entries_path {
+            Some((log, Entry::FILE))
+        } else if path == self.running_path {
+            Some((log, RunningEntry::FILE))
+        } else {
+            None
+        }
+    }
+
+    /// Reads the whole file as text as it is on disk.
+    fn read_file_text(&self, path: &Path) -> Result<String, StorageError> {
+        let contents = if is_stdin(path) {
+            log::debug!("reading standard input");
+            let mut contents = Vec::new();
+            // One byte more than allowed tells whether there is more
+            io::stdin()
+                .lock()
+                .take(self.max_file_size.saturating_add(1))
+                .read_to_end(&mut contents)?;
+            self.check_file_size(path, contents.len() as u64)?;
+            contents
+        } else {
+            self.read_contents(path)?
+        };
+        String::from_utf8(contents)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err).into())
+    }
+
+    /// Deals with an unparsable last line without a line break, as left behind by an interrupted
+    /// write. It is moved to a `.corrupt` file next to the file if enabled, otherwise reading
+    /// fails.
+    fn quarantine_partial_line(
+        &self,
+        path: &Path,
+        lines: &[String],
+        partial: String,
+    ) -> Result<(), StorageError> {
+        let error = StorageError::PartialLine(lines.len() + 1);
+        if !self.quarantine_partial_lines || is_stdin(path) {
+            return Err(error);
+        }
+        let mut corrupt = path.as_os_str().to_owned();
+        corrupt.push(".corrupt");
+        let corrupt = PathBuf::from(corrupt);
+        match self.write_mode {
+            WriteMode::Write => {}
+            WriteMode::DryRun => {
+                println!(
+                    "Would move the incomplete last line of {} to {}",
+                    path.display(),
+                    corrupt.display()
+                );
+                return Ok(());
+            }
+            WriteMode::ReadOnly => return Err(error),
+        }
+
+        let mut file =
+            permissions::private(OpenOptions::new().create(true).append(true)).open(&corrupt)?;
+        writeln!(file, "{}", partial)?;
+        self.write_file(path, lines.to_vec())?;
+        log::warn!(
+            "moved the incomplete last line of {} to {}",
+            path.display(),
+            corrupt.display()
+        );
+        Ok(())
+    }
+
+    /// Reads the contents of the file, decrypted if needed. A missing file is read as empty.
+    fn read_contents(&self, path: &Path) -> Result<Vec<u8>, StorageError> {
+        let mut file = match File::open(path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                log::debug!("{} does not exist, reading it as empty", path.display());
+                return Ok(Vec::new());
+            }
+            Err(err) => return Err(err.into()),
+        };
+        self.check_file_size(path, file.metadata()?.len())?;
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents)?;
+        log::debug!("read {} bytes from {}", contents.len(), path.display());
+        #[cfg(feature = "encryption")]
+        if let Some(key) = &self.key {
+            if Key::is_encrypted(&contents) {
+                return Ok(key.decrypt(&contents)?);
+            }
+        }
+        Ok(contents)
+    }
+
+    fn normalize_file<T: Record>(&self, path: &Path, dry_run: bool) -> Result<usize, StorageError> {
+        let lines = self.read_raw_lines::<T>(path)?;
+        let changed = lines
+            .iter()
+            .filter(|(line, value)| *line != value.to_string())
+            .count();
+        if changed > 0 && !dry_run {
+            self.write_lines::<T>(path, lines.iter().map(|(_, value)| value.to_string()))?;
+        }
+        Ok(changed)
+    }
+
+    /// Replaces the lines of the kind in the file with the lines, each terminated by a line break.
+    /// In a single data file the lines of the other kind are kept, with the running entries at
+    /// the end.
+    ///
+    /// The lines are written to a temporary file next to it first, which then replaces the file,
+    /// so an interrupted write never leaves a truncated file behind.
+    fn write_lines<T: Record>(
+        &self,
+        path: &Path,
+        lines: impl Iterator<Item = String>,
+    ) -> Result<(), StorageError> {
+        if is_stdin(path) {
+            return Err(StorageError::WriteToStdin);
+        }
+        let mut lines: Vec<String> = lines.collect();
+        let mut existing = self.read_all_lines(path)?.0;
+        // Keep the format version header, see `migrations`
+        let header = if existing
+            .first()
+            .is_some_and(|line| migrations::parse_header(line).is_some())
+        {
+            Some(existing.remove(0))
+        } else {
+            None
+        };
+        // The lines of the kind before and after, for the audit log
+        let audited = self.audit.as_ref().map(|log| {
+            let before: Vec<String> = existing
+                .iter()
+                .filter_map(|line| match self.single_file {
+                    true => T::strip_prefix(line),
+                    false => Some(line.as_str()),
+                })
+                .map(str::to_string)
+                .collect();
+            (log, before, lines.clone())
+        });
+        if self.single_file {
+            let (mut kept, mut written) = (Vec::new(), Vec::new());
+            for line in existing {
+                if T::strip_prefix(&line).is_none() {
+                    kept.push(line);
+                }
+            }
+            for line in lines {
+                written.push(format!("{}{}", T::PREFIX, line));
+            }
+            lines = if T::PREFIX.is_empty() {
+                written.into_iter().chain(kept).collect()
+            } else {
+                kept.into_iter().chain(written).collect()
+            };
+        }
+        if let Some(header) = header {
+            lines.insert(0, header);
+        }
+        self.write_file(path, lines)?;
+        if let Some((log, before, after)) = audited.filter(|_| self.write_mode == WriteMode::Write)
+        {
+            let changes = audit::changes(&before, &after, |line| {
+                T::from_str(line).ok().map(|value| value.audit_key())
+            });
+            log.record(crate::now(), T::FILE, changes)?;
+        }
+        Ok(())
+    }
+
+    /// Replaces the content of the file with the lines, each terminated by a line break. With an
+    /// operation log the changed lines are recorded in it instead.
+    fn write_file(&self, path: &Path, lines: Vec<String>) -> Result<(), StorageError> {
+        log::debug!("writing {} lines to {}", lines.len(), path.display());
+        match self.write_mode {
+            WriteMode::Write => {}
+            WriteMode::DryRun => {
+                let contents: String = lines.into_iter().map(|line| line + "\n").collect();
+                print!("Would write {}:\n{}", path.display(), contents);
+                return Ok(());
+            }
+            WriteMode::ReadOnly => return Err(StorageError::ReadOnly),
+        }
+        if let Some((log, file)) = self.oplog_file(path) {
+            let (before, _) = self.read_all_lines(path)?;
+            log.record(crate::now(), file, oplog::diff(&before, &lines))?;
+            return Ok(());
+        }
+        self.replace_file(path, lines)
+    }
+
+    /// Replaces the file on disk with the lines, each terminated by a line break.
+    fn replace_file(&self, path: &Path, lines: Vec<String>) -> Result<(), StorageError> {
+        let contents: String = lines.into_iter().map(|line| line + "\n").collect();
+        create_parent_dir(path)?;
+        #[cfg(feature = "encryption")]
+        let contents = match &self.key {
+            Some(key) => key.encrypt(contents.as_bytes())?,
+            None => contents.into_bytes(),
+        };
+        let mut temporary = path.as_os_str().to_owned();
+        temporary.push(".tmp");
+        permissions::write_private(Path::new(&temporary), contents)?;
+        permissions::keep_permissions(path, Path::new(&temporary))?;
+        fs::rename(&temporary, path)?;
+        Ok(())
+    }
+
+    fn append_line<T: Record>(&self, path: &Path, value: &T) -> Result<(), StorageError> {
+        if is_stdin(path) {
+            return Err(StorageError::WriteToStdin);
+        }
+        match self.write_mode {
+            WriteMode::Write => {}
+            WriteMode::DryRun => {
+                println!("Would append to {}:\n{}", path.display(), value);
+                return Ok(());
+            }
+            WriteMode::ReadOnly => return Err(StorageError::ReadOnly),
+        }
+        if let Some((log, file)) = self.oplog_file(path) {
+            log.record(crate::now(), file, vec![(Op::Add, value.to_string())])?;
+            if let Some(log) = &self.audit {
+                log.record(crate::now(), T::FILE, vec![(None, Some(value.to_string()))])?;
+            }
+            return Ok(());
+        }
+        // Encrypted files can't be appended to, so they are rewritten instead
+        #[cfg(feature = "encryption")]
+        if self.key.is_some() {
+            let mut lines: Vec<String> = self
+                .read_raw_lines::<T>(path)?
+                .into_iter()
+                .map(|(line, _)| line)
+                .collect();
+            lines.push(value.to_string());
+            return self.write_lines::<T>(path, lines.into_iter());
+        }
+        log::debug!("appending a line to {}", path.display());
+        create_parent_dir(path)?;
+        let prefix = if self.single_file { T::PREFIX } else { "" };
+        let mut file =
+            permissions::private(OpenOptions::new().create(true).read(true).append(true))
+                .open(path)?;
+        if !ends_with_line_break(&mut file)? {
+            // Fail on or quarantine a partial last line before it ends up in the middle
+            if self.single_file {
+                self.read_raw_lines::<Entry>(path)?;
+                self.read_raw_lines::<RunningEntry>(path)?;
+            } else {
+                self.read_raw_lines::<T>(path)?;
+            }
+            if !ends_with_line_break(&mut file)? {
+                writeln!(file)?;
+            }
+        }
+        writeln!(file, "{}{}", prefix, value)?;
+        if let Some(log) = &self.audit {
+            log.record(crate::now(), T::FILE, vec![(None, Some(value.to_string()))])?;
+        }
+        Ok(())
+    }
+}
+
+/// Renames the account and its sub-accounts in the lines, returning the number of renamed lines.
+fn rename_lines<T: Record, A: AsRef<str> + From<String>>(
+    lines: &mut [(String, T)],
+    account: impl Fn(&mut T) -> &mut A,
+    old: &str,
+    new: &str,
+) -> usize {
+    let mut renamed = 0;
+    for (line, value) in lines {
+        let account = account(value);
+        if is_within_account(account.as_ref(), old) {
+            *account = format!("{}{}", new, &account.as_ref()[old.len()..]).into();
+            *line = value.to_string();
+            renamed += 1;
+        }
+    }
+    renamed
+}
+
+/// Finds the last completed entry for the account, or the last one of all if no account is given.
+fn last_position(
+    entries: &[(String, Entry)],
+    account: Option<&str>,
+) -> Result<usize, StorageError> {
+    entries
+        .iter()
+        .enumerate()
+        .filter(|(_, (_, entry))| account.is_none_or(|account| *entry.account == *account))
+        .max_by_key(|(_, (_, entry))| entry.start)
+        .map(|(position, _)| position)
+        .ok_or_else(|| StorageError::NoEntries(account.map(str::to_string)))
+}
+
+/// Finds the running entry for the account, which may only be omitted when there is exactly one
+/// running entry.
+fn running_position(
+    running_entries: &[(String, RunningEntry)],
+    account: Option<&str>,
+) -> Result<usize, StorageError> {
+    // Error immediately if there are no running entries
+    if running_entries.is_empty() {
+        return Err(StorageError::NoRunningEntries);
+    }
+
+    match account {
+        Some(account) => running_entries
+            .iter()
+            .position(|(_, entry)| &*entry.account == account)
+            .ok_or_else(|| StorageError::NotRunning(account.to_string())),
+        None if running_entries.len() != 1 => Err(StorageError::AccountRequired),
+        None => Ok(0),
+    }
+}
+
+/// A kind of line in the data files.
+trait Record: FromStr<Err = ParseError> + fmt::Display {
+    /// The prefix of the lines of this kind in a single data file.
+    const PREFIX: &'static str;
+    /// The name of the file of this kind in the audit log.
+    const FILE: &'static str;
+
+    /// What the line is about, so a line replacing it with the same key is a change of it in the
+    /// audit log.
+    fn audit_key(&self) -> String;
+
+    /// Parses a line like `from_str`, sharing what values of the kind have in common with those
+    /// parsed before with the interner.
+    fn parse_interned(line: &str, _interner: &mut Interner) -> Result<Self, ParseError> {
+        Self::from_str(line)
+    }
+
+    /// Removes the prefix if the line in a single data file is of this kind.
+    fn strip_prefix(line: &str) -> Option<&str> {
+        line.strip_prefix(Self::PREFIX)
+    }
+}
+
+impl Record for Entry {
+    const PREFIX: &'static str = "";
+    const FILE: &'static str = "entries";
+
+    fn audit_key(&self) -> String {
+        self.id()
+    }
+
+    fn parse_interned(line: &str, interner: &mut Interner) -> Result<Self, ParseError> {
+        Entry::parse(line).map(|entry| entry.to_interned_entry(interner))
+    }
+
+    fn strip_prefix(line: &str) -> Option<&str> {
+        match RunningEntry::strip_prefix(line) {
+            Some(_) => None,
+            None => Some(line),
+        }
+    }
+}
+
+impl Record for RunningEntry {
+    const PREFIX: &'static str = "RUNNING ";
+    const FILE: &'static str = "running";
+
+    fn audit_key(&self) -> String {
+        self.account.to_string()
+    }
+}
+
+/// Whether the path refers to standard input rather than a file.
+fn is_stdin(path: &Path) -> bool {
+    path == Path::new("-")
+}
+
+/// Warns about the lines of the file skipped for not being parsable, by their numbers.
+fn warn_skipped_lines(path: &Path, numbers: &[usize]) {
+    if numbers.is_empty() {
+        return;
+    }
+    let numbers: Vec<String> = numbers.iter().map(usize::to_string).collect();
+    log::warn!(
+        "skipped {} of {} which cannot be parsed: {}",
+        if numbers.len() == 1 {
+            "a line"
+        } else {
+            "lines"
+        },
+        path.display(),
+        numbers.join(", ")
+    );
+}
+
+/// Whether the file is empty or ends with a line break, so a line can be appended to it.
+fn ends_with_line_break(file: &mut File) -> io::Result<bool> {
+    if file.metadata()?.len() == 0 {
+        return Ok(true);
+    }
+    file.seek(SeekFrom::End(-1))?;
+    let mut last = [0];
+    file.read_exact(&mut last)?;
+    Ok(last[0] == b'\n')
+}
+
+fn create_parent_dir(path: &Path) -> io::Result<()> {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => fs::create_dir_all(dir),
+        _ => Ok(()),
+    }
+}
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum StorageError {
+    Io(io::Error),
+    Parse {
+        line: usize,
+        err: ParseError,
+    },
+    AlreadyRunning(String),
+    NotRunning(String),
+    NoRunningEntries,
+    /// There are no completed entries, for the account if one is given.
+    NoEntries(Option<String>),
+    AccountRequired,
+    #[cfg(feature = "encryption")]
+    Encryption(EncryptionError),
+    /// An additional entries file in the compact format cannot be read.
+    #[cfg(feature = "compact")]
+    Compact(PathBuf, DecodeError),
+    /// The last line of the file is incomplete, probably from an interrupted write.
+    PartialLine(usize),
+    /// The file is in a newer version of the format, see `migrations`.
+    UnsupportedVersion(UnsupportedVersion),
+    /// The entry does not last any time, and the zero duration policy refuses it.
+    ZeroDuration,
+    /// The entry would not be recorded correctly.
+    Invalid(ValidationError),
+    /// No single entry is selected, see `Selector`.
+    Select(SelectError),
+    /// The time to split at is not within the entry.
+    OutsideEntry(DateTime<Utc>),
+    /// The changed entry would overlap this entry.
+    Overlap(Box<Entry>),
+    WriteToStdin,
+    /// The files were opened read-only.
+    ReadOnly,
+    /// The line is longer than the maximum length, see `Storage::limits`.
+    LineTooLong {
+        line: usize,
+        max: usize,
+    },
+    /// The file is larger than the maximum size, see `Storage::limits`.
+    FileTooLarge {
+        path: PathBuf,
+        size: u64,
+        max: u64,
+    },
+}
+
+impl From<io::Error> for StorageError {
+    fn from(err: io::Error) -> Self {
+        StorageError::Io(err)
+    }
+}
+
+impl From<ValidationError> for StorageError {
+    fn from(err: ValidationError) -> Self {
+        StorageError::Invalid(err)
+    }
+}
+
+impl From<SelectError> for StorageError {
+    fn from(err: SelectError) -> Self {
+        StorageError::Select(err)
+    }
+}
+
+impl From<UnsupportedVersion> for StorageError {
+    fn from(err: UnsupportedVersion) -> Self {
+        StorageError::UnsupportedVersion(err)
+    }
+}
+
+#[cfg(feature = "encryption")]
+impl From<EncryptionError> for StorageError {
+    fn from(err: EncryptionError) -> Self {
+        StorageError::Encryption(err)
+    }
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::Io(err) => err.fmt(f),
+            StorageError::Parse { line, err } => write!(f, "line {}: {}", line, err),
+            StorageError::AlreadyRunning(account) => write!(
+                f,
+                r#"there is already a running entry for the account "{}""#,
+                account
+            ),
+            StorageError::NotRunning(account) => write!(
+                f,
+                r#"no running entries for the account "{}" were found"#,
+                account
+            ),
+            StorageError::NoRunningEntries => write!(f, "no running entries"),
+            StorageError::NoEntries(Some(account)) => {
+                write!(f, r#"no entries for the account "{}" were found"#, account)
+            }
+            StorageError::NoEntries(None) => write!(f, "no entries"),
+            StorageError::AccountRequired => write!(
+                f,
+                "account must be specified when there is more than one running entry"
+            ),
+            #[cfg(feature = "encryption")]
+            StorageError::Encryption(err) => err.fmt(f),
+            #[cfg(feature = "compact")]
+            StorageError::Compact(path, err) => write!(f, "{}: {}", path.display(), err),
+            StorageError::PartialLine(line) => write!(
+                f,
+                "line {} is incomplete, probably because writing it was interrupted",
+                line
+            ),
+            StorageError::UnsupportedVersion(err) => err.fmt(f),
+            StorageError::ZeroDuration => write!(f, "the entry does not last any time"),
+            StorageError::Invalid(err) => err.fmt(f),
+            StorageError::Select(err) => write!(f, "{}", err),
+            StorageError::OutsideEntry(at) => write!(
+                f,
+                "{} is not within the entry",
+                at.with_timezone(&Local).format("%Y-%m-%d %H:%M")
+            ),
+            StorageError::Overlap(entry) => write!(f, "the entry would overlap {}", entry),
+            StorageError::WriteToStdin => write!(f, "cannot write to standard input"),
+            StorageError::ReadOnly => write!(f, "the data files are read-only"),
+            StorageError::LineTooLong { line, max } => write!(
+                f,
+                "line {} is longer than the maximum of {} bytes, the file may be corrupted",
+                line, max
+            ),
+            StorageError::FileTooLarge { path, size, max } => write!(
+                f,
+                "{} is {} bytes, larger than the maximum of {} bytes",
+                path.display(),
+                size,
+                max
+            ),
+        }
+    }
+}
+
+impl Error for StorageError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            StorageError::Io(err) => Some(err),
+            StorageError::Parse { err, .. } => Some(err),
+            #[cfg(feature = "encryption")]
+            StorageError::Encryption(err) => Some(err),
+            #[cfg(feature = "compact")]
+            StorageError::Compact(_, err) => Some(err),
+            StorageError::UnsupportedVersion(err) => Some(err),
+            StorageError::Invalid(err) => Some(err),
+            StorageError::Select(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::sync::Arc;
+
+    /// Creates storage backed by files in a fresh temporary directory.
+    fn temporary_storage(name: &str) -> Storage {
+        let dir = std::env::temp_dir().join(format!("timetracker-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        Storage::new(dir.join("entries"), dir.join("running"))
+    }
+
+    fn datetime(s: &str) -> DateTime<Utc> {
+        DateTime::from_str(s).unwrap()
+    }
+
+    fn running_entry(account: &str, start: &str) -> RunningEntry {
+        RunningEntry {
+            start: datetime(start),
+            account: account.into(),
+            tags: Vec::new(),
+            billable: false,
+            category: None,
+            description: None,
+            meta: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn missing_files_have_no_entries() {
+        let storage = temporary_storage("missing");
+
+        assert!(storage.entries().unwrap().is_empty());
+        assert!(storage.running_entries().unwrap().is_empty());
+    }
+
+    #[test]
+    fn start_and_stop() {
+        let storage = temporary_storage("start-stop");
+
+        storage
+            .start(running_entry("Time Tracker", "2021-07-03T10:00:00Z"))
+            .unwrap();
+        let entry = storage
+            .stop(None, datetime("2021-07-03T13:00:00Z"))
+            .unwrap();
+
+        assert_eq!(storage.entries().unwrap(), vec![entry]);
+        assert!(storage.running_entries().unwrap().is_empty());
+    }
+
+    #[test]
+    fn start_twice() {
+        let storage = temporary_storage("start-twice");
+
+        storage
+            .start(running_entry("A", "2021-07-03T10:00:00Z"))
+            .unwrap();
+        let result = storage.start(running_entry("A", "2021-07-03T11:00:00Z"));
+
+        assert!(matches!(result, Err(StorageError::AlreadyRunning(_))));
+    }
+
+    #[test]
+    fn start_duplicate() {
+        let storage = temporary_storage("start-duplicate");
+
+        let first = storage
+            .start(running_entry("A", "2021-07-03T10:00:00Z"))
+            .unwrap();
+        let second = storage
+            .start(running_entry("A", "2021-07-03T10:00:01Z"))
+            .unwrap();
+
+        assert_eq!(second, first);
+        assert_eq!(storage.running_entries().unwrap(), vec![first]);
+        let storage = storage.duplicate_window(Duration::zero());
+        assert!(matches!(
+            storage.start(running_entry("A", "2021-07-03T10:00:01Z")),
+            Err(StorageError::AlreadyRunning(_))
+        ));
+    }
+
+    #[test]
+    fn stop_requires_account_with_multiple_running() {
+        let storage = temporary_storage("stop-ambiguous");
+
+        storage
+            .start(running_entry("A", "2021-07-03T10:00:00Z"))
+            .unwrap();
+        storage
+            .start(running_entry("B", "2021-07-03T11:00:00Z"))
+            .unwrap();
+
+        assert!(matches!(
+            storage.stop(None, datetime("2021-07-03T12:00:00Z")),
+            Err(StorageError::AccountRequired)
+        ));
+        assert_eq!(
+            storage
+                .stop(Some("B"), datetime("2021-07-03T12:00:00Z"))
+                .unwrap()
+                .account
+                .as_ref(),
+            "B"
+        );
+        assert_eq!(storage.running_entries().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn start_after_stop_appends_new_line() {
+        let storage = temporary_storage("start-after-stop");
+
+        storage
+            .start(running_entry("A", "2021-07-03T10:00:00Z"))
+            .unwrap();
+        storage
+            .start(running_entry("B", "2021-07-03T10:00:00Z"))
+            .unwrap();
+        storage
+            .stop(Some("A"), datetime("2021-07-03T12:00:00Z"))
+            .unwrap();
+        storage
+            .start(running_entry("C", "2021-07-03T12:00:00Z"))
+            .unwrap();
+
+        assert_eq!(storage.running_entries().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn stop_preserves_other_running_lines() {
+        let storage = temporary_storage("stop-preserve");
+        fs::write(
+            storage.running_path(),
+            "2021-07-03T12:00:00+02:00 A\n2021-07-03T10:30:00.5Z B\n",
+        )
+        .unwrap();
+
+        storage
+            .stop(Some("B"), datetime("2021-07-03T12:00:00Z"))
+            .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(storage.running_path()).unwrap(),
+            "2021-07-03T12:00:00+02:00 A\n"
+        );
+    }
+
+    #[test]
+    fn stop_split_at_midnight() {
+        let storage = temporary_storage("stop-split");
+        storage
+            .append_running_entry(&running_entry("A", "2021-07-03T20:00:00Z"))
+            .unwrap();
+
+        let entries = storage
+            .stop_split_days(None, datetime("2021-07-04T02:00:00Z"), &Utc)
+            .unwrap();
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            fs::read_to_string(storage.entries_path()).unwrap(),
+            "2021-07-03T20:00:00Z 2021-07-04T00:00:00Z A\n\
+             2021-07-04T00:00:00Z 2021-07-04T02:00:00Z A\n"
+        );
+        assert_eq!(fs::read_to_string(storage.running_path()).unwrap(), "");
+    }
+
+    #[test]
+    fn update_last_entry_of_account() {
+        let storage = temporary_storage("update-last");
+        fs::write(
+            storage.entries_path(),
+            "2021-07-03T10:00:00Z 2021-07-03T11:00:00Z A ; first\n\
+             2021-07-03T11:00:00+00:00 2021-07-03T12:00:00Z B\n\
+             2021-07-03T12:00:00Z 2021-07-03T13:00:00Z A ; second\n",
+        )
+        .unwrap();
+
+        let entry = storage
+            .update_last_entry(Some("B"), |entry| entry.annotate("note"))
+            .unwrap();
+        storage
+            .update_last_entry(None, |entry| entry.annotate("more"))
+            .unwrap();
+
+        assert_eq!(entry.description.as_deref(), Some("note"));
+        assert_eq!(
+            fs::read_to_string(storage.entries_path()).unwrap(),
+            "2021-07-03T10:00:00Z 2021-07-03T11:00:00Z A ; first\n\
+             2021-07-03T11:00:00Z 2021-07-03T12:00:00Z B ; note\n\
+             2021-07-03T12:00:00Z 2021-07-03T13:00:00Z A ; second; more\n"
+        );
+        assert!(matches!(
+            storage.update_last_entry(Some("C"), |_| ()),
+            Err(StorageError::NoEntries(Some(_)))
+        ));
+    }
+
+    #[test]
+    fn split_last_entry() {
+        let storage = temporary_storage("split");
+        fs::write(
+            storage.entries_path(),
+            "2021-07-03T10:00:00Z 2021-07-03T12:00:00Z A\n\
+             2021-07-03T08:00:00+00:00 2021-07-03T09:00:00Z B\n",
+        )
+        .unwrap();
+
+        storage
+            .split_entry(&Selector::Last, datetime("2021-07-03T11:00:00Z"), |entry| {
+                entry.account = "C".into()
+            })
+            .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(storage.entries_path()).unwrap(),
+            "2021-07-03T10:00:00Z 2021-07-03T11:00:00Z A\n\
+             2021-07-03T11:00:00Z 2021-07-03T12:00:00Z C\n\
+             2021-07-03T08:00:00+00:00 2021-07-03T09:00:00Z B\n"
+        );
+        let b = Entry::from_str("2021-07-03T08:00:00Z 2021-07-03T09:00:00Z B").unwrap();
+        let selector = Selector::Id(b.id()[..6].to_string());
+        assert!(matches!(
+            storage.split_entry(&selector, datetime("2021-07-03T10:00:00Z"), |_| ()),
+            Err(StorageError::OutsideEntry(_))
+        ));
+        storage
+            .split_entry(&selector, datetime("2021-07-03T08:30:00Z"), |_| ())
+            .unwrap();
+        assert_eq!(
+            fs::read_to_string(storage.entries_path())
+                .unwrap()
+                .lines()
+                .count(),
+            4
+        );
+    }
+
+    #[test]
+    fn reassign_time_range() {
+        let storage = temporary_storage("reassign");
+        fs::write(
+            storage.entries_path(),
+            "2021-07-03T08:00:00+00:00 2021-07-03T09:00:00Z A\n\
+             2021-07-03T10:00:00Z 2021-07-03T13:30:00Z A #dev\n\
+             2021-07-03T13:30:00Z 2021-07-03T14:00:00Z C\n\
+             2021-07-03T14:00:00Z 2021-07-03T16:00:00Z B\n",
+        )
+        .unwrap();
+
+        let reassigned = storage
+            .reassign(
+                datetime("2021-07-03T13:00:00Z")..datetime("2021-07-03T15:00:00Z"),
+                |entry| entry.account = "C".into(),
+            )
+            .unwrap();
+
+        assert_eq!(reassigned.len(), 2);
+        assert_eq!(
+            fs::read_to_string(storage.entries_path()).unwrap(),
+            "2021-07-03T08:00:00+00:00 2021-07-03T09:00:00Z A\n\
+             2021-07-03T10:00:00Z 2021-07-03T13:00:00Z A #dev\n\
+             2021-07-03T13:00:00Z 2021-07-03T13:30:00Z C #dev\n\
+             2021-07-03T13:30:00Z 2021-07-03T14:00:00Z C\n\
+             2021-07-03T14:00:00Z 2021-07-03T15:00:00Z C\n\
+             2021-07-03T15:00:00Z 2021-07-03T16:00:00Z B\n"
+        );
+    }
+
+    #[test]
+    fn trim_last_entry() {
+        let storage = temporary_storage("trim");
+        fs::write(
+            storage.entries_path(),
+            "2021-07-03T10:00:00Z 2021-07-03T12:00:00Z A\n\
+             2021-07-03T12:30:00Z 2021-07-03T13:00:00Z B\n",
+        )
+        .unwrap();
+
+        let trimmed = storage
+            .trim_last_entry(
+                Some("A"),
+                Duration::minutes(10),
+                Duration::minutes(-15),
+                false,
+            )
+            .unwrap();
+        assert_eq!(
+            trimmed.to_string(),
+            "2021-07-03T10:10:00Z 2021-07-03T11:45:00Z A"
+        );
+        assert_eq!(
+            fs::read_to_string(storage.entries_path()).unwrap(),
+            "2021-07-03T10:10:00Z 2021-07-03T11:45:00Z A\n\
+             2021-07-03T12:30:00Z 2021-07-03T13:00:00Z B\n"
+        );
+
+        assert!(matches!(
+            storage.trim_last_entry(Some("A"), Duration::zero(), Duration::hours(1), false),
+            Err(StorageError::Overlap(entry)) if &*entry.account == "B"
+        ));
+        assert!(storage
+            .trim_last_entry(Some("A"), Duration::zero(), Duration::hours(1), true)
+            .is_ok());
+        assert!(matches!(
+            storage.trim_last_entry(None, Duration::minutes(30), Duration::zero(), false),
+            Err(StorageError::ZeroDuration)
+        ));
+        assert!(matches!(
+            storage.trim_last_entry(None, Duration::hours(1), Duration::zero(), false),
+            Err(StorageError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn merge_entries() {
+        let storage = temporary_storage("merge");
+        fs::write(
+            storage.entries_path(),
+            "2021-07-03T10:00:00Z 2021-07-03T11:00:00Z A\n\
+             2021-07-03T13:00:00+00:00 2021-07-03T14:00:00Z A\n\
+             2021-07-03T11:02:00Z 2021-07-03T12:00:00Z A #dev\n\
+             2021-07-03T12:01:00Z 2021-07-03T12:30:00Z A\n\
+             2021-07-03T12:30:00Z 2021-07-03T12:45:00Z B\n",
+        )
+        .unwrap();
+
+        let merged = storage.merge(Duration::minutes(5)).unwrap();
+
+        assert_eq!(merged.len(), 1);
+        assert_eq!(
+            fs::read_to_string(storage.entries_path()).unwrap(),
+            "2021-07-03T10:00:00Z 2021-07-03T12:30:00Z A #dev\n\
+             2021-07-03T13:00:00+00:00 2021-07-03T14:00:00Z A\n\
+             2021-07-03T12:30:00Z 2021-07-03T12:45:00Z B\n"
+        );
+        assert!(storage.merge(Duration::minutes(5)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn clean_entries() {
+        let storage = temporary_storage("clean");
+        let contents = "2021-07-03T10:00:00+00:00 2021-07-03T11:00:00Z A\n\
+                        2021-07-03T11:00:00Z 2021-07-03T11:00:00Z A #dev ; Standup\n\
+                        2021-07-03T12:00:00Z 2021-07-03T11:30:00Z B\n";
+        fs::write(storage.entries_path(), contents).unwrap();
+
+        assert_eq!(storage.clean(false).unwrap().len(), 2);
+        assert_eq!(
+            fs::read_to_string(storage.entries_path()).unwrap(),
+            "2021-07-03T10:00:00+00:00 2021-07-03T11:00:00Z A\n"
+        );
+
+        fs::write(storage.entries_path(), contents).unwrap();
+        assert_eq!(storage.clean(true).unwrap().len(), 2);
+        assert_eq!(
+            fs::read_to_string(storage.entries_path()).unwrap(),
+            "2021-07-03T10:00:00Z 2021-07-03T11:00:00Z A #dev ; Standup\n"
+        );
+        assert!(storage.clean(true).unwrap().is_empty());
+    }
+
+    #[test]
+    fn zero_duration_policy() {
+        let storage = temporary_storage("zero-duration");
+        storage
+            .start(running_entry("A", "2021-07-03T10:00:00Z"))
+            .unwrap();
+
+        let refusing = storage.clone().zero_duration(ZeroDurationPolicy::Error);
+        assert!(matches!(
+            refusing.stop(None, datetime("2021-07-03T10:00:00Z")),
+            Err(StorageError::ZeroDuration)
+        ));
+
+        let dropping = storage.zero_duration(ZeroDurationPolicy::Drop);
+        dropping
+            .stop(None, datetime("2021-07-03T10:00:00Z"))
+            .unwrap();
+        assert!(dropping.entries().unwrap().is_empty());
+        assert!(dropping.running_entries().unwrap().is_empty());
+    }
+
+    #[test]
+    fn audit_changes() {
+        let storage = temporary_storage("audit");
+        let log = AuditLog::new(
+            storage.entries_path().with_file_name("audit"),
+            vec!["test".to_string()],
+        );
+        let storage = storage.audit(log.clone());
+        storage
+            .start(running_entry("A", "2021-07-03T10:00:00Z"))
+            .unwrap();
+        storage
+            .stop(None, datetime("2021-07-03T11:00:00Z"))
+            .unwrap();
+        storage.sort(false).unwrap();
+        storage.rename_account("A", "B").unwrap();
+
+        let changes: Vec<(String, Option<String>, Option<String>)> = log
+            .changes()
+            .unwrap()
+            .into_iter()
+            .map(|change| (change.file, change.before, change.after))
+            .collect();
+        let line = |s: &str| Some(s.to_string());
+        assert_eq!(
+            changes,
+            vec![
+                ("running".to_string(), None, line("2021-07-03T10:00:00Z A")),
+                (
+                    "entries".to_string(),
+                    None,
+                    line("2021-07-03T10:00:00Z 2021-07-03T11:00:00Z A")
+                ),
+                ("running".to_string(), line("2021-07-03T10:00:00Z A"), None),
+                // Renamed entries are other entries
+                (
+                    "entries".to_string(),
+                    line("2021-07-03T10:00:00Z 2021-07-03T11:00:00Z A"),
+                    None
+                ),
+                (
+                    "entries".to_string(),
+                    None,
+                    line("2021-07-03T10:00:00Z 2021-07-03T11:00:00Z B")
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn record_changes_in_oplog() {
+        let storage = temporary_storage("oplog");
+        let dir = storage.entries_path().with_file_name("ops");
+        let laptop = storage
+            .clone()
+            .oplog(OpLog::new(dir.clone(), "laptop".to_string()));
+        let desktop = storage
+            .clone()
+            .oplog(OpLog::new(dir, "desktop".to_string()));
+        laptop
+            .start(running_entry("A", "2021-07-03T10:00:00Z"))
+            .unwrap();
+        laptop.stop(None, datetime("2021-07-03T11:00:00Z")).unwrap();
+        desktop
+            .start(running_entry("B", "2021-07-03T12:00:00Z"))
+            .unwrap();
+        assert!(!storage.entries_path().exists());
+        assert_eq!(desktop.entries().unwrap().len(), 1);
+        assert_eq!(laptop.running_entries().unwrap().len(), 1);
+
+        assert_eq!(laptop.compact_oplog().unwrap(), 4);
+        assert_eq!(storage.entries().unwrap(), desktop.entries().unwrap());
+        assert_eq!(
+            storage.running_entries().unwrap(),
+            vec![running_entry("B", "2021-07-03T12:00:00Z")]
+        );
+        assert_eq!(desktop.compact_oplog().unwrap(), 0);
+    }
+
+    #[test]
+    fn round_starts_and_stops() {
+        let storage = temporary_storage("rounding").rounding(
+            Some("down 5m".parse().unwrap()),
+            Some("up 5m".parse().unwrap()),
+        );
+        let started = storage
+            .start(running_entry("A", "2021-07-03T10:03:00Z"))
+            .unwrap();
+        assert_eq!(started.start, datetime("2021-07-03T10:00:00Z"));
+        let stopped = storage
+            .stop(None, datetime("2021-07-03T10:41:10Z"))
+            .unwrap();
+        assert_eq!(stopped.stop, datetime("2021-07-03T10:45:00Z"));
+
+        // Stops are not rounded to before the start
+        let storage = storage.rounding(None, Some("down 1h".parse().unwrap()));
+        storage
+            .start(running_entry("B", "2021-07-03T11:10:00Z"))
+            .unwrap();
+        let stopped = storage
+            .stop(None, datetime("2021-07-03T11:20:00Z"))
+            .unwrap();
+        assert_eq!(stopped.stop, stopped.start);
+    }
+
+    #[test]
+    fn read_entries_where() {
+        let storage = temporary_storage("where");
+        fs::write(
+            storage.entries_path(),
+            "# timetracker entries format 1\n\
+             2021-07-03T08:00:00Z 2021-07-03T09:00:00Z A\n\
+             2021-07-03T10:00:00Z 2021-07-03T11:00:00Z B #x ; Review\n\
+             2021-07-03T12:00:00Z 2021-07-03T13:00:00Z A\n",
+        )
+        .unwrap();
+
+        let entries = storage
+            .entries_where(|entry| entry.account() == "B")
+            .unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries, storage.entries().unwrap()[1..2]);
+
+        fs::write(storage.entries_path(), "2021-07-03T08:00:00Z nope A\n").unwrap();
+        assert!(storage.entries_where(|_| true).unwrap().is_empty());
+        assert!(matches!(
+            storage.strict(true).entries_where(|_| true),
+            Err(StorageError::Parse { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn read_entries_since_with_index() {
+        let storage = temporary_storage("index").index(true);
+        fs::write(
+            storage.entries_path(),
+            "2021-07-01T10:00:00Z 2021-07-01T11:00:00Z A\n\
+             2021-07-02T22:00:00Z 2021-07-03T02:00:00Z B\n\
+             2021-07-03T10:00:00Z 2021-07-03T11:00:00Z C\n",
+        )
+        .unwrap();
+        let accounts = |entries: Vec<Entry>| -> Vec<String> {
+            entries
+                .into_iter()
+                .map(|entry| entry.account.to_string())
+                .collect()
+        };
+        let since = datetime("2021-07-03T01:00:00Z");
+
+        assert_eq!(
+            accounts(storage.entries_since(since, |_| true).unwrap()),
+            vec!["B", "C"]
+        );
+        let index_path = Index::path(storage.entries_path());
+        let metadata = fs::metadata(storage.entries_path()).unwrap();
+        assert!(Index::load(&index_path).unwrap().is_current(&metadata));
+
+        // Appending keeps the index up to date
+        storage
+            .append_entry(&Entry::from_str("2021-07-04T10:00:00Z 2021-07-04T11:00:00Z D").unwrap())
+            .unwrap();
+        let metadata = fs::metadata(storage.entries_path()).unwrap();
+        assert!(Index::load(&index_path).unwrap().is_current(&metadata));
+        assert_eq!(
+            accounts(storage.entries_since(since, |_| true).unwrap()),
+            vec!["B", "C", "D"]
+        );
+
+        // Out of order the file is read as a whole
+        storage
+            .append_entry(&Entry::from_str("2021-07-03T05:00:00Z 2021-07-03T06:00:00Z E").unwrap())
+            .unwrap();
+        assert_eq!(
+            accounts(storage.entries_since(since, |_| true).unwrap()),
+            vec!["B", "C", "D", "E"]
+        );
+        let metadata = fs::metadata(storage.entries_path()).unwrap();
+        assert!(!Index::load(&index_path).unwrap().is_current(&metadata));
+
+        let mut visited = Vec::new();
+        storage
+            .visit_entries_since(since, |entry| visited.push(entry.account().to_string()))
+            .unwrap();
+        assert_eq!(visited, vec!["B", "C", "D", "E"]);
+    }
+
+    #[test]
+    fn refuse_invalid_entries() {
+        let storage = temporary_storage("invalid");
+        let mut running = running_entry("A", "2021-07-03T10:00:00Z");
+        running.tags.push("two words".to_string());
+        assert!(matches!(
+            storage.start(running),
+            Err(StorageError::Invalid(ValidationError::InvalidTag(_)))
+        ));
+
+        storage
+            .start(running_entry("A", "2021-07-03T10:00:00Z"))
+            .unwrap();
+        assert!(matches!(
+            storage.stop(None, datetime("2021-07-03T09:00:00Z")),
+            Err(StorageError::Invalid(
+                ValidationError::StopBeforeStart { .. }
+            ))
+        ));
+        assert!(storage.entries().unwrap().is_empty());
+        assert_eq!(storage.running_entries().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn parse_errors_are_chained() {
+        let storage = temporary_storage("chained").strict(true);
+        fs::write(storage.entries_path(), "2021-07-03T10:00:00Z nope A\n").unwrap();
+        let err = storage.entries().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 1: invalid date at column 22: input contains invalid characters"
+        );
+        let parse_err = err.source().unwrap();
+        assert_eq!(parse_err.downcast_ref::<ParseError>().unwrap().column(), 22);
+        assert!(parse_err.source().is_some());
+    }
+
+    #[test]
+    fn update_running_entry() {
+        let storage = temporary_storage("update-running");
+        fs::write(
+            storage.running_path(),
+            "2021-07-03T12:00:00+02:00 A\n2021-07-03T10:30:00Z B\n",
+        )
+        .unwrap();
+
+        storage
+            .update_running_entry(Some("B"), |entry| entry.annotate("pairing"))
+            .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(storage.running_path()).unwrap(),
+            "2021-07-03T12:00:00+02:00 A\n2021-07-03T10:30:00Z B ; pairing\n"
+        );
+        assert!(matches!(
+            storage.update_running_entry(None, |_| ()),
+            Err(StorageError::AccountRequired)
+        ));
+    }
+
+    #[test]
+    fn stop_all_running_entries() {
+        let storage = temporary_storage("stop-all");
+        storage
+            .append_running_entry(&running_entry("A", "2021-07-03T10:00:00Z"))
+            .unwrap();
+        storage
+            .append_running_entry(&running_entry("B", "2021-07-03T11:00:00Z"))
+            .unwrap();
+
+        let entries = storage.stop_all(datetime("2021-07-03T12:00:00Z")).unwrap();
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            fs::read_to_string(storage.entries_path()).unwrap(),
+            "2021-07-03T10:00:00Z 2021-07-03T12:00:00Z A\n\
+             2021-07-03T11:00:00Z 2021-07-03T12:00:00Z B\n"
+        );
+        assert_eq!(fs::read_to_string(storage.running_path()).unwrap(), "");
+        assert!(matches!(
+            storage.stop_all(datetime("2021-07-03T13:00:00Z")),
+            Err(StorageError::NoRunningEntries)
+        ));
+    }
+
+    #[test]
+    fn stop_due_running_entries() {
+        let storage = temporary_storage("stop-due");
+        storage
+            .append_running_entry(&running_entry("A", "2021-07-03T10:00:00Z"))
+            .unwrap();
+        storage
+            .append_running_entry(&running_entry("B", "2021-07-03T19:00:00Z"))
+            .unwrap();
+        let due = |entry: &RunningEntry| Some(entry.start + Duration::hours(8));
+        let tag = |mut entry: Entry| {
+            entry.tags.push("auto-stopped".to_string());
+            vec![entry]
+        };
+
+        let entries = storage
+            .stop_due(datetime("2021-07-03T20:00:00Z"), due, tag)
+            .unwrap();
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(
+            fs::read_to_string(storage.entries_path()).unwrap(),
+            "2021-07-03T10:00:00Z 2021-07-03T18:00:00Z A #auto-stopped\n"
+        );
+        assert_eq!(
+            fs::read_to_string(storage.running_path()).unwrap(),
+            "2021-07-03T19:00:00Z B\n"
+        );
+        assert!(storage
+            .stop_due(datetime("2021-07-03T21:00:00Z"), due, tag)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn rename_account_and_sub_accounts() {
+        let storage = temporary_storage("rename");
+        fs::write(
+            storage.entries_path(),
+            "2021-07-03T08:00:00+00:00 2021-07-03T09:00:00Z Client:Support #dev\n\
+             2021-07-03T09:00:00+00:00 2021-07-03T10:00:00Z ClientB\n\
+             2021-07-03T10:00:00Z 2021-07-03T11:00:00Z Client\n",
+        )
+        .unwrap();
+        storage
+            .start(running_entry("Client:Support", "2021-07-03T11:00:00Z"))
+            .unwrap();
+
+        assert_eq!(storage.rename_account("Client", "ClientA").unwrap(), (2, 1));
+        assert_eq!(
+            fs::read_to_string(storage.entries_path()).unwrap(),
+            "2021-07-03T08:00:00Z 2021-07-03T09:00:00Z ClientA:Support #dev\n\
+             2021-07-03T09:00:00+00:00 2021-07-03T10:00:00Z ClientB\n\
+             2021-07-03T10:00:00Z 2021-07-03T11:00:00Z ClientA\n"
+        );
+        assert_eq!(
+            &*storage.running_entries().unwrap()[0].account,
+            "ClientA:Support"
+        );
+        assert_eq!(storage.rename_account("Other", "Else").unwrap(), (0, 0));
+    }
+
+    #[test]
+    fn update_matching_entries() {
+        let storage = temporary_storage("update");
+        fs::write(
+            storage.entries_path(),
+            "2021-07-03T08:00:00+00:00 2021-07-03T09:00:00Z A\n\
+             2021-07-03T09:00:00Z 2021-07-03T10:00:00Z B #billable\n",
+        )
+        .unwrap();
+        let tag = |entry: &mut Entry| {
+            let untagged = !entry.tags.iter().any(|tag| tag == "billable");
+            if untagged {
+                entry.tags.push("billable".to_string());
+            }
+            untagged
+        };
+
+        let updated = storage.update_entries(tag).unwrap();
+        assert_eq!(updated.len(), 1);
+        assert_eq!(&*updated[0].account, "A");
+        assert_eq!(
+            fs::read_to_string(storage.entries_path()).unwrap(),
+            "2021-07-03T08:00:00Z 2021-07-03T09:00:00Z A #billable\n\
+             2021-07-03T09:00:00Z 2021-07-03T10:00:00Z B #billable\n"
+        );
+        assert_eq!(storage.update_entries(tag).unwrap(), Vec::new());
+    }
+
+    #[test]
+    fn share_account_names() {
+        let storage = temporary_storage("intern");
+        fs::write(
+            storage.entries_path(),
+            "2021-07-03T08:00:00Z 2021-07-03T09:00:00Z A\n\
+             2021-07-03T09:00:00Z 2021-07-03T10:00:00Z B\n\
+             2021-07-03T10:00:00Z 2021-07-03T11:00:00Z A #x\n",
+        )
+        .unwrap();
+
+        for entries in [
+            storage.entries().unwrap(),
+            storage.entries_where(|_| true).unwrap(),
+        ] {
+            assert!(Arc::ptr_eq(&entries[0].account, &entries[2].account));
+            assert!(!Arc::ptr_eq(&entries[0].account, &entries[1].account));
+        }
+    }
+
+    #[test]
+    fn replace_entries_by_id() {
+        let storage = temporary_storage("replace");
+        fs::write(
+            storage.entries_path(),
+            "2021-07-03T08:00:00+00:00 2021-07-03T09:00:00Z A\n\
+             2021-07-03T09:00:00Z 2021-07-03T10:00:00Z B\n",
+        )
+        .unwrap();
+        let old = Entry::from_str("2021-07-03T09:00:00Z 2021-07-03T10:00:00Z B").unwrap();
+        let new = Entry::from_str("2021-07-03T09:00:00Z 2021-07-03T10:30:00Z B ; Longer").unwrap();
+        let replacements: HashMap<String, Entry> = vec![(old.id(), new)].into_iter().collect();
+
+        assert_eq!(storage.replace_entries(&replacements).unwrap(), 1);
+        assert_eq!(
+            fs::read_to_string(storage.entries_path()).unwrap(),
+            "2021-07-03T08:00:00+00:00 2021-07-03T09:00:00Z A\n\
+             2021-07-03T09:00:00Z 2021-07-03T10:30:00Z B ; Longer\n"
+        );
+        assert_eq!(storage.replace_entries(&HashMap::new()).unwrap(), 0);
+    }
+
+    #[test]
+    fn sort_entries_file() {
+        let storage = temporary_storage("sort");
+        fs::write(
+            storage.entries_path(),
+            "2021-07-03T12:00:00+02:00 2021-07-03T13:00:00Z B\n\
+             2021-07-03T09:00:00Z 2021-07-03T10:00:00Z A\n\
+             2021-07-03T10:00:00Z 2021-07-03T11:00:00Z A\n",
+        )
+        .unwrap();
+
+        assert!(storage.sort(true).unwrap());
+        assert!(storage.sort(false).unwrap());
+        assert!(!storage.sort(false).unwrap());
+        assert_eq!(
+            fs::read_to_string(storage.entries_path()).unwrap(),
+            "2021-07-03T09:00:00Z 2021-07-03T10:00:00Z A\n\
+             2021-07-03T12:00:00+02:00 2021-07-03T13:00:00Z B\n\
+             2021-07-03T10:00:00Z 2021-07-03T11:00:00Z A\n"
+        );
+    }
+
+    #[test]
+    fn append_in_order() {
+        let storage = temporary_storage("append-sorted").keep_sorted(true);
+        for line in &[
+            "2021-07-03T10:00:00Z 2021-07-03T11:00:00Z A",
+            "2021-07-03T12:00:00Z 2021-07-03T13:00:00Z C",
+            "2021-07-03T10:00:00Z 2021-07-03T10:30:00Z B",
+            "2021-07-03T08:00:00Z 2021-07-03T09:00:00Z D",
+        ] {
+            storage
+                .append_entry(&Entry::from_str(line).unwrap())
+                .unwrap();
+        }
+
+        assert_eq!(
+            fs::read_to_string(storage.entries_path()).unwrap(),
+            "2021-07-03T08:00:00Z 2021-07-03T09:00:00Z D\n\
+             2021-07-03T10:00:00Z 2021-07-03T11:00:00Z A\n\
+             2021-07-03T10:00:00Z 2021-07-03T10:30:00Z B\n\
+             2021-07-03T12:00:00Z 2021-07-03T13:00:00Z C\n"
+        );
+    }
+
+    #[test]
+    fn normalize_files() {
+        let storage = temporary_storage("normalize");
+        fs::write(
+            storage.entries_path(),
+            "2021-07-03T12:00:00+02:00 2021-07-03T13:00:00.000Z A\n\
+             2021-07-03T13:00:00Z 2021-07-03T14:00:00Z B\n",
+        )
+        .unwrap();
+
+        assert_eq!(storage.normalize(true).unwrap(), (1, 0));
+        assert_eq!(storage.normalize(false).unwrap(), (1, 0));
+        assert_eq!(
+            fs::read_to_string(storage.entries_path()).unwrap(),
+            "2021-07-03T10:00:00Z 2021-07-03T13:00:00Z A\n\
+             2021-07-03T13:00:00Z 2021-07-03T14:00:00Z B\n"
+        );
+        assert_eq!(storage.normalize(true).unwrap(), (0, 0));
+    }
+
+    #[test]
+    fn refuse_writing_to_stdin() {
+        let storage = Storage::new(PathBuf::from("-"), PathBuf::from("-"));
+
+        assert!(matches!(
+            storage.append_running_entry(&running_entry("A", "2021-07-03T10:00:00Z")),
+            Err(StorageError::WriteToStdin)
+        ));
+    }
+
+    #[test]
+    fn additional_entries_files() {
+        let storage = temporary_storage("additional");
+        let archive = storage.entries_path().with_file_name("archive");
+        fs::write(
+            storage.entries_path(),
+            "2021-07-03T12:00:00Z 2021-07-03T13:00:00Z A\n",
+        )
+        .unwrap();
+        fs::write(&archive, "2021-07-02T12:00:00Z 2021-07-02T13:00:00Z B\n").unwrap();
+        let storage = storage.additional_entries_files(vec![archive.clone()]);
+
+        let accounts: Vec<String> = storage
+            .entries()
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.account.to_string())
+            .collect();
+        assert_eq!(accounts, vec!["B", "A"]);
+
+        storage
+            .append_entry(&Entry::from_str("2021-07-03T14:00:00Z 2021-07-03T15:00:00Z C").unwrap())
+            .unwrap();
+        assert_eq!(
+            fs::read_to_string(&archive).unwrap(),
+            "2021-07-02T12:00:00Z 2021-07-02T13:00:00Z B\n"
+        );
+        assert_eq!(storage.entries().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn list_invalid_lines() {
+        let storage = temporary_storage("invalid-lines");
+        fs::write(
+            storage.entries_path(),
+            "# timetracker entries format 1\n\
+             2021-07-03T10:00:00Z 2021-07-03T11:00:00Z A\n\
+             2021-07-03T12:00:00Z\n\
+             2021-07-03T13:00:00Z 2021-07-03T14:00:00Z B\n",
+        )
+        .unwrap();
+        fs::write(storage.running_path(), "soon A\n").unwrap();
+
+        let invalid = storage.invalid_lines().unwrap();
+        assert_eq!(
+            invalid
+                .iter()
+                .map(|invalid| (invalid.path.as_path(), invalid.number))
+                .collect::<Vec<_>>(),
+            vec![(storage.entries_path(), 3), (storage.running_path(), 1)]
+        );
+        assert_eq!(invalid[0].content, "2021-07-03T12:00:00Z");
+        assert_eq!(storage.valid_entries().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn limit_lines_and_files() {
+        let storage = temporary_storage("limits");
+        fs::write(
+            storage.entries_path(),
+            format!(
+                "2021-07-03T10:00:00Z 2021-07-03T11:00:00Z A\n\
+                 2021-07-03T12:00:00Z 2021-07-03T13:00:00Z A ; {}\n",
+                "x".repeat(100)
+            ),
+        )
+        .unwrap();
+        assert_eq!(storage.entries().unwrap().len(), 2);
+
+        let storage = storage.limits(100, 1000);
+        assert!(matches!(
+            storage.entries(),
+            Err(StorageError::LineTooLong { line: 2, max: 100 })
+        ));
+        assert!(matches!(
+            storage.invalid_lines(),
+            Err(StorageError::LineTooLong { line: 2, .. })
+        ));
+
+        let storage = storage.limits(1000, 100);
+        assert!(matches!(
+            storage.entries(),
+            Err(StorageError::FileTooLarge {
+                size: 191,
+                max: 100,
+                ..
+            })
+        ));
+        assert!(storage.running_entries().unwrap().is_empty());
+    }
+
+    #[test]
+    fn format_version_header() {
+        let storage = temporary_storage("format-version");
+        fs::write(
+            storage.entries_path(),
+            "2021-07-03T11:00:00Z 2021-07-03T12:00:00Z B\n\
+             2021-07-03T10:00:00Z 2021-07-03T11:00:00Z A\n",
+        )
+        .unwrap();
+        assert_eq!(storage.format_version().unwrap(), 0);
+        assert_eq!(storage.migrate().unwrap(), Some(0));
+        assert_eq!(storage.migrate().unwrap(), None);
+        assert_eq!(
+            storage.format_version().unwrap(),
+            migrations::CURRENT_VERSION
+        );
+
+        // The header is skipped when reading and kept when rewriting
+        assert_eq!(storage.entries().unwrap().len(), 2);
+        assert!(storage.sort(false).unwrap());
+        assert_eq!(
+            fs::read_to_string(storage.entries_path()).unwrap(),
+            "# timetracker entries format 3\n\
+             2021-07-03T10:00:00Z 2021-07-03T11:00:00Z A\n\
+             2021-07-03T11:00:00Z 2021-07-03T12:00:00Z B\n"
+        );
+
+        fs::write(storage.entries_path(), "# timetracker entries format 99\n").unwrap();
+        assert!(matches!(
+            storage.entries(),
+            Err(StorageError::UnsupportedVersion(UnsupportedVersion(99)))
+        ));
+    }
+
+    #[test]
+    fn single_file() {
+        let path = temporary_storage("single-file")
+            .entries_path()
+            .to_path_buf();
+        fs::write(&path, "2021-07-03T08:00:00+00:00 2021-07-03T09:00:00Z A\n").unwrap();
+        let storage = Storage::single_file(path.clone());
+
+        storage
+            .start(running_entry("A", "2021-07-03T10:00:00Z"))
+            .unwrap();
+        storage
+            .start(running_entry("B", "2021-07-03T10:30:00Z"))
+            .unwrap();
+        storage
+            .append_entry(&Entry::from_str("2021-07-03T07:00:00Z 2021-07-03T07:30:00Z C").unwrap())
+            .unwrap();
+        storage
+            .stop(Some("A"), datetime("2021-07-03T11:00:00Z"))
+            .unwrap();
+
+        // The stopped entry takes the place of its running line
+        assert_eq!(storage.entries().unwrap().len(), 3);
+        assert_eq!(
+            storage.running_entries().unwrap(),
+            vec![running_entry("B", "2021-07-03T10:30:00Z")]
+        );
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "2021-07-03T08:00:00+00:00 2021-07-03T09:00:00Z A\n\
+             2021-07-03T10:00:00Z 2021-07-03T11:00:00Z A\n\
+             RUNNING 2021-07-03T10:30:00Z B\n\
+             2021-07-03T07:00:00Z 2021-07-03T07:30:00Z C\n"
+        );
+
+        fs::write(&path, "RUNNING 2021-07-03T10:30:00Z B\nnot an entry\n").unwrap();
+        assert!(matches!(
+            storage.strict(true).entries(),
+            Err(StorageError::Parse { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn partial_last_line() {
+        let storage = temporary_storage("partial-last-line");
+        let path = storage.entries_path().to_path_buf();
+        fs::write(
+            &path,
+            "2021-07-03T08:00:00Z 2021-07-03T09:00:00Z A\n2021-07-03T10:00:00Z 2021-07",
+        )
+        .unwrap();
+        assert!(matches!(
+            storage.entries(),
+            Err(StorageError::PartialLine(2))
+        ));
+        assert!(matches!(
+            storage.append_entry(
+                &Entry::from_str("2021-07-03T11:00:00Z 2021-07-03T12:00:00Z B").unwrap()
+            ),
+            Err(StorageError::PartialLine(2))
+        ));
+
+        let storage = storage.quarantine_partial_lines(true);
+        assert_eq!(storage.entries().unwrap().len(), 1);
+        assert_eq!(
+            fs::read_to_string(path.with_extension("corrupt")).unwrap(),
+            "2021-07-03T10:00:00Z 2021-07\n"
+        );
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "2021-07-03T08:00:00Z 2021-07-03T09:00:00Z A\n"
+        );
+    }
+
+    #[test]
+    fn append_after_unterminated_line() {
+        let storage = temporary_storage("append-unterminated");
+        let path = storage.entries_path().to_path_buf();
+        fs::write(&path, "2021-07-03T08:00:00Z 2021-07-03T09:00:00Z A").unwrap();
+        storage
+            .append_entry(&Entry::from_str("2021-07-03T10:00:00Z 2021-07-03T11:00:00Z B").unwrap())
+            .unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "2021-07-03T08:00:00Z 2021-07-03T09:00:00Z A\n\
+             2021-07-03T10:00:00Z 2021-07-03T11:00:00Z B\n"
+        );
+    }
+
+    #[test]
+    fn dry_run_leaves_files_untouched() {
+        let storage = temporary_storage("dry-run");
+        storage
+            .start(running_entry("A", "2021-07-03T10:00:00Z"))
+            .unwrap();
+        let dry_run = storage.clone().write_mode(WriteMode::DryRun);
+
+        let entry = dry_run
+            .stop(None, datetime("2021-07-03T12:00:00Z"))
+            .unwrap();
+        dry_run
+            .start(running_entry("B", "2021-07-03T12:00:00Z"))
+            .unwrap();
+
+        assert_eq!(&*entry.account, "A");
+        assert!(storage.entries().unwrap().is_empty());
+        assert_eq!(
+            storage.running_entries().unwrap(),
+            vec![running_entry("A", "2021-07-03T10:00:00Z")]
+        );
+    }
+
+    #[cfg(feature = "encryption")]
+    #[test]
+    fn encrypt_files() {
+        use age::secrecy::ExposeSecret;
+
+        let secret = age::x25519::Identity::generate().to_string();
+        let key: Key = secret.expose_secret().parse().unwrap();
+        let plain = temporary_storage("encrypted");
+        fs::write(
+            plain.entries_path(),
+            "2021-07-03T08:00:00Z 2021-07-03T09:00:00Z A\n",
+        )
+        .unwrap();
+        let storage = plain.clone().key(key);
+
+        storage
+            .start(running_entry("B", "2021-07-03T10:00:00Z"))
+            .unwrap();
+        storage
+            .stop(None, datetime("2021-07-03T11:00:00Z"))
+            .unwrap();
+
+        assert_eq!(storage.entries().unwrap().len(), 2);
+        assert!(Key::is_encrypted(
+            &fs::read(storage.entries_path()).unwrap()
+        ));
+        assert!(plain.entries().is_err());
+    }
+
+    #[test]
+    fn read_only_refuses_changes() {
+        let storage = temporary_storage("read-only").write_mode(WriteMode::ReadOnly);
+
+        assert!(matches!(
+            storage.start(running_entry("A", "2021-07-03T10:00:00Z")),
+            Err(StorageError::ReadOnly)
+        ));
+        assert!(storage.running_entries().unwrap().is_empty());
+    }
+
+    #[test]
+    fn report_line_of_parse_error() {
+        let storage = temporary_storage("parse-error");
+        fs::write(
+            storage.entries_path(),
+            "2021-07-03T10:00:00Z 2021-07-03T13:00:00Z A\nnonsense\n",
+        )
+        .unwrap();
+
+        // Skipped when reading, but never when changing the file
+        assert_eq!(storage.entries().unwrap().len(), 1);
+        assert!(matches!(
+            storage.rename_account("A", "B"),
+            Err(StorageError::Parse { line: 2, .. })
+        ));
+        assert!(matches!(
+            storage.strict(true).entries(),
+            Err(StorageError::Parse { line: 2, .. })
+        ));
+    }
+}
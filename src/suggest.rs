@@ -0,0 +1,114 @@
+//! Suggestions of accounts to start when none is given, from when the accounts were tracked
+//! before.
+//!
+//! Every earlier entry counts for its account, less the longer ago it started, halving every
+//! `HALF_LIFE_DAYS`. It counts more when it started close to the time of day it is now, and less
+//! when it started on a weekend and today is a work day or the other way around, so an account
+//! like `Meetings`, started at 09:30 on weekdays, comes first on weekday mornings.
+
+use crate::Entry;
+use chrono::{DateTime, Datelike, TimeZone, Timelike, Utc, Weekday};
+use std::collections::HashMap;
+
+/// The days after which an entry counts half as much.
+pub const HALF_LIFE_DAYS: f64 = 14.0;
+
+/// The minutes from the time of day within which an entry counts more, the closer the more.
+const TIME_OF_DAY_WINDOW: f64 = 90.0;
+
+/// How much more an entry started at the time of day counts.
+const TIME_OF_DAY_WEIGHT: f64 = 2.0;
+
+/// How much an entry started on a day of the other kind, weekend or work day, counts.
+const OTHER_KIND_OF_DAY: f64 = 0.25;
+
+/// An account and how likely it is to be started, a higher score being more likely.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion {
+    pub account: String,
+    pub score: f64,
+}
+
+/// The accounts of the entries, the most likely to be started at the time first, by the time of
+/// day and the day of the week in the time zone of the time.
+pub fn suggest<Tz: TimeZone>(entries: &[Entry], now: &DateTime<Tz>) -> Vec<Suggestion> {
+    let mut scores: HashMap<&str, f64> = HashMap::new();
+    for entry in entries {
+        let start = entry.start.with_timezone(&now.timezone());
+        let age = (now.with_timezone(&Utc) - entry.start).num_seconds().max(0) as f64 / 86400.0;
+        let mut score = 0.5f64.powf(age / HALF_LIFE_DAYS);
+
+        let minutes = |time: &DateTime<Tz>| (time.hour() * 60 + time.minute()) as f64;
+        let distance = (minutes(&start) - minutes(now)).abs();
+        let distance = distance.min(24.0 * 60.0 - distance);
+        score *= 1.0 + TIME_OF_DAY_WEIGHT * (1.0 - distance / TIME_OF_DAY_WINDOW).max(0.0);
+
+        if is_weekend(start.weekday()) != is_weekend(now.weekday()) {
+            score *= OTHER_KIND_OF_DAY;
+        }
+        *scores.entry(&entry.account).or_default() += score;
+    }
+    let mut suggestions: Vec<Suggestion> = scores
+        .into_iter()
+        .map(|(account, score)| Suggestion {
+            account: account.to_string(),
+            score,
+        })
+        .collect();
+    suggestions.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| a.account.cmp(&b.account))
+    });
+    suggestions
+}
+
+fn is_weekend(weekday: Weekday) -> bool {
+    matches!(weekday, Weekday::Sat | Weekday::Sun)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn accounts(entries: &[Entry], now: &str) -> Vec<String> {
+        suggest(entries, &DateTime::<Utc>::from_str(now).unwrap())
+            .into_iter()
+            .map(|suggestion| suggestion.account)
+            .collect()
+    }
+
+    #[test]
+    fn rank_by_recency_and_time_of_day() {
+        let entries: Vec<Entry> = [
+            // Meetings every weekday morning of the week before
+            "2021-06-28T09:30:00Z 2021-06-28T10:00:00Z Meetings",
+            "2021-06-29T09:30:00Z 2021-06-29T10:00:00Z Meetings",
+            "2021-06-30T09:30:00Z 2021-06-30T10:00:00Z Meetings",
+            "2021-07-01T09:30:00Z 2021-07-01T10:00:00Z Meetings",
+            "2021-07-02T09:30:00Z 2021-07-02T10:00:00Z Meetings",
+            // Development in the afternoons, more recently
+            "2021-07-01T13:00:00Z 2021-07-01T17:00:00Z Dev",
+            "2021-07-02T13:00:00Z 2021-07-02T17:00:00Z Dev",
+            // A hobby project on the weekend
+            "2021-07-03T10:00:00Z 2021-07-03T12:00:00Z Hobby",
+            // Something done once, long ago
+            "2021-01-04T09:30:00Z 2021-01-04T10:00:00Z Old",
+        ]
+        .iter()
+        .map(|s| Entry::from_str(s).unwrap())
+        .collect();
+
+        // Monday morning
+        assert_eq!(
+            accounts(&entries, "2021-07-05T09:25:00Z"),
+            vec!["Meetings", "Dev", "Hobby", "Old"]
+        );
+        // Monday afternoon
+        assert_eq!(accounts(&entries, "2021-07-05T13:30:00Z")[0], "Dev");
+        // Sunday morning
+        assert_eq!(accounts(&entries, "2021-07-04T10:00:00Z")[0], "Hobby");
+        assert!(suggest(&[], &Utc::now()).is_empty());
+    }
+}
@@ -0,0 +1,377 @@
+//! Synchronization of the data files through git.
+
+use std::error::Error;
+use std::ffi::OsStr;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// A git repository containing (some of) the data files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Repository {
+    root: PathBuf,
+}
+
+impl Repository {
+    /// Finds the repository containing the given directory, if any.
+    pub fn discover(dir: &Path) -> Option<Self> {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["rev-parse", "--show-toplevel"])
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        let root = String::from_utf8(output.stdout).ok()?;
+        Some(Repository {
+            root: PathBuf::from(root.trim_end()),
+        })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Commits the current state of the given files, ignoring files outside of the repository.
+    ///
+    /// Returns whether a commit was made, which is not the case if none of the files changed.
+    pub fn commit(&self, paths: &[&Path], message: &str) -> Result<bool, SyncError> {
+        let paths = self.tracked_paths(paths);
+        if paths.is_empty() {
+            return Ok(false);
+        }
+
+        // Refuse to commit files that still contain an unresolved merge
+        let conflicting: Vec<PathBuf> = paths
+            .iter()
+            .filter(|path| {
+                fs::read_to_string(path)
+                    .map(|contents| contains_conflict_markers(&contents))
+                    .unwrap_or(false)
+            })
+            .cloned()
+            .collect();
+        if !conflicting.is_empty() {
+            return Err(SyncError::Conflict(conflicting));
+        }
+
+        let mut add: Vec<&OsStr> = vec!["add".as_ref(), "--".as_ref()];
+        add.extend(paths.iter().map(|path| path.as_os_str()));
+        self.git(&add)?;
+
+        // `git diff --quiet` exits with 1 if there are differences
+        let mut diff: Vec<&OsStr> = vec![
+            "diff".as_ref(),
+            "--cached".as_ref(),
+            "--quiet".as_ref(),
+            "--".as_ref(),
+        ];
+        diff.extend(paths.iter().map(|path| path.as_os_str()));
+        if self.run(&diff)?.status.success() {
+            return Ok(false);
+        }
+
+        let mut commit: Vec<&OsStr> = vec![
+            "commit".as_ref(),
+            "--quiet".as_ref(),
+            "-m".as_ref(),
+            message.as_ref(),
+            "--".as_ref(),
+        ];
+        commit.extend(paths.iter().map(|path| path.as_os_str()));
+        self.git(&commit)?;
+        Ok(true)
+    }
+
+    /// The name of the checked out branch, or `None` if no branch is checked out.
+    pub fn current_branch(&self) -> Result<Option<String>, SyncError> {
+        let output = self.run(&[
+            "symbolic-ref".as_ref(),
+            "--short".as_ref(),
+            "--quiet".as_ref(),
+            "HEAD".as_ref(),
+        ])?;
+        Ok(
+            Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+                .filter(|branch| output.status.success() && !branch.is_empty()),
+        )
+    }
+
+    /// The subject of the last commit, or `None` if there are no commits yet.
+    pub fn last_commit_subject(&self) -> Result<Option<String>, SyncError> {
+        let output = self.run(&["log".as_ref(), "-1".as_ref(), "--format=%s".as_ref()])?;
+        Ok(
+            Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+                .filter(|subject| output.status.success() && !subject.is_empty()),
+        )
+    }
+
+    /// Whether the repository has any remotes to pull from and push to.
+    pub fn has_remote(&self) -> Result<bool, SyncError> {
+        let output = self.git(&["remote".as_ref()])?;
+        Ok(!output.stdout.is_empty())
+    }
+
+    /// Merges changes from the upstream branch, reporting the conflicting files if the merge
+    /// could not be completed automatically.
+    pub fn pull(&self) -> Result<(), SyncError> {
+        let output = self.run(&[
+            "pull".as_ref(),
+            "--no-rebase".as_ref(),
+            "--no-edit".as_ref(),
+        ])?;
+        if output.status.success() {
+            return Ok(());
+        }
+
+        let conflicts = self.conflicts()?;
+        if conflicts.is_empty() {
+            Err(SyncError::git("pull", &output))
+        } else {
+            Err(SyncError::Conflict(conflicts))
+        }
+    }
+
+    pub fn push(&self) -> Result<(), SyncError> {
+        self.git(&["push".as_ref(), "--quiet".as_ref()])?;
+        Ok(())
+    }
+
+    /// Lists the files with unresolved merge conflicts.
+    pub fn conflicts(&self) -> Result<Vec<PathBuf>, SyncError> {
+        let output = self.git(&[
+            "diff".as_ref(),
+            "--name-only".as_ref(),
+            "--diff-filter=U".as_ref(),
+        ])?;
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(|line| self.root.join(line))
+            .collect())
+    }
+
+    fn tracked_paths(&self, paths: &[&Path]) -> Vec<PathBuf> {
+        paths
+            .iter()
+            .filter_map(|path| fs::canonicalize(path).ok())
+            .filter(|path| path.starts_with(&self.root))
+            .collect()
+    }
+
+    fn run(&self, args: &[&OsStr]) -> Result<Output, SyncError> {
+        log::debug!("running git {:?} in {}", args, self.root.display());
+        Ok(Command::new("git")
+            .arg("-C")
+            .arg(&self.root)
+            .args(args)
+            .output()?)
+    }
+
+    /// Runs a git command, treating a non-zero exit status as an error.
+    fn git(&self, args: &[&OsStr]) -> Result<Output, SyncError> {
+        let output = self.run(args)?;
+        if output.status.success() {
+            Ok(output)
+        } else {
+            Err(SyncError::git(&args[0].to_string_lossy(), &output))
+        }
+    }
+}
+
+/// Checks whether the text contains the markers git leaves behind in a file with an unresolved
+/// merge conflict.
+pub fn contains_conflict_markers(contents: &str) -> bool {
+    contents
+        .lines()
+        .any(|line| line.starts_with("<<<<<<< ") || line.starts_with(">>>>>>> "))
+}
+
+#[derive(Debug)]
+pub enum SyncError {
+    Io(io::Error),
+    Git { command: String, stderr: String },
+    Conflict(Vec<PathBuf>),
+}
+
+impl SyncError {
+    fn git(command: &str, output: &Output) -> Self {
+        SyncError::Git {
+            command: command.to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        }
+    }
+}
+
+impl From<io::Error> for SyncError {
+    fn from(err: io::Error) -> Self {
+        SyncError::Io(err)
+    }
+}
+
+impl fmt::Display for SyncError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SyncError::Io(err) => err.fmt(f),
+            SyncError::Git { command, stderr } => write!(f, "git {} failed: {}", command, stderr),
+            SyncError::Conflict(paths) => write!(
+                f,
+                "unresolved merge conflicts in {}",
+                paths
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+impl Error for SyncError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Whether git can be run, the tests using it pass without it.
+    fn has_git() -> bool {
+        Command::new("git").arg("--version").output().is_ok()
+    }
+
+    /// Runs git in the directory, panicking if it fails.
+    fn git(dir: &Path, args: &[&str]) {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "git {:?} failed: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    /// Sets who commits, which may not be configured where the tests run.
+    fn configure(dir: &Path) {
+        git(dir, &["config", "user.name", "Test"]);
+        git(dir, &["config", "user.email", "test@example.com"]);
+    }
+
+    /// A bare repository to pull from and push to with a commit of the entries file, and a clone
+    /// of it in the directory with the name.
+    fn clone_remote(dir: &TempDir, name: &str) -> Repository {
+        let remote = dir.path().join("remote.git");
+        if !remote.exists() {
+            git(dir.path(), &["init", "--quiet", "--bare", "remote.git"]);
+            let first = dir.path().join("first");
+            git(dir.path(), &["clone", "--quiet", "remote.git", "first"]);
+            configure(&first);
+            fs::write(first.join("entries"), "").unwrap();
+            git(&first, &["add", "entries"]);
+            git(&first, &["commit", "--quiet", "-m", "Add entries"]);
+            git(&first, &["push", "--quiet", "-u", "origin", "HEAD"]);
+        }
+        git(dir.path(), &["clone", "--quiet", "remote.git", name]);
+        configure(&dir.path().join(name));
+        Repository::discover(&dir.path().join(name)).unwrap()
+    }
+
+    #[test]
+    fn commit_changed_files() {
+        if !has_git() {
+            return;
+        }
+        let dir = TempDir::new().unwrap();
+        git(dir.path(), &["init", "--quiet"]);
+        configure(dir.path());
+        let repository = Repository::discover(dir.path()).unwrap();
+        let entries = dir.path().join("entries");
+        fs::write(&entries, "2021-07-03T10:00:00Z 2021-07-03T13:00:00Z A\n").unwrap();
+
+        assert_eq!(repository.last_commit_subject().unwrap(), None);
+        assert!(repository.commit(&[&entries], "Stop A").unwrap());
+        assert_eq!(
+            repository.last_commit_subject().unwrap().as_deref(),
+            Some("Stop A")
+        );
+        // Nothing changed since
+        assert!(!repository.commit(&[&entries], "Stop A again").unwrap());
+        // Files outside the repository are left out
+        let outside = TempDir::new().unwrap();
+        let other = outside.path().join("entries");
+        fs::write(&other, "").unwrap();
+        assert!(!repository.commit(&[&other], "Outside").unwrap());
+        assert!(!repository.has_remote().unwrap());
+
+        fs::write(&entries, "<<<<<<< HEAD\n=======\n>>>>>>> origin/main\n").unwrap();
+        assert!(matches!(
+            repository.commit(&[&entries], "Conflict"),
+            Err(SyncError::Conflict(_))
+        ));
+    }
+
+    #[test]
+    fn pull_and_push() {
+        if !has_git() {
+            return;
+        }
+        let dir = TempDir::new().unwrap();
+        let a = clone_remote(&dir, "a");
+        let b = clone_remote(&dir, "b");
+        assert!(a.has_remote().unwrap());
+
+        let entry = "2021-07-03T10:00:00Z 2021-07-03T13:00:00Z A\n";
+        fs::write(a.root().join("entries"), entry).unwrap();
+        assert!(a.commit(&[&a.root().join("entries")], "Stop A").unwrap());
+        a.push().unwrap();
+        b.pull().unwrap();
+        assert_eq!(fs::read_to_string(b.root().join("entries")).unwrap(), entry);
+
+        // Both change the same line
+        for (repository, account) in &[(&a, "B"), (&b, "C")] {
+            let path = repository.root().join("entries");
+            fs::write(
+                &path,
+                format!("2021-07-03T10:00:00Z 2021-07-03T13:00:00Z {}\n", account),
+            )
+            .unwrap();
+            assert!(repository.commit(&[&path], "Rename").unwrap());
+        }
+        a.push().unwrap();
+        assert!(matches!(b.push(), Err(SyncError::Git { .. })));
+        match b.pull() {
+            Err(SyncError::Conflict(paths)) => {
+                assert_eq!(paths, vec![b.root().join("entries")])
+            }
+            other => panic!("expected a conflict, got {:?}", other),
+        }
+        assert_eq!(b.conflicts().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn detect_conflict_markers() {
+        let contents = "2021-07-03T10:00:00Z 2021-07-03T13:00:00Z A\n\
+                        <<<<<<< HEAD\n\
+                        2021-07-04T10:00:00Z 2021-07-04T13:00:00Z B\n\
+                        =======\n\
+                        2021-07-04T10:00:00Z 2021-07-04T12:00:00Z B\n\
+                        >>>>>>> origin/main\n";
+
+        assert!(contains_conflict_markers(contents));
+    }
+
+    #[test]
+    fn no_conflict_markers() {
+        let contents = "2021-07-03T10:00:00Z 2021-07-03T13:00:00Z A\n\
+                        2021-07-04T10:00:00Z 2021-07-04T13:00:00Z B\n";
+
+        assert!(!contains_conflict_markers(contents));
+    }
+}
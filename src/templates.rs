@@ -0,0 +1,272 @@
+//! Templates with placeholders, for the descriptions of entries when they are started and the
+//! layout of exported documents.
+
+use chrono::{DateTime, TimeZone};
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// Fills in the placeholders of a description template:
+///
+/// - `{account}` with the account of the entry,
+/// - `{date}` with the date, e.g. `2021-07-03`,
+/// - `{time}` with the time, e.g. `09:30`,
+/// - `{weekday}` with the day of the week, e.g. `Saturday`.
+///
+/// Any other text in braces is kept as it is.
+pub fn expand<Tz: TimeZone>(template: &str, account: &str, now: &DateTime<Tz>) -> String
+where
+    Tz::Offset: Display,
+{
+    fill_placeholders(template, |name| match name {
+        "account" => Some(account.to_string()),
+        "date" => Some(now.format("%Y-%m-%d").to_string()),
+        "time" => Some(now.format("%H:%M").to_string()),
+        "weekday" => Some(now.format("%A").to_string()),
+        _ => None,
+    })
+}
+
+/// Replaces each name in braces with the value for it, keeping names without a value in braces.
+pub fn fill_placeholders(template: &str, value: impl Fn(&str) -> Option<String>) -> String {
+    let mut filled = String::new();
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        filled.push_str(&rest[..open]);
+        rest = &rest[open..];
+        let close = match rest.find('}') {
+            Some(close) => close,
+            None => break,
+        };
+        match value(&rest[1..close]) {
+            Some(value) => filled.push_str(&value),
+            None => filled.push_str(&rest[..=close]),
+        }
+        rest = &rest[close + 1..];
+    }
+    filled.push_str(rest);
+    filled
+}
+
+/// A value filled into a layout: the text of a placeholder, or the items a section is repeated
+/// for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Text(String),
+    /// Markup of the output format filled in as it is, unlike text, e.g. an SVG chart.
+    Markup(String),
+    List(Vec<Context>),
+}
+
+/// The values of the names in a layout.
+pub type Context = BTreeMap<String, Value>;
+
+/// A template for the layout of a document. Besides placeholders like `{total}`, it has sections
+/// like `{#days}...{/days}`, which are repeated for each item of a list. Within a section the
+/// names of the item are filled in, and those outside it where the item has none. A section for
+/// a text is included once unless the text is empty. Section tags on a line of their own are
+/// removed along with the line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layout {
+    nodes: Vec<Node>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Node {
+    Text(String),
+    Placeholder(String),
+    Section(String, Vec<Node>),
+}
+
+impl FromStr for Layout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // The sections being parsed, innermost last, with the nodes parsed so far
+        let mut sections: Vec<(&str, Vec<Node>)> = vec![("", Vec::new())];
+        let mut rest = s;
+        let mut at_line_start = true;
+        while let Some(open) = rest.find('{') {
+            let close = match rest[open..].find('}') {
+                Some(close) => open + close,
+                None => break,
+            };
+            let tag = &rest[open + 1..close];
+            let mut text = &rest[..open];
+            let mut after = close + 1;
+
+            let is_section = tag.starts_with('#') || tag.starts_with('/');
+            let line_start = match text.rfind('\n') {
+                Some(newline) => Some(newline + 1),
+                None if at_line_start => Some(0),
+                None => None,
+            };
+            let line_end = rest[after..]
+                .find('\n')
+                .map_or(rest.len(), |end| after + end + 1);
+            let standalone = is_section
+                && line_start.is_some_and(|start| text[start..].trim().is_empty())
+                && rest[after..line_end].trim().is_empty();
+            if standalone {
+                text = &text[..line_start.unwrap_or(0)];
+                after = line_end;
+            }
+            at_line_start = standalone;
+
+            let nodes = &mut sections.last_mut().expect("the document is a section").1;
+            if !text.is_empty() {
+                nodes.push(Node::Text(text.to_string()));
+            }
+            if let Some(name) = tag.strip_prefix('#') {
+                sections.push((name, Vec::new()));
+            } else if let Some(name) = tag.strip_prefix('/') {
+                match sections.pop() {
+                    Some((open, nodes)) if open == name && !sections.is_empty() => sections
+                        .last_mut()
+                        .expect("the document is a section")
+                        .1
+                        .push(Node::Section(name.to_string(), nodes)),
+                    _ => return Err(format!("`{{/{}}}` does not close a section", name)),
+                }
+            } else {
+                nodes.push(Node::Placeholder(tag.to_string()));
+            }
+            rest = &rest[after..];
+        }
+        if let Some((name, _)) = sections.get(1) {
+            return Err(format!("the section `{{#{}}}` is not closed", name));
+        }
+        let mut nodes = sections.pop().expect("the document is a section").1;
+        if !rest.is_empty() {
+            nodes.push(Node::Text(rest.to_string()));
+        }
+        Ok(Layout { nodes })
+    }
+}
+
+impl Layout {
+    /// Fills in the values, escaping every text for the output format. Placeholders without a
+    /// value are kept as they are, sections without one are left out.
+    pub fn render(&self, context: &Context, escape: impl Fn(&str) -> String) -> String {
+        let mut rendered = String::new();
+        render_nodes(&self.nodes, &[context], &escape, &mut rendered);
+        rendered
+    }
+}
+
+fn render_nodes(
+    nodes: &[Node],
+    scopes: &[&Context],
+    escape: &impl Fn(&str) -> String,
+    rendered: &mut String,
+) {
+    let lookup = |name: &str| scopes.iter().rev().find_map(|scope| scope.get(name));
+    for node in nodes {
+        match node {
+            Node::Text(text) => rendered.push_str(text),
+            Node::Placeholder(name) => match lookup(name) {
+                Some(Value::Text(text)) => rendered.push_str(&escape(text)),
+                Some(Value::Markup(markup)) => rendered.push_str(markup),
+                _ => {
+                    rendered.push('{');
+                    rendered.push_str(name);
+                    rendered.push('}');
+                }
+            },
+            Node::Section(name, nodes) => match lookup(name) {
+                Some(Value::List(items)) => {
+                    for item in items {
+                        let mut inner = scopes.to_vec();
+                        inner.push(item);
+                        render_nodes(nodes, &inner, escape, rendered);
+                    }
+                }
+                Some(Value::Text(text)) | Some(Value::Markup(text)) if !text.is_empty() => {
+                    render_nodes(nodes, scopes, escape, rendered)
+                }
+                _ => {}
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{FixedOffset, Utc};
+    use std::str::FromStr;
+
+    #[test]
+    fn expand_placeholders() {
+        let now = DateTime::<Utc>::from_str("2021-07-03T23:30:00Z").unwrap();
+
+        assert_eq!(
+            expand("Standup {weekday} {date} {time} ({account})", "dev", &now),
+            "Standup Saturday 2021-07-03 23:30 (dev)"
+        );
+        assert_eq!(
+            expand(
+                "{date}",
+                "dev",
+                &now.with_timezone(&FixedOffset::east(3600))
+            ),
+            "2021-07-04"
+        );
+    }
+
+    #[test]
+    fn keep_unknown_placeholders() {
+        let now = DateTime::<Utc>::from_str("2021-07-03T10:00:00Z").unwrap();
+
+        assert_eq!(expand("{unknown} {date", "dev", &now), "{unknown} {date");
+        assert_eq!(expand("no placeholders", "dev", &now), "no placeholders");
+    }
+
+    fn text(s: &str) -> Value {
+        Value::Text(s.to_string())
+    }
+
+    #[test]
+    fn render_layouts() {
+        let layout = Layout::from_str(
+            "Days:\n  {#days}\n- {date}: {total}{#note} ({note}){/note} of {max}\n  {/days}\nTotal {total} {unknown}",
+        )
+        .unwrap();
+        let day = |date: &str, total: &str, note: &str| {
+            let mut day = Context::new();
+            day.insert("date".to_string(), text(date));
+            day.insert("total".to_string(), text(total));
+            day.insert("note".to_string(), text(note));
+            day
+        };
+        let mut context = Context::new();
+        context.insert(
+            "days".to_string(),
+            Value::List(vec![day("Mon", "1h", "<late>"), day("Tue", "2h", "")]),
+        );
+        context.insert("total".to_string(), text("3h"));
+        context.insert("max".to_string(), text("8h"));
+
+        assert_eq!(
+            layout.render(&context, |text| text.replace('<', "&lt;")),
+            "Days:\n- Mon: 1h (&lt;late>) of 8h\n- Tue: 2h of 8h\nTotal 3h {unknown}"
+        );
+        context.insert("unknown".to_string(), Value::Markup("<svg/>".to_string()));
+        assert!(layout
+            .render(&context, |text| text.replace('<', "&lt;"))
+            .ends_with("Total 3h <svg/>"));
+    }
+
+    #[test]
+    fn reject_unbalanced_sections() {
+        assert!(Layout::from_str("{#days}").is_err());
+        assert!(Layout::from_str("{/days}").is_err());
+        assert!(Layout::from_str("{#days}{#entries}{/days}{/entries}").is_err());
+        assert_eq!(
+            Layout::from_str("{#a}x{/a} {b")
+                .unwrap()
+                .render(&Context::new(), str::to_string),
+            " {b"
+        );
+    }
+}
@@ -0,0 +1,260 @@
+//! Parsing of times given on the command line, both absolute and relative to the current time.
+
+use crate::duration::parse_duration;
+use crate::report::start_of_day;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, TimeZone, Utc, Weekday};
+use std::ops::Range;
+use std::str::FromStr;
+
+/// Parses a time relative to `now`, in the time zone of `now`. Accepts:
+///
+/// - RFC 3339 timestamps, e.g. `2021-07-03T14:00:00+02:00`,
+/// - `now`,
+/// - durations ago, e.g. `15m ago` or `1h30m ago`,
+/// - a day optionally followed by a time, e.g. `yesterday 14:00` or `2021-07-03 9:30`, where the
+///   day is `today`, `yesterday`, `tomorrow`, a date or a weekday (the last one up to today) and
+///   the time defaults to the start of the day,
+/// - a bare time, e.g. `14:00` or `14:00:30`, which is read as today.
+pub fn parse<Tz: TimeZone>(s: &str, now: &DateTime<Tz>) -> Result<DateTime<Utc>, String> {
+    let s = s.trim();
+    let invalid = || format!(r#"invalid time "{}""#, s);
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(s) {
+        return Ok(datetime.with_timezone(&Utc));
+    }
+    if s.eq_ignore_ascii_case("now") {
+        return Ok(now.with_timezone(&Utc));
+    }
+    if let Some(duration) = s.strip_suffix(" ago") {
+        let duration = parse_duration(duration).map_err(|_| invalid())?;
+        return Ok(now.with_timezone(&Utc) - duration);
+    }
+
+    let mut words = s.split_whitespace();
+    let first = words.next().ok_or_else(invalid)?;
+    let (date, time) = match parse_time(first) {
+        Some(time) => (now.date().naive_local(), Some(time)),
+        None => {
+            let date = parse_day(first, now.date().naive_local()).ok_or_else(invalid)?;
+            let time = match words.next() {
+                Some(word) => Some(parse_time(word).ok_or_else(invalid)?),
+                None => None,
+            };
+            (date, time)
+        }
+    };
+    if words.next().is_some() {
+        return Err(invalid());
+    }
+
+    let time = match time {
+        Some(time) => time,
+        None => return Ok(start_of_day(date, &now.timezone())),
+    };
+    let naive = date.and_time(time);
+    now.timezone()
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(|datetime| datetime.with_timezone(&Utc))
+        .ok_or_else(|| format!(r#"the local time "{}" does not exist"#, s))
+}
+
+/// Parses an ISO week as the range from its first Monday midnight to the next, in the time zone
+/// of `now`. Accepts a week like `2024-W15`, `this` or `last`.
+pub fn parse_week<Tz: TimeZone>(
+    s: &str,
+    now: &DateTime<Tz>,
+) -> Result<Range<DateTime<Utc>>, String> {
+    let s = s.trim();
+    let invalid = || format!(r#"invalid week "{}", expected e.g. "2024-W15""#, s);
+    let today = now.date().naive_local();
+    let monday = match s.to_lowercase().as_str() {
+        "this" => today - Duration::days(today.weekday().num_days_from_monday() as i64),
+        "last" => today - Duration::days(today.weekday().num_days_from_monday() as i64 + 7),
+        week => {
+            let (year, week) = week.split_once("-w").ok_or_else(invalid)?;
+            let year = year.parse().map_err(|_| invalid())?;
+            let week = week.parse().map_err(|_| invalid())?;
+            NaiveDate::from_isoywd_opt(year, week, Weekday::Mon).ok_or_else(invalid)?
+        }
+    };
+    Ok(local_range(
+        monday,
+        monday + Duration::days(7),
+        &now.timezone(),
+    ))
+}
+
+/// Parses a calendar month as the range from midnight on its first day to the next, in the time
+/// zone of `now`. Accepts a month like `2024-03`, `this` or `last`.
+pub fn parse_month<Tz: TimeZone>(
+    s: &str,
+    now: &DateTime<Tz>,
+) -> Result<Range<DateTime<Utc>>, String> {
+    let s = s.trim();
+    let invalid = || format!(r#"invalid month "{}", expected e.g. "2024-03""#, s);
+    let today = now.date().naive_local();
+    let (year, month) = match s.to_lowercase().as_str() {
+        "this" => (today.year(), today.month()),
+        "last" if today.month() == 1 => (today.year() - 1, 12),
+        "last" => (today.year(), today.month() - 1),
+        month => {
+            let (year, month) = month.split_once('-').ok_or_else(invalid)?;
+            (
+                year.parse().map_err(|_| invalid())?,
+                month.parse().map_err(|_| invalid())?,
+            )
+        }
+    };
+    let first = NaiveDate::from_ymd_opt(year, month, 1).ok_or_else(invalid)?;
+    let next = if month == 12 {
+        NaiveDate::from_ymd_opt(year + 1, 1, 1)
+    } else {
+        NaiveDate::from_ymd_opt(year, month + 1, 1)
+    }
+    .ok_or_else(invalid)?;
+    Ok(local_range(first, next, &now.timezone()))
+}
+
+/// The range from the start of one date to the start of another, which is later than midnight on
+/// days starting with a daylight saving time transition.
+fn local_range<Tz: TimeZone>(start: NaiveDate, end: NaiveDate, tz: &Tz) -> Range<DateTime<Utc>> {
+    start_of_day(start, tz)..start_of_day(end, tz)
+}
+
+fn parse_time(s: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(s, "%H:%M")
+        .or_else(|_| NaiveTime::parse_from_str(s, "%H:%M:%S"))
+        .ok()
+}
+
+fn parse_day(s: &str, today: NaiveDate) -> Option<NaiveDate> {
+    match s.to_lowercase().as_str() {
+        "today" => Some(today),
+        "yesterday" => Some(today.pred()),
+        "tomorrow" => Some(today.succ()),
+        day => {
+            if let Ok(date) = NaiveDate::parse_from_str(day, "%Y-%m-%d") {
+                return Some(date);
+            }
+            let weekday = Weekday::from_str(day).ok()?;
+            let days_back =
+                (today.weekday().num_days_from_monday() + 7 - weekday.num_days_from_monday()) % 7;
+            Some(today - Duration::days(days_back as i64))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::FixedOffset;
+
+    /// Saturday 2021-07-03 at 15:30 in UTC+2.
+    fn now() -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339("2021-07-03T15:30:00+02:00").unwrap()
+    }
+
+    fn parsed(s: &str) -> String {
+        parse(s, &now()).unwrap().to_rfc3339()
+    }
+
+    #[test]
+    fn parse_absolute_times() {
+        assert_eq!(parsed("2021-07-01T08:00:00Z"), "2021-07-01T08:00:00+00:00");
+        assert_eq!(parsed("2021-07-01"), "2021-06-30T22:00:00+00:00");
+        assert_eq!(parsed("2021-07-01 9:30"), "2021-07-01T07:30:00+00:00");
+    }
+
+    #[test]
+    fn parse_relative_times() {
+        assert_eq!(parsed("now"), "2021-07-03T13:30:00+00:00");
+        assert_eq!(parsed("15m ago"), "2021-07-03T13:15:00+00:00");
+        assert_eq!(parsed("1h30m ago"), "2021-07-03T12:00:00+00:00");
+    }
+
+    #[test]
+    fn parse_times_of_day() {
+        assert_eq!(parsed("14:00"), "2021-07-03T12:00:00+00:00");
+        assert_eq!(parsed("14:00:30"), "2021-07-03T12:00:30+00:00");
+        assert_eq!(parsed("today"), "2021-07-02T22:00:00+00:00");
+        assert_eq!(parsed("yesterday 14:00"), "2021-07-02T12:00:00+00:00");
+        assert_eq!(parsed("Tomorrow 08:00"), "2021-07-04T06:00:00+00:00");
+    }
+
+    #[test]
+    fn parse_weekdays() {
+        assert_eq!(parsed("monday"), "2021-06-27T22:00:00+00:00");
+        assert_eq!(parsed("fri 17:00"), "2021-07-02T15:00:00+00:00");
+        assert_eq!(parsed("saturday"), "2021-07-02T22:00:00+00:00");
+    }
+
+    #[test]
+    fn parse_weeks() {
+        let week = parse_week("2021-W26", &now()).unwrap();
+        assert_eq!(week.start.to_rfc3339(), "2021-06-27T22:00:00+00:00");
+        assert_eq!(week.end.to_rfc3339(), "2021-07-04T22:00:00+00:00");
+        assert_eq!(parse_week("this", &now()).unwrap(), week);
+        assert_eq!(
+            parse_week("last", &now()).unwrap().start.to_rfc3339(),
+            "2021-06-20T22:00:00+00:00"
+        );
+        // 2021-01-01 belongs to the last week of 2020
+        assert_eq!(
+            parse_week("2020-W53", &now()).unwrap().end.to_rfc3339(),
+            "2021-01-03T22:00:00+00:00"
+        );
+        for s in &["2021-26", "2021-W54", "W26", "2021-Wxx"] {
+            assert!(parse_week(s, &now()).is_err(), "{:?} should be rejected", s);
+        }
+    }
+
+    #[test]
+    fn parse_months() {
+        let month = parse_month("2021-07", &now()).unwrap();
+        assert_eq!(month.start.to_rfc3339(), "2021-06-30T22:00:00+00:00");
+        assert_eq!(month.end.to_rfc3339(), "2021-07-31T22:00:00+00:00");
+        assert_eq!(parse_month("this", &now()).unwrap(), month);
+        assert_eq!(
+            parse_month("last", &now()).unwrap().start.to_rfc3339(),
+            "2021-05-31T22:00:00+00:00"
+        );
+        assert_eq!(
+            parse_month("2021-12", &now()).unwrap().end.to_rfc3339(),
+            "2021-12-31T22:00:00+00:00"
+        );
+        // Midnight was skipped when daylight saving time started on 2018-11-04 in Brazil
+        let brazil = chrono_tz::America::Sao_Paulo
+            .ymd(2018, 11, 20)
+            .and_hms(12, 0, 0);
+        assert_eq!(
+            parse_month("2018-11", &brazil).unwrap().end.to_rfc3339(),
+            "2018-12-01T02:00:00+00:00"
+        );
+        assert_eq!(
+            parse("2018-11-04", &brazil).unwrap().to_rfc3339(),
+            "2018-11-04T03:00:00+00:00"
+        );
+        // The month after the last one chrono can represent
+        for s in &["2021-13", "2021", "July", "2021-07-01", "262143-12"] {
+            assert!(
+                parse_month(s, &now()).is_err(),
+                "{:?} should be rejected",
+                s
+            );
+        }
+    }
+
+    #[test]
+    fn reject_invalid_times() {
+        for s in &[
+            "",
+            "soon",
+            "15 ago",
+            "25:00",
+            "yesterday noon",
+            "today 14:00 extra",
+        ] {
+            assert!(parse(s, &now()).is_err(), "{:?} should be rejected", s);
+        }
+    }
+}
@@ -0,0 +1,416 @@
+//! A collection of entries with queries for reports.
+
+use crate::intern::Interner;
+use crate::report::{is_within_account, split_at_midnight};
+use crate::Entry;
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+use regex::Regex;
+use std::collections::{btree_map, BTreeMap};
+use std::iter::FromIterator;
+use std::ops::Range;
+use std::sync::Arc;
+
+/// Completed entries ordered by start time.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Timesheet {
+    entries: Vec<Entry>,
+    /// The distinct accounts of the entries in order, sharing the names with the entries.
+    accounts: Vec<Arc<str>>,
+}
+
+impl Timesheet {
+    /// Orders the entries, making the entries for an account share its name.
+    pub fn new(mut entries: Vec<Entry>) -> Self {
+        entries.sort_by_key(|entry| entry.start);
+        let mut interner = Interner::new();
+        for entry in &mut entries {
+            entry.account = interner.intern(&entry.account);
+        }
+        let accounts = interner.into_names();
+        Timesheet { entries, accounts }
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    pub fn into_entries(self) -> Vec<Entry> {
+        self.entries
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The distinct accounts of the entries, ordered by name, without going through the entries.
+    pub fn accounts(&self) -> impl Iterator<Item = &str> {
+        self.accounts.iter().map(|account| &**account)
+    }
+
+    /// Keeps the entries for the account and its sub-accounts.
+    pub fn filter_by_account(&self, account: &str) -> Timesheet {
+        self.entries
+            .iter()
+            .filter(|entry| is_within_account(&entry.account, account))
+            .cloned()
+            .collect()
+    }
+
+    /// Keeps the entries with the tag.
+    pub fn filter_by_tag(&self, tag: &str) -> Timesheet {
+        self.entries
+            .iter()
+            .filter(|entry| entry.tags.iter().any(|t| t == tag))
+            .cloned()
+            .collect()
+    }
+
+    /// Keeps the entries which are billable, or the ones which are not.
+    pub fn filter_by_billable(&self, billable: bool) -> Timesheet {
+        self.entries
+            .iter()
+            .filter(|entry| entry.billable == billable)
+            .cloned()
+            .collect()
+    }
+
+    /// Keeps the entries whose account or description matches the pattern.
+    pub fn filter_by_pattern(&self, pattern: &Regex) -> Timesheet {
+        self.entries
+            .iter()
+            .filter(|entry| {
+                pattern.is_match(&entry.account)
+                    || entry
+                        .description
+                        .as_deref()
+                        .is_some_and(|description| pattern.is_match(description))
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Keeps the time tracked within the range, cutting entries which are only partly within it.
+    pub fn between(&self, range: Range<DateTime<Utc>>) -> Timesheet {
+        self.entries
+            .iter()
+            .filter(|entry| entry.start < range.end && entry.stop > range.start)
+            .map(|entry| Entry {
+                start: entry.start.max(range.start),
+                stop: entry.stop.min(range.end),
+                ..entry.clone()
+            })
+            .collect()
+    }
+
+    pub fn total_duration(&self) -> Duration {
+        self.entries.iter().fold(Duration::zero(), |total, entry| {
+            total + (entry.stop - entry.start)
+        })
+    }
+
+    /// Groups the entries by day in the given time zone, splitting entries which span midnight.
+    pub fn group_by_day<Tz: TimeZone>(&self, tz: &Tz) -> BTreeMap<NaiveDate, Timesheet> {
+        let mut days: BTreeMap<NaiveDate, Vec<Entry>> = BTreeMap::new();
+        for part in self
+            .entries
+            .iter()
+            .flat_map(|entry| split_at_midnight(entry, tz))
+        {
+            let date = part.start.with_timezone(tz).naive_local().date();
+            days.entry(date).or_default().push(part);
+        }
+        days.into_iter()
+            .map(|(date, entries)| (date, Timesheet::new(entries)))
+            .collect()
+    }
+
+    /// Coalesces the entries of each account on each day in the given time zone into one entry,
+    /// starting with the first of them and lasting their total duration. Billable and
+    /// non-billable time are kept apart, and the tags and descriptions are combined.
+    pub fn coalesce_by_day<Tz: TimeZone>(&self, tz: &Tz) -> Timesheet {
+        let mut coalesced = Vec::new();
+        for day in self.group_by_day(tz).values() {
+            let mut accounts: BTreeMap<(&str, bool), Entry> = BTreeMap::new();
+            for entry in &day.entries {
+                match accounts.entry((&entry.account, entry.billable)) {
+                    btree_map::Entry::Vacant(vacant) => {
+                        vacant.insert(entry.clone());
+                    }
+                    btree_map::Entry::Occupied(mut occupied) => {
+                        let first = occupied.get_mut();
+                        let duration = (first.stop - first.start) + (entry.stop - entry.start);
+                        first.merge(entry);
+                        first.stop = first.start + duration;
+                    }
+                }
+            }
+            coalesced.extend(accounts.into_values());
+        }
+        Timesheet::new(coalesced)
+    }
+
+    /// Groups the entries by their exact account, ordered by account name.
+    pub fn group_by_account(&self) -> BTreeMap<String, Timesheet> {
+        let mut accounts: BTreeMap<String, Vec<Entry>> = BTreeMap::new();
+        for entry in &self.entries {
+            accounts
+                .entry(entry.account.to_string())
+                .or_default()
+                .push(entry.clone());
+        }
+        accounts
+            .into_iter()
+            .map(|(account, entries)| (account, Timesheet::new(entries)))
+            .collect()
+    }
+
+    /// Groups the entries by their category, with the entries without one first.
+    pub fn group_by_category(&self) -> BTreeMap<Option<String>, Timesheet> {
+        let mut categories: BTreeMap<Option<String>, Vec<Entry>> = BTreeMap::new();
+        for entry in &self.entries {
+            categories
+                .entry(entry.category.clone())
+                .or_default()
+                .push(entry.clone());
+        }
+        categories
+            .into_iter()
+            .map(|(category, entries)| (category, Timesheet::new(entries)))
+            .collect()
+    }
+
+    /// Groups the entries by the person who tracked them, with the entries without one first,
+    /// see `Entry::user`.
+    pub fn group_by_user(&self) -> BTreeMap<Option<String>, Timesheet> {
+        let mut users: BTreeMap<Option<String>, Vec<Entry>> = BTreeMap::new();
+        for entry in &self.entries {
+            users
+                .entry(entry.user().map(str::to_string))
+                .or_default()
+                .push(entry.clone());
+        }
+        users
+            .into_iter()
+            .map(|(user, entries)| (user, Timesheet::new(entries)))
+            .collect()
+    }
+}
+
+impl From<Vec<Entry>> for Timesheet {
+    fn from(entries: Vec<Entry>) -> Self {
+        Timesheet::new(entries)
+    }
+}
+
+impl FromIterator<Entry> for Timesheet {
+    fn from_iter<I: IntoIterator<Item = Entry>>(iter: I) -> Self {
+        Timesheet::new(iter.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn entry(s: &str) -> Entry {
+        Entry::from_str(s).unwrap()
+    }
+
+    fn datetime(s: &str) -> DateTime<Utc> {
+        DateTime::from_str(s).unwrap()
+    }
+
+    fn timesheet() -> Timesheet {
+        Timesheet::new(vec![
+            entry("2021-07-04T10:00:00Z 2021-07-04T11:00:00Z Client:Support"),
+            entry("2021-07-03T22:00:00Z 2021-07-04T02:00:00Z Client"),
+            entry("2021-07-03T10:00:00Z 2021-07-03T12:00:00Z Other"),
+        ])
+    }
+
+    #[test]
+    fn order_entries_by_start() {
+        let starts: Vec<_> = timesheet().entries().iter().map(|e| e.start).collect();
+
+        assert!(starts.windows(2).all(|pair| pair[0] <= pair[1]));
+    }
+
+    #[test]
+    fn filter_and_total() {
+        let timesheet = timesheet();
+
+        assert_eq!(timesheet.total_duration(), Duration::hours(7));
+        assert_eq!(
+            timesheet.filter_by_account("Client").total_duration(),
+            Duration::hours(5)
+        );
+        assert!(timesheet.filter_by_account("Cli").is_empty());
+    }
+
+    #[test]
+    fn filter_billable() {
+        let timesheet = Timesheet::new(vec![
+            entry("2021-07-03T10:00:00Z 2021-07-03T12:00:00Z Client $"),
+            entry("2021-07-03T13:00:00Z 2021-07-03T14:00:00Z Other"),
+        ]);
+
+        assert_eq!(
+            timesheet.filter_by_billable(true).total_duration(),
+            Duration::hours(2)
+        );
+        assert_eq!(
+            timesheet.filter_by_billable(false).total_duration(),
+            Duration::hours(1)
+        );
+    }
+
+    #[test]
+    fn filter_by_tag() {
+        let timesheet = Timesheet::new(vec![
+            entry("2021-07-03T10:00:00Z 2021-07-03T12:00:00Z Client #dev #review"),
+            entry("2021-07-03T13:00:00Z 2021-07-03T14:00:00Z Client #dev"),
+            entry("2021-07-03T15:00:00Z 2021-07-03T16:00:00Z Other #developer"),
+        ]);
+
+        assert_eq!(timesheet.filter_by_tag("dev").entries().len(), 2);
+        assert_eq!(
+            timesheet.filter_by_tag("review").total_duration(),
+            Duration::hours(2)
+        );
+    }
+
+    #[test]
+    fn filter_by_pattern() {
+        let timesheet = Timesheet::new(vec![
+            entry("2021-07-03T10:00:00Z 2021-07-03T12:00:00Z Client ; Fix the login bug"),
+            entry("2021-07-03T13:00:00Z 2021-07-03T14:00:00Z Login:Support"),
+            entry("2021-07-03T15:00:00Z 2021-07-03T16:00:00Z Other #login"),
+        ]);
+
+        let pattern = Regex::new("(?i)login").unwrap();
+        assert_eq!(
+            timesheet.filter_by_pattern(&pattern).total_duration(),
+            Duration::hours(3)
+        );
+        let pattern = Regex::new("log(in|out) bug").unwrap();
+        assert_eq!(timesheet.filter_by_pattern(&pattern).entries().len(), 1);
+    }
+
+    #[test]
+    fn coalesce_days() {
+        let timesheet = Timesheet::new(vec![
+            entry("2021-07-03T08:00:00Z 2021-07-03T09:00:00Z Client #a ; Meeting"),
+            entry("2021-07-03T10:00:00Z 2021-07-03T10:30:00Z Other"),
+            entry("2021-07-03T13:00:00Z 2021-07-03T15:00:00Z Client #b ; Review"),
+            entry("2021-07-03T23:00:00Z 2021-07-04T01:00:00Z Client"),
+        ]);
+
+        assert_eq!(
+            timesheet.coalesce_by_day(&Utc).into_entries(),
+            vec![
+                entry("2021-07-03T08:00:00Z 2021-07-03T12:00:00Z Client #a #b ; Meeting; Review"),
+                entry("2021-07-03T10:00:00Z 2021-07-03T10:30:00Z Other"),
+                entry("2021-07-04T00:00:00Z 2021-07-04T01:00:00Z Client"),
+            ]
+        );
+    }
+
+    #[test]
+    fn cut_entries_to_range() {
+        let between =
+            timesheet().between(datetime("2021-07-04T00:00:00Z")..datetime("2021-07-04T10:30:00Z"));
+
+        assert_eq!(
+            between.into_entries(),
+            vec![
+                entry("2021-07-04T00:00:00Z 2021-07-04T02:00:00Z Client"),
+                entry("2021-07-04T10:00:00Z 2021-07-04T10:30:00Z Client:Support"),
+            ]
+        );
+    }
+
+    #[test]
+    fn group_entries() {
+        let timesheet = timesheet();
+
+        let days: Vec<(NaiveDate, Duration)> = timesheet
+            .group_by_day(&Utc)
+            .into_iter()
+            .map(|(date, day)| (date, day.total_duration()))
+            .collect();
+        assert_eq!(
+            days,
+            vec![
+                (NaiveDate::from_ymd(2021, 7, 3), Duration::hours(4)),
+                (NaiveDate::from_ymd(2021, 7, 4), Duration::hours(3)),
+            ]
+        );
+
+        let accounts: Vec<(String, usize)> = timesheet
+            .group_by_account()
+            .into_iter()
+            .map(|(account, entries)| (account, entries.entries().len()))
+            .collect();
+        assert_eq!(
+            accounts,
+            vec![
+                ("Client".to_string(), 1),
+                ("Client:Support".to_string(), 1),
+                ("Other".to_string(), 1),
+            ]
+        );
+        assert_eq!(
+            timesheet.accounts().collect::<Vec<_>>(),
+            vec!["Client", "Client:Support", "Other"]
+        );
+    }
+
+    #[test]
+    fn group_by_category() {
+        let timesheet = Timesheet::new(vec![
+            entry("2021-07-03T10:00:00Z 2021-07-03T12:00:00Z Client @development"),
+            entry("2021-07-03T13:00:00Z 2021-07-03T14:00:00Z Other @meetings"),
+            entry("2021-07-03T15:00:00Z 2021-07-03T16:00:00Z Other @development"),
+            entry("2021-07-03T16:00:00Z 2021-07-03T17:00:00Z Other"),
+        ]);
+
+        let categories: Vec<(Option<String>, Duration)> = timesheet
+            .group_by_category()
+            .into_iter()
+            .map(|(category, entries)| (category, entries.total_duration()))
+            .collect();
+        assert_eq!(
+            categories,
+            vec![
+                (None, Duration::hours(1)),
+                (Some("development".to_string()), Duration::hours(3)),
+                (Some("meetings".to_string()), Duration::hours(1)),
+            ]
+        );
+    }
+
+    #[test]
+    fn group_by_user() {
+        let timesheet = Timesheet::new(vec![
+            entry("2021-07-03T10:00:00Z 2021-07-03T12:00:00Z Client user=bob"),
+            entry("2021-07-03T13:00:00Z 2021-07-03T14:00:00Z Client user=alice"),
+            entry("2021-07-03T15:00:00Z 2021-07-03T16:00:00Z Other user=bob"),
+            entry("2021-07-03T16:00:00Z 2021-07-03T17:00:00Z Other"),
+        ]);
+
+        let users: Vec<(Option<String>, Duration)> = timesheet
+            .group_by_user()
+            .into_iter()
+            .map(|(user, entries)| (user, entries.total_duration()))
+            .collect();
+        assert_eq!(
+            users,
+            vec![
+                (None, Duration::hours(1)),
+                (Some("alice".to_string()), Duration::hours(1)),
+                (Some("bob".to_string()), Duration::hours(3)),
+            ]
+        );
+    }
+}
@@ -0,0 +1,154 @@
+//! Time zones given on the command line.
+
+use chrono::offset::LocalResult;
+use chrono::{FixedOffset, Local, NaiveDate, NaiveDateTime, Offset, TimeZone, Utc};
+use chrono_tz::Tz;
+use std::fmt;
+use std::str::FromStr;
+
+/// The local time zone, UTC, a named time zone like `Europe/Oslo` or a fixed offset like `+02:00`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Zone {
+    Local,
+    Utc,
+    Named(Tz),
+    Fixed(FixedOffset),
+}
+
+impl FromStr for Zone {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "local" => Ok(Zone::Local),
+            "utc" | "UTC" => Ok(Zone::Utc),
+            _ => s
+                .parse()
+                .ok()
+                .map(Zone::Named)
+                .or_else(|| parse_offset(s).map(Zone::Fixed))
+                .ok_or_else(|| format!(r#"unknown time zone "{}""#, s)),
+        }
+    }
+}
+
+/// Writes the zone as it is given, with offsets like `+02:00`.
+impl fmt::Display for Zone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Zone::Local => write!(f, "local"),
+            Zone::Utc => write!(f, "utc"),
+            Zone::Named(tz) => write!(f, "{}", tz.name()),
+            Zone::Fixed(offset) => {
+                let seconds = offset.local_minus_utc();
+                let sign = if seconds < 0 { '-' } else { '+' };
+                let minutes = seconds.abs() / 60;
+                write!(f, "{}{:02}:{:02}", sign, minutes / 60, minutes % 60)
+            }
+        }
+    }
+}
+
+/// Parses an offset like `+02:00` or `-0530`.
+fn parse_offset(s: &str) -> Option<FixedOffset> {
+    let sign = match s.chars().next()? {
+        '+' => 1,
+        '-' => -1,
+        _ => return None,
+    };
+    let digits = s[1..].replace(':', "");
+    if digits.len() != 4 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let (hours, minutes): (i32, i32) = (digits[..2].parse().ok()?, digits[2..].parse().ok()?);
+    if minutes >= 60 {
+        return None;
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+impl TimeZone for Zone {
+    type Offset = FixedOffset;
+
+    fn from_offset(offset: &FixedOffset) -> Self {
+        Zone::Fixed(*offset)
+    }
+
+    fn offset_from_local_date(&self, local: &NaiveDate) -> LocalResult<FixedOffset> {
+        match self {
+            Zone::Local => Local.offset_from_local_date(local),
+            Zone::Utc => Utc.offset_from_local_date(local).map(|offset| offset.fix()),
+            Zone::Named(tz) => tz.offset_from_local_date(local).map(|offset| offset.fix()),
+            Zone::Fixed(offset) => offset.offset_from_local_date(local),
+        }
+    }
+
+    fn offset_from_local_datetime(&self, local: &NaiveDateTime) -> LocalResult<FixedOffset> {
+        match self {
+            Zone::Local => Local.offset_from_local_datetime(local),
+            Zone::Utc => Utc
+                .offset_from_local_datetime(local)
+                .map(|offset| offset.fix()),
+            Zone::Named(tz) => tz
+                .offset_from_local_datetime(local)
+                .map(|offset| offset.fix()),
+            Zone::Fixed(offset) => offset.offset_from_local_datetime(local),
+        }
+    }
+
+    fn offset_from_utc_date(&self, utc: &NaiveDate) -> FixedOffset {
+        match self {
+            Zone::Local => Local.offset_from_utc_date(utc),
+            Zone::Utc => Utc.offset_from_utc_date(utc).fix(),
+            Zone::Named(tz) => tz.offset_from_utc_date(utc).fix(),
+            Zone::Fixed(offset) => offset.offset_from_utc_date(utc),
+        }
+    }
+
+    fn offset_from_utc_datetime(&self, utc: &NaiveDateTime) -> FixedOffset {
+        match self {
+            Zone::Local => Local.offset_from_utc_datetime(utc),
+            Zone::Utc => Utc.offset_from_utc_datetime(utc).fix(),
+            Zone::Named(tz) => tz.offset_from_utc_datetime(utc).fix(),
+            Zone::Fixed(offset) => offset.offset_from_utc_datetime(utc),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::DateTime;
+
+    #[test]
+    fn parse_zones() {
+        assert_eq!("local".parse(), Ok(Zone::Local));
+        assert_eq!("utc".parse(), Ok(Zone::Utc));
+        assert_eq!(
+            "Europe/Oslo".parse(),
+            Ok(Zone::Named(chrono_tz::Europe::Oslo))
+        );
+        assert_eq!(
+            "+05:30".parse(),
+            Ok(Zone::Fixed(FixedOffset::east(5 * 3600 + 30 * 60)))
+        );
+        assert_eq!("-0100".parse(), Ok(Zone::Fixed(FixedOffset::west(3600))));
+        assert!("Mars/Olympus".parse::<Zone>().is_err());
+        assert!("+5".parse::<Zone>().is_err());
+
+        for zone in &["local", "utc", "Europe/Oslo", "+05:30", "-01:00"] {
+            assert_eq!(zone.parse::<Zone>().unwrap().to_string(), *zone);
+        }
+    }
+
+    #[test]
+    fn convert_to_named_zone() {
+        let datetime: DateTime<Utc> = "2021-07-03T10:00:00Z".parse().unwrap();
+        let zone: Zone = "Europe/Oslo".parse().unwrap();
+
+        assert_eq!(
+            datetime.with_timezone(&zone).to_rfc3339(),
+            "2021-07-03T12:00:00+02:00"
+        );
+    }
+}
@@ -0,0 +1,306 @@
+//! End-to-end tests of the `tt` binary, each against data files in a temporary directory of its
+//! own, in UTC and without colors so the output is the same everywhere.
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+use tempfile::TempDir;
+
+/// The data and config files of a test, and runs `tt` with them.
+struct Tt {
+    dir: TempDir,
+}
+
+impl Tt {
+    fn new() -> Self {
+        Tt {
+            dir: TempDir::new().expect("could not create a temporary directory"),
+        }
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.dir.path().join(name)
+    }
+
+    fn write(&self, name: &str, contents: &str) {
+        fs::write(self.path(name), contents).unwrap();
+    }
+
+    /// The contents of the file, empty if it does not exist.
+    fn read(&self, name: &str) -> String {
+        fs::read_to_string(self.path(name)).unwrap_or_default()
+    }
+
+    fn run(&self, args: &[&str]) -> Output {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_timetracker"));
+        command
+            .arg("--file")
+            .arg(self.path("entries"))
+            .arg("--running-file")
+            .arg(self.path("running"))
+            .arg("--config")
+            .arg(self.path("config.toml"))
+            .args(args)
+            .env("HOME", self.dir.path())
+            .env("TZ", "UTC")
+            .env("LANG", "C")
+            .env("NO_COLOR", "1")
+            .env_remove("LC_ALL")
+            .env_remove("RUST_LOG");
+        for (name, _) in std::env::vars_os() {
+            if name.to_string_lossy().starts_with("TIMETRACKER_") {
+                command.env_remove(name);
+            }
+        }
+        command.output().expect("could not run tt")
+    }
+
+    /// Runs `tt` expecting it to succeed, returning what it printed.
+    fn ok(&self, args: &[&str]) -> String {
+        let output = self.run(args);
+        assert!(
+            output.status.success(),
+            "tt {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8(output.stdout).unwrap()
+    }
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn start_stop_and_export() {
+    let tt = Tt::new();
+    tt.ok(&["start", "dev", "--tag", "x", "--at", "2021-07-01 09:00"]);
+    assert_eq!(tt.read("running"), "2021-07-01T09:00:00Z dev #x\n");
+
+    tt.ok(&["stop", "--at", "2021-07-01 10:30"]);
+    assert_eq!(tt.read("running"), "");
+    assert!(tt
+        .read("entries")
+        .ends_with("2021-07-01T09:00:00Z 2021-07-01T10:30:00Z dev #x\n"));
+
+    assert_eq!(
+        tt.ok(&["export", "--format", "csv"]),
+        "start,stop,account,billable,tags,description\n\
+         2021-07-01T09:00:00+00:00,2021-07-01T10:30:00+00:00,dev,false,x,\n"
+    );
+    let report = tt.ok(&["report", "--since", "2021-07-01", "--until", "2021-07-02"]);
+    assert!(report.contains("Total            1h 30m"), "{}", report);
+}
+
+#[test]
+fn dry_run_writes_nothing() {
+    let tt = Tt::new();
+    tt.ok(&["--dry-run", "start", "dev", "--at", "2021-07-01 09:00"]);
+    assert!(!tt.path("running").exists());
+    assert!(!tt.path("entries").exists());
+}
+
+#[test]
+fn fail_with_exit_codes() {
+    let tt = Tt::new();
+    let stopped = tt.run(&["stop"]);
+    assert_eq!(stopped.status.code(), Some(4));
+    assert!(stderr(&stopped).contains("no running entries"));
+
+    tt.ok(&["start", "dev", "--at", "2021-07-01 09:00"]);
+    let started = tt.run(&["start", "dev", "--at", "2021-07-01 09:30"]);
+    assert_eq!(started.status.code(), Some(3));
+
+    let stopped = tt.run(&["stop", "--at", "2021-07-01 08:00"]);
+    assert_eq!(stopped.status.code(), Some(6));
+    assert_eq!(tt.read("running"), "2021-07-01T09:00:00Z dev\n");
+}
+
+#[test]
+fn read_missing_files_as_empty() {
+    let tt = Tt::new();
+    assert_eq!(tt.ok(&["log"]), "");
+    let report = tt.ok(&["report"]);
+    assert!(report.contains("Total            0h 00m"), "{}", report);
+    assert_eq!(tt.ok(&["running"]), "");
+}
+
+#[test]
+fn skip_malformed_lines() {
+    let tt = Tt::new();
+    tt.write(
+        "entries",
+        "2021-07-01T09:00:00Z 2021-07-01T10:00:00Z dev\nnot an entry\n\
+         2021-07-01T10:00:00Z 2021-07-01T11:00:00Z meetings\n",
+    );
+
+    let output = tt.run(&["log", "--since", "2021-07-01"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("dev"));
+    assert!(stdout.lines().all(|line| line == line.trim_end()));
+    assert!(stderr(&output).contains("skipped a line"));
+
+    let strict = tt.run(&["--strict", "log", "--since", "2021-07-01"]);
+    assert!(!strict.status.success());
+    assert!(stderr(&strict).contains("line 2"));
+
+    let doctor = tt.run(&["doctor"]);
+    assert_eq!(doctor.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&doctor.stdout).contains("line 2"));
+}
+
+#[test]
+fn use_fixed_current_time() {
+    let tt = Tt::new();
+    tt.ok(&["--now", "2021-07-01T09:00:00Z", "start", "dev"]);
+    assert_eq!(tt.read("running"), "2021-07-01T09:00:00Z dev\n");
+
+    let future = tt.run(&["--now", "2021-07-01T10:00:00Z", "stop", "--at", "11:00"]);
+    assert_eq!(future.status.code(), Some(6));
+
+    tt.ok(&["--now", "2021-07-01T12:00:00Z", "stop", "--at", "11:00"]);
+    assert!(tt
+        .read("entries")
+        .ends_with("2021-07-01T09:00:00Z 2021-07-01T11:00:00Z dev\n"));
+}
+
+#[test]
+fn refuse_invalid_config() {
+    let tt = Tt::new();
+    tt.write(
+        "config.toml",
+        "[storage]\nkeep_sortd = true\n\n[stop]\nmax_running_duration = \"12\"\n",
+    );
+
+    let started = tt.run(&["start", "dev"]);
+    assert_eq!(started.status.code(), Some(1));
+    assert!(stderr(&started).contains("line 2: unknown field `keep_sortd`"));
+    assert!(!tt.path("running").exists());
+
+    let lint = tt.run(&["lint-config"]);
+    assert_eq!(lint.status.code(), Some(1));
+    let problems = String::from_utf8(lint.stdout).unwrap();
+    assert_eq!(problems.lines().count(), 2, "{}", problems);
+    assert!(problems.contains("config.toml:5: invalid duration"));
+}
+
+#[test]
+fn compare_with_the_period_before() {
+    let tt = Tt::new();
+    tt.write(
+        "entries",
+        "2021-06-22T09:00:00Z 2021-06-22T10:00:00Z dev\n\
+         2021-06-29T09:00:00Z 2021-06-29T11:00:00Z dev\n",
+    );
+
+    let report = tt.ok(&["report", "--compare", "--week", "2021-W26"]);
+    assert!(report.contains("dev"), "{}", report);
+    assert!(report.contains("+100%"), "{}", report);
+
+    let json = tt.run(&[
+        "report",
+        "--compare",
+        "--week",
+        "2021-W26",
+        "--format",
+        "json",
+    ]);
+    assert_eq!(json.status.code(), Some(1));
+    assert!(stderr(&json).contains("cannot be used with --compare"));
+}
+
+#[test]
+fn line_up_report_in_named_time_zone() {
+    let tt = Tt::new();
+    tt.write("entries", "2021-07-01T09:00:00Z 2021-07-01T10:30:00Z dev\n");
+
+    let report = tt.ok(&[
+        "report",
+        "--since",
+        "2021-07-01",
+        "--until",
+        "2021-07-02",
+        "--timezone",
+        "Europe/Oslo",
+    ]);
+    let dev = report.lines().find(|line| line.starts_with("dev")).unwrap();
+    let total = report
+        .lines()
+        .find(|line| line.starts_with("Total"))
+        .unwrap();
+    assert_eq!(dev.len(), total.len(), "{}", report);
+}
+
+#[test]
+fn end_today_lines_without_details_at_the_account() {
+    let tt = Tt::new();
+    tt.write(
+        "entries",
+        "2021-07-01T09:00:00Z 2021-07-01T10:00:00Z dev\n\
+         2021-07-01T10:00:00Z 2021-07-01T11:00:00Z meetings #standup\n",
+    );
+
+    let today = tt.ok(&["--now", "2021-07-01T12:00:00Z", "today"]);
+    assert!(today.contains("dev\n"), "{}", today);
+    assert!(
+        today.lines().all(|line| line == line.trim_end()),
+        "{}",
+        today
+    );
+}
+
+#[test]
+fn write_status_as_json() {
+    let tt = Tt::new();
+    tt.write(
+        "entries",
+        "2021-07-01T08:00:00Z 2021-07-01T09:00:00Z meetings\n",
+    );
+    tt.ok(&["start", "dev", "--tag", "x", "--at", "2021-07-01 09:00"]);
+
+    let status = tt.ok(&[
+        "--now",
+        "2021-07-01T09:30:00Z",
+        "status",
+        "--format",
+        "json",
+    ]);
+    assert!(status.contains("\"account\": \"dev\""), "{}", status);
+    assert!(status.contains("\"today_seconds\": 5400"), "{}", status);
+    assert!(tt.ok(&["schema", "status"]).contains("\"today_seconds\""));
+
+    let short = tt.run(&["status", "--format", "json", "--short"]);
+    assert_eq!(short.status.code(), Some(1));
+}
+
+#[test]
+fn count_merged_entries() {
+    let tt = Tt::new();
+    tt.write(
+        "entries",
+        "2021-07-01T09:00:00Z 2021-07-01T10:00:00Z dev\n\
+         2021-07-01T10:02:00Z 2021-07-01T11:00:00Z dev\n",
+    );
+    assert_eq!(
+        tt.ok(&["merge"]),
+        "2021-07-01T09:00:00Z 2021-07-01T11:00:00Z dev\n1 merged entry\n"
+    );
+    assert_eq!(tt.ok(&["merge"]), "0 merged entries\n");
+}
+
+#[test]
+fn show_the_date_of_entries_running_since_before_today() {
+    let tt = Tt::new();
+    tt.write(
+        "running",
+        "2021-07-01T09:00:00Z dev #x\n2021-07-02T09:30:00Z ops\n",
+    );
+    let status = tt.ok(&["--now", "2021-07-02T10:00:00Z", "status"]);
+    assert!(status.starts_with(
+        "Running: dev  since 2021-07-01 09:00   25h 00m  #x\n\
+         Running: ops  since 09:30               0h 30m\n\
+         warning: 2 entries running at once since 09:30 (0h 30m)"
+    ));
+}